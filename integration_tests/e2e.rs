@@ -0,0 +1,264 @@
+//! End-to-end tests: an orchestrator backed by an in-memory SurrealDB
+//! discovers the mock MCP service in `tests/fixtures/mock_mcp_service.rs`
+//! and is driven through the MCP protocol like any client would. Embeddings
+//! come from a stub OpenAI-compatible endpoint, so no model is downloaded.
+
+use std::path::Path;
+
+use axum::Json;
+use rmcp::model::CallToolRequestParams;
+use rmcp::service::{RoleClient, RunningService, ServiceExt};
+use rmcp::transport::TokioChildProcess;
+use serde_json::{Value, json};
+use surrealdb::RecordId;
+use tempfile::TempDir;
+use tokio::process::Command;
+use unicity_orchestrator::{
+    DatabaseConfig, ElicitationFallbackPolicy, EmbeddingConfig, EmbeddingModelKind, Fact,
+    LiteralValue, OrchestratorConfig, SymbolicExpression, SymbolicRule, create_server_with_config,
+};
+
+const MOCK_SERVICE: &str = env!("CARGO_BIN_EXE_mock_mcp_service");
+
+/// Length of the stub embeddings.
+const STUB_DIMENSION: usize = 64;
+
+/// Write an `mcp.json` in `dir` that starts the mock service.
+fn write_mcp_config(dir: &Path) -> std::path::PathBuf {
+    let path = dir.join("mcp.json");
+    let config = json!({
+        "mcpServers": {
+            "mock-weather": { "command": MOCK_SERVICE }
+        }
+    });
+    std::fs::write(&path, config.to_string()).unwrap();
+    path
+}
+
+/// Bag-of-words vector of `text`: each lowercase word adds one to a bucket
+/// picked by its hash, so texts sharing words are similar.
+fn stub_embedding(text: &str) -> Vec<f32> {
+    use std::hash::{Hash, Hasher};
+    let mut vector = vec![0.0; STUB_DIMENSION];
+    for word in text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+    {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        word.to_lowercase().hash(&mut hasher);
+        vector[hasher.finish() as usize % STUB_DIMENSION] += 1.0;
+    }
+    vector
+}
+
+/// Serve an OpenAI-compatible `/embeddings` endpoint returning
+/// [`stub_embedding`]s, and build an embedding config that uses it.
+async fn stub_embedding_config() -> EmbeddingConfig {
+    async fn embeddings(Json(body): Json<Value>) -> Json<Value> {
+        let data: Vec<Value> = body["input"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .enumerate()
+            .map(|(index, text)| {
+                json!({
+                    "index": index,
+                    "embedding": stub_embedding(text.as_str().unwrap_or_default()),
+                })
+            })
+            .collect();
+        Json(json!({ "data": data }))
+    }
+
+    let router = axum::Router::new().route("/embeddings", axum::routing::post(embeddings));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, router).await });
+
+    EmbeddingConfig {
+        model: EmbeddingModelKind::OpenAiTextEmbeddingAda002,
+        remote_endpoint: Some(format!("http://{addr}/embeddings").parse().unwrap()),
+        api_key: Some("stub".to_string()),
+        dimension: STUB_DIMENSION,
+        ..Default::default()
+    }
+}
+
+/// Call `name` and parse the JSON text of the first content item.
+async fn call_json(client: &RunningService<RoleClient, ()>, name: &str, args: Value) -> Value {
+    let result = client
+        .call_tool(CallToolRequestParams {
+            meta: None,
+            name: name.to_string().into(),
+            arguments: args.as_object().cloned(),
+            task: None,
+        })
+        .await
+        .unwrap_or_else(|e| panic!("{name} failed: {e}"));
+    let text = result
+        .content
+        .first()
+        .and_then(|content| content.as_text())
+        .unwrap_or_else(|| panic!("{name} returned no text content"))
+        .text
+        .clone();
+    serde_json::from_str(&text).unwrap_or_else(|_| panic!("{name} returned non-JSON: {text}"))
+}
+
+fn fact(predicate: &str, arguments: Vec<SymbolicExpression>) -> SymbolicExpression {
+    SymbolicExpression::Fact(Fact {
+        predicate: predicate.to_string(),
+        arguments,
+        confidence: None,
+    })
+}
+
+/// Rule planning goals that mention a `report` with `format_report`.
+fn report_rule() -> SymbolicRule {
+    SymbolicRule {
+        id: RecordId::from_table_key("symbolic_rule", "weather_report"),
+        name: "Weather report".to_string(),
+        description: "Reports are written by format_report".to_string(),
+        antecedents: vec![fact("forecast_available", Vec::new())],
+        consequents: vec![
+            fact("report", Vec::new()),
+            fact(
+                "use_tool",
+                vec![SymbolicExpression::Literal(LiteralValue::String(
+                    "format_report".to_string(),
+                ))],
+            ),
+        ],
+        confidence: 0.9,
+        priority: 10,
+    }
+}
+
+fn schema_id(schema: &Value) -> Option<&str> {
+    schema.get("$id").and_then(Value::as_str)
+}
+
+#[tokio::test]
+async fn test_mock_service_tools_form_type_chain() {
+    let transport = TokioChildProcess::new(Command::new(MOCK_SERVICE)).unwrap();
+    let client = ().serve(transport).await.unwrap();
+
+    let tools = client.list_all_tools().await.unwrap();
+    let names: Vec<&str> = tools.iter().map(|t| t.name.as_ref()).collect();
+    assert_eq!(names, ["geocode_city", "weather_forecast", "format_report"]);
+
+    // Each tool's output type is the next tool's input type
+    for pair in tools.windows(2) {
+        let output = Value::Object(pair[0].output_schema.as_deref().unwrap().clone());
+        let input = Value::Object(pair[1].input_schema.as_ref().clone());
+        assert!(schema_id(&output).is_some());
+        assert_eq!(schema_id(&output), schema_id(&input));
+    }
+
+    let coordinates = call_json(&client, "geocode_city", json!({ "city": "Berlin" })).await;
+    assert_eq!(
+        coordinates,
+        json!({ "city": "Berlin", "lat": 52.52, "lon": 13.405 })
+    );
+
+    client.cancel().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_select_plan_and_execute_through_mcp() {
+    let dir = TempDir::new().unwrap();
+    let config_path = write_mcp_config(dir.path());
+
+    let server = create_server_with_config(OrchestratorConfig {
+        db: DatabaseConfig {
+            url: "memory".to_string(),
+            ..Default::default()
+        },
+        embedding: stub_embedding_config().await,
+        // The test client doesn't answer approval prompts
+        elicitation_policy: ElicitationFallbackPolicy::Allow,
+        mcp_config: Some(config_path),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    let (client_stream, server_stream) = tokio::io::duplex(64 * 1024);
+    let mcp_server = server.as_ref().clone();
+    tokio::spawn(async move {
+        let running = mcp_server.serve(server_stream).await.unwrap();
+        let _ = running.waiting().await;
+    });
+    let client = ().serve(client_stream).await.unwrap();
+
+    // Discovery stored the mock service's tools
+    let listed = call_json(&client, "unicity.debug.list_tools", json!({})).await;
+    assert_eq!(listed["status"], "ok");
+    let tool_id = |name: &str| {
+        listed["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|t| t["toolName"] == name)
+            .and_then(|t| t["toolId"].as_str())
+            .unwrap_or_else(|| panic!("`{name}` was not discovered"))
+            .to_string()
+    };
+    let geocode_id = tool_id("geocode_city");
+    let forecast_id = tool_id("weather_forecast");
+    let report_id = tool_id("format_report");
+
+    let selected = call_json(
+        &client,
+        "unicity.select_tool",
+        json!({ "query": "weather forecast for a latitude and longitude" }),
+    )
+    .await;
+    assert_eq!(selected["status"], "ok", "{selected}");
+    assert_eq!(selected["selection"]["toolName"], "weather_forecast");
+    assert_eq!(selected["selection"]["toolId"], forecast_id.as_str());
+
+    // No rule plans a report yet
+    let goal = json!({ "query": "a readable weather report for a city" });
+    let plan = call_json(&client, "unicity.plan_tools", goal.clone()).await;
+    assert_eq!(plan["status"], "no_match", "{plan}");
+
+    server
+        .orchestrator()
+        .add_symbolic_rule(report_rule())
+        .await
+        .unwrap();
+    let plan = call_json(&client, "unicity.plan_tools", goal).await;
+    assert_eq!(plan["status"], "ok", "{plan}");
+    let steps = plan["steps"].as_array().unwrap();
+    assert_eq!(steps.len(), 1, "{plan}");
+    assert_eq!(steps[0]["toolName"], "format_report");
+    assert_eq!(steps[0]["inputs"], json!(["summary", "temperature_c"]));
+
+    // Run the whole chain, feeding each result into the next tool
+    let execute = |tool_id: String, args: Value| {
+        let client = &client;
+        async move {
+            call_json(
+                client,
+                "unicity.execute_tool",
+                json!({ "toolId": tool_id, "args": args }),
+            )
+            .await
+        }
+    };
+    let coordinates = execute(geocode_id, json!({ "city": "Berlin" })).await;
+    assert_eq!(
+        coordinates,
+        json!({ "city": "Berlin", "lat": 52.52, "lon": 13.405 })
+    );
+    let forecast = execute(forecast_id, coordinates).await;
+    assert_eq!(
+        forecast,
+        json!({ "summary": "Sunny at 52.52,13.40", "temperature_c": 21.5 })
+    );
+    let report = execute(report_id, forecast).await;
+    assert_eq!(report, json!({ "text": "Sunny at 52.52,13.40, 21.5 °C" }));
+
+    client.cancel().await.unwrap();
+}
@@ -0,0 +1,215 @@
+//! GraphQL schema served over WebSocket at `GET /graphql/ws`.
+//!
+//! Only subscriptions are exposed so far. Callers authenticate with an API key
+//! on the upgrade request and only receive events for their own executions.
+
+use async_graphql::{Context, Data, EmptyMutation, ID, Object, Schema, Subscription};
+use async_graphql_axum::{GraphQLProtocol, GraphQLWebSocket};
+use axum::{
+    Extension,
+    extract::{State, WebSocketUpgrade},
+    http::{Extensions, HeaderMap, StatusCode},
+    response::Response,
+};
+use futures::Stream;
+use tokio::sync::broadcast::error::RecvError;
+
+use super::{AppState, authenticate_api_key};
+use crate::auth::UserContext;
+use crate::orchestrator::events::{ExecutionEventSender, ToolExecutionEvent};
+
+pub type OrchestratorSchema = Schema<QueryRoot, EmptyMutation, SubscriptionRoot>;
+
+pub fn build_schema() -> OrchestratorSchema {
+    Schema::build(QueryRoot, EmptyMutation, SubscriptionRoot).finish()
+}
+
+/// The authenticated caller of a GraphQL connection.
+struct Viewer(UserContext);
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// User id of the authenticated caller; subscriptions are filtered by it.
+    async fn viewer(&self, ctx: &Context<'_>) -> async_graphql::Result<ID> {
+        Ok(ID(ctx.data::<Viewer>()?.0.user_id_string()))
+    }
+}
+
+pub struct SubscriptionRoot;
+
+#[Subscription]
+impl SubscriptionRoot {
+    /// Outcome of each tool execution run for the caller, optionally limited
+    /// to one tool. `userId` may only name the caller.
+    async fn tool_execution_events(
+        &self,
+        ctx: &Context<'_>,
+        tool_id: Option<ID>,
+        user_id: Option<ID>,
+    ) -> async_graphql::Result<impl Stream<Item = ExecutionEvent>> {
+        let viewer = ctx.data::<Viewer>()?.0.user_id_string();
+        if user_id.is_some_and(|u| *u != viewer) {
+            return Err("Not authorized to see another user's executions".into());
+        }
+        let tool_id = tool_id.map(|t| t.0);
+        let rx = ctx.data::<ExecutionEventSender>()?.subscribe();
+
+        Ok(futures::stream::unfold(rx, move |mut rx| {
+            let viewer = viewer.clone();
+            let tool_id = tool_id.clone();
+            async move {
+                loop {
+                    match rx.recv().await {
+                        Ok(event) if event.is_visible_to(&viewer, tool_id.as_deref()) => {
+                            return Some((ExecutionEvent(event), rx));
+                        }
+                        // Missed events are skipped rather than ending the subscription
+                        Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                        Err(RecvError::Closed) => return None,
+                    }
+                }
+            }
+        }))
+    }
+}
+
+/// GraphQL view of a [`ToolExecutionEvent`].
+pub struct ExecutionEvent(ToolExecutionEvent);
+
+#[Object(name = "ToolExecutionEvent")]
+impl ExecutionEvent {
+    async fn tool_id(&self) -> ID {
+        ID(self.0.tool_id.clone())
+    }
+
+    async fn tool_name(&self) -> &str {
+        &self.0.tool_name
+    }
+
+    async fn service_id(&self) -> ID {
+        ID(self.0.service_id.clone())
+    }
+
+    async fn user_id(&self) -> Option<ID> {
+        self.0.user_id.as_ref().map(|u| ID(u.to_string()))
+    }
+
+    async fn success(&self) -> bool {
+        self.0.success
+    }
+
+    async fn error(&self) -> Option<&str> {
+        self.0.error.as_deref()
+    }
+
+    async fn duration_ms(&self) -> u64 {
+        self.0.duration_ms
+    }
+
+    /// RFC 3339 time the execution finished.
+    async fn timestamp(&self) -> String {
+        self.0.timestamp.to_rfc3339()
+    }
+}
+
+/// Upgrade to a GraphQL WebSocket (`graphql-transport-ws` or `graphql-ws`)
+/// authenticated by the request's API key.
+pub(super) async fn graphql_ws(
+    State(state): State<AppState>,
+    Extension(schema): Extension<OrchestratorSchema>,
+    headers: HeaderMap,
+    extensions: Extensions,
+    protocol: GraphQLProtocol,
+    ws: WebSocketUpgrade,
+) -> Result<Response, StatusCode> {
+    let orchestrator = state.lock().await;
+    let user = authenticate_api_key(&orchestrator, &headers, &extensions).await?;
+
+    let mut data = Data::default();
+    data.insert(Viewer(user));
+    data.insert(orchestrator.execution_events().clone());
+    drop(orchestrator);
+
+    Ok(ws
+        .protocols(async_graphql::http::ALL_WEBSOCKET_PROTOCOLS)
+        .on_upgrade(move |stream| {
+            GraphQLWebSocket::new(stream, schema, protocol)
+                .with_data(data)
+                .serve()
+        }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ExternalUserId, IdentityProvider};
+    use futures::StreamExt;
+    use surrealdb::RecordId;
+
+    fn viewer(key: &str) -> Viewer {
+        Viewer(UserContext::new(
+            RecordId::from_table_key("user", key),
+            ExternalUserId::new(key),
+            IdentityProvider::new("api_key"),
+            None,
+            None,
+        ))
+    }
+
+    fn event(user: &str, tool: &str) -> ToolExecutionEvent {
+        ToolExecutionEvent {
+            tool_id: tool.to_string(),
+            tool_name: "search".to_string(),
+            service_id: "service:web".to_string(),
+            user_id: Some(ExternalUserId::new(user)),
+            success: true,
+            error: None,
+            duration_ms: 5,
+            timestamp: chrono::Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_subscription_only_delivers_own_events() {
+        let (sender, _) = tokio::sync::broadcast::channel(16);
+        let request = async_graphql::Request::new(
+            r#"subscription { toolExecutionEvents(toolId: "tool:search") { toolId userId } }"#,
+        )
+        .data(viewer("alice"))
+        .data(sender.clone());
+        let schema = build_schema();
+        let mut stream = schema.execute_stream(request);
+
+        // The subscription only subscribes once polled
+        let next = tokio::spawn(async move { stream.next().await.unwrap() });
+        while sender.receiver_count() == 0 {
+            tokio::task::yield_now().await;
+        }
+        sender.send(event("user:bob", "tool:search")).unwrap();
+        sender.send(event("user:alice", "tool:other")).unwrap();
+        sender.send(event("user:alice", "tool:search")).unwrap();
+
+        let response = next.await.unwrap();
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        assert_eq!(
+            response.data.into_json().unwrap(),
+            serde_json::json!({
+                "toolExecutionEvents": { "toolId": "tool:search", "userId": "user:alice" }
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_subscription_rejects_other_users() {
+        let (sender, _) = tokio::sync::broadcast::channel::<ToolExecutionEvent>(16);
+        let request = async_graphql::Request::new(
+            r#"subscription { toolExecutionEvents(userId: "user:bob") { toolId } }"#,
+        )
+        .data(viewer("alice"))
+        .data(sender);
+        let response = build_schema().execute_stream(request).next().await.unwrap();
+        assert_eq!(response.errors.len(), 1);
+    }
+}
@@ -1,67 +1,254 @@
 // REST API endpoints for the orchestrator
 
 use axum::{
-    Router,
-    extract::State,
-    http::StatusCode,
-    response::Json,
-    routing::{get, post},
+    Extension, Router,
+    body::Body,
+    extract::{
+        Path, Query, State,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+    },
+    http::{Extensions, HeaderMap, StatusCode, header},
+    middleware,
+    response::{IntoResponse, Json, Response},
+    routing::{get, patch, post, put},
 };
+use rmcp::model::JsonObject;
+use serde::Deserialize;
 use serde_json::Value;
 use std::sync::Arc;
+use std::time::Duration;
+use surrealdb::RecordId;
 use tokio::sync::Mutex;
 use tower::ServiceBuilder;
 use tower_http::cors::CorsLayer;
 use tower_http::trace::TraceLayer;
+use utoipa::{IntoParams, OpenApi, ToSchema};
 
-use crate::orchestrator::Orchestrator;
+use crate::auth::{AuthConfig, AuthError, AuthExtractor, UserContext, UserStore};
+use crate::correlation::{assign_correlation_id, http_request_span};
+use crate::db::DiscoverySource;
+use crate::db::queries::{
+    LiveQueryHandle, QueryBuilder, ToolChangeEvent, decode_tool_cursor, encode_tool_cursor,
+};
+use crate::db::schema::{SelectionFeedbackCreate, SelectionOutcome, SlaClass, ToolFilter};
+use crate::elicitation::{ADMIN_ROLE, ApprovalAction, ApprovalRequest, ElicitationError};
+use crate::knowledge_graph::{
+    EmbeddingError, FeedbackLimitError, InvalidRuleError, KnowledgeGraph, MergeResult,
+    RuleConflictError, SymbolicExpression, SymbolicRule, TypeUriGraph,
+};
+use crate::orchestrator::cost::CostEstimate;
+use crate::orchestrator::{Orchestrator, ToolQueryOptions};
+use crate::prompts::{CatalogExport, PromptError, PromptFilter};
+use crate::tools::{HealthStatus, ToolRegistry};
+use crate::types::{ExternalUserId, ServiceId, ServiceName, ToolId};
+
+mod graphql;
+mod openapi;
+mod security;
+
+pub use openapi::{AdminApi, DOCS_CSP_POLICY, PublicApi};
+pub use security::{DEFAULT_CSP_POLICY, SecurityHeaders, SecurityHeadersLayer};
 
 pub type AppState = Arc<Mutex<Orchestrator>>;
 
-pub fn create_public_router(state: AppState) -> Router {
+pub fn create_public_router(state: AppState, security: SecurityHeadersLayer) -> Router {
     Router::new()
         .route("/health", get(health_check))
         .route("/query", post(query_tools))
+        .route("/query/estimate", post(estimate_query_cost))
+        .route("/query/by-vector", post(query_tools_by_vector))
+        .route("/feedback/tool-selection", post(tool_selection_feedback))
         .route("/services", get(list_services))
+        .route("/services/{id}/icon", get(service_icon))
+        .route("/me/permissions/export", get(export_own_permissions))
+        .route("/me/preferences/embedding", put(set_own_embedding_backend))
+        .route("/graphql/ws", get(graphql::graphql_ws))
+        .merge(openapi::docs_router(PublicApi::openapi()))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            reject_rate_limited_ips,
+        ))
         .layer(
             ServiceBuilder::new()
-                .layer(TraceLayer::new_for_http())
+                .layer(Extension(graphql::build_schema()))
+                .layer(middleware::from_fn(assign_correlation_id))
+                .layer(TraceLayer::new_for_http().make_span_with(http_request_span::<Body>))
+                .layer(security)
                 .layer(CorsLayer::permissive()),
         )
         .with_state(state)
 }
 
-pub fn create_admin_router(state: AppState) -> Router {
+pub fn create_admin_router(state: AppState, security: SecurityHeadersLayer) -> Router {
     Router::new()
         .route("/health", get(health_check))
         // .route("/sync", post(sync_registries)) // TODO
         .route("/discover", post(discover_tools))
+        .route("/reload-config", post(reload_config))
+        .route("/prune", post(prune_stale_records))
+        .route("/services", get(list_service_info))
+        .route("/services/similarity", get(service_similarity))
+        .route(
+            "/services/{id}/refresh-prompts",
+            post(refresh_service_prompts),
+        )
+        .route("/tools", get(list_tools))
+        .route("/tools/similarity", get(tool_similarity))
+        .route("/tools/blocked", get(list_blocked_tools))
+        .route("/tools/{id}/unblock", post(unblock_tool))
+        .route("/tools/{id}/metadata", patch(patch_tool_metadata))
+        .route("/tools/{id}/suggest-args", post(suggest_tool_arguments))
+        .route("/tools/{id}/changelog", get(tool_changelog))
+        .route("/tools/{id}/schema-diff", get(tool_schema_diff))
+        .route(
+            "/tools/{id}/normalized-description",
+            get(normalized_tool_description),
+        )
+        .route("/prompts", get(list_prompts))
+        .route("/prompts/catalog.json", get(prompt_catalog_json))
+        .route("/prompts/catalog.md", get(prompt_catalog_markdown))
+        .route(
+            "/prompts/{service_id}/{name}/arguments",
+            get(inferred_prompt_arguments),
+        )
+        .route("/ws/tools", get(tool_changes_ws))
+        .route("/resources/conflicts", get(list_resource_conflicts))
+        .route("/resources/batch-read", post(batch_read_resources))
+        .route("/stats/plan-cache", get(plan_cache_stats))
+        .route("/cache/clear", post(clear_tool_cache))
+        .route("/auth/rate-limits", get(auth_rate_limits))
+        .route("/api-keys/{prefix}/rotate", post(rotate_api_key))
+        .route("/metrics", get(metrics))
+        .route("/plans/{id}/diagram", get(plan_diagram))
+        .route("/permissions/bulk", post(bulk_grant_permissions))
+        .route(
+            "/users/{id}/permissions/export",
+            get(export_user_permissions),
+        )
+        .route("/type-graph/path", get(type_graph_path))
+        .route("/knowledge-graph/merge", post(merge_knowledge_graph))
+        .route("/rules", get(list_rules).post(create_rule))
+        .route("/rules/coverage", get(rule_coverage))
+        .route("/rules/{id}", put(update_rule).delete(delete_rule))
+        .route("/rules/{id}/pretty", get(pretty_rule))
+        .merge(openapi::docs_router(AdminApi::openapi()))
         .layer(
             ServiceBuilder::new()
-                .layer(TraceLayer::new_for_http())
+                .layer(middleware::from_fn(assign_correlation_id))
+                .layer(TraceLayer::new_for_http().make_span_with(http_request_span::<Body>))
+                .layer(security)
                 .layer(CorsLayer::permissive()),
         )
         .with_state(state)
 }
 
-async fn health_check() -> Result<Json<Value>, StatusCode> {
-    Ok(Json(serde_json::json!({
+/// Answer requests from IPs with too many failed API key authentications
+/// with `429 Too Many Requests`.
+async fn reject_rate_limited_ips(
+    State(state): State<AppState>,
+    request: axum::extract::Request,
+    next: middleware::Next,
+) -> Response {
+    let limiter = state.lock().await.auth_rate_limiter().clone();
+    match limiter.reject(request.headers(), request.extensions()) {
+        Some(response) => response,
+        None => next.run(request).await,
+    }
+}
+
+/// Report server health, including tool handler health when a `ToolRegistry`
+/// extension is attached to the router.
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "health",
+    responses((status = 200, description = "Server and tool handler health", body = Value))
+)]
+pub(crate) async fn health_check(
+    registry: Option<Extension<Arc<ToolRegistry>>>,
+) -> Result<Json<Value>, StatusCode> {
+    let mut body = serde_json::json!({
         "status": "healthy",
         "timestamp": chrono::Utc::now().to_rfc3339()
-    })))
+    });
+
+    if let Some(Extension(registry)) = registry {
+        let checks = registry.health_check_all().await;
+        let status = if checks
+            .iter()
+            .any(|(_, health)| matches!(health, HealthStatus::Failed(_)))
+        {
+            "unhealthy"
+        } else if checks
+            .iter()
+            .any(|(_, health)| matches!(health, HealthStatus::Degraded(_)))
+        {
+            "degraded"
+        } else {
+            "healthy"
+        };
+        let tools: serde_json::Map<String, Value> = checks
+            .into_iter()
+            .map(|(name, health)| (name, serde_json::json!(health)))
+            .collect();
+
+        body["status"] = serde_json::json!(status);
+        body["tools"] = Value::Object(tools);
+    }
+
+    Ok(Json(body))
+}
+
+/// Request body for `POST /query`.
+#[derive(Debug, Deserialize, ToSchema)]
+struct QueryRequest {
+    /// Natural-language description of the task.
+    query: String,
+    /// Extra context passed to the selection pipeline, e.g. file paths.
+    #[serde(default)]
+    context: Option<Value>,
+    /// Rank deprecated tools like any other instead of halving their confidence.
+    #[serde(default)]
+    include_deprecated: bool,
+    /// BCP-47 tag of the query's language, e.g. `de`; English if unset.
+    #[serde(default)]
+    language: Option<String>,
+    /// Only select tools whose declared and measured SLA meet this one.
+    #[serde(default)]
+    required_sla: Option<SlaClass>,
 }
 
+/// Find the tools best matching a natural-language query.
+#[utoipa::path(
+    post,
+    path = "/query",
+    tag = "query",
+    request_body = QueryRequest,
+    params(("X-Embedding-Model" = Option<String>, Header, description = "Allowed embedding model to search with for this request")),
+    responses((status = 200, description = "Matching tool selections", body = Value))
+)]
 async fn query_tools(
     State(state): State<AppState>,
-    Json(payload): Json<Value>,
+    headers: HeaderMap,
+    Json(payload): Json<QueryRequest>,
 ) -> Result<Json<Value>, StatusCode> {
-    let query = payload
-        .get("query")
-        .and_then(|q| q.as_str())
-        .ok_or(StatusCode::BAD_REQUEST)?
-        .to_string();
-
-    let context = payload.get("context").cloned();
+    let QueryRequest {
+        query,
+        context,
+        include_deprecated,
+        language,
+        required_sla,
+    } = payload;
+    let options = ToolQueryOptions {
+        include_deprecated,
+        language,
+        required_sla,
+        embedding_model: headers
+            .get("x-embedding-model")
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string),
+        ..Default::default()
+    };
 
     // Read-only operation: we only need an immutable borrow of the orchestrator,
     // but we go through the mutex so we share the same instance with mutating ops.
@@ -70,7 +257,7 @@ async fn query_tools(
     // Note: REST API currently doesn't support authentication, so we pass None
     // for user_context. To add auth, extract user from request headers here.
     let selections = orchestrator
-        .query_tools(&query, context, None)
+        .query_tools(&query, context, None, &options)
         .await
         .map_err(|_e| StatusCode::INTERNAL_SERVER_ERROR)?;
 
@@ -80,6 +267,36 @@ async fn query_tools(
     })))
 }
 
+/// Request body for `POST /query/estimate`.
+#[derive(Debug, Deserialize, ToSchema)]
+struct EstimateRequest {
+    /// Natural-language description of the task.
+    query: String,
+    /// Extra context passed to the selection pipeline, e.g. file paths.
+    #[serde(default)]
+    context: Option<Value>,
+}
+
+/// Estimate what answering a query would cost, without executing any tool.
+#[utoipa::path(
+    post,
+    path = "/query/estimate",
+    tag = "query",
+    request_body = EstimateRequest,
+    responses((status = 200, description = "Expected embedding, inference and tool costs", body = Value))
+)]
+async fn estimate_query_cost(
+    State(state): State<AppState>,
+    Json(payload): Json<EstimateRequest>,
+) -> Result<Json<CostEstimate>, StatusCode> {
+    let orchestrator = state.lock().await;
+    let estimate = orchestrator
+        .estimate_query_cost(&payload.query, payload.context)
+        .await
+        .map_err(|_e| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(estimate))
+}
+
 // TODO
 // async fn sync_registries(
 //     State(state): State<AppState>,
@@ -107,6 +324,78 @@ async fn query_tools(
 //     })))
 // }
 
+/// Request body for `POST /query/by-vector`.
+#[derive(Debug, Deserialize, ToSchema)]
+struct VectorQuery {
+    embedding: Vec<f32>,
+    #[serde(default = "default_k")]
+    k: usize,
+    #[serde(default)]
+    min_similarity: f32,
+}
+
+fn default_k() -> usize {
+    10
+}
+
+/// Most results `POST /query/by-vector` returns, whatever `k` asks for.
+const MAX_VECTOR_QUERY_K: usize = 100;
+
+/// Find tools nearest to a pre-computed embedding, skipping the embedding model.
+#[utoipa::path(
+    post,
+    path = "/query/by-vector",
+    tag = "query",
+    request_body = VectorQuery,
+    responses(
+        (status = 200, description = "Nearest tools by similarity", body = Value),
+        (status = 400, description = "Empty embedding, wrong dimension or `k` of 0"),
+    )
+)]
+async fn query_tools_by_vector(
+    State(state): State<AppState>,
+    Json(payload): Json<VectorQuery>,
+) -> Result<Json<Value>, StatusCode> {
+    if payload.embedding.is_empty() || payload.k == 0 {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let k = payload.k.min(MAX_VECTOR_QUERY_K);
+
+    let orchestrator = state.lock().await;
+    let hits = orchestrator
+        .query_tools_by_vector(payload.embedding, k, payload.min_similarity)
+        .await
+        .map_err(|e| match e.downcast_ref::<EmbeddingError>() {
+            Some(EmbeddingError::DimensionMismatch { .. }) => StatusCode::BAD_REQUEST,
+            Some(_) | None => StatusCode::INTERNAL_SERVER_ERROR,
+        })?;
+
+    let results: Vec<Value> = hits
+        .iter()
+        .filter_map(|hit| hit.tool.as_ref().map(|tool| (hit, tool)))
+        .map(|(hit, tool)| {
+            serde_json::json!({
+                "toolId": tool.id.to_string(),
+                "toolName": tool.name,
+                "serviceId": tool.service_id.to_string(),
+                "similarity": hit.similarity,
+            })
+        })
+        .collect();
+
+    Ok(Json(serde_json::json!({
+        "results": results,
+        "count": results.len(),
+    })))
+}
+
+/// Rediscover tools from every configured MCP service.
+#[utoipa::path(
+    post,
+    path = "/discover",
+    tag = "tools",
+    responses((status = 200, description = "Number of services and tools discovered", body = Value))
+)]
 async fn discover_tools(State(state): State<AppState>) -> Result<Json<Value>, StatusCode> {
     // Mutating operation: (re)discover tools from all known MCP services.
     let mut orchestrator = state.lock().await;
@@ -123,10 +412,1656 @@ async fn discover_tools(State(state): State<AppState>) -> Result<Json<Value>, St
     })))
 }
 
+/// Re-read `mcp.json` and start, update or stop services to match it.
+#[utoipa::path(
+    post,
+    path = "/reload-config",
+    tag = "services",
+    responses((status = 200, description = "Names of the services added, updated and removed", body = Value))
+)]
+async fn reload_config(State(state): State<AppState>) -> Result<Json<Value>, StatusCode> {
+    let result = state.lock().await.reload_config().await.map_err(|e| {
+        tracing::error!("Failed to reload mcp.json: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(serde_json::json!({
+        "added": result.added,
+        "updated": result.updated,
+        "removed": result.removed,
+    })))
+}
+
+/// List known services with their tool counts and health status, plus how
+/// many times each configured server timed out during startup.
+#[utoipa::path(
+    get,
+    path = "/services",
+    tag = "services",
+    responses((status = 200, description = "Known services with tool counts and health", body = Value))
+)]
+async fn list_service_info(State(state): State<AppState>) -> Result<Json<Value>, StatusCode> {
+    let orchestrator = state.lock().await;
+    let services = orchestrator
+        .list_services()
+        .await
+        .map_err(|_e| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(serde_json::json!({
+        "services": services,
+        "count": services.len(),
+        "startupFailures": orchestrator.startup_failures().snapshot(),
+    })))
+}
+
+/// Most URIs accepted by one `POST /resources/batch-read` request.
+const MAX_BATCH_READ_URIS: usize = 100;
+
+/// Reads in flight at once when a batch doesn't set `maxConcurrent`.
+const DEFAULT_BATCH_READ_CONCURRENCY: usize = 8;
+
+/// Upper bound for a batch's `maxConcurrent`.
+const MAX_BATCH_READ_CONCURRENCY: usize = 32;
+
+/// Request body for `POST /resources/batch-read`.
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct BatchReadRequest {
+    uris: Vec<String>,
+    #[serde(default)]
+    max_concurrent: Option<usize>,
+}
+
+/// Read several resources in parallel. Each URI maps to either its `result`
+/// or the `error` that reading it produced.
+#[utoipa::path(
+    post,
+    path = "/resources/batch-read",
+    tag = "resources",
+    request_body = BatchReadRequest,
+    responses(
+        (status = 200, description = "Result or error for each URI", body = Value),
+        (status = 400, description = "More than 100 URIs"),
+    )
+)]
+async fn batch_read_resources(
+    State(state): State<AppState>,
+    Json(request): Json<BatchReadRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    if request.uris.len() > MAX_BATCH_READ_URIS {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let max_concurrent = request
+        .max_concurrent
+        .unwrap_or(DEFAULT_BATCH_READ_CONCURRENCY)
+        .clamp(1, MAX_BATCH_READ_CONCURRENCY);
+
+    // Not held while reading, so other requests aren't blocked
+    let forwarder = state.lock().await.resource_forwarder().clone();
+    let results = forwarder.batch_read(&request.uris, max_concurrent).await;
+
+    let mut body = serde_json::Map::new();
+    for (uri, result) in results {
+        let entry = match result {
+            Ok(result) => serde_json::json!({ "result": result }),
+            Err(e) => serde_json::json!({ "error": e.to_string() }),
+        };
+        body.insert(uri, entry);
+    }
+    Ok(Json(Value::Object(body)))
+}
+
+/// List resource URIs exposed by more than one service.
+///
+/// Each entry reports the conflicting services and the resolution that was applied.
+#[utoipa::path(
+    get,
+    path = "/resources/conflicts",
+    tag = "resources",
+    responses((status = 200, description = "Conflicting resource URIs", body = Value))
+)]
+async fn list_resource_conflicts(State(state): State<AppState>) -> Result<Json<Value>, StatusCode> {
+    let orchestrator = state.lock().await;
+    let conflicts = orchestrator.resource_forwarder().conflicts().await;
+
+    Ok(Json(serde_json::json!({
+        "conflicts": conflicts,
+        "count": conflicts.len(),
+    })))
+}
+
+/// Hit ratio and size of the `plan_tools_for_query` cache.
+#[utoipa::path(
+    get,
+    path = "/stats/plan-cache",
+    tag = "stats",
+    responses((status = 200, description = "Plan cache statistics", body = Value))
+)]
+async fn plan_cache_stats(State(state): State<AppState>) -> Result<Json<Value>, StatusCode> {
+    let orchestrator = state.lock().await;
+    let stats = orchestrator.plan_cache().stats();
+    serde_json::to_value(stats)
+        .map(Json)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+#[serde(rename_all = "camelCase")]
+struct ClearCacheQuery {
+    /// Only clear this tool's results, as `tool:abc` or the bare key.
+    tool_id: Option<String>,
+}
+
+/// Drop cached tool results, for one tool or for all of them.
+#[utoipa::path(
+    post,
+    path = "/cache/clear",
+    tag = "stats",
+    params(ClearCacheQuery),
+    responses((status = 200, description = "Number of cached results dropped", body = Value))
+)]
+async fn clear_tool_cache(
+    State(state): State<AppState>,
+    Query(query): Query<ClearCacheQuery>,
+) -> Json<Value> {
+    let tool_id = query.tool_id.as_deref().map(parse_tool_id);
+    let cleared = state.lock().await.clear_tool_cache(tool_id.as_ref());
+    Json(serde_json::json!({ "cleared": cleared }))
+}
+
+/// Client IPs currently rejected for too many failed API key authentications.
+#[utoipa::path(
+    get,
+    path = "/auth/rate-limits",
+    tag = "auth",
+    responses((status = 200, description = "Penalized IPs with their failure counts and retry delays", body = Value))
+)]
+async fn auth_rate_limits(State(state): State<AppState>) -> Json<Value> {
+    let penalized = state
+        .lock()
+        .await
+        .auth_rate_limiter()
+        .penalized(std::time::Instant::now());
+    Json(serde_json::json!({
+        "count": penalized.len(),
+        "penalized": penalized,
+    }))
+}
+
+/// Replace an API key by a new key for the same user, keeping its name,
+/// expiry and scopes. The old key stops working immediately.
+///
+/// The new key is only ever returned in this response.
+#[utoipa::path(
+    post,
+    path = "/api-keys/{prefix}/rotate",
+    tag = "auth",
+    params(("prefix" = String, Path, description = "Prefix of the key to rotate, e.g. `uo_abc12345`")),
+    responses(
+        (status = 200, description = "The new key and its prefix", body = Value),
+        (status = 404, description = "No active key has this prefix"),
+    )
+)]
+async fn rotate_api_key(
+    State(state): State<AppState>,
+    Path(prefix): Path<String>,
+) -> Result<Json<Value>, StatusCode> {
+    let orchestrator = state.lock().await;
+    let (key, prefix) = orchestrator
+        .rotate_api_key(&prefix)
+        .await
+        .map_err(|_e| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(serde_json::json!({
+        "key": key,
+        "prefix": prefix,
+    })))
+}
+
+/// Prometheus metrics, such as elicitation completion rates.
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "stats",
+    responses((status = 200, description = "Metrics in the Prometheus text format", body = String, content_type = "text/plain"))
+)]
+async fn metrics(State(state): State<AppState>) -> Response {
+    let body = state.lock().await.metrics().render();
+    (
+        [(
+            header::CONTENT_TYPE,
+            crate::metrics::PROMETHEUS_CONTENT_TYPE,
+        )],
+        body,
+    )
+        .into_response()
+}
+
+/// Mermaid flowchart of a recent plan, as returned by `unicity.plan_tools`.
+#[utoipa::path(
+    get,
+    path = "/plans/{id}/diagram",
+    tag = "plans",
+    params(("id" = String, Path, description = "The plan's `planId`")),
+    responses(
+        (status = 200, description = "Mermaid source of the plan", body = String, content_type = "text/plain"),
+        (status = 404, description = "No recent plan with this id"),
+    )
+)]
+async fn plan_diagram(
+    State(state): State<AppState>,
+    Path(plan_id): Path<String>,
+) -> Result<Response, StatusCode> {
+    let plan = state
+        .lock()
+        .await
+        .find_plan(&plan_id)
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok((
+        [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+        plan.diagram,
+    )
+        .into_response())
+}
+
+/// A single permission in a bulk grant request.
+#[derive(Debug, Deserialize, ToSchema)]
+struct BulkGrantItem {
+    #[schema(value_type = String)]
+    tool_id: ToolId,
+    #[schema(value_type = String)]
+    service_id: ServiceId,
+    #[serde(default)]
+    #[schema(value_type = Option<String>)]
+    service_name: Option<ServiceName>,
+    #[schema(value_type = String)]
+    user_id: ExternalUserId,
+    action: ApprovalAction,
+    /// Expiry in seconds from now; permanent when omitted.
+    #[serde(default)]
+    expires_in: Option<u64>,
+}
+
+/// Import a set of tool permissions in one transaction.
+///
+/// The caller is identified by a database-backed API key in `X-API-Key` and
+/// must hold the admin role; their user id is recorded as the requester in the
+/// audit log. As when users grant permissions themselves, a tool that requires
+/// the admin role can only be always allowed for a user who holds it. A
+/// permission replaces the user's existing one for the same tool and service.
+#[utoipa::path(
+    post,
+    path = "/permissions/bulk",
+    tag = "permissions",
+    security(("api_key" = [])),
+    request_body = Vec<BulkGrantItem>,
+    responses(
+        (status = 200, description = "Granted permissions", body = Value),
+        (status = 400, description = "A permission could not be granted"),
+        (status = 401, description = "Missing or unknown API key"),
+        (status = 403, description = "The caller is not an admin, or a user lacks the role needed to always allow a tool"),
+    )
+)]
+async fn bulk_grant_permissions(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    extensions: Extensions,
+    Json(items): Json<Vec<BulkGrantItem>>,
+) -> Result<Json<Value>, StatusCode> {
+    let orchestrator = state.lock().await;
+    let caller = authenticate_api_key(&orchestrator, &headers, &extensions).await?;
+    if !caller.has_role(ADMIN_ROLE) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let requested_by = ExternalUserId::new(caller.user_id_string());
+    let user_store = UserStore::new(orchestrator.db().clone());
+
+    let mut grants = Vec::with_capacity(items.len());
+    for item in items {
+        let required_role =
+            QueryBuilder::find_tool_by_id(orchestrator.db(), parse_tool_id(item.tool_id.as_str()))
+                .await
+                .map_err(|_e| StatusCode::INTERNAL_SERVER_ERROR)?
+                .and_then(|tool| tool.required_role);
+        // Permissions are granted by the user they are for
+        let granter_roles = match item.user_id.as_str().parse::<RecordId>() {
+            Ok(user_id) => user_store
+                .get_roles(&user_id)
+                .await
+                .map_err(|_e| StatusCode::INTERNAL_SERVER_ERROR)?,
+            Err(_) => Vec::new(),
+        };
+
+        let request = ApprovalRequest {
+            service_name: item
+                .service_name
+                .unwrap_or_else(|| ServiceName::new(item.service_id.as_str())),
+            tool_id: item.tool_id,
+            service_id: item.service_id,
+            user_id: item.user_id,
+            arguments: None,
+            required_role,
+            granter_roles,
+        };
+        grants.push((
+            request,
+            item.action,
+            item.expires_in.map(Duration::from_secs),
+        ));
+    }
+
+    let permissions = orchestrator
+        .elicitation_coordinator()
+        .approval_manager()
+        .bulk_grant(grants, Some(&requested_by))
+        .await
+        .map_err(|e| match e {
+            ElicitationError::InvalidSchema(_) => StatusCode::BAD_REQUEST,
+            ElicitationError::Unauthorized(_) => StatusCode::FORBIDDEN,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        })?;
+
+    Ok(Json(serde_json::json!({
+        "permissions": permissions,
+        "count": permissions.len(),
+    })))
+}
+
+/// Export a user's tool permissions as a signed document.
+#[utoipa::path(
+    get,
+    path = "/users/{id}/permissions/export",
+    tag = "permissions",
+    params(("id" = String, Path, description = "External user id")),
+    responses((status = 200, description = "Signed permission set", body = Value))
+)]
+async fn export_user_permissions(
+    State(state): State<AppState>,
+    Path(user_id): Path<String>,
+) -> Result<Json<Value>, StatusCode> {
+    let orchestrator = state.lock().await;
+    let set = orchestrator
+        .elicitation_coordinator()
+        .store()
+        .export_permissions(&user_id)
+        .await
+        .map_err(|_e| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    serde_json::to_value(set)
+        .map(Json)
+        .map_err(|_e| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// Export the calling user's tool permissions as a signed document.
+///
+/// The caller is identified by a database-backed API key in `X-API-Key`.
+#[utoipa::path(
+    get,
+    path = "/me/permissions/export",
+    tag = "permissions",
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "Signed permission set", body = Value),
+        (status = 401, description = "Missing or unknown API key"),
+    )
+)]
+async fn export_own_permissions(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    extensions: Extensions,
+) -> Result<Json<Value>, StatusCode> {
+    let orchestrator = state.lock().await;
+    let user = authenticate_api_key(&orchestrator, &headers, &extensions).await?;
+
+    let set = orchestrator
+        .elicitation_coordinator()
+        .store()
+        .export_permissions(&user.user_id_string())
+        .await
+        .map_err(|_e| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    serde_json::to_value(set)
+        .map(Json)
+        .map_err(|_e| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// Request body for `PUT /me/preferences/embedding`.
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct EmbeddingPreferenceRequest {
+    /// Model to search with, or `null` to use the global model again.
+    model_name: Option<String>,
+}
+
+/// Choose the embedding model used for the calling user's searches.
+///
+/// The caller is identified by a database-backed API key in `X-API-Key`.
+#[utoipa::path(
+    put,
+    path = "/me/preferences/embedding",
+    tag = "query",
+    request_body = EmbeddingPreferenceRequest,
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "The model now used for the caller's searches", body = Value),
+        (status = 400, description = "The model is not allowed"),
+        (status = 401, description = "Missing or unknown API key"),
+    )
+)]
+async fn set_own_embedding_backend(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    extensions: Extensions,
+    Json(request): Json<EmbeddingPreferenceRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let orchestrator = state.lock().await;
+    let user = authenticate_api_key(&orchestrator, &headers, &extensions).await?;
+
+    let backend = orchestrator
+        .set_user_embedding_backend(&user, request.model_name.as_deref())
+        .await
+        .map_err(|e| match e.downcast_ref::<EmbeddingError>() {
+            Some(EmbeddingError::BackendNotAllowed(_)) => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        })?;
+
+    Ok(Json(serde_json::json!({
+        "modelName": backend.as_ref().map(|b| b.model_name.as_str()),
+        "modelArchitecture": backend.as_ref().map(|b| b.architecture()),
+    })))
+}
+
+/// Resolve the caller from the request's API key header.
+async fn authenticate_api_key(
+    orchestrator: &Orchestrator,
+    headers: &HeaderMap,
+    extensions: &Extensions,
+) -> Result<UserContext, StatusCode> {
+    let config = AuthConfig::with_db_api_keys();
+    let api_key = headers
+        .get(config.api_key_header.as_str())
+        .and_then(|v| v.to_str().ok())
+        .ok_or(StatusCode::UNAUTHORIZED)?
+        .to_string();
+
+    let limiter = orchestrator.auth_rate_limiter().clone();
+    let ip = limiter.client_ip(headers, extensions);
+    AuthExtractor::new(config, orchestrator.db().clone())
+        .with_rate_limiter(limiter)
+        .extract_from_api_key(&api_key, ip, None)
+        .await
+        .map_err(|e| match e {
+            AuthError::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            _ => StatusCode::UNAUTHORIZED,
+        })
+}
+
+/// Tools per page of `GET /tools` when `limit` is omitted.
+const DEFAULT_TOOL_PAGE_SIZE: usize = 100;
+
+/// Largest page `GET /tools` returns, whatever `limit` asks for.
+const MAX_TOOL_PAGE_SIZE: usize = 1000;
+
+/// Query parameters for `GET /tools`.
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ListToolsQuery {
+    /// Only return tools from this discovery source kind, e.g. `registry`.
+    source: Option<String>,
+    /// `nextCursor` of the previous page.
+    cursor: Option<String>,
+    /// Tools per page (default 100, at most 1000).
+    limit: Option<usize>,
+}
+
+/// List stored tools with where each was discovered, one page at a time.
+#[utoipa::path(
+    get,
+    path = "/tools",
+    tag = "tools",
+    params(ListToolsQuery),
+    responses(
+        (status = 200, description = "A page of stored tools, ordered by id", body = Value),
+        (status = 400, description = "Unknown discovery source or malformed cursor"),
+    )
+)]
+async fn list_tools(
+    State(state): State<AppState>,
+    Query(params): Query<ListToolsQuery>,
+) -> Result<Json<Value>, StatusCode> {
+    if let Some(source) = &params.source
+        && !DiscoverySource::KINDS.contains(&source.as_str())
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let after_id = match params.cursor.as_deref() {
+        Some(cursor) => Some(decode_tool_cursor(cursor).ok_or(StatusCode::BAD_REQUEST)?),
+        None => None,
+    };
+    let page_size = params
+        .limit
+        .unwrap_or(DEFAULT_TOOL_PAGE_SIZE)
+        .clamp(1, MAX_TOOL_PAGE_SIZE);
+    let filter = ToolFilter {
+        source: params.source,
+        ..Default::default()
+    };
+
+    let orchestrator = state.lock().await;
+    let (tools, next) =
+        QueryBuilder::paginated_tools(orchestrator.db(), after_id, page_size, filter)
+            .await
+            .map_err(|_e| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let tools: Vec<Value> = tools
+        .iter()
+        .map(|t| {
+            serde_json::json!({
+                "toolId": t.id.to_string(),
+                "toolName": t.name,
+                "serviceId": t.service_id.to_string(),
+                "status": t.status,
+                "discovery_source": t.discovery_source,
+                "metadata": t.metadata,
+                "geoAvailability": t.geo_availability,
+                "slaClass": t.sla_class,
+                "actualLatencyP99Ms": t.actual_latency_p99_ms,
+                "actualErrorRatePct": t.actual_error_rate_pct,
+            })
+        })
+        .collect();
+
+    Ok(Json(serde_json::json!({
+        "tools": tools,
+        "count": tools.len(),
+        "nextCursor": next.as_ref().map(encode_tool_cursor),
+    })))
+}
+
+/// Stream tool additions, updates and removals over a WebSocket.
+#[utoipa::path(
+    get,
+    path = "/ws/tools",
+    tag = "tools",
+    responses((status = 101, description = "WebSocket of tool change events"))
+)]
+async fn tool_changes_ws(
+    State(state): State<AppState>,
+    ws: WebSocketUpgrade,
+) -> Result<Response, StatusCode> {
+    let db = state.lock().await.db().clone();
+    let (tx, rx) = tokio::sync::mpsc::channel(64);
+    let handle = QueryBuilder::subscribe_tool_changes(&db, tx)
+        .await
+        .map_err(|_e| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(ws.on_upgrade(move |socket| forward_tool_changes(socket, rx, handle)))
+}
+
+/// Send each change as a JSON text message until either side goes away.
+async fn forward_tool_changes(
+    mut socket: WebSocket,
+    mut rx: tokio::sync::mpsc::Receiver<ToolChangeEvent>,
+    _handle: LiveQueryHandle,
+) {
+    loop {
+        tokio::select! {
+            event = rx.recv() => {
+                let Some(event) = event else { break };
+                let text = tool_change_json(&event).to_string();
+                if socket.send(Message::Text(text.into())).await.is_err() {
+                    break;
+                }
+            }
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+fn tool_change_json(event: &ToolChangeEvent) -> Value {
+    let tool_json = |t: &crate::db::ToolRecord| {
+        serde_json::json!({
+            "toolId": t.id.to_string(),
+            "toolName": t.name,
+            "serviceId": t.service_id.to_string(),
+            "description": t.description,
+            "status": t.status,
+            "discovery_source": t.discovery_source,
+            "metadata": t.metadata,
+        })
+    };
+    match event {
+        ToolChangeEvent::Created(tool) => serde_json::json!({
+            "type": "created",
+            "tool": tool_json(tool),
+        }),
+        ToolChangeEvent::Updated { old, new } => serde_json::json!({
+            "type": "updated",
+            "old": tool_json(old),
+            "new": tool_json(new),
+        }),
+        ToolChangeEvent::Deleted(id) => serde_json::json!({
+            "type": "deleted",
+            "toolId": id.to_string(),
+        }),
+    }
+}
+
+/// Merge annotations into a tool's metadata.
+///
+/// The body is a JSON merge patch: keys set to `null` are removed.
+#[utoipa::path(
+    patch,
+    path = "/tools/{id}/metadata",
+    tag = "tools",
+    params(("id" = String, Path, description = "Tool id, as `tool:abc` or the bare key")),
+    request_body = Object,
+    responses(
+        (status = 200, description = "The merged metadata", body = Value),
+        (status = 404, description = "No such tool"),
+    )
+)]
+async fn patch_tool_metadata(
+    State(state): State<AppState>,
+    Path(tool_id): Path<String>,
+    Json(patch): Json<JsonObject>,
+) -> Result<Json<Value>, StatusCode> {
+    let orchestrator = state.lock().await;
+    let metadata = QueryBuilder::patch_tool_metadata(orchestrator.db(), &tool_id, &patch)
+        .await
+        .map_err(|_e| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(serde_json::json!({
+        "toolId": tool_id,
+        "metadata": metadata,
+    })))
+}
+
+/// List blocked tools with the reason each was blocked.
+#[utoipa::path(
+    get,
+    path = "/tools/blocked",
+    tag = "tools",
+    responses((status = 200, description = "Blocked tools and their block reasons", body = Value))
+)]
+async fn list_blocked_tools(State(state): State<AppState>) -> Result<Json<Value>, StatusCode> {
+    let orchestrator = state.lock().await;
+    let tools = QueryBuilder::list_blocked_tools(orchestrator.db())
+        .await
+        .map_err(|_e| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let tools: Vec<Value> = tools
+        .iter()
+        .map(|t| {
+            serde_json::json!({
+                "toolId": t.id.to_string(),
+                "toolName": t.name,
+                "serviceId": t.service_id.to_string(),
+                "reason": t.block_reason,
+                "message": t.block_reason.as_ref().map(|reason| reason.to_string()),
+            })
+        })
+        .collect();
+
+    Ok(Json(serde_json::json!({
+        "tools": tools,
+        "count": tools.len(),
+    })))
+}
+
+/// Make a blocked tool available again, e.g. after setting a missing
+/// environment variable. Rediscovering the service checks the variables again.
+#[utoipa::path(
+    post,
+    path = "/tools/{id}/unblock",
+    tag = "tools",
+    params(("id" = String, Path, description = "Tool id, as `tool:abc` or the bare key")),
+    responses(
+        (status = 200, description = "The tool is available", body = Value),
+        (status = 404, description = "No such tool"),
+    )
+)]
+async fn unblock_tool(
+    State(state): State<AppState>,
+    Path(tool_id): Path<String>,
+) -> Result<Json<Value>, StatusCode> {
+    let tool = state
+        .lock()
+        .await
+        .unblock_tool(&tool_id)
+        .await
+        .map_err(|_e| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(serde_json::json!({
+        "toolId": tool.id.to_string(),
+        "toolName": tool.name,
+        "status": tool.status,
+    })))
+}
+
+/// Serve the cached icon of a service.
+#[utoipa::path(
+    get,
+    path = "/services/{id}/icon",
+    tag = "services",
+    params(("id" = String, Path, description = "Service id, as `service:abc` or the bare key")),
+    responses(
+        (status = 200, description = "The icon image", content_type = "image/*"),
+        (status = 404, description = "No cached icon"),
+    )
+)]
+async fn service_icon(
+    State(state): State<AppState>,
+    Path(service_id): Path<String>,
+) -> Result<Response, StatusCode> {
+    let service_id = service_id
+        .parse::<RecordId>()
+        .unwrap_or_else(|_| RecordId::from_table_key("service", &service_id));
+
+    let db = state.lock().await.db().clone();
+    let icon = QueryBuilder::get_service_icon(&db, &service_id)
+        .await
+        .map_err(|_e| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, icon.mime_type),
+            (header::CACHE_CONTROL, "max-age=86400".to_string()),
+        ],
+        icon.data.into_inner(),
+    )
+        .into_response())
+}
+
+/// Parse a tool id given either as `tool:abc` or as a bare key.
+fn parse_tool_id(id: &str) -> RecordId {
+    id.parse::<RecordId>()
+        .unwrap_or_else(|_| RecordId::from_table_key("tool", id))
+}
+
+/// Body of `POST /tools/{id}/suggest-args`.
+#[derive(Debug, Deserialize, ToSchema)]
+struct SuggestArgsRequest {
+    #[schema(value_type = String)]
+    user_id: ExternalUserId,
+    /// Arguments filled in so far.
+    #[serde(default)]
+    #[schema(value_type = Object)]
+    arguments: JsonObject,
+}
+
+/// Suggest argument sets from the user's past successful invocations of a tool.
+#[utoipa::path(
+    post,
+    path = "/tools/{id}/suggest-args",
+    tag = "tools",
+    params(("id" = String, Path, description = "Tool id, as `tool:abc` or the bare key")),
+    request_body = SuggestArgsRequest,
+    responses((status = 200, description = "Suggested argument sets", body = Value))
+)]
+async fn suggest_tool_arguments(
+    State(state): State<AppState>,
+    Path(tool_id): Path<String>,
+    Json(payload): Json<SuggestArgsRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let tool_id = parse_tool_id(&tool_id);
+
+    let orchestrator = state.lock().await;
+    let suggestions = orchestrator
+        .suggest_arguments(&tool_id, &payload.arguments, &payload.user_id)
+        .await
+        .map_err(|_e| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(serde_json::json!({
+        "toolId": tool_id.to_string(),
+        "suggestions": suggestions,
+        "count": suggestions.len(),
+    })))
+}
+
+/// Query parameters for `GET /prompts`.
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ListPromptsQuery {
+    /// Only return prompts advertising this capability, e.g. `code_review`.
+    capability: Option<String>,
+}
+
+/// List discovered prompts under their namespaced names.
+#[utoipa::path(
+    get,
+    path = "/prompts",
+    tag = "prompts",
+    params(ListPromptsQuery),
+    responses((status = 200, description = "Discovered prompts", body = Value))
+)]
+async fn list_prompts(
+    State(state): State<AppState>,
+    Query(params): Query<ListPromptsQuery>,
+) -> Result<Json<Value>, StatusCode> {
+    let forwarder = state.lock().await.prompt_forwarder().clone();
+    let filter = PromptFilter {
+        capability: params.capability,
+    };
+    let mut prompts = forwarder.discovered_prompts(Some(&filter)).await;
+    prompts.sort_by(|a, b| a.name.as_str().cmp(b.name.as_str()));
+
+    let prompts: Vec<Value> = prompts
+        .iter()
+        .map(|p| {
+            serde_json::json!({
+                "name": p.name.as_str(),
+                "title": p.title,
+                "description": p.description,
+                "serviceId": p.service_id,
+                "serviceName": p.service_name,
+                "capabilities": p.capabilities,
+            })
+        })
+        .collect();
+
+    Ok(Json(serde_json::json!({
+        "prompts": prompts,
+        "count": prompts.len(),
+    })))
+}
+
+/// Catalog of all discovered prompts, grouped by service.
+#[utoipa::path(
+    get,
+    path = "/prompts/catalog.json",
+    tag = "prompts",
+    responses((status = 200, description = "Prompt catalog", body = Value))
+)]
+async fn prompt_catalog_json(State(state): State<AppState>) -> Json<CatalogExport> {
+    let forwarder = state.lock().await.prompt_forwarder().clone();
+    Json(forwarder.export_catalog().await)
+}
+
+/// Catalog of all discovered prompts, rendered as Markdown.
+#[utoipa::path(
+    get,
+    path = "/prompts/catalog.md",
+    tag = "prompts",
+    responses(
+        (status = 200, description = "Prompt catalog as Markdown", body = String, content_type = "text/markdown"),
+    )
+)]
+async fn prompt_catalog_markdown(State(state): State<AppState>) -> Response {
+    let forwarder = state.lock().await.prompt_forwarder().clone();
+    let catalog = forwarder.export_catalog().await;
+    (
+        [(header::CONTENT_TYPE, "text/markdown; charset=utf-8")],
+        catalog.to_markdown(),
+    )
+        .into_response()
+}
+
+/// Declared prompt arguments with types inferred from past `prompts/get` calls.
+#[utoipa::path(
+    get,
+    path = "/prompts/{service_id}/{name}/arguments",
+    tag = "prompts",
+    params(
+        ("service_id" = String, Path, description = "Service id"),
+        ("name" = String, Path, description = "Prompt name"),
+    ),
+    responses(
+        (status = 200, description = "Prompt arguments with inferred types", body = Value),
+        (status = 404, description = "No such prompt"),
+    )
+)]
+async fn inferred_prompt_arguments(
+    State(state): State<AppState>,
+    Path((service_id, name)): Path<(ServiceId, String)>,
+) -> Result<Json<Value>, StatusCode> {
+    let forwarder = state.lock().await.prompt_forwarder().clone();
+    let arguments = forwarder
+        .infer_argument_types(&service_id, &name)
+        .await
+        .map_err(|e| match e {
+            PromptError::NotFound(_) => StatusCode::NOT_FOUND,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        })?;
+
+    Ok(Json(serde_json::json!({
+        "serviceId": service_id,
+        "prompt": name,
+        "arguments": arguments,
+    })))
+}
+
+/// Re-discover one service's prompts, e.g. after it was restarted, without
+/// rebuilding the rest of the prompt registry.
+#[utoipa::path(
+    post,
+    path = "/services/{id}/refresh-prompts",
+    tag = "prompts",
+    params(("id" = String, Path, description = "Service id")),
+    responses(
+        (status = 200, description = "Prompts added and removed, and the service's conflicting prompt names", body = Value),
+        (status = 500, description = "The service's prompts could not be listed; its old prompts are kept"),
+    )
+)]
+async fn refresh_service_prompts(
+    State(state): State<AppState>,
+    Path(service_id): Path<ServiceId>,
+) -> Result<Json<Value>, StatusCode> {
+    let forwarder = state.lock().await.prompt_forwarder().clone();
+    let result = forwarder
+        .refresh_service_prompts(service_id.as_str())
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(serde_json::json!({
+        "serviceId": service_id,
+        "added": result.added,
+        "removed": result.removed,
+        "conflicts": result.conflicts,
+    })))
+}
+
+/// Tools unseen for this many days are pruned when `max_age_days` is omitted.
+const DEFAULT_PRUNE_MAX_AGE_DAYS: u32 = 30;
+
+/// Query parameters for `POST /prune`.
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct PruneQuery {
+    #[serde(default)]
+    dry_run: bool,
+    max_age_days: Option<u32>,
+}
+
+/// Delete (or with `dry_run`, count) stale tools, services and embeddings.
+#[utoipa::path(
+    post,
+    path = "/prune",
+    tag = "tools",
+    params(PruneQuery),
+    responses((status = 200, description = "Records removed, or that would be removed", body = Value))
+)]
+async fn prune_stale_records(
+    State(state): State<AppState>,
+    Query(params): Query<PruneQuery>,
+) -> Result<Json<Value>, StatusCode> {
+    let max_age_days = params.max_age_days.unwrap_or(DEFAULT_PRUNE_MAX_AGE_DAYS);
+
+    let mut orchestrator = state.lock().await;
+    let result = if params.dry_run {
+        orchestrator.preview_stale_records(max_age_days).await
+    } else {
+        orchestrator.prune_stale_records(max_age_days).await
+    }
+    .map_err(|_e| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(serde_json::json!({
+        "dryRun": params.dry_run,
+        "maxAgeDays": max_age_days,
+        "toolsRemoved": result.tools_removed,
+        "servicesRemoved": result.services_removed,
+        "embeddingsRemoved": result.embeddings_removed,
+    })))
+}
+
+/// Query parameters for `GET /tools/similarity`.
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ToolSimilarityQuery {
+    a: String,
+    b: String,
+}
+
+/// Explain the embedding similarity of two tools.
+#[utoipa::path(
+    get,
+    path = "/tools/similarity",
+    tag = "tools",
+    params(ToolSimilarityQuery),
+    responses(
+        (status = 200, description = "Similarity explanation", body = Value),
+        (status = 404, description = "A tool or its embedding is missing"),
+    )
+)]
+async fn tool_similarity(
+    State(state): State<AppState>,
+    Query(params): Query<ToolSimilarityQuery>,
+) -> Result<Json<Value>, StatusCode> {
+    let orchestrator = state.lock().await;
+    let explanation = orchestrator
+        .explain_tool_similarity(&params.a, &params.b)
+        .await
+        .map_err(|e| match e.downcast_ref::<EmbeddingError>() {
+            Some(EmbeddingError::ToolNotFound(_) | EmbeddingError::MissingEmbedding(_)) => {
+                StatusCode::NOT_FOUND
+            }
+            Some(
+                EmbeddingError::DimensionMismatch { .. } | EmbeddingError::BackendNotAllowed(_),
+            )
+            | None => StatusCode::INTERNAL_SERVER_ERROR,
+        })?;
+
+    Ok(Json(serde_json::json!({
+        "a": params.a,
+        "b": params.b,
+        "explanation": explanation,
+    })))
+}
+
+/// Pairs of services whose tools embed alike, most similar first.
+///
+/// Each service is compared by the centroid of its tools' embeddings; pairs
+/// above the configured `service_duplicate_threshold` are returned. A very
+/// high similarity usually means the same service is configured twice.
+#[utoipa::path(
+    get,
+    path = "/services/similarity",
+    tag = "services",
+    responses(
+        (status = 200, description = "Similar service pairs", body = Value),
+    )
+)]
+async fn service_similarity(State(state): State<AppState>) -> Result<Json<Value>, StatusCode> {
+    let orchestrator = state.lock().await;
+    let pairs = orchestrator
+        .service_similarity()
+        .await
+        .map_err(|_e| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let pairs: Vec<Value> = pairs
+        .into_iter()
+        .map(|(a, b, similarity)| {
+            serde_json::json!({
+                "serviceA": a,
+                "serviceB": b,
+                "similarity": similarity,
+            })
+        })
+        .collect();
+    Ok(Json(serde_json::json!({ "pairs": pairs })))
+}
+
+/// Show a tool's description before and after embedding normalization.
+#[utoipa::path(
+    get,
+    path = "/tools/{id}/normalized-description",
+    tag = "tools",
+    params(("id" = String, Path, description = "Tool id, as `tool:abc` or the bare key")),
+    responses(
+        (status = 200, description = "Description before and after normalization", body = Value),
+        (status = 404, description = "No such tool"),
+    )
+)]
+async fn normalized_tool_description(
+    State(state): State<AppState>,
+    Path(tool_id): Path<String>,
+) -> Result<Json<Value>, StatusCode> {
+    let tool_id = parse_tool_id(&tool_id);
+
+    let orchestrator = state.lock().await;
+    let normalized = orchestrator
+        .normalized_tool_description(&tool_id)
+        .await
+        .map_err(|e| match e.downcast_ref::<EmbeddingError>() {
+            Some(EmbeddingError::ToolNotFound(_)) => StatusCode::NOT_FOUND,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        })?;
+
+    Ok(Json(serde_json::json!({
+        "toolId": tool_id.to_string(),
+        "description": normalized.description,
+        "normalizedDescription": normalized.normalized_description,
+        "steps": normalized.steps,
+    })))
+}
+
+/// A tool's release notes from its `_meta.changelog`, newest first.
+#[utoipa::path(
+    get,
+    path = "/tools/{id}/changelog",
+    tag = "tools",
+    params(("id" = String, Path, description = "Tool id, as `tool:abc` or the bare key")),
+    responses(
+        (status = 200, description = "Changelog entries, newest first", body = Value),
+        (status = 404, description = "No such tool"),
+    )
+)]
+async fn tool_changelog(
+    State(state): State<AppState>,
+    Path(tool_id): Path<String>,
+) -> Result<Json<Value>, StatusCode> {
+    let tool_id = parse_tool_id(&tool_id);
+
+    let orchestrator = state.lock().await;
+    let changelog = orchestrator
+        .tool_changelog(&tool_id)
+        .await
+        .map_err(|_e| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(serde_json::json!({
+        "toolId": tool_id.to_string(),
+        "changelog": changelog,
+    })))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct SchemaDiffQuery {
+    /// Older schema version (default: the version before `to_version`).
+    from_version: Option<u32>,
+    /// Newer schema version (default: the latest version).
+    to_version: Option<u32>,
+}
+
+/// Differences between two recorded versions of a tool's input schema.
+///
+/// A new version is recorded whenever discovery sees the schema change.
+#[utoipa::path(
+    get,
+    path = "/tools/{id}/schema-diff",
+    tag = "tools",
+    params(
+        ("id" = String, Path, description = "Tool id, as `tool:abc` or the bare key"),
+        SchemaDiffQuery,
+    ),
+    responses(
+        (status = 200, description = "Added, removed and modified fields", body = Value),
+        (status = 404, description = "No such tool or schema version"),
+    )
+)]
+async fn tool_schema_diff(
+    State(state): State<AppState>,
+    Path(tool_id): Path<String>,
+    Query(query): Query<SchemaDiffQuery>,
+) -> Result<Json<Value>, StatusCode> {
+    let tool_id = parse_tool_id(&tool_id);
+
+    let orchestrator = state.lock().await;
+    let (from_version, to_version, diff) = orchestrator
+        .tool_schema_diff(&tool_id, query.from_version, query.to_version)
+        .await
+        .map_err(|_e| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(serde_json::json!({
+        "toolId": tool_id.to_string(),
+        "fromVersion": from_version,
+        "toVersion": to_version,
+        "added": diff.added,
+        "removed": diff.removed,
+        "modified": diff.modified,
+        "breaking": diff.breaking,
+    })))
+}
+
+/// Request body for `POST /rules` and `PUT /rules/{id}`.
+#[derive(Debug, Deserialize, ToSchema)]
+struct CreateRuleRequest {
+    name: String,
+    #[serde(default)]
+    description: String,
+    #[schema(value_type = Vec<Object>)]
+    antecedents: Vec<SymbolicExpression>,
+    #[schema(value_type = Vec<Object>)]
+    consequents: Vec<SymbolicExpression>,
+    confidence: f32,
+    #[serde(default)]
+    priority: u32,
+}
+
+/// Create a symbolic rule.
+///
+/// Invalid rules are rejected with `422 Unprocessable Entity` and every
+/// validation error; rules that duplicate or contradict a loaded rule are
+/// rejected with `409 Conflict` and the conflicting rules.
+#[utoipa::path(
+    post,
+    path = "/rules",
+    tag = "rules",
+    request_body = CreateRuleRequest,
+    responses(
+        (status = 201, description = "The id of the new rule", body = Value),
+        (status = 409, description = "The rule duplicates or contradicts a loaded rule", body = Value),
+        (status = 422, description = "The rule failed validation", body = Value),
+    )
+)]
+async fn create_rule(
+    State(state): State<AppState>,
+    Json(payload): Json<CreateRuleRequest>,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    let rule = SymbolicRule {
+        id: surrealdb::RecordId::from_table_key("symbolic_rule", uuid::Uuid::new_v4().to_string()),
+        name: payload.name,
+        description: payload.description,
+        antecedents: payload.antecedents,
+        consequents: payload.consequents,
+        confidence: payload.confidence,
+        priority: payload.priority,
+    };
+
+    let orchestrator = state.lock().await;
+    match orchestrator.add_symbolic_rule(rule).await {
+        Ok(id) => Ok((
+            StatusCode::CREATED,
+            Json(serde_json::json!({ "id": id.to_string() })),
+        )),
+        Err(e) => rule_rejection(e),
+    }
+}
+
+/// `422` for an [`InvalidRuleError`] and `409` for a [`RuleConflictError`],
+/// with the details in the body; `500` for anything else.
+fn rule_rejection(e: anyhow::Error) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    if let Some(invalid) = e.downcast_ref::<InvalidRuleError>() {
+        return Ok((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(serde_json::json!({
+                "errors": invalid.errors,
+                "messages": invalid.errors.iter().map(|e| e.to_string()).collect::<Vec<_>>(),
+            })),
+        ));
+    }
+    if let Some(conflict) = e.downcast_ref::<RuleConflictError>() {
+        let conflicts: Vec<Value> = conflict
+            .conflicts
+            .iter()
+            .map(|c| {
+                serde_json::json!({
+                    "ruleId": c.rule_id.to_string(),
+                    "ruleName": c.rule_name,
+                    "kind": c.kind,
+                    "message": c.to_string(),
+                })
+            })
+            .collect();
+        return Ok((
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({ "conflicts": conflicts })),
+        ));
+    }
+    Err(StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// Replace a symbolic rule, given either as `symbolic_rule:abc` or as a bare
+/// key, keeping its id.
+///
+/// The body and the error responses are those of `POST /rules`.
+#[utoipa::path(
+    put,
+    path = "/rules/{id}",
+    tag = "rules",
+    params(("id" = String, Path, description = "Rule id, as `symbolic_rule:abc` or the bare key")),
+    request_body = CreateRuleRequest,
+    responses(
+        (status = 200, description = "The rule as stored", body = Value),
+        (status = 404, description = "No live rule has this id"),
+        (status = 409, description = "The rule duplicates or contradicts a loaded rule", body = Value),
+        (status = 422, description = "The rule failed validation", body = Value),
+    )
+)]
+async fn update_rule(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(payload): Json<CreateRuleRequest>,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    let rule = SymbolicRule {
+        id: id
+            .parse::<RecordId>()
+            .unwrap_or_else(|_| RecordId::from_table_key("symbolic_rule", &id)),
+        name: payload.name,
+        description: payload.description,
+        antecedents: payload.antecedents,
+        consequents: payload.consequents,
+        confidence: payload.confidence,
+        priority: payload.priority,
+    };
+
+    let orchestrator = state.lock().await;
+    match orchestrator.update_symbolic_rule(rule).await {
+        Ok(Some(rule)) => Ok((
+            StatusCode::OK,
+            Json(serde_json::json!({
+                "id": rule.id.to_string(),
+                "name": rule.name,
+                "description": rule.description,
+                "antecedents": rule.antecedents,
+                "consequents": rule.consequents,
+                "confidence": rule.confidence,
+                "priority": rule.priority,
+                "display_text": rule.pretty_print(),
+            })),
+        )),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => rule_rejection(e),
+    }
+}
+
+/// List the symbolic rules used for inference, each with a human-readable
+/// `display_text`.
+#[utoipa::path(
+    get,
+    path = "/rules",
+    tag = "rules",
+    responses((status = 200, description = "Loaded rules", body = Value))
+)]
+async fn list_rules(State(state): State<AppState>) -> Json<Value> {
+    let rules = state.lock().await.symbolic_rules().await;
+    let rules: Vec<Value> = rules
+        .iter()
+        .map(|rule| {
+            serde_json::json!({
+                "id": rule.id.to_string(),
+                "name": rule.name,
+                "description": rule.description,
+                "antecedents": rule.antecedents,
+                "consequents": rule.consequents,
+                "confidence": rule.confidence,
+                "priority": rule.priority,
+                "display_text": rule.pretty_print(),
+            })
+        })
+        .collect();
+
+    Json(serde_json::json!({
+        "rules": rules,
+        "count": rules.len(),
+    }))
+}
+
+/// A loaded symbolic rule in logic notation, e.g.
+/// `name: [a(?X)] ⟹ [b(?X)] [conf=0.9, prio=1]`.
+#[utoipa::path(
+    get,
+    path = "/rules/{id}/pretty",
+    tag = "rules",
+    params(("id" = String, Path, description = "Rule id, as `symbolic_rule:abc` or the bare key")),
+    responses(
+        (status = 200, description = "The rule in logic notation", body = String, content_type = "text/plain"),
+        (status = 404, description = "No loaded rule has this id"),
+    )
+)]
+async fn pretty_rule(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Response, StatusCode> {
+    let rule_id = id
+        .parse::<RecordId>()
+        .unwrap_or_else(|_| RecordId::from_table_key("symbolic_rule", &id));
+
+    let rules = state.lock().await.symbolic_rules().await;
+    let rule = rules
+        .iter()
+        .find(|rule| rule.id == rule_id)
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok((
+        [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+        rule.pretty_print(),
+    )
+        .into_response())
+}
+
+/// Soft-delete a symbolic rule, given either as `symbolic_rule:abc` or as a
+/// bare key.
+#[utoipa::path(
+    delete,
+    path = "/rules/{id}",
+    tag = "rules",
+    params(("id" = String, Path, description = "Rule id, as `symbolic_rule:abc` or the bare key")),
+    responses(
+        (status = 204, description = "The rule was soft-deleted"),
+        (status = 404, description = "No live rule has this id"),
+    )
+)]
+async fn delete_rule(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, StatusCode> {
+    let rule_id = id
+        .parse::<RecordId>()
+        .unwrap_or_else(|_| RecordId::from_table_key("symbolic_rule", &id));
+
+    let orchestrator = state.lock().await;
+    match orchestrator.remove_symbolic_rule(&rule_id).await {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+/// Report which tools the symbolic rules select, how often each rule fires,
+/// and which tools only the embedding fallback reaches.
+#[utoipa::path(
+    get,
+    path = "/rules/coverage",
+    tag = "rules",
+    responses((status = 200, description = "Covered and uncovered tools and rule hit counts", body = Value))
+)]
+async fn rule_coverage(State(state): State<AppState>) -> Result<Json<Value>, StatusCode> {
+    let report = state
+        .lock()
+        .await
+        .rule_coverage()
+        .await
+        .map_err(|_e| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let rule_hit_counts: serde_json::Map<String, Value> = report
+        .rule_hit_counts
+        .iter()
+        .map(|(id, hits)| (id.to_string(), Value::from(*hits)))
+        .collect();
+    let rule_candidates: Vec<Value> = report
+        .uncovered_tools
+        .iter()
+        .map(|tool| {
+            serde_json::json!({
+                "toolName": tool,
+                "reason": "No rule selects this tool; it is only reachable through embedding search",
+            })
+        })
+        .collect();
+
+    Ok(Json(serde_json::json!({
+        "coveredTools": report.covered_tools,
+        "uncoveredTools": report.uncovered_tools,
+        "ruleHitCounts": rule_hit_counts,
+        "ruleCandidates": rule_candidates,
+    })))
+}
+
+/// Query parameters for `GET /type-graph/path`.
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct TypeGraphPathQuery {
+    from: String,
+    to: String,
+    #[serde(default = "default_max_depth")]
+    max_depth: usize,
+}
+
+fn default_max_depth() -> usize {
+    5
+}
+
+/// Deepest chain `GET /type-graph/path` searches for.
+const MAX_TYPE_GRAPH_DEPTH: usize = 8;
+
+/// Find tool chains that turn a value of one type URI into another.
+#[utoipa::path(
+    get,
+    path = "/type-graph/path",
+    tag = "tools",
+    params(TypeGraphPathQuery),
+    responses((status = 200, description = "Tool chains between the two type URIs", body = Value))
+)]
+async fn type_graph_path(
+    State(state): State<AppState>,
+    Query(mut params): Query<TypeGraphPathQuery>,
+) -> Result<Json<Value>, StatusCode> {
+    params.max_depth = params.max_depth.min(MAX_TYPE_GRAPH_DEPTH);
+
+    // Snapshot the tools, then search without holding the orchestrator
+    let tools: Vec<crate::db::ToolRecord> = {
+        let orchestrator = state.lock().await;
+        orchestrator
+            .db()
+            .query("SELECT * FROM tool")
+            .await
+            .map_err(|_e| StatusCode::INTERNAL_SERVER_ERROR)?
+            .take(0)
+            .map_err(|_e| StatusCode::INTERNAL_SERVER_ERROR)?
+    };
+
+    let graph = TypeUriGraph::from_tools(tools.into_iter().filter(|t| !t.is_blocked()));
+    let describe = |path: &[crate::db::ToolRecord]| {
+        serde_json::json!({
+            "tools": path
+                .iter()
+                .map(|t| serde_json::json!({
+                    "toolId": t.id.to_string(),
+                    "toolName": t.name,
+                    "serviceId": t.service_id.to_string(),
+                }))
+                .collect::<Vec<_>>(),
+            "estimated_cost": TypeUriGraph::estimated_cost(path),
+            "path_cost": KnowledgeGraph::path_cost(path),
+        })
+    };
+
+    let mut paths = graph.all_paths(&params.from, &params.to, params.max_depth);
+    paths.sort_by_key(|p| p.len());
+    let shortest = graph
+        .shortest_path(&params.from, &params.to)
+        .map(|p| describe(&p));
+    let mut reachable: Vec<String> = graph.reachable_from(&params.from).into_iter().collect();
+    reachable.sort();
+
+    Ok(Json(serde_json::json!({
+        "from": params.from,
+        "to": params.to,
+        "max_depth": params.max_depth,
+        "shortest": shortest,
+        "paths": paths.iter().map(|p| describe(p)).collect::<Vec<_>>(),
+        "count": paths.len(),
+        "reachable": reachable,
+    })))
+}
+
+/// Merge a knowledge graph exported by another orchestrator into this one's.
+///
+/// The body is the other graph's `KnowledgeGraph::to_json()` export. Merged
+/// nodes are kept until the graph is next rebuilt from the database.
+#[utoipa::path(
+    post,
+    path = "/knowledge-graph/merge",
+    tag = "tools",
+    request_body = Value,
+    responses(
+        (status = 200, description = "Counts of added tools, services and edges, and conflicts", body = Value),
+        (status = 400, description = "The body is not a knowledge graph export"),
+    )
+)]
+async fn merge_knowledge_graph(
+    State(state): State<AppState>,
+    Json(export): Json<Value>,
+) -> Result<Json<MergeResult>, (StatusCode, String)> {
+    let other = KnowledgeGraph::from_json(export).map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            format!("Invalid knowledge graph: {e}"),
+        )
+    })?;
+    let result = state.lock().await.merge_knowledge_graph(other);
+    Ok(Json(result))
+}
+
+/// Body of `POST /feedback/tool-selection`.
+#[derive(Debug, Deserialize, ToSchema)]
+struct SelectionFeedbackRequest {
+    session_id: String,
+    query: String,
+    selected_tool_id: Option<String>,
+    #[serde(default)]
+    rejected_tool_ids: Vec<String>,
+    outcome: SelectionOutcome,
+}
+
+/// Record whether the user went with a tool selection, so tools that keep
+/// being rejected for similar queries rank lower.
+///
+/// The caller is identified by a database-backed API key in `X-API-Key`.
+#[utoipa::path(
+    post,
+    path = "/feedback/tool-selection",
+    tag = "query",
+    request_body = SelectionFeedbackRequest,
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "The id of the recorded feedback", body = Value),
+        (status = 401, description = "Missing or unknown API key"),
+        (status = 429, description = "The caller sent too much feedback in the last hour"),
+    )
+)]
+async fn tool_selection_feedback(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    extensions: Extensions,
+    Json(payload): Json<SelectionFeedbackRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    // Embedding the query can be slow, so don't hold the orchestrator for it
+    let (user, recorder) = {
+        let orchestrator = state.lock().await;
+        let user = authenticate_api_key(&orchestrator, &headers, &extensions).await?;
+        (user, orchestrator.selection_feedback_recorder())
+    };
+
+    let feedback = SelectionFeedbackCreate {
+        user_id: user.user_id_string(),
+        session_id: payload.session_id,
+        query: payload.query,
+        query_embedding: None,
+        selected_tool_id: payload.selected_tool_id.as_deref().map(parse_tool_id),
+        rejected_tool_ids: payload
+            .rejected_tool_ids
+            .iter()
+            .map(|id| parse_tool_id(id))
+            .collect(),
+        outcome: payload.outcome,
+    };
+
+    let record = recorder.record(feedback).await.map_err(|e| {
+        if e.is::<FeedbackLimitError>() {
+            StatusCode::TOO_MANY_REQUESTS
+        } else {
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    })?;
+
+    Ok(Json(serde_json::json!({
+        "status": "recorded",
+        "id": record.id.to_string(),
+    })))
+}
+
 /// List all discovered MCP services.
 ///
 /// This is a read-only endpoint that returns information about all services
 /// that have been discovered and registered in the orchestrator.
+#[utoipa::path(
+    get,
+    path = "/services",
+    tag = "services",
+    responses((status = 200, description = "Discovered services", body = Value))
+)]
 async fn list_services(State(state): State<AppState>) -> Result<Json<Value>, StatusCode> {
     // Read-only operation: query services from the database
     let orchestrator = state.lock().await;
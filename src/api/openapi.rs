@@ -0,0 +1,208 @@
+//! OpenAPI 3.1 descriptions of the public and admin REST APIs.
+//!
+//! Each router serves its own spec at `GET /openapi.json` and a Swagger UI at
+//! `GET /docs`, so the admin endpoints are only documented on the admin port.
+
+use axum::{
+    Router,
+    http::{HeaderValue, header},
+    middleware,
+    response::Response,
+};
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+use utoipa_swagger_ui::SwaggerUi;
+
+use super::*;
+use crate::db::schema::SelectionOutcome;
+
+/// `Content-Security-Policy` for the Swagger UI, which loads its own scripts,
+/// styles and images and fetches the spec from the same origin.
+pub const DOCS_CSP_POLICY: &str = "default-src 'self'; script-src 'self' 'unsafe-inline'; style-src 'self' 'unsafe-inline'; img-src 'self' data:";
+
+/// The user-facing API served on the public port.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Unicity Orchestrator", description = "Public REST API"),
+    paths(
+        health_check,
+        query_tools,
+        estimate_query_cost,
+        query_tools_by_vector,
+        tool_selection_feedback,
+        list_services,
+        service_icon,
+        export_own_permissions,
+        set_own_embedding_backend,
+    ),
+    components(schemas(
+        QueryRequest,
+        EstimateRequest,
+        VectorQuery,
+        SelectionFeedbackRequest,
+        SelectionOutcome,
+        EmbeddingPreferenceRequest,
+    )),
+    modifiers(&SecuritySchemes)
+)]
+pub struct PublicApi;
+
+/// The mutating API served on the admin port.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Unicity Orchestrator Admin", description = "Admin REST API"),
+    paths(
+        health_check,
+        discover_tools,
+        prune_stale_records,
+        list_service_info,
+        service_similarity,
+        refresh_service_prompts,
+        list_tools,
+        tool_similarity,
+        patch_tool_metadata,
+        list_blocked_tools,
+        unblock_tool,
+        suggest_tool_arguments,
+        tool_changelog,
+        tool_schema_diff,
+        normalized_tool_description,
+        list_prompts,
+        prompt_catalog_json,
+        prompt_catalog_markdown,
+        inferred_prompt_arguments,
+        tool_changes_ws,
+        list_resource_conflicts,
+        batch_read_resources,
+        plan_cache_stats,
+        clear_tool_cache,
+        auth_rate_limits,
+        rotate_api_key,
+        metrics,
+        plan_diagram,
+        reload_config,
+        bulk_grant_permissions,
+        export_user_permissions,
+        type_graph_path,
+        merge_knowledge_graph,
+        list_rules,
+        create_rule,
+        update_rule,
+        rule_coverage,
+        delete_rule,
+        pretty_rule,
+    ),
+    components(schemas(
+        BatchReadRequest,
+        BulkGrantItem,
+        ApprovalAction,
+        SuggestArgsRequest,
+        CreateRuleRequest,
+    )),
+    modifiers(&SecuritySchemes)
+)]
+pub struct AdminApi;
+
+/// Registers the `Bearer` JWT and `X-API-Key` schemes that clients can
+/// authenticate with.
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer_auth",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("JWT")
+                    .build(),
+            ),
+        );
+        components.add_security_scheme(
+            "api_key",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("X-API-Key"))),
+        );
+    }
+}
+
+/// Routes serving `spec` at `/openapi.json` and a Swagger UI for it at `/docs`.
+pub(super) fn docs_router<S>(spec: utoipa::openapi::OpenApi) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    Router::from(SwaggerUi::new("/docs").url("/openapi.json", spec))
+        .layer(middleware::map_response(allow_docs_assets))
+}
+
+/// Relax the CSP for the docs routes; the security headers layer keeps a CSP
+/// that is already set.
+async fn allow_docs_assets(mut response: Response) -> Response {
+    response
+        .headers_mut()
+        .entry(header::CONTENT_SECURITY_POLICY)
+        .or_insert(HeaderValue::from_static(DOCS_CSP_POLICY));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_specs_cover_routes_and_security_schemes() {
+        let public = PublicApi::openapi();
+        let paths: Vec<&str> = public.paths.paths.keys().map(String::as_str).collect();
+        assert!(paths.contains(&"/query"));
+        assert!(paths.contains(&"/me/preferences/embedding"));
+        assert!(!paths.contains(&"/discover"));
+
+        let schemes = &public.components.as_ref().unwrap().security_schemes;
+        assert!(schemes.contains_key("bearer_auth"));
+        assert!(schemes.contains_key("api_key"));
+
+        let admin = AdminApi::openapi();
+        assert!(admin.paths.paths.contains_key("/rules/{id}"));
+        assert!(
+            admin
+                .components
+                .as_ref()
+                .unwrap()
+                .schemas
+                .contains_key("CreateRuleRequest")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_docs_router_serves_spec_with_relaxed_csp() {
+        let router =
+            docs_router::<()>(PublicApi::openapi()).layer(crate::api::SecurityHeadersLayer::new());
+        let response = router
+            .oneshot(
+                Request::builder()
+                    .uri("/openapi.json")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_SECURITY_POLICY],
+            DOCS_CSP_POLICY
+        );
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let spec: Value = serde_json::from_slice(&body).unwrap();
+        assert!(spec["openapi"].as_str().unwrap().starts_with("3.1"));
+        assert_eq!(
+            spec["paths"]["/query"]["post"]["summary"],
+            "Find the tools best matching a natural-language query."
+        );
+    }
+}
@@ -0,0 +1,168 @@
+//! Security headers added to every REST API response.
+
+use axum::http::{HeaderName, HeaderValue, Request, Response, header};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+
+/// Default `Content-Security-Policy`: the API serves JSON only, so nothing may load.
+pub const DEFAULT_CSP_POLICY: &str = "default-src 'none'";
+
+const HSTS_POLICY: &str = "max-age=63072000; includeSubDomains";
+const PERMISSIONS_POLICY: &str = "camera=(), microphone=(), geolocation=(), payment=(), usb=()";
+
+/// Layer that adds HSTS, CSP, framing, sniffing, referrer and permissions headers.
+///
+/// Headers already set by a handler are left untouched.
+#[derive(Debug, Clone)]
+pub struct SecurityHeadersLayer {
+    headers: Arc<Vec<(HeaderName, HeaderValue)>>,
+}
+
+impl SecurityHeadersLayer {
+    /// Create the layer with the default CSP policy.
+    pub fn new() -> Self {
+        Self::build(HeaderValue::from_static(DEFAULT_CSP_POLICY))
+    }
+
+    /// Create the layer with a custom `Content-Security-Policy`.
+    pub fn with_csp_policy(csp_policy: &str) -> anyhow::Result<Self> {
+        let value = HeaderValue::from_str(csp_policy)
+            .map_err(|e| anyhow::anyhow!("Invalid CSP policy `{}`: {}", csp_policy, e))?;
+        Ok(Self::build(value))
+    }
+
+    fn build(csp_policy: HeaderValue) -> Self {
+        let headers = vec![
+            (
+                header::STRICT_TRANSPORT_SECURITY,
+                HeaderValue::from_static(HSTS_POLICY),
+            ),
+            (
+                header::X_CONTENT_TYPE_OPTIONS,
+                HeaderValue::from_static("nosniff"),
+            ),
+            (header::X_FRAME_OPTIONS, HeaderValue::from_static("DENY")),
+            (header::CONTENT_SECURITY_POLICY, csp_policy),
+            (
+                header::REFERRER_POLICY,
+                HeaderValue::from_static("no-referrer"),
+            ),
+            (
+                HeaderName::from_static("permissions-policy"),
+                HeaderValue::from_static(PERMISSIONS_POLICY),
+            ),
+        ];
+        Self {
+            headers: Arc::new(headers),
+        }
+    }
+}
+
+impl Default for SecurityHeadersLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Layer<S> for SecurityHeadersLayer {
+    type Service = SecurityHeaders<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        SecurityHeaders {
+            inner,
+            headers: self.headers.clone(),
+        }
+    }
+}
+
+/// Service produced by [`SecurityHeadersLayer`].
+#[derive(Debug, Clone)]
+pub struct SecurityHeaders<S> {
+    inner: S,
+    headers: Arc<Vec<(HeaderName, HeaderValue)>>,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for SecurityHeaders<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        let future = self.inner.call(request);
+        let headers = self.headers.clone();
+
+        Box::pin(async move {
+            let mut response = future.await?;
+            let response_headers = response.headers_mut();
+            for (name, value) in headers.iter() {
+                response_headers
+                    .entry(name.clone())
+                    .or_insert_with(|| value.clone());
+            }
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::body::Body;
+    use axum::routing::get;
+    use tower::ServiceExt;
+
+    async fn get_root(router: Router) -> Response<Body> {
+        router
+            .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_adds_security_headers() {
+        let router = Router::new()
+            .route("/", get(|| async { "ok" }))
+            .layer(SecurityHeadersLayer::new());
+        let response = get_root(router).await;
+        let headers = response.headers();
+
+        assert_eq!(headers["x-content-type-options"], "nosniff");
+        assert_eq!(headers["x-frame-options"], "DENY");
+        assert_eq!(headers["content-security-policy"], DEFAULT_CSP_POLICY);
+        assert_eq!(headers["referrer-policy"], "no-referrer");
+        assert!(headers.contains_key("strict-transport-security"));
+        assert!(headers.contains_key("permissions-policy"));
+    }
+
+    #[tokio::test]
+    async fn test_custom_csp_and_handler_headers_win() {
+        let router = Router::new()
+            .route(
+                "/",
+                get(|| async { ([(header::X_FRAME_OPTIONS, "SAMEORIGIN")], "ok") }),
+            )
+            .layer(SecurityHeadersLayer::with_csp_policy("default-src 'self'").unwrap());
+        let response = get_root(router).await;
+        let headers = response.headers();
+
+        assert_eq!(headers["content-security-policy"], "default-src 'self'");
+        assert_eq!(headers["x-frame-options"], "SAMEORIGIN");
+    }
+
+    #[test]
+    fn test_invalid_csp_policy_is_rejected() {
+        assert!(SecurityHeadersLayer::with_csp_policy("default-src\n'none'").is_err());
+    }
+}
@@ -4,6 +4,28 @@ use crate::types::{ExternalUserId, IdentityProvider};
 use serde::{Deserialize, Serialize};
 use surrealdb::RecordId;
 
+/// MCP error code for a call the user is not authorized to make.
+pub const UNAUTHORIZED_ERROR_CODE: i32 = -32003;
+
+/// A tool call was refused because the user lacks the role the tool requires.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingRoleError {
+    pub tool_id: String,
+    pub required_role: String,
+}
+
+impl std::fmt::Display for MissingRoleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Tool {} requires the '{}' role",
+            self.tool_id, self.required_role
+        )
+    }
+}
+
+impl std::error::Error for MissingRoleError {}
+
 /// User context extracted from the HTTP request.
 ///
 /// This struct is passed through the request handling chain to provide
@@ -26,6 +48,11 @@ pub struct UserContext {
     ip_address: Option<String>,
     /// Client user agent (for audit logging)
     user_agent: Option<String>,
+    /// ISO 3166-1 alpha-2 country of the client, e.g. from `X-Geo-Country`
+    country_code: Option<String>,
+    /// Roles granted to the user, e.g. `admin`
+    #[serde(default)]
+    roles: Vec<String>,
 }
 
 impl UserContext {
@@ -47,6 +74,8 @@ impl UserContext {
             is_anonymous,
             ip_address: None,
             user_agent: None,
+            country_code: None,
+            roles: Vec::new(),
         }
     }
 
@@ -61,6 +90,8 @@ impl UserContext {
             is_anonymous: true,
             ip_address: None,
             user_agent: None,
+            country_code: None,
+            roles: Vec::new(),
         }
     }
 
@@ -75,6 +106,27 @@ impl UserContext {
         self
     }
 
+    /// Set the client's country, stored uppercase. Blank codes are ignored.
+    pub fn with_country_code(mut self, country_code: Option<String>) -> Self {
+        self.country_code = country_code
+            .map(|code| code.trim().to_ascii_uppercase())
+            .filter(|code| !code.is_empty());
+        self
+    }
+
+    /// Set the roles granted to the user. Blank and duplicate roles are dropped.
+    pub fn with_roles(mut self, roles: Vec<String>) -> Self {
+        let mut roles: Vec<String> = roles
+            .into_iter()
+            .map(|role| role.trim().to_string())
+            .filter(|role| !role.is_empty())
+            .collect();
+        roles.sort();
+        roles.dedup();
+        self.roles = roles;
+        self
+    }
+
     /// Get the database user ID.
     pub fn user_id(&self) -> &RecordId {
         &self.user_id
@@ -120,6 +172,21 @@ impl UserContext {
         self.user_agent.as_deref()
     }
 
+    /// Get the client's ISO 3166-1 alpha-2 country code.
+    pub fn country_code(&self) -> Option<&str> {
+        self.country_code.as_deref()
+    }
+
+    /// Get the roles granted to the user.
+    pub fn roles(&self) -> &[String] {
+        &self.roles
+    }
+
+    /// Check if the user has been granted `role`.
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|r| r == role)
+    }
+
     /// Get a display-friendly name for this user.
     pub fn display(&self) -> String {
         if let Some(name) = &self.display_name {
@@ -180,6 +247,32 @@ mod tests {
         assert_eq!(ctx.user_agent(), Some("Mozilla/5.0"));
     }
 
+    #[test]
+    fn test_user_context_with_country_code() {
+        let ctx = UserContext::anonymous(test_user_id()).with_country_code(Some(" de ".into()));
+        assert_eq!(ctx.country_code(), Some("DE"));
+
+        let ctx = UserContext::anonymous(test_user_id()).with_country_code(Some(String::new()));
+        assert_eq!(ctx.country_code(), None);
+    }
+
+    #[test]
+    fn test_user_context_with_roles() {
+        let ctx = UserContext::anonymous(test_user_id());
+        assert!(ctx.roles().is_empty());
+        assert!(!ctx.has_role("admin"));
+
+        let ctx = ctx.with_roles(vec![
+            "operator".to_string(),
+            " admin ".to_string(),
+            String::new(),
+            "operator".to_string(),
+        ]);
+        assert_eq!(ctx.roles(), ["admin", "operator"]);
+        assert!(ctx.has_role("admin"));
+        assert!(!ctx.has_role("auditor"));
+    }
+
     #[test]
     fn test_user_context_display() {
         // With display name
@@ -1,17 +1,23 @@
 //! Authentication extractor for HTTP requests.
 
 use std::fmt;
+use std::net::IpAddr;
 use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use crate::auth::context::UserContext;
-use crate::auth::jwks::{DEFAULT_CACHE_TTL_SECONDS, JwksCache};
+use crate::auth::jwks::{JwksCache, JwksCacheStats, default_cache_ttl_seconds};
+use crate::auth::rate_limit::{
+    AuthRateLimiter, DEFAULT_AUTH_WINDOW_SECS, DEFAULT_MAX_AUTH_FAILURES_PER_WINDOW,
+};
 use crate::auth::user_store::UserStore;
 use crate::db::Db;
+use crate::metrics::MetricsCollector;
 use crate::types::{ApiKeyHash, ApiKeyPrefix, ExternalUserId, IdentityProvider};
 use jsonwebtoken::{Algorithm, Validation, decode, decode_header};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use surrealdb::RecordId;
 use tracing::debug;
 
 /// Authentication configuration.
@@ -33,7 +39,7 @@ pub struct AuthConfig {
     /// JWKS endpoint URL for key fetching
     #[serde(default)]
     pub jwks_url: Option<String>,
-    /// JWKS cache TTL in seconds (default: 3600)
+    /// JWKS cache TTL in seconds (default: 3600, or `JWKS_CACHE_TTL` if set)
     #[serde(default = "default_jwks_cache_seconds")]
     pub jwks_cache_seconds: u64,
     /// Whether to allow stale JWKS cache on fetch failure
@@ -42,10 +48,29 @@ pub struct AuthConfig {
     /// Whether to enable database-backed API key lookup
     #[serde(default)]
     pub db_api_keys_enabled: bool,
+    /// Failed authentications an IP may make per window before its requests
+    /// are rejected with `429 Too Many Requests`; `0` disables the limit
+    #[serde(default = "default_max_auth_failures_per_window")]
+    pub max_auth_failures_per_window: u32,
+    /// Length of the sliding window failed authentications are counted in
+    #[serde(default = "default_window_secs")]
+    pub window_secs: u64,
+    /// Reverse proxies whose `X-Forwarded-For` / `X-Real-IP` headers are
+    /// believed; requests from anywhere else are keyed on the peer address
+    #[serde(default)]
+    pub trusted_proxies: Vec<IpAddr>,
 }
 
 fn default_jwks_cache_seconds() -> u64 {
-    DEFAULT_CACHE_TTL_SECONDS
+    default_cache_ttl_seconds()
+}
+
+fn default_max_auth_failures_per_window() -> u32 {
+    DEFAULT_MAX_AUTH_FAILURES_PER_WINDOW
+}
+
+fn default_window_secs() -> u64 {
+    DEFAULT_AUTH_WINDOW_SECS
 }
 
 fn default_allow_stale_jwks() -> bool {
@@ -63,9 +88,12 @@ impl Default for AuthConfig {
             jwt_issuer: None,
             jwt_audience: None,
             jwks_url: None,
-            jwks_cache_seconds: DEFAULT_CACHE_TTL_SECONDS,
+            jwks_cache_seconds: default_cache_ttl_seconds(),
             allow_stale_jwks: true,
             db_api_keys_enabled: false,
+            max_auth_failures_per_window: DEFAULT_MAX_AUTH_FAILURES_PER_WINDOW,
+            window_secs: DEFAULT_AUTH_WINDOW_SECS,
+            trusted_proxies: Vec::new(),
         }
     }
 }
@@ -105,7 +133,7 @@ impl AuthConfig {
             jwt_issuer: Some(issuer),
             jwt_audience: audience,
             jwks_url: Some(jwks_url),
-            jwks_cache_seconds: DEFAULT_CACHE_TTL_SECONDS,
+            jwks_cache_seconds: default_cache_ttl_seconds(),
             allow_stale_jwks: true,
             ..Default::default()
         }
@@ -131,6 +159,20 @@ pub enum AuthError {
     DatabaseError(String),
     /// JWKS error
     JwksError(String),
+    /// Too many failed authentications from the client's IP
+    RateLimited { retry_after: Duration },
+}
+
+impl AuthError {
+    /// Whether the error means the presented credentials were wrong, as
+    /// opposed to missing, belonging to a deactivated user, or unverifiable
+    /// because of an internal error.
+    pub fn is_credential_failure(&self) -> bool {
+        matches!(
+            self,
+            Self::InvalidApiKey | Self::ApiKeyExpired | Self::ApiKeyRevoked | Self::InvalidToken(_)
+        )
+    }
 }
 
 impl fmt::Display for AuthError {
@@ -144,6 +186,11 @@ impl fmt::Display for AuthError {
             Self::UserDeactivated => write!(f, "User account is deactivated"),
             Self::DatabaseError(msg) => write!(f, "Database error: {}", msg),
             Self::JwksError(msg) => write!(f, "JWKS error: {}", msg),
+            Self::RateLimited { retry_after } => write!(
+                f,
+                "Too many failed authentication attempts, retry after {}s",
+                crate::auth::rate_limit::retry_after_secs(*retry_after)
+            ),
         }
     }
 }
@@ -155,6 +202,7 @@ pub struct AuthExtractor {
     config: AuthConfig,
     user_store: Arc<UserStore>,
     jwks_cache: Option<Arc<JwksCache>>,
+    rate_limiter: Arc<AuthRateLimiter>,
     db: Db,
 }
 
@@ -170,14 +218,29 @@ impl AuthExtractor {
             ))
         });
 
+        let rate_limiter = Arc::new(AuthRateLimiter::from_config(&config));
+
         Self {
             config,
             user_store: Arc::new(UserStore::new(db.clone())),
             jwks_cache,
+            rate_limiter,
             db,
         }
     }
 
+    /// Count failed authentications in `rate_limiter`, e.g. to share it with
+    /// other extractors, instead of one built from the config.
+    pub fn with_rate_limiter(mut self, rate_limiter: Arc<AuthRateLimiter>) -> Self {
+        self.rate_limiter = rate_limiter;
+        self
+    }
+
+    /// Get reference to the failed authentication limiter.
+    pub fn rate_limiter(&self) -> &Arc<AuthRateLimiter> {
+        &self.rate_limiter
+    }
+
     /// Get reference to the user store.
     pub fn user_store(&self) -> &Arc<UserStore> {
         &self.user_store
@@ -188,12 +251,56 @@ impl AuthExtractor {
         &self.db
     }
 
+    /// Get JWKS cache statistics, if JWT verification via JWKS is configured.
+    pub async fn jwks_stats(&self) -> Option<JwksCacheStats> {
+        match &self.jwks_cache {
+            Some(cache) => Some(cache.stats().await),
+            None => None,
+        }
+    }
+
+    /// Export the JWKS cache's lookup counters through `metrics`, if JWT
+    /// verification via JWKS is configured.
+    pub fn register_metrics(&self, metrics: &MetricsCollector) {
+        if let Some(cache) = &self.jwks_cache {
+            metrics.register(cache.clone());
+        }
+    }
+
+    /// Reject `ip_address` if it has failed to authenticate too often.
+    fn check_rate_limit(&self, ip_address: Option<&str>) -> Result<(), AuthError> {
+        match ip_address {
+            Some(ip) => self
+                .rate_limiter
+                .check(ip, Instant::now())
+                .map_err(|retry_after| AuthError::RateLimited { retry_after }),
+            None => Ok(()),
+        }
+    }
+
+    /// Count the outcome of authenticating `ip_address` with credentials.
+    fn record_attempt<T>(&self, ip_address: Option<&str>, result: &Result<T, AuthError>) {
+        let Some(ip) = ip_address else {
+            return;
+        };
+        match result {
+            Ok(_) => self.rate_limiter.record_success(ip),
+            Err(e) if e.is_credential_failure() => {
+                self.rate_limiter.record_failure(ip, Instant::now())
+            }
+            Err(_) => {}
+        }
+    }
+
     /// Extract user context from HTTP headers.
     ///
     /// This checks authentication in order:
     /// 1. Bearer token (JWT) if enabled
     /// 2. API key header
     /// 3. Anonymous if allowed
+    ///
+    /// IPs with too many failed authentications are rejected with
+    /// `AuthError::RateLimited` before any credentials are checked.
     pub async fn extract_user(
         &self,
         authorization: Option<&str>,
@@ -201,16 +308,23 @@ impl AuthExtractor {
         ip_address: Option<String>,
         user_agent: Option<String>,
     ) -> Result<UserContext, AuthError> {
+        self.check_rate_limit(ip_address.as_deref())?;
+        let ip = ip_address.clone();
+
         // Try Bearer token first
         if let Some(auth_header) = authorization
             && let Some(token) = auth_header.strip_prefix("Bearer ")
         {
-            return self.extract_from_jwt(token, ip_address, user_agent).await;
+            let result = self.extract_from_jwt(token, ip_address, user_agent).await;
+            self.record_attempt(ip.as_deref(), &result);
+            return result;
         }
 
         // Try API key
         if let Some(key) = api_key {
-            return self.extract_from_api_key(key, ip_address, user_agent).await;
+            let result = self.api_key_user(key, ip_address, user_agent).await;
+            self.record_attempt(ip.as_deref(), &result);
+            return result;
         }
 
         // Fall back to anonymous if allowed
@@ -221,6 +335,39 @@ impl AuthExtractor {
         Err(AuthError::Unauthenticated)
     }
 
+    /// Extract user context from the `_meta.auth` object of an MCP request.
+    ///
+    /// Reads `_meta.auth.bearer_token` (validated as a JWT) or, failing that,
+    /// `_meta.auth.api_key`. Returns `Ok(None)` when the request carries no
+    /// credentials, so the caller can fall back to the session user.
+    pub async fn extract_from_mcp_metadata(
+        &self,
+        meta: &serde_json::Map<String, serde_json::Value>,
+        ip_address: Option<String>,
+        user_agent: Option<String>,
+    ) -> Result<Option<UserContext>, AuthError> {
+        let Some(auth) = meta.get("auth").and_then(|v| v.as_object()) else {
+            return Ok(None);
+        };
+        let ip = ip_address.clone();
+
+        if let Some(token) = auth.get("bearer_token").and_then(|v| v.as_str()) {
+            self.check_rate_limit(ip.as_deref())?;
+            let result = self.extract_from_jwt(token, ip_address, user_agent).await;
+            self.record_attempt(ip.as_deref(), &result);
+            return result.map(Some);
+        }
+
+        if let Some(key) = auth.get("api_key").and_then(|v| v.as_str()) {
+            self.check_rate_limit(ip.as_deref())?;
+            let result = self.api_key_user(key, ip_address, user_agent).await;
+            self.record_attempt(ip.as_deref(), &result);
+            return result.map(Some);
+        }
+
+        Ok(None)
+    }
+
     /// Extract user from JWT token with RS256 signature verification.
     async fn extract_from_jwt(
         &self,
@@ -304,17 +451,34 @@ impl AuthExtractor {
             claims.email,
             claims.name,
         )
+        .with_roles(claims.roles)
         .with_client_info(ip_address, user_agent);
 
         Ok(ctx)
     }
 
     /// Extract user from API key.
+    ///
+    /// Like `extract_user`, rejects IPs with too many failed authentications.
     pub async fn extract_from_api_key(
         &self,
         key: &str,
         ip_address: Option<String>,
         user_agent: Option<String>,
+    ) -> Result<UserContext, AuthError> {
+        self.check_rate_limit(ip_address.as_deref())?;
+        let ip = ip_address.clone();
+        let result = self.api_key_user(key, ip_address, user_agent).await;
+        self.record_attempt(ip.as_deref(), &result);
+        result
+    }
+
+    /// Resolve the user of a static or database-backed API key.
+    async fn api_key_user(
+        &self,
+        key: &str,
+        ip_address: Option<String>,
+        user_agent: Option<String>,
     ) -> Result<UserContext, AuthError> {
         // Database-backed API key lookup
         if self.config.db_api_keys_enabled {
@@ -346,6 +510,7 @@ impl AuthExtractor {
             return Err(AuthError::UserDeactivated);
         }
 
+        let roles = self.user_roles(&user.id).await?;
         let ctx = UserContext::new(
             user.id,
             ExternalUserId::new(key_hash.into_inner()),
@@ -353,6 +518,7 @@ impl AuthExtractor {
             None,
             Some("API User".to_string()),
         )
+        .with_roles(roles)
         .with_client_info(ip_address, user_agent);
 
         Ok(ctx)
@@ -416,6 +582,7 @@ impl AuthExtractor {
             .clone()
             .unwrap_or_else(|| format!("API Key {}", api_key.key_prefix));
 
+        let roles = self.user_roles(&user_id).await?;
         let ctx = UserContext::new(
             user_id,
             ExternalUserId::new(format!("api_key:{}", api_key.key_prefix)),
@@ -423,11 +590,20 @@ impl AuthExtractor {
             None,
             Some(display_name),
         )
+        .with_roles(roles)
         .with_client_info(ip_address, user_agent);
 
         Ok(ctx)
     }
 
+    /// Roles granted to an API key user in the `user_roles` table.
+    async fn user_roles(&self, user_id: &RecordId) -> Result<Vec<String>, AuthError> {
+        self.user_store
+            .get_roles(user_id)
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))
+    }
+
     /// Extract anonymous user for local mode.
     async fn extract_anonymous(
         &self,
@@ -462,6 +638,9 @@ pub struct JwtClaims {
     pub name: Option<String>,
     /// Expiration time (Unix timestamp)
     pub exp: Option<u64>,
+    /// Roles granted to the user
+    #[serde(default)]
+    pub roles: Vec<String>,
 }
 
 /// Hash an API key for storage and lookup (don't store raw keys).
@@ -507,7 +686,7 @@ mod tests {
         assert_eq!(config.api_key_header, "X-API-Key");
         assert!(!config.jwt_enabled);
         assert!(config.jwks_url.is_none());
-        assert_eq!(config.jwks_cache_seconds, DEFAULT_CACHE_TTL_SECONDS);
+        assert_eq!(config.jwks_cache_seconds, default_cache_ttl_seconds());
         assert!(config.allow_stale_jwks);
     }
 
@@ -633,6 +812,43 @@ mod tests {
         assert_eq!(ctx.provider().as_str(), "api_key");
     }
 
+    #[tokio::test]
+    async fn test_api_key_user_roles_from_table() {
+        let db = setup_test_db().await;
+        let config = AuthConfig::with_api_key("secret123".to_string());
+        let extractor = AuthExtractor::new(config, db);
+
+        let ctx = extractor
+            .extract_user(None, Some("secret123"), None, None)
+            .await
+            .unwrap();
+        assert!(ctx.roles().is_empty());
+
+        let store = extractor.user_store();
+        store.grant_role(ctx.user_id(), "admin").await.unwrap();
+        store.grant_role(ctx.user_id(), "admin").await.unwrap();
+        store.grant_role(ctx.user_id(), "auditor").await.unwrap();
+        assert_eq!(
+            store.get_roles(ctx.user_id()).await.unwrap(),
+            ["admin", "auditor"]
+        );
+
+        let ctx = extractor
+            .extract_user(None, Some("secret123"), None, None)
+            .await
+            .unwrap();
+        assert!(ctx.has_role("admin"));
+        assert!(ctx.has_role("auditor"));
+
+        assert!(store.revoke_role(ctx.user_id(), "auditor").await.unwrap());
+        assert!(!store.revoke_role(ctx.user_id(), "auditor").await.unwrap());
+        let ctx = extractor
+            .extract_user(None, Some("secret123"), None, None)
+            .await
+            .unwrap();
+        assert_eq!(ctx.roles(), ["admin"]);
+    }
+
     #[tokio::test]
     async fn test_auth_extractor_api_key_invalid() {
         let db = setup_test_db().await;
@@ -647,6 +863,37 @@ mod tests {
         assert!(matches!(result.unwrap_err(), AuthError::InvalidApiKey));
     }
 
+    #[tokio::test]
+    async fn test_auth_extractor_rate_limits_failing_ip() {
+        let db = setup_test_db().await;
+        let config = AuthConfig {
+            max_auth_failures_per_window: 2,
+            ..AuthConfig::with_api_key("secret123".to_string())
+        };
+        let extractor = AuthExtractor::new(config, db);
+        let ip = || Some("10.0.0.1".to_string());
+
+        // A success in between resets the count
+        for key in ["wrong_key", "secret123", "wrong_key", "wrong_key"] {
+            let _ = extractor.extract_user(None, Some(key), ip(), None).await;
+        }
+
+        let result = extractor
+            .extract_user(None, Some("secret123"), ip(), None)
+            .await;
+        assert!(matches!(result, Err(AuthError::RateLimited { .. })));
+        assert_eq!(
+            extractor.rate_limiter().penalized(Instant::now())[0].failures,
+            2
+        );
+
+        // Other IPs are unaffected
+        let result = extractor
+            .extract_user(None, Some("secret123"), Some("10.0.0.2".to_string()), None)
+            .await;
+        assert!(result.is_ok());
+    }
+
     #[tokio::test]
     async fn test_auth_extractor_no_auth_required() {
         let db = setup_test_db().await;
@@ -659,6 +906,51 @@ mod tests {
         assert!(matches!(result.unwrap_err(), AuthError::Unauthenticated));
     }
 
+    #[tokio::test]
+    async fn test_extract_from_mcp_metadata() {
+        let db = setup_test_db().await;
+        let config = AuthConfig::with_api_key("secret123".to_string());
+        let extractor = AuthExtractor::new(config, db);
+        let meta = |value: serde_json::Value| value.as_object().unwrap().clone();
+
+        let ctx = extractor
+            .extract_from_mcp_metadata(
+                &meta(serde_json::json!({"auth": {"api_key": "secret123"}})),
+                None,
+                None,
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(ctx.provider().as_str(), "api_key");
+
+        let result = extractor
+            .extract_from_mcp_metadata(
+                &meta(serde_json::json!({"auth": {"api_key": "wrong_key"}})),
+                None,
+                None,
+            )
+            .await;
+        assert!(matches!(result.unwrap_err(), AuthError::InvalidApiKey));
+
+        // JWT is not enabled for this extractor
+        let result = extractor
+            .extract_from_mcp_metadata(
+                &meta(serde_json::json!({"auth": {"bearer_token": "abc"}})),
+                None,
+                None,
+            )
+            .await;
+        assert!(matches!(result.unwrap_err(), AuthError::InvalidToken(_)));
+
+        // No credentials: the caller falls back to the session user
+        let none = extractor
+            .extract_from_mcp_metadata(&meta(serde_json::json!({"progressToken": 1})), None, None)
+            .await
+            .unwrap();
+        assert!(none.is_none());
+    }
+
     #[tokio::test]
     async fn test_user_deactivation_blocks_access() {
         let db = setup_test_db().await;
@@ -681,13 +973,62 @@ mod tests {
         assert!(matches!(result.unwrap_err(), AuthError::UserDeactivated));
     }
 
+    #[tokio::test]
+    async fn test_rotated_api_key_is_revoked() {
+        let db = setup_test_db().await;
+        let (old_key, prefix, hash) = generate_api_key();
+        crate::db::QueryBuilder::create_api_key(
+            &db,
+            &crate::db::ApiKeyCreate {
+                key_hash: hash.clone(),
+                key_prefix: prefix,
+                user_id: None,
+                name: Some("ci".to_string()),
+                expires_at: None,
+                scopes: Some(vec!["tools:read".to_string()]),
+            },
+        )
+        .await
+        .unwrap();
+        let extractor = AuthExtractor::new(AuthConfig::with_db_api_keys(), db);
+        let before = extractor
+            .extract_from_api_key(&old_key, None, None)
+            .await
+            .unwrap();
+
+        let (new_key, new_prefix, new_hash) =
+            extractor.user_store().rotate_api_key(&hash).await.unwrap();
+        assert_eq!(hash_api_key(&new_key), new_hash);
+        assert!(new_key.starts_with(new_prefix.as_str()));
+
+        let result = extractor.extract_from_api_key(&old_key, None, None).await;
+        assert!(matches!(result.unwrap_err(), AuthError::ApiKeyRevoked));
+        let after = extractor
+            .extract_from_api_key(&new_key, None, None)
+            .await
+            .unwrap();
+        assert_eq!(after.user_id(), before.user_id());
+
+        let rotated =
+            crate::db::QueryBuilder::find_api_key_by_hash(&extractor.db, new_hash.as_str())
+                .await
+                .unwrap()
+                .unwrap();
+        assert_eq!(rotated.name.as_deref(), Some("ci"));
+        assert_eq!(rotated.scopes, Some(vec!["tools:read".to_string()]));
+
+        // A revoked key can't be rotated again
+        assert!(extractor.user_store().rotate_api_key(&hash).await.is_err());
+    }
+
     #[test]
     fn test_jwt_claims_deserialization() {
         let json = r#"{
             "sub": "user123",
             "email": "user@example.com",
             "name": "Test User",
-            "exp": 1735689600
+            "exp": 1735689600,
+            "roles": ["admin"]
         }"#;
 
         let claims: JwtClaims = serde_json::from_str(json).unwrap();
@@ -695,5 +1036,10 @@ mod tests {
         assert_eq!(claims.email, Some("user@example.com".to_string()));
         assert_eq!(claims.name, Some("Test User".to_string()));
         assert_eq!(claims.exp, Some(1735689600));
+        assert_eq!(claims.roles, ["admin"]);
+
+        // Tokens without roles grant none
+        let claims: JwtClaims = serde_json::from_str(r#"{ "sub": "user123" }"#).unwrap();
+        assert!(claims.roles.is_empty());
     }
 }
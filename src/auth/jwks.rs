@@ -4,7 +4,9 @@
 //! from a JWKS endpoint for JWT signature verification.
 
 use std::collections::HashMap;
+use std::fmt::Write;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{Duration, Instant};
 
 use base64::Engine;
@@ -13,9 +15,14 @@ use serde::Deserialize;
 use tokio::sync::RwLock;
 use tracing::{debug, warn};
 
+use crate::metrics::{MetricSource, write_header};
+
 /// Default cache TTL in seconds (1 hour).
 pub const DEFAULT_CACHE_TTL_SECONDS: u64 = 3600;
 
+/// Environment variable that overrides [`DEFAULT_CACHE_TTL_SECONDS`].
+pub const CACHE_TTL_ENV_VAR: &str = "JWKS_CACHE_TTL";
+
 /// Maximum stale cache age in seconds (24 hours).
 pub const MAX_STALE_CACHE_SECONDS: u64 = 86400;
 
@@ -45,6 +52,62 @@ pub struct JwksDocument {
     pub keys: Vec<Jwk>,
 }
 
+/// Resolve the default cache TTL, honouring the `JWKS_CACHE_TTL` env var.
+///
+/// Falls back to [`DEFAULT_CACHE_TTL_SECONDS`] when the variable is unset or
+/// is not a valid number of seconds.
+pub fn default_cache_ttl_seconds() -> u64 {
+    parse_cache_ttl(std::env::var(CACHE_TTL_ENV_VAR).ok().as_deref())
+}
+
+fn parse_cache_ttl(value: Option<&str>) -> u64 {
+    match value.map(str::trim) {
+        Some(raw) => match raw.parse::<u64>() {
+            Ok(secs) => secs,
+            Err(_) => {
+                warn!(
+                    "Ignoring invalid {} value {:?}, using {}s",
+                    CACHE_TTL_ENV_VAR, raw, DEFAULT_CACHE_TTL_SECONDS
+                );
+                DEFAULT_CACHE_TTL_SECONDS
+            }
+        },
+        None => DEFAULT_CACHE_TTL_SECONDS,
+    }
+}
+
+/// Point-in-time snapshot of JWKS cache statistics.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JwksCacheStats {
+    /// Lookups served directly from the cache.
+    pub hits: u64,
+    /// Lookups that required a fetch from the JWKS endpoint.
+    pub misses: u64,
+    /// Successful fetches from the JWKS endpoint.
+    pub refreshes: u64,
+    /// Lookups served from a stale cache after a failed fetch.
+    pub stale_hits: u64,
+    /// Number of keys currently cached.
+    pub key_count: usize,
+    /// Seconds since the last successful fetch, if any.
+    pub age_seconds: Option<u64>,
+}
+
+impl JwksCacheStats {
+    /// Fraction of lookups served from the cache (stale hits included).
+    ///
+    /// Returns `0.0` when no lookups have been made yet.
+    pub fn hit_ratio(&self) -> f64 {
+        let served = self.hits + self.stale_hits;
+        let total = served + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            served as f64 / total as f64
+        }
+    }
+}
+
 /// Cached key entry with metadata.
 #[derive(Clone)]
 struct CachedKey {
@@ -61,12 +124,17 @@ pub struct JwksCache {
     cache_ttl: Duration,
     /// Whether to allow stale cache on fetch failure.
     allow_stale: bool,
-    /// Cached keys by kid.
-    keys: Arc<RwLock<HashMap<String, CachedKey>>>,
+    /// Cached keys by kid (`None` for keys published without a kid).
+    keys: Arc<RwLock<HashMap<Option<String>, CachedKey>>>,
     /// Last successful fetch time.
     last_fetch: Arc<RwLock<Option<Instant>>>,
     /// HTTP client for fetching JWKS.
     client: reqwest::Client,
+    /// Lookup counters, exposed through [`JwksCache::stats`].
+    hits: AtomicU64,
+    misses: AtomicU64,
+    refreshes: AtomicU64,
+    stale_hits: AtomicU64,
 }
 
 impl JwksCache {
@@ -82,6 +150,10 @@ impl JwksCache {
                 .timeout(Duration::from_secs(10))
                 .build()
                 .expect("Failed to create HTTP client"),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            refreshes: AtomicU64::new(0),
+            stale_hits: AtomicU64::new(0),
         }
     }
 
@@ -101,10 +173,12 @@ impl JwksCache {
 
         // Try to get from cache first
         if !should_refresh && let Some(key) = self.get_from_cache(kid).await {
+            self.hits.fetch_add(1, Ordering::Relaxed);
             return Ok(key);
         }
 
-        // Need to refresh or key not found
+        // Need to refresh or key not found (e.g. the issuer rotated keys)
+        self.misses.fetch_add(1, Ordering::Relaxed);
         match self.fetch_keys().await {
             Ok(()) => {
                 // Try to get the key again after refresh
@@ -127,6 +201,7 @@ impl JwksCache {
                     if stale_ok {
                         warn!("JWKS fetch failed, using stale cache: {}", e);
                         if let Some(key) = self.get_from_cache(kid).await {
+                            self.stale_hits.fetch_add(1, Ordering::Relaxed);
                             return Ok(key);
                         }
                     }
@@ -142,10 +217,14 @@ impl JwksCache {
         let keys = self.keys.read().await;
 
         match kid {
-            Some(k) => keys.get(k).map(|c| c.decoding_key.clone()),
+            Some(k) => keys
+                .get(&Some(k.to_string()))
+                .map(|c| c.decoding_key.clone()),
             None => {
-                // Return the first key if no kid specified
-                keys.values().next().map(|c| c.decoding_key.clone())
+                // Prefer a key published without a kid, otherwise the first key
+                keys.get(&None)
+                    .or_else(|| keys.values().next())
+                    .map(|c| c.decoding_key.clone())
             }
         }
     }
@@ -191,8 +270,8 @@ impl JwksCache {
 
             match Self::jwk_to_decoding_key(&jwk) {
                 Ok(decoding_key) => {
-                    let kid = jwk.kid.clone().unwrap_or_else(|| "default".to_string());
-                    debug!("Cached key with kid: {}", kid);
+                    let kid = jwk.kid.clone();
+                    debug!("Cached key with kid: {:?}", kid);
                     new_keys.insert(
                         kid,
                         CachedKey {
@@ -221,6 +300,7 @@ impl JwksCache {
             let mut last_fetch = self.last_fetch.write().await;
             *last_fetch = Some(now);
         }
+        self.refreshes.fetch_add(1, Ordering::Relaxed);
 
         debug!("Successfully cached {} keys", self.keys.read().await.len());
         Ok(())
@@ -268,6 +348,19 @@ impl JwksCache {
         self.keys.read().await.len()
     }
 
+    /// Get a snapshot of cache statistics (hit/miss counts and cache age).
+    pub async fn stats(&self) -> JwksCacheStats {
+        let age_seconds = self.last_fetch.read().await.map(|t| t.elapsed().as_secs());
+        JwksCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            refreshes: self.refreshes.load(Ordering::Relaxed),
+            stale_hits: self.stale_hits.load(Ordering::Relaxed),
+            key_count: self.keys.read().await.len(),
+            age_seconds,
+        }
+    }
+
     /// Clear the cache (useful for testing).
     pub async fn clear(&self) {
         let mut keys = self.keys.write().await;
@@ -306,6 +399,57 @@ impl std::fmt::Display for JwksCacheError {
 
 impl std::error::Error for JwksCacheError {}
 
+impl MetricSource for JwksCache {
+    fn encode(&self, out: &mut String) {
+        write_header(
+            out,
+            "jwks_cache_lookups_total",
+            "JWKS key lookups, by whether the cache served them",
+            "counter",
+        );
+        for (result, counter) in [
+            ("hit", &self.hits),
+            ("miss", &self.misses),
+            ("stale", &self.stale_hits),
+        ] {
+            let _ = writeln!(
+                out,
+                "jwks_cache_lookups_total{{result=\"{}\"}} {}",
+                result,
+                counter.load(Ordering::Relaxed)
+            );
+        }
+        write_header(
+            out,
+            "jwks_cache_refreshes_total",
+            "Successful fetches from the JWKS endpoint",
+            "counter",
+        );
+        let _ = writeln!(
+            out,
+            "jwks_cache_refreshes_total {}",
+            self.refreshes.load(Ordering::Relaxed)
+        );
+
+        // Only reported once keys have been fetched; skipped while a refresh
+        // holds the lock
+        let last_fetch = self.last_fetch.try_read().ok().and_then(|t| *t);
+        if let Some(last_fetch) = last_fetch {
+            write_header(
+                out,
+                "jwks_cache_age_seconds",
+                "Seconds since the keys were last fetched",
+                "gauge",
+            );
+            let _ = writeln!(
+                out,
+                "jwks_cache_age_seconds {}",
+                last_fetch.elapsed().as_secs()
+            );
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -337,6 +481,69 @@ mod tests {
         assert!(!cache.has_keys().await);
     }
 
+    #[test]
+    fn test_parse_cache_ttl() {
+        assert_eq!(parse_cache_ttl(None), DEFAULT_CACHE_TTL_SECONDS);
+        assert_eq!(parse_cache_ttl(Some("120")), 120);
+        assert_eq!(parse_cache_ttl(Some(" 60 ")), 60);
+        assert_eq!(parse_cache_ttl(Some("soon")), DEFAULT_CACHE_TTL_SECONDS);
+        assert_eq!(parse_cache_ttl(Some("-5")), DEFAULT_CACHE_TTL_SECONDS);
+    }
+
+    #[test]
+    fn test_jwks_cache_stats_hit_ratio() {
+        assert_eq!(JwksCacheStats::default().hit_ratio(), 0.0);
+
+        let stats = JwksCacheStats {
+            hits: 3,
+            misses: 1,
+            ..Default::default()
+        };
+        assert_eq!(stats.hit_ratio(), 0.75);
+
+        let stats = JwksCacheStats {
+            hits: 1,
+            misses: 2,
+            stale_hits: 1,
+            ..Default::default()
+        };
+        assert_eq!(stats.hit_ratio(), 0.5);
+    }
+
+    #[tokio::test]
+    async fn test_jwks_cache_counts_miss_on_failed_fetch() {
+        // Unroutable endpoint so the fetch fails fast without network access
+        let cache = JwksCache::new("http://127.0.0.1:1/jwks.json".to_string(), 3600, true);
+
+        let result = cache.get_key(Some("missing")).await;
+        assert!(matches!(result, Err(JwksCacheError::FetchError(_))));
+
+        let stats = cache.stats().await;
+        assert_eq!(stats.hits, 0);
+        assert_eq!(stats.misses, 1);
+        assert_eq!(stats.refreshes, 0);
+        assert_eq!(stats.key_count, 0);
+        assert!(stats.age_seconds.is_none());
+
+        let mut out = String::new();
+        cache.encode(&mut out);
+        assert!(out.contains("jwks_cache_lookups_total{result=\"hit\"} 0\n"));
+        assert!(out.contains("jwks_cache_lookups_total{result=\"miss\"} 1\n"));
+        assert!(out.contains("jwks_cache_refreshes_total 0\n"));
+        assert!(!out.contains("jwks_cache_age_seconds"));
+    }
+
+    #[tokio::test]
+    async fn test_jwks_cache_reports_age_once_fetched() {
+        let cache = JwksCache::new("http://127.0.0.1:1/jwks.json".to_string(), 3600, true);
+        *cache.last_fetch.write().await = Some(Instant::now() - Duration::from_secs(42));
+
+        let mut out = String::new();
+        cache.encode(&mut out);
+        assert!(out.contains("# TYPE jwks_cache_age_seconds gauge\n"));
+        assert!(out.contains("jwks_cache_age_seconds 42\n"));
+    }
+
     #[test]
     fn test_jwk_deserialization() {
         let json = r#"{
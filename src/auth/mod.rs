@@ -31,9 +31,13 @@
 mod context;
 mod extractor;
 pub mod jwks;
+pub mod rate_limit;
 mod user_store;
 
-pub use context::UserContext;
+pub use context::{MissingRoleError, UNAUTHORIZED_ERROR_CODE, UserContext};
 pub use extractor::{AuthConfig, AuthError, AuthExtractor, generate_api_key, hash_api_key};
-pub use jwks::{DEFAULT_CACHE_TTL_SECONDS, JwksCache, JwksCacheError};
+pub use jwks::{
+    DEFAULT_CACHE_TTL_SECONDS, JwksCache, JwksCacheError, JwksCacheStats, default_cache_ttl_seconds,
+};
+pub use rate_limit::{AuthRateLimiter, PenalizedIp};
 pub use user_store::UserStore;
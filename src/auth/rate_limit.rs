@@ -0,0 +1,426 @@
+//! Per-IP limit on failed authentication attempts.
+//!
+//! Failures are counted in a sliding window per client IP. Once an IP reaches
+//! the limit, every request from it is rejected with `429 Too Many Requests`
+//! until its oldest failure leaves the window. A successful authentication
+//! clears the IP's failures. State is kept in memory; the least recently
+//! failing IPs are evicted once [`MAX_TRACKED_IPS`] are tracked.
+//!
+//! The client IP is the address of the TCP peer, which requires serving the
+//! router with `into_make_service_with_connect_info::<SocketAddr>()`.
+//! `X-Forwarded-For` and `X-Real-IP` are only believed when the peer is one
+//! of the configured trusted proxies.
+
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::{Extensions, HeaderMap, HeaderValue, StatusCode, header};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+
+use crate::auth::AuthConfig;
+
+/// Default [`AuthConfig::max_auth_failures_per_window`].
+pub const DEFAULT_MAX_AUTH_FAILURES_PER_WINDOW: u32 = 10;
+
+/// Default [`AuthConfig::window_secs`].
+pub const DEFAULT_AUTH_WINDOW_SECS: u64 = 300;
+
+/// Most IPs whose failures are remembered at once.
+pub const MAX_TRACKED_IPS: usize = 10_000;
+
+/// An IP currently rejected for too many failed authentications.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PenalizedIp {
+    pub ip_address: String,
+    /// Failures within the current window.
+    pub failures: u32,
+    /// Seconds until the IP may try again.
+    pub retry_after_secs: u64,
+}
+
+/// Failure times per IP, plus the IPs ordered by their latest failure so the
+/// stalest one can be evicted without scanning.
+#[derive(Default)]
+struct Failures {
+    by_ip: HashMap<String, VecDeque<Instant>>,
+    by_last_failure: BTreeSet<(Instant, String)>,
+}
+
+impl Failures {
+    /// Forget every failure of `ip`.
+    fn remove(&mut self, ip: &str) {
+        if let Some(last) = self
+            .by_ip
+            .remove(ip)
+            .and_then(|times| times.back().copied())
+        {
+            self.by_last_failure.remove(&(last, ip.to_string()));
+        }
+    }
+}
+
+/// Sliding-window counter of failed authentications per client IP.
+pub struct AuthRateLimiter {
+    max_failures: u32,
+    window: Duration,
+    trusted_proxies: Vec<IpAddr>,
+    failures: std::sync::Mutex<Failures>,
+}
+
+impl AuthRateLimiter {
+    /// A limiter allowing `max_failures` failures per `window`; `0` disables it.
+    pub fn new(max_failures: u32, window: Duration) -> Self {
+        Self {
+            max_failures,
+            window,
+            trusted_proxies: Vec::new(),
+            failures: std::sync::Mutex::new(Failures::default()),
+        }
+    }
+
+    /// A limiter with the thresholds and trusted proxies in `config`.
+    pub fn from_config(config: &AuthConfig) -> Self {
+        Self::new(
+            config.max_auth_failures_per_window,
+            Duration::from_secs(config.window_secs),
+        )
+        .with_trusted_proxies(config.trusted_proxies.clone())
+    }
+
+    /// Believe the forwarding headers of requests from these proxies.
+    pub fn with_trusted_proxies(mut self, trusted_proxies: Vec<IpAddr>) -> Self {
+        self.trusted_proxies = trusted_proxies;
+        self
+    }
+
+    /// The client IP of a request, from the `ConnectInfo` in its extensions
+    /// and, when that peer is a trusted proxy, its forwarding headers.
+    ///
+    /// `None` if the router was not served with connect info.
+    pub fn client_ip(&self, headers: &HeaderMap, extensions: &Extensions) -> Option<String> {
+        let peer = extensions.get::<ConnectInfo<SocketAddr>>()?.0.ip();
+        Some(client_ip(peer, headers, &self.trusted_proxies).to_string())
+    }
+
+    /// Whether `ip` may attempt to authenticate at `now`.
+    ///
+    /// Returns how long to wait if it may not.
+    pub fn check(&self, ip: &str, now: Instant) -> Result<(), Duration> {
+        if self.max_failures == 0 {
+            return Ok(());
+        }
+        let mut failures = self
+            .failures
+            .lock()
+            .expect("auth rate limiter lock poisoned");
+        let Some(times) = failures.by_ip.get_mut(ip) else {
+            return Ok(());
+        };
+        let last = times.back().copied();
+        self.expire(times, now);
+        match self.retry_after(times, now) {
+            Some(retry_after) => Err(retry_after),
+            None => {
+                if times.is_empty() {
+                    failures.by_ip.remove(ip);
+                    if let Some(last) = last {
+                        failures.by_last_failure.remove(&(last, ip.to_string()));
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Count a failed authentication from `ip` at `now`.
+    pub fn record_failure(&self, ip: &str, now: Instant) {
+        if self.max_failures == 0 {
+            return;
+        }
+        let mut failures = self
+            .failures
+            .lock()
+            .expect("auth rate limiter lock poisoned");
+        if !failures.by_ip.contains_key(ip)
+            && failures.by_ip.len() >= MAX_TRACKED_IPS
+            && let Some((_, stalest)) = failures.by_last_failure.pop_first()
+        {
+            failures.by_ip.remove(&stalest);
+        }
+
+        let Failures {
+            by_ip,
+            by_last_failure,
+        } = &mut *failures;
+        let times = by_ip.entry(ip.to_string()).or_default();
+        if let Some(last) = times.back() {
+            by_last_failure.remove(&(*last, ip.to_string()));
+        }
+        self.expire(times, now);
+        times.push_back(now);
+        by_last_failure.insert((now, ip.to_string()));
+        if times.len() == self.max_failures as usize {
+            tracing::warn!(
+                ip = %ip,
+                failures = times.len(),
+                "Too many failed authentications, rejecting requests from this IP"
+            );
+        }
+    }
+
+    /// Forget the failures of `ip` after it authenticated successfully.
+    pub fn record_success(&self, ip: &str) {
+        self.failures
+            .lock()
+            .expect("auth rate limiter lock poisoned")
+            .remove(ip);
+    }
+
+    /// IPs rejected at `now`, sorted by address.
+    pub fn penalized(&self, now: Instant) -> Vec<PenalizedIp> {
+        let mut failures = self
+            .failures
+            .lock()
+            .expect("auth rate limiter lock poisoned");
+        let Failures {
+            by_ip,
+            by_last_failure,
+        } = &mut *failures;
+        let mut penalized = Vec::new();
+        by_ip.retain(|ip, times| {
+            let last = times.back().copied();
+            self.expire(times, now);
+            if let Some(last) = last
+                && times.is_empty()
+            {
+                by_last_failure.remove(&(last, ip.clone()));
+                return false;
+            }
+            if let Some(retry_after) = self.retry_after(times, now) {
+                penalized.push(PenalizedIp {
+                    ip_address: ip.clone(),
+                    failures: u32::try_from(times.len()).unwrap_or(u32::MAX),
+                    retry_after_secs: retry_after_secs(retry_after),
+                });
+            }
+            true
+        });
+        penalized.sort_by(|a, b| a.ip_address.cmp(&b.ip_address));
+        penalized
+    }
+
+    /// `429 Too Many Requests` for a request whose client IP is rejected.
+    pub fn reject(&self, headers: &HeaderMap, extensions: &Extensions) -> Option<Response> {
+        let ip = self.client_ip(headers, extensions)?;
+        let retry_after = self.check(&ip, Instant::now()).err()?;
+        Some(too_many_requests(retry_after))
+    }
+
+    /// Drop failures that have left the window.
+    fn expire(&self, times: &mut VecDeque<Instant>, now: Instant) {
+        while times
+            .front()
+            .is_some_and(|t| now.saturating_duration_since(*t) >= self.window)
+        {
+            times.pop_front();
+        }
+    }
+
+    /// Time until the oldest failure leaves the window, if the limit is reached.
+    fn retry_after(&self, times: &VecDeque<Instant>, now: Instant) -> Option<Duration> {
+        if self.max_failures == 0 || times.len() < self.max_failures as usize {
+            return None;
+        }
+        let oldest = *times.front()?;
+        Some(
+            self.window
+                .saturating_sub(now.saturating_duration_since(oldest)),
+        )
+    }
+}
+
+/// `retry_after` in whole seconds for a `Retry-After` header, at least 1.
+pub fn retry_after_secs(retry_after: Duration) -> u64 {
+    retry_after.as_secs_f64().ceil().max(1.0) as u64
+}
+
+/// `429 Too Many Requests` with a `Retry-After` header.
+pub fn too_many_requests(retry_after: Duration) -> Response {
+    let mut response = (
+        StatusCode::TOO_MANY_REQUESTS,
+        "Too many failed authentication attempts",
+    )
+        .into_response();
+    response.headers_mut().insert(
+        header::RETRY_AFTER,
+        HeaderValue::from(retry_after_secs(retry_after)),
+    );
+    response
+}
+
+/// Middleware answering requests from rejected IPs with `429 Too Many
+/// Requests` before they reach the handler.
+pub async fn reject_rate_limited_ips(
+    State(limiter): State<Arc<AuthRateLimiter>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    match limiter.reject(request.headers(), request.extensions()) {
+        Some(response) => response,
+        None => next.run(request).await,
+    }
+}
+
+/// The client IP of a request from `peer`.
+///
+/// If `peer` is a trusted proxy, the client is the rightmost `X-Forwarded-For`
+/// entry that isn't itself a trusted proxy, or else `X-Real-IP`; entries
+/// that aren't IP addresses end the search. Otherwise the headers are ignored,
+/// as any client could set them.
+pub fn client_ip(peer: IpAddr, headers: &HeaderMap, trusted_proxies: &[IpAddr]) -> IpAddr {
+    if !trusted_proxies.contains(&peer) {
+        return peer;
+    }
+    let forwarded = headers
+        .get_all("X-Forwarded-For")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .collect::<Vec<_>>();
+    for entry in forwarded.iter().rev() {
+        match entry.trim().parse::<IpAddr>() {
+            Ok(ip) if trusted_proxies.contains(&ip) => continue,
+            Ok(ip) => return ip,
+            Err(_) => return peer,
+        }
+    }
+    headers
+        .get("X-Real-IP")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(peer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limit_reached_within_window() {
+        let limiter = AuthRateLimiter::new(3, Duration::from_secs(60));
+        let start = Instant::now();
+        for i in 0..3 {
+            assert!(limiter.check("10.0.0.1", start).is_ok());
+            limiter.record_failure("10.0.0.1", start + Duration::from_secs(i * 10));
+        }
+
+        let now = start + Duration::from_secs(25);
+        assert_eq!(limiter.check("10.0.0.1", now), Err(Duration::from_secs(35)));
+        assert!(limiter.check("10.0.0.2", now).is_ok());
+        assert_eq!(
+            limiter.penalized(now),
+            vec![PenalizedIp {
+                ip_address: "10.0.0.1".to_string(),
+                failures: 3,
+                retry_after_secs: 35,
+            }]
+        );
+
+        // The oldest failure slides out of the window
+        let later = start + Duration::from_secs(60);
+        assert!(limiter.check("10.0.0.1", later).is_ok());
+        assert!(limiter.penalized(later).is_empty());
+    }
+
+    #[test]
+    fn test_success_resets_failures() {
+        let limiter = AuthRateLimiter::new(2, Duration::from_secs(60));
+        let now = Instant::now();
+        limiter.record_failure("10.0.0.1", now);
+        limiter.record_success("10.0.0.1");
+        limiter.record_failure("10.0.0.1", now);
+        assert!(limiter.check("10.0.0.1", now).is_ok());
+    }
+
+    #[test]
+    fn test_zero_limit_disables_limiting() {
+        let limiter = AuthRateLimiter::new(0, Duration::from_secs(60));
+        let now = Instant::now();
+        limiter.record_failure("10.0.0.1", now);
+        assert!(limiter.check("10.0.0.1", now).is_ok());
+    }
+
+    #[test]
+    fn test_evicts_stalest_ip_when_full() {
+        let limiter = AuthRateLimiter::new(5, Duration::from_secs(3600));
+        let start = Instant::now();
+        for i in 0..MAX_TRACKED_IPS {
+            limiter.record_failure(
+                &format!("ip-{}", i),
+                start + Duration::from_millis(i as u64),
+            );
+        }
+        // Failing again makes ip-0 the most recent, so ip-1 is evicted
+        let now = start + Duration::from_secs(60);
+        limiter.record_failure("ip-0", now);
+        limiter.record_failure("10.0.0.1", now);
+
+        let failures = limiter.failures.lock().unwrap();
+        assert_eq!(failures.by_ip.len(), MAX_TRACKED_IPS);
+        assert_eq!(failures.by_last_failure.len(), MAX_TRACKED_IPS);
+        assert_eq!(failures.by_ip["ip-0"].len(), 2);
+        assert!(!failures.by_ip.contains_key("ip-1"));
+        assert!(failures.by_ip.contains_key("10.0.0.1"));
+    }
+
+    #[test]
+    fn test_client_ip_trusts_headers_only_from_proxies() {
+        let peer: IpAddr = "192.168.0.1".parse().unwrap();
+        let proxy: IpAddr = "10.0.0.254".parse().unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert("X-Real-IP", HeaderValue::from_static("10.0.0.2"));
+        headers.insert(
+            "X-Forwarded-For",
+            HeaderValue::from_static("10.0.0.1, 10.0.0.3, 10.0.0.254"),
+        );
+
+        // Spoofed headers from an untrusted peer are ignored
+        assert_eq!(client_ip(peer, &headers, &[]), peer);
+        assert_eq!(client_ip(peer, &headers, &[proxy]), peer);
+
+        // Behind a trusted proxy the rightmost untrusted hop is the client
+        assert_eq!(
+            client_ip(proxy, &headers, &[proxy]),
+            "10.0.0.3".parse::<IpAddr>().unwrap()
+        );
+
+        headers.remove("X-Forwarded-For");
+        assert_eq!(
+            client_ip(proxy, &headers, &[proxy]),
+            "10.0.0.2".parse::<IpAddr>().unwrap()
+        );
+        headers.remove("X-Real-IP");
+        assert_eq!(client_ip(proxy, &headers, &[proxy]), proxy);
+    }
+
+    #[test]
+    fn test_limiter_client_ip_needs_connect_info() {
+        let limiter = AuthRateLimiter::new(3, Duration::from_secs(60));
+        let mut headers = HeaderMap::new();
+        headers.insert("X-Real-IP", HeaderValue::from_static("10.0.0.2"));
+        let mut extensions = Extensions::new();
+        assert_eq!(limiter.client_ip(&headers, &extensions), None);
+
+        extensions.insert(ConnectInfo(SocketAddr::from(([192, 168, 0, 1], 4000))));
+        assert_eq!(
+            limiter.client_ip(&headers, &extensions).as_deref(),
+            Some("192.168.0.1")
+        );
+    }
+}
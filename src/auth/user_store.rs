@@ -3,11 +3,15 @@
 use anyhow::Result;
 use surrealdb::RecordId;
 
+use crate::auth::generate_api_key;
 use crate::db::Db;
+use crate::db::queries::QueryBuilder;
 use crate::db::schema::{
     AuditAction, AuditLogCreate, UserCreate, UserPreferencesRecord, UserPreferencesUpdate,
     UserRecord,
 };
+use crate::knowledge_graph::EmbeddingBackendConfig;
+use crate::types::{ApiKeyHash, ApiKeyPrefix};
 
 /// User store for database operations.
 pub struct UserStore {
@@ -182,6 +186,106 @@ impl UserStore {
         Ok(())
     }
 
+    /// Get the roles granted to a user in the `user_roles` table, sorted.
+    pub async fn get_roles(&self, user_id: &RecordId) -> Result<Vec<String>> {
+        let query = "SELECT VALUE role FROM user_roles WHERE user_id = $user_id ORDER BY role";
+
+        let mut res = self
+            .db
+            .query(query)
+            .bind(("user_id", user_id.clone()))
+            .await?;
+
+        let roles: Vec<String> = res.take(0)?;
+        Ok(roles)
+    }
+
+    /// Grant a role to a user. Granting a role the user already has is a no-op.
+    pub async fn grant_role(&self, user_id: &RecordId, role: &str) -> Result<()> {
+        let query = r#"
+            IF array::len((SELECT id FROM user_roles WHERE user_id = $user_id AND role = $role)) = 0 {
+                CREATE user_roles SET user_id = $user_id, role = $role;
+            };
+        "#;
+
+        self.db
+            .query(query)
+            .bind(("user_id", user_id.clone()))
+            .bind(("role", role.to_string()))
+            .await?
+            .check()?;
+
+        Ok(())
+    }
+
+    /// Revoke a role from a user.
+    ///
+    /// Returns `false` if the user didn't have the role.
+    pub async fn revoke_role(&self, user_id: &RecordId, role: &str) -> Result<bool> {
+        let query = "DELETE user_roles WHERE user_id = $user_id AND role = $role RETURN BEFORE";
+
+        let mut res = self
+            .db
+            .query(query)
+            .bind(("user_id", user_id.clone()))
+            .bind(("role", role.to_string()))
+            .await?;
+
+        let removed: Vec<RecordId> = res.take((0, "id"))?;
+        Ok(!removed.is_empty())
+    }
+
+    /// Replace the active API key with `old_key_hash` by a new key for the
+    /// same user, keeping its name, expiry and scopes. The old key is
+    /// deactivated in the same transaction, so it is rejected as revoked from
+    /// then on.
+    ///
+    /// Keys created without a user are bound to the user they authenticated
+    /// as, so the user record survives the prefix change. Returns the new key
+    /// as `(full_key, prefix, hash)`; the full key cannot be recovered later.
+    pub async fn rotate_api_key(
+        &self,
+        old_key_hash: &ApiKeyHash,
+    ) -> Result<(String, ApiKeyPrefix, ApiKeyHash)> {
+        let old = QueryBuilder::find_api_key_by_hash(&self.db, old_key_hash.as_str())
+            .await?
+            .filter(|key| key.is_active)
+            .ok_or_else(|| anyhow::anyhow!("No active API key with this hash"))?;
+        let user_id = match old.user_id {
+            Some(user_id) => user_id,
+            None => {
+                self.get_or_create_user(
+                    &format!("api_key:{}", old.key_prefix),
+                    "api_key",
+                    None,
+                    old.name.as_deref(),
+                )
+                .await?
+                .id
+            }
+        };
+
+        let (full_key, prefix, hash) = generate_api_key();
+        QueryBuilder::rotate_api_key(&self.db, old_key_hash, &user_id, &prefix, &hash)
+            .await?
+            .ok_or_else(|| {
+                anyhow::anyhow!("API key {} was revoked concurrently", old.key_prefix)
+            })?;
+
+        self.audit_log(AuditLogCreate {
+            user_id: Some(user_id.to_string()),
+            action: AuditAction::ApiKeyRotated.as_str().to_string(),
+            resource_type: "api_key".to_string(),
+            resource_id: Some(prefix.to_string()),
+            details: Some(serde_json::json!({ "previous_prefix": old.key_prefix })),
+            ip_address: None,
+            user_agent: None,
+        })
+        .await?;
+
+        Ok((full_key, prefix, hash))
+    }
+
     /// Create default preferences for a new user.
     async fn create_default_preferences(&self, user_id: &RecordId) -> Result<()> {
         let query = r#"
@@ -265,6 +369,11 @@ impl UserStore {
             binds.push(("notify_grant".to_string(), serde_json::json!(notify_grant)));
         }
 
+        if let Some(language) = &update.preferred_language {
+            updates.push("preferred_language = $language");
+            binds.push(("language".to_string(), serde_json::json!(language)));
+        }
+
         if updates.is_empty() {
             return Ok(());
         }
@@ -288,6 +397,32 @@ impl UserStore {
         Ok(())
     }
 
+    /// Set or clear the embedding model used for the user's searches.
+    pub async fn set_embedding_backend_override(
+        &self,
+        user_id: &RecordId,
+        backend: Option<EmbeddingBackendConfig>,
+    ) -> Result<()> {
+        if self.get_preferences(user_id).await?.is_none() {
+            self.create_default_preferences(user_id).await?;
+        }
+
+        let query = r#"
+            UPDATE user_preferences SET
+                embedding_backend_override = $backend,
+                updated_at = time::now()
+            WHERE user_id = $user_id
+        "#;
+
+        self.db
+            .query(query)
+            .bind(("user_id", user_id.clone()))
+            .bind(("backend", backend))
+            .await?;
+
+        Ok(())
+    }
+
     /// Check if a service is trusted by the user.
     pub async fn is_service_trusted(&self, user_id: &RecordId, service_id: &str) -> Result<bool> {
         if let Some(prefs) = self.get_preferences(user_id).await?
@@ -521,6 +656,36 @@ mod tests {
         assert_eq!(prefs.default_approval_mode, "prompt");
         assert_eq!(prefs.elicitation_timeout_seconds, 300);
         assert!(prefs.remember_decisions);
+        assert!(prefs.embedding_backend_override.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_set_embedding_backend_override() {
+        let db = setup_test_db().await;
+        let store = UserStore::new(db);
+
+        let user = store
+            .get_or_create_user("sub123", "jwt", None, None)
+            .await
+            .unwrap();
+
+        let backend = EmbeddingBackendConfig {
+            model_name: "jinaai/jina-embeddings-v2-small-en".to_string(),
+            model_architecture: "jina".to_string(),
+        };
+        store
+            .set_embedding_backend_override(&user.id, Some(backend.clone()))
+            .await
+            .unwrap();
+        let prefs = store.get_preferences(&user.id).await.unwrap().unwrap();
+        assert_eq!(prefs.embedding_backend_override, Some(backend));
+
+        store
+            .set_embedding_backend_override(&user.id, None)
+            .await
+            .unwrap();
+        let prefs = store.get_preferences(&user.id).await.unwrap().unwrap();
+        assert!(prefs.embedding_backend_override.is_none());
     }
 
     #[tokio::test]
@@ -1,10 +1,15 @@
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::Mutex;
 use tracing::{Level, info};
 use tracing_subscriber::EnvFilter;
-use unicity_orchestrator::{AuthConfig, DatabaseConfig, Orchestrator, create_server};
+use unicity_orchestrator::{
+    AuthConfig, DatabaseConfig, Orchestrator, ToolQueryOptions, create_server,
+};
 
 // rmcp imports for MCP stdio server mode
 use rmcp::service::ServiceExt;
@@ -29,6 +34,15 @@ enum Commands {
         admin_bind: String,
         #[arg(long, default_value = "memory")]
         db_url: String,
+        /// On startup, delete unused tools not seen by discovery in this many days
+        #[arg(long)]
+        auto_prune_days: Option<u32>,
+        /// URL that alerts, such as newly advertised breaking tool changes, are POSTed to
+        #[arg(long, env = "ORCHESTRATOR_NOTIFICATION_WEBHOOK")]
+        notification_webhook: Option<String>,
+        /// Comma-separated proxy IPs whose X-Forwarded-For / X-Real-IP headers are believed
+        #[arg(long, env = "ORCHESTRATOR_TRUSTED_PROXIES", value_delimiter = ',')]
+        trusted_proxies: Vec<IpAddr>,
     },
     /// Discover tools from configured MCP services
     DiscoverTools,
@@ -37,6 +51,12 @@ enum Commands {
         query: String,
         #[arg(short, long)]
         context: Option<String>,
+        /// BCP-47 tag of the query's language, e.g. `de`
+        #[arg(short, long)]
+        language: Option<String>,
+        /// Country (ISO 3166-1 alpha-2) to check region-restricted tools against
+        #[arg(long)]
+        geo_override: Option<String>,
     },
     /// Run as an MCP stdio server (for use in mcp.json)
     McpStdio {
@@ -68,6 +88,9 @@ enum Commands {
         /// Enable database-backed API key lookup
         #[arg(long, default_value_t = false)]
         enable_db_api_keys: bool,
+        /// Comma-separated proxy IPs whose X-Forwarded-For / X-Real-IP headers are believed
+        #[arg(long, env = "ORCHESTRATOR_TRUSTED_PROXIES", value_delimiter = ',')]
+        trusted_proxies: Vec<IpAddr>,
     },
     /// Initialize the database
     Init {
@@ -103,6 +126,98 @@ enum Commands {
         #[arg(long, default_value = "memory")]
         db_url: String,
     },
+    /// Delete cached registry manifests
+    ClearCache {
+        /// Registry whose cached manifests should be removed
+        registry_id: String,
+        /// Cache directory (defaults to ~/.cache/unicity-orchestrator/manifests)
+        #[arg(long)]
+        cache_dir: Option<PathBuf>,
+    },
+    /// Write a catalog of the prompts offered by configured MCP services
+    GenerateCatalog {
+        /// File to write the catalog to
+        output: PathBuf,
+        #[arg(long, value_enum, default_value_t = CatalogFormat::Markdown)]
+        format: CatalogFormat,
+    },
+    /// Run a registry's `transform_manifest` expression against a sample response
+    ValidateRegistryTransform {
+        /// Registry config (JSON) holding the `transform_manifest` expression
+        config: PathBuf,
+        /// Raw `GET /manifests` response saved from the registry
+        sample_response: PathBuf,
+    },
+}
+
+/// Output format of `generate-catalog`.
+#[derive(Clone, Copy, ValueEnum)]
+enum CatalogFormat {
+    Json,
+    Markdown,
+}
+
+/// How long a graceful shutdown waits for running tool executions.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Resolves on SIGTERM or Ctrl-C.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("Failed to listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+    info!("Shutdown signal received");
+}
+
+/// Re-read `mcp.json` with `reload` every time the process receives SIGHUP.
+#[cfg(unix)]
+fn spawn_reload_on_sighup<F, Fut>(reload: F)
+where
+    F: Fn() -> Fut + Send + 'static,
+    Fut: std::future::Future<Output = Result<unicity_orchestrator::ConfigReloadResult>> + Send,
+{
+    tokio::spawn(async move {
+        let mut hangup = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())
+        {
+            Ok(signal) => signal,
+            Err(e) => {
+                tracing::error!("Failed to listen for SIGHUP: {}", e);
+                return;
+            }
+        };
+        while hangup.recv().await.is_some() {
+            info!("SIGHUP received, reloading mcp.json");
+            match reload().await {
+                Ok(result) => info!(
+                    "Reloaded mcp.json: added {:?}, updated {:?}, removed {:?}",
+                    result.added, result.updated, result.removed
+                ),
+                Err(e) => tracing::error!("Failed to reload mcp.json: {}", e),
+            }
+        }
+    });
 }
 
 #[tokio::main]
@@ -123,6 +238,9 @@ async fn main() -> Result<()> {
             port,
             admin_bind,
             db_url,
+            auto_prune_days,
+            notification_webhook,
+            trusted_proxies,
         } => {
             info!("Starting orchestrator server on port {}", port);
             info!("Starting admin API on {}", admin_bind);
@@ -134,13 +252,42 @@ async fn main() -> Result<()> {
             info!("Using database url for REST server: {}", db_config.url);
 
             let mut orchestrator = Orchestrator::new(db_config).await?;
+            orchestrator.set_alert_webhook(notification_webhook);
+            orchestrator.set_auth_config(&AuthConfig {
+                trusted_proxies,
+                ..Default::default()
+            });
             orchestrator.warmup().await?;
 
+            if let Some(days) = auto_prune_days {
+                let pruned = orchestrator.prune_stale_records(days).await?;
+                info!(
+                    "Auto-pruned {} tools, {} services and {} embeddings unseen for {} days",
+                    pruned.tools_removed, pruned.services_removed, pruned.embeddings_removed, days
+                );
+            }
+
+            let security = orchestrator.security_headers()?;
+
+            // Taken before locking so shutdown doesn't wait on the orchestrator mutex
+            let shutdown = orchestrator.shutdown_handle();
+
             // Shared orchestrator state for both public and admin routers.
             let shared = Arc::new(Mutex::new(orchestrator));
 
-            let public_app = unicity_orchestrator::api::create_public_router(shared.clone());
-            let admin_app = unicity_orchestrator::api::create_admin_router(shared.clone());
+            #[cfg(unix)]
+            spawn_reload_on_sighup({
+                let shared = shared.clone();
+                move || {
+                    let shared = shared.clone();
+                    async move { shared.lock().await.reload_config().await }
+                }
+            });
+
+            let public_app =
+                unicity_orchestrator::api::create_public_router(shared.clone(), security.clone());
+            let admin_app =
+                unicity_orchestrator::api::create_admin_router(shared.clone(), security);
 
             let public_listener =
                 tokio::net::TcpListener::bind(format!("0.0.0.0:{}", port)).await?;
@@ -149,10 +296,41 @@ async fn main() -> Result<()> {
             info!("Public server listening on http://0.0.0.0:{}", port);
             info!("Admin server listening on http://{}", admin_bind);
 
-            tokio::try_join!(
-                axum::serve(public_listener, public_app),
-                axum::serve(admin_listener, admin_app),
-            )?;
+            // On SIGTERM/Ctrl-C stop accepting connections and drain running
+            // tool executions; exit once drained even if clients stay connected
+            let drain = {
+                let shutdown = shutdown.clone();
+                async move {
+                    shutdown_signal().await;
+                    shutdown.shutdown(SHUTDOWN_TIMEOUT).await;
+                }
+            };
+            let serve = async {
+                tokio::try_join!(
+                    axum::serve(
+                        public_listener,
+                        public_app.into_make_service_with_connect_info::<SocketAddr>(),
+                    )
+                    .with_graceful_shutdown({
+                        let shutdown = shutdown.clone();
+                        async move { shutdown.stopped().await }
+                    }),
+                    axum::serve(
+                        admin_listener,
+                        admin_app.into_make_service_with_connect_info::<SocketAddr>(),
+                    )
+                    .with_graceful_shutdown({
+                        let shutdown = shutdown.clone();
+                        async move { shutdown.stopped().await }
+                    }),
+                )
+            };
+            tokio::select! {
+                result = serve => {
+                    result?;
+                }
+                _ = drain => {}
+            }
         }
         Commands::DiscoverTools => {
             info!("Discovering tools using default database configuration");
@@ -160,7 +338,12 @@ async fn main() -> Result<()> {
             let count = orchestrator.discover_tools().await?;
             println!("Discovered {} services and {} tools", count.0, count.1);
         }
-        Commands::Query { query, context } => {
+        Commands::Query {
+            query,
+            context,
+            language,
+            geo_override,
+        } => {
             info!(
                 "Running query command. query='{}', context_present={}",
                 query,
@@ -173,7 +356,16 @@ async fn main() -> Result<()> {
 
             // CLI query runs without user context (anonymous mode)
             let selections = orchestrator
-                .query_tools(&query, context_value, None)
+                .query_tools(
+                    &query,
+                    context_value,
+                    None,
+                    &ToolQueryOptions {
+                        language,
+                        geo_override,
+                        ..Default::default()
+                    },
+                )
                 .await?;
 
             println!("Query: {}", query);
@@ -222,6 +414,7 @@ async fn main() -> Result<()> {
             jwt_issuer,
             jwt_audience,
             enable_db_api_keys,
+            trusted_proxies,
         } => {
             info!(
                 "Starting MCP HTTP server (rmcp) on {} with db_url={}",
@@ -236,6 +429,15 @@ async fn main() -> Result<()> {
 
             let server = create_server(db_config).await?;
 
+            #[cfg(unix)]
+            spawn_reload_on_sighup({
+                let orchestrator = server.orchestrator().clone();
+                move || {
+                    let orchestrator = orchestrator.clone();
+                    async move { orchestrator.reload_config().await }
+                }
+            });
+
             // Build auth config based on CLI args
             let auth_config = build_auth_config(
                 allow_anonymous,
@@ -244,9 +446,20 @@ async fn main() -> Result<()> {
                 jwt_issuer,
                 jwt_audience,
                 enable_db_api_keys,
-            );
+            )
+            .map(|config| AuthConfig {
+                trusted_proxies,
+                ..config
+            });
 
-            unicity_orchestrator::server::start_mcp_http(server, &bind, auth_config).await?;
+            tokio::select! {
+                result = unicity_orchestrator::server::start_mcp_http(server.clone(), &bind, auth_config) => {
+                    result?;
+                }
+                _ = shutdown_signal() => {
+                    server.graceful_shutdown(SHUTDOWN_TIMEOUT).await;
+                }
+            }
         }
         Commands::Init { db_url } => {
             let db_config = DatabaseConfig {
@@ -384,6 +597,61 @@ async fn main() -> Result<()> {
                 println!("No API key found with prefix '{}'.", key_prefix);
             }
         }
+        Commands::ClearCache {
+            registry_id,
+            cache_dir,
+        } => {
+            let cache_dir =
+                cache_dir.unwrap_or_else(unicity_orchestrator::default_manifest_cache_dir);
+            let removed = unicity_orchestrator::clear_manifest_cache(&cache_dir, &registry_id)?;
+            println!(
+                "Removed {} cached manifest(s) for registry '{}'.",
+                removed, registry_id
+            );
+        }
+        Commands::GenerateCatalog { output, format } => {
+            info!("Generating prompt catalog using default database configuration");
+            let mut orchestrator = Orchestrator::new(DatabaseConfig::default()).await?;
+            orchestrator.warmup().await?;
+            let catalog = orchestrator.prompt_forwarder().export_catalog().await;
+
+            let contents = match format {
+                CatalogFormat::Json => serde_json::to_string_pretty(&catalog)?,
+                CatalogFormat::Markdown => catalog.to_markdown(),
+            };
+            std::fs::write(&output, contents)?;
+            println!(
+                "Wrote catalog of {} services to {}",
+                catalog.services.len(),
+                output.display()
+            );
+        }
+        Commands::ValidateRegistryTransform {
+            config,
+            sample_response,
+        } => {
+            let registry: serde_json::Value =
+                serde_json::from_str(&std::fs::read_to_string(&config)?)?;
+            let expression = registry
+                .get("transform_manifest")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| {
+                    anyhow::anyhow!("{} has no `transform_manifest` string", config.display())
+                })?;
+            let response: serde_json::Value =
+                serde_json::from_str(&std::fs::read_to_string(&sample_response)?)?;
+
+            let manifests = unicity_orchestrator::apply_manifest_transform(expression, &response)?;
+            println!("{}", serde_json::to_string_pretty(&manifests)?);
+
+            let errors = unicity_orchestrator::manifest_shape_errors(&manifests);
+            if !errors.is_empty() {
+                for error in &errors {
+                    eprintln!("{}", error);
+                }
+                anyhow::bail!("the result does not match the manifest shape");
+            }
+        }
     }
 
     Ok(())
@@ -1,6 +1,12 @@
+use crate::resources::ConflictResolution;
 use crate::types::ServiceConfigId;
-use serde::Deserialize;
-use std::{collections::BTreeMap, env, fs, path::PathBuf};
+use serde::{Deserialize, Deserializer};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    env, fmt, fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 #[derive(Debug, Deserialize)]
 pub struct McpJsonConfig {
@@ -31,9 +37,206 @@ pub struct McpServerConfig {
     pub auto_approve: Vec<String>,
     #[serde(default, rename = "disabled_tools")]
     pub disabled_tools: Vec<String>,
+    #[serde(default)]
+    pub conflict_resolution: ConflictResolution,
+    /// How long elicitations forwarded from this server wait for the user.
+    #[serde(default = "default_elicitation_timeout_secs")]
+    pub elicitation_timeout_secs: i64,
+    /// Prefix the system prompt of sampling requests from this server with
+    /// the service and tool that sent them.
+    #[serde(default = "default_sampling_provenance")]
+    pub sampling_provenance: bool,
+    /// The prefix to use instead of [`DEFAULT_SAMPLING_PROVENANCE_PREFIX`];
+    /// `{origin}` is replaced by the service id and, if known, `/tool`.
+    #[serde(default)]
+    pub sampling_provenance_prefix: Option<String>,
+    /// How long the server gets to finish the MCP `initialize` handshake.
+    #[serde(default = "default_startup_timeout_secs")]
+    pub startup_timeout_secs: u64,
+    /// Which of the server's tools may run; services in `mcp.json` are trusted
+    /// unless this says otherwise.
+    #[serde(default)]
+    pub trust_level: TrustLevel,
+    /// Absolute paths `command` must be one of; any command may run if unset.
+    #[serde(default)]
+    pub command_allowlist: Option<Vec<String>>,
+    /// Security headers the orchestrator's HTTP servers send.
+    #[serde(default)]
+    pub security: SecurityConfig,
+}
+
+/// The `security` section of a server in `mcp.json`.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize)]
+pub struct SecurityConfig {
+    /// `Content-Security-Policy` sent with every REST and MCP HTTP response;
+    /// `default-src 'none'` if no enabled server sets one.
+    #[serde(default)]
+    pub csp_policy: Option<String>,
+}
+
+/// Environment variable listing, comma-separated, the absolute paths every
+/// stdio service `command` must be one of. Unset means no global restriction.
+pub const ALLOWED_MCP_COMMANDS: &str = "ALLOWED_MCP_COMMANDS";
+
+/// The commands allowed by [`ALLOWED_MCP_COMMANDS`], if it is set.
+pub fn allowed_mcp_commands() -> Option<Vec<String>> {
+    let value = env::var(ALLOWED_MCP_COMMANDS).ok()?;
+    Some(
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|command| !command.is_empty())
+            .map(str::to_string)
+            .collect(),
+    )
+}
+
+/// Whether `command` is an absolute path listed in `allowlist`.
+fn is_allowed_command(command: &str, allowlist: &[String]) -> bool {
+    Path::new(command).is_absolute() && allowlist.iter().any(|allowed| allowed == command)
+}
+
+/// Default elicitation timeout, in seconds.
+pub const DEFAULT_ELICITATION_TIMEOUT_SECS: i64 = 300;
+
+fn default_elicitation_timeout_secs() -> i64 {
+    DEFAULT_ELICITATION_TIMEOUT_SECS
+}
+
+fn default_sampling_provenance() -> bool {
+    true
+}
+
+/// Prefix sampling requests get unless a service sets its own.
+pub const DEFAULT_SAMPLING_PROVENANCE_PREFIX: &str = "[Requested by {origin}]";
+
+/// Default startup timeout, in seconds.
+pub const DEFAULT_STARTUP_TIMEOUT_SECS: u64 = 30;
+
+fn default_startup_timeout_secs() -> u64 {
+    DEFAULT_STARTUP_TIMEOUT_SECS
 }
 
-#[derive(Debug, Clone)]
+/// What the tools of a `Community` service may do beyond returning output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AllowedCapability {
+    /// Tools not marked `output_only` in their `_meta`, which may change state.
+    SideEffects,
+    /// Tools declaring the `filesystem` capability.
+    Filesystem,
+    /// Tools declaring the `network` capability.
+    Network,
+    /// Tools declaring the `process` capability, i.e. running commands.
+    Process,
+}
+
+impl AllowedCapability {
+    /// The capability a tool declares with `name` in its `_meta.capabilities`.
+    fn from_declared(name: &str) -> Option<Self> {
+        match name {
+            "filesystem" => Some(AllowedCapability::Filesystem),
+            "network" => Some(AllowedCapability::Network),
+            "process" => Some(AllowedCapability::Process),
+            _ => None,
+        }
+    }
+}
+
+/// How far a service's tools are trusted.
+///
+/// In `mcp.json` this is `"trusted"`, `"untrusted"` or
+/// `{ "community": ["network", ...] }`.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrustLevel {
+    /// Any tool may run.
+    #[default]
+    Trusted,
+    /// Only `output_only` tools without declared capabilities may run, plus
+    /// whatever the listed capabilities allow.
+    Community(Vec<AllowedCapability>),
+    /// No tool may run.
+    Untrusted,
+}
+
+impl TrustLevel {
+    /// The trust level of services synced from a registry.
+    pub fn community_default() -> Self {
+        TrustLevel::Community(Vec::new())
+    }
+
+    /// Check whether a tool marked `output_only` and declaring `capabilities`
+    /// may run, returning the reason if not.
+    pub fn check_tool(&self, output_only: bool, capabilities: &[String]) -> Result<(), String> {
+        let allowed = match self {
+            TrustLevel::Trusted => return Ok(()),
+            TrustLevel::Community(allowed) => allowed,
+            TrustLevel::Untrusted => return Err("the service is untrusted".to_string()),
+        };
+        if !output_only && !allowed.contains(&AllowedCapability::SideEffects) {
+            return Err(
+                "the tool is not marked `output_only` and side effects are not allowed".to_string(),
+            );
+        }
+        match capabilities.iter().find(|name| {
+            AllowedCapability::from_declared(name).is_none_or(|c| !allowed.contains(&c))
+        }) {
+            Some(name) => Err(format!("capability `{}` is not allowed", name)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A problem found while validating a service config.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigValidationError {
+    /// A stdio service has an empty `command`.
+    EmptyCommand,
+    /// `elicitation_timeout_secs` is zero or negative.
+    InvalidTimeout(i64),
+    /// An HTTP service `url` that isn't an absolute http(s) URL.
+    InvalidUrl(String),
+    /// A `${NAME}` reference whose variable isn't set.
+    MissingRequiredEnvVar(String),
+    /// The same server name appears more than once in `mcpServers`.
+    DuplicateServiceName(String),
+    /// A stdio `command` missing from the service's `command_allowlist` or
+    /// from [`ALLOWED_MCP_COMMANDS`].
+    DisallowedCommand(String),
+    /// A `security.csp_policy` that can't be sent as a header value.
+    InvalidCspPolicy(String),
+}
+
+impl fmt::Display for ConfigValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigValidationError::EmptyCommand => write!(f, "`command` must not be empty"),
+            ConfigValidationError::InvalidTimeout(secs) => {
+                write!(f, "`elicitation_timeout_secs` must be positive, got {secs}")
+            }
+            ConfigValidationError::InvalidUrl(url) => {
+                write!(f, "`url` must be an absolute http(s) URL, got `{url}`")
+            }
+            ConfigValidationError::MissingRequiredEnvVar(name) => {
+                write!(f, "environment variable `{name}` is not set")
+            }
+            ConfigValidationError::DuplicateServiceName(name) => {
+                write!(f, "server name `{name}` is defined more than once")
+            }
+            ConfigValidationError::DisallowedCommand(cmd) => {
+                write!(f, "command `{cmd}` is not on the allowlist")
+            }
+            ConfigValidationError::InvalidCspPolicy(policy) => {
+                write!(f, "`csp_policy` is not a valid header value: `{policy}`")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigValidationError {}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum McpServiceConfig {
     Stdio {
         id: ServiceConfigId,
@@ -43,6 +246,14 @@ pub enum McpServiceConfig {
         disabled: bool,
         auto_approve: Vec<String>,
         disabled_tools: Vec<String>,
+        conflict_resolution: ConflictResolution,
+        elicitation_timeout_secs: i64,
+        sampling_provenance: bool,
+        sampling_provenance_prefix: Option<String>,
+        startup_timeout_secs: u64,
+        trust_level: TrustLevel,
+        command_allowlist: Option<Vec<String>>,
+        security: SecurityConfig,
     },
     Http {
         id: ServiceConfigId,
@@ -51,6 +262,13 @@ pub enum McpServiceConfig {
         disabled: bool,
         auto_approve: Vec<String>,
         disabled_tools: Vec<String>,
+        conflict_resolution: ConflictResolution,
+        elicitation_timeout_secs: i64,
+        sampling_provenance: bool,
+        sampling_provenance_prefix: Option<String>,
+        startup_timeout_secs: u64,
+        trust_level: TrustLevel,
+        security: SecurityConfig,
     },
 }
 
@@ -66,6 +284,14 @@ impl McpServiceConfig {
                 disabled: cfg.disabled,
                 auto_approve: cfg.auto_approve,
                 disabled_tools: cfg.disabled_tools,
+                conflict_resolution: cfg.conflict_resolution,
+                elicitation_timeout_secs: cfg.elicitation_timeout_secs,
+                sampling_provenance: cfg.sampling_provenance,
+                sampling_provenance_prefix: cfg.sampling_provenance_prefix,
+                startup_timeout_secs: cfg.startup_timeout_secs,
+                trust_level: cfg.trust_level,
+                command_allowlist: cfg.command_allowlist,
+                security: cfg.security,
             });
         }
 
@@ -77,6 +303,13 @@ impl McpServiceConfig {
                 disabled: cfg.disabled,
                 auto_approve: cfg.auto_approve,
                 disabled_tools: cfg.disabled_tools,
+                conflict_resolution: cfg.conflict_resolution,
+                elicitation_timeout_secs: cfg.elicitation_timeout_secs,
+                sampling_provenance: cfg.sampling_provenance,
+                sampling_provenance_prefix: cfg.sampling_provenance_prefix,
+                startup_timeout_secs: cfg.startup_timeout_secs,
+                trust_level: cfg.trust_level,
+                security: cfg.security,
             });
         }
 
@@ -85,6 +318,246 @@ impl McpServiceConfig {
             id
         ))
     }
+
+    /// The server name this config was loaded under.
+    pub fn id(&self) -> &ServiceConfigId {
+        match self {
+            McpServiceConfig::Stdio { id, .. } | McpServiceConfig::Http { id, .. } => id,
+        }
+    }
+
+    /// Replace `${NAME}` and `${NAME:-default}` references in `command`,
+    /// `args`, `env`, `url` and `headers` with values from the environment.
+    ///
+    /// A reference to an unset variable without a default is left in place;
+    /// the names of those variables are returned as the error, each once.
+    pub fn expand_env_vars(&mut self) -> Result<(), Vec<String>> {
+        let values: Vec<&mut String> = match self {
+            McpServiceConfig::Stdio {
+                command, args, env, ..
+            } => std::iter::once(command)
+                .chain(args.iter_mut())
+                .chain(env.values_mut())
+                .collect(),
+            McpServiceConfig::Http { url, headers, .. } => {
+                std::iter::once(url).chain(headers.values_mut()).collect()
+            }
+        };
+
+        let mut missing = BTreeSet::new();
+        for value in values {
+            *value = expand_env_vars(value);
+            missing.extend(unresolved_env_vars(value));
+        }
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(missing.into_iter().collect())
+        }
+    }
+
+    /// Check the config for problems that would otherwise surface only once
+    /// the service is started. Returns every problem found, not just the first.
+    ///
+    /// Stdio commands are checked against [`ALLOWED_MCP_COMMANDS`] if it is set.
+    pub fn validate(&self) -> Vec<ConfigValidationError> {
+        self.validate_with_allowed_commands(allowed_mcp_commands().as_deref())
+    }
+
+    /// [`Self::validate`] with `allowed_commands` as the global command allowlist.
+    pub fn validate_with_allowed_commands(
+        &self,
+        allowed_commands: Option<&[String]>,
+    ) -> Vec<ConfigValidationError> {
+        let mut errors = Vec::new();
+        let mut unresolved = BTreeSet::new();
+
+        match self {
+            McpServiceConfig::Stdio {
+                command, args, env, ..
+            } => {
+                if command.trim().is_empty() {
+                    errors.push(ConfigValidationError::EmptyCommand);
+                } else if let Err(e) = self.check_command(allowed_commands) {
+                    errors.push(e);
+                }
+                for value in std::iter::once(command).chain(args).chain(env.values()) {
+                    unresolved.extend(unresolved_env_vars(value));
+                }
+            }
+            McpServiceConfig::Http { url, headers, .. } => {
+                let url_vars = unresolved_env_vars(url);
+                // A URL with an unset variable in it is reported as the variable.
+                if url_vars.is_empty() && !is_http_url(url) {
+                    errors.push(ConfigValidationError::InvalidUrl(url.clone()));
+                }
+                unresolved.extend(url_vars);
+                for value in headers.values() {
+                    unresolved.extend(unresolved_env_vars(value));
+                }
+            }
+        }
+
+        let timeout = self.elicitation_timeout_secs();
+        if timeout <= 0 {
+            errors.push(ConfigValidationError::InvalidTimeout(timeout));
+        }
+
+        if let Some(policy) = &self.security().csp_policy
+            && http::HeaderValue::from_str(policy).is_err()
+        {
+            errors.push(ConfigValidationError::InvalidCspPolicy(policy.clone()));
+        }
+
+        errors.extend(
+            unresolved
+                .into_iter()
+                .map(ConfigValidationError::MissingRequiredEnvVar),
+        );
+        errors
+    }
+
+    /// Check a stdio `command` against the service's `command_allowlist` and
+    /// `allowed_commands`, the global allowlist; unset lists allow any command.
+    pub fn check_command(
+        &self,
+        allowed_commands: Option<&[String]>,
+    ) -> Result<(), ConfigValidationError> {
+        let McpServiceConfig::Stdio {
+            command,
+            command_allowlist,
+            ..
+        } = self
+        else {
+            return Ok(());
+        };
+        let allowed = [command_allowlist.as_deref(), allowed_commands]
+            .into_iter()
+            .flatten()
+            .all(|allowlist| is_allowed_command(command, allowlist));
+        if allowed {
+            Ok(())
+        } else {
+            Err(ConfigValidationError::DisallowedCommand(command.clone()))
+        }
+    }
+
+    fn elicitation_timeout_secs(&self) -> i64 {
+        match self {
+            McpServiceConfig::Stdio {
+                elicitation_timeout_secs,
+                ..
+            }
+            | McpServiceConfig::Http {
+                elicitation_timeout_secs,
+                ..
+            } => *elicitation_timeout_secs,
+        }
+    }
+
+    /// Whether the service is turned off with `disabled` in `mcp.json`.
+    pub fn is_disabled(&self) -> bool {
+        match self {
+            McpServiceConfig::Stdio { disabled, .. } | McpServiceConfig::Http { disabled, .. } => {
+                *disabled
+            }
+        }
+    }
+
+    /// The first of `names` set neither in the service's `env` nor in the
+    /// orchestrator's environment, which the service process inherits.
+    pub fn missing_env_var<'a>(&self, names: &'a [String]) -> Option<&'a str> {
+        let configured = match self {
+            McpServiceConfig::Stdio { env, .. } => Some(env),
+            McpServiceConfig::Http { .. } => None,
+        };
+        names
+            .iter()
+            .find(|name| {
+                !configured.is_some_and(|env| env.contains_key(name.as_str()))
+                    && std::env::var(name).is_err()
+            })
+            .map(String::as_str)
+    }
+
+    /// How resource URI conflicts caused by this service are resolved.
+    pub fn conflict_resolution(&self) -> ConflictResolution {
+        match self {
+            McpServiceConfig::Stdio {
+                conflict_resolution,
+                ..
+            }
+            | McpServiceConfig::Http {
+                conflict_resolution,
+                ..
+            } => *conflict_resolution,
+        }
+    }
+
+    /// The provenance prefix for sampling requests from this service, or
+    /// `None` if they are relayed unchanged.
+    pub fn sampling_provenance_prefix(&self) -> Option<&str> {
+        match self {
+            McpServiceConfig::Stdio {
+                sampling_provenance,
+                sampling_provenance_prefix,
+                ..
+            }
+            | McpServiceConfig::Http {
+                sampling_provenance,
+                sampling_provenance_prefix,
+                ..
+            } => sampling_provenance.then(|| {
+                sampling_provenance_prefix
+                    .as_deref()
+                    .unwrap_or(DEFAULT_SAMPLING_PROVENANCE_PREFIX)
+            }),
+        }
+    }
+
+    /// How long elicitations forwarded from this service wait for the user.
+    pub fn elicitation_timeout(&self) -> Duration {
+        match u64::try_from(self.elicitation_timeout_secs()) {
+            Ok(secs) if secs > 0 => Duration::from_secs(secs),
+            _ => Duration::from_secs(DEFAULT_ELICITATION_TIMEOUT_SECS as u64),
+        }
+    }
+
+    /// Which of the service's tools may run.
+    pub fn trust_level(&self) -> &TrustLevel {
+        match self {
+            McpServiceConfig::Stdio { trust_level, .. }
+            | McpServiceConfig::Http { trust_level, .. } => trust_level,
+        }
+    }
+
+    /// The service's `security` section.
+    pub fn security(&self) -> &SecurityConfig {
+        match self {
+            McpServiceConfig::Stdio { security, .. } | McpServiceConfig::Http { security, .. } => {
+                security
+            }
+        }
+    }
+
+    /// How long the service gets to finish the MCP `initialize` handshake.
+    pub fn startup_timeout(&self) -> Duration {
+        let secs = match self {
+            McpServiceConfig::Stdio {
+                startup_timeout_secs,
+                ..
+            }
+            | McpServiceConfig::Http {
+                startup_timeout_secs,
+                ..
+            } => *startup_timeout_secs,
+        };
+        if secs > 0 {
+            Duration::from_secs(secs)
+        } else {
+            Duration::from_secs(DEFAULT_STARTUP_TIMEOUT_SECS)
+        }
+    }
 }
 
 pub fn resolve_mcp_json_path() -> anyhow::Result<PathBuf> {
@@ -113,6 +586,106 @@ pub fn resolve_mcp_json_path() -> anyhow::Result<PathBuf> {
     Ok(default_path)
 }
 
+/// Where registry manifests are cached when a registry sets no `cache_dir`:
+/// `$XDG_CACHE_HOME/unicity-orchestrator/manifests`, falling back to `~/.cache`.
+pub fn default_manifest_cache_dir() -> PathBuf {
+    let base = env::var("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|_| env::var("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .unwrap_or_else(|_| env::temp_dir());
+    base.join("unicity-orchestrator").join("manifests")
+}
+
+/// Directory holding the cached manifests of one registry.
+pub fn registry_cache_dir(cache_dir: &Path, registry_id: &str) -> std::io::Result<PathBuf> {
+    let is_plain_name = !registry_id.is_empty()
+        && registry_id != "."
+        && registry_id != ".."
+        && !registry_id.contains(['/', '\\']);
+    if !is_plain_name {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("invalid registry id `{registry_id}`"),
+        ));
+    }
+    Ok(cache_dir.join(registry_id))
+}
+
+/// Delete every cached manifest of `registry_id`, returning how many were removed.
+pub fn clear_manifest_cache(cache_dir: &Path, registry_id: &str) -> std::io::Result<usize> {
+    let dir = registry_cache_dir(cache_dir, registry_id)?;
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e),
+    };
+
+    let mut removed = 0;
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "json") {
+            fs::remove_file(&path)?;
+            removed += 1;
+        }
+    }
+    // Only succeeds once the directory is empty, which is all we want
+    let _ = fs::remove_dir(&dir);
+    Ok(removed)
+}
+
+/// Fields a registry manifest must have to deserialize as a `RegistryManifest`.
+const REGISTRY_MANIFEST_REQUIRED_FIELDS: &[&str] = &[
+    "name",
+    "version",
+    "mcp_version",
+    "schema_version",
+    "manifest_url",
+    "download_url",
+    "tags",
+    "dependencies",
+];
+
+/// Apply a registry's `transform_manifest` JMESPath expression to a raw
+/// `GET /manifests` response, producing the canonical manifest list.
+pub fn apply_manifest_transform(
+    expression: &str,
+    response: &serde_json::Value,
+) -> anyhow::Result<serde_json::Value> {
+    let expression = jmespath::compile(expression)
+        .map_err(|e| anyhow::anyhow!("invalid transform_manifest expression: {e}"))?;
+    let data = jmespath::Variable::from_serializable(response)
+        .map_err(|e| anyhow::anyhow!("registry response is not valid JSON: {e}"))?;
+    let result = expression
+        .search(data)
+        .map_err(|e| anyhow::anyhow!("transform_manifest failed: {e}"))?;
+    Ok(serde_json::to_value(&*result)?)
+}
+
+/// Problems that keep a transformed response from deserializing as a list of
+/// manifests, e.g. `manifest 2: missing \`download_url\``.
+pub fn manifest_shape_errors(manifests: &serde_json::Value) -> Vec<String> {
+    let Some(manifests) = manifests.as_array() else {
+        return vec!["expected an array of manifests".to_string()];
+    };
+
+    let mut errors = Vec::new();
+    for (index, manifest) in manifests.iter().enumerate() {
+        let Some(manifest) = manifest.as_object() else {
+            errors.push(format!("manifest {}: expected an object", index + 1));
+            continue;
+        };
+        for field in REGISTRY_MANIFEST_REQUIRED_FIELDS {
+            if !manifest.contains_key(*field) {
+                errors.push(format!("manifest {}: missing `{}`", index + 1, field));
+            }
+        }
+    }
+    errors
+}
+
+/// Expand `${NAME}` references in `input`. `${NAME:-default}` falls back to
+/// `default` when the variable is unset or empty; other references to unset
+/// variables are kept as `${NAME}`.
 fn expand_env_vars(input: &str) -> String {
     let mut out = String::with_capacity(input.len());
     let mut chars = input.chars().peekable();
@@ -128,12 +701,19 @@ fn expand_env_vars(input: &str) -> String {
                 }
                 name.push(c);
             }
-            if let Ok(val) = env::var(&name) {
-                out.push_str(&val);
-            } else {
-                out.push_str("${");
-                out.push_str(&name);
-                out.push('}');
+            let (name, default) = match name.split_once(":-") {
+                Some((name, default)) => (name, Some(default)),
+                None => (name.as_str(), None),
+            };
+            match (env::var(name), default) {
+                (Ok(val), Some(default)) if val.is_empty() => out.push_str(default),
+                (Ok(val), _) => out.push_str(&val),
+                (Err(_), Some(default)) => out.push_str(default),
+                (Err(_), None) => {
+                    out.push_str("${");
+                    out.push_str(name);
+                    out.push('}');
+                }
             }
         } else {
             out.push(ch);
@@ -143,46 +723,112 @@ fn expand_env_vars(input: &str) -> String {
     out
 }
 
-fn expand_server(cfg: McpServerConfig) -> McpServerConfig {
-    let mut cfg = cfg;
-
-    for val in cfg.env.values_mut() {
-        *val = expand_env_vars(val);
-    }
-    for val in cfg.headers.values_mut() {
-        *val = expand_env_vars(val);
-    }
-    if let Some(cmd) = cfg.command.as_mut() {
-        *cmd = expand_env_vars(cmd);
-    }
-    cfg.args = cfg.args.into_iter().map(|a| expand_env_vars(&a)).collect();
-    if let Some(url) = cfg.url.as_mut() {
-        *url = expand_env_vars(url);
+/// Names of `${NAME}` references left in `input` after expansion.
+fn unresolved_env_vars(input: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut rest = input;
+    while let Some(start) = rest.find("${") {
+        let after = &rest[start + 2..];
+        let Some(end) = after.find('}') else {
+            break;
+        };
+        names.push(after[..end].to_string());
+        rest = &after[end + 1..];
     }
+    names
+}
 
-    cfg
+fn is_http_url(input: &str) -> bool {
+    url::Url::parse(input)
+        .map(|u| matches!(u.scheme(), "http" | "https") && u.has_host())
+        .unwrap_or(false)
 }
 
 pub struct McpConfigs(pub Vec<McpServiceConfig>);
 
 impl McpConfigs {
-    pub fn load() -> anyhow::Result<Self> {
-        let path = resolve_mcp_json_path()?;
-        Self::load_from_path(path)
-    }
-
+    /// Load and validate the config file at `path`.
+    ///
+    /// Every server is validated before any is returned, so a bad config fails
+    /// startup with one error that lists each problem and the line it's on.
     pub fn load_from_path(path: PathBuf) -> anyhow::Result<Self> {
-        let config: McpJsonConfig = serde_json::from_str(&fs::read_to_string(&path)?)?;
-        Self::load_from_config(config)
+        let source = fs::read_to_string(&path)?;
+        let locator = SourceLocator::new(&path, &source);
+
+        // A map silently keeps the last of two servers with the same name, so
+        // look at the raw key list first.
+        let raw: RawMcpJsonConfig = serde_json::from_str(&source)?;
+        let mut seen = BTreeSet::new();
+        let mut problems = Vec::new();
+        for id in raw.mcp_servers {
+            if !seen.insert(id.clone()) {
+                let error = ConfigValidationError::DuplicateServiceName(id.clone());
+                problems.push(locator.describe(&id, &error, locator.error_line(&id, &error)));
+            }
+        }
+
+        let config: McpJsonConfig = serde_json::from_str(&source)?;
+        Self::load_validated(config, &locator, problems)
     }
 
+    #[allow(dead_code)]
     pub fn load_from_config(cfg: McpJsonConfig) -> anyhow::Result<Self> {
+        Self::load_validated(cfg, &SourceLocator::default(), Vec::new())
+    }
+
+    fn load_validated(
+        cfg: McpJsonConfig,
+        locator: &SourceLocator<'_>,
+        mut problems: Vec<String>,
+    ) -> anyhow::Result<Self> {
         let mut services = Vec::new();
         for (id, server_cfg) in cfg.mcp_servers {
-            let expanded = expand_server(server_cfg);
-            services.push(McpServiceConfig::from_json(id, expanded)?);
+            let mut service = match McpServiceConfig::from_json(id.clone(), server_cfg) {
+                Ok(service) => service,
+                Err(e) => {
+                    problems.push(locator.describe(&id, &e, locator.server_line(&id)));
+                    continue;
+                }
+            };
+            // Unset variables stay as `${NAME}` and are reported by validate()
+            // below, with the line they're on
+            let _ = service.expand_env_vars();
+            // Disabled services are never started, so they aren't held to the rules.
+            if !service.is_disabled() {
+                for error in service.validate() {
+                    problems.push(locator.describe(&id, &error, locator.error_line(&id, &error)));
+                }
+            }
+            services.push(service);
         }
 
+        // The HTTP servers send one policy, so enabled servers setting one must agree
+        let policies: BTreeSet<&str> = services
+            .iter()
+            .filter(|service| !service.is_disabled())
+            .filter_map(|service| service.security().csp_policy.as_deref())
+            .collect();
+        if policies.len() > 1 {
+            problems.push(format!(
+                "servers set different `security.csp_policy` values: {}",
+                policies
+                    .iter()
+                    .map(|policy| format!("`{policy}`"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+
+        if !problems.is_empty() {
+            let origin = locator
+                .path
+                .map(|p| format!(" in {}", p.display()))
+                .unwrap_or_default();
+            return Err(anyhow::anyhow!(
+                "Invalid MCP configuration{origin}:\n  {}",
+                problems.join("\n  ")
+            ));
+        }
         Ok(Self(services))
     }
 
@@ -190,6 +836,132 @@ impl McpConfigs {
     pub fn len(&self) -> usize {
         self.0.len()
     }
+
+    /// The `security.csp_policy` set by the enabled servers, if any.
+    pub fn csp_policy(&self) -> Option<&str> {
+        self.0
+            .iter()
+            .filter(|service| !service.is_disabled())
+            .find_map(|service| service.security().csp_policy.as_deref())
+    }
+}
+
+/// Just the server names from `mcp.json`, in order and including repeats.
+#[derive(Deserialize)]
+struct RawMcpJsonConfig {
+    #[serde(rename = "mcpServers", deserialize_with = "deserialize_keys")]
+    mcp_servers: Vec<String>,
+}
+
+fn deserialize_keys<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct KeysVisitor;
+
+    impl<'de> serde::de::Visitor<'de> for KeysVisitor {
+        type Value = Vec<String>;
+
+        fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("a map of server names to server configs")
+        }
+
+        fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+        where
+            A: serde::de::MapAccess<'de>,
+        {
+            let mut keys = Vec::new();
+            while let Some((key, serde::de::IgnoredAny)) = map.next_entry()? {
+                keys.push(key);
+            }
+            Ok(keys)
+        }
+    }
+
+    deserializer.deserialize_map(KeysVisitor)
+}
+
+/// Maps validation errors back to line numbers in the config source.
+///
+/// The default locator has no source and reports errors without a location.
+#[derive(Default)]
+struct SourceLocator<'a> {
+    path: Option<&'a Path>,
+    lines: Vec<&'a str>,
+}
+
+impl<'a> SourceLocator<'a> {
+    fn new(path: &'a Path, source: &'a str) -> Self {
+        Self {
+            path: Some(path),
+            lines: source.lines().collect(),
+        }
+    }
+
+    fn describe(&self, id: &str, error: &dyn fmt::Display, line: Option<usize>) -> String {
+        match (self.path, line) {
+            (Some(path), Some(line)) => {
+                format!("{}:{line}: server `{id}`: {error}", path.display())
+            }
+            (Some(path), None) => format!("{}: server `{id}`: {error}", path.display()),
+            (None, _) => format!("server `{id}`: {error}"),
+        }
+    }
+
+    /// Zero-based indices of the lines that open server `id`.
+    fn server_key_lines(&self, id: &str) -> Vec<usize> {
+        let key = format!("\"{id}\"");
+        self.lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| {
+                line.find(&key)
+                    .is_some_and(|at| line[at + key.len()..].trim_start().starts_with(':'))
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    fn server_line(&self, id: &str) -> Option<usize> {
+        self.server_key_lines(id).first().map(|i| i + 1)
+    }
+
+    /// The line holding the offending field, falling back to the server's own line.
+    fn error_line(&self, id: &str, error: &ConfigValidationError) -> Option<usize> {
+        let key_lines = self.server_key_lines(id);
+        let start = *key_lines.first()?;
+        let needle = match error {
+            ConfigValidationError::EmptyCommand | ConfigValidationError::DisallowedCommand(_) => {
+                "\"command\"".to_string()
+            }
+            ConfigValidationError::InvalidTimeout(_) => "\"elicitation_timeout_secs\"".to_string(),
+            ConfigValidationError::InvalidUrl(_) => "\"url\"".to_string(),
+            ConfigValidationError::InvalidCspPolicy(_) => "\"csp_policy\"".to_string(),
+            ConfigValidationError::MissingRequiredEnvVar(name) => format!("${{{name}}}"),
+            ConfigValidationError::DuplicateServiceName(_) => {
+                return key_lines.get(1).map(|i| i + 1);
+            }
+        };
+        let end = self.block_end(start).unwrap_or(self.lines.len());
+        let found = self.lines[start..end]
+            .iter()
+            .position(|line| line.contains(&needle))
+            .map(|offset| start + offset);
+        Some(found.unwrap_or(start) + 1)
+    }
+
+    /// Where the server block opened at `start` ends: the next line that sits
+    /// at the same indentation or shallower.
+    fn block_end(&self, start: usize) -> Option<usize> {
+        let indent = |line: &str| line.len() - line.trim_start().len();
+        let depth = indent(self.lines[start]);
+        self.lines
+            .iter()
+            .enumerate()
+            .skip(start + 1)
+            .find(|(_, line)| !line.trim().is_empty() && indent(line) <= depth)
+            .map(|(i, _)| i)
+    }
 }
 
 impl Iterator for McpConfigs {
@@ -210,6 +982,44 @@ mod tests {
     use std::collections::BTreeMap;
     use tempfile::TempDir;
 
+    #[test]
+    fn test_trust_level_from_json_and_check_tool() {
+        let parse = |value: serde_json::Value| -> TrustLevel {
+            let cfg: McpServerConfig = serde_json::from_value(value).unwrap();
+            cfg.trust_level
+        };
+        assert_eq!(
+            parse(serde_json::json!({ "command": "x" })),
+            TrustLevel::Trusted
+        );
+        assert_eq!(
+            parse(serde_json::json!({ "command": "x", "trust_level": "untrusted" })),
+            TrustLevel::Untrusted
+        );
+        let community = parse(serde_json::json!({
+            "command": "x",
+            "trust_level": { "community": ["network"] }
+        }));
+        assert_eq!(
+            community,
+            TrustLevel::Community(vec![AllowedCapability::Network])
+        );
+
+        let network = vec!["network".to_string()];
+        let filesystem = vec!["filesystem".to_string()];
+        assert!(community.check_tool(true, &[]).is_ok());
+        assert!(community.check_tool(true, &network).is_ok());
+        assert!(community.check_tool(true, &filesystem).is_err());
+        assert!(community.check_tool(false, &[]).is_err());
+        assert!(
+            TrustLevel::community_default()
+                .check_tool(true, &["unknown".to_string()])
+                .is_err()
+        );
+        assert!(TrustLevel::Untrusted.check_tool(true, &[]).is_err());
+        assert!(TrustLevel::Trusted.check_tool(false, &filesystem).is_ok());
+    }
+
     #[test]
     fn test_mcp_service_config_from_json_stdio() {
         let id = "test-server".to_string();
@@ -222,6 +1032,14 @@ mod tests {
             disabled: false,
             auto_approve: vec!["tool1".to_string()],
             disabled_tools: vec!["tool2".to_string()],
+            conflict_resolution: ConflictResolution::LastWins,
+            elicitation_timeout_secs: 120,
+            sampling_provenance: true,
+            sampling_provenance_prefix: None,
+            startup_timeout_secs: 90,
+            trust_level: TrustLevel::default(),
+            security: SecurityConfig::default(),
+            command_allowlist: None,
         };
 
         let result = McpServiceConfig::from_json(id, cfg).unwrap();
@@ -235,6 +1053,11 @@ mod tests {
                 disabled,
                 auto_approve,
                 disabled_tools,
+                conflict_resolution,
+                elicitation_timeout_secs,
+                sampling_provenance,
+                startup_timeout_secs,
+                ..
             } => {
                 assert_eq!(id.as_str(), "test-server");
                 assert_eq!(command, "node");
@@ -243,6 +1066,10 @@ mod tests {
                 assert!(!disabled);
                 assert_eq!(auto_approve, vec!["tool1"]);
                 assert_eq!(disabled_tools, vec!["tool2"]);
+                assert_eq!(conflict_resolution, ConflictResolution::LastWins);
+                assert_eq!(elicitation_timeout_secs, 120);
+                assert!(sampling_provenance);
+                assert_eq!(startup_timeout_secs, 90);
             }
             _ => panic!("Expected Stdio variant"),
         }
@@ -260,6 +1087,14 @@ mod tests {
             disabled: true,
             auto_approve: vec![],
             disabled_tools: vec!["tool3".to_string()],
+            conflict_resolution: ConflictResolution::default(),
+            elicitation_timeout_secs: DEFAULT_ELICITATION_TIMEOUT_SECS,
+            sampling_provenance: true,
+            sampling_provenance_prefix: None,
+            startup_timeout_secs: DEFAULT_STARTUP_TIMEOUT_SECS,
+            trust_level: TrustLevel::default(),
+            security: SecurityConfig::default(),
+            command_allowlist: None,
         };
 
         let result = McpServiceConfig::from_json(id, cfg).unwrap();
@@ -272,6 +1107,11 @@ mod tests {
                 disabled,
                 auto_approve,
                 disabled_tools,
+                conflict_resolution,
+                elicitation_timeout_secs,
+                sampling_provenance,
+                startup_timeout_secs,
+                ..
             } => {
                 assert_eq!(id.as_str(), "test-server");
                 assert_eq!(url, "http://localhost:3000");
@@ -282,6 +1122,10 @@ mod tests {
                 assert!(disabled);
                 assert_eq!(auto_approve, Vec::<String>::new());
                 assert_eq!(disabled_tools, vec!["tool3"]);
+                assert_eq!(conflict_resolution, ConflictResolution::FirstWins);
+                assert_eq!(elicitation_timeout_secs, DEFAULT_ELICITATION_TIMEOUT_SECS);
+                assert!(sampling_provenance);
+                assert_eq!(startup_timeout_secs, DEFAULT_STARTUP_TIMEOUT_SECS);
             }
             _ => panic!("Expected Http variant"),
         }
@@ -299,6 +1143,14 @@ mod tests {
             disabled: false,
             auto_approve: vec![],
             disabled_tools: vec![],
+            conflict_resolution: ConflictResolution::default(),
+            elicitation_timeout_secs: DEFAULT_ELICITATION_TIMEOUT_SECS,
+            sampling_provenance: true,
+            sampling_provenance_prefix: None,
+            startup_timeout_secs: DEFAULT_STARTUP_TIMEOUT_SECS,
+            trust_level: TrustLevel::default(),
+            security: SecurityConfig::default(),
+            command_allowlist: None,
         };
 
         let result = McpServiceConfig::from_json(id, cfg);
@@ -350,14 +1202,45 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_expand_env_vars_with_defaults() {
+        unsafe {
+            env::set_var("EXPAND_DEFAULT_SET", "value");
+            env::set_var("EXPAND_DEFAULT_EMPTY", "");
+            env::remove_var("EXPAND_DEFAULT_UNSET");
+        }
+
+        assert_eq!(expand_env_vars("${EXPAND_DEFAULT_SET:-other}"), "value");
+        assert_eq!(expand_env_vars("${EXPAND_DEFAULT_EMPTY:-other}"), "other");
+        assert_eq!(
+            expand_env_vars("http://${EXPAND_DEFAULT_UNSET:-localhost:8080}/mcp"),
+            "http://localhost:8080/mcp"
+        );
+        assert_eq!(expand_env_vars("${EXPAND_DEFAULT_UNSET:-}"), "");
+        assert_eq!(
+            expand_env_vars("${EXPAND_DEFAULT_UNSET}"),
+            "${EXPAND_DEFAULT_UNSET}"
+        );
+
+        unsafe {
+            env::remove_var("EXPAND_DEFAULT_SET");
+            env::remove_var("EXPAND_DEFAULT_EMPTY");
+        }
+    }
+
     #[test]
     fn test_expand_server() {
         unsafe {
             env::set_var("HOME", "/home/user");
+            env::remove_var("EXPAND_SERVER_UNSET");
         }
 
         let mut env = BTreeMap::new();
         env.insert("HOME_DIR".to_string(), "${HOME}".to_string());
+        env.insert(
+            "TOKEN".to_string(),
+            "${EXPAND_SERVER_UNSET}-${EXPAND_SERVER_UNSET}".to_string(),
+        );
 
         let mut headers = BTreeMap::new();
         headers.insert("Auth".to_string(), "Bearer ${HOME}/token".to_string());
@@ -371,16 +1254,47 @@ mod tests {
             disabled: false,
             auto_approve: vec![],
             disabled_tools: vec![],
+            conflict_resolution: ConflictResolution::default(),
+            elicitation_timeout_secs: DEFAULT_ELICITATION_TIMEOUT_SECS,
+            sampling_provenance: true,
+            sampling_provenance_prefix: None,
+            startup_timeout_secs: DEFAULT_STARTUP_TIMEOUT_SECS,
+            trust_level: TrustLevel::default(),
+            security: SecurityConfig::default(),
+            command_allowlist: None,
         };
 
-        let result = expand_server(cfg);
+        // With a command the config is a stdio service
+        let mut stdio = McpServiceConfig::from_json("stdio".to_string(), cfg.clone()).unwrap();
+        assert_eq!(
+            stdio.expand_env_vars(),
+            Err(vec!["EXPAND_SERVER_UNSET".to_string()])
+        );
+        let McpServiceConfig::Stdio {
+            command, args, env, ..
+        } = stdio
+        else {
+            panic!("expected a stdio service");
+        };
+        assert_eq!(command, "/home/user/bin/server");
+        assert_eq!(args, vec!["--config", "/home/user/config.json"]);
+        assert_eq!(env.get("HOME_DIR"), Some(&"/home/user".to_string()));
 
-        assert_eq!(result.command.unwrap(), "/home/user/bin/server");
-        assert_eq!(result.args, vec!["--config", "/home/user/config.json"]);
-        assert_eq!(result.env.get("HOME_DIR"), Some(&"/home/user".to_string()));
-        assert_eq!(result.url.unwrap(), "http:///home/user:3000");
+        let mut http = McpServiceConfig::from_json(
+            "http".to_string(),
+            McpServerConfig {
+                command: None,
+                ..cfg
+            },
+        )
+        .unwrap();
+        assert_eq!(http.expand_env_vars(), Ok(()));
+        let McpServiceConfig::Http { url, headers, .. } = http else {
+            panic!("expected an HTTP service");
+        };
+        assert_eq!(url, "http:///home/user:3000");
         assert_eq!(
-            result.headers.get("Auth"),
+            headers.get("Auth"),
             Some(&"Bearer /home/user/token".to_string())
         );
 
@@ -519,4 +1433,296 @@ mod tests {
             _ => panic!("Expected Http variant"),
         }
     }
+
+    fn stdio_config(command: &str) -> McpServerConfig {
+        McpServerConfig {
+            command: Some(command.to_string()),
+            args: vec![],
+            env: BTreeMap::new(),
+            url: None,
+            headers: BTreeMap::new(),
+            disabled: false,
+            auto_approve: vec![],
+            disabled_tools: vec![],
+            conflict_resolution: ConflictResolution::default(),
+            elicitation_timeout_secs: DEFAULT_ELICITATION_TIMEOUT_SECS,
+            startup_timeout_secs: DEFAULT_STARTUP_TIMEOUT_SECS,
+            trust_level: TrustLevel::default(),
+            security: SecurityConfig::default(),
+            command_allowlist: None,
+            sampling_provenance: true,
+            sampling_provenance_prefix: None,
+        }
+    }
+
+    #[test]
+    fn test_validate_collects_every_error() {
+        let mut cfg = stdio_config("  ");
+        cfg.args = vec!["--token=${UNSET_VALIDATE_TOKEN}".to_string()];
+        cfg.elicitation_timeout_secs = -5;
+        let service = McpServiceConfig::from_json("bad".to_string(), cfg).unwrap();
+
+        assert_eq!(
+            service.validate(),
+            vec![
+                ConfigValidationError::EmptyCommand,
+                ConfigValidationError::InvalidTimeout(-5),
+                ConfigValidationError::MissingRequiredEnvVar("UNSET_VALIDATE_TOKEN".to_string()),
+            ]
+        );
+        // A bad timeout never reaches the elicitation layer.
+        assert_eq!(
+            service.elicitation_timeout(),
+            Duration::from_secs(DEFAULT_ELICITATION_TIMEOUT_SECS as u64)
+        );
+
+        let ok = McpServiceConfig::from_json("ok".to_string(), stdio_config("node")).unwrap();
+        assert!(ok.validate().is_empty());
+    }
+
+    #[test]
+    fn test_validate_command_allowlist() {
+        let allowed = vec!["/usr/bin/node".to_string()];
+
+        let mut cfg = stdio_config("/usr/bin/node");
+        cfg.command_allowlist = Some(allowed.clone());
+        let service = McpServiceConfig::from_json("ok".to_string(), cfg.clone()).unwrap();
+        assert!(service.validate_with_allowed_commands(None).is_empty());
+        assert!(
+            service
+                .validate_with_allowed_commands(Some(&allowed))
+                .is_empty()
+        );
+        // Both lists must allow the command
+        assert_eq!(
+            service.validate_with_allowed_commands(Some(&["/usr/bin/python3".to_string()])),
+            vec![ConfigValidationError::DisallowedCommand(
+                "/usr/bin/node".to_string()
+            )]
+        );
+
+        // Relative commands never match, even if listed
+        cfg.command = Some("node".to_string());
+        cfg.command_allowlist = Some(vec!["node".to_string()]);
+        let service = McpServiceConfig::from_json("relative".to_string(), cfg).unwrap();
+        assert_eq!(
+            service.validate_with_allowed_commands(None),
+            vec![ConfigValidationError::DisallowedCommand("node".to_string())]
+        );
+
+        // Without a service allowlist only the global one applies
+        let service =
+            McpServiceConfig::from_json("global".to_string(), stdio_config("/bin/rm")).unwrap();
+        assert!(service.validate_with_allowed_commands(None).is_empty());
+        assert_eq!(
+            service.check_command(Some(&allowed)),
+            Err(ConfigValidationError::DisallowedCommand(
+                "/bin/rm".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_missing_env_var() {
+        let mut cfg = stdio_config("node");
+        cfg.env = BTreeMap::from([("CONFIGURED_ONLY_KEY".to_string(), "x".to_string())]);
+        let service = McpServiceConfig::from_json("search".to_string(), cfg).unwrap();
+
+        let names = vec![
+            "PATH".to_string(),
+            "CONFIGURED_ONLY_KEY".to_string(),
+            "UNSET_REQUIRED_TOOL_KEY".to_string(),
+        ];
+        assert_eq!(
+            service.missing_env_var(&names),
+            Some("UNSET_REQUIRED_TOOL_KEY")
+        );
+        assert_eq!(service.missing_env_var(&names[..2]), None);
+    }
+
+    #[test]
+    fn test_validate_http_url() {
+        let mut cfg = stdio_config("unused");
+        cfg.command = None;
+        cfg.url = Some("ftp://example.com/mcp".to_string());
+        let service = McpServiceConfig::from_json("remote".to_string(), cfg.clone()).unwrap();
+        assert_eq!(
+            service.validate(),
+            vec![ConfigValidationError::InvalidUrl(
+                "ftp://example.com/mcp".to_string()
+            )]
+        );
+
+        cfg.url = Some("${UNSET_VALIDATE_HOST}/mcp".to_string());
+        let service = McpServiceConfig::from_json("remote".to_string(), cfg).unwrap();
+        assert_eq!(
+            service.validate(),
+            vec![ConfigValidationError::MissingRequiredEnvVar(
+                "UNSET_VALIDATE_HOST".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_load_from_path_reports_all_errors_with_lines() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("mcp.json");
+        fs::write(
+            &path,
+            r#"{
+  "mcpServers": {
+    "empty": {
+      "command": ""
+    },
+    "remote": {
+      "url": "not a url",
+      "elicitation_timeout_secs": 0
+    },
+    "off": {
+      "command": "",
+      "disabled": true
+    },
+    "empty": {
+      "command": "node"
+    }
+  }
+}"#,
+        )
+        .unwrap();
+
+        let message = McpConfigs::load_from_path(path.clone())
+            .err()
+            .expect("invalid config should fail to load")
+            .to_string();
+        let p = path.display();
+
+        assert!(message.contains(&format!(
+            "{p}:14: server `empty`: server name `empty` is defined more than once"
+        )));
+        assert!(message.contains(&format!(
+            "{p}:7: server `remote`: `url` must be an absolute http(s) URL"
+        )));
+        assert!(message.contains(&format!(
+            "{p}:8: server `remote`: `elicitation_timeout_secs` must be positive, got 0"
+        )));
+        assert!(!message.contains("server `off`"));
+    }
+
+    #[test]
+    fn test_csp_policy_from_security_section() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("mcp.json");
+        fs::write(
+            &path,
+            r#"{
+  "mcpServers": {
+    "a": { "command": "node", "security": { "csp_policy": "default-src 'self'" } },
+    "b": { "command": "node" },
+    "off": { "command": "node", "disabled": true, "security": { "csp_policy": "x" } }
+  }
+}"#,
+        )
+        .unwrap();
+        let configs = McpConfigs::load_from_path(path.clone()).unwrap();
+        assert_eq!(configs.csp_policy(), Some("default-src 'self'"));
+
+        fs::write(
+            &path,
+            r#"{
+  "mcpServers": {
+    "a": { "command": "node", "security": { "csp_policy": "default-src 'self'" } },
+    "b": { "command": "node", "security": { "csp_policy": "default-src 'none'" } },
+    "c": {
+      "command": "node",
+      "security": { "csp_policy": "default-src\n'none'" }
+    }
+  }
+}"#,
+        )
+        .unwrap();
+        let message = McpConfigs::load_from_path(path.clone())
+            .err()
+            .expect("conflicting policies should fail to load")
+            .to_string();
+        assert!(message.contains("servers set different `security.csp_policy` values"));
+        assert!(message.contains(&format!(
+            "{}:7: server `c`: `csp_policy` is not a valid header value",
+            path.display()
+        )));
+    }
+
+    #[test]
+    fn test_sampling_provenance_prefix() {
+        let prefix = |json: &str| {
+            let cfg: McpServerConfig = serde_json::from_str(json).unwrap();
+            McpServiceConfig::from_json("a".to_string(), cfg)
+                .unwrap()
+                .sampling_provenance_prefix()
+                .map(str::to_string)
+        };
+        assert_eq!(
+            prefix(r#"{ "command": "node" }"#).as_deref(),
+            Some(DEFAULT_SAMPLING_PROVENANCE_PREFIX)
+        );
+        assert_eq!(
+            prefix(r#"{ "command": "node", "sampling_provenance_prefix": "({origin})" }"#)
+                .as_deref(),
+            Some("({origin})")
+        );
+        assert_eq!(
+            prefix(r#"{ "url": "https://x", "sampling_provenance": false }"#),
+            None
+        );
+    }
+
+    #[test]
+    fn test_clear_manifest_cache() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry_dir = registry_cache_dir(temp_dir.path(), "main").unwrap();
+        fs::create_dir_all(&registry_dir).unwrap();
+        fs::write(registry_dir.join("fs-1.0.0.json"), "{}").unwrap();
+        fs::write(registry_dir.join("git-2.1.0.json"), "{}").unwrap();
+        let other_dir = registry_cache_dir(temp_dir.path(), "other").unwrap();
+        fs::create_dir_all(&other_dir).unwrap();
+        fs::write(other_dir.join("fs-1.0.0.json"), "{}").unwrap();
+
+        assert_eq!(clear_manifest_cache(temp_dir.path(), "main").unwrap(), 2);
+        assert!(!registry_dir.exists());
+        assert!(other_dir.join("fs-1.0.0.json").exists());
+
+        // Clearing an uncached registry is a no-op
+        assert_eq!(clear_manifest_cache(temp_dir.path(), "main").unwrap(), 0);
+        assert!(clear_manifest_cache(temp_dir.path(), "../other").is_err());
+    }
+
+    #[test]
+    fn test_apply_manifest_transform() {
+        let response = serde_json::json!({
+            "servers": [
+                { "pkg": { "name": "fs", "version": "1.0.0" }, "protocol": "2025-06-18" },
+                { "pkg": { "name": "git" } }
+            ]
+        });
+        let expression = "servers[].{name: pkg.name, version: pkg.version, \
+            mcp_version: protocol, schema_version: '1.0.0', tags: `[]`, dependencies: `[]`}";
+
+        let manifests = apply_manifest_transform(expression, &response).unwrap();
+        assert_eq!(manifests[0]["name"], "fs");
+        assert_eq!(manifests[0]["mcp_version"], "2025-06-18");
+        assert_eq!(manifests[1]["version"], serde_json::Value::Null);
+        assert_eq!(
+            manifest_shape_errors(&manifests),
+            vec![
+                "manifest 1: missing `manifest_url`",
+                "manifest 1: missing `download_url`",
+                "manifest 2: missing `manifest_url`",
+                "manifest 2: missing `download_url`",
+            ]
+        );
+        assert_eq!(
+            manifest_shape_errors(&serde_json::json!({})),
+            vec!["expected an array of manifests"]
+        );
+        assert!(apply_manifest_transform("servers[", &response).is_err());
+    }
 }
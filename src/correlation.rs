@@ -0,0 +1,135 @@
+//! Correlation IDs that tie together the spans and log lines of a single HTTP
+//! request or MCP tool call.
+//!
+//! The ID lives in a Tokio task-local for the duration of the request, so code
+//! deep in the call stack (e.g. a live query subscription) can attach it to
+//! its own spans, including ones running on tasks it spawns.
+
+use axum::extract::Request;
+use axum::http::HeaderValue;
+use axum::middleware::Next;
+use axum::response::Response;
+use std::future::Future;
+use tracing::Span;
+
+/// Header carrying the correlation ID. An incoming value is reused so traces
+/// can span several services; otherwise a new UUID is assigned.
+pub const CORRELATION_ID_HEADER: &str = "x-correlation-id";
+
+/// Longest incoming correlation ID that is accepted as-is.
+const MAX_CORRELATION_ID_LEN: usize = 128;
+
+tokio::task_local! {
+    static CORRELATION_ID: String;
+}
+
+/// Correlation ID of a request, stored in its extensions.
+#[derive(Debug, Clone)]
+pub struct CorrelationId(pub String);
+
+pub fn new_correlation_id() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+/// The correlation ID of the request being handled by the current task, if any.
+pub fn current_correlation_id() -> Option<String> {
+    CORRELATION_ID.try_with(Clone::clone).ok()
+}
+
+/// Run `future` with `correlation_id` as the current correlation ID.
+pub async fn with_correlation_id<F: Future>(correlation_id: String, future: F) -> F::Output {
+    CORRELATION_ID.scope(correlation_id, future).await
+}
+
+/// Axum middleware that assigns each request a correlation ID, makes it
+/// current while the request is handled and echoes it in the response.
+///
+/// Must sit outside `TraceLayer` so [`http_request_span`] can find the ID.
+pub async fn assign_correlation_id(mut request: Request, next: Next) -> Response {
+    let correlation_id = request
+        .headers()
+        .get(CORRELATION_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.is_empty() && value.len() <= MAX_CORRELATION_ID_LEN)
+        .map(str::to_string)
+        .unwrap_or_else(new_correlation_id);
+    request
+        .extensions_mut()
+        .insert(CorrelationId(correlation_id.clone()));
+
+    let mut response = with_correlation_id(correlation_id.clone(), next.run(request)).await;
+    if let Ok(value) = HeaderValue::from_str(&correlation_id) {
+        response.headers_mut().insert(CORRELATION_ID_HEADER, value);
+    }
+    response
+}
+
+/// `TraceLayer` span for a request, carrying its correlation ID.
+pub fn http_request_span<B>(request: &axum::http::Request<B>) -> Span {
+    let correlation_id = request
+        .extensions()
+        .get::<CorrelationId>()
+        .map(|id| id.0.as_str())
+        .unwrap_or_default();
+    tracing::info_span!(
+        "http_request",
+        method = %request.method(),
+        uri = %request.uri(),
+        correlation_id,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::body::Body;
+    use axum::routing::get;
+    use tower::ServiceExt;
+
+    fn router() -> Router {
+        Router::new()
+            .route(
+                "/",
+                get(|| async { current_correlation_id().unwrap_or_default() }),
+            )
+            .layer(axum::middleware::from_fn(assign_correlation_id))
+    }
+
+    async fn correlation_ids(request: axum::http::Request<Body>) -> (String, String) {
+        let response = router().oneshot(request).await.unwrap();
+        let header = response.headers()[CORRELATION_ID_HEADER]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (header, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_assigns_correlation_id_per_request() {
+        let request = || axum::http::Request::builder().uri("/").body(Body::empty());
+        let (header, seen) = correlation_ids(request().unwrap()).await;
+        assert!(uuid::Uuid::parse_str(&header).is_ok());
+        assert_eq!(seen, header);
+
+        let (other, _) = correlation_ids(request().unwrap()).await;
+        assert_ne!(other, header);
+        assert!(current_correlation_id().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_reuses_incoming_correlation_id() {
+        let request = axum::http::Request::builder()
+            .uri("/")
+            .header(CORRELATION_ID_HEADER, "upstream-42")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(
+            correlation_ids(request).await,
+            ("upstream-42".to_string(), "upstream-42".to_string())
+        );
+    }
+}
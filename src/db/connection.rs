@@ -64,6 +64,7 @@ pub async fn ensure_schema(db: &Db) -> Result<()> {
          DEFINE FIELD version ON TABLE service TYPE string;
          DEFINE FIELD website_url ON TABLE service TYPE option<string>;
          DEFINE FIELD origin ON TABLE service TYPE string;
+         DEFINE FIELD icon_fetch_failed ON TABLE service TYPE bool DEFAULT false;
          DEFINE FIELD registry_id ON TABLE service TYPE option<record<registry>>;
          DEFINE FIELD created_at ON TABLE service VALUE time::now();
          DEFINE FIELD updated_at ON TABLE service VALUE time::now();",
@@ -79,8 +80,25 @@ pub async fn ensure_schema(db: &Db) -> Result<()> {
          DEFINE FIELD input_ty ON TABLE tool TYPE option<object>;
          DEFINE FIELD output_ty ON TABLE tool TYPE option<object>;
          DEFINE FIELD usage_count ON TABLE tool TYPE number DEFAULT 0;
+         DEFINE FIELD status ON TABLE tool TYPE option<string>;
+         DEFINE FIELD metadata ON TABLE tool TYPE object DEFAULT {};
+         DEFINE FIELD input_examples ON TABLE tool TYPE array<object> DEFAULT [];
+         DEFINE FIELD changelog ON TABLE tool TYPE array<object> DEFAULT [];
+         DEFINE FIELD deprecation_notice ON TABLE tool TYPE option<object>;
+         DEFINE FIELD descriptions ON TABLE tool FLEXIBLE TYPE object DEFAULT {};
+         DEFINE FIELD inject_user_context ON TABLE tool TYPE array<object> DEFAULT [];
+         DEFINE FIELD geo_availability ON TABLE tool TYPE option<array<string>>;
+         DEFINE FIELD required_env_vars ON TABLE tool TYPE array<string> DEFAULT [];
+         DEFINE FIELD sla_class ON TABLE tool TYPE option<object>;
+         DEFINE FIELD cost_per_call ON TABLE tool TYPE option<float>;
+         DEFINE FIELD cache_ttl_seconds ON TABLE tool TYPE option<int>;
+         DEFINE FIELD required_role ON TABLE tool TYPE option<string>;
+         DEFINE FIELD actual_latency_p99_ms ON TABLE tool TYPE option<int>;
+         DEFINE FIELD actual_error_rate_pct ON TABLE tool TYPE option<float>;
+         DEFINE FIELD block_reason ON TABLE tool FLEXIBLE TYPE option<object>;
          DEFINE FIELD created_at ON TABLE tool VALUE time::now();
-         DEFINE FIELD updated_at ON TABLE tool VALUE time::now();",
+         DEFINE FIELD updated_at ON TABLE tool VALUE time::now();
+         DEFINE FIELD last_seen_at ON TABLE tool TYPE option<datetime>;",
 
         // Embedding table
         "DEFINE TABLE embedding SCHEMAFULL;
@@ -90,6 +108,17 @@ pub async fn ensure_schema(db: &Db) -> Result<()> {
          DEFINE FIELD content_hash ON TABLE embedding TYPE string;
          DEFINE FIELD created_at ON TABLE embedding VALUE time::now();",
 
+        // Embeddings of a tool's translated descriptions, one per language
+        "DEFINE TABLE tool_embedding_by_lang SCHEMAFULL;
+         DEFINE FIELD tool ON TABLE tool_embedding_by_lang TYPE record<tool>;
+         DEFINE FIELD language ON TABLE tool_embedding_by_lang TYPE string;
+         DEFINE FIELD model ON TABLE tool_embedding_by_lang TYPE string;
+         DEFINE FIELD content_hash ON TABLE tool_embedding_by_lang TYPE string;
+         DEFINE FIELD vector ON TABLE tool_embedding_by_lang TYPE array<float>;
+         DEFINE FIELD created_at ON TABLE tool_embedding_by_lang VALUE time::now();
+         DEFINE INDEX tool_embedding_by_lang_key ON TABLE tool_embedding_by_lang COLUMNS tool, language, model UNIQUE;
+         DEFINE INDEX tool_embedding_by_lang_language ON TABLE tool_embedding_by_lang COLUMNS language, model;",
+
         // Typed relationship between tools
         "DEFINE TABLE tool_compatibility SCHEMAFULL;
          DEFINE FIELD in ON TABLE tool_compatibility TYPE record<tool>;
@@ -122,10 +151,15 @@ pub async fn ensure_schema(db: &Db) -> Result<()> {
          DEFINE FIELD registry_id ON TABLE manifest TYPE record<registry>;
          DEFINE FIELD name ON TABLE manifest TYPE string;
          DEFINE FIELD version ON TABLE manifest TYPE string;
-         DEFINE FIELD content ON TABLE manifest TYPE object;
+         DEFINE FIELD description ON TABLE manifest TYPE option<string>;
+         DEFINE FIELD metadata ON TABLE manifest FLEXIBLE TYPE option<object>;
+         DEFINE FIELD content ON TABLE manifest FLEXIBLE TYPE object;
          DEFINE FIELD hash ON TABLE manifest TYPE string;
+         DEFINE FIELD checksum ON TABLE manifest TYPE option<string>;
+         DEFINE FIELD tags ON TABLE manifest TYPE array<string> DEFAULT [];
          DEFINE FIELD is_active ON TABLE manifest TYPE bool DEFAULT true;
-         DEFINE FIELD created_at ON TABLE manifest VALUE time::now();",
+         DEFINE FIELD created_at ON TABLE manifest VALUE time::now();
+         DEFINE FIELD updated_at ON TABLE manifest TYPE option<datetime>;",
 
         // Indexes for performance
         "DEFINE INDEX tool_service_id ON TABLE tool COLUMNS service_id;
@@ -140,11 +174,16 @@ pub async fn ensure_schema(db: &Db) -> Result<()> {
          DEFINE FIELD name ON TABLE symbolic_rule TYPE string;
          DEFINE FIELD description ON TABLE symbolic_rule TYPE option<string>;
          DEFINE FIELD antecedents ON TABLE symbolic_rule TYPE array;
+         DEFINE FIELD antecedents.* ON TABLE symbolic_rule FLEXIBLE TYPE object;
          DEFINE FIELD consequents ON TABLE symbolic_rule TYPE array;
+         DEFINE FIELD consequents.* ON TABLE symbolic_rule FLEXIBLE TYPE object;
          DEFINE FIELD confidence ON TABLE symbolic_rule TYPE float;
          DEFINE FIELD priority ON TABLE symbolic_rule TYPE int;
          DEFINE FIELD is_active ON TABLE symbolic_rule TYPE bool DEFAULT true;
-         DEFINE FIELD created_at ON TABLE symbolic_rule VALUE time::now();",
+         DEFINE FIELD soft_deleted_at ON TABLE symbolic_rule TYPE option<datetime>;
+         DEFINE FIELD created_at ON TABLE symbolic_rule VALUE time::now();
+         DEFINE FIELD updated_at ON TABLE symbolic_rule VALUE time::now();
+         DEFINE INDEX symbolic_rule_updated_at ON TABLE symbolic_rule COLUMNS updated_at;",
 
         // Permission table for tool approval and elicitation
         "DEFINE TABLE permission SCHEMAFULL;
@@ -155,7 +194,25 @@ pub async fn ensure_schema(db: &Db) -> Result<()> {
          DEFINE FIELD created_at ON TABLE permission VALUE time::now();
          DEFINE FIELD expires_at ON TABLE permission TYPE option<datetime>;
          DEFINE INDEX permission_tool_user ON TABLE permission COLUMNS tool_id, user_id;
-         DEFINE INDEX permission_service_user ON TABLE permission COLUMNS service_id, user_id;",
+         DEFINE INDEX permission_service_user ON TABLE permission COLUMNS service_id, user_id;
+         -- Audit every permission write, including ones that bypass the application
+         DEFINE EVENT OVERWRITE permission_changed ON TABLE permission
+             WHEN $event = 'CREATE' OR $event = 'DELETE'
+             THEN (
+                 CREATE audit_log CONTENT {
+                     user_id: $value.user_id,
+                     action: IF $event = 'CREATE' THEN 'permission_created' ELSE 'permission_deleted' END,
+                     resource_type: 'permission',
+                     resource_id: <string> $value.id,
+                     details: {
+                         tool_id: $value.tool_id,
+                         service_id: $value.service_id,
+                         action: $value.action,
+                         changed_by: IF $auth.id THEN <string> $auth.id ELSE NONE END,
+                         timestamp: time::now()
+                     }
+                 }
+             );",
 
         // User table for multi-tenant identity management
         // Users are identified by external identity (e.g., from JWT, session, API key)
@@ -170,6 +227,13 @@ pub async fn ensure_schema(db: &Db) -> Result<()> {
          DEFINE FIELD last_seen_at ON TABLE user TYPE option<datetime>;
          DEFINE INDEX user_external_id ON TABLE user COLUMNS external_id, provider UNIQUE;",
 
+        // Roles of users that authenticate without JWT claims, e.g. with API keys
+        "DEFINE TABLE user_roles SCHEMAFULL;
+         DEFINE FIELD user_id ON TABLE user_roles TYPE record<user>;
+         DEFINE FIELD role ON TABLE user_roles TYPE string;
+         DEFINE FIELD created_at ON TABLE user_roles VALUE time::now();
+         DEFINE INDEX user_roles_user_role ON TABLE user_roles COLUMNS user_id, role UNIQUE;",
+
         // User preferences for per-user settings
         "DEFINE TABLE user_preferences SCHEMAFULL;
          DEFINE FIELD user_id ON TABLE user_preferences TYPE record<user>;
@@ -183,6 +247,11 @@ pub async fn ensure_schema(db: &Db) -> Result<()> {
          -- Notification settings
          DEFINE FIELD notify_on_tool_execution ON TABLE user_preferences TYPE bool DEFAULT false;
          DEFINE FIELD notify_on_permission_grant ON TABLE user_preferences TYPE bool DEFAULT true;
+         -- Search settings
+         DEFINE FIELD embedding_backend_override ON TABLE user_preferences TYPE option<object>;     -- Overrides the global embedding model
+         DEFINE FIELD embedding_backend_override.model_name ON TABLE user_preferences TYPE string;
+         DEFINE FIELD embedding_backend_override.model_architecture ON TABLE user_preferences TYPE string DEFAULT '';
+         DEFINE FIELD preferred_language ON TABLE user_preferences TYPE option<string>;                -- BCP-47 tag of the user's query language
          -- Timestamps
          DEFINE FIELD created_at ON TABLE user_preferences VALUE time::now();
          DEFINE FIELD updated_at ON TABLE user_preferences VALUE time::now();
@@ -194,7 +263,7 @@ pub async fn ensure_schema(db: &Db) -> Result<()> {
          DEFINE FIELD action ON TABLE audit_log TYPE string;             -- 'tool_executed', 'permission_granted', 'login', etc.
          DEFINE FIELD resource_type ON TABLE audit_log TYPE string;      -- 'tool', 'service', 'permission', etc.
          DEFINE FIELD resource_id ON TABLE audit_log TYPE option<string>;
-         DEFINE FIELD details ON TABLE audit_log TYPE option<object>;    -- Additional context
+         DEFINE FIELD OVERWRITE details ON TABLE audit_log FLEXIBLE TYPE option<object>; -- Additional context; FLEXIBLE keeps its keys
          DEFINE FIELD ip_address ON TABLE audit_log TYPE option<string>; -- Client IP if available
          DEFINE FIELD user_agent ON TABLE audit_log TYPE option<string>; -- Client user agent if available
          DEFINE FIELD created_at ON TABLE audit_log VALUE time::now();
@@ -202,6 +271,56 @@ pub async fn ensure_schema(db: &Db) -> Result<()> {
          DEFINE INDEX audit_log_action ON TABLE audit_log COLUMNS action;
          DEFINE INDEX audit_log_created_at ON TABLE audit_log COLUMNS created_at;",
 
+        // Tool invocations with their arguments, used for argument suggestions
+        "DEFINE TABLE tool_usage_event SCHEMAFULL;
+         DEFINE FIELD tool_id ON TABLE tool_usage_event TYPE record<tool>;
+         DEFINE FIELD user_id ON TABLE tool_usage_event TYPE string;
+         DEFINE FIELD arguments ON TABLE tool_usage_event FLEXIBLE TYPE object;
+         DEFINE FIELD success ON TABLE tool_usage_event TYPE bool;
+         DEFINE FIELD duration_ms ON TABLE tool_usage_event TYPE option<int>;
+         DEFINE FIELD created_at ON TABLE tool_usage_event VALUE time::now();
+         DEFINE INDEX tool_usage_event_tool_user ON TABLE tool_usage_event COLUMNS tool_id, user_id;",
+
+        // Input schema snapshots, one per change, used for schema diffs
+        "DEFINE TABLE tool_schema_history SCHEMAFULL;
+         DEFINE FIELD service_id ON TABLE tool_schema_history TYPE record<service>;
+         DEFINE FIELD tool_name ON TABLE tool_schema_history TYPE string;
+         DEFINE FIELD version ON TABLE tool_schema_history TYPE int;
+         DEFINE FIELD input_schema ON TABLE tool_schema_history FLEXIBLE TYPE object;
+         DEFINE FIELD input_ty ON TABLE tool_schema_history FLEXIBLE TYPE object;
+         DEFINE FIELD recorded_at ON TABLE tool_schema_history VALUE time::now();
+         DEFINE INDEX tool_schema_history_version ON TABLE tool_schema_history COLUMNS service_id, tool_name, version UNIQUE;",
+
+        // Prompt requests with their arguments, used to infer argument types
+        "DEFINE TABLE prompt_usage_event SCHEMAFULL;
+         DEFINE FIELD service_id ON TABLE prompt_usage_event TYPE string;
+         DEFINE FIELD prompt_name ON TABLE prompt_usage_event TYPE string;
+         DEFINE FIELD arguments ON TABLE prompt_usage_event FLEXIBLE TYPE object;
+         DEFINE FIELD success ON TABLE prompt_usage_event TYPE bool;
+         DEFINE FIELD created_at ON TABLE prompt_usage_event VALUE time::now();
+         DEFINE INDEX prompt_usage_event_prompt ON TABLE prompt_usage_event COLUMNS service_id, prompt_name;",
+
+        // User feedback on tool selections, used to penalize rejected tools
+        "DEFINE TABLE selection_feedback SCHEMAFULL;
+         DEFINE FIELD user_id ON TABLE selection_feedback TYPE string DEFAULT '';
+         DEFINE FIELD session_id ON TABLE selection_feedback TYPE string;
+         DEFINE FIELD query ON TABLE selection_feedback TYPE string;
+         DEFINE FIELD query_embedding ON TABLE selection_feedback TYPE option<array<float>>;
+         DEFINE FIELD selected_tool_id ON TABLE selection_feedback TYPE option<record<tool>>;
+         DEFINE FIELD rejected_tool_ids ON TABLE selection_feedback TYPE array<record<tool>> DEFAULT [];
+         DEFINE FIELD outcome ON TABLE selection_feedback TYPE string;  -- 'accepted' or 'rejected'
+         DEFINE FIELD created_at ON TABLE selection_feedback VALUE time::now();
+         DEFINE INDEX selection_feedback_session ON TABLE selection_feedback COLUMNS session_id;
+         DEFINE INDEX selection_feedback_user ON TABLE selection_feedback COLUMNS user_id, created_at;",
+
+        // Service icons downloaded from `server_info.icons`
+        "DEFINE TABLE service_icon SCHEMAFULL;
+         DEFINE FIELD service_id ON TABLE service_icon TYPE record<service>;
+         DEFINE FIELD data ON TABLE service_icon TYPE bytes;
+         DEFINE FIELD mime_type ON TABLE service_icon TYPE string;
+         DEFINE FIELD fetched_at ON TABLE service_icon VALUE time::now();
+         DEFINE INDEX service_icon_service ON TABLE service_icon COLUMNS service_id UNIQUE;",
+
         // API key table for database-backed API key authentication
         "DEFINE TABLE api_key SCHEMAFULL;
          DEFINE FIELD key_hash ON TABLE api_key TYPE string;
@@ -1,7 +1,11 @@
 pub mod connection;
 pub mod queries;
+pub mod resolver;
 pub mod schema;
+pub mod schema_diff;
 
 pub use connection::*;
 pub use queries::*;
+pub use resolver::*;
 pub use schema::*;
+pub use schema_diff::*;
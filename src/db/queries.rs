@@ -4,18 +4,113 @@
 // real SurrealDB queries, but keep the logic simple so we can evolve them
 // alongside the schema and graph engine.
 
+use crate::correlation::current_correlation_id;
+use crate::db::resolver::JsonSchemaResolver;
 use crate::db::schema::{
-    ApiKeyCreate, ApiKeyRecord, CompatibilityType, CreateToolRecord, ManifestRecord, ServiceCreate,
-    ServiceRecord, ToolCompatibility, ToolRecord, ToolSearchQuery, ToolSearchResult, ToolSequence,
+    ApiKeyCreate, ApiKeyRecord, AuditLogCreate, BlockReason, ChangelogEntry, CompatibilityType,
+    CreateToolRecord, MAX_CHANGELOG_ENTRIES, MAX_INPUT_EXAMPLES, ManifestRecord,
+    PromptUsageEventRecord, SelectionFeedbackCreate, SelectionFeedbackRecord, ServiceCreate,
+    ServiceIconRecord, ServiceRecord, ToolCompatibility, ToolFilter, ToolRecord,
+    ToolSchemaHistoryRecord, ToolSearchQuery, ToolSearchResult, ToolSequence, ToolUsageEventRecord,
+    TypedSchema,
 };
+use crate::knowledge_graph::ToolStatus;
+use crate::types::{ApiKeyHash, ApiKeyPrefix, ExternalUserId, ServiceId};
 use anyhow::{Result, anyhow};
-use serde::Deserialize;
+use base64::Engine;
+use futures::StreamExt;
+use rmcp::model::JsonObject;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use surrealdb::RecordId;
+use std::collections::{HashMap, HashSet};
+use surrealdb::sql::{Bytes, Datetime};
+use surrealdb::{Action, Notification, RecordId};
 use surrealdb::{Surreal, engine::any::Any};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::Instrument;
 
 pub struct QueryBuilder;
 
+/// A change to the `tool` table reported by a live query.
+// Events are sent one at a time over a channel, so their size doesn't matter
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone)]
+pub enum ToolChangeEvent {
+    Created(ToolRecord),
+    Updated { old: ToolRecord, new: ToolRecord },
+    Deleted(RecordId),
+}
+
+/// A running live query on the `tool` table.
+///
+/// Dropping the handle kills the live query and closes the event channel.
+pub struct LiveQueryHandle {
+    task: JoinHandle<()>,
+}
+
+impl Drop for LiveQueryHandle {
+    fn drop(&mut self) {
+        // The task owns the live query stream, which kills the query when dropped
+        self.task.abort();
+    }
+}
+
+/// Records removed (or, for a dry run, that would be removed) by
+/// [`QueryBuilder::prune_stale_records`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PruneResult {
+    pub tools_removed: usize,
+    pub services_removed: usize,
+    pub embeddings_removed: usize,
+}
+
+/// Example argument sets to store for a tool, capped at `MAX_INPUT_EXAMPLES`.
+fn stored_input_examples(tool: &CreateToolRecord) -> Value {
+    Value::Array(
+        tool.input_examples
+            .iter()
+            .take(MAX_INPUT_EXAMPLES)
+            .cloned()
+            .map(Value::Object)
+            .collect(),
+    )
+}
+
+/// Changelog entries to store for a tool, capped at `MAX_CHANGELOG_ENTRIES`.
+fn stored_changelog(tool: &CreateToolRecord) -> Vec<ChangelogEntry> {
+    tool.changelog
+        .iter()
+        .take(MAX_CHANGELOG_ENTRIES)
+        .cloned()
+        .collect()
+}
+
+/// Merge `patch` into `target` following JSON merge patch rules: nested
+/// objects merge recursively, `null` removes a key, anything else replaces it.
+fn merge_metadata(target: &mut JsonObject, patch: &JsonObject) {
+    for (key, value) in patch {
+        match value {
+            Value::Null => {
+                target.remove(key);
+            }
+            Value::Object(patch_obj) => {
+                if let Some(Value::Object(target_obj)) = target.get_mut(key) {
+                    merge_metadata(target_obj, patch_obj);
+                } else {
+                    let mut fresh = JsonObject::new();
+                    merge_metadata(&mut fresh, patch_obj);
+                    target.insert(key.clone(), Value::Object(fresh));
+                }
+            }
+            other => {
+                target.insert(key.clone(), other.clone());
+            }
+        }
+    }
+}
+
 impl QueryBuilder {
     /// Create a new service record in the database.
     ///
@@ -55,8 +150,16 @@ impl QueryBuilder {
     ///
     /// This is currently a simple CREATE and does not attempt to deduplicate
     /// tools by (service_id, name). In the future this can be upgraded to
-    /// a true UPSERT keyed by that pair.
+    /// a true UPSERT keyed by that pair. Metadata is the exception: it is
+    /// merged into the metadata of an existing tool with the same pair, so
+    /// manual annotations survive rediscovery.
     pub async fn upsert_tool(db: &Surreal<Any>, data: &CreateToolRecord) -> Result<ToolRecord> {
+        let mut metadata = Self::find_tool_by_name(db, &data.service_id, &data.name)
+            .await?
+            .map(|existing| existing.metadata)
+            .unwrap_or_default();
+        merge_metadata(&mut metadata, &data.metadata);
+
         let mut res = db
             .query(
                 r#"
@@ -69,9 +172,23 @@ impl QueryBuilder {
                     embedding_id = $embedding_id,
                     input_ty = $input_ty,
                     output_ty = $output_ty,
+                    discovery_source = $discovery_source,
+                    metadata = $metadata,
+                    input_examples = $input_examples,
+                    changelog = $changelog,
+                    deprecation_notice = $deprecation_notice,
+                    descriptions = $descriptions,
+                    inject_user_context = $inject_user_context,
+                    geo_availability = $geo_availability,
+                    required_env_vars = $required_env_vars,
+                    sla_class = $sla_class,
+                    cost_per_call = $cost_per_call,
+                    cache_ttl_seconds = $cache_ttl_seconds,
+                    required_role = $required_role,
                     usage_count = 0,
                     created_at = time::now(),
-                    updated_at = time::now()
+                    updated_at = time::now(),
+                    last_seen_at = time::now()
                 "#,
             )
             .bind(("service_id", data.service_id.clone()))
@@ -82,10 +199,223 @@ impl QueryBuilder {
             .bind(("embedding_id", data.embedding_id.clone()))
             .bind(("input_ty", data.input_ty.clone()))
             .bind(("output_ty", data.output_ty.clone()))
+            .bind(("discovery_source", data.discovery_source.clone()))
+            .bind(("metadata", Value::Object(metadata)))
+            .bind(("input_examples", stored_input_examples(data)))
+            .bind(("changelog", stored_changelog(data)))
+            .bind(("deprecation_notice", data.deprecation_notice.clone()))
+            .bind(("descriptions", data.descriptions.clone()))
+            .bind(("inject_user_context", data.inject_user_context.clone()))
+            .bind(("geo_availability", data.geo_availability.clone()))
+            .bind(("required_env_vars", data.required_env_vars.clone()))
+            .bind(("sla_class", data.sla_class))
+            .bind(("cost_per_call", data.cost_per_call))
+            .bind(("cache_ttl_seconds", data.cache_ttl_seconds))
+            .bind(("required_role", data.required_role.clone()))
             .await?;
 
         let created: Option<ToolRecord> = res.take(0)?;
-        created.ok_or_else(|| anyhow!("failed to create tool record"))
+        let created = created.ok_or_else(|| anyhow!("failed to create tool record"))?;
+
+        Self::record_tool_schema(
+            db,
+            &data.service_id,
+            &data.name,
+            &created.input_schema,
+            &created.typed_input(),
+        )
+        .await?;
+        Ok(created)
+    }
+
+    /// Record `input_schema` as the next version in `tool_schema_history`
+    /// unless it matches the latest recorded version.
+    ///
+    /// Returns the new version, or `None` if the schema is unchanged.
+    pub async fn record_tool_schema(
+        db: &Surreal<Any>,
+        service_id: &RecordId,
+        tool_name: &str,
+        input_schema: &JsonObject,
+        input_ty: &TypedSchema,
+    ) -> Result<Option<u32>> {
+        let latest = Self::list_tool_schema_history(db, service_id, tool_name)
+            .await?
+            .pop();
+        if latest
+            .as_ref()
+            .is_some_and(|latest| &latest.input_schema == input_schema)
+        {
+            return Ok(None);
+        }
+
+        let version = latest.map_or(1, |latest| latest.version + 1);
+        db.query(
+            r#"
+            CREATE tool_schema_history SET
+                service_id = $service_id,
+                tool_name = $tool_name,
+                version = $version,
+                input_schema = $input_schema,
+                input_ty = $input_ty
+            "#,
+        )
+        .bind(("service_id", service_id.clone()))
+        .bind(("tool_name", tool_name.to_string()))
+        .bind(("version", version))
+        .bind(("input_schema", Value::Object(input_schema.clone())))
+        .bind(("input_ty", serde_json::to_value(input_ty)?))
+        .await?
+        .check()?;
+
+        Ok(Some(version))
+    }
+
+    /// Every recorded input schema of a tool, oldest version first.
+    pub async fn list_tool_schema_history(
+        db: &Surreal<Any>,
+        service_id: &RecordId,
+        tool_name: &str,
+    ) -> Result<Vec<ToolSchemaHistoryRecord>> {
+        let mut res = db
+            .query(
+                r#"
+                SELECT * FROM tool_schema_history
+                WHERE service_id = $service_id AND tool_name = $tool_name
+                ORDER BY version ASC
+                "#,
+            )
+            .bind(("service_id", service_id.clone()))
+            .bind(("tool_name", tool_name.to_string()))
+            .await?;
+
+        let history: Vec<ToolSchemaHistoryRecord> = res.take(0)?;
+        Ok(history)
+    }
+
+    /// Find the most recently updated tool with `name` owned by `service_id`.
+    pub async fn find_tool_by_name(
+        db: &Surreal<Any>,
+        service_id: &RecordId,
+        name: &str,
+    ) -> Result<Option<ToolRecord>> {
+        let mut res = db
+            .query(
+                r#"
+                SELECT * FROM tool
+                WHERE service_id = $service_id AND name = $name
+                ORDER BY updated_at DESC
+                LIMIT 1
+                "#,
+            )
+            .bind(("service_id", service_id.clone()))
+            .bind(("name", name.to_string()))
+            .await?;
+
+        let tool: Option<ToolRecord> = res.take(0)?;
+        Ok(tool)
+    }
+
+    /// Apply a JSON merge patch to a tool's metadata.
+    ///
+    /// Keys set to `null` in `patch` are removed. Returns the updated metadata,
+    /// or `None` if no tool has the given ID.
+    pub async fn patch_tool_metadata(
+        db: &Surreal<Any>,
+        tool_id: &str,
+        patch: &JsonObject,
+    ) -> Result<Option<JsonObject>> {
+        let tool: Option<ToolRecord> = db
+            .query("SELECT * FROM type::thing($id)")
+            .bind(("id", tool_id.to_string()))
+            .await?
+            .take(0)?;
+        let Some(tool) = tool else {
+            return Ok(None);
+        };
+
+        let mut metadata = tool.metadata;
+        merge_metadata(&mut metadata, patch);
+
+        db.query("UPDATE $id SET metadata = $metadata, updated_at = time::now()")
+            .bind(("id", tool.id))
+            .bind(("metadata", Value::Object(metadata.clone())))
+            .await?
+            .check()?;
+
+        Ok(Some(metadata))
+    }
+
+    /// Stream changes to the `tool` table into `tx` using a `LIVE SELECT`.
+    ///
+    /// Live notifications only carry the new state of a record, so the previous
+    /// state reported in [`ToolChangeEvent::Updated`] comes from a snapshot of
+    /// the table that is kept current as events arrive. The subscription ends
+    /// when the handle is dropped or `tx`'s receiver is closed.
+    ///
+    /// The subscription runs in a `tool_live_query` span carrying the
+    /// correlation ID of the request that opened it.
+    pub async fn subscribe_tool_changes(
+        db: &Surreal<Any>,
+        tx: mpsc::Sender<ToolChangeEvent>,
+    ) -> Result<LiveQueryHandle> {
+        let span = tracing::info_span!(
+            "tool_live_query",
+            correlation_id = current_correlation_id().unwrap_or_default(),
+        );
+
+        // Start listening before taking the snapshot so no change is missed
+        let (mut stream, existing) = async {
+            let stream = db.select::<Vec<ToolRecord>>("tool").live().await?;
+            let existing: Vec<ToolRecord> = db.query("SELECT * FROM tool").await?.take(0)?;
+            tracing::debug!(tools = existing.len(), "Tool live query started");
+            anyhow::Ok((stream, existing))
+        }
+        .instrument(span.clone())
+        .await?;
+        let mut known: HashMap<String, ToolRecord> = existing
+            .into_iter()
+            .map(|tool| (tool.id.to_string(), tool))
+            .collect();
+
+        let task = tokio::spawn(
+            async move {
+                while let Some(notification) = stream.next().await {
+                    let Notification { action, data, .. } = match notification {
+                        Ok(notification) => notification,
+                        Err(e) => {
+                            tracing::warn!("Tool live query error: {}", e);
+                            continue;
+                        }
+                    };
+                    let key = data.id.to_string();
+                    let event = match action {
+                        Action::Create => {
+                            known.insert(key, data.clone());
+                            ToolChangeEvent::Created(data)
+                        }
+                        Action::Update => {
+                            let old = known
+                                .insert(key, data.clone())
+                                .unwrap_or_else(|| data.clone());
+                            ToolChangeEvent::Updated { old, new: data }
+                        }
+                        Action::Delete => {
+                            known.remove(&key);
+                            ToolChangeEvent::Deleted(data.id)
+                        }
+                        _ => continue,
+                    };
+                    if tx.send(event).await.is_err() {
+                        break;
+                    }
+                }
+                tracing::debug!("Tool live query ended");
+            }
+            .instrument(span),
+        );
+
+        Ok(LiveQueryHandle { task })
     }
 
     /// Find a tool by its ID.
@@ -108,11 +438,109 @@ impl QueryBuilder {
         Ok(tool)
     }
 
+    /// Length of the vectors stored for `model`, or `None` if none are stored yet.
+    pub async fn embedding_dimension(db: &Surreal<Any>, model: &str) -> Result<Option<usize>> {
+        let mut res = db
+            .query(
+                r#"
+                SELECT VALUE array::len(vector) FROM embedding
+                WHERE model = $model
+                LIMIT 1
+                "#,
+            )
+            .bind(("model", model.to_string()))
+            .await?;
+
+        let len: Option<usize> = res.take(0)?;
+        Ok(len)
+    }
+
+    /// Embedding vectors of the given tools, keyed by tool id.
+    ///
+    /// Tools without a stored embedding are left out.
+    pub async fn tool_embedding_vectors(
+        db: &Surreal<Any>,
+        tool_ids: &[RecordId],
+    ) -> Result<HashMap<String, Vec<f32>>> {
+        #[derive(Deserialize)]
+        struct ToolVector {
+            id: RecordId,
+            vector: Option<Vec<f32>>,
+        }
+
+        let mut res = db
+            .query(
+                r#"
+                SELECT id, embedding_id.vector AS vector FROM tool
+                WHERE id IN $ids
+                "#,
+            )
+            .bind(("ids", tool_ids.to_vec()))
+            .await?;
+
+        let rows: Vec<ToolVector> = res.take(0)?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| Some((row.id.to_string(), row.vector?)))
+            .collect())
+    }
+
+    /// Embedding vectors stored for `model`, with the service of their tool.
+    ///
+    /// Blocked tools and tools without an embedding are left out.
+    pub async fn service_tool_embedding_vectors(
+        db: &Surreal<Any>,
+        model: &str,
+    ) -> Result<Vec<(RecordId, Vec<f32>)>> {
+        #[derive(Deserialize)]
+        struct ServiceVector {
+            service_id: RecordId,
+            vector: Option<Vec<f32>>,
+        }
+
+        let mut res = db
+            .query(
+                r#"
+                SELECT service_id, embedding_id.vector AS vector FROM tool
+                WHERE embedding_id.model = $model AND status != $blocked
+                "#,
+            )
+            .bind(("model", model.to_string()))
+            .bind(("blocked", ToolStatus::Blocked))
+            .await?;
+
+        let rows: Vec<ServiceVector> = res.take(0)?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| Some((row.service_id, row.vector?)))
+            .collect())
+    }
+
+    /// Number of tools stored per service, keyed by service id.
+    pub async fn tool_counts_by_service(db: &Surreal<Any>) -> Result<HashMap<String, usize>> {
+        #[derive(Deserialize)]
+        struct ServiceToolCount {
+            service_id: RecordId,
+            count: usize,
+        }
+
+        let mut res = db
+            .query("SELECT service_id, count() AS count FROM tool GROUP BY service_id")
+            .await?;
+        let rows: Vec<ServiceToolCount> = res.take(0)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.service_id.to_string(), row.count))
+            .collect())
+    }
+
     /// Find tools by vector similarity against the `embedding` table.
     ///
     /// Returns `(ToolRecord, similarity_score)` tuples.
     pub async fn find_tools_by_embedding(
         db: &Surreal<Any>,
+        model: &str,
         query_vector: Vec<f32>,
         limit: u32,
         threshold: f32,
@@ -131,11 +559,13 @@ impl QueryBuilder {
                     id,
                     vector::similarity::cosine(vector, $query_vec) AS score
                 FROM embedding
-                WHERE vector::similarity::cosine(vector, $query_vec) >= $threshold
+                WHERE model = $model
+                    AND vector::similarity::cosine(vector, $query_vec) >= $threshold
                 ORDER BY score DESC
                 LIMIT $limit
                 "#,
             )
+            .bind(("model", model.to_string()))
             .bind(("query_vec", query_vector))
             .bind(("threshold", threshold))
             .bind(("limit", limit as i64))
@@ -164,6 +594,215 @@ impl QueryBuilder {
         Ok(results)
     }
 
+    /// Find tools by vector similarity against their translated descriptions.
+    ///
+    /// Only embeddings tagged with one of `languages` are searched. A tool
+    /// matching several tags is returned once, with its best score.
+    pub async fn find_tools_by_language_embedding(
+        db: &Surreal<Any>,
+        model: &str,
+        languages: &[String],
+        query_vector: Vec<f32>,
+        limit: u32,
+        threshold: f32,
+    ) -> Result<Vec<(ToolRecord, f32)>> {
+        #[derive(Deserialize)]
+        struct LanguageHit {
+            tool: RecordId,
+            score: f32,
+        }
+
+        let mut res = db
+            .query(
+                r#"
+                SELECT
+                    tool,
+                    vector::similarity::cosine(vector, $query_vec) AS score
+                FROM tool_embedding_by_lang
+                WHERE model = $model
+                    AND language IN $languages
+                    AND vector::similarity::cosine(vector, $query_vec) >= $threshold
+                ORDER BY score DESC
+                LIMIT $limit
+                "#,
+            )
+            .bind(("model", model.to_string()))
+            .bind(("languages", languages.to_vec()))
+            .bind(("query_vec", query_vector))
+            .bind(("threshold", threshold))
+            .bind(("limit", limit as i64))
+            .await?;
+
+        let mut hits: Vec<LanguageHit> = res.take(0)?;
+        let mut seen = HashSet::new();
+        hits.retain(|hit| seen.insert(hit.tool.to_string()));
+
+        let ids: Vec<RecordId> = hits.iter().map(|hit| hit.tool.clone()).collect();
+        let tools: Vec<ToolRecord> = db
+            .query("SELECT * FROM tool WHERE id IN $ids")
+            .bind(("ids", ids))
+            .await?
+            .take(0)?;
+
+        Ok(hits
+            .into_iter()
+            .filter_map(|hit| {
+                let tool = tools.iter().find(|tool| tool.id == hit.tool)?;
+                Some((tool.clone(), hit.score))
+            })
+            .collect())
+    }
+
+    /// Content hashes of the translated-description embeddings from `model`,
+    /// keyed by `(tool id, language)`.
+    pub async fn language_embedding_hashes(
+        db: &Surreal<Any>,
+        model: &str,
+    ) -> Result<HashMap<(String, String), String>> {
+        #[derive(Deserialize)]
+        struct Row {
+            tool: RecordId,
+            language: String,
+            content_hash: String,
+        }
+
+        let mut res = db
+            .query(
+                "SELECT tool, language, content_hash FROM tool_embedding_by_lang WHERE model = $model",
+            )
+            .bind(("model", model.to_string()))
+            .await?;
+        let rows: Vec<Row> = res.take(0)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| ((row.tool.to_string(), row.language), row.content_hash))
+            .collect())
+    }
+
+    /// Store the embedding of a tool's description in `language`, replacing
+    /// any earlier one from the same model.
+    pub async fn store_language_embedding(
+        db: &Surreal<Any>,
+        tool_id: &RecordId,
+        language: &str,
+        model: &str,
+        content_hash: String,
+        vector: Vec<f32>,
+    ) -> Result<()> {
+        db.query(
+            r#"
+            BEGIN TRANSACTION;
+            DELETE tool_embedding_by_lang
+                WHERE tool = $tool AND language = $language AND model = $model;
+            CREATE tool_embedding_by_lang SET
+                tool = $tool,
+                language = $language,
+                model = $model,
+                content_hash = $hash,
+                vector = $vector;
+            COMMIT TRANSACTION;
+            "#,
+        )
+        .bind(("tool", tool_id.clone()))
+        .bind(("language", language.to_string()))
+        .bind(("model", model.to_string()))
+        .bind(("hash", content_hash))
+        .bind(("vector", vector))
+        .await?
+        .check()?;
+        Ok(())
+    }
+
+    /// Delete a tool's translated-description embeddings for languages it no
+    /// longer lists.
+    pub async fn delete_stale_language_embeddings(
+        db: &Surreal<Any>,
+        tool_id: &RecordId,
+        languages: &[String],
+    ) -> Result<()> {
+        db.query(
+            "DELETE tool_embedding_by_lang WHERE tool = $tool AND language NOTINSIDE $languages",
+        )
+        .bind(("tool", tool_id.clone()))
+        .bind(("languages", languages.to_vec()))
+        .await?
+        .check()?;
+        Ok(())
+    }
+
+    /// Content hashes that already have an embedding from `model`.
+    pub async fn embedded_content_hashes(db: &Surreal<Any>, model: &str) -> Result<Vec<String>> {
+        let mut res = db
+            .query("SELECT VALUE content_hash FROM embedding WHERE model = $model")
+            .bind(("model", model.to_string()))
+            .await?;
+        Ok(res.take(0)?)
+    }
+
+    /// Unlink tools from embeddings made by a model other than `model`, so
+    /// the next `update_tool_embeddings` embeds them again. Returns the
+    /// number of tools unlinked.
+    pub async fn clear_stale_tool_embeddings(db: &Surreal<Any>, model: &str) -> Result<usize> {
+        let mut res = db
+            .query(
+                r#"
+                UPDATE tool SET embedding_id = NONE
+                WHERE embedding_id != NONE AND embedding_id.model != $model
+                RETURN id
+                "#,
+            )
+            .bind(("model", model.to_string()))
+            .await?
+            .check()?;
+        let cleared: Vec<RecordId> = res.take((0, "id"))?;
+        Ok(cleared.len())
+    }
+
+    /// Vector search over the embeddings `model` made of the given content
+    /// hashes. Returns `(content_hash, score)` pairs, best first.
+    pub async fn find_embeddings_by_model(
+        db: &Surreal<Any>,
+        model: &str,
+        content_hashes: &[String],
+        query_vector: Vec<f32>,
+        limit: u32,
+        threshold: f32,
+    ) -> Result<Vec<(String, f32)>> {
+        #[derive(Deserialize)]
+        struct EmbeddingHit {
+            content_hash: String,
+            score: f32,
+        }
+
+        let mut res = db
+            .query(
+                r#"
+                SELECT
+                    content_hash,
+                    vector::similarity::cosine(vector, $query_vec) AS score
+                FROM embedding
+                WHERE model = $model
+                    AND content_hash IN $hashes
+                    AND vector::similarity::cosine(vector, $query_vec) >= $threshold
+                ORDER BY score DESC
+                LIMIT $limit
+                "#,
+            )
+            .bind(("model", model.to_string()))
+            .bind(("hashes", content_hashes.to_vec()))
+            .bind(("query_vec", query_vector))
+            .bind(("threshold", threshold))
+            .bind(("limit", limit as i64))
+            .await?;
+
+        let hits: Vec<EmbeddingHit> = res.take(0)?;
+        Ok(hits
+            .into_iter()
+            .map(|hit| (hit.content_hash, hit.score))
+            .collect())
+    }
+
     /// Find tools that could form a simple one-hop chain from a start tool
     /// to some target output type.
     ///
@@ -245,10 +884,15 @@ impl QueryBuilder {
     /// search. This can be evolved as `ToolSearchQuery` matures.
     pub async fn search_tools_semantically(
         db: &Surreal<Any>,
-        _query: &ToolSearchQuery,
+        query: &ToolSearchQuery,
     ) -> Result<ToolSearchResult> {
-        let mut res = db.query("SELECT * FROM tool LIMIT 50").await?;
+        let mut res = db.query("SELECT * FROM tool").await?;
         let tools: Vec<ToolRecord> = res.take(0)?;
+        let tools: Vec<ToolRecord> = tools
+            .into_iter()
+            .filter(|tool| tool.metadata_contains(&query.metadata_contains))
+            .take(50)
+            .collect();
         let total_count = tools.len() as u64;
 
         Ok(ToolSearchResult {
@@ -280,273 +924,1324 @@ impl QueryBuilder {
         Ok(())
     }
 
-    /// Create a new compatibility edge between two tools.
-    pub async fn create_compatibility_edge(
+    /// Refresh the stored tools of a service from a fresh `list_tools` result.
+    ///
+    /// Existing tools (matched by service and name) have their description and
+    /// schemas replaced, their metadata merged and their status cleared; tools that were not seen before
+    /// are created. Returns the number of tools refreshed or created.
+    pub async fn refresh_service_tools(
         db: &Surreal<Any>,
-        from_tool: &RecordId,
-        to_tool: &RecordId,
-        compatibility_type: CompatibilityType,
-        confidence: f32,
-        reasoning: Option<String>,
-    ) -> Result<ToolCompatibility> {
-        let mut res = db
-            .query(
+        service_id: &RecordId,
+        tools: &[CreateToolRecord],
+    ) -> Result<usize> {
+        for tool in tools {
+            let Some(existing) = Self::find_tool_by_name(db, service_id, &tool.name).await? else {
+                Self::upsert_tool(db, tool).await?;
+                continue;
+            };
+            let mut metadata = existing.metadata;
+            merge_metadata(&mut metadata, &tool.metadata);
+
+            let input_ty = JsonSchemaResolver::typed_schema(&tool.input_schema);
+            let output_ty = tool
+                .output_schema
+                .as_ref()
+                .map(JsonSchemaResolver::typed_schema);
+
+            db.query(
                 r#"
-                CREATE tool_compatibility SET
-                    in = $in,
-                    out = $out,
-                    compatibility_type = $ctype,
-                    confidence = $confidence,
-                    reasoning = $reasoning,
-                    created_at = time::now()
-                "#,
+                    UPDATE tool
+                    SET description = $description,
+                        input_schema = $input_schema,
+                        output_schema = $output_schema,
+                        input_ty = $input_ty,
+                        output_ty = $output_ty,
+                        metadata = $metadata,
+                        input_examples = $input_examples,
+                        changelog = $changelog,
+                        deprecation_notice = $deprecation_notice,
+                        descriptions = $descriptions,
+                        inject_user_context = $inject_user_context,
+                        geo_availability = $geo_availability,
+                        required_env_vars = $required_env_vars,
+                        sla_class = $sla_class,
+                        cost_per_call = $cost_per_call,
+                        cache_ttl_seconds = $cache_ttl_seconds,
+                        required_role = $required_role,
+                        status = NONE,
+                        block_reason = NONE,
+                        updated_at = time::now(),
+                        last_seen_at = time::now()
+                    WHERE service_id = $service_id AND name = $name
+                    "#,
             )
-            .bind(("in", from_tool.clone()))
-            .bind(("out", to_tool.clone()))
-            .bind(("ctype", compatibility_type))
-            .bind(("confidence", confidence))
-            .bind(("reasoning", reasoning))
-            .await?;
+            .bind(("service_id", service_id.clone()))
+            .bind(("name", tool.name.clone()))
+            .bind(("description", tool.description.clone()))
+            .bind(("input_schema", Value::Object(tool.input_schema.clone())))
+            .bind(("output_schema", tool.output_schema.clone()))
+            .bind(("input_ty", input_ty))
+            .bind(("output_ty", output_ty))
+            .bind(("metadata", Value::Object(metadata)))
+            .bind(("input_examples", stored_input_examples(tool)))
+            .bind(("changelog", stored_changelog(tool)))
+            .bind(("deprecation_notice", tool.deprecation_notice.clone()))
+            .bind(("descriptions", tool.descriptions.clone()))
+            .bind(("inject_user_context", tool.inject_user_context.clone()))
+            .bind(("geo_availability", tool.geo_availability.clone()))
+            .bind(("required_env_vars", tool.required_env_vars.clone()))
+            .bind(("sla_class", tool.sla_class))
+            .bind(("cost_per_call", tool.cost_per_call))
+            .bind(("cache_ttl_seconds", tool.cache_ttl_seconds))
+            .bind(("required_role", tool.required_role.clone()))
+            .await?
+            .check()?;
+        }
 
-        let created: Option<ToolCompatibility> = res.take(0)?;
-        created.ok_or_else(|| anyhow!("failed to create compatibility edge"))
+        Ok(tools.len())
     }
 
-    /// Get all manifests for a given registry.
-    pub async fn get_registry_manifests(
+    /// Set (or clear, with `None`) the status of every tool owned by a
+    /// service, clearing any block reason.
+    pub async fn set_service_tools_status(
         db: &Surreal<Any>,
-        registry_id: &RecordId,
-    ) -> Result<Vec<ManifestRecord>> {
-        let mut res = db
-            .query(
-                r#"
-                SELECT * FROM manifest
-                WHERE registry_id = $registry_id
+        service_id: &RecordId,
+        status: Option<ToolStatus>,
+    ) -> Result<()> {
+        db.query(
+            r#"
+                UPDATE tool
+                SET status = $status,
+                    block_reason = NONE,
+                    updated_at = time::now()
+                WHERE service_id = $service_id
                 "#,
-            )
-            .bind(("registry_id", registry_id.clone()))
+        )
+        .bind(("service_id", service_id.clone()))
+        .bind(("status", status))
+        .await?;
+
+        Ok(())
+    }
+
+    /// Block every tool owned by a service for `reason`.
+    pub async fn block_service_tools(
+        db: &Surreal<Any>,
+        service_id: &RecordId,
+        reason: BlockReason,
+    ) -> Result<()> {
+        db.query(
+            r#"
+                UPDATE tool
+                SET status = $status,
+                    block_reason = $reason,
+                    updated_at = time::now()
+                WHERE service_id = $service_id
+                "#,
+        )
+        .bind(("service_id", service_id.clone()))
+        .bind(("status", ToolStatus::Blocked))
+        .bind(("reason", reason))
+        .await?
+        .check()?;
+
+        Ok(())
+    }
+
+    /// Block a single tool for `reason`.
+    pub async fn block_tool(
+        db: &Surreal<Any>,
+        tool_id: &RecordId,
+        reason: BlockReason,
+    ) -> Result<()> {
+        db.query(
+            "UPDATE $id SET status = $status, block_reason = $reason, updated_at = time::now()",
+        )
+        .bind(("id", tool_id.clone()))
+        .bind(("status", ToolStatus::Blocked))
+        .bind(("reason", reason))
+        .await?
+        .check()?;
+
+        Ok(())
+    }
+
+    /// Make a blocked tool available again. Returns the updated tool, or
+    /// `None` if no tool has the given ID.
+    pub async fn unblock_tool(db: &Surreal<Any>, tool_id: &str) -> Result<Option<ToolRecord>> {
+        let tool: Option<ToolRecord> = db
+            .query("SELECT * FROM type::thing($id)")
+            .bind(("id", tool_id.to_string()))
+            .await?
+            .take(0)?;
+        let Some(tool) = tool else {
+            return Ok(None);
+        };
+
+        let mut res = db
+            .query("UPDATE $id SET status = NONE, block_reason = NONE, updated_at = time::now()")
+            .bind(("id", tool.id))
             .await?;
 
-        let manifests: Vec<ManifestRecord> = res.take(0)?;
-        Ok(manifests)
+        let tool: Option<ToolRecord> = res.take(0)?;
+        Ok(tool)
     }
 
-    /// Sync a manifest into the database, creating a new record.
+    /// One page of tools ordered by id, starting after `after_id`.
     ///
-    /// This is deliberately simple for now: it always creates a new manifest
-    /// row. In the future, this can be upgraded to use UPSERT semantics keyed
-    /// by (registry_id, hash) or similar.
-    pub async fn sync_manifest_to_db(
+    /// Keyset pagination keeps pages stable while tools are added or removed.
+    /// Returns the page and the id to pass as `after_id` for the next one,
+    /// `None` on the last page.
+    pub async fn paginated_tools(
         db: &Surreal<Any>,
-        registry_id: &RecordId,
-        manifest_content: &Value,
-        hash: &str,
-    ) -> Result<ManifestRecord> {
-        // Try to extract a name and version from the manifest content if present.
-        let name = manifest_content
-            .get("name")
-            .and_then(|v| v.as_str())
-            .unwrap_or("unknown")
-            .to_string();
+        after_id: Option<RecordId>,
+        page_size: usize,
+        filter: ToolFilter,
+    ) -> Result<(Vec<ToolRecord>, Option<RecordId>)> {
+        let mut conditions = Vec::new();
+        if after_id.is_some() {
+            conditions.push("id > $after_id");
+        }
+        if filter.service_id.is_some() {
+            conditions.push("service_id = $service_id");
+        }
+        if filter.source.is_some() {
+            // Tools stored before sources were tracked came from static config
+            conditions.push("(discovery_source.source ?? 'static_config') = $source");
+        }
+        let where_clause = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!(" WHERE {}", conditions.join(" AND "))
+        };
 
-        let version = manifest_content
-            .get("version")
-            .and_then(|v| v.as_str())
-            .unwrap_or("0.0.0")
-            .to_string();
+        // Fetch one extra tool to learn whether another page follows
+        let mut res = db
+            .query(format!(
+                "SELECT * FROM tool{} ORDER BY id ASC LIMIT $size",
+                where_clause
+            ))
+            .bind(("after_id", after_id))
+            .bind(("service_id", filter.service_id))
+            .bind(("source", filter.source))
+            .bind(("size", page_size + 1))
+            .await?;
+
+        let mut tools: Vec<ToolRecord> = res.take(0)?;
+        let next = if tools.len() > page_size {
+            tools.truncate(page_size);
+            tools.last().map(|tool| tool.id.clone())
+        } else {
+            None
+        };
+        Ok((tools, next))
+    }
+
+    /// Every blocked tool, ordered by name.
+    pub async fn list_blocked_tools(db: &Surreal<Any>) -> Result<Vec<ToolRecord>> {
+        let mut res = db
+            .query("SELECT * FROM tool WHERE status = $status ORDER BY name")
+            .bind(("status", ToolStatus::Blocked))
+            .await?;
+
+        let tools: Vec<ToolRecord> = res.take(0)?;
+        Ok(tools)
+    }
+
+    /// Delete tools that were last seen before `cutoff` and never used, along
+    /// with their compatibility and sequence edges, the embeddings no other
+    /// tool shares and services left without tools that weren't updated since
+    /// `cutoff` either.
+    ///
+    /// Tools discovered before `last_seen_at` existed fall back to
+    /// `updated_at`. With `dry_run` nothing is deleted and the counts describe
+    /// what would be.
+    pub async fn prune_stale_records(
+        db: &Surreal<Any>,
+        cutoff: Datetime,
+        dry_run: bool,
+    ) -> Result<PruneResult> {
+        #[derive(Deserialize)]
+        struct StaleTool {
+            id: RecordId,
+            service_id: RecordId,
+            embedding_id: Option<RecordId>,
+        }
 
         let mut res = db
             .query(
                 r#"
-                CREATE manifest SET
-                    registry_id = $registry_id,
-                    name = $name,
-                    version = $version,
-                    content = $content,
-                    hash = $hash,
-                    is_active = true,
-                    created_at = time::now()
+                SELECT id, service_id, embedding_id FROM tool
+                WHERE (last_seen_at ?? updated_at) < $cutoff AND usage_count == 0
                 "#,
             )
-            .bind(("registry_id", registry_id.clone()))
-            .bind(("name", name))
-            .bind(("version", version))
-            .bind(("content", manifest_content.clone()))
-            .bind(("hash", hash.to_string()))
+            .bind(("cutoff", cutoff.clone()))
             .await?;
+        let stale: Vec<StaleTool> = res.take(0)?;
+        let tool_ids: Vec<RecordId> = stale.iter().map(|tool| tool.id.clone()).collect();
+        let stale_services: Vec<RecordId> =
+            stale.iter().map(|tool| tool.service_id.clone()).collect();
 
-        let created: Option<ManifestRecord> = res.take(0)?;
-        created.ok_or_else(|| anyhow!("failed to create manifest record"))
-    }
-
-    // =========================================================================
-    // API Key Management
-    // =========================================================================
-
-    /// Find an API key by its hash.
-    pub async fn find_api_key_by_hash(
-        db: &Surreal<Any>,
-        key_hash: &str,
-    ) -> Result<Option<ApiKeyRecord>> {
+        // A service goes once none of its tools are left, either because they
+        // were all stale or because it never had any for a while
         let mut res = db
             .query(
                 r#"
-                SELECT * FROM api_key
-                WHERE key_hash = $key_hash
-                LIMIT 1
+                LET $kept = SELECT service_id, embedding_id FROM tool WHERE id NOTINSIDE $tool_ids;
+                SELECT VALUE id FROM service
+                WHERE id NOTINSIDE $kept.service_id
+                    AND (id INSIDE $stale_services OR updated_at < $cutoff);
+                SELECT VALUE embedding_id FROM $kept WHERE embedding_id != NONE;
                 "#,
             )
-            .bind(("key_hash", key_hash.to_string()))
+            .bind(("tool_ids", tool_ids.clone()))
+            .bind(("stale_services", stale_services))
+            .bind(("cutoff", cutoff))
             .await?;
+        let service_ids: Vec<RecordId> = res.take(1)?;
+        let kept_embeddings: Vec<RecordId> = res.take(2)?;
+
+        // Duplicate tool records share embeddings with the same content hash
+        let kept_embeddings: HashSet<String> =
+            kept_embeddings.iter().map(ToString::to_string).collect();
+        let mut embedding_ids: Vec<RecordId> = Vec::new();
+        for id in stale.into_iter().filter_map(|tool| tool.embedding_id) {
+            if !kept_embeddings.contains(&id.to_string()) && !embedding_ids.contains(&id) {
+                embedding_ids.push(id);
+            }
+        }
 
-        let api_key: Option<ApiKeyRecord> = res.take(0)?;
-        Ok(api_key)
+        let result = PruneResult {
+            tools_removed: tool_ids.len(),
+            services_removed: service_ids.len(),
+            embeddings_removed: embedding_ids.len(),
+        };
+        if dry_run || result == PruneResult::default() {
+            return Ok(result);
+        }
+
+        db.query(
+            r#"
+            BEGIN TRANSACTION;
+            DELETE tool_compatibility WHERE in INSIDE $tool_ids OR out INSIDE $tool_ids;
+            DELETE tool_sequence WHERE in INSIDE $tool_ids OR out INSIDE $tool_ids;
+            DELETE tool_embedding_by_lang WHERE tool INSIDE $tool_ids;
+            DELETE tool WHERE id INSIDE $tool_ids;
+            DELETE embedding WHERE id INSIDE $embedding_ids;
+            DELETE service WHERE id INSIDE $service_ids;
+            COMMIT TRANSACTION;
+            "#,
+        )
+        .bind(("tool_ids", tool_ids))
+        .bind(("embedding_ids", embedding_ids))
+        .bind(("service_ids", service_ids))
+        .await?
+        .check()?;
+
+        Ok(result)
     }
 
-    /// Find an API key by its prefix.
-    pub async fn find_api_key_by_prefix(
+    /// Write an entry to the audit log.
+    pub async fn create_audit_log(db: &Surreal<Any>, entry: AuditLogCreate) -> Result<()> {
+        db.query(
+            r#"
+                CREATE audit_log CONTENT {
+                    user_id: $user_id,
+                    action: $action,
+                    resource_type: $resource_type,
+                    resource_id: $resource_id,
+                    details: $details,
+                    ip_address: $ip_address,
+                    user_agent: $user_agent
+                }
+                "#,
+        )
+        .bind(("user_id", entry.user_id))
+        .bind(("action", entry.action))
+        .bind(("resource_type", entry.resource_type))
+        .bind(("resource_id", entry.resource_id))
+        .bind(("details", entry.details))
+        .bind(("ip_address", entry.ip_address))
+        .bind(("user_agent", entry.user_agent))
+        .await?;
+
+        Ok(())
+    }
+
+    /// Record a tool invocation for argument history.
+    pub async fn record_tool_usage(
         db: &Surreal<Any>,
-        key_prefix: &str,
-    ) -> Result<Option<ApiKeyRecord>> {
+        tool_id: &RecordId,
+        user_id: &ExternalUserId,
+        arguments: &JsonObject,
+        success: bool,
+        duration_ms: u64,
+    ) -> Result<()> {
+        db.query(
+            r#"
+                CREATE tool_usage_event CONTENT {
+                    tool_id: $tool_id,
+                    user_id: $user_id,
+                    arguments: $arguments,
+                    success: $success,
+                    duration_ms: $duration_ms
+                }
+                "#,
+        )
+        .bind(("tool_id", tool_id.clone()))
+        .bind(("user_id", user_id.to_string()))
+        .bind(("arguments", arguments.clone()))
+        .bind(("success", success))
+        .bind(("duration_ms", duration_ms))
+        .await?
+        .check()?;
+
+        Ok(())
+    }
+
+    /// Timed tool invocations recorded in the last `window`, oldest first.
+    pub async fn list_timed_tool_usage(
+        db: &Surreal<Any>,
+        window: std::time::Duration,
+    ) -> Result<Vec<ToolUsageEventRecord>> {
+        let since = Datetime::from(chrono::Utc::now() - chrono::Duration::from_std(window)?);
         let mut res = db
             .query(
                 r#"
-                SELECT * FROM api_key
-                WHERE key_prefix = $key_prefix
-                LIMIT 1
+                SELECT * FROM tool_usage_event
+                WHERE created_at >= $since AND duration_ms != NONE
+                ORDER BY created_at ASC
                 "#,
             )
-            .bind(("key_prefix", key_prefix.to_string()))
+            .bind(("since", since))
             .await?;
 
-        let api_key: Option<ApiKeyRecord> = res.take(0)?;
-        Ok(api_key)
+        let events: Vec<ToolUsageEventRecord> = res.take(0)?;
+        Ok(events)
     }
 
-    /// Create a new API key record.
-    pub async fn create_api_key(db: &Surreal<Any>, data: &ApiKeyCreate) -> Result<ApiKeyRecord> {
+    /// Store the measured latency and error rate of a tool.
+    pub async fn update_tool_sla_stats(
+        db: &Surreal<Any>,
+        tool_id: &str,
+        latency_p99_ms: u32,
+        error_rate_pct: f32,
+    ) -> Result<()> {
+        db.query(
+            "UPDATE type::thing($id) SET actual_latency_p99_ms = $p99, actual_error_rate_pct = $error_rate",
+        )
+        .bind(("id", tool_id.to_string()))
+        .bind(("p99", latency_p99_ms))
+        .bind(("error_rate", error_rate_pct))
+        .await?
+        .check()?;
+
+        Ok(())
+    }
+
+    /// List a user's most recent successful invocations of a tool, newest first.
+    pub async fn list_successful_tool_usage(
+        db: &Surreal<Any>,
+        tool_id: &RecordId,
+        user_id: &ExternalUserId,
+        limit: usize,
+    ) -> Result<Vec<ToolUsageEventRecord>> {
         let mut res = db
             .query(
                 r#"
-                CREATE api_key SET
-                    key_hash = $key_hash,
-                    key_prefix = $key_prefix,
-                    user_id = $user_id,
-                    name = $name,
-                    is_active = true,
-                    expires_at = $expires_at,
-                    scopes = $scopes,
-                    created_at = time::now(),
-                    last_used_at = NONE
+                SELECT * FROM tool_usage_event
+                WHERE tool_id = $tool_id AND user_id = $user_id AND success = true
+                ORDER BY created_at DESC
+                LIMIT $limit
                 "#,
             )
-            .bind(("key_hash", data.key_hash.to_string()))
-            .bind(("key_prefix", data.key_prefix.to_string()))
-            .bind(("user_id", data.user_id.clone()))
-            .bind(("name", data.name.clone()))
-            .bind(("expires_at", data.expires_at.clone()))
-            .bind(("scopes", data.scopes.clone()))
+            .bind(("tool_id", tool_id.clone()))
+            .bind(("user_id", user_id.to_string()))
+            .bind(("limit", limit))
             .await?;
 
-        let created: Option<ApiKeyRecord> = res.take(0)?;
-        created.ok_or_else(|| anyhow!("failed to create API key record"))
+        let events: Vec<ToolUsageEventRecord> = res.take(0)?;
+        Ok(events)
     }
 
-    /// Update the last_used_at timestamp for an API key.
-    pub async fn update_api_key_last_used(db: &Surreal<Any>, key_id: &RecordId) -> Result<()> {
+    /// Find a service by its record id.
+    pub async fn find_service_by_id(
+        db: &Surreal<Any>,
+        service_id: &RecordId,
+    ) -> Result<Option<ServiceRecord>> {
+        let mut res = db
+            .query("SELECT * FROM service WHERE id = $id LIMIT 1")
+            .bind(("id", service_id.clone()))
+            .await?;
+
+        let service: Option<ServiceRecord> = res.take(0)?;
+        Ok(service)
+    }
+
+    /// Store a downloaded service icon, replacing any previous one, and clear
+    /// the service's `icon_fetch_failed` flag.
+    pub async fn store_service_icon(
+        db: &Surreal<Any>,
+        service_id: &RecordId,
+        data: Vec<u8>,
+        mime_type: &str,
+    ) -> Result<()> {
         db.query(
             r#"
-                UPDATE api_key
-                SET last_used_at = time::now()
-                WHERE id = $id
+                BEGIN TRANSACTION;
+                DELETE service_icon WHERE service_id = $service_id;
+                CREATE service_icon CONTENT {
+                    service_id: $service_id,
+                    data: $data,
+                    mime_type: $mime_type
+                };
+                UPDATE $service_id SET icon_fetch_failed = false;
+                COMMIT TRANSACTION;
                 "#,
         )
-        .bind(("id", key_id.clone()))
-        .await?;
+        .bind(("service_id", service_id.clone()))
+        .bind(("data", Bytes::from(data)))
+        .bind(("mime_type", mime_type.to_string()))
+        .await?
+        .check()?;
 
         Ok(())
     }
 
-    /// Deactivate (revoke) an API key.
-    pub async fn deactivate_api_key(db: &Surreal<Any>, key_id: &RecordId) -> Result<()> {
+    /// Get the cached icon for a service, if one was downloaded.
+    pub async fn get_service_icon(
+        db: &Surreal<Any>,
+        service_id: &RecordId,
+    ) -> Result<Option<ServiceIconRecord>> {
+        let mut res = db
+            .query("SELECT * FROM service_icon WHERE service_id = $service_id LIMIT 1")
+            .bind(("service_id", service_id.clone()))
+            .await?;
+
+        let icon: Option<ServiceIconRecord> = res.take(0)?;
+        Ok(icon)
+    }
+
+    /// Flag a service whose icons could not be downloaded.
+    pub async fn mark_service_icon_fetch_failed(
+        db: &Surreal<Any>,
+        service_id: &RecordId,
+    ) -> Result<()> {
+        db.query("UPDATE $service_id SET icon_fetch_failed = true")
+            .bind(("service_id", service_id.clone()))
+            .await?
+            .check()?;
+
+        Ok(())
+    }
+
+    /// Record a forwarded `prompts/get` request.
+    pub async fn record_prompt_usage(
+        db: &Surreal<Any>,
+        service_id: &ServiceId,
+        prompt_name: &str,
+        arguments: &JsonObject,
+        success: bool,
+    ) -> Result<()> {
         db.query(
             r#"
-                UPDATE api_key
-                SET is_active = false
-                WHERE id = $id
+                CREATE prompt_usage_event CONTENT {
+                    service_id: $service_id,
+                    prompt_name: $prompt_name,
+                    arguments: $arguments,
+                    success: $success
+                }
                 "#,
         )
-        .bind(("id", key_id.clone()))
-        .await?;
+        .bind(("service_id", service_id.to_string()))
+        .bind(("prompt_name", prompt_name.to_string()))
+        .bind(("arguments", arguments.clone()))
+        .bind(("success", success))
+        .await?
+        .check()?;
 
         Ok(())
     }
 
-    /// Deactivate (revoke) an API key by its prefix.
-    pub async fn deactivate_api_key_by_prefix(db: &Surreal<Any>, key_prefix: &str) -> Result<bool> {
+    /// List the most recent successful requests for a prompt, newest first.
+    pub async fn list_successful_prompt_usage(
+        db: &Surreal<Any>,
+        service_id: &ServiceId,
+        prompt_name: &str,
+        limit: usize,
+    ) -> Result<Vec<PromptUsageEventRecord>> {
         let mut res = db
             .query(
                 r#"
-                UPDATE api_key
-                SET is_active = false
-                WHERE key_prefix = $key_prefix
-                RETURN AFTER
+                SELECT * FROM prompt_usage_event
+                WHERE service_id = $service_id AND prompt_name = $prompt_name AND success = true
+                ORDER BY created_at DESC
+                LIMIT $limit
                 "#,
             )
-            .bind(("key_prefix", key_prefix.to_string()))
+            .bind(("service_id", service_id.to_string()))
+            .bind(("prompt_name", prompt_name.to_string()))
+            .bind(("limit", limit))
             .await?;
 
-        let updated: Option<ApiKeyRecord> = res.take(0)?;
-        Ok(updated.is_some())
+        let events: Vec<PromptUsageEventRecord> = res.take(0)?;
+        Ok(events)
     }
 
-    /// List all API keys (for administrative purposes).
-    /// Returns keys sorted by creation date, most recent first.
-    pub async fn list_api_keys(db: &Surreal<Any>) -> Result<Vec<ApiKeyRecord>> {
+    /// Record feedback on a tool selection.
+    pub async fn create_selection_feedback(
+        db: &Surreal<Any>,
+        feedback: SelectionFeedbackCreate,
+    ) -> Result<SelectionFeedbackRecord> {
         let mut res = db
             .query(
                 r#"
-                SELECT * FROM api_key
-                ORDER BY created_at DESC
+                CREATE selection_feedback CONTENT {
+                    user_id: $user_id,
+                    session_id: $session_id,
+                    query: $query,
+                    query_embedding: $query_embedding,
+                    selected_tool_id: $selected_tool_id,
+                    rejected_tool_ids: $rejected_tool_ids,
+                    outcome: $outcome
+                }
                 "#,
             )
+            .bind(("user_id", feedback.user_id))
+            .bind(("session_id", feedback.session_id))
+            .bind(("query", feedback.query))
+            .bind(("query_embedding", feedback.query_embedding))
+            .bind(("selected_tool_id", feedback.selected_tool_id))
+            .bind(("rejected_tool_ids", feedback.rejected_tool_ids))
+            .bind(("outcome", feedback.outcome))
             .await?;
 
-        let api_keys: Vec<ApiKeyRecord> = res.take(0)?;
-        Ok(api_keys)
+        let created: Option<SelectionFeedbackRecord> = res.take(0)?;
+        created.ok_or_else(|| anyhow!("failed to create selection feedback"))
     }
 
-    /// List active API keys only.
-    pub async fn list_active_api_keys(db: &Surreal<Any>) -> Result<Vec<ApiKeyRecord>> {
+    /// Number of selection feedback entries `user_id` recorded in the last hour.
+    pub async fn count_recent_selection_feedback(db: &Surreal<Any>, user_id: &str) -> Result<u64> {
+        #[derive(Deserialize)]
+        struct Count {
+            count: u64,
+        }
+
         let mut res = db
             .query(
                 r#"
-                SELECT * FROM api_key
-                WHERE is_active = true
-                ORDER BY created_at DESC
+                SELECT count() AS count FROM selection_feedback
+                WHERE user_id = $user_id AND created_at > time::now() - 1h
+                GROUP ALL
                 "#,
             )
+            .bind(("user_id", user_id.to_string()))
             .await?;
 
-        let api_keys: Vec<ApiKeyRecord> = res.take(0)?;
-        Ok(api_keys)
+        let count: Option<Count> = res.take(0)?;
+        Ok(count.map_or(0, |c| c.count))
     }
-}
 
-#[cfg(test)]
+    /// List the most recent selection feedback, newest first.
+    pub async fn list_selection_feedback(
+        db: &Surreal<Any>,
+        limit: usize,
+    ) -> Result<Vec<SelectionFeedbackRecord>> {
+        let mut res = db
+            .query(
+                r#"
+                SELECT * FROM selection_feedback
+                ORDER BY created_at DESC
+                LIMIT $limit
+                "#,
+            )
+            .bind(("limit", limit))
+            .await?;
+
+        let feedback: Vec<SelectionFeedbackRecord> = res.take(0)?;
+        Ok(feedback)
+    }
+
+    /// Create a new compatibility edge between two tools.
+    pub async fn create_compatibility_edge(
+        db: &Surreal<Any>,
+        from_tool: &RecordId,
+        to_tool: &RecordId,
+        compatibility_type: CompatibilityType,
+        confidence: f32,
+        reasoning: Option<String>,
+    ) -> Result<ToolCompatibility> {
+        let mut res = db
+            .query(
+                r#"
+                CREATE tool_compatibility SET
+                    in = $in,
+                    out = $out,
+                    compatibility_type = $ctype,
+                    confidence = $confidence,
+                    reasoning = $reasoning,
+                    created_at = time::now()
+                "#,
+            )
+            .bind(("in", from_tool.clone()))
+            .bind(("out", to_tool.clone()))
+            .bind(("ctype", compatibility_type))
+            .bind(("confidence", confidence))
+            .bind(("reasoning", reasoning))
+            .await?;
+
+        let created: Option<ToolCompatibility> = res.take(0)?;
+        created.ok_or_else(|| anyhow!("failed to create compatibility edge"))
+    }
+
+    /// Get all manifests for a given registry.
+    pub async fn get_registry_manifests(
+        db: &Surreal<Any>,
+        registry_id: &RecordId,
+    ) -> Result<Vec<ManifestRecord>> {
+        let mut res = db
+            .query(
+                r#"
+                SELECT * FROM manifest
+                WHERE registry_id = $registry_id
+                "#,
+            )
+            .bind(("registry_id", registry_id.clone()))
+            .await?;
+
+        let manifests: Vec<ManifestRecord> = res.take(0)?;
+        Ok(manifests)
+    }
+
+    /// Sync a manifest into the database, creating a new record.
+    ///
+    /// This is deliberately simple for now: it always creates a new manifest
+    /// row. In the future, this can be upgraded to use UPSERT semantics keyed
+    /// by (registry_id, hash) or similar.
+    pub async fn sync_manifest_to_db(
+        db: &Surreal<Any>,
+        registry_id: &RecordId,
+        manifest_content: &Value,
+        hash: &str,
+    ) -> Result<ManifestRecord> {
+        // Try to extract a name and version from the manifest content if present.
+        let name = manifest_content
+            .get("name")
+            .and_then(|v| v.as_str())
+            .unwrap_or("unknown")
+            .to_string();
+
+        let version = manifest_content
+            .get("version")
+            .and_then(|v| v.as_str())
+            .unwrap_or("0.0.0")
+            .to_string();
+
+        let mut res = db
+            .query(
+                r#"
+                CREATE manifest SET
+                    registry_id = $registry_id,
+                    name = $name,
+                    version = $version,
+                    content = $content,
+                    hash = $hash,
+                    is_active = true,
+                    created_at = time::now()
+                "#,
+            )
+            .bind(("registry_id", registry_id.clone()))
+            .bind(("name", name))
+            .bind(("version", version))
+            .bind(("content", manifest_content.clone()))
+            .bind(("hash", hash.to_string()))
+            .await?;
+
+        let created: Option<ManifestRecord> = res.take(0)?;
+        created.ok_or_else(|| anyhow!("failed to create manifest record"))
+    }
+
+    // =========================================================================
+    // API Key Management
+    // =========================================================================
+
+    /// Find an API key by its hash.
+    pub async fn find_api_key_by_hash(
+        db: &Surreal<Any>,
+        key_hash: &str,
+    ) -> Result<Option<ApiKeyRecord>> {
+        let mut res = db
+            .query(
+                r#"
+                SELECT * FROM api_key
+                WHERE key_hash = $key_hash
+                LIMIT 1
+                "#,
+            )
+            .bind(("key_hash", key_hash.to_string()))
+            .await?;
+
+        let api_key: Option<ApiKeyRecord> = res.take(0)?;
+        Ok(api_key)
+    }
+
+    /// Find an API key by its prefix.
+    pub async fn find_api_key_by_prefix(
+        db: &Surreal<Any>,
+        key_prefix: &str,
+    ) -> Result<Option<ApiKeyRecord>> {
+        let mut res = db
+            .query(
+                r#"
+                SELECT * FROM api_key
+                WHERE key_prefix = $key_prefix
+                LIMIT 1
+                "#,
+            )
+            .bind(("key_prefix", key_prefix.to_string()))
+            .await?;
+
+        let api_key: Option<ApiKeyRecord> = res.take(0)?;
+        Ok(api_key)
+    }
+
+    /// Create a new API key record.
+    pub async fn create_api_key(db: &Surreal<Any>, data: &ApiKeyCreate) -> Result<ApiKeyRecord> {
+        let mut res = db
+            .query(
+                r#"
+                CREATE api_key SET
+                    key_hash = $key_hash,
+                    key_prefix = $key_prefix,
+                    user_id = $user_id,
+                    name = $name,
+                    is_active = true,
+                    expires_at = $expires_at,
+                    scopes = $scopes,
+                    created_at = time::now(),
+                    last_used_at = NONE
+                "#,
+            )
+            .bind(("key_hash", data.key_hash.to_string()))
+            .bind(("key_prefix", data.key_prefix.to_string()))
+            .bind(("user_id", data.user_id.clone()))
+            .bind(("name", data.name.clone()))
+            .bind(("expires_at", data.expires_at.clone()))
+            .bind(("scopes", data.scopes.clone()))
+            .await?;
+
+        let created: Option<ApiKeyRecord> = res.take(0)?;
+        created.ok_or_else(|| anyhow!("failed to create API key record"))
+    }
+
+    /// In one transaction, deactivate the active key with `old_key_hash` and
+    /// create a key with the given prefix and hash for `user_id`, copying the
+    /// old key's name, expiry and scopes.
+    ///
+    /// Returns `None`, changing nothing, if no active key has `old_key_hash`.
+    pub async fn rotate_api_key(
+        db: &Surreal<Any>,
+        old_key_hash: &ApiKeyHash,
+        user_id: &RecordId,
+        key_prefix: &ApiKeyPrefix,
+        key_hash: &ApiKeyHash,
+    ) -> Result<Option<ApiKeyRecord>> {
+        let mut res = db
+            .query(
+                r#"
+                BEGIN TRANSACTION;
+                LET $old = (
+                    UPDATE api_key SET is_active = false
+                    WHERE key_hash = $old_key_hash AND is_active = true
+                    RETURN BEFORE
+                );
+                IF array::len($old) > 0 {
+                    CREATE api_key SET
+                        key_hash = $key_hash,
+                        key_prefix = $key_prefix,
+                        user_id = $user_id,
+                        name = $old[0].name,
+                        is_active = true,
+                        expires_at = $old[0].expires_at,
+                        scopes = $old[0].scopes,
+                        created_at = time::now(),
+                        last_used_at = NONE
+                };
+                SELECT * FROM api_key WHERE key_hash = $key_hash;
+                COMMIT TRANSACTION;
+                "#,
+            )
+            .bind(("old_key_hash", old_key_hash.to_string()))
+            .bind(("user_id", user_id.clone()))
+            .bind(("key_prefix", key_prefix.to_string()))
+            .bind(("key_hash", key_hash.to_string()))
+            .await?
+            .check()?;
+
+        // Statements: LET, IF, SELECT of the new key
+        let created: Option<ApiKeyRecord> = res.take(2)?;
+        Ok(created)
+    }
+
+    /// Update the last_used_at timestamp for an API key.
+    pub async fn update_api_key_last_used(db: &Surreal<Any>, key_id: &RecordId) -> Result<()> {
+        db.query(
+            r#"
+                UPDATE api_key
+                SET last_used_at = time::now()
+                WHERE id = $id
+                "#,
+        )
+        .bind(("id", key_id.clone()))
+        .await?;
+
+        Ok(())
+    }
+
+    /// Deactivate (revoke) an API key.
+    pub async fn deactivate_api_key(db: &Surreal<Any>, key_id: &RecordId) -> Result<()> {
+        db.query(
+            r#"
+                UPDATE api_key
+                SET is_active = false
+                WHERE id = $id
+                "#,
+        )
+        .bind(("id", key_id.clone()))
+        .await?;
+
+        Ok(())
+    }
+
+    /// Deactivate (revoke) an API key by its prefix.
+    pub async fn deactivate_api_key_by_prefix(db: &Surreal<Any>, key_prefix: &str) -> Result<bool> {
+        let mut res = db
+            .query(
+                r#"
+                UPDATE api_key
+                SET is_active = false
+                WHERE key_prefix = $key_prefix
+                RETURN AFTER
+                "#,
+            )
+            .bind(("key_prefix", key_prefix.to_string()))
+            .await?;
+
+        let updated: Option<ApiKeyRecord> = res.take(0)?;
+        Ok(updated.is_some())
+    }
+
+    /// List all API keys (for administrative purposes).
+    /// Returns keys sorted by creation date, most recent first.
+    pub async fn list_api_keys(db: &Surreal<Any>) -> Result<Vec<ApiKeyRecord>> {
+        let mut res = db
+            .query(
+                r#"
+                SELECT * FROM api_key
+                ORDER BY created_at DESC
+                "#,
+            )
+            .await?;
+
+        let api_keys: Vec<ApiKeyRecord> = res.take(0)?;
+        Ok(api_keys)
+    }
+
+    /// List active API keys only.
+    pub async fn list_active_api_keys(db: &Surreal<Any>) -> Result<Vec<ApiKeyRecord>> {
+        let mut res = db
+            .query(
+                r#"
+                SELECT * FROM api_key
+                WHERE is_active = true
+                ORDER BY created_at DESC
+                "#,
+            )
+            .await?;
+
+        let api_keys: Vec<ApiKeyRecord> = res.take(0)?;
+        Ok(api_keys)
+    }
+}
+
+/// Encode a tool id returned by `QueryBuilder::paginated_tools` as an
+/// opaque page cursor.
+pub fn encode_tool_cursor(id: &RecordId) -> String {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(id.to_string())
+}
+
+/// Decode a cursor from `encode_tool_cursor`; `None` if it is malformed.
+pub fn decode_tool_cursor(cursor: &str) -> Option<RecordId> {
+    let bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(cursor)
+        .ok()?;
+    let id: RecordId = String::from_utf8(bytes).ok()?.parse().ok()?;
+    (id.table() == "tool").then_some(id)
+}
+
+#[cfg(test)]
 mod tests {
     use crate::db::connection::DatabaseConfig;
     use crate::db::connection::create_connection;
     use crate::db::{
-        CompatibilityType, CreateToolRecord, QueryBuilder, ServiceCreate, ServiceOrigin,
-        ToolSearchQuery, TypedSchema,
+        BlockReason, ChangelogEntry, CompatibilityType, CreateToolRecord, DeprecationNotice,
+        DiscoverySource, MAX_CHANGELOG_ENTRIES, QueryBuilder, ServiceCreate, ServiceOrigin,
+        ToolChangeEvent, ToolFilter, ToolSearchQuery, TypedSchema, decode_tool_cursor,
+        deprecation_from_meta, encode_tool_cursor, geo_availability_from_meta, language_fallbacks,
+        new_breaking_changes,
     };
+    use crate::knowledge_graph::ToolStatus;
+    use crate::types::ExternalUserId;
     use serde_json::json;
+    use std::collections::HashMap;
     use surrealdb::RecordId;
 
     #[tokio::test]
-    async fn test_upsert_service() {
-        // Create an in-memory database for testing
+    async fn test_upsert_service() {
+        // Create an in-memory database for testing
+        let config = DatabaseConfig {
+            url: "memory".to_string(),
+            namespace: "test".to_string(),
+            database: "test".to_string(),
+            username: None,
+            password: None,
+        };
+        let db = create_connection(config).await.unwrap();
+
+        // Create test data
+        let service_data = ServiceCreate {
+            name: "test_service".to_string(),
+            title: Some("Test Service".to_string()),
+            version: "1.0.0".to_string(),
+            icons: None,
+            website_url: Some("https://example.com".to_string()),
+            origin: ServiceOrigin::StaticConfig,
+            registry_id: None,
+        };
+
+        // Test upsert_service
+        let result = QueryBuilder::upsert_service(&db, &service_data).await;
+        assert!(
+            result.is_ok(),
+            "Failed to upsert service: {:?}",
+            result.err()
+        );
+
+        let service = result.unwrap();
+        assert_eq!(service.name, Some("test_service".to_string()));
+        assert_eq!(service.title, Some("Test Service".to_string()));
+        assert_eq!(service.version, "1.0.0");
+        assert_eq!(service.website_url, Some("https://example.com".to_string()));
+        assert!(matches!(service.origin, ServiceOrigin::StaticConfig));
+        assert!(service.created_at.is_some());
+        assert!(service.updated_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_upsert_tool() {
+        let config = DatabaseConfig {
+            url: "memory".to_string(),
+            namespace: "test".to_string(),
+            database: "test".to_string(),
+            username: None,
+            password: None,
+        };
+        let db = create_connection(config).await.unwrap();
+
+        // First create a service to reference
+        let service_data = ServiceCreate {
+            name: "test_service".to_string(),
+            title: Some("Test Service".to_string()),
+            version: "1.0.0".to_string(),
+            icons: None,
+            website_url: None,
+            origin: ServiceOrigin::StaticConfig,
+            registry_id: None,
+        };
+        let service = QueryBuilder::upsert_service(&db, &service_data)
+            .await
+            .unwrap();
+
+        // Create test tool data
+        let mut input_schema = serde_json::Map::new();
+        input_schema.insert("type".to_string(), json!("string"));
+
+        let mut output_schema = serde_json::Map::new();
+        output_schema.insert("type".to_string(), json!("object"));
+
+        let tool_data = CreateToolRecord {
+            service_id: service.id.clone(),
+            metadata: Default::default(),
+            input_examples: Vec::new(),
+            changelog: Vec::new(),
+            deprecation_notice: None,
+            descriptions: HashMap::new(),
+            inject_user_context: Vec::new(),
+            geo_availability: None,
+            required_env_vars: Vec::new(),
+            sla_class: None,
+            cost_per_call: None,
+            cache_ttl_seconds: None,
+            required_role: None,
+            discovery_source: DiscoverySource::Registry {
+                registry_id: "registry:main".to_string(),
+                manifest_name: "test_service".to_string(),
+            },
+            name: "test_tool".to_string(),
+            description: Some("A test tool".to_string()),
+            input_schema: input_schema.clone(),
+            output_schema: Some(output_schema.clone()),
+            embedding_id: None,
+            input_ty: Some(TypedSchema {
+                schema_type: "string".to_string(),
+                properties: None,
+                items: None,
+                required: None,
+                enum_values: None,
+            }),
+            output_ty: Some(TypedSchema {
+                schema_type: "object".to_string(),
+                properties: None,
+                items: None,
+                required: None,
+                enum_values: None,
+            }),
+        };
+
+        // Test upsert_tool
+        let result = QueryBuilder::upsert_tool(&db, &tool_data).await;
+        assert!(result.is_ok(), "Failed to upsert tool: {:?}", result.err());
+
+        let tool = result.unwrap();
+        assert_eq!(tool.name, "test_tool");
+        assert_eq!(tool.description, Some("A test tool".to_string()));
+        assert_eq!(tool.service_id, service.id);
+        assert_eq!(tool.usage_count, 0);
+        assert_eq!(tool.discovery_source, tool_data.discovery_source);
+        assert!(tool.created_at.is_some());
+        assert!(tool.updated_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_tool_without_discovery_source_defaults_to_static_config() {
+        let config = DatabaseConfig {
+            url: "memory".to_string(),
+            namespace: "test".to_string(),
+            database: "test".to_string(),
+            username: None,
+            password: None,
+        };
+        let db = create_connection(config).await.unwrap();
+
+        db.query(
+            "CREATE tool:legacy SET name = 'legacy', service_id = service:one, input_schema = {}, usage_count = 0",
+        )
+        .await
+        .unwrap();
+
+        let tool = QueryBuilder::find_tool_by_id(&db, RecordId::from(("tool", "legacy")))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(tool.discovery_source, DiscoverySource::default());
+        assert_eq!(tool.discovery_source.kind(), "static_config");
+    }
+
+    #[tokio::test]
+    async fn test_find_tool_by_id() {
+        let config = DatabaseConfig {
+            url: "memory".to_string(),
+            namespace: "test".to_string(),
+            database: "test".to_string(),
+            username: None,
+            password: None,
+        };
+        let db = create_connection(config).await.unwrap();
+
+        // Create a service and tool first
+        let service_data = ServiceCreate {
+            name: "test_service".to_string(),
+            title: None,
+            version: "1.0.0".to_string(),
+            icons: None,
+            website_url: None,
+            origin: ServiceOrigin::StaticConfig,
+            registry_id: None,
+        };
+        let service = QueryBuilder::upsert_service(&db, &service_data)
+            .await
+            .unwrap();
+
+        let mut input_schema = serde_json::Map::new();
+        input_schema.insert("type".to_string(), json!("string"));
+
+        let tool_data = CreateToolRecord {
+            service_id: service.id.clone(),
+            metadata: Default::default(),
+            input_examples: Vec::new(),
+            changelog: Vec::new(),
+            deprecation_notice: None,
+            descriptions: HashMap::new(),
+            inject_user_context: Vec::new(),
+            geo_availability: None,
+            required_env_vars: Vec::new(),
+            sla_class: None,
+            cost_per_call: None,
+            cache_ttl_seconds: None,
+            required_role: None,
+            discovery_source: DiscoverySource::default(),
+            name: "test_tool".to_string(),
+            description: None,
+            input_schema,
+            output_schema: None,
+            embedding_id: None,
+            input_ty: None,
+            output_ty: None,
+        };
+        let created_tool = QueryBuilder::upsert_tool(&db, &tool_data).await.unwrap();
+
+        // Test find_tool_by_id
+        let result = QueryBuilder::find_tool_by_id(&db, created_tool.id.clone()).await;
+        assert!(
+            result.is_ok(),
+            "Failed to find tool by id: {:?}",
+            result.err()
+        );
+
+        let found_tool = result.unwrap();
+        assert!(found_tool.is_some(), "Tool should be found");
+        assert_eq!(found_tool.unwrap().id, created_tool.id);
+    }
+
+    #[tokio::test]
+    async fn test_find_tool_by_id_not_found() {
+        let config = DatabaseConfig {
+            url: "memory".to_string(),
+            namespace: "test".to_string(),
+            database: "test".to_string(),
+            username: None,
+            password: None,
+        };
+        let db = create_connection(config).await.unwrap();
+
+        // Test with non-existent ID
+        let fake_id = RecordId::from(("tool", "nonexistent"));
+        let result = QueryBuilder::find_tool_by_id(&db, fake_id).await;
+        assert!(result.is_ok());
+
+        let found_tool = result.unwrap();
+        assert!(found_tool.is_none(), "Non-existent tool should return None");
+    }
+
+    #[tokio::test]
+    async fn test_find_tools_by_embedding_empty_result() {
+        let config = DatabaseConfig {
+            url: "memory".to_string(),
+            namespace: "test".to_string(),
+            database: "test".to_string(),
+            username: None,
+            password: None,
+        };
+        let db = create_connection(config).await.unwrap();
+
+        // Test with empty database
+        let query_vector = vec![0.1, 0.2, 0.3];
+        let result =
+            QueryBuilder::find_tools_by_embedding(&db, "test-model", query_vector, 10, 0.5).await;
+        assert!(result.is_ok());
+
+        let tools = result.unwrap();
+        assert!(
+            tools.is_empty(),
+            "Should return empty result when no embeddings exist"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_find_embeddings_by_model_filters_model_and_hash() {
+        let config = DatabaseConfig {
+            url: "memory".to_string(),
+            namespace: "test".to_string(),
+            database: "test".to_string(),
+            username: None,
+            password: None,
+        };
+        let db = create_connection(config).await.unwrap();
+        crate::db::ensure_schema(&db).await.unwrap();
+
+        db.query(
+            r#"
+            CREATE embedding SET vector = [1.0, 0.0], model = 'small', content_type = 'tool', content_hash = 'a';
+            CREATE embedding SET vector = [0.6, 0.8], model = 'small', content_type = 'tool', content_hash = 'b';
+            CREATE embedding SET vector = [1.0, 0.0], model = 'small', content_type = 'tool', content_hash = 'gone';
+            CREATE embedding SET vector = [1.0, 0.0, 0.0], model = 'large', content_type = 'tool', content_hash = 'a';
+            "#,
+        )
+        .await
+        .unwrap();
+
+        let hashes = vec!["a".to_string(), "b".to_string()];
+        let hits =
+            QueryBuilder::find_embeddings_by_model(&db, "small", &hashes, vec![1.0, 0.0], 10, 0.5)
+                .await
+                .unwrap();
+        let hit_hashes: Vec<&str> = hits.iter().map(|(hash, _)| hash.as_str()).collect();
+        assert_eq!(hit_hashes, vec!["a", "b"]);
+
+        let mut embedded = QueryBuilder::embedded_content_hashes(&db, "large")
+            .await
+            .unwrap();
+        embedded.sort();
+        assert_eq!(embedded, vec!["a".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_clear_stale_tool_embeddings() {
+        let config = DatabaseConfig {
+            url: "memory".to_string(),
+            namespace: "test".to_string(),
+            database: "test".to_string(),
+            username: None,
+            password: None,
+        };
+        let db = create_connection(config).await.unwrap();
+        crate::db::ensure_schema(&db).await.unwrap();
+
+        db.query(
+            r#"
+            CREATE embedding:old SET vector = [1.0, 0.0], model = 'old-model', content_type = 'tool', content_hash = 'a';
+            CREATE embedding:new SET vector = [1.0, 0.0, 0.0], model = 'new-model', content_type = 'tool', content_hash = 'b';
+            CREATE tool:stale SET service_id = service:s, name = 'stale', input_schema = {}, embedding_id = embedding:old;
+            CREATE tool:fresh SET service_id = service:s, name = 'fresh', input_schema = {}, embedding_id = embedding:new;
+            CREATE tool:unembedded SET service_id = service:s, name = 'unembedded', input_schema = {};
+            "#,
+        )
+        .await
+        .unwrap()
+        .check()
+        .unwrap();
+
+        let cleared = QueryBuilder::clear_stale_tool_embeddings(&db, "new-model")
+            .await
+            .unwrap();
+        assert_eq!(cleared, 1);
+
+        let mut res = db
+            .query("SELECT VALUE name FROM tool WHERE embedding_id = NONE ORDER BY name")
+            .await
+            .unwrap();
+        let unembedded: Vec<String> = res.take(0).unwrap();
+        assert_eq!(unembedded, vec!["stale", "unembedded"]);
+
+        // Nothing left to clear once every embedding is from the current model
+        let cleared = QueryBuilder::clear_stale_tool_embeddings(&db, "new-model")
+            .await
+            .unwrap();
+        assert_eq!(cleared, 0);
+    }
+
+    #[tokio::test]
+    async fn test_embedding_dimension() {
         let config = DatabaseConfig {
             url: "memory".to_string(),
             namespace: "test".to_string(),
@@ -556,37 +2251,319 @@ mod tests {
         };
         let db = create_connection(config).await.unwrap();
 
-        // Create test data
+        let dimension = QueryBuilder::embedding_dimension(&db, "test-model")
+            .await
+            .unwrap();
+        assert_eq!(dimension, None);
+
+        db.query("CREATE embedding SET vector = [0.1, 0.2, 0.3], model = 'test-model'")
+            .await
+            .unwrap();
+
+        let dimension = QueryBuilder::embedding_dimension(&db, "test-model")
+            .await
+            .unwrap();
+        assert_eq!(dimension, Some(3));
+        let other = QueryBuilder::embedding_dimension(&db, "other-model")
+            .await
+            .unwrap();
+        assert_eq!(other, None);
+    }
+
+    #[tokio::test]
+    async fn test_tool_counts_by_service() {
+        let config = DatabaseConfig {
+            url: "memory".to_string(),
+            namespace: "test".to_string(),
+            database: "test".to_string(),
+            username: None,
+            password: None,
+        };
+        let db = create_connection(config).await.unwrap();
+
+        assert!(
+            QueryBuilder::tool_counts_by_service(&db)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        db.query(
+            r#"
+            CREATE tool SET name = 'a', service_id = service:one;
+            CREATE tool SET name = 'b', service_id = service:one;
+            CREATE tool SET name = 'c', service_id = service:two;
+            "#,
+        )
+        .await
+        .unwrap();
+
+        let counts = QueryBuilder::tool_counts_by_service(&db).await.unwrap();
+        assert_eq!(counts.len(), 2);
+        assert_eq!(counts["service:one"], 2);
+        assert_eq!(counts["service:two"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_find_tool_chain_no_start_tool() {
+        let config = DatabaseConfig {
+            url: "memory".to_string(),
+            namespace: "test".to_string(),
+            database: "test".to_string(),
+            username: None,
+            password: None,
+        };
+        let db = create_connection(config).await.unwrap();
+
+        // Test with non-existent start tool
+        let fake_id = RecordId::from(("tool", "nonexistent"));
+        let result = QueryBuilder::find_tool_chain(&db, fake_id, "string".to_string()).await;
+        assert!(result.is_ok());
+
+        let tools = result.unwrap();
+        assert!(
+            tools.is_empty(),
+            "Should return empty result when start tool doesn't exist"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_tool_usage_patterns() {
+        let config = DatabaseConfig {
+            url: "memory".to_string(),
+            namespace: "test".to_string(),
+            database: "test".to_string(),
+            username: None,
+            password: None,
+        };
+        let db = create_connection(config).await.unwrap();
+
+        // Test with empty database
+        let fake_id = RecordId::from(("tool", "nonexistent"));
+        let result = QueryBuilder::get_tool_usage_patterns(&db, &fake_id).await;
+        assert!(result.is_ok());
+
+        let patterns = result.unwrap();
+        assert!(
+            patterns.is_empty(),
+            "Should return empty result when no patterns exist"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search_tools_semantically() {
+        let config = DatabaseConfig {
+            url: "memory".to_string(),
+            namespace: "test".to_string(),
+            database: "test".to_string(),
+            username: None,
+            password: None,
+        };
+        let db = create_connection(config).await.unwrap();
+
+        let query = ToolSearchQuery {
+            text_query: Some("test".to_string()),
+            input_types: None,
+            output_types: None,
+            service_ids: None,
+            metadata_contains: HashMap::new(),
+            min_confidence: None,
+            include_embeddings: false,
+            limit: Some(10),
+            offset: None,
+        };
+
+        // Test search with empty database
+        let result = QueryBuilder::search_tools_semantically(&db, &query).await;
+        assert!(result.is_ok());
+
+        let search_result = result.unwrap();
+        assert_eq!(search_result.total_count, 0);
+        assert!(search_result.tools.is_empty());
+        assert!(search_result.embeddings.is_none());
+        assert_eq!(search_result.search_time_ms, 0);
+    }
+
+    #[tokio::test]
+    async fn test_update_tool_usage() {
+        let config = DatabaseConfig {
+            url: "memory".to_string(),
+            namespace: "test".to_string(),
+            database: "test".to_string(),
+            username: None,
+            password: None,
+        };
+        let db = create_connection(config).await.unwrap();
+
+        // Create a service and tool first
         let service_data = ServiceCreate {
             name: "test_service".to_string(),
-            title: Some("Test Service".to_string()),
+            title: None,
             version: "1.0.0".to_string(),
             icons: None,
-            website_url: Some("https://example.com".to_string()),
+            website_url: None,
             origin: ServiceOrigin::StaticConfig,
             registry_id: None,
         };
+        let service = QueryBuilder::upsert_service(&db, &service_data)
+            .await
+            .unwrap();
 
-        // Test upsert_service
-        let result = QueryBuilder::upsert_service(&db, &service_data).await;
+        let mut input_schema = serde_json::Map::new();
+        input_schema.insert("type".to_string(), json!("string"));
+
+        let tool_data = CreateToolRecord {
+            service_id: service.id.clone(),
+            metadata: Default::default(),
+            input_examples: Vec::new(),
+            changelog: Vec::new(),
+            deprecation_notice: None,
+            descriptions: HashMap::new(),
+            inject_user_context: Vec::new(),
+            geo_availability: None,
+            required_env_vars: Vec::new(),
+            sla_class: None,
+            cost_per_call: None,
+            cache_ttl_seconds: None,
+            required_role: None,
+            discovery_source: DiscoverySource::default(),
+            name: "test_tool".to_string(),
+            description: None,
+            input_schema,
+            output_schema: None,
+            embedding_id: None,
+            input_ty: None,
+            output_ty: None,
+        };
+        let created_tool = QueryBuilder::upsert_tool(&db, &tool_data).await.unwrap();
+
+        // Test update_tool_usage
+        let result = QueryBuilder::update_tool_usage(&db, &created_tool.id, true).await;
         assert!(
             result.is_ok(),
-            "Failed to upsert service: {:?}",
+            "Failed to update tool usage: {:?}",
+            result.err()
+        );
+
+        // Verify the usage count was incremented
+        let updated_tool = QueryBuilder::find_tool_by_id(&db, created_tool.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(updated_tool.usage_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_create_compatibility_edge() {
+        let config = DatabaseConfig {
+            url: "memory".to_string(),
+            namespace: "test".to_string(),
+            database: "test".to_string(),
+            username: None,
+            password: None,
+        };
+        let db = create_connection(config).await.unwrap();
+
+        // Create two tools
+        let service_data = ServiceCreate {
+            name: "test_service".to_string(),
+            title: None,
+            version: "1.0.0".to_string(),
+            icons: None,
+            website_url: None,
+            origin: ServiceOrigin::StaticConfig,
+            registry_id: None,
+        };
+        let service = QueryBuilder::upsert_service(&db, &service_data)
+            .await
+            .unwrap();
+
+        let mut input_schema = serde_json::Map::new();
+        input_schema.insert("type".to_string(), json!("string"));
+
+        let tool1_data = CreateToolRecord {
+            service_id: service.id.clone(),
+            metadata: Default::default(),
+            input_examples: Vec::new(),
+            changelog: Vec::new(),
+            deprecation_notice: None,
+            descriptions: HashMap::new(),
+            inject_user_context: Vec::new(),
+            geo_availability: None,
+            required_env_vars: Vec::new(),
+            sla_class: None,
+            cost_per_call: None,
+            cache_ttl_seconds: None,
+            required_role: None,
+            discovery_source: DiscoverySource::default(),
+            name: "tool1".to_string(),
+            description: None,
+            input_schema: input_schema.clone(),
+            output_schema: None,
+            embedding_id: None,
+            input_ty: None,
+            output_ty: None,
+        };
+        let tool1 = QueryBuilder::upsert_tool(&db, &tool1_data).await.unwrap();
+
+        let tool2_data = CreateToolRecord {
+            service_id: service.id.clone(),
+            metadata: Default::default(),
+            input_examples: Vec::new(),
+            changelog: Vec::new(),
+            deprecation_notice: None,
+            descriptions: HashMap::new(),
+            inject_user_context: Vec::new(),
+            geo_availability: None,
+            required_env_vars: Vec::new(),
+            sla_class: None,
+            cost_per_call: None,
+            cache_ttl_seconds: None,
+            required_role: None,
+            discovery_source: DiscoverySource::default(),
+            name: "tool2".to_string(),
+            description: None,
+            input_schema,
+            output_schema: None,
+            embedding_id: None,
+            input_ty: None,
+            output_ty: None,
+        };
+        let tool2 = QueryBuilder::upsert_tool(&db, &tool2_data).await.unwrap();
+
+        // Test create_compatibility_edge
+        let result = QueryBuilder::create_compatibility_edge(
+            &db,
+            &tool1.id,
+            &tool2.id,
+            CompatibilityType::DataFlow,
+            0.9,
+            Some("Test compatibility".to_string()),
+        )
+        .await;
+        assert!(
+            result.is_ok(),
+            "Failed to create compatibility edge: {:?}",
             result.err()
         );
-
-        let service = result.unwrap();
-        assert_eq!(service.name, Some("test_service".to_string()));
-        assert_eq!(service.title, Some("Test Service".to_string()));
-        assert_eq!(service.version, "1.0.0");
-        assert_eq!(service.website_url, Some("https://example.com".to_string()));
-        assert!(matches!(service.origin, ServiceOrigin::StaticConfig));
-        assert!(service.created_at.is_some());
-        assert!(service.updated_at.is_some());
+
+        let compatibility = result.unwrap();
+        assert_eq!(compatibility.r#in, tool1.id);
+        assert_eq!(compatibility.out, tool2.id);
+        assert!(matches!(
+            compatibility.compatibility_type,
+            CompatibilityType::DataFlow
+        ));
+        assert_eq!(compatibility.confidence, 0.9);
+        assert_eq!(
+            compatibility.reasoning,
+            Some("Test compatibility".to_string())
+        );
+        assert!(compatibility.created_at.is_some());
     }
 
     #[tokio::test]
-    async fn test_upsert_tool() {
+    async fn test_refresh_service_tools_and_status() {
         let config = DatabaseConfig {
             url: "memory".to_string(),
             namespace: "test".to_string(),
@@ -596,10 +2573,9 @@ mod tests {
         };
         let db = create_connection(config).await.unwrap();
 
-        // First create a service to reference
         let service_data = ServiceCreate {
             name: "test_service".to_string(),
-            title: Some("Test Service".to_string()),
+            title: None,
             version: "1.0.0".to_string(),
             icons: None,
             website_url: None,
@@ -610,226 +2586,695 @@ mod tests {
             .await
             .unwrap();
 
-        // Create test tool data
         let mut input_schema = serde_json::Map::new();
-        input_schema.insert("type".to_string(), json!("string"));
-
-        let mut output_schema = serde_json::Map::new();
-        output_schema.insert("type".to_string(), json!("object"));
+        input_schema.insert("type".to_string(), json!("object"));
 
         let tool_data = CreateToolRecord {
             service_id: service.id.clone(),
-            name: "test_tool".to_string(),
-            description: Some("A test tool".to_string()),
+            metadata: Default::default(),
+            input_examples: Vec::new(),
+            changelog: Vec::new(),
+            deprecation_notice: None,
+            descriptions: HashMap::new(),
+            inject_user_context: Vec::new(),
+            geo_availability: None,
+            required_env_vars: Vec::new(),
+            sla_class: None,
+            cost_per_call: None,
+            cache_ttl_seconds: None,
+            required_role: None,
+            discovery_source: DiscoverySource::default(),
+            name: "read_file".to_string(),
+            description: Some("Old description".to_string()),
             input_schema: input_schema.clone(),
-            output_schema: Some(output_schema.clone()),
+            output_schema: None,
             embedding_id: None,
-            input_ty: Some(TypedSchema {
-                schema_type: "string".to_string(),
-                properties: None,
-                items: None,
-                required: None,
-                enum_values: None,
-            }),
-            output_ty: Some(TypedSchema {
-                schema_type: "object".to_string(),
-                properties: None,
-                items: None,
-                required: None,
-                enum_values: None,
-            }),
+            input_ty: None,
+            output_ty: None,
         };
+        let existing = QueryBuilder::upsert_tool(&db, &tool_data).await.unwrap();
+        assert!(existing.status.is_none());
 
-        // Test upsert_tool
-        let result = QueryBuilder::upsert_tool(&db, &tool_data).await;
-        assert!(result.is_ok(), "Failed to upsert tool: {:?}", result.err());
+        // Blocking marks every tool of the service
+        QueryBuilder::set_service_tools_status(&db, &service.id, Some(ToolStatus::Blocked))
+            .await
+            .unwrap();
+        let blocked = QueryBuilder::find_tool_by_id(&db, existing.id.clone())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(blocked.is_blocked());
 
-        let tool = result.unwrap();
-        assert_eq!(tool.name, "test_tool");
-        assert_eq!(tool.description, Some("A test tool".to_string()));
-        assert_eq!(tool.service_id, service.id);
-        assert_eq!(tool.usage_count, 0);
-        assert!(tool.created_at.is_some());
-        assert!(tool.updated_at.is_some());
+        // Refreshing updates the existing tool, clears its status and adds new tools
+        let refreshed = CreateToolRecord {
+            description: Some("New description".to_string()),
+            ..tool_data.clone()
+        };
+        let added = CreateToolRecord {
+            name: "write_file".to_string(),
+            ..tool_data
+        };
+        let count = QueryBuilder::refresh_service_tools(&db, &service.id, &[refreshed, added])
+            .await
+            .unwrap();
+        assert_eq!(count, 2);
+
+        let updated = QueryBuilder::find_tool_by_id(&db, existing.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(updated.description, Some("New description".to_string()));
+        assert!(!updated.is_blocked());
+
+        let all: Vec<crate::db::ToolRecord> = db
+            .query("SELECT * FROM tool WHERE service_id = $sid")
+            .bind(("sid", service.id.clone()))
+            .await
+            .unwrap()
+            .take(0)
+            .unwrap();
+        assert_eq!(all.len(), 2);
     }
 
     #[tokio::test]
-    async fn test_find_tool_by_id() {
+    async fn test_tool_input_examples_from_meta() {
         let config = DatabaseConfig {
             url: "memory".to_string(),
-            namespace: "test".to_string(),
-            database: "test".to_string(),
-            username: None,
-            password: None,
+            ..Default::default()
         };
         let db = create_connection(config).await.unwrap();
+        crate::db::ensure_schema(&db).await.unwrap();
 
-        // Create a service and tool first
-        let service_data = ServiceCreate {
-            name: "test_service".to_string(),
-            title: None,
-            version: "1.0.0".to_string(),
-            icons: None,
-            website_url: None,
-            origin: ServiceOrigin::StaticConfig,
-            registry_id: None,
+        let service = QueryBuilder::upsert_service(
+            &db,
+            &ServiceCreate {
+                name: "geo".to_string(),
+                title: None,
+                version: "1.0.0".to_string(),
+                icons: None,
+                website_url: None,
+                origin: ServiceOrigin::StaticConfig,
+                registry_id: None,
+            },
+        )
+        .await
+        .unwrap();
+
+        let mut examples: Vec<_> = (0..7)
+            .map(|i| json!({ "city": format!("city-{i}") }))
+            .collect();
+        examples.insert(0, json!("not an object"));
+        let mut mcp_tool =
+            rmcp::model::Tool::new("geocode", "Geocode a city", rmcp::model::JsonObject::new());
+        mcp_tool.meta = Some(rmcp::model::Meta(
+            json!({ "examples": examples })
+                .as_object()
+                .cloned()
+                .unwrap(),
+        ));
+
+        let data =
+            CreateToolRecord::from_mcp_tool(&service.id, &mcp_tool, DiscoverySource::default());
+        assert_eq!(data.input_examples.len(), 5);
+        let tool = QueryBuilder::upsert_tool(&db, &data).await.unwrap();
+        assert_eq!(tool.input_examples[0]["city"], json!("city-0"));
+
+        // Rediscovery replaces the stored examples
+        let rediscovered = CreateToolRecord {
+            input_examples: vec![json!({ "city": "Tallinn" }).as_object().cloned().unwrap()],
+            ..data
         };
-        let service = QueryBuilder::upsert_service(&db, &service_data)
+        QueryBuilder::refresh_service_tools(&db, &service.id, &[rediscovered])
+            .await
+            .unwrap();
+        let refreshed = QueryBuilder::find_tool_by_id(&db, tool.id.clone())
             .await
+            .unwrap()
             .unwrap();
+        assert_eq!(refreshed.input_examples.len(), 1);
+        assert_eq!(refreshed.input_examples[0]["city"], json!("Tallinn"));
+    }
 
-        let mut input_schema = serde_json::Map::new();
-        input_schema.insert("type".to_string(), json!("string"));
+    #[tokio::test]
+    async fn test_tool_cache_ttl_from_meta() {
+        let config = DatabaseConfig {
+            url: "memory".to_string(),
+            ..Default::default()
+        };
+        let db = create_connection(config).await.unwrap();
+        crate::db::ensure_schema(&db).await.unwrap();
 
-        let tool_data = CreateToolRecord {
-            service_id: service.id.clone(),
-            name: "test_tool".to_string(),
-            description: None,
-            input_schema,
-            output_schema: None,
-            embedding_id: None,
-            input_ty: None,
-            output_ty: None,
+        let service = QueryBuilder::upsert_service(
+            &db,
+            &ServiceCreate {
+                name: "geo".to_string(),
+                title: None,
+                version: "1.0.0".to_string(),
+                icons: None,
+                website_url: None,
+                origin: ServiceOrigin::StaticConfig,
+                registry_id: None,
+            },
+        )
+        .await
+        .unwrap();
+
+        let tool_with_meta = |meta: serde_json::Value| {
+            let mut mcp_tool =
+                rmcp::model::Tool::new("geocode", "Geocode a city", rmcp::model::JsonObject::new());
+            mcp_tool.meta = Some(rmcp::model::Meta(meta.as_object().cloned().unwrap()));
+            CreateToolRecord::from_mcp_tool(&service.id, &mcp_tool, DiscoverySource::default())
         };
-        let created_tool = QueryBuilder::upsert_tool(&db, &tool_data).await.unwrap();
 
-        // Test find_tool_by_id
-        let result = QueryBuilder::find_tool_by_id(&db, created_tool.id.clone()).await;
-        assert!(
-            result.is_ok(),
-            "Failed to find tool by id: {:?}",
-            result.err()
-        );
+        let data = tool_with_meta(json!({ "cache_ttl_seconds": 300 }));
+        assert_eq!(data.cache_ttl_seconds, Some(300));
+        let tool = QueryBuilder::upsert_tool(&db, &data).await.unwrap();
+        assert_eq!(tool.cache_ttl_seconds, Some(300));
 
-        let found_tool = result.unwrap();
-        assert!(found_tool.is_some(), "Tool should be found");
-        assert_eq!(found_tool.unwrap().id, created_tool.id);
+        // Zero, negative and non-numeric TTLs disable caching
+        for ttl in [json!(0), json!(-5), json!("300")] {
+            let data = tool_with_meta(json!({ "cache_ttl_seconds": ttl }));
+            assert_eq!(data.cache_ttl_seconds, None);
+        }
     }
 
     #[tokio::test]
-    async fn test_find_tool_by_id_not_found() {
+    async fn test_tool_required_role_from_meta() {
         let config = DatabaseConfig {
             url: "memory".to_string(),
-            namespace: "test".to_string(),
-            database: "test".to_string(),
-            username: None,
-            password: None,
+            ..Default::default()
         };
         let db = create_connection(config).await.unwrap();
+        crate::db::ensure_schema(&db).await.unwrap();
 
-        // Test with non-existent ID
-        let fake_id = RecordId::from(("tool", "nonexistent"));
-        let result = QueryBuilder::find_tool_by_id(&db, fake_id).await;
-        assert!(result.is_ok());
+        let service = QueryBuilder::upsert_service(
+            &db,
+            &ServiceCreate {
+                name: "ops".to_string(),
+                title: None,
+                version: "1.0.0".to_string(),
+                icons: None,
+                website_url: None,
+                origin: ServiceOrigin::StaticConfig,
+                registry_id: None,
+            },
+        )
+        .await
+        .unwrap();
+
+        let tool_with_meta = |meta: serde_json::Value| {
+            let mut mcp_tool = rmcp::model::Tool::new(
+                "restart",
+                "Restart a server",
+                rmcp::model::JsonObject::new(),
+            );
+            mcp_tool.meta = Some(rmcp::model::Meta(meta.as_object().cloned().unwrap()));
+            CreateToolRecord::from_mcp_tool(&service.id, &mcp_tool, DiscoverySource::default())
+        };
 
-        let found_tool = result.unwrap();
-        assert!(found_tool.is_none(), "Non-existent tool should return None");
+        let data = tool_with_meta(json!({ "required_role": " admin " }));
+        assert_eq!(data.required_role.as_deref(), Some("admin"));
+        let tool = QueryBuilder::upsert_tool(&db, &data).await.unwrap();
+        assert_eq!(tool.required_role.as_deref(), Some("admin"));
+
+        // Blank and non-string roles are ignored
+        for role in [json!(""), json!(["admin"])] {
+            let data = tool_with_meta(json!({ "required_role": role }));
+            assert_eq!(data.required_role, None);
+        }
     }
 
     #[tokio::test]
-    async fn test_find_tools_by_embedding_empty_result() {
+    async fn test_tool_changelog_from_meta() {
         let config = DatabaseConfig {
             url: "memory".to_string(),
-            namespace: "test".to_string(),
-            database: "test".to_string(),
-            username: None,
-            password: None,
+            ..Default::default()
         };
         let db = create_connection(config).await.unwrap();
+        crate::db::ensure_schema(&db).await.unwrap();
 
-        // Test with empty database
-        let query_vector = vec![0.1, 0.2, 0.3];
-        let result = QueryBuilder::find_tools_by_embedding(&db, query_vector, 10, 0.5).await;
-        assert!(result.is_ok());
+        let service = QueryBuilder::upsert_service(
+            &db,
+            &ServiceCreate {
+                name: "geo".to_string(),
+                title: None,
+                version: "1.0.0".to_string(),
+                icons: None,
+                website_url: None,
+                origin: ServiceOrigin::StaticConfig,
+                registry_id: None,
+            },
+        )
+        .await
+        .unwrap();
+
+        let mut changelog: Vec<_> = (1..=12)
+            .map(|i| {
+                json!({
+                    "version": format!("1.{i}.0"),
+                    "date": format!("2025-01-{i:02}"),
+                    "changes": [format!("Release {i}")],
+                })
+            })
+            .collect();
+        changelog.push(json!({ "version": "no date" }));
+        let mut mcp_tool =
+            rmcp::model::Tool::new("geocode", "Geocode a city", rmcp::model::JsonObject::new());
+        mcp_tool.meta = Some(rmcp::model::Meta(
+            json!({ "changelog": changelog })
+                .as_object()
+                .cloned()
+                .unwrap(),
+        ));
 
-        let tools = result.unwrap();
-        assert!(
-            tools.is_empty(),
-            "Should return empty result when no embeddings exist"
+        let data =
+            CreateToolRecord::from_mcp_tool(&service.id, &mcp_tool, DiscoverySource::default());
+        let tool = QueryBuilder::upsert_tool(&db, &data).await.unwrap();
+        assert_eq!(tool.changelog.len(), MAX_CHANGELOG_ENTRIES);
+        assert_eq!(tool.changelog[0].version, "1.12.0");
+        assert_eq!(tool.changelog[9].version, "1.3.0");
+        assert!(!tool.changelog[0].breaking);
+
+        // A new breaking release is reported once
+        let mut rediscovered = data.clone();
+        rediscovered.changelog.insert(
+            0,
+            ChangelogEntry {
+                version: "2.0.0".to_string(),
+                date: "2025-02-01".to_string(),
+                changes: vec!["Renamed `city` to `query`".to_string()],
+                breaking: true,
+            },
         );
+        QueryBuilder::refresh_service_tools(&db, &service.id, std::slice::from_ref(&rediscovered))
+            .await
+            .unwrap();
+        let refreshed = QueryBuilder::find_tool_by_id(&db, tool.id.clone())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(refreshed.changelog.len(), MAX_CHANGELOG_ENTRIES);
+        let breaking = new_breaking_changes(&tool.changelog, &refreshed.changelog);
+        assert_eq!(breaking.len(), 1);
+        assert_eq!(breaking[0].version, "2.0.0");
+        assert!(new_breaking_changes(&refreshed.changelog, &refreshed.changelog).is_empty());
     }
 
     #[tokio::test]
-    async fn test_find_tool_chain_no_start_tool() {
+    async fn test_upsert_tool_records_schema_history() {
         let config = DatabaseConfig {
             url: "memory".to_string(),
-            namespace: "test".to_string(),
-            database: "test".to_string(),
-            username: None,
-            password: None,
+            ..Default::default()
         };
         let db = create_connection(config).await.unwrap();
+        crate::db::ensure_schema(&db).await.unwrap();
 
-        // Test with non-existent start tool
-        let fake_id = RecordId::from(("tool", "nonexistent"));
-        let result = QueryBuilder::find_tool_chain(&db, fake_id, "string".to_string()).await;
-        assert!(result.is_ok());
+        let service = QueryBuilder::upsert_service(
+            &db,
+            &ServiceCreate {
+                name: "fs".to_string(),
+                title: None,
+                version: "1.0.0".to_string(),
+                icons: None,
+                website_url: None,
+                origin: ServiceOrigin::StaticConfig,
+                registry_id: None,
+            },
+        )
+        .await
+        .unwrap();
+
+        let schema = |required: serde_json::Value| {
+            json!({
+                "type": "object",
+                "properties": {"path": {"type": "string"}, "limit": {"type": "integer"}},
+                "required": required
+            })
+            .as_object()
+            .cloned()
+            .unwrap()
+        };
+        for input_schema in [
+            schema(json!(["path"])),
+            schema(json!(["path"])),
+            schema(json!(["path", "limit"])),
+        ] {
+            let mcp_tool = rmcp::model::Tool::new("read", "Read a file", input_schema);
+            let data =
+                CreateToolRecord::from_mcp_tool(&service.id, &mcp_tool, DiscoverySource::default());
+            QueryBuilder::upsert_tool(&db, &data).await.unwrap();
+        }
 
-        let tools = result.unwrap();
-        assert!(
-            tools.is_empty(),
-            "Should return empty result when start tool doesn't exist"
+        // Rediscovering an unchanged schema doesn't add a version
+        let history = QueryBuilder::list_tool_schema_history(&db, &service.id, "read")
+            .await
+            .unwrap();
+        let versions: Vec<u32> = history.iter().map(|snapshot| snapshot.version).collect();
+        assert_eq!(versions, vec![1, 2]);
+        assert_eq!(history[1].input_schema, schema(json!(["path", "limit"])));
+        assert_eq!(
+            history[1].input_ty.required,
+            Some(vec!["path".to_string(), "limit".to_string()])
         );
     }
 
     #[tokio::test]
-    async fn test_get_tool_usage_patterns() {
+    async fn test_deprecation_notice_from_meta_is_stored_and_cleared() {
         let config = DatabaseConfig {
             url: "memory".to_string(),
-            namespace: "test".to_string(),
-            database: "test".to_string(),
-            username: None,
-            password: None,
+            ..Default::default()
         };
         let db = create_connection(config).await.unwrap();
+        crate::db::ensure_schema(&db).await.unwrap();
 
-        // Test with empty database
-        let fake_id = RecordId::from(("tool", "nonexistent"));
-        let result = QueryBuilder::get_tool_usage_patterns(&db, &fake_id).await;
-        assert!(result.is_ok());
+        let service = QueryBuilder::upsert_service(
+            &db,
+            &ServiceCreate {
+                name: "geo".to_string(),
+                title: None,
+                version: "2.0.0".to_string(),
+                icons: None,
+                website_url: None,
+                origin: ServiceOrigin::StaticConfig,
+                registry_id: None,
+            },
+        )
+        .await
+        .unwrap();
+
+        let mut mcp_tool =
+            rmcp::model::Tool::new("geocode", "Geocode a city", rmcp::model::JsonObject::new());
+        mcp_tool.meta = Some(rmcp::model::Meta(
+            json!({
+                "deprecated": {
+                    "since_version": "2.0.0",
+                    "replacement_tool": "geocode_v2",
+                    "message": "Use structured addresses",
+                }
+            })
+            .as_object()
+            .cloned()
+            .unwrap(),
+        ));
+        let data =
+            CreateToolRecord::from_mcp_tool(&service.id, &mcp_tool, DiscoverySource::default());
+        let tool = QueryBuilder::upsert_tool(&db, &data).await.unwrap();
 
-        let patterns = result.unwrap();
+        let notice = tool.deprecation_notice.unwrap();
+        assert_eq!(notice.replacement_tool.as_deref(), Some("geocode_v2"));
+        assert_eq!(notice.removal_date, None);
+        assert_eq!(
+            notice.warning("geocode"),
+            "Tool `geocode` is deprecated since version 2.0.0: Use structured addresses. \
+             Use `geocode_v2` instead."
+        );
+
+        // A bare flag is a notice without details; dropping it clears the notice
+        let flagged = json!({ "deprecated": true });
+        assert_eq!(
+            deprecation_from_meta(flagged.as_object().unwrap()),
+            Some(DeprecationNotice::default())
+        );
+        let undeprecated = json!({ "deprecated": false });
+        assert_eq!(
+            deprecation_from_meta(undeprecated.as_object().unwrap()),
+            None
+        );
+
+        let mut rediscovered = data.clone();
+        rediscovered.deprecation_notice = None;
+        QueryBuilder::refresh_service_tools(&db, &service.id, std::slice::from_ref(&rediscovered))
+            .await
+            .unwrap();
+        let refreshed = QueryBuilder::find_tool_by_id(&db, tool.id.clone())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(refreshed.deprecation_notice, None);
+    }
+
+    #[tokio::test]
+    async fn test_geo_availability_from_meta() {
+        let config = DatabaseConfig {
+            url: "memory".to_string(),
+            ..Default::default()
+        };
+        let db = create_connection(config).await.unwrap();
+        crate::db::ensure_schema(&db).await.unwrap();
+
+        let service = QueryBuilder::upsert_service(
+            &db,
+            &ServiceCreate {
+                name: "payments".to_string(),
+                title: None,
+                version: "1.0.0".to_string(),
+                icons: None,
+                website_url: None,
+                origin: ServiceOrigin::StaticConfig,
+                registry_id: None,
+            },
+        )
+        .await
+        .unwrap();
+
+        let mut mcp_tool =
+            rmcp::model::Tool::new("pay", "Send a payment", rmcp::model::JsonObject::new());
+        mcp_tool.meta = Some(rmcp::model::Meta(
+            json!({ "geo_availability": ["us", "CA", "USA", 1] })
+                .as_object()
+                .cloned()
+                .unwrap(),
+        ));
+        let data =
+            CreateToolRecord::from_mcp_tool(&service.id, &mcp_tool, DiscoverySource::default());
+        let tool = QueryBuilder::upsert_tool(&db, &data).await.unwrap();
+
+        assert_eq!(
+            tool.geo_availability,
+            Some(vec!["US".to_string(), "CA".to_string()])
+        );
+        assert!(tool.is_available_in(Some("us")));
+        assert!(!tool.is_available_in(Some("DE")));
+        assert!(!tool.is_available_in(None));
+
+        let unrestricted = json!({ "geo_availability": "US" });
+        assert_eq!(
+            geo_availability_from_meta(unrestricted.as_object().unwrap()),
+            None
+        );
+
+        let mut rediscovered = data.clone();
+        rediscovered.geo_availability = None;
+        QueryBuilder::refresh_service_tools(&db, &service.id, std::slice::from_ref(&rediscovered))
+            .await
+            .unwrap();
+        let refreshed = QueryBuilder::find_tool_by_id(&db, tool.id.clone())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(refreshed.geo_availability, None);
+        assert!(refreshed.is_available_in(None));
+    }
+
+    #[tokio::test]
+    async fn test_block_and_unblock_tool() {
+        let config = DatabaseConfig {
+            url: "memory".to_string(),
+            ..Default::default()
+        };
+        let db = create_connection(config).await.unwrap();
+        crate::db::ensure_schema(&db).await.unwrap();
+
+        let service = QueryBuilder::upsert_service(
+            &db,
+            &ServiceCreate {
+                name: "search".to_string(),
+                title: None,
+                version: "1.0.0".to_string(),
+                icons: None,
+                website_url: None,
+                origin: ServiceOrigin::StaticConfig,
+                registry_id: None,
+            },
+        )
+        .await
+        .unwrap();
+
+        let mut mcp_tool =
+            rmcp::model::Tool::new("search", "Search the web", rmcp::model::JsonObject::new());
+        mcp_tool.meta = Some(rmcp::model::Meta(
+            json!({ "required_env_vars": ["SEARCH_API_KEY", " ", 3] })
+                .as_object()
+                .cloned()
+                .unwrap(),
+        ));
+        let data =
+            CreateToolRecord::from_mcp_tool(&service.id, &mcp_tool, DiscoverySource::default());
+        let tool = QueryBuilder::upsert_tool(&db, &data).await.unwrap();
+        assert_eq!(tool.required_env_vars, vec!["SEARCH_API_KEY".to_string()]);
+
+        let reason = BlockReason::MissingEnvVar("SEARCH_API_KEY".to_string());
+        QueryBuilder::block_tool(&db, &tool.id, reason.clone())
+            .await
+            .unwrap();
+        let blocked = QueryBuilder::list_blocked_tools(&db).await.unwrap();
+        assert_eq!(blocked.len(), 1);
+        assert!(blocked[0].is_blocked());
+        assert_eq!(blocked[0].block_reason, Some(reason));
+
+        let unblocked = QueryBuilder::unblock_tool(&db, &tool.id.to_string())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!unblocked.is_blocked());
+        assert_eq!(unblocked.block_reason, None);
         assert!(
-            patterns.is_empty(),
-            "Should return empty result when no patterns exist"
+            QueryBuilder::list_blocked_tools(&db)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            QueryBuilder::unblock_tool(&db, "tool:missing")
+                .await
+                .unwrap()
+                .is_none()
         );
+
+        // Rediscovery clears the reason along with the status
+        QueryBuilder::block_service_tools(&db, &service.id, BlockReason::ServiceUnavailable)
+            .await
+            .unwrap();
+        QueryBuilder::refresh_service_tools(&db, &service.id, std::slice::from_ref(&data))
+            .await
+            .unwrap();
+        let refreshed = QueryBuilder::find_tool_by_id(&db, tool.id.clone())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(refreshed.status.is_none());
+        assert_eq!(refreshed.block_reason, None);
     }
 
     #[tokio::test]
-    async fn test_search_tools_semantically() {
+    async fn test_translated_descriptions_and_language_embedding_search() {
         let config = DatabaseConfig {
             url: "memory".to_string(),
-            namespace: "test".to_string(),
-            database: "test".to_string(),
-            username: None,
-            password: None,
+            ..Default::default()
         };
         let db = create_connection(config).await.unwrap();
+        crate::db::ensure_schema(&db).await.unwrap();
 
-        let query = ToolSearchQuery {
-            text_query: Some("test".to_string()),
-            input_types: None,
-            output_types: None,
-            service_ids: None,
-            min_confidence: None,
-            include_embeddings: false,
-            limit: Some(10),
-            offset: None,
-        };
+        let service = QueryBuilder::upsert_service(
+            &db,
+            &ServiceCreate {
+                name: "weather".to_string(),
+                title: None,
+                version: "1.0.0".to_string(),
+                icons: None,
+                website_url: None,
+                origin: ServiceOrigin::StaticConfig,
+                registry_id: None,
+            },
+        )
+        .await
+        .unwrap();
 
-        // Test search with empty database
-        let result = QueryBuilder::search_tools_semantically(&db, &query).await;
-        assert!(result.is_ok());
+        let mut mcp_tool = rmcp::model::Tool::new(
+            "forecast",
+            "Weather forecast for a city",
+            rmcp::model::JsonObject::new(),
+        );
+        mcp_tool.meta = Some(rmcp::model::Meta(
+            json!({
+                "descriptions": {
+                    "DE": "Wettervorhersage für eine Stadt",
+                    "fr-CA": "Prévisions météo pour une ville",
+                    "ja": 42,
+                }
+            })
+            .as_object()
+            .cloned()
+            .unwrap(),
+        ));
+        let data =
+            CreateToolRecord::from_mcp_tool(&service.id, &mcp_tool, DiscoverySource::default());
+        let tool = QueryBuilder::upsert_tool(&db, &data).await.unwrap();
 
-        let search_result = result.unwrap();
-        assert_eq!(search_result.total_count, 0);
-        assert!(search_result.tools.is_empty());
-        assert!(search_result.embeddings.is_none());
-        assert_eq!(search_result.search_time_ms, 0);
+        let mut languages: Vec<&str> = tool.descriptions.keys().map(String::as_str).collect();
+        languages.sort();
+        assert_eq!(languages, vec!["de", "fr-ca"]);
+
+        QueryBuilder::store_language_embedding(
+            &db,
+            &tool.id,
+            "de",
+            "test-model",
+            "hash-1".to_string(),
+            vec![1.0, 0.0],
+        )
+        .await
+        .unwrap();
+        // Re-storing replaces the earlier embedding
+        QueryBuilder::store_language_embedding(
+            &db,
+            &tool.id,
+            "de",
+            "test-model",
+            "hash-2".to_string(),
+            vec![0.0, 1.0],
+        )
+        .await
+        .unwrap();
+        let hashes = QueryBuilder::language_embedding_hashes(&db, "test-model")
+            .await
+            .unwrap();
+        assert_eq!(
+            hashes.get(&(tool.id.to_string(), "de".to_string())),
+            Some(&"hash-2".to_string())
+        );
+
+        // `de-AT` negotiates down to the `de` embedding
+        assert_eq!(language_fallbacks("de-AT"), vec!["de-at", "de"]);
+        let hits = QueryBuilder::find_tools_by_language_embedding(
+            &db,
+            "test-model",
+            &language_fallbacks("de-AT"),
+            vec![0.0, 1.0],
+            10,
+            0.5,
+        )
+        .await
+        .unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].0.id, tool.id);
+        assert!((hits[0].1 - 1.0).abs() < 1e-6);
+
+        let french = QueryBuilder::find_tools_by_language_embedding(
+            &db,
+            "test-model",
+            &language_fallbacks("fr"),
+            vec![0.0, 1.0],
+            10,
+            0.5,
+        )
+        .await
+        .unwrap();
+        assert!(french.is_empty());
+
+        QueryBuilder::delete_stale_language_embeddings(&db, &tool.id, &["fr-ca".to_string()])
+            .await
+            .unwrap();
+        assert!(
+            QueryBuilder::language_embedding_hashes(&db, "test-model")
+                .await
+                .unwrap()
+                .is_empty()
+        );
     }
 
     #[tokio::test]
-    async fn test_update_tool_usage() {
+    async fn test_tool_metadata_merge_patch_and_filter() {
         let config = DatabaseConfig {
             url: "memory".to_string(),
             namespace: "test".to_string(),
@@ -838,8 +3283,8 @@ mod tests {
             password: None,
         };
         let db = create_connection(config).await.unwrap();
+        crate::db::ensure_schema(&db).await.unwrap();
 
-        // Create a service and tool first
         let service_data = ServiceCreate {
             name: "test_service".to_string(),
             title: None,
@@ -853,118 +3298,232 @@ mod tests {
             .await
             .unwrap();
 
-        let mut input_schema = serde_json::Map::new();
-        input_schema.insert("type".to_string(), json!("string"));
-
         let tool_data = CreateToolRecord {
             service_id: service.id.clone(),
-            name: "test_tool".to_string(),
+            metadata: json!({"cost_tier": "low", "sla": {"class": "gold"}})
+                .as_object()
+                .cloned()
+                .unwrap(),
+            input_examples: Vec::new(),
+            changelog: Vec::new(),
+            deprecation_notice: None,
+            descriptions: HashMap::new(),
+            inject_user_context: Vec::new(),
+            geo_availability: None,
+            required_env_vars: Vec::new(),
+            sla_class: None,
+            cost_per_call: None,
+            cache_ttl_seconds: None,
+            required_role: None,
+            discovery_source: DiscoverySource::default(),
+            name: "geocode".to_string(),
             description: None,
-            input_schema,
+            input_schema: serde_json::Map::new(),
             output_schema: None,
             embedding_id: None,
             input_ty: None,
             output_ty: None,
         };
-        let created_tool = QueryBuilder::upsert_tool(&db, &tool_data).await.unwrap();
-
-        // Test update_tool_usage
-        let result = QueryBuilder::update_tool_usage(&db, &created_tool.id, true).await;
-        assert!(
-            result.is_ok(),
-            "Failed to update tool usage: {:?}",
-            result.err()
+        let tool = QueryBuilder::upsert_tool(&db, &tool_data).await.unwrap();
+        assert_eq!(tool.metadata["cost_tier"], json!("low"));
+
+        // Rediscovery merges `_meta` into what is stored instead of replacing it
+        let rediscovered = CreateToolRecord {
+            metadata: json!({"region": "eu", "sla": {"uptime": 99.9}})
+                .as_object()
+                .cloned()
+                .unwrap(),
+            ..tool_data
+        };
+        QueryBuilder::refresh_service_tools(&db, &service.id, &[rediscovered])
+            .await
+            .unwrap();
+        let refreshed = QueryBuilder::find_tool_by_id(&db, tool.id.clone())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            serde_json::Value::Object(refreshed.metadata),
+            json!({
+                "cost_tier": "low",
+                "region": "eu",
+                "sla": {"class": "gold", "uptime": 99.9},
+            })
         );
 
-        // Verify the usage count was incremented
-        let updated_tool = QueryBuilder::find_tool_by_id(&db, created_tool.id)
+        // A manual patch can remove keys with null
+        let patch = json!({"cost_tier": null, "region": "us"})
+            .as_object()
+            .cloned()
+            .unwrap();
+        let patched = QueryBuilder::patch_tool_metadata(&db, &tool.id.to_string(), &patch)
             .await
             .unwrap()
             .unwrap();
-        assert_eq!(updated_tool.usage_count, 1);
+        assert!(!patched.contains_key("cost_tier"));
+        assert_eq!(patched["region"], json!("us"));
+
+        let missing = QueryBuilder::patch_tool_metadata(&db, "tool:missing", &patch)
+            .await
+            .unwrap();
+        assert!(missing.is_none());
+
+        let search = |key: &str, value: serde_json::Value| ToolSearchQuery {
+            text_query: None,
+            input_types: None,
+            output_types: None,
+            service_ids: None,
+            metadata_contains: HashMap::from([(key.to_string(), value)]),
+            min_confidence: None,
+            include_embeddings: false,
+            limit: None,
+            offset: None,
+        };
+        let found = QueryBuilder::search_tools_semantically(&db, &search("region", json!("us")))
+            .await
+            .unwrap();
+        assert_eq!(found.tools.len(), 1);
+        let found = QueryBuilder::search_tools_semantically(&db, &search("region", json!("eu")))
+            .await
+            .unwrap();
+        assert!(found.tools.is_empty());
     }
 
     #[tokio::test]
-    async fn test_create_compatibility_edge() {
+    async fn test_subscribe_tool_changes() {
         let config = DatabaseConfig {
             url: "memory".to_string(),
-            namespace: "test".to_string(),
-            database: "test".to_string(),
-            username: None,
-            password: None,
+            ..Default::default()
         };
         let db = create_connection(config).await.unwrap();
+        crate::db::ensure_schema(&db).await.unwrap();
 
-        // Create two tools
-        let service_data = ServiceCreate {
-            name: "test_service".to_string(),
-            title: None,
-            version: "1.0.0".to_string(),
-            icons: None,
-            website_url: None,
-            origin: ServiceOrigin::StaticConfig,
-            registry_id: None,
-        };
-        let service = QueryBuilder::upsert_service(&db, &service_data)
+        let service = QueryBuilder::upsert_service(
+            &db,
+            &ServiceCreate {
+                name: "test_service".to_string(),
+                title: None,
+                version: "1.0.0".to_string(),
+                icons: None,
+                website_url: None,
+                origin: ServiceOrigin::StaticConfig,
+                registry_id: None,
+            },
+        )
+        .await
+        .unwrap();
+
+        let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+        let handle = QueryBuilder::subscribe_tool_changes(&db, tx).await.unwrap();
+        async fn next(
+            rx: &mut tokio::sync::mpsc::Receiver<ToolChangeEvent>,
+        ) -> Option<ToolChangeEvent> {
+            tokio::time::timeout(std::time::Duration::from_secs(5), rx.recv())
+                .await
+                .expect("timed out waiting for a tool change")
+        }
+
+        let tool = QueryBuilder::upsert_tool(
+            &db,
+            &CreateToolRecord {
+                service_id: service.id.clone(),
+                metadata: Default::default(),
+                input_examples: Vec::new(),
+                changelog: Vec::new(),
+                deprecation_notice: None,
+                descriptions: HashMap::new(),
+                inject_user_context: Vec::new(),
+                geo_availability: None,
+                required_env_vars: Vec::new(),
+                sla_class: None,
+                cost_per_call: None,
+                cache_ttl_seconds: None,
+                required_role: None,
+                discovery_source: DiscoverySource::default(),
+                name: "read_file".to_string(),
+                description: Some("Old description".to_string()),
+                input_schema: serde_json::Map::new(),
+                output_schema: None,
+                embedding_id: None,
+                input_ty: None,
+                output_ty: None,
+            },
+        )
+        .await
+        .unwrap();
+        match next(&mut rx).await {
+            Some(ToolChangeEvent::Created(created)) => assert_eq!(created.id, tool.id),
+            other => panic!("expected Created, got {other:?}"),
+        }
+
+        db.query("UPDATE $id SET description = 'New description'")
+            .bind(("id", tool.id.clone()))
             .await
             .unwrap();
+        match next(&mut rx).await {
+            Some(ToolChangeEvent::Updated { old, new }) => {
+                assert_eq!(old.description.as_deref(), Some("Old description"));
+                assert_eq!(new.description.as_deref(), Some("New description"));
+            }
+            other => panic!("expected Updated, got {other:?}"),
+        }
 
-        let mut input_schema = serde_json::Map::new();
-        input_schema.insert("type".to_string(), json!("string"));
+        db.query("DELETE $id")
+            .bind(("id", tool.id.clone()))
+            .await
+            .unwrap();
+        match next(&mut rx).await {
+            Some(ToolChangeEvent::Deleted(id)) => assert_eq!(id, tool.id),
+            other => panic!("expected Deleted, got {other:?}"),
+        }
 
-        let tool1_data = CreateToolRecord {
-            service_id: service.id.clone(),
-            name: "tool1".to_string(),
-            description: None,
-            input_schema: input_schema.clone(),
-            output_schema: None,
-            embedding_id: None,
-            input_ty: None,
-            output_ty: None,
-        };
-        let tool1 = QueryBuilder::upsert_tool(&db, &tool1_data).await.unwrap();
+        // Dropping the handle ends the subscription
+        drop(handle);
+        assert!(next(&mut rx).await.is_none());
+    }
 
-        let tool2_data = CreateToolRecord {
-            service_id: service.id.clone(),
-            name: "tool2".to_string(),
-            description: None,
-            input_schema,
-            output_schema: None,
-            embedding_id: None,
-            input_ty: None,
-            output_ty: None,
+    #[tokio::test]
+    async fn test_list_successful_tool_usage() {
+        let config = DatabaseConfig {
+            url: "memory".to_string(),
+            ..Default::default()
         };
-        let tool2 = QueryBuilder::upsert_tool(&db, &tool2_data).await.unwrap();
+        let db = create_connection(config).await.unwrap();
+        crate::db::ensure_schema(&db).await.unwrap();
 
-        // Test create_compatibility_edge
-        let result = QueryBuilder::create_compatibility_edge(
-            &db,
-            &tool1.id,
-            &tool2.id,
-            CompatibilityType::DataFlow,
-            0.9,
-            Some("Test compatibility".to_string()),
-        )
-        .await;
-        assert!(
-            result.is_ok(),
-            "Failed to create compatibility edge: {:?}",
-            result.err()
-        );
+        let tool_id = RecordId::from(("tool", "read_file"));
+        let alice = ExternalUserId::new("alice");
+        let bob = ExternalUserId::new("bob");
+        let args = |path: &str| json!({ "path": path }).as_object().unwrap().clone();
 
-        let compatibility = result.unwrap();
-        assert_eq!(compatibility.r#in, tool1.id);
-        assert_eq!(compatibility.out, tool2.id);
-        assert!(matches!(
-            compatibility.compatibility_type,
-            CompatibilityType::DataFlow
-        ));
-        assert_eq!(compatibility.confidence, 0.9);
-        assert_eq!(
-            compatibility.reasoning,
-            Some("Test compatibility".to_string())
-        );
-        assert!(compatibility.created_at.is_some());
+        QueryBuilder::record_tool_usage(&db, &tool_id, &alice, &args("/a"), true, 120)
+            .await
+            .unwrap();
+        QueryBuilder::record_tool_usage(&db, &tool_id, &alice, &args("/missing"), false, 30)
+            .await
+            .unwrap();
+        QueryBuilder::record_tool_usage(&db, &tool_id, &bob, &args("/b"), true, 80)
+            .await
+            .unwrap();
+
+        let events = QueryBuilder::list_successful_tool_usage(&db, &tool_id, &alice, 10)
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].arguments, args("/a"));
+        assert!(events[0].success);
+        assert_eq!(events[0].duration_ms, Some(120));
+
+        let timed = QueryBuilder::list_timed_tool_usage(&db, std::time::Duration::from_secs(60))
+            .await
+            .unwrap();
+        assert_eq!(timed.len(), 3);
+
+        let other_tool = RecordId::from(("tool", "write_file"));
+        let events = QueryBuilder::list_successful_tool_usage(&db, &other_tool, &alice, 10)
+            .await
+            .unwrap();
+        assert!(events.is_empty());
     }
 
     #[tokio::test]
@@ -1098,4 +3657,185 @@ mod tests {
         };
         assert_eq!(schema.schema_type, "any");
     }
+
+    #[tokio::test]
+    async fn test_prune_stale_records() {
+        let config = DatabaseConfig {
+            url: "memory".to_string(),
+            namespace: "test".to_string(),
+            database: "test".to_string(),
+            username: None,
+            password: None,
+        };
+        let db = create_connection(config).await.unwrap();
+        crate::db::ensure_schema(&db).await.unwrap();
+
+        db.query(
+            r#"
+            CREATE service:gone SET name = 'gone', version = '1', origin = 'static_config';
+            CREATE service:live SET name = 'live', version = '1', origin = 'static_config';
+            CREATE embedding:shared SET vector = [1.0], model = 'm', content_type = 'tool', content_hash = 's';
+            CREATE embedding:own SET vector = [1.0], model = 'm', content_type = 'tool', content_hash = 'o';
+            CREATE tool:stale SET name = 'stale', service_id = service:gone, input_schema = {},
+                embedding_id = embedding:own, last_seen_at = d'2020-01-01T00:00:00Z';
+            CREATE tool:stale_dup SET name = 'stale', service_id = service:gone, input_schema = {},
+                embedding_id = embedding:shared, last_seen_at = d'2020-01-01T00:00:00Z';
+            CREATE tool:used SET name = 'used', service_id = service:live, input_schema = {},
+                usage_count = 3, last_seen_at = d'2020-01-01T00:00:00Z';
+            CREATE tool:fresh SET name = 'fresh', service_id = service:live, input_schema = {},
+                embedding_id = embedding:shared, last_seen_at = time::now();
+            CREATE tool_compatibility SET in = tool:stale, out = tool:fresh, compatibility_type = 'output_to_input';
+            CREATE tool_sequence SET in = tool:used, out = tool:stale, sequence_type = 'common';
+            "#,
+        )
+        .await
+        .unwrap()
+        .check()
+        .unwrap();
+
+        let cutoff =
+            surrealdb::sql::Datetime::from(chrono::Utc::now() - chrono::Duration::days(30));
+        let expected = super::PruneResult {
+            tools_removed: 2,
+            services_removed: 1,
+            embeddings_removed: 1,
+        };
+
+        let preview = QueryBuilder::prune_stale_records(&db, cutoff.clone(), true)
+            .await
+            .unwrap();
+        assert_eq!(preview, expected);
+        let tools: Vec<RecordId> = db
+            .query("SELECT VALUE id FROM tool")
+            .await
+            .unwrap()
+            .take(0)
+            .unwrap();
+        assert_eq!(tools.len(), 4);
+
+        let pruned = QueryBuilder::prune_stale_records(&db, cutoff.clone(), false)
+            .await
+            .unwrap();
+        assert_eq!(pruned, expected);
+
+        let mut res = db
+            .query(
+                r#"
+                SELECT VALUE meta::id(id) FROM tool;
+                SELECT VALUE meta::id(id) FROM service;
+                SELECT VALUE meta::id(id) FROM embedding;
+                count(SELECT * FROM tool_compatibility) + count(SELECT * FROM tool_sequence);
+                "#,
+            )
+            .await
+            .unwrap();
+        let mut tools: Vec<String> = res.take(0).unwrap();
+        tools.sort();
+        let services: Vec<String> = res.take(1).unwrap();
+        let embeddings: Vec<String> = res.take(2).unwrap();
+        let edges: Option<usize> = res.take(3).unwrap();
+        assert_eq!(tools, vec!["fresh", "used"]);
+        assert_eq!(services, vec!["live"]);
+        assert_eq!(embeddings, vec!["shared"]);
+        assert_eq!(edges, Some(0));
+
+        assert_eq!(
+            QueryBuilder::prune_stale_records(&db, cutoff, false)
+                .await
+                .unwrap(),
+            super::PruneResult::default()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_paginated_tools_pages_stay_stable() {
+        let config = DatabaseConfig {
+            url: "memory".to_string(),
+            ..Default::default()
+        };
+        let db = create_connection(config).await.unwrap();
+        crate::db::ensure_schema(&db).await.unwrap();
+
+        let service = QueryBuilder::upsert_service(
+            &db,
+            &ServiceCreate {
+                name: "catalog".to_string(),
+                title: None,
+                version: "1.0.0".to_string(),
+                icons: None,
+                website_url: None,
+                origin: ServiceOrigin::StaticConfig,
+                registry_id: None,
+            },
+        )
+        .await
+        .unwrap();
+        let create = |name: &str, source: DiscoverySource| {
+            let tool =
+                rmcp::model::Tool::new(name.to_string(), "A tool", rmcp::model::JsonObject::new());
+            CreateToolRecord::from_mcp_tool(&service.id, &tool, source)
+        };
+        for i in 0..5 {
+            QueryBuilder::upsert_tool(&db, &create(&format!("tool{}", i), Default::default()))
+                .await
+                .unwrap();
+        }
+
+        let mut seen: Vec<RecordId> = Vec::new();
+        let mut after_id = None;
+        let mut pages = 0;
+        loop {
+            let (page, next) =
+                QueryBuilder::paginated_tools(&db, after_id, 2, ToolFilter::default())
+                    .await
+                    .unwrap();
+            pages += 1;
+            seen.extend(page.into_iter().map(|tool| tool.id));
+            if pages == 1 {
+                // Tools added mid-way don't shift later pages
+                QueryBuilder::upsert_tool(&db, &create("late", Default::default()))
+                    .await
+                    .unwrap();
+            }
+            let Some(next) = next else { break };
+            let cursor = encode_tool_cursor(&next);
+            assert_eq!(decode_tool_cursor(&cursor), Some(next.clone()));
+            after_id = Some(next);
+        }
+
+        let mut sorted = seen.clone();
+        sorted.sort_by_key(|id| id.to_string());
+        sorted.dedup();
+        assert_eq!(seen, sorted, "pages must be ordered without duplicates");
+        let all: Vec<RecordId> = db
+            .query("SELECT VALUE id FROM tool ORDER BY id ASC")
+            .await
+            .unwrap()
+            .take(0)
+            .unwrap();
+        assert!(seen.len() >= 5);
+        assert!(seen.iter().all(|id| all.contains(id)));
+
+        let registry = DiscoverySource::Registry {
+            registry_id: "main".to_string(),
+            manifest_name: "catalog".to_string(),
+        };
+        QueryBuilder::upsert_tool(&db, &create("synced", registry))
+            .await
+            .unwrap();
+        let filter = ToolFilter {
+            source: Some("registry".to_string()),
+            ..Default::default()
+        };
+        let (page, next) = QueryBuilder::paginated_tools(&db, None, 10, filter)
+            .await
+            .unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].name, "synced");
+        assert!(next.is_none());
+
+        assert!(decode_tool_cursor("not a cursor!").is_none());
+        let service_cursor = encode_tool_cursor(&service.id);
+        assert!(decode_tool_cursor(&service_cursor).is_none());
+    }
 }
@@ -0,0 +1,271 @@
+//! `$ref` resolution for JSON Schemas.
+//!
+//! MCP services often factor shared shapes into `$defs` and point at them with
+//! `$ref`. `TypedSchema::from_json_schema` only sees the schema it is given, so
+//! references are inlined first.
+
+use crate::db::schema::TypedSchema;
+use anyhow::{Result, anyhow};
+use rmcp::model::JsonObject;
+use serde_json::{Value, json};
+
+/// Deepest chain of `$ref`s inlined inside one another.
+pub const MAX_REF_DEPTH: usize = 16;
+
+/// Most schema nodes a resolved schema may contain. Inlining copies the
+/// target of every `$ref`, so a few definitions that each refer to the next
+/// several times would otherwise grow the result exponentially.
+pub const MAX_RESOLVED_NODES: usize = 10_000;
+
+/// Inlines local `$ref` pointers in JSON Schemas.
+pub struct JsonSchemaResolver;
+
+impl JsonSchemaResolver {
+    /// Return a copy of `schema` with every `$ref` replaced by its target.
+    ///
+    /// Only local references (`#`, `#/$defs/...`) are supported. A reference
+    /// that points back into a definition that is already being inlined is
+    /// replaced with `{"type": "object"}` to break the cycle. Keywords next to
+    /// a `$ref` override those of its target. The root `$defs`/`definitions`
+    /// are dropped from the result since nothing refers to them any more.
+    ///
+    /// References nested deeper than [`MAX_REF_DEPTH`], or met once the result
+    /// holds [`MAX_RESOLVED_NODES`] nodes, are left in place, and the root
+    /// definitions are then kept so they still resolve.
+    pub fn resolve(schema: &Value) -> Result<Value> {
+        let mut inliner = Inliner {
+            root: schema,
+            stack: Vec::new(),
+            nodes: 0,
+            truncated: false,
+        };
+        let mut resolved = inliner.inline(schema)?;
+        if let Value::Object(map) = &mut resolved
+            && !inliner.truncated
+        {
+            map.remove("$defs");
+            map.remove("definitions");
+        }
+        Ok(resolved)
+    }
+
+    /// Build a `TypedSchema` from `schema` after resolving its references.
+    ///
+    /// Falls back to the unresolved schema if a reference cannot be resolved.
+    pub fn typed_schema(schema: &JsonObject) -> TypedSchema {
+        match Self::resolve(&Value::Object(schema.clone())) {
+            Ok(Value::Object(resolved)) => TypedSchema::from_json_schema(&resolved),
+            Ok(_) => TypedSchema::from_json_schema(schema),
+            Err(e) => {
+                tracing::warn!("Using unresolved schema: {}", e);
+                TypedSchema::from_json_schema(schema)
+            }
+        }
+    }
+}
+
+/// State of one [`JsonSchemaResolver::resolve`] call.
+struct Inliner<'a> {
+    root: &'a Value,
+    /// References being inlined, outermost first
+    stack: Vec<String>,
+    /// Nodes written to the result so far
+    nodes: usize,
+    /// Whether a reference was left in place
+    truncated: bool,
+}
+
+impl<'a> Inliner<'a> {
+    fn inline(&mut self, node: &'a Value) -> Result<Value> {
+        self.nodes += 1;
+        match node {
+            Value::Object(map) => {
+                if let Some(Value::String(reference)) = map.get("$ref") {
+                    if self.stack.contains(reference) {
+                        return Ok(json!({"type": "object"}));
+                    }
+                    if self.stack.len() >= MAX_REF_DEPTH || self.nodes >= MAX_RESOLVED_NODES {
+                        self.truncated = true;
+                        return Ok(node.clone());
+                    }
+
+                    let target = self.lookup(reference)?;
+                    self.stack.push(reference.clone());
+                    let resolved = self.inline(target);
+                    self.stack.pop();
+                    let mut resolved = resolved?;
+
+                    if let Value::Object(resolved_map) = &mut resolved {
+                        for (key, value) in map.iter().filter(|(key, _)| *key != "$ref") {
+                            resolved_map.insert(key.clone(), self.inline(value)?);
+                        }
+                    }
+                    return Ok(resolved);
+                }
+
+                let mut inlined = JsonObject::new();
+                for (key, value) in map {
+                    inlined.insert(key.clone(), self.inline(value)?);
+                }
+                Ok(Value::Object(inlined))
+            }
+            Value::Array(items) => items
+                .iter()
+                .map(|item| self.inline(item))
+                .collect::<Result<Vec<_>>>()
+                .map(Value::Array),
+            other => Ok(other.clone()),
+        }
+    }
+
+    fn lookup(&self, reference: &str) -> Result<&'a Value> {
+        let pointer = reference
+            .strip_prefix('#')
+            .ok_or_else(|| anyhow!("Unsupported non-local $ref `{}`", reference))?;
+        self.root
+            .pointer(pointer)
+            .ok_or_else(|| anyhow!("Unresolvable $ref `{}`", reference))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_inlines_defs() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "page": {"$ref": "#/$defs/Pagination", "description": "Paging"},
+                "tags": {"type": "array", "items": {"$ref": "#/definitions/Tag"}}
+            },
+            "$defs": {
+                "Pagination": {
+                    "type": "object",
+                    "properties": {"limit": {"type": "integer"}},
+                    "description": "Pagination options"
+                }
+            },
+            "definitions": {"Tag": {"type": "string"}}
+        });
+        let original = schema.clone();
+
+        let resolved = JsonSchemaResolver::resolve(&schema).unwrap();
+
+        assert_eq!(schema, original);
+        assert_eq!(
+            resolved,
+            json!({
+                "type": "object",
+                "properties": {
+                    "page": {
+                        "type": "object",
+                        "properties": {"limit": {"type": "integer"}},
+                        "description": "Paging"
+                    },
+                    "tags": {"type": "array", "items": {"type": "string"}}
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_resolve_breaks_cycles() {
+        let schema = json!({
+            "$ref": "#/$defs/Node",
+            "$defs": {
+                "Node": {
+                    "type": "object",
+                    "properties": {
+                        "children": {"type": "array", "items": {"$ref": "#/$defs/Node"}}
+                    }
+                }
+            }
+        });
+
+        let resolved = JsonSchemaResolver::resolve(&schema).unwrap();
+
+        assert_eq!(
+            resolved["properties"]["children"]["items"],
+            json!({"type": "object"})
+        );
+        assert!(resolved.get("$defs").is_none());
+    }
+
+    #[test]
+    fn test_resolve_leaves_deep_refs_in_place() {
+        // D0 -> D1 -> ... -> D19, each referring to the next
+        let defs: serde_json::Map<String, Value> = (0..20)
+            .map(|i| {
+                let def = if i == 19 {
+                    json!({"type": "string"})
+                } else {
+                    json!({"type": "object", "properties": {"next": {"$ref": format!("#/$defs/D{}", i + 1)}}})
+                };
+                (format!("D{}", i), def)
+            })
+            .collect();
+        let schema = json!({"$ref": "#/$defs/D0", "$defs": defs});
+
+        let resolved = JsonSchemaResolver::resolve(&schema).unwrap();
+
+        let mut node = &resolved;
+        for _ in 0..MAX_REF_DEPTH - 1 {
+            node = &node["properties"]["next"];
+        }
+        assert_eq!(
+            node["properties"]["next"],
+            json!({"$ref": format!("#/$defs/D{}", MAX_REF_DEPTH)})
+        );
+        assert!(resolved.get("$defs").is_some());
+    }
+
+    #[test]
+    fn test_resolve_stops_inlining_at_node_budget() {
+        // Each definition refers to the next one four times: 4^12 copies of D12
+        let defs: serde_json::Map<String, Value> = (0..13)
+            .map(|i| {
+                let def = if i == 12 {
+                    json!({"type": "string"})
+                } else {
+                    let next = json!({"$ref": format!("#/$defs/D{}", i + 1)});
+                    json!({"type": "object", "properties": {
+                        "a": next, "b": next, "c": next, "d": next
+                    }})
+                };
+                (format!("D{}", i), def)
+            })
+            .collect();
+        let schema = json!({"$ref": "#/$defs/D0", "$defs": defs});
+
+        let resolved = JsonSchemaResolver::resolve(&schema).unwrap();
+
+        let size = resolved.to_string().len();
+        assert!(size < 10 * schema.to_string().len() + 100 * MAX_RESOLVED_NODES);
+        assert!(resolved.to_string().contains("\"$ref\""));
+        assert!(resolved.get("$defs").is_some());
+    }
+
+    #[test]
+    fn test_resolve_rejects_unknown_refs() {
+        assert!(JsonSchemaResolver::resolve(&json!({"$ref": "#/$defs/Missing"})).is_err());
+        assert!(
+            JsonSchemaResolver::resolve(&json!({"$ref": "https://example.com/s.json"})).is_err()
+        );
+    }
+
+    #[test]
+    fn test_typed_schema_sees_resolved_properties() {
+        let schema = json!({
+            "type": "object",
+            "properties": {"page": {"$ref": "#/$defs/Page"}},
+            "$defs": {"Page": {"type": "integer"}}
+        });
+
+        let typed = JsonSchemaResolver::typed_schema(schema.as_object().unwrap());
+
+        let properties = typed.properties.unwrap();
+        assert_eq!(properties["page"].schema_type, "integer");
+    }
+}
@@ -2,8 +2,12 @@ use rmcp::model::{Icon, JsonObject};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
-use surrealdb::{RecordId, sql::Datetime};
+use surrealdb::{
+    RecordId,
+    sql::{Bytes, Datetime},
+};
 
+use crate::knowledge_graph::{EmbeddingBackendConfig, ToolStatus};
 use crate::types::{ApiKeyHash, ApiKeyPrefix};
 
 /// Persisted representation of an MCP service in SurrealDB.
@@ -25,6 +29,9 @@ pub struct ServiceRecord {
     pub origin: ServiceOrigin,
     /// Optional reference to the registry this service came from.
     pub registry_id: Option<RecordId>,
+    /// Set when none of the service's icons could be downloaded.
+    #[serde(default)]
+    pub icon_fetch_failed: bool,
     /// When this record was first created.
     pub created_at: Option<Datetime>,
     /// When this record was last updated.
@@ -61,6 +68,47 @@ pub enum ServiceOrigin {
     Broadcast,
 }
 
+/// Where a tool was discovered, for tracing why a tool appeared or disappeared.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "source", rename_all = "snake_case")]
+pub enum DiscoverySource {
+    /// Listed in an `mcp.json` file.
+    StaticConfig { config_path: String },
+    /// Synced from a registry manifest.
+    Registry {
+        registry_id: String,
+        manifest_name: String,
+    },
+    /// Announced over mDNS.
+    Mdns { service_host: String },
+    /// Pushed through a webhook.
+    Webhook { payload_id: String },
+}
+
+impl DiscoverySource {
+    /// All source kinds, as used by `kind()`.
+    pub const KINDS: [&'static str; 4] = ["static_config", "registry", "mdns", "webhook"];
+
+    /// Name of the variant, e.g. `"registry"`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            DiscoverySource::StaticConfig { .. } => "static_config",
+            DiscoverySource::Registry { .. } => "registry",
+            DiscoverySource::Mdns { .. } => "mdns",
+            DiscoverySource::Webhook { .. } => "webhook",
+        }
+    }
+}
+
+/// Tools stored before sources were tracked all came from static config.
+impl Default for DiscoverySource {
+    fn default() -> Self {
+        DiscoverySource::StaticConfig {
+            config_path: String::new(),
+        }
+    }
+}
+
 /// Persisted representation of a single MCP tool plus analysis metadata.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolRecord {
@@ -84,12 +132,165 @@ pub struct ToolRecord {
     pub output_ty: Option<TypedSchema>,
     /// Number of times this tool has been executed.
     pub usage_count: u64,
+    /// Lifecycle status set by the service supervisor (`None` means available).
+    #[serde(default)]
+    pub status: Option<ToolStatus>,
+    /// Where this tool was discovered.
+    #[serde(default)]
+    pub discovery_source: DiscoverySource,
+    /// Free-form annotations such as cost tier or SLA class, merged from the
+    /// tool's `_meta` and from manual edits.
+    #[serde(default)]
+    pub metadata: JsonObject,
+    /// Example argument sets advertised in the tool's `_meta.examples`.
+    #[serde(default)]
+    pub input_examples: Vec<JsonObject>,
+    /// Release notes advertised in the tool's `_meta.changelog`, newest first.
+    #[serde(default)]
+    pub changelog: Vec<ChangelogEntry>,
+    /// Set when the tool's `_meta.deprecated` marks it as deprecated.
+    #[serde(default)]
+    pub deprecation_notice: Option<DeprecationNotice>,
+    /// Translations of the description from the tool's `_meta.descriptions`,
+    /// by lowercase BCP-47 language tag. `description` is assumed to be English.
+    #[serde(default)]
+    pub descriptions: HashMap<String, String>,
+    /// Arguments filled in from the caller's identity, from the tool's
+    /// `_meta.inject_user_context`.
+    #[serde(default)]
+    pub inject_user_context: Vec<InjectedField>,
+    /// Uppercase ISO 3166-1 alpha-2 codes of the countries the tool may be
+    /// selected in, from the tool's `_meta.geo_availability`. `None` means everywhere.
+    #[serde(default)]
+    pub geo_availability: Option<Vec<String>>,
+    /// Environment variables the tool needs, from the tool's
+    /// `_meta.required_env_vars`. Tools missing one are blocked at discovery.
+    #[serde(default)]
+    pub required_env_vars: Vec<String>,
+    /// Service level the tool declares in its `_meta.sla`.
+    #[serde(default)]
+    pub sla_class: Option<SlaClass>,
+    /// Price of one call in US cents, from the tool's `_meta.cost_per_call`.
+    #[serde(default)]
+    pub cost_per_call: Option<f32>,
+    /// How long results of identical calls are reused, from the tool's
+    /// `_meta.cache_ttl_seconds`. `None` means results are never cached.
+    #[serde(default)]
+    pub cache_ttl_seconds: Option<u64>,
+    /// Role a user must hold to execute the tool, from the tool's
+    /// `_meta.required_role`. `None` lets every user execute it.
+    #[serde(default)]
+    pub required_role: Option<String>,
+    /// 99th percentile latency of recent executions, in milliseconds.
+    /// Computed by the `SlaMonitor`; `None` until the tool has been timed.
+    #[serde(default)]
+    pub actual_latency_p99_ms: Option<u32>,
+    /// Percentage of recent executions that failed, computed by the `SlaMonitor`.
+    #[serde(default)]
+    pub actual_error_rate_pct: Option<f32>,
+    /// Why the tool is blocked, if it is.
+    #[serde(default)]
+    pub block_reason: Option<BlockReason>,
     /// When this record was first created.
     pub created_at: Option<Datetime>,
     /// When this record was last updated.
     pub updated_at: Option<Datetime>,
 }
 
+impl ToolRecord {
+    /// Whether this tool has been blocked, e.g. because its service could not be restarted.
+    pub fn is_blocked(&self) -> bool {
+        matches!(self.status, Some(ToolStatus::Blocked))
+    }
+
+    /// Whether every key in `filter` is present in the metadata with an equal value.
+    pub fn metadata_contains(&self, filter: &HashMap<String, Value>) -> bool {
+        filter
+            .iter()
+            .all(|(key, value)| self.metadata.get(key) == Some(value))
+    }
+
+    /// Cost of running this tool, from the `estimated_cost` metadata annotation.
+    pub fn estimated_cost(&self) -> Option<f32> {
+        self.metadata
+            .get("estimated_cost")
+            .and_then(Value::as_f64)
+            .map(|cost| cost as f32)
+    }
+
+    /// Whether the tool only returns output without side effects, from the
+    /// `output_only` metadata annotation.
+    pub fn is_output_only(&self) -> bool {
+        self.metadata
+            .get("output_only")
+            .and_then(Value::as_bool)
+            .unwrap_or(false)
+    }
+
+    /// Capabilities such as `filesystem` or `network` the tool declares in
+    /// its `capabilities` metadata annotation.
+    pub fn declared_capabilities(&self) -> Vec<String> {
+        self.metadata
+            .get("capabilities")
+            .and_then(Value::as_array)
+            .map(|names| {
+                names
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(|name| name.trim().to_string())
+                    .filter(|name| !name.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Whether the tool may be selected for a user in `country`, an ISO 3166-1
+    /// alpha-2 code. Region-restricted tools are unavailable when the country is unknown.
+    pub fn is_available_in(&self, country: Option<&str>) -> bool {
+        match &self.geo_availability {
+            None => true,
+            Some(countries) => country.is_some_and(|country| {
+                countries
+                    .iter()
+                    .any(|allowed| allowed.eq_ignore_ascii_case(country.trim()))
+            }),
+        }
+    }
+
+    /// Whether the tool can be relied on to meet `required`.
+    ///
+    /// The declared `sla_class` must meet it, and the measured latency and
+    /// error rate, once known, must stay within its limits. Tools that don't
+    /// declare an SLA never meet one.
+    pub fn meets_sla(&self, required: &SlaClass) -> bool {
+        self.sla_class
+            .as_ref()
+            .is_some_and(|declared| declared.meets(required))
+            && self
+                .actual_latency_p99_ms
+                .is_none_or(|p99| p99 <= required.max_latency_ms)
+            && self
+                .actual_error_rate_pct
+                .is_none_or(|rate| rate <= required.max_error_rate_pct)
+    }
+
+    /// Typed input schema, derived from the raw schema if it wasn't stored.
+    pub fn typed_input(&self) -> TypedSchema {
+        self.input_ty
+            .clone()
+            .unwrap_or_else(|| TypedSchema::from_json_schema(&self.input_schema))
+    }
+
+    /// Typed output schema, derived from the raw schema if it wasn't stored.
+    pub fn typed_output(&self) -> Option<TypedSchema> {
+        self.output_ty.clone().or_else(|| {
+            self.output_schema
+                .as_ref()
+                .map(TypedSchema::from_json_schema)
+        })
+    }
+}
+
 /// Payload used when inserting a new tool into the database.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateToolRecord {
@@ -109,6 +310,419 @@ pub struct CreateToolRecord {
     pub input_ty: Option<TypedSchema>,
     /// Normalized output schema.
     pub output_ty: Option<TypedSchema>,
+    /// Where this tool was discovered.
+    pub discovery_source: DiscoverySource,
+    /// Annotations to merge into any metadata already stored for the tool.
+    #[serde(default)]
+    pub metadata: JsonObject,
+    /// Example argument sets; at most `MAX_INPUT_EXAMPLES` are stored.
+    #[serde(default)]
+    pub input_examples: Vec<JsonObject>,
+    /// Release notes, newest first; at most `MAX_CHANGELOG_ENTRIES` are stored.
+    #[serde(default)]
+    pub changelog: Vec<ChangelogEntry>,
+    /// Deprecation advertised in `_meta.deprecated`, if any.
+    #[serde(default)]
+    pub deprecation_notice: Option<DeprecationNotice>,
+    /// Translated descriptions by lowercase BCP-47 language tag.
+    #[serde(default)]
+    pub descriptions: HashMap<String, String>,
+    /// Arguments filled in from the caller's identity.
+    #[serde(default)]
+    pub inject_user_context: Vec<InjectedField>,
+    /// Countries the tool may be selected in, if it is region-restricted.
+    #[serde(default)]
+    pub geo_availability: Option<Vec<String>>,
+    /// Environment variables the tool needs.
+    #[serde(default)]
+    pub required_env_vars: Vec<String>,
+    /// Declared service level, if any.
+    #[serde(default)]
+    pub sla_class: Option<SlaClass>,
+    /// Price of one call in US cents, if declared.
+    #[serde(default)]
+    pub cost_per_call: Option<f32>,
+    /// Seconds results of identical calls are cached for, if declared.
+    #[serde(default)]
+    pub cache_ttl_seconds: Option<u64>,
+    /// Role required to execute the tool, if declared.
+    #[serde(default)]
+    pub required_role: Option<String>,
+}
+
+impl CreateToolRecord {
+    /// Build a tool payload from a tool advertised by a running MCP service.
+    pub fn from_mcp_tool(
+        service_id: &RecordId,
+        tool: &rmcp::model::Tool,
+        discovery_source: DiscoverySource,
+    ) -> Self {
+        Self {
+            service_id: service_id.clone(),
+            name: tool.name.to_string(),
+            description: tool.description.as_ref().map(|d| d.to_string()),
+            input_schema: (*tool.input_schema).clone(),
+            output_schema: tool.output_schema.as_ref().map(|schema| (**schema).clone()),
+            embedding_id: None,
+            input_ty: None,
+            output_ty: None,
+            discovery_source,
+            metadata: tool
+                .meta
+                .as_ref()
+                .map(|meta| meta.0.clone())
+                .unwrap_or_default(),
+            input_examples: tool
+                .meta
+                .as_ref()
+                .map(|meta| input_examples_from_meta(&meta.0))
+                .unwrap_or_default(),
+            changelog: tool
+                .meta
+                .as_ref()
+                .map(|meta| changelog_from_meta(&meta.0))
+                .unwrap_or_default(),
+            deprecation_notice: tool
+                .meta
+                .as_ref()
+                .and_then(|meta| deprecation_from_meta(&meta.0)),
+            descriptions: tool
+                .meta
+                .as_ref()
+                .map(|meta| descriptions_from_meta(&meta.0))
+                .unwrap_or_default(),
+            inject_user_context: tool
+                .meta
+                .as_ref()
+                .map(|meta| injected_fields_from_meta(&meta.0))
+                .unwrap_or_default(),
+            geo_availability: tool
+                .meta
+                .as_ref()
+                .and_then(|meta| geo_availability_from_meta(&meta.0)),
+            required_env_vars: tool
+                .meta
+                .as_ref()
+                .map(|meta| required_env_vars_from_meta(&meta.0))
+                .unwrap_or_default(),
+            sla_class: tool
+                .meta
+                .as_ref()
+                .and_then(|meta| sla_class_from_meta(&meta.0)),
+            cost_per_call: tool
+                .meta
+                .as_ref()
+                .and_then(|meta| meta.0.get("cost_per_call"))
+                .and_then(Value::as_f64)
+                .filter(|cost| *cost >= 0.0)
+                .map(|cost| cost as f32),
+            cache_ttl_seconds: tool
+                .meta
+                .as_ref()
+                .and_then(|meta| meta.0.get("cache_ttl_seconds"))
+                .and_then(Value::as_u64)
+                .filter(|ttl| *ttl > 0),
+            required_role: tool
+                .meta
+                .as_ref()
+                .and_then(|meta| meta.0.get("required_role"))
+                .and_then(Value::as_str)
+                .map(str::trim)
+                .filter(|role| !role.is_empty())
+                .map(str::to_string),
+        }
+    }
+}
+
+/// Maximum number of example argument sets stored per tool.
+pub const MAX_INPUT_EXAMPLES: usize = 5;
+
+/// Read example argument sets from a tool's `_meta.examples` array.
+///
+/// Entries that are not objects are ignored.
+pub fn input_examples_from_meta(meta: &JsonObject) -> Vec<JsonObject> {
+    meta.get("examples")
+        .and_then(Value::as_array)
+        .map(|examples| {
+            examples
+                .iter()
+                .filter_map(Value::as_object)
+                .take(MAX_INPUT_EXAMPLES)
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Maximum number of changelog entries stored per tool.
+pub const MAX_CHANGELOG_ENTRIES: usize = 10;
+
+/// One release in a tool's `_meta.changelog`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChangelogEntry {
+    /// Version the entry describes, e.g. `2.0.0`.
+    pub version: String,
+    /// Release date, ideally ISO 8601 (`2025-06-01`) so entries sort by it.
+    pub date: String,
+    /// What changed in this version.
+    #[serde(default)]
+    pub changes: Vec<String>,
+    /// Whether the release breaks existing callers.
+    #[serde(default)]
+    pub breaking: bool,
+}
+
+/// Read release notes from a tool's `_meta.changelog` array.
+///
+/// Malformed entries are ignored. Entries are ordered newest first by date and
+/// only the newest `MAX_CHANGELOG_ENTRIES` are kept.
+pub fn changelog_from_meta(meta: &JsonObject) -> Vec<ChangelogEntry> {
+    let mut changelog: Vec<ChangelogEntry> = meta
+        .get("changelog")
+        .and_then(Value::as_array)
+        .map(|entries| {
+            entries
+                .iter()
+                .filter_map(|entry| serde_json::from_value(entry.clone()).ok())
+                .collect()
+        })
+        .unwrap_or_default();
+    changelog.sort_by(|a, b| b.date.cmp(&a.date));
+    changelog.truncate(MAX_CHANGELOG_ENTRIES);
+    changelog
+}
+
+/// Breaking entries in `current` for versions that `previous` did not list.
+pub fn new_breaking_changes<'a>(
+    previous: &[ChangelogEntry],
+    current: &'a [ChangelogEntry],
+) -> Vec<&'a ChangelogEntry> {
+    current
+        .iter()
+        .filter(|entry| entry.breaking)
+        .filter(|entry| !previous.iter().any(|known| known.version == entry.version))
+        .collect()
+}
+
+/// A service's notice that one of its tools is deprecated.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeprecationNotice {
+    /// Version that deprecated the tool; empty when the service didn't say.
+    #[serde(default)]
+    pub since_version: String,
+    /// Name of the tool to use instead.
+    #[serde(default)]
+    pub replacement_tool: Option<String>,
+    /// When the tool is expected to go away, ideally ISO 8601.
+    #[serde(default)]
+    pub removal_date: Option<String>,
+    /// Explanation for users; empty when the service didn't say.
+    #[serde(default)]
+    pub message: String,
+}
+
+impl DeprecationNotice {
+    /// A one-line warning to show users of `tool_name`.
+    pub fn warning(&self, tool_name: &str) -> String {
+        let mut warning = format!("Tool `{}` is deprecated", tool_name);
+        if !self.since_version.is_empty() {
+            warning.push_str(&format!(" since version {}", self.since_version));
+        }
+        if !self.message.is_empty() {
+            warning.push_str(&format!(": {}", self.message));
+        }
+        warning.push('.');
+        if let Some(replacement) = &self.replacement_tool {
+            warning.push_str(&format!(" Use `{}` instead.", replacement));
+        }
+        if let Some(removal_date) = &self.removal_date {
+            warning.push_str(&format!(" It will be removed on {}.", removal_date));
+        }
+        warning
+    }
+}
+
+/// Read a deprecation from a tool's `_meta.deprecated`.
+///
+/// Accepts a notice object, a message string or `true`. Anything else,
+/// including `false` and malformed objects, means the tool isn't deprecated.
+pub fn deprecation_from_meta(meta: &JsonObject) -> Option<DeprecationNotice> {
+    match meta.get("deprecated")? {
+        notice @ Value::Object(_) => serde_json::from_value(notice.clone()).ok(),
+        Value::String(message) => Some(DeprecationNotice {
+            message: message.clone(),
+            ..Default::default()
+        }),
+        Value::Bool(true) => Some(DeprecationNotice::default()),
+        _ => None,
+    }
+}
+
+/// Read translated descriptions from a tool's `_meta.descriptions` object,
+/// which maps BCP-47 language tags to descriptions.
+///
+/// Tags are lowercased; blank tags and non-string descriptions are ignored.
+pub fn descriptions_from_meta(meta: &JsonObject) -> HashMap<String, String> {
+    meta.get("descriptions")
+        .and_then(Value::as_object)
+        .map(|descriptions| {
+            descriptions
+                .iter()
+                .filter(|(tag, _)| !tag.trim().is_empty())
+                .filter_map(|(tag, description)| {
+                    Some((tag.trim().to_lowercase(), description.as_str()?.to_string()))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Where an injected argument's value comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UserContextField {
+    /// The user's external identity, e.g. the JWT `sub` claim.
+    UserId,
+    Email,
+    DisplayName,
+    /// The identity provider that authenticated the user.
+    Provider,
+    /// The client's IP address.
+    IpAddress,
+}
+
+/// A tool argument the orchestrator fills in from the caller's `UserContext`,
+/// so the tool can know who is calling without exposing it in its schema.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InjectedField {
+    /// Top-level argument to set.
+    pub arg_name: String,
+    pub source: UserContextField,
+}
+
+/// Read injected arguments from a tool's `_meta.inject_user_context` array,
+/// e.g. `[{ "arg_name": "caller", "source": "email" }]`.
+///
+/// Malformed entries are ignored.
+pub fn injected_fields_from_meta(meta: &JsonObject) -> Vec<InjectedField> {
+    meta.get("inject_user_context")
+        .and_then(Value::as_array)
+        .map(|fields| {
+            fields
+                .iter()
+                .filter_map(|field| serde_json::from_value(field.clone()).ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Read the countries a tool is restricted to from its `_meta.geo_availability`
+/// array of ISO 3166-1 alpha-2 codes, e.g. `["US", "CA"]`.
+///
+/// Codes are uppercased; entries that aren't two ASCII letters are ignored. A
+/// missing or non-array value means the tool isn't restricted.
+pub fn geo_availability_from_meta(meta: &JsonObject) -> Option<Vec<String>> {
+    let countries = meta.get("geo_availability")?.as_array()?;
+    Some(
+        countries
+            .iter()
+            .filter_map(Value::as_str)
+            .map(|code| code.trim().to_ascii_uppercase())
+            .filter(|code| code.len() == 2 && code.chars().all(|c| c.is_ascii_alphabetic()))
+            .collect(),
+    )
+}
+
+/// Read the environment variables a tool needs from its
+/// `_meta.required_env_vars` array of names. Blank and non-string entries are ignored.
+pub fn required_env_vars_from_meta(meta: &JsonObject) -> Vec<String> {
+    meta.get("required_env_vars")
+        .and_then(Value::as_array)
+        .map(|names| {
+            names
+                .iter()
+                .filter_map(Value::as_str)
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Service level a tool declares, or a caller requires.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct SlaClass {
+    /// Slowest acceptable response time, in milliseconds.
+    pub max_latency_ms: u32,
+    /// Percentage of time the tool is available, e.g. `99.9`.
+    pub availability_pct: f32,
+    /// Highest acceptable percentage of failed executions.
+    pub max_error_rate_pct: f32,
+}
+
+impl SlaClass {
+    /// Whether this level is at least as strict as `required` on every axis.
+    pub fn meets(&self, required: &SlaClass) -> bool {
+        self.max_latency_ms <= required.max_latency_ms
+            && self.availability_pct >= required.availability_pct
+            && self.max_error_rate_pct <= required.max_error_rate_pct
+    }
+}
+
+/// Read the service level a tool declares in its `_meta.sla` object, e.g.
+/// `{"max_latency_ms": 500, "availability_pct": 99.9, "max_error_rate_pct": 0.5}`.
+///
+/// A missing or incomplete object means the tool declares no SLA.
+pub fn sla_class_from_meta(meta: &JsonObject) -> Option<SlaClass> {
+    serde_json::from_value(meta.get("sla")?.clone()).ok()
+}
+
+/// Why a tool was blocked.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum BlockReason {
+    /// The supervisor gave up restarting the tool's service.
+    ServiceUnavailable,
+    /// The tool's service was removed from `mcp.json`.
+    ServiceRemoved,
+    /// An environment variable from `_meta.required_env_vars` isn't set.
+    MissingEnvVar(String),
+}
+
+impl std::fmt::Display for BlockReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BlockReason::ServiceUnavailable => {
+                write!(f, "the service could not be restarted")
+            }
+            BlockReason::ServiceRemoved => write!(f, "the service was removed from mcp.json"),
+            BlockReason::MissingEnvVar(name) => {
+                write!(f, "environment variable `{name}` is not set")
+            }
+        }
+    }
+}
+
+/// Tags to look for when negotiating `language`, most specific first:
+/// `de-AT` yields `de-at` and then `de`.
+pub fn language_fallbacks(language: &str) -> Vec<String> {
+    let tag = language.trim().to_lowercase();
+    let mut tags = Vec::new();
+    if let Some((primary, _)) = tag.split_once('-') {
+        tags.push(tag.clone());
+        tags.push(primary.to_string());
+    } else if !tag.is_empty() {
+        tags.push(tag);
+    }
+    tags
+}
+
+/// Whether `language` is English, which the main `description` is written in.
+pub fn is_english(language: &str) -> bool {
+    language_fallbacks(language)
+        .last()
+        .is_some_and(|tag| tag == "en")
 }
 
 /// Simplified, normalized representation of a JSON schema used for type reasoning.
@@ -181,7 +795,10 @@ impl TypedSchema {
                             enum_values: None,
                         }
                     }
-                    other => Self::simple(other),
+                    other => TypedSchema {
+                        enum_values: schema.get("enum").and_then(|v| v.as_array()).cloned(),
+                        ..Self::simple(other)
+                    },
                 },
                 Value::Array(arr) => {
                     // type: ["string", "null"] etc. -> mark overall type as "union"
@@ -268,6 +885,80 @@ impl TypedSchema {
         }
     }
 
+    /// How well a value of this schema fits `input`, from 0.0 (not at all) to
+    /// 1.0 (fully).
+    ///
+    /// `any` on either side fits fully. An object scores the average of its
+    /// fit for each property `input` requires, where a missing property scores
+    /// 0.0; without required properties it scores the average fit of the
+    /// properties both declare. Arrays score their items. A union fits as well
+    /// as its best matching member, and producing a union scores 0.5 since it
+    /// may produce a member `input` doesn't accept.
+    pub fn structural_compatibility_score(&self, input: &TypedSchema) -> f32 {
+        let (from, to) = (self.schema_type.as_str(), input.schema_type.as_str());
+        match (from, to) {
+            ("any", _) | (_, "any") => 1.0,
+            (_, "union") => match &input.enum_values {
+                Some(members) => members
+                    .iter()
+                    .map(|member| match member {
+                        Value::String(t) if t == from => 1.0,
+                        Value::Object(schema) => self
+                            .structural_compatibility_score(&TypedSchema::from_json_schema(schema)),
+                        _ => 0.0,
+                    })
+                    .fold(0.0, f32::max),
+                None => 0.5,
+            },
+            ("union", _) => 0.5,
+            ("integer", "number") => 1.0,
+            ("number", "integer") => 0.5,
+            ("object", "object") => {
+                let own = self.properties.as_ref();
+                let required = input.required.as_deref().unwrap_or_default();
+                let scores: Vec<f32> = if required.is_empty() {
+                    let Some(wanted) = &input.properties else {
+                        return 1.0;
+                    };
+                    wanted
+                        .iter()
+                        .filter_map(|(name, schema)| {
+                            own.and_then(|props| props.get(name))
+                                .map(|prop| prop.structural_compatibility_score(schema))
+                        })
+                        .collect()
+                } else {
+                    required
+                        .iter()
+                        .map(|name| {
+                            let Some(prop) = own.and_then(|props| props.get(name)) else {
+                                return 0.0;
+                            };
+                            input
+                                .properties
+                                .as_ref()
+                                .and_then(|props| props.get(name))
+                                .map_or(1.0, |schema| prop.structural_compatibility_score(schema))
+                        })
+                        .collect()
+                };
+                if scores.is_empty() {
+                    1.0
+                } else {
+                    scores.iter().sum::<f32>() / scores.len() as f32
+                }
+            }
+            ("array", "array") => match (&self.items, &input.items) {
+                (Some(from_items), Some(to_items)) => {
+                    from_items.structural_compatibility_score(to_items)
+                }
+                _ => 1.0,
+            },
+            _ if from == to => 1.0,
+            _ => 0.0,
+        }
+    }
+
     // small helper for primitive types
     fn simple(schema_type: &str) -> TypedSchema {
         TypedSchema {
@@ -407,6 +1098,15 @@ pub struct ManifestCreate {
     pub is_active: bool,
 }
 
+/// Narrows the tools returned by `QueryBuilder::paginated_tools`.
+#[derive(Debug, Clone, Default)]
+pub struct ToolFilter {
+    /// Only tools of this service.
+    pub service_id: Option<RecordId>,
+    /// Only tools from this discovery source kind, e.g. `registry`.
+    pub source: Option<String>,
+}
+
 /// High-level search query for tools, combining text and type filters.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolSearchQuery {
@@ -414,6 +1114,9 @@ pub struct ToolSearchQuery {
     pub input_types: Option<Vec<String>>,
     pub output_types: Option<Vec<String>>,
     pub service_ids: Option<Vec<RecordId>>,
+    /// Only match tools whose metadata has each of these key-value pairs.
+    #[serde(default)]
+    pub metadata_contains: HashMap<String, Value>,
     pub min_confidence: Option<f32>,
     pub include_embeddings: bool,
     pub limit: Option<u32>,
@@ -522,6 +1225,9 @@ pub struct PlanStep {
     pub expected_outputs: Vec<String>,
     pub parallel: bool,
     pub dependencies: Vec<u32>,
+    /// Optional description with `{{key}}` placeholders resolved from `inputs`.
+    #[serde(default)]
+    pub description_template: Option<String>,
 }
 
 /// Identity provider types for user authentication.
@@ -615,6 +1321,12 @@ pub struct UserPreferencesRecord {
     pub notify_on_tool_execution: bool,
     /// Whether to notify on permission grant
     pub notify_on_permission_grant: bool,
+    /// Embedding model used for this user's searches instead of the global one
+    #[serde(default)]
+    pub embedding_backend_override: Option<EmbeddingBackendConfig>,
+    /// BCP-47 tag of the language this user's queries are written in
+    #[serde(default)]
+    pub preferred_language: Option<String>,
     /// When preferences were created
     pub created_at: Option<Datetime>,
     /// Last update time
@@ -631,6 +1343,7 @@ pub struct UserPreferencesUpdate {
     pub remember_decisions: Option<bool>,
     pub notify_on_tool_execution: Option<bool>,
     pub notify_on_permission_grant: Option<bool>,
+    pub preferred_language: Option<String>,
 }
 
 /// Audit log action types.
@@ -649,6 +1362,14 @@ pub enum AuditAction {
     PermissionDenied,
     /// Permission was revoked
     PermissionRevoked,
+    /// Several permissions were imported at once
+    PermissionsBulkGranted,
+    /// Permissions were imported from an exported permission set
+    PermissionsImported,
+    /// A permission row was created; written by the `permission_changed` DB event
+    PermissionCreated,
+    /// A permission row was deleted; written by the `permission_changed` DB event
+    PermissionDeleted,
     /// Elicitation was requested
     ElicitationRequested,
     /// Elicitation was completed
@@ -659,6 +1380,14 @@ pub enum AuditAction {
     OAuthCompleted,
     /// User preferences updated
     PreferencesUpdated,
+    /// MCP service was restarted after an unexpected exit
+    ServiceRestarted,
+    /// MCP service could not be restarted and its tools were blocked
+    ServiceRestartFailed,
+    /// An API key was replaced by a new key for the same user
+    ApiKeyRotated,
+    /// A resource was written through `unicity.write_resource`
+    ResourceWritten,
 }
 
 impl AuditAction {
@@ -670,11 +1399,19 @@ impl AuditAction {
             Self::PermissionGranted => "permission_granted",
             Self::PermissionDenied => "permission_denied",
             Self::PermissionRevoked => "permission_revoked",
+            Self::PermissionsBulkGranted => "permissions_bulk_granted",
+            Self::PermissionsImported => "permissions_imported",
+            Self::PermissionCreated => "permission_created",
+            Self::PermissionDeleted => "permission_deleted",
             Self::ElicitationRequested => "elicitation_requested",
             Self::ElicitationCompleted => "elicitation_completed",
             Self::OAuthStarted => "oauth_started",
             Self::OAuthCompleted => "oauth_completed",
             Self::PreferencesUpdated => "preferences_updated",
+            Self::ServiceRestarted => "service_restarted",
+            Self::ServiceRestartFailed => "service_restart_failed",
+            Self::ApiKeyRotated => "api_key_rotated",
+            Self::ResourceWritten => "resource_written",
         }
     }
 }
@@ -714,6 +1451,126 @@ pub struct AuditLogCreate {
     pub user_agent: Option<String>,
 }
 
+/// A single tool invocation, recorded with its arguments and outcome.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolUsageEventRecord {
+    /// Database identifier
+    pub id: RecordId,
+    /// The tool that was invoked
+    pub tool_id: RecordId,
+    /// External ID of the invoking user
+    pub user_id: String,
+    /// Arguments passed to the tool
+    pub arguments: JsonObject,
+    /// Whether the invocation succeeded
+    pub success: bool,
+    /// How long the invocation took; `None` for events recorded before
+    /// executions were timed
+    #[serde(default)]
+    pub duration_ms: Option<u64>,
+    /// When the invocation happened
+    pub created_at: Option<Datetime>,
+}
+
+/// Snapshot of a tool's input schema, recorded whenever discovery sees it change.
+///
+/// Snapshots are keyed by service and tool name rather than tool ID, since
+/// rediscovery creates a new tool record.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolSchemaHistoryRecord {
+    /// Database identifier
+    pub id: RecordId,
+    /// Service that owns the tool
+    pub service_id: RecordId,
+    /// Tool name as declared by the service
+    pub tool_name: String,
+    /// Schema version, starting at 1
+    pub version: u32,
+    /// Raw JSON input schema
+    pub input_schema: JsonObject,
+    /// Normalized input schema
+    pub input_ty: TypedSchema,
+    /// When the schema was first seen
+    pub recorded_at: Option<Datetime>,
+}
+
+/// Icon data downloaded for a service.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceIconRecord {
+    /// Database identifier
+    pub id: RecordId,
+    /// The service the icon belongs to
+    pub service_id: RecordId,
+    /// Raw image bytes
+    pub data: Bytes,
+    /// MIME type, e.g. `image/png` or `image/svg+xml`
+    pub mime_type: String,
+    /// When the icon was downloaded
+    pub fetched_at: Option<Datetime>,
+}
+
+/// A single `prompts/get` request forwarded to a service.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptUsageEventRecord {
+    /// Database identifier
+    pub id: RecordId,
+    /// Service that owns the prompt
+    pub service_id: String,
+    /// Prompt name as declared by the service
+    pub prompt_name: String,
+    /// Arguments passed to the prompt
+    pub arguments: JsonObject,
+    /// Whether the service returned the prompt
+    pub success: bool,
+    /// When the request happened
+    pub created_at: Option<Datetime>,
+}
+
+/// Whether the user went with a tool selection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SelectionOutcome {
+    Accepted,
+    Rejected,
+}
+
+/// Feedback on a tool selection returned for a query.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelectionFeedbackRecord {
+    /// Database identifier
+    pub id: RecordId,
+    /// The user who gave the feedback
+    #[serde(default)]
+    pub user_id: String,
+    /// Client session the selection was made in
+    pub session_id: String,
+    /// The query the selection answered
+    pub query: String,
+    /// Embedding of `query`, used to find feedback for similar queries
+    pub query_embedding: Option<Vec<f32>>,
+    /// The tool the feedback is about
+    pub selected_tool_id: Option<RecordId>,
+    /// Other suggested tools the user passed over
+    #[serde(default)]
+    pub rejected_tool_ids: Vec<RecordId>,
+    /// Whether `selected_tool_id` was accepted
+    pub outcome: SelectionOutcome,
+    /// When the feedback was given
+    pub created_at: Option<Datetime>,
+}
+
+/// Payload for recording selection feedback.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelectionFeedbackCreate {
+    pub user_id: String,
+    pub session_id: String,
+    pub query: String,
+    pub query_embedding: Option<Vec<f32>>,
+    pub selected_tool_id: Option<RecordId>,
+    pub rejected_tool_ids: Vec<RecordId>,
+    pub outcome: SelectionOutcome,
+}
+
 /// Persisted API key record for database-backed authentication.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ApiKeyRecord {
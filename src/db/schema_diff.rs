@@ -0,0 +1,319 @@
+//! Structured diff between two versions of a tool's input schema.
+//!
+//! Schemas are flattened into fields keyed by path (`user.name`, `tags[]`)
+//! and compared field by field. A diff is breaking when callers written
+//! against the old schema may be rejected by the new one: a required field
+//! was removed or a field's type was narrowed.
+
+use super::schema::TypedSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// A field present in only one of the two schemas.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldDiff {
+    /// Dotted path of the field; array items are suffixed with `[]`.
+    pub path: String,
+    #[serde(rename = "type")]
+    pub schema_type: String,
+    pub required: bool,
+}
+
+/// A field whose type or required status changed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldModification {
+    pub path: String,
+    pub old_type: String,
+    pub new_type: String,
+    pub old_required: bool,
+    pub new_required: bool,
+    /// Whether the new type accepts fewer values than the old one.
+    pub narrowed: bool,
+}
+
+/// Differences between an old and a new input schema.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SchemaDiff {
+    pub added: Vec<FieldDiff>,
+    pub removed: Vec<FieldDiff>,
+    pub modified: Vec<FieldModification>,
+    /// Whether a required field was removed or a field's type was narrowed.
+    pub breaking: bool,
+}
+
+impl SchemaDiff {
+    /// Compare `old` against `new`. Fields are listed in path order.
+    pub fn between(old: &TypedSchema, new: &TypedSchema) -> Self {
+        let old_fields = flatten(old);
+        let new_fields = flatten(new);
+        let mut diff = SchemaDiff::default();
+
+        for (path, field) in &old_fields {
+            match new_fields.get(path) {
+                None => diff.removed.push(field.to_diff(path)),
+                Some(new_field) => {
+                    let narrowed = narrows(field.schema, new_field.schema);
+                    let changed = narrowed
+                        || field.required != new_field.required
+                        || type_label(field.schema) != type_label(new_field.schema);
+                    if changed {
+                        diff.modified.push(FieldModification {
+                            path: path.clone(),
+                            old_type: type_label(field.schema),
+                            new_type: type_label(new_field.schema),
+                            old_required: field.required,
+                            new_required: new_field.required,
+                            narrowed,
+                        });
+                    }
+                }
+            }
+        }
+        for (path, field) in &new_fields {
+            if !old_fields.contains_key(path) {
+                diff.added.push(field.to_diff(path));
+            }
+        }
+
+        diff.breaking = diff.removed.iter().any(|field| field.required)
+            || diff.modified.iter().any(|field| field.narrowed);
+        diff
+    }
+
+    /// Whether the two schemas have the same fields.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+struct Field<'a> {
+    schema: &'a TypedSchema,
+    required: bool,
+}
+
+impl Field<'_> {
+    fn to_diff(&self, path: &str) -> FieldDiff {
+        FieldDiff {
+            path: path.to_string(),
+            schema_type: type_label(self.schema),
+            required: self.required,
+        }
+    }
+}
+
+/// Every field of an object schema, including nested object properties and
+/// array items, by path.
+fn flatten(schema: &TypedSchema) -> BTreeMap<String, Field<'_>> {
+    let mut fields = BTreeMap::new();
+    collect_fields(schema, "", &mut fields);
+    fields
+}
+
+fn collect_fields<'a>(
+    schema: &'a TypedSchema,
+    prefix: &str,
+    fields: &mut BTreeMap<String, Field<'a>>,
+) {
+    if let Some(properties) = &schema.properties {
+        let required = schema.required.as_deref().unwrap_or_default();
+        for (name, property) in properties {
+            let path = if prefix.is_empty() {
+                name.clone()
+            } else {
+                format!("{}.{}", prefix, name)
+            };
+            fields.insert(
+                path.clone(),
+                Field {
+                    schema: property,
+                    required: required.contains(name),
+                },
+            );
+            collect_fields(property, &path, fields);
+        }
+    }
+    if let Some(items) = &schema.items
+        && !prefix.is_empty()
+    {
+        let path = format!("{}[]", prefix);
+        fields.insert(
+            path.clone(),
+            Field {
+                schema: items,
+                required: false,
+            },
+        );
+        collect_fields(items, &path, fields);
+    }
+}
+
+/// Type name shown in a diff: unions list their member types and enums
+/// their values.
+fn type_label(schema: &TypedSchema) -> String {
+    match (&schema.enum_values, schema.schema_type.as_str()) {
+        (Some(members), "union") => members
+            .iter()
+            .map(|member| match member {
+                Value::String(ty) => ty.clone(),
+                Value::Object(variant) => TypedSchema::from_json_schema(variant).schema_type,
+                _ => "any".to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(" | "),
+        (Some(values), ty) => {
+            let values: Vec<String> = values.iter().map(Value::to_string).collect();
+            format!("{} ({})", ty, values.join(", "))
+        }
+        (None, ty) => ty.to_string(),
+    }
+}
+
+/// Whether `new` accepts fewer values than `old`.
+///
+/// Moving to `any`, from `integer` to `number`, into a union containing the
+/// old type, or to a superset of enum values or union members widens;
+/// any other type change narrows.
+fn narrows(old: &TypedSchema, new: &TypedSchema) -> bool {
+    let (from, to) = (old.schema_type.as_str(), new.schema_type.as_str());
+    match (from, to) {
+        (_, "any") => false,
+        _ if from == to => match (&old.enum_values, &new.enum_values) {
+            (_, None) => false,
+            (None, Some(_)) => true,
+            (Some(old_values), Some(new_values)) => {
+                !old_values.iter().all(|value| new_values.contains(value))
+            }
+        },
+        ("integer", "number") => false,
+        (_, "union") => !new
+            .enum_values
+            .as_ref()
+            .is_some_and(|members| members.contains(&Value::String(from.to_string()))),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schema(value: Value) -> TypedSchema {
+        TypedSchema::from_json_schema(value.as_object().unwrap())
+    }
+
+    fn v1() -> TypedSchema {
+        schema(json!({
+            "type": "object",
+            "properties": {
+                "path": {"type": "string"},
+                "limit": {"type": "integer"},
+                "mode": {"type": "string", "enum": ["r", "w", "a"]},
+                "owner": {
+                    "type": "object",
+                    "properties": {"name": {"type": "string"}},
+                    "required": ["name"]
+                }
+            },
+            "required": ["path"]
+        }))
+    }
+
+    #[test]
+    fn test_identical_schemas_have_no_diff() {
+        let diff = SchemaDiff::between(&v1(), &v1());
+        assert!(diff.is_empty());
+        assert!(!diff.breaking);
+    }
+
+    #[test]
+    fn test_widening_changes_are_not_breaking() {
+        let v2 = schema(json!({
+            "type": "object",
+            "properties": {
+                "path": {"type": "string"},
+                "limit": {"type": "number"},
+                "mode": {"type": "string", "enum": ["r", "w", "a", "x"]},
+                "owner": {
+                    "type": "object",
+                    "properties": {"name": {"type": ["string", "null"]}}
+                },
+                "tags": {"type": "array", "items": {"type": "string"}}
+            },
+            "required": ["path"]
+        }));
+        let diff = SchemaDiff::between(&v1(), &v2);
+
+        assert!(!diff.breaking);
+        assert_eq!(
+            diff.added,
+            vec![
+                FieldDiff {
+                    path: "tags".to_string(),
+                    schema_type: "array".to_string(),
+                    required: false,
+                },
+                FieldDiff {
+                    path: "tags[]".to_string(),
+                    schema_type: "string".to_string(),
+                    required: false,
+                },
+            ]
+        );
+        assert!(diff.removed.is_empty());
+        let paths: Vec<&str> = diff.modified.iter().map(|m| m.path.as_str()).collect();
+        assert_eq!(paths, vec!["limit", "mode", "owner.name"]);
+        assert_eq!(diff.modified[2].new_type, "string | null");
+        assert!(diff.modified[2].old_required && !diff.modified[2].new_required);
+    }
+
+    #[test]
+    fn test_removed_required_field_is_breaking() {
+        let v2 = schema(json!({
+            "type": "object",
+            "properties": {"limit": {"type": "integer"}}
+        }));
+        let diff = SchemaDiff::between(&v1(), &v2);
+
+        assert!(diff.breaking);
+        assert!(
+            diff.removed
+                .iter()
+                .any(|field| field.path == "path" && field.required)
+        );
+        assert!(diff.removed.iter().any(|field| field.path == "owner.name"));
+    }
+
+    #[test]
+    fn test_narrowed_types_are_breaking() {
+        for (property, narrowed) in [
+            ("limit", json!({"type": "string"})),
+            ("mode", json!({"type": "string", "enum": ["r"]})),
+            ("path", json!({"type": "string", "enum": ["/tmp"]})),
+        ] {
+            let mut v2 = json!({
+                "type": "object",
+                "properties": {
+                    "path": {"type": "string"},
+                    "limit": {"type": "integer"},
+                    "mode": {"type": "string", "enum": ["r", "w", "a"]},
+                    "owner": {
+                        "type": "object",
+                        "properties": {"name": {"type": "string"}},
+                        "required": ["name"]
+                    }
+                },
+                "required": ["path"]
+            });
+            v2["properties"][property] = narrowed;
+            let diff = SchemaDiff::between(&v1(), &schema(v2));
+
+            assert!(diff.breaking, "narrowing `{}` should be breaking", property);
+            assert_eq!(diff.modified.len(), 1);
+            assert!(diff.modified[0].narrowed);
+        }
+    }
+}
@@ -7,6 +7,7 @@
 //!
 //! Permissions are stored per-user and can have optional expiration.
 
+use crate::db::schema::{AuditAction, AuditLogCreate};
 use crate::elicitation::store::PermissionStore;
 use crate::elicitation::{
     CreateElicitationResult, ElicitationAction, ElicitationError, ElicitationResult,
@@ -15,9 +16,10 @@ use crate::elicitation::{
 use crate::types::{ExternalUserId, ServiceId, ServiceName, ToolId};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use std::time::Duration;
 
 /// Approval action for a tool execution request.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ApprovalAction {
     /// Approve this single execution only
@@ -60,6 +62,10 @@ pub struct ApprovalRequest {
     pub user_id: ExternalUserId,
     /// Arguments being passed to the tool (for context)
     pub arguments: Option<serde_json::Value>,
+    /// Role the tool requires, from its `required_role`
+    pub required_role: Option<String>,
+    /// Roles of the user granting the permission
+    pub granter_roles: Vec<String>,
 }
 
 /// Result of a permission check.
@@ -75,15 +81,84 @@ pub enum PermissionStatus {
     Expired,
 }
 
+/// Role whose tools can only be always allowed by users holding it.
+pub const ADMIN_ROLE: &str = "admin";
+
+/// Refuse an `AlwaysAllow` grant for a tool that requires the admin role
+/// unless the granting user is an admin.
+fn check_grant_allowed(request: &ApprovalRequest, action: ApprovalAction) -> ElicitationResult<()> {
+    if action == ApprovalAction::AlwaysAllow
+        && request.required_role.as_deref() == Some(ADMIN_ROLE)
+        && !request.granter_roles.iter().any(|role| role == ADMIN_ROLE)
+    {
+        return Err(ElicitationError::Unauthorized(format!(
+            "only users with the '{}' role can always allow {}",
+            ADMIN_ROLE, request.tool_id
+        )));
+    }
+    Ok(())
+}
+
+/// Settings for how tool approvals are requested and remembered.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApprovalConfig {
+    /// How long to wait for the user to answer an approval prompt.
+    pub timeout: Duration,
+    /// How long a granted or denied permission is kept; `None` keeps it until revoked.
+    pub permission_ttl: Option<Duration>,
+}
+
+impl Default for ApprovalConfig {
+    fn default() -> Self {
+        Self {
+            timeout: super::DEFAULT_ELICITATION_TIMEOUT,
+            permission_ttl: None,
+        }
+    }
+}
+
+/// The status a stored permission (or its absence) grants.
+fn permission_status(permission: Option<&ToolPermission>) -> PermissionStatus {
+    let Some(permission) = permission else {
+        return PermissionStatus::Required;
+    };
+
+    // Check expiration
+    if let Some(expires_at) = &permission.expires_at
+        && let Ok(expiry) = chrono::DateTime::parse_from_rfc3339(expires_at)
+        && expiry < chrono::Utc::now()
+    {
+        return PermissionStatus::Expired;
+    }
+
+    match permission.action {
+        ApprovalAction::AllowOnce => {
+            // One-time permissions are consumed after use
+            PermissionStatus::Granted
+        }
+        ApprovalAction::AlwaysAllow => PermissionStatus::Granted,
+        ApprovalAction::Deny => PermissionStatus::Denied,
+    }
+}
+
 /// Manager for tool approval permissions.
 pub struct ApprovalManager {
     store: Arc<PermissionStore>,
+    permission_ttl: Option<Duration>,
 }
 
 impl ApprovalManager {
     /// Create a new approval manager.
     pub fn new(store: Arc<PermissionStore>) -> Self {
-        Self { store }
+        Self::new_with_config(store, &ApprovalConfig::default())
+    }
+
+    /// Create a new approval manager with the given approval settings.
+    pub fn new_with_config(store: Arc<PermissionStore>, config: &ApprovalConfig) -> Self {
+        Self {
+            store,
+            permission_ttl: config.permission_ttl,
+        }
     }
 
     /// Check if a tool execution is approved for the given user.
@@ -95,52 +170,142 @@ impl ApprovalManager {
         service_id: &ServiceId,
         user_id: &ExternalUserId,
     ) -> ElicitationResult<PermissionStatus> {
-        match self
+        let permission = self
             .store
             .get_permission(tool_id.as_str(), service_id.as_str(), user_id.as_str())
-            .await?
-        {
-            Some(permission) => {
-                // Check expiration
-                if let Some(expires_at) = &permission.expires_at
-                    && let Ok(expiry) = chrono::DateTime::parse_from_rfc3339(expires_at)
-                    && expiry < chrono::Utc::now()
-                {
-                    return Ok(PermissionStatus::Expired);
-                }
+            .await?;
+        Ok(permission_status(permission.as_ref()))
+    }
 
-                match permission.action {
-                    ApprovalAction::AllowOnce => {
-                        // One-time permissions are consumed after use
-                        Ok(PermissionStatus::Granted)
-                    }
-                    ApprovalAction::AlwaysAllow => Ok(PermissionStatus::Granted),
-                    ApprovalAction::Deny => Ok(PermissionStatus::Denied),
-                }
-            }
-            None => Ok(PermissionStatus::Required),
-        }
+    /// Check several tool executions at once, e.g. every step of a plan before
+    /// running any of them.
+    ///
+    /// All permissions are looked up in a single query. Statuses are returned
+    /// in the order of `requests`, so callers can report every step that still
+    /// needs approval instead of stopping at the first.
+    pub async fn check_permission_batch(
+        &self,
+        requests: &[(ToolId, ServiceId, ExternalUserId)],
+    ) -> ElicitationResult<Vec<PermissionStatus>> {
+        let keys: Vec<(String, String, String)> = requests
+            .iter()
+            .map(|(tool_id, service_id, user_id)| {
+                (
+                    tool_id.to_string(),
+                    service_id.to_string(),
+                    user_id.to_string(),
+                )
+            })
+            .collect();
+
+        let permissions = self.store.get_permissions_batch(&keys).await?;
+        Ok(permissions
+            .iter()
+            .map(|permission| permission_status(permission.as_ref()))
+            .collect())
     }
 
     /// Grant a permission for the given tool and user.
+    ///
+    /// The permission expires after the configured `permission_ttl`, if any.
+    /// Only admins can always allow a tool that requires the admin role.
     pub async fn grant_permission(
         &self,
         request: &ApprovalRequest,
         action: ApprovalAction,
     ) -> ElicitationResult<ToolPermission> {
+        check_grant_allowed(request, action)?;
+        let now = chrono::Utc::now();
+        let expires_at = self
+            .permission_ttl
+            .map(|ttl| {
+                chrono::Duration::from_std(ttl)
+                    .map(|ttl| (now + ttl).to_rfc3339())
+                    .map_err(|_| {
+                        ElicitationError::InvalidSchema("permission_ttl out of range".to_string())
+                    })
+            })
+            .transpose()?;
+
         let permission = ToolPermission {
             id: None,
             tool_id: request.tool_id.clone(),
             service_id: request.service_id.clone(),
             user_id: request.user_id.clone(),
             action,
-            created_at: chrono::Utc::now().to_rfc3339(),
-            expires_at: None, // TODO: Make configurable
+            created_at: now.to_rfc3339(),
+            expires_at,
         };
 
         self.store.save_permission(&permission).await
     }
 
+    /// Grant many permissions at once, e.g. when importing from another instance.
+    ///
+    /// All permissions are written in a single transaction together with one
+    /// bulk-grant audit entry attributed to `requested_by`; if any insert fails
+    /// nothing is written. A grant's `Duration` sets its expiry relative to now.
+    /// As with [`Self::grant_permission`], only admins can always allow a tool
+    /// that requires the admin role.
+    pub async fn bulk_grant(
+        &self,
+        grants: Vec<(ApprovalRequest, ApprovalAction, Option<Duration>)>,
+        requested_by: Option<&ExternalUserId>,
+    ) -> ElicitationResult<Vec<ToolPermission>> {
+        if grants.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let now = chrono::Utc::now();
+        let permissions = grants
+            .into_iter()
+            .map(|(request, action, expires_in)| {
+                check_grant_allowed(&request, action)?;
+                let expires_at = expires_in
+                    .map(|d| {
+                        chrono::Duration::from_std(d)
+                            .map(|d| (now + d).to_rfc3339())
+                            .map_err(|_| {
+                                ElicitationError::InvalidSchema(format!(
+                                    "expires_in out of range for tool {}",
+                                    request.tool_id
+                                ))
+                            })
+                    })
+                    .transpose()?;
+
+                Ok(ToolPermission {
+                    id: None,
+                    tool_id: request.tool_id,
+                    service_id: request.service_id,
+                    user_id: request.user_id,
+                    action,
+                    created_at: now.to_rfc3339(),
+                    expires_at,
+                })
+            })
+            .collect::<ElicitationResult<Vec<_>>>()?;
+
+        let mut users: Vec<&str> = permissions.iter().map(|p| p.user_id.as_str()).collect();
+        users.sort_unstable();
+        users.dedup();
+
+        let audit = AuditLogCreate {
+            user_id: requested_by.map(|id| id.to_string()),
+            action: AuditAction::PermissionsBulkGranted.as_str().to_string(),
+            resource_type: "permission".to_string(),
+            resource_id: None,
+            details: Some(serde_json::json!({
+                "count": permissions.len(),
+                "users": users,
+            })),
+            ip_address: None,
+            user_agent: None,
+        };
+
+        self.store.save_permissions(&permissions, audit).await
+    }
+
     /// Consume a one-time permission after use.
     pub async fn consume_permission(
         &self,
@@ -332,6 +497,8 @@ mod tests {
             service_name: ServiceName::new("GitHub"),
             user_id: ExternalUserId::new("user:test123"),
             arguments: Some(serde_json::json!({"path": "/tmp/test.txt"})),
+            required_role: None,
+            granter_roles: Vec::new(),
         }
     }
 
@@ -402,6 +569,118 @@ mod tests {
         assert_eq!(status, PermissionStatus::Granted);
     }
 
+    #[tokio::test]
+    async fn test_only_admins_always_allow_admin_tools() {
+        let (manager, _db) = setup_approval_manager().await;
+        let request = ApprovalRequest {
+            required_role: Some(ADMIN_ROLE.to_string()),
+            granter_roles: vec!["operator".to_string()],
+            ..test_request()
+        };
+
+        let err = manager
+            .grant_permission(&request, ApprovalAction::AlwaysAllow)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ElicitationError::Unauthorized(_)));
+        assert_eq!(
+            err.to_mcp_error().code,
+            rmcp::model::ErrorCode(crate::auth::UNAUTHORIZED_ERROR_CODE)
+        );
+        let err = manager
+            .bulk_grant(
+                vec![(request.clone(), ApprovalAction::AlwaysAllow, None)],
+                None,
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ElicitationError::Unauthorized(_)));
+        let status = manager
+            .check_permission(&request.tool_id, &request.service_id, &request.user_id)
+            .await
+            .unwrap();
+        assert_eq!(status, PermissionStatus::Required);
+
+        // Non-admins can still allow a single call
+        manager
+            .grant_permission(&request, ApprovalAction::AllowOnce)
+            .await
+            .unwrap();
+
+        let admin = ApprovalRequest {
+            granter_roles: vec![ADMIN_ROLE.to_string()],
+            ..request
+        };
+        manager
+            .grant_permission(&admin, ApprovalAction::AlwaysAllow)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_check_permission_batch_returns_statuses_in_request_order() {
+        let (manager, _db) = setup_approval_manager().await;
+        let allowed = test_request();
+        let denied = ApprovalRequest {
+            tool_id: ToolId::new("tool:delete_file"),
+            ..test_request()
+        };
+        manager
+            .grant_permission(&allowed, ApprovalAction::AlwaysAllow)
+            .await
+            .unwrap();
+        manager
+            .grant_permission(&denied, ApprovalAction::Deny)
+            .await
+            .unwrap();
+
+        let key = |request: &ApprovalRequest| {
+            (
+                request.tool_id.clone(),
+                request.service_id.clone(),
+                request.user_id.clone(),
+            )
+        };
+        let unknown = (
+            ToolId::new("tool:xyz"),
+            allowed.service_id.clone(),
+            allowed.user_id.clone(),
+        );
+        let other_user = (
+            allowed.tool_id.clone(),
+            allowed.service_id.clone(),
+            ExternalUserId::new("user:other"),
+        );
+
+        let statuses = manager
+            .check_permission_batch(&[
+                key(&denied),
+                unknown,
+                key(&allowed),
+                other_user,
+                key(&allowed),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(
+            statuses,
+            vec![
+                PermissionStatus::Denied,
+                PermissionStatus::Required,
+                PermissionStatus::Granted,
+                PermissionStatus::Required,
+                PermissionStatus::Granted,
+            ]
+        );
+        assert!(
+            manager
+                .check_permission_batch(&[])
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
     #[tokio::test]
     async fn test_check_permission_returns_granted_for_allow_once() {
         let (manager, _db) = setup_approval_manager().await;
@@ -848,4 +1127,185 @@ mod tests {
 
         assert_eq!(status, PermissionStatus::Granted);
     }
+
+    #[tokio::test]
+    async fn test_grant_permission_applies_configured_ttl() {
+        let db = setup_test_db().await;
+        let store = Arc::new(PermissionStore::new(db));
+        let config = ApprovalConfig {
+            permission_ttl: Some(Duration::from_secs(3600)),
+            ..Default::default()
+        };
+        let manager = ApprovalManager::new_with_config(store, &config);
+        let request = test_request();
+
+        let permission = manager
+            .grant_permission(&request, ApprovalAction::AlwaysAllow)
+            .await
+            .unwrap();
+        let expires_at = chrono::DateTime::parse_from_rfc3339(&permission.expires_at.unwrap())
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+        let remaining = expires_at - chrono::Utc::now();
+        assert!(remaining > chrono::Duration::minutes(59));
+        assert!(remaining <= chrono::Duration::hours(1));
+
+        let status = manager
+            .check_permission(&request.tool_id, &request.service_id, &request.user_id)
+            .await
+            .unwrap();
+        assert_eq!(status, PermissionStatus::Granted);
+    }
+
+    fn request_for(tool: &str, user: &str) -> ApprovalRequest {
+        ApprovalRequest {
+            tool_id: ToolId::new(tool),
+            service_id: ServiceId::new("service:github"),
+            service_name: ServiceName::new("GitHub"),
+            user_id: ExternalUserId::new(user),
+            arguments: None,
+            required_role: None,
+            granter_roles: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bulk_grant_inserts_all_permissions_and_one_audit_entry() {
+        let (manager, db) = setup_approval_manager().await;
+        let admin = ExternalUserId::new("user:admin");
+
+        let created = manager
+            .bulk_grant(
+                vec![
+                    (
+                        request_for("tool:a", "user:alice"),
+                        ApprovalAction::AlwaysAllow,
+                        None,
+                    ),
+                    (
+                        request_for("tool:b", "user:bob"),
+                        ApprovalAction::Deny,
+                        Some(Duration::from_secs(3600)),
+                    ),
+                ],
+                Some(&admin),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(created.len(), 2);
+        assert!(created.iter().all(|p| p.id.is_some()));
+        assert!(created[0].expires_at.is_none());
+        assert!(created[1].expires_at.is_some());
+
+        let status = manager
+            .check_permission(
+                &ToolId::new("tool:b"),
+                &ServiceId::new("service:github"),
+                &ExternalUserId::new("user:bob"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(status, PermissionStatus::Denied);
+
+        let mut res = db
+            .query("SELECT user_id, action, details FROM audit_log")
+            .await
+            .unwrap();
+        let audits: Vec<serde_json::Value> = res.take(0).unwrap();
+        assert_eq!(audits.len(), 1);
+        assert_eq!(audits[0]["action"], "permissions_bulk_granted");
+        assert_eq!(audits[0]["user_id"], "user:admin");
+        assert_eq!(audits[0]["details"]["count"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_bulk_grant_replaces_existing_permissions() {
+        let (manager, db) = setup_approval_manager().await;
+        manager
+            .grant_permission(&request_for("tool:a", "user:alice"), ApprovalAction::Deny)
+            .await
+            .unwrap();
+
+        let saved = manager
+            .bulk_grant(
+                vec![(
+                    request_for("tool:a", "user:alice"),
+                    ApprovalAction::AlwaysAllow,
+                    None,
+                )],
+                None,
+            )
+            .await
+            .unwrap();
+        assert!(saved[0].id.is_some());
+
+        let mut res = db
+            .query("SELECT VALUE action FROM permission WHERE user_id = 'user:alice'")
+            .await
+            .unwrap();
+        let actions: Vec<String> = res.take(0).unwrap();
+        assert_eq!(actions, vec!["always_allow".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_bulk_grant_checks_granter_roles() {
+        let (manager, _db) = setup_approval_manager().await;
+        let mut request = request_for("tool:admin", "user:alice");
+        request.required_role = Some(ADMIN_ROLE.to_string());
+
+        let result = manager
+            .bulk_grant(
+                vec![(request.clone(), ApprovalAction::AlwaysAllow, None)],
+                None,
+            )
+            .await;
+        assert!(matches!(result, Err(ElicitationError::Unauthorized(_))));
+
+        request.granter_roles = vec![ADMIN_ROLE.to_string()];
+        manager
+            .bulk_grant(vec![(request, ApprovalAction::AlwaysAllow, None)], None)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_bulk_grant_rolls_back_on_failure() {
+        let (manager, db) = setup_approval_manager().await;
+        db.query(
+            "DEFINE FIELD OVERWRITE tool_id ON permission TYPE string \
+             ASSERT string::starts_with($value, 'tool:')",
+        )
+        .await
+        .unwrap();
+
+        let result = manager
+            .bulk_grant(
+                vec![
+                    (
+                        request_for("tool:a", "user:alice"),
+                        ApprovalAction::AlwaysAllow,
+                        None,
+                    ),
+                    (
+                        request_for("not-a-tool", "user:alice"),
+                        ApprovalAction::AlwaysAllow,
+                        None,
+                    ),
+                ],
+                None,
+            )
+            .await;
+        assert!(result.is_err());
+
+        let permissions = manager
+            .list_user_permissions(&ExternalUserId::new("user:alice"))
+            .await
+            .unwrap();
+        assert!(permissions.is_empty());
+
+        let mut res = db.query("SELECT * FROM audit_log").await.unwrap();
+        let audits: Vec<serde_json::Value> = res.take(0).unwrap();
+        assert!(audits.is_empty());
+    }
 }
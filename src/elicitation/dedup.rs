@@ -0,0 +1,242 @@
+//! Sharing of identical elicitations asked of the same user concurrently.
+//!
+//! Parallel plan steps may need the same approval at the same time. Instead
+//! of prompting the user once per step, the first request is sent and later
+//! identical requests wait for its answer.
+
+use super::{ElicitationError, ElicitationResult, ElicitationSchema};
+use crate::types::{ExternalUserId, ToolId};
+use rmcp::model::CreateElicitationResult;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::watch;
+
+type SharedResult = ElicitationResult<CreateElicitationResult>;
+
+/// Identifies an elicitation: the user asked, and a hash of the tool, message
+/// and schema.
+pub type DedupKey = (ExternalUserId, String);
+
+/// Elicitations in flight, by [`DedupKey`]. Each entry receives the answer
+/// once the request that was actually sent completes.
+#[derive(Default)]
+pub struct ElicitationDeduplicator {
+    pending: Mutex<HashMap<DedupKey, watch::Receiver<Option<SharedResult>>>>,
+}
+
+impl ElicitationDeduplicator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Key of an elicitation about `tool_id` asked of `user_id`.
+    ///
+    /// The tool is part of the key so that approvals for different tools are
+    /// never shared, even if their messages happen to match.
+    pub fn key(
+        user_id: &ExternalUserId,
+        tool_id: &ToolId,
+        message: &str,
+        schema: &ElicitationSchema,
+    ) -> DedupKey {
+        let mut hasher = Sha256::new();
+        for part in [
+            tool_id.as_str().as_bytes(),
+            message.as_bytes(),
+            &serde_json::to_vec(schema).unwrap_or_default(),
+        ] {
+            hasher.update((part.len() as u64).to_be_bytes());
+            hasher.update(part);
+        }
+        (user_id.clone(), format!("{:x}", hasher.finalize()))
+    }
+
+    /// Run `send` unless an elicitation with the same key is already in
+    /// flight, in which case wait up to `timeout` for its result instead.
+    pub async fn run<F, Fut>(
+        self: &Arc<Self>,
+        key: DedupKey,
+        timeout: Duration,
+        send: F,
+    ) -> SharedResult
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = SharedResult>,
+    {
+        let sender = {
+            let mut pending = self.pending.lock().expect("dedup lock poisoned");
+            match pending.get(&key) {
+                Some(receiver) => Err(receiver.clone()),
+                None => {
+                    let (sender, receiver) = watch::channel(None);
+                    pending.insert(key.clone(), receiver);
+                    Ok(sender)
+                }
+            }
+        };
+
+        let sender = match sender {
+            Ok(sender) => sender,
+            Err(mut receiver) => {
+                tracing::debug!(user_id = %key.0, "Waiting on an identical pending elicitation");
+                return match tokio::time::timeout(
+                    timeout,
+                    receiver.wait_for(|result| result.is_some()),
+                )
+                .await
+                {
+                    Ok(Ok(result)) => result.clone().unwrap_or(Err(ElicitationError::Canceled)),
+                    // The original request was dropped before it was answered
+                    Ok(Err(_)) => Err(ElicitationError::Canceled),
+                    Err(_) => Err(ElicitationError::TimedOut { after: timeout }),
+                };
+            }
+        };
+
+        // Forget the entry even if this future is dropped mid-request, so later
+        // requests don't wait on an answer that will never come
+        let _guard = RemoveOnDrop {
+            deduplicator: self,
+            key,
+        };
+        let result = send().await;
+        sender.send_replace(Some(result.clone()));
+        result
+    }
+
+    /// Number of elicitations in flight.
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.pending.lock().expect("dedup lock poisoned").len()
+    }
+}
+
+struct RemoveOnDrop<'a> {
+    deduplicator: &'a ElicitationDeduplicator,
+    key: DedupKey,
+}
+
+impl Drop for RemoveOnDrop<'_> {
+    fn drop(&mut self) {
+        self.deduplicator
+            .pending
+            .lock()
+            .expect("dedup lock poisoned")
+            .remove(&self.key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::model::ElicitationAction;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn schema() -> ElicitationSchema {
+        ElicitationSchema::builder()
+            .required_string("reason")
+            .build()
+            .unwrap()
+    }
+
+    fn accepted() -> CreateElicitationResult {
+        CreateElicitationResult {
+            action: ElicitationAction::Accept,
+            content: Some(serde_json::json!({"reason": "ok"})),
+        }
+    }
+
+    #[test]
+    fn test_key_separates_users_tools_and_messages() {
+        let alice = ExternalUserId::new("alice");
+        let tool = ToolId::new("tool:a");
+        let key = ElicitationDeduplicator::key(&alice, &tool, "Allow?", &schema());
+
+        assert_eq!(
+            key,
+            ElicitationDeduplicator::key(&alice, &tool, "Allow?", &schema())
+        );
+        assert_ne!(
+            key,
+            ElicitationDeduplicator::key(&ExternalUserId::new("bob"), &tool, "Allow?", &schema())
+        );
+        assert_ne!(
+            key,
+            ElicitationDeduplicator::key(&alice, &ToolId::new("tool:b"), "Allow?", &schema())
+        );
+        assert_ne!(
+            key,
+            ElicitationDeduplicator::key(&alice, &tool, "Allow now?", &schema())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_identical_concurrent_elicitations_are_sent_once() {
+        let dedup = Arc::new(ElicitationDeduplicator::new());
+        let key = ElicitationDeduplicator::key(
+            &ExternalUserId::new("alice"),
+            &ToolId::new("tool:a"),
+            "Allow?",
+            &schema(),
+        );
+        let sent = AtomicUsize::new(0);
+        let (answer, answered) = tokio::sync::oneshot::channel::<()>();
+
+        // join! polls in order: the first request is sent and waits for the
+        // answer, the second finds it pending, then the user answers
+        let (first, second, ()) = tokio::join!(
+            dedup.run(key.clone(), Duration::from_secs(5), || async {
+                sent.fetch_add(1, Ordering::SeqCst);
+                answered.await.unwrap();
+                Ok(accepted())
+            }),
+            dedup.run(key, Duration::from_secs(5), || async {
+                sent.fetch_add(1, Ordering::SeqCst);
+                Ok(accepted())
+            }),
+            async {
+                answer.send(()).unwrap();
+            },
+        );
+        assert_eq!(sent.load(Ordering::SeqCst), 1);
+        assert_eq!(first.unwrap().action, ElicitationAction::Accept);
+        assert_eq!(second.unwrap().action, ElicitationAction::Accept);
+        assert_eq!(dedup.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_waiting_on_a_pending_elicitation_times_out() {
+        let dedup = Arc::new(ElicitationDeduplicator::new());
+        let key = ElicitationDeduplicator::key(
+            &ExternalUserId::new("alice"),
+            &ToolId::new("tool:a"),
+            "Allow?",
+            &schema(),
+        );
+
+        let first = {
+            let (dedup, key) = (dedup.clone(), key.clone());
+            tokio::spawn(async move {
+                dedup
+                    .run(key, Duration::from_secs(5), std::future::pending)
+                    .await
+            })
+        };
+        while dedup.len() == 0 {
+            tokio::task::yield_now().await;
+        }
+
+        let result = dedup
+            .run(key, Duration::from_millis(10), || async { Ok(accepted()) })
+            .await;
+        assert!(matches!(result, Err(ElicitationError::TimedOut { .. })));
+
+        // Dropping the original request forgets it
+        first.abort();
+        let _ = first.await;
+        assert_eq!(dedup.len(), 0);
+    }
+}
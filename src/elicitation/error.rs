@@ -2,8 +2,10 @@
 //!
 //! Includes MCP-spec error codes:
 //! - `-32042`: URL elicitation required (server needs OAuth/external auth)
+//! - `-32003`: Unauthorized (the user lacks a required role)
 
 use std::fmt;
+use std::time::Duration;
 
 /// MCP error code for URL elicitation required.
 ///
@@ -35,6 +37,12 @@ pub enum ElicitationError {
     /// The elicitation has expired.
     Expired,
 
+    /// The client did not respond to the elicitation in time.
+    TimedOut {
+        /// How long the orchestrator waited
+        after: Duration,
+    },
+
     /// The elicitation ID was not found.
     NotFound(String),
 
@@ -51,6 +59,14 @@ pub enum ElicitationError {
         provider: String,
     },
 
+    /// The user lacks the role needed for the operation.
+    ///
+    /// This maps to MCP error code -32003.
+    Unauthorized(String),
+
+    /// A signed document failed signature verification.
+    InvalidSignature,
+
     /// Database error occurred.
     Database(String),
 
@@ -67,12 +83,17 @@ impl fmt::Display for ElicitationError {
             Self::Declined => write!(f, "User declined the elicitation request"),
             Self::Canceled => write!(f, "User canceled the elicitation request"),
             Self::Expired => write!(f, "The elicitation has expired"),
+            Self::TimedOut { after } => {
+                write!(f, "Elicitation timed out after {}s", after.as_secs())
+            }
             Self::NotFound(id) => write!(f, "Elicitation not found: {}", id),
             Self::UrlElicitationRequired {
                 message, provider, ..
             } => {
                 write!(f, "URL elicitation required for {}: {}", provider, message)
             }
+            Self::Unauthorized(msg) => write!(f, "Unauthorized: {}", msg),
+            Self::InvalidSignature => write!(f, "Signature verification failed"),
             Self::Database(msg) => write!(f, "Database error: {}", msg),
             Self::Internal(msg) => write!(f, "Internal error: {}", msg),
         }
@@ -135,11 +156,24 @@ impl ElicitationError {
             Self::Expired => {
                 rmcp::ErrorData::new(ErrorCode(-32001), "Elicitation expired".to_string(), None)
             }
+            Self::TimedOut { after } => rmcp::ErrorData::new(
+                ErrorCode(-32001),
+                format!("Elicitation timed out after {}s", after.as_secs()),
+                None,
+            ),
             Self::NotFound(id) => rmcp::ErrorData::new(
                 ErrorCode(-32002),
                 format!("Elicitation not found: {}", id),
                 None,
             ),
+            Self::Unauthorized(msg) => rmcp::ErrorData::new(
+                ErrorCode(crate::auth::UNAUTHORIZED_ERROR_CODE),
+                format!("Unauthorized: {}", msg),
+                None,
+            ),
+            Self::InvalidSignature => {
+                rmcp::ErrorData::invalid_params("Signature verification failed".to_string(), None)
+            }
             Self::Database(msg) | Self::Internal(msg) => {
                 rmcp::ErrorData::internal_error(msg.clone(), None)
             }
@@ -24,6 +24,10 @@ struct TestClient {
     response_content: Option<serde_json::Value>,
     /// Track received elicitations for assertions
     received_elicitations: Arc<RwLock<Vec<CreateElicitationRequestParams>>>,
+    /// How long to wait before answering (simulates a user who walked away)
+    response_delay: std::time::Duration,
+    /// Track received cancellation notifications for assertions
+    received_cancellations: Arc<RwLock<Vec<CancelledNotificationParam>>>,
 }
 
 impl TestClient {
@@ -32,9 +36,22 @@ impl TestClient {
             response_action: action,
             response_content: content,
             received_elicitations: Arc::new(RwLock::new(Vec::new())),
+            response_delay: std::time::Duration::ZERO,
+            received_cancellations: Arc::new(RwLock::new(Vec::new())),
         }
     }
 
+    fn stalling(delay: std::time::Duration) -> Self {
+        Self {
+            response_delay: delay,
+            ..Self::declining()
+        }
+    }
+
+    fn received_cancellations(&self) -> Arc<RwLock<Vec<CancelledNotificationParam>>> {
+        self.received_cancellations.clone()
+    }
+
     fn accepting(content: serde_json::Value) -> Self {
         Self::new(ElicitationAction::Accept, Some(content))
     }
@@ -72,10 +89,12 @@ impl ClientHandler for TestClient {
         let action = self.response_action.clone();
         let content = self.response_content.clone();
         let received = self.received_elicitations.clone();
+        let delay = self.response_delay;
 
         async move {
             // Store the received request for later assertions
             received.write().await.push(request);
+            tokio::time::sleep(delay).await;
 
             Ok(CreateElicitationResult { action, content })
         }
@@ -83,10 +102,13 @@ impl ClientHandler for TestClient {
 
     fn on_cancelled(
         &self,
-        _notification: CancelledNotificationParam,
+        notification: CancelledNotificationParam,
         _context: NotificationContext<RoleClient>,
     ) -> impl Future<Output = ()> + Send + '_ {
-        std::future::ready(())
+        let received = self.received_cancellations.clone();
+        async move {
+            received.write().await.push(notification);
+        }
     }
 
     fn on_progress(
@@ -397,3 +419,69 @@ async fn test_elicitation_with_provenance() {
         Err(_) => panic!("Test timed out"),
     }
 }
+
+#[tokio::test]
+async fn test_elicitation_timeout_cancels_request() {
+    use super::{ElicitationCoordinator, ElicitationError};
+
+    let db = crate::db::create_connection(crate::db::DatabaseConfig {
+        url: "memory".to_string(),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+    let coordinator = ElicitationCoordinator::new(db).unwrap();
+    coordinator
+        .set_client_capabilities(&ClientCapabilities::builder().enable_elicitation().build())
+        .await;
+    coordinator
+        .set_timeout(std::time::Duration::from_millis(200))
+        .await;
+
+    let (client_stream, server_stream) = tokio::io::duplex(4096);
+
+    let server = TestServer::new();
+    let client = TestClient::stalling(std::time::Duration::from_secs(10));
+    let received_cancellations = client.received_cancellations();
+
+    let (server_read, server_write) = tokio::io::split(server_stream);
+    let (client_read, client_write) = tokio::io::split(client_stream);
+
+    let server_handle = tokio::spawn(async move {
+        let running = server.serve((server_read, server_write)).await.unwrap();
+        coordinator.set_peer(running.peer().clone()).await;
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+        let schema = ElicitationSchema::builder()
+            .required_string("reason")
+            .build()
+            .unwrap();
+
+        let result = coordinator
+            .create_elicitation("Why do you need access?", schema)
+            .await;
+
+        // Give the cancellation notification time to arrive
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        (running, result)
+    });
+
+    let client_handle =
+        tokio::spawn(async move { client.serve((client_read, client_write)).await });
+
+    let result = tokio::time::timeout(tokio::time::Duration::from_secs(5), async {
+        let (server_result, _) = tokio::join!(server_handle, client_handle);
+        server_result
+    })
+    .await;
+
+    match result {
+        Ok(Ok((_, Err(ElicitationError::TimedOut { after })))) => {
+            assert_eq!(after, std::time::Duration::from_millis(200));
+            assert_eq!(received_cancellations.read().await.len(), 1);
+        }
+        Ok(Ok((_, other))) => panic!("Expected a timeout, got {:?}", other),
+        Ok(Err(e)) => panic!("Server task failed: {:?}", e),
+        Err(_) => panic!("Test timed out"),
+    }
+}
@@ -0,0 +1,183 @@
+//! Completion, timeout and latency metrics for elicitations.
+
+use super::{ElicitationAction, ElicitationMode};
+use crate::metrics::{CounterVec, HistogramVec, MetricSource};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Bucket bounds of `elicitation_duration_seconds`. Users may take minutes to
+/// answer, so the buckets reach past the default timeout.
+const DURATION_BUCKETS: &[f64] = &[1.0, 5.0, 15.0, 30.0, 60.0, 120.0, 300.0, 600.0];
+
+/// URL-mode flows not completed within this long count as timed out.
+const MAX_URL_FLOW_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// Counters and durations of elicitations, labelled by `mode` (`form` or
+/// `url`) and, on completion, the client's `action`.
+pub struct ElicitationMetrics {
+    started: CounterVec,
+    completed: CounterVec,
+    timed_out: CounterVec,
+    duration: HistogramVec,
+    /// Start times of URL-mode flows awaiting their callback, by elicitation id.
+    url_started: Mutex<HashMap<String, Instant>>,
+}
+
+impl ElicitationMetrics {
+    pub fn new() -> Self {
+        Self {
+            started: CounterVec::new(
+                "elicitation_started_total",
+                "Elicitations sent to the client",
+                &["mode"],
+            ),
+            completed: CounterVec::new(
+                "elicitation_completed_total",
+                "Elicitations the client answered, by action",
+                &["mode", "action"],
+            ),
+            timed_out: CounterVec::new(
+                "elicitation_timed_out_total",
+                "Elicitations the client did not answer in time",
+                &["mode"],
+            ),
+            duration: HistogramVec::new(
+                "elicitation_duration_seconds",
+                "Time from sending an elicitation to its answer or timeout",
+                &["mode"],
+                DURATION_BUCKETS,
+            ),
+            url_started: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Record that an elicitation was sent.
+    pub fn record_started(&self, mode: ElicitationMode) {
+        self.started.inc(&[mode.as_str()]);
+    }
+
+    /// Record the client's answer to an elicitation started `elapsed` ago.
+    pub fn record_completed(
+        &self,
+        mode: ElicitationMode,
+        action: &ElicitationAction,
+        elapsed: Duration,
+    ) {
+        self.completed.inc(&[mode.as_str(), action_label(action)]);
+        self.duration
+            .observe(&[mode.as_str()], elapsed.as_secs_f64());
+    }
+
+    /// Record an elicitation that went unanswered for `elapsed`.
+    pub fn record_timed_out(&self, mode: ElicitationMode, elapsed: Duration) {
+        self.timed_out.inc(&[mode.as_str()]);
+        self.duration
+            .observe(&[mode.as_str()], elapsed.as_secs_f64());
+    }
+
+    /// Record the start of a URL-mode flow, remembered until its callback.
+    ///
+    /// Flows older than an hour are dropped and counted as timed out.
+    pub fn record_url_started(&self, elicitation_id: &str) {
+        self.record_started(ElicitationMode::Url);
+        self.expire_url_flows(Instant::now(), MAX_URL_FLOW_AGE);
+        self.url_started
+            .lock()
+            .expect("metrics lock poisoned")
+            .insert(elicitation_id.to_string(), Instant::now());
+    }
+
+    /// Record the callback of a URL-mode flow. Unknown flows are ignored.
+    pub fn record_url_completed(&self, elicitation_id: &str) {
+        let started_at = self
+            .url_started
+            .lock()
+            .expect("metrics lock poisoned")
+            .remove(elicitation_id);
+        if let Some(started_at) = started_at {
+            self.record_completed(
+                ElicitationMode::Url,
+                &ElicitationAction::Accept,
+                started_at.elapsed(),
+            );
+        }
+    }
+
+    /// Count URL-mode flows started `max_age` or longer before `now` as timed out.
+    fn expire_url_flows(&self, now: Instant, max_age: Duration) {
+        let mut expired = Vec::new();
+        self.url_started
+            .lock()
+            .expect("metrics lock poisoned")
+            .retain(|_, started_at| {
+                let age = now.saturating_duration_since(*started_at);
+                if age < max_age {
+                    return true;
+                }
+                expired.push(age);
+                false
+            });
+        for age in expired {
+            self.record_timed_out(ElicitationMode::Url, age);
+        }
+    }
+}
+
+impl Default for ElicitationMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MetricSource for ElicitationMetrics {
+    fn encode(&self, out: &mut String) {
+        self.started.encode(out);
+        self.completed.encode(out);
+        self.timed_out.encode(out);
+        self.duration.encode(out);
+    }
+}
+
+fn action_label(action: &ElicitationAction) -> &'static str {
+    match action {
+        ElicitationAction::Accept => "accept",
+        ElicitationAction::Decline => "decline",
+        ElicitationAction::Cancel => "cancel",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_elicitation_metrics_label_by_mode_and_action() {
+        let metrics = ElicitationMetrics::new();
+        metrics.record_started(ElicitationMode::Form);
+        metrics.record_completed(
+            ElicitationMode::Form,
+            &ElicitationAction::Decline,
+            Duration::from_secs(2),
+        );
+        metrics.record_url_started("elicit-1");
+        metrics.record_url_started("elicit-2");
+        metrics.record_url_completed("elicit-1");
+        // Unknown or already completed flows are ignored
+        metrics.record_url_completed("elicit-1");
+        metrics.expire_url_flows(Instant::now(), Duration::ZERO);
+
+        assert_eq!(metrics.started.get(&["form"]), 1);
+        assert_eq!(metrics.started.get(&["url"]), 2);
+        assert_eq!(metrics.completed.get(&["form", "decline"]), 1);
+        assert_eq!(metrics.completed.get(&["url", "accept"]), 1);
+        assert_eq!(metrics.timed_out.get(&["url"]), 1);
+        assert_eq!(metrics.duration.count(&["form"]), 1);
+        assert_eq!(metrics.duration.count(&["url"]), 2);
+
+        let mut out = String::new();
+        metrics.encode(&mut out);
+        assert!(out.contains("elicitation_completed_total{mode=\"form\",action=\"decline\"} 1\n"));
+        assert!(out.contains("elicitation_timed_out_total{mode=\"url\"} 1\n"));
+    }
+}
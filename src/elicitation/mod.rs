@@ -58,20 +58,26 @@
 //! - `-32042`: URL elicitation required (server needs OAuth/external auth before proceeding)
 
 mod approval;
+mod dedup;
 mod error;
 mod form;
 #[cfg(test)]
 mod integration_tests;
+mod metrics;
 mod provenance;
 mod store;
 mod url;
 
 pub use approval::{
-    ApprovalAction, ApprovalManager, ApprovalRequest, PermissionStatus, ToolPermission,
+    ADMIN_ROLE, ApprovalAction, ApprovalConfig, ApprovalManager, ApprovalRequest, PermissionStatus,
+    ToolPermission,
 };
 pub use error::{ElicitationError, ElicitationResult};
 pub use form::FormHandler;
-pub use provenance::{wrap_url_with_provenance, wrap_with_provenance};
+pub use metrics::ElicitationMetrics;
+pub use provenance::{
+    wrap_sampling_with_provenance, wrap_url_with_provenance, wrap_with_provenance,
+};
 pub use store::PermissionStore;
 pub use url::UrlHandler;
 
@@ -81,12 +87,18 @@ pub use rmcp::model::{
     PrimitiveSchema, StringFormat,
 };
 
-use crate::types::{OAuthUrl, ServiceName};
+use crate::types::{ExternalUserId, OAuthUrl, ServiceName, ToolId};
 use anyhow::Result;
+use dedup::ElicitationDeduplicator;
 use rmcp::model::ClientCapabilities;
-use rmcp::service::{Peer, RoleServer};
+use rmcp::model::{
+    CancelledNotificationParam, ClientResult, CreateElicitationRequest, RequestId, ServerRequest,
+};
+use rmcp::service::{Peer, PeerRequestOptions, RoleServer, ServiceError};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 
 /// Policy for handling tool execution when the client doesn't support elicitation.
@@ -104,6 +116,12 @@ pub enum ElicitationFallbackPolicy {
     /// Use this for backwards compatibility with older clients, but be aware
     /// this bypasses the approval system entirely for those clients.
     Allow,
+    /// Ask clients that support elicitation as usual; for those that don't,
+    /// grant an `AllowOnce` permission and run the tool. With `cache_result`
+    /// the permission is kept, so later calls to the tool are not re-evaluated
+    /// until it expires (see `ApprovalConfig::permission_ttl`). Meant for
+    /// low-risk tools in mixed-client environments.
+    AskOnce { cache_result: bool },
 }
 
 /// Elicitation modes supported by the orchestrator.
@@ -165,6 +183,10 @@ pub struct ElicitationCompleteNotification {
     pub elicitation_id: String,
 }
 
+/// How long an elicitation waits for the client unless configured otherwise.
+const DEFAULT_ELICITATION_TIMEOUT: Duration =
+    Duration::from_secs(crate::config::DEFAULT_ELICITATION_TIMEOUT_SECS as u64);
+
 /// Main elicitation coordinator.
 ///
 /// The coordinator manages all elicitation operations and routes requests
@@ -192,34 +214,53 @@ pub struct ElicitationCoordinator {
 
     /// Policy for handling clients that don't support elicitation
     fallback_policy: Arc<RwLock<ElicitationFallbackPolicy>>,
+
+    /// How long to wait for the client to answer an elicitation
+    timeout: Arc<RwLock<Duration>>,
+
+    /// Per-service overrides of `timeout`, keyed by service name
+    service_timeouts: Arc<RwLock<HashMap<String, Duration>>>,
+
+    /// Elicitation requests awaiting the client's answer, keyed by request id
+    pending: Arc<RwLock<HashMap<RequestId, PendingElicitation>>>,
+
+    /// Completion and timeout metrics, rendered on `GET /metrics`
+    metrics: Arc<ElicitationMetrics>,
+
+    /// Identical elicitations in flight for a user, shared across sessions
+    deduplicator: Arc<ElicitationDeduplicator>,
+}
+
+/// An elicitation request the client hasn't answered yet.
+struct PendingElicitation {
+    peer: Peer<RoleServer>,
+    /// Wakes the waiting caller when the request is cancelled.
+    cancel: tokio::sync::oneshot::Sender<()>,
 }
 
 impl ElicitationCoordinator {
     /// Create a new elicitation coordinator.
     pub fn new(db: surrealdb::Surreal<surrealdb::engine::any::Any>) -> Result<Self> {
-        let store = Arc::new(PermissionStore::new(db));
-        let approval_manager = Arc::new(ApprovalManager::new(store.clone()));
-        let form_handler = Arc::new(FormHandler::new());
-        let url_handler = Arc::new(UrlHandler::new(store.clone())?);
-
-        Ok(Self {
-            client_capabilities: Arc::new(RwLock::new(None)),
-            peer: Arc::new(RwLock::new(None)),
-            form_handler,
-            url_handler,
-            approval_manager,
-            store,
-            fallback_policy: Arc::new(RwLock::new(ElicitationFallbackPolicy::default())),
-        })
+        Self::new_with_policy(db, ElicitationFallbackPolicy::default())
     }
 
     /// Create a new elicitation coordinator with a specific fallback policy.
     pub fn new_with_policy(
         db: surrealdb::Surreal<surrealdb::engine::any::Any>,
         fallback_policy: ElicitationFallbackPolicy,
+    ) -> Result<Self> {
+        Self::new_with_config(db, fallback_policy, &ApprovalConfig::default())
+    }
+
+    /// Create a new elicitation coordinator with a fallback policy and
+    /// approval settings.
+    pub fn new_with_config(
+        db: surrealdb::Surreal<surrealdb::engine::any::Any>,
+        fallback_policy: ElicitationFallbackPolicy,
+        approval: &ApprovalConfig,
     ) -> Result<Self> {
         let store = Arc::new(PermissionStore::new(db));
-        let approval_manager = Arc::new(ApprovalManager::new(store.clone()));
+        let approval_manager = Arc::new(ApprovalManager::new_with_config(store.clone(), approval));
         let form_handler = Arc::new(FormHandler::new());
         let url_handler = Arc::new(UrlHandler::new(store.clone())?);
 
@@ -231,9 +272,33 @@ impl ElicitationCoordinator {
             approval_manager,
             store,
             fallback_policy: Arc::new(RwLock::new(fallback_policy)),
+            timeout: Arc::new(RwLock::new(approval.timeout)),
+            service_timeouts: Arc::new(RwLock::new(HashMap::new())),
+            pending: Arc::new(RwLock::new(HashMap::new())),
+            metrics: Arc::new(ElicitationMetrics::new()),
+            deduplicator: Arc::new(ElicitationDeduplicator::new()),
         })
     }
 
+    /// A coordinator for one client session.
+    ///
+    /// It shares permissions, policy, timeouts and metrics with `self`, but has
+    /// its own peer, client capabilities and pending requests, so elicitations
+    /// go only to the session's client.
+    pub fn for_session(&self) -> Self {
+        Self {
+            client_capabilities: Arc::new(RwLock::new(None)),
+            peer: Arc::new(RwLock::new(None)),
+            pending: Arc::new(RwLock::new(HashMap::new())),
+            ..self.clone()
+        }
+    }
+
+    /// Completion and timeout metrics of the elicitations sent so far.
+    pub fn metrics(&self) -> &Arc<ElicitationMetrics> {
+        &self.metrics
+    }
+
     /// Set the fallback policy for clients that don't support elicitation.
     pub async fn set_fallback_policy(&self, policy: ElicitationFallbackPolicy) {
         *self.fallback_policy.write().await = policy;
@@ -244,6 +309,34 @@ impl ElicitationCoordinator {
         *self.fallback_policy.read().await
     }
 
+    /// Set how long elicitation requests wait for the client to respond.
+    ///
+    /// Applies to form-mode requests and to the lifetime of URL-mode OAuth state.
+    pub async fn set_timeout(&self, duration: Duration) {
+        *self.timeout.write().await = duration;
+    }
+
+    /// Get the current elicitation timeout.
+    pub async fn timeout(&self) -> Duration {
+        *self.timeout.read().await
+    }
+
+    /// Override the timeout for elicitations forwarded from a downstream service.
+    pub async fn set_service_timeout(&self, service_name: impl Into<String>, duration: Duration) {
+        self.service_timeouts
+            .write()
+            .await
+            .insert(service_name.into(), duration);
+    }
+
+    /// Get the timeout for elicitations forwarded from `service_name`.
+    async fn service_timeout(&self, service_name: &str) -> Duration {
+        match self.service_timeouts.read().await.get(service_name) {
+            Some(timeout) => *timeout,
+            None => self.timeout().await,
+        }
+    }
+
     /// Store the peer reference for sending elicitation requests.
     ///
     /// This should be called during `initialize` when we receive the peer from context.
@@ -332,7 +425,46 @@ impl ElicitationCoordinator {
         message: impl Into<String>,
         schema: ElicitationSchema,
     ) -> ElicitationResult<CreateElicitationResult> {
-        self.create_elicitation_internal(message.into(), schema)
+        let timeout = self.timeout().await;
+        self.create_elicitation_internal(message.into(), schema, timeout, ElicitationMode::Form)
+            .await
+    }
+
+    /// Send an elicitation request, giving up after `timeout`.
+    ///
+    /// On expiry the client is sent a `notifications/cancelled` for the request
+    /// and `ElicitationError::TimedOut` is returned.
+    pub async fn create_elicitation_with_timeout(
+        &self,
+        message: impl Into<String>,
+        schema: ElicitationSchema,
+        timeout: Duration,
+    ) -> ElicitationResult<CreateElicitationResult> {
+        self.create_elicitation_internal(message.into(), schema, timeout, ElicitationMode::Form)
+            .await
+    }
+
+    /// Ask `user_id` about `tool_id`, sharing the answer with identical
+    /// requests already waiting.
+    ///
+    /// When parallel steps need the same approval, only the first request is
+    /// sent; the others wait for its answer, up to `service_name`'s
+    /// elicitation timeout. Requests about different tools are never shared.
+    pub async fn create_user_elicitation(
+        &self,
+        user_id: &ExternalUserId,
+        tool_id: &ToolId,
+        service_name: &str,
+        message: impl Into<String>,
+        schema: ElicitationSchema,
+    ) -> ElicitationResult<CreateElicitationResult> {
+        let message = message.into();
+        let timeout = self.service_timeout(service_name).await;
+        let key = ElicitationDeduplicator::key(user_id, tool_id, &message, &schema);
+        self.deduplicator
+            .run(key, timeout, || {
+                self.create_elicitation_internal(message, schema, timeout, ElicitationMode::Form)
+            })
             .await
     }
 
@@ -357,7 +489,8 @@ impl ElicitationCoordinator {
         service_name: &str,
     ) -> ElicitationResult<CreateElicitationResult> {
         let wrapped_message = wrap_with_provenance(message, service_name);
-        self.create_elicitation_internal(wrapped_message, schema)
+        let timeout = self.service_timeout(service_name).await;
+        self.create_elicitation_internal(wrapped_message, schema, timeout, ElicitationMode::Form)
             .await
     }
 
@@ -382,15 +515,82 @@ impl ElicitationCoordinator {
         service_name: &str,
         service_id: &str,
     ) -> UrlElicitationRequest {
+        self.metrics.record_url_started(&request.elicitation_id);
         wrap_url_with_provenance(request, service_name, service_id)
     }
 
+    /// Create a URL-mode OAuth elicitation for `user_id`, pointing at the
+    /// orchestrator's connect URL for `provider`.
+    ///
+    /// The caller delivers the returned request to the client. The flow must
+    /// be completed with [`Self::complete_url_elicitation`] within the
+    /// elicitation timeout.
+    pub async fn create_url_elicitation(
+        &self,
+        user_id: &str,
+        provider: &str,
+        redirect_uri: &str,
+        message: impl Into<String>,
+    ) -> ElicitationResult<UrlElicitationRequest> {
+        let elicitation_id = self
+            .create_url_elicitation_state(user_id, provider, redirect_uri)
+            .await?;
+        let connect_url = self
+            .url_handler
+            .build_connect_url(provider, &elicitation_id);
+        self.metrics.record_url_started(&elicitation_id);
+
+        Ok(UrlElicitationRequest {
+            message: message.into(),
+            url: OAuthUrl::new(connect_url),
+            elicitation_id,
+            service_name: Some(ServiceName::new(provider)),
+        })
+    }
+
+    /// Create the OAuth state for a URL-mode elicitation.
+    ///
+    /// The state expires after the elicitation timeout, so a flow the user
+    /// abandons cannot be completed later.
+    async fn create_url_elicitation_state(
+        &self,
+        user_id: &str,
+        provider: &str,
+        redirect_uri: &str,
+    ) -> ElicitationResult<String> {
+        let timeout = self.timeout().await;
+        self.url_handler
+            .create_oauth_state(user_id, provider, redirect_uri, timeout.as_secs())
+            .await
+    }
+
+    /// Cancel every elicitation still waiting for the client, sending each a
+    /// `notifications/cancelled`. Waiting callers get `ElicitationError::Canceled`.
+    ///
+    /// Returns the number of cancelled requests.
+    pub async fn cancel_pending_elicitations(&self, reason: &str) -> usize {
+        let pending: Vec<_> = self.pending.write().await.drain().collect();
+        let count = pending.len();
+        for (request_id, elicitation) in pending {
+            let params = CancelledNotificationParam {
+                request_id,
+                reason: Some(reason.to_string()),
+            };
+            if let Err(e) = elicitation.peer.notify_cancelled(params).await {
+                tracing::debug!("Failed to send elicitation cancellation: {:?}", e);
+            }
+            let _ = elicitation.cancel.send(());
+        }
+        count
+    }
+
     /// Complete a URL-mode elicitation (e.g., after OAuth callback).
     ///
     /// This should be called when the OAuth callback is received, indicating
     /// the user has completed the URL-mode authorization flow. It:
-    /// 1. Consumes the OAuth state (preventing replay)
-    /// 2. Logs the completion for audit purposes
+    /// 1. Rejects flows whose OAuth state has expired
+    /// 2. Consumes the OAuth state (preventing replay)
+    /// 3. Logs the completion for audit purposes
     ///
     /// Note: URL mode doesn't use the standard MCP elicitation/create flow,
     /// so there's no response to send back. The service should instead check
@@ -401,8 +601,13 @@ impl ElicitationCoordinator {
     ///
     /// # Returns
     /// * `Ok(())` - If the elicitation was found and marked complete
+    /// * `Err(ElicitationError::Expired)` - If the elicitation timed out
     /// * `Err` - If the elicitation was not found or already consumed
     pub async fn complete_url_elicitation(&self, elicitation_id: &str) -> ElicitationResult<()> {
+        self.url_handler
+            .validate_oauth_state(elicitation_id)
+            .await?;
+
         tracing::info!(
             elicitation_id = elicitation_id,
             "URL-mode elicitation completed"
@@ -410,6 +615,7 @@ impl ElicitationCoordinator {
 
         // Consume the OAuth state (marks it as used, prevents replay)
         self.url_handler.complete_oauth_flow(elicitation_id).await?;
+        self.metrics.record_url_completed(elicitation_id);
 
         Ok(())
     }
@@ -449,10 +655,14 @@ impl ElicitationCoordinator {
     }
 
     /// Internal implementation for sending elicitation requests.
+    ///
+    /// `mode` labels the request in the elicitation metrics.
     async fn create_elicitation_internal(
         &self,
         message: impl Into<String>,
         schema: ElicitationSchema,
+        timeout: Duration,
+        mode: ElicitationMode,
     ) -> ElicitationResult<CreateElicitationResult> {
         // Check if client supports elicitation
         if !self.client_supports_elicitation().await {
@@ -462,9 +672,11 @@ impl ElicitationCoordinator {
         }
 
         // Get the peer
-        let peer_guard = self.peer.read().await;
-        let peer = peer_guard
-            .as_ref()
+        let peer = self
+            .peer
+            .read()
+            .await
+            .clone()
             .ok_or_else(|| ElicitationError::Internal("No peer connected".to_string()))?;
 
         // Create the request parameters
@@ -473,11 +685,66 @@ impl ElicitationCoordinator {
             requested_schema: schema,
             meta: None,
         };
+        let request = ServerRequest::CreateElicitationRequest(CreateElicitationRequest {
+            method: Default::default(),
+            params,
+            extensions: Default::default(),
+        });
+        let options = PeerRequestOptions {
+            timeout: Some(timeout),
+            meta: None,
+        };
+
+        let handle = peer
+            .send_cancellable_request(request, options)
+            .await
+            .map_err(|e| {
+                ElicitationError::Internal(format!("Elicitation request failed: {:?}", e))
+            })?;
+        let request_id = handle.id.clone();
+        let started_at = Instant::now();
+        self.metrics.record_started(mode);
+        let (cancel, cancelled) = tokio::sync::oneshot::channel();
+        self.pending
+            .write()
+            .await
+            .insert(request_id.clone(), PendingElicitation { peer, cancel });
 
-        // Send the elicitation request
-        let result = peer.create_elicitation(params).await.map_err(|e| {
-            ElicitationError::Internal(format!("Elicitation request failed: {:?}", e))
-        })?;
+        // Wait for the answer; on timeout rmcp notifies the client that the
+        // request was cancelled
+        let response = tokio::select! {
+            response = handle.await_response() => Some(response),
+            _ = cancelled => None,
+        };
+        self.pending.write().await.remove(&request_id);
+
+        let result = match response {
+            Some(Ok(ClientResult::CreateElicitationResult(result))) => {
+                self.metrics
+                    .record_completed(mode, &result.action, started_at.elapsed());
+                result
+            }
+            Some(Ok(_)) => {
+                return Err(ElicitationError::Internal(
+                    "Unexpected response to elicitation request".to_string(),
+                ));
+            }
+            Some(Err(ServiceError::Timeout { timeout })) => {
+                tracing::warn!(
+                    timeout_secs = timeout.as_secs(),
+                    "Elicitation request timed out"
+                );
+                self.metrics.record_timed_out(mode, started_at.elapsed());
+                return Err(ElicitationError::TimedOut { after: timeout });
+            }
+            Some(Err(e)) => {
+                return Err(ElicitationError::Internal(format!(
+                    "Elicitation request failed: {:?}",
+                    e
+                )));
+            }
+            None => return Err(ElicitationError::Canceled),
+        };
 
         Ok(result)
     }
@@ -563,6 +830,40 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_fallback_policy_ask_once_round_trips() {
+        let policy = ElicitationFallbackPolicy::AskOnce { cache_result: true };
+        let json = serde_json::to_string(&policy).unwrap();
+        assert_eq!(json, r#"{"ask_once":{"cache_result":true}}"#);
+        assert_eq!(
+            serde_json::from_str::<ElicitationFallbackPolicy>(&json).unwrap(),
+            policy
+        );
+    }
+
+    #[tokio::test]
+    async fn test_set_fallback_policy_ask_once() {
+        let db_config = crate::db::DatabaseConfig {
+            url: "memory".to_string(),
+            ..Default::default()
+        };
+        let db = crate::db::create_connection(db_config).await.unwrap();
+        let policy = ElicitationFallbackPolicy::AskOnce {
+            cache_result: false,
+        };
+
+        let coordinator = ElicitationCoordinator::new_with_policy(db, policy).unwrap();
+        assert_eq!(coordinator.fallback_policy().await, policy);
+
+        coordinator
+            .set_fallback_policy(ElicitationFallbackPolicy::AskOnce { cache_result: true })
+            .await;
+        assert_eq!(
+            coordinator.fallback_policy().await,
+            ElicitationFallbackPolicy::AskOnce { cache_result: true }
+        );
+    }
+
     #[test]
     fn test_fallback_policy_deserialize_invalid_fails() {
         let result = serde_json::from_str::<ElicitationFallbackPolicy>("\"invalid\"");
@@ -682,6 +983,59 @@ mod tests {
         assert!(Arc::strong_count(manager) >= 1);
     }
 
+    #[tokio::test]
+    async fn test_url_elicitation_completes_once_within_timeout() {
+        let db = setup_test_db().await;
+        let coordinator = ElicitationCoordinator::new(db).unwrap();
+
+        let request = coordinator
+            .create_url_elicitation(
+                "user123",
+                "github",
+                "http://localhost/callback",
+                "Authorize GitHub",
+            )
+            .await
+            .unwrap();
+        assert!(request.url.as_str().contains(&request.elicitation_id));
+
+        coordinator
+            .complete_url_elicitation(&request.elicitation_id)
+            .await
+            .unwrap();
+        assert!(matches!(
+            coordinator
+                .complete_url_elicitation(&request.elicitation_id)
+                .await,
+            Err(ElicitationError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_expired_url_elicitation_is_rejected() {
+        let db = setup_test_db().await;
+        let coordinator = ElicitationCoordinator::new(db).unwrap();
+        coordinator.set_timeout(Duration::from_secs(1)).await;
+
+        let request = coordinator
+            .create_url_elicitation(
+                "user123",
+                "github",
+                "http://localhost/callback",
+                "Authorize GitHub",
+            )
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+
+        assert!(matches!(
+            coordinator
+                .complete_url_elicitation(&request.elicitation_id)
+                .await,
+            Err(ElicitationError::Expired)
+        ));
+    }
+
     #[tokio::test]
     async fn test_coordinator_store_accessible() {
         let db = setup_test_db().await;
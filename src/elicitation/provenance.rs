@@ -7,6 +7,7 @@
 //! service is asking for information before approving.
 
 use crate::elicitation::UrlElicitationRequest;
+use rmcp::model::CreateMessageRequestParams;
 
 /// Wrap an elicitation request with service provenance.
 ///
@@ -68,6 +69,27 @@ pub fn wrap_with_provenance(message: &str, service_name: &str) -> String {
     ProvenanceWrapper.wrap_message(message, service_name)
 }
 
+/// Prefix a sampling request's system prompt with the service, and the tool
+/// if known, that sent it, so the user can tell who is asking for a
+/// completion. `{origin}` in `prefix` is replaced by `service_id/tool_name`.
+pub fn wrap_sampling_with_provenance(
+    mut params: CreateMessageRequestParams,
+    prefix: &str,
+    service_id: &str,
+    tool_name: Option<&str>,
+) -> CreateMessageRequestParams {
+    let origin = match tool_name {
+        Some(tool_name) => format!("{}/{}", service_id, tool_name),
+        None => service_id.to_string(),
+    };
+    let prefix = prefix.replace("{origin}", &origin);
+    params.system_prompt = Some(match params.system_prompt.take() {
+        Some(system_prompt) => format!("{} {}", prefix, system_prompt),
+        None => prefix,
+    });
+    params
+}
+
 /// Convenience function to wrap a URL elicitation request with provenance.
 pub fn wrap_url_with_provenance(
     request: UrlElicitationRequest,
@@ -81,6 +103,51 @@ pub fn wrap_url_with_provenance(
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_wrap_sampling_with_provenance() {
+        use rmcp::model::{Content, Role, SamplingMessage};
+
+        let params = CreateMessageRequestParams {
+            meta: None,
+            task: None,
+            messages: vec![SamplingMessage {
+                role: Role::User,
+                content: Content::text("Summarize this"),
+            }],
+            model_preferences: None,
+            system_prompt: Some("You are terse.".to_string()),
+            include_context: None,
+            temperature: None,
+            max_tokens: 100,
+            stop_sequences: None,
+            metadata: None,
+        };
+
+        let prefix = crate::config::DEFAULT_SAMPLING_PROVENANCE_PREFIX;
+        let wrapped =
+            wrap_sampling_with_provenance(params.clone(), prefix, "github", Some("search"));
+        assert_eq!(
+            wrapped.system_prompt.as_deref(),
+            Some("[Requested by github/search] You are terse.")
+        );
+
+        let custom = wrap_sampling_with_provenance(params.clone(), "<{origin}>", "github", None);
+        assert_eq!(
+            custom.system_prompt.as_deref(),
+            Some("<github> You are terse.")
+        );
+
+        let params = CreateMessageRequestParams {
+            system_prompt: None,
+            ..params
+        };
+        let wrapped = wrap_sampling_with_provenance(params, prefix, "github", None);
+        assert_eq!(
+            wrapped.system_prompt.as_deref(),
+            Some("[Requested by github]")
+        );
+    }
+
     #[test]
     fn test_wrap_message() {
         let wrapper = ProvenanceWrapper;
@@ -4,9 +4,15 @@
 //! - Tool approval permissions
 //! - OAuth state for URL mode elicitations
 //! - User preferences for elicitation
+//! - Signed permission exports for data portability
 
+use crate::db::schema::{AuditAction, AuditLogCreate};
 use crate::elicitation::{ElicitationError, ElicitationResult, ToolPermission};
 use crate::types::{ExternalUserId, IdentityProvider, RedirectUri};
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
 use std::sync::Arc;
 use surrealdb::Surreal;
 use surrealdb::engine::any::Any;
@@ -18,8 +24,18 @@ pub struct PermissionStore {
     db: Surreal<Any>,
     /// In-memory cache for OAuth state (for security - don't persist to DB)
     oauth_state: Arc<Mutex<std::collections::HashMap<String, OAuthEntry>>>,
+    /// HMAC key used to sign permission exports
+    signing_key: Arc<Vec<u8>>,
 }
 
+/// Environment variable holding the key used to sign permission exports.
+pub const SIGNING_KEY_ENV_VAR: &str = "ORCHESTRATOR_SIGNING_KEY";
+
+/// Version of the `PortablePermissionSet` document format.
+const PORTABLE_FORMAT_VERSION: u32 = 1;
+
+type HmacSha256 = Hmac<Sha256>;
+
 /// OAuth state entry for URL mode elicitation.
 #[derive(Clone, Debug)]
 struct OAuthEntry {
@@ -34,13 +50,38 @@ struct OAuthEntry {
 
 impl PermissionStore {
     /// Create a new permission store.
+    ///
+    /// Permission exports are signed with the key in `ORCHESTRATOR_SIGNING_KEY`.
+    /// Without it, a random key is generated, so exports can only be imported
+    /// back into the same process.
     pub fn new(db: Surreal<Any>) -> Self {
+        let signing_key = match std::env::var(SIGNING_KEY_ENV_VAR) {
+            Ok(key) if !key.is_empty() => key.into_bytes(),
+            _ => {
+                tracing::warn!(
+                    "{} is not set; permission exports are signed with an ephemeral key",
+                    SIGNING_KEY_ENV_VAR
+                );
+                [uuid::Uuid::new_v4(), uuid::Uuid::new_v4()]
+                    .iter()
+                    .flat_map(|id| id.into_bytes())
+                    .collect()
+            }
+        };
+
         Self {
             db,
             oauth_state: Arc::new(Mutex::new(std::collections::HashMap::new())),
+            signing_key: Arc::new(signing_key),
         }
     }
 
+    /// Use `key` to sign and verify permission exports.
+    pub fn with_signing_key(mut self, key: impl Into<Vec<u8>>) -> Self {
+        self.signing_key = Arc::new(key.into());
+        self
+    }
+
     /// Save a tool permission.
     pub async fn save_permission(
         &self,
@@ -93,6 +134,89 @@ impl PermissionStore {
         Ok(result)
     }
 
+    /// Save several tool permissions in a single transaction.
+    ///
+    /// A permission replaces any stored one for the same tool, service and
+    /// user. Either every permission and the audit entry are written, or none are.
+    pub async fn save_permissions(
+        &self,
+        permissions: &[ToolPermission],
+        audit: AuditLogCreate,
+    ) -> ElicitationResult<Vec<ToolPermission>> {
+        let rows: Vec<serde_json::Value> = permissions
+            .iter()
+            .map(|permission| {
+                serde_json::json!({
+                    "tool_id": permission.tool_id,
+                    "service_id": permission.service_id,
+                    "user_id": permission.user_id,
+                    "action": permission.action,
+                    "created_at": permission.created_at,
+                    "expires_at": permission.expires_at,
+                })
+            })
+            .collect();
+
+        let query = r#"
+            BEGIN TRANSACTION;
+            FOR $row IN $rows {
+                LET $existing = (
+                    SELECT VALUE id FROM permission
+                    WHERE tool_id = $row.tool_id
+                      AND service_id = $row.service_id
+                      AND user_id = $row.user_id
+                );
+                IF array::len($existing) > 0 {
+                    UPDATE $existing SET action = $row.action, expires_at = $row.expires_at;
+                } ELSE {
+                    CREATE permission CONTENT $row;
+                };
+            };
+            CREATE audit_log CONTENT $audit;
+            SELECT id, tool_id, service_id, user_id FROM permission
+            WHERE [tool_id, service_id, user_id] INSIDE $rows.map(|$row| [$row.tool_id, $row.service_id, $row.user_id]);
+            COMMIT TRANSACTION;
+        "#;
+
+        let mut res = self
+            .db
+            .query(query)
+            .bind(("rows", rows))
+            .bind(("audit", audit))
+            .await
+            .map_err(|e| ElicitationError::Database(e.to_string()))?
+            .check()
+            .map_err(|e| ElicitationError::Database(e.to_string()))?;
+
+        #[derive(serde::Deserialize)]
+        struct Saved {
+            id: surrealdb::RecordId,
+            tool_id: String,
+            service_id: String,
+            user_id: String,
+        }
+
+        let saved: Vec<Saved> = res
+            .take(2)
+            .map_err(|e| ElicitationError::Database(e.to_string()))?;
+
+        Ok(permissions
+            .iter()
+            .map(|permission| {
+                let mut result = permission.clone();
+                result.id = saved
+                    .iter()
+                    .find(|row| {
+                        row.tool_id == permission.tool_id.as_str()
+                            && row.service_id == permission.service_id.as_str()
+                            && row.user_id == permission.user_id.as_str()
+                    })
+                    .map(|row| row.id.to_string());
+                result
+            })
+            .collect())
+    }
+
     /// Get a permission for a specific tool, service, and user.
     pub async fn get_permission(
         &self,
@@ -138,6 +262,66 @@ impl PermissionStore {
         Ok(result.into_iter().next())
     }
 
+    /// Get the permissions for several `(tool_id, service_id, user_id)` keys
+    /// in a single query.
+    ///
+    /// Results are in the order of `keys`, with the newest permission for each.
+    pub async fn get_permissions_batch(
+        &self,
+        keys: &[(String, String, String)],
+    ) -> ElicitationResult<Vec<Option<ToolPermission>>> {
+        if keys.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let batch_params: Vec<[&str; 3]> = keys
+            .iter()
+            .map(|(tool_id, service_id, user_id)| {
+                [tool_id.as_str(), service_id.as_str(), user_id.as_str()]
+            })
+            .collect();
+
+        // Select specific fields to avoid id deserialization issues
+        let query = r#"
+            SELECT
+                meta::id(id) as id,
+                tool_id,
+                service_id,
+                user_id,
+                action,
+                created_at,
+                expires_at
+            FROM permission
+            WHERE [tool_id, service_id, user_id] INSIDE $batch_params
+            ORDER BY created_at DESC
+        "#;
+
+        let mut res = self
+            .db
+            .query(query)
+            .bind(("batch_params", serde_json::json!(batch_params)))
+            .await
+            .map_err(|e| ElicitationError::Database(e.to_string()))?;
+
+        let permissions: Vec<ToolPermission> = res
+            .take(0)
+            .map_err(|e| ElicitationError::Database(e.to_string()))?;
+
+        Ok(keys
+            .iter()
+            .map(|(tool_id, service_id, user_id)| {
+                permissions
+                    .iter()
+                    .find(|permission| {
+                        permission.tool_id.as_str() == tool_id
+                            && permission.service_id.as_str() == service_id
+                            && permission.user_id.as_str() == user_id
+                    })
+                    .cloned()
+            })
+            .collect())
+    }
+
     /// Delete a specific permission.
     pub async fn delete_permission(
         &self,
@@ -267,6 +451,190 @@ impl PermissionStore {
     }
 }
 
+/// A user's tool permissions exported as a signed, timestamped document.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PortablePermissionSet {
+    /// Document format version
+    pub format_version: u32,
+    /// The user the permissions were exported for
+    pub user_id: ExternalUserId,
+    /// When the export was made (RFC 3339)
+    pub exported_at: String,
+    /// The exported permissions, without their database IDs
+    pub permissions: Vec<ToolPermission>,
+    /// Base64 HMAC-SHA256 over the other fields
+    pub signature: String,
+}
+
+/// The fields of a `PortablePermissionSet` covered by its signature.
+#[derive(Serialize)]
+struct SignedPermissionSet<'a> {
+    format_version: u32,
+    user_id: &'a ExternalUserId,
+    exported_at: &'a str,
+    permissions: &'a [ToolPermission],
+}
+
+impl PortablePermissionSet {
+    fn signing_payload(&self) -> ElicitationResult<Vec<u8>> {
+        serde_json::to_vec(&SignedPermissionSet {
+            format_version: self.format_version,
+            user_id: &self.user_id,
+            exported_at: &self.exported_at,
+            permissions: &self.permissions,
+        })
+        .map_err(|e| ElicitationError::Internal(e.to_string()))
+    }
+}
+
+/// What to do when an imported permission matches one the target user already has.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    /// Keep the existing permission and drop the imported one
+    #[default]
+    Skip,
+    /// Replace the existing permission with the imported one
+    Overwrite,
+}
+
+impl PermissionStore {
+    fn mac(&self) -> HmacSha256 {
+        HmacSha256::new_from_slice(&self.signing_key).expect("HMAC accepts keys of any length")
+    }
+
+    fn sign(&self, set: &PortablePermissionSet) -> ElicitationResult<String> {
+        let mut mac = self.mac();
+        mac.update(&set.signing_payload()?);
+        Ok(base64::engine::general_purpose::STANDARD.encode(mac.finalize().into_bytes()))
+    }
+
+    /// Export all of a user's permissions as a signed document.
+    pub async fn export_permissions(
+        &self,
+        user_id: &str,
+    ) -> ElicitationResult<PortablePermissionSet> {
+        let query = r#"
+            SELECT
+                tool_id,
+                service_id,
+                user_id,
+                action,
+                created_at,
+                expires_at
+            FROM permission
+            WHERE user_id = $user_id
+            ORDER BY created_at ASC
+        "#;
+
+        let mut res = self
+            .db
+            .query(query)
+            .bind(("user_id", user_id.to_string()))
+            .await
+            .map_err(|e| ElicitationError::Database(e.to_string()))?;
+
+        let permissions: Vec<ToolPermission> = res
+            .take(0)
+            .map_err(|e| ElicitationError::Database(e.to_string()))?;
+
+        let mut set = PortablePermissionSet {
+            format_version: PORTABLE_FORMAT_VERSION,
+            user_id: ExternalUserId::new(user_id),
+            exported_at: chrono::Utc::now().to_rfc3339(),
+            permissions,
+            signature: String::new(),
+        };
+
+        set.signature = self.sign(&set)?;
+
+        Ok(set)
+    }
+
+    /// Import an exported permission set for `target_user_id`.
+    ///
+    /// The set's signature must have been made with this store's signing key.
+    /// Expired permissions are dropped, and permissions the target user already
+    /// has for the same tool and service are handled according to `conflict`.
+    /// Returns the permissions that were written.
+    pub async fn import_permissions(
+        &self,
+        set: PortablePermissionSet,
+        target_user_id: &str,
+        conflict: ConflictPolicy,
+    ) -> ElicitationResult<Vec<ToolPermission>> {
+        let signature = base64::engine::general_purpose::STANDARD
+            .decode(&set.signature)
+            .map_err(|_| ElicitationError::InvalidSignature)?;
+        let mut mac = self.mac();
+        mac.update(&set.signing_payload()?);
+        mac.verify_slice(&signature)
+            .map_err(|_| ElicitationError::InvalidSignature)?;
+
+        let now = chrono::Utc::now();
+        let mut permissions = Vec::new();
+        let mut skipped = 0;
+
+        for permission in &set.permissions {
+            let expired = permission
+                .expires_at
+                .as_deref()
+                .and_then(|at| chrono::DateTime::parse_from_rfc3339(at).ok())
+                .is_some_and(|at| at < now);
+            if expired {
+                skipped += 1;
+                continue;
+            }
+
+            let tool_id = permission.tool_id.as_str();
+            let service_id = permission.service_id.as_str();
+            if self
+                .get_permission(tool_id, service_id, target_user_id)
+                .await?
+                .is_some()
+            {
+                match conflict {
+                    ConflictPolicy::Skip => {
+                        skipped += 1;
+                        continue;
+                    }
+                    ConflictPolicy::Overwrite => {
+                        self.delete_permission(tool_id, service_id, target_user_id)
+                            .await?
+                    }
+                }
+            }
+
+            permissions.push(ToolPermission {
+                id: None,
+                user_id: ExternalUserId::new(target_user_id),
+                ..permission.clone()
+            });
+        }
+
+        if permissions.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let audit = AuditLogCreate {
+            user_id: Some(target_user_id.to_string()),
+            action: AuditAction::PermissionsImported.as_str().to_string(),
+            resource_type: "permission".to_string(),
+            resource_id: None,
+            details: Some(serde_json::json!({
+                "count": permissions.len(),
+                "skipped": skipped,
+                "source_user": set.user_id,
+                "exported_at": set.exported_at,
+            })),
+            ip_address: None,
+            user_agent: None,
+        };
+
+        self.save_permissions(&permissions, audit).await
+    }
+}
+
 /// OAuth state for URL mode elicitation.
 #[derive(Clone, Debug)]
 pub struct OAuthState {
@@ -306,6 +674,8 @@ impl PermissionStore {
     }
 
     /// Retrieve and validate OAuth state.
+    ///
+    /// Expired state is removed and reported as `ElicitationError::Expired`.
     pub async fn get_oauth_state(
         &self,
         elicitation_id: &str,
@@ -316,7 +686,7 @@ impl PermissionStore {
             // Check expiration
             if chrono::Utc::now() > entry.expires_at {
                 state_map.remove(elicitation_id);
-                return Ok(None);
+                return Err(ElicitationError::Expired);
             }
 
             return Ok(Some(OAuthState {
@@ -354,6 +724,8 @@ impl PermissionStore {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::elicitation::ApprovalAction;
+    use crate::types::{ServiceId, ToolId};
 
     #[test]
     fn test_oauth_state_in_memory() {
@@ -371,4 +743,203 @@ mod tests {
         assert_eq!(state.elicitation_id, "test-id");
         assert_eq!(state.provider.as_str(), "github");
     }
+
+    async fn setup_store() -> PermissionStore {
+        let db = crate::db::create_connection(crate::db::DatabaseConfig {
+            url: "memory".to_string(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        crate::db::ensure_schema(&db).await.unwrap();
+        PermissionStore::new(db).with_signing_key("test-signing-key")
+    }
+
+    fn permission(tool: &str, user: &str, action: ApprovalAction) -> ToolPermission {
+        ToolPermission {
+            id: None,
+            tool_id: ToolId::new(tool),
+            service_id: ServiceId::new("service:github"),
+            user_id: ExternalUserId::new(user),
+            action,
+            created_at: chrono::Utc::now().to_rfc3339(),
+            expires_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_permission_changes_are_audited_by_the_database() {
+        let store = setup_store().await;
+        store
+            .save_permission(&permission(
+                "tool:a",
+                "user:alice",
+                ApprovalAction::AlwaysAllow,
+            ))
+            .await
+            .unwrap();
+        store
+            .delete_permission("tool:a", "service:github", "user:alice")
+            .await
+            .unwrap();
+        // Writes that bypass the store are audited too
+        store
+            .db
+            .query("CREATE permission CONTENT { tool_id: 'tool:b', service_id: 'service:github', user_id: 'user:bob', action: 'deny' }")
+            .await
+            .unwrap()
+            .check()
+            .unwrap();
+
+        let entries: Vec<serde_json::Value> = store
+            .db
+            .query("SELECT action, user_id, details, created_at FROM audit_log WHERE resource_type = 'permission' ORDER BY created_at")
+            .await
+            .unwrap()
+            .take(0)
+            .unwrap();
+        let summary: Vec<(&str, &str, &str)> = entries
+            .iter()
+            .map(|e| {
+                (
+                    e["action"].as_str().unwrap(),
+                    e["user_id"].as_str().unwrap(),
+                    e["details"]["tool_id"].as_str().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    AuditAction::PermissionCreated.as_str(),
+                    "user:alice",
+                    "tool:a"
+                ),
+                (
+                    AuditAction::PermissionDeleted.as_str(),
+                    "user:alice",
+                    "tool:a"
+                ),
+                (
+                    AuditAction::PermissionCreated.as_str(),
+                    "user:bob",
+                    "tool:b"
+                ),
+            ]
+        );
+        assert_eq!(entries[0]["details"]["action"], "always_allow");
+        assert!(entries[0]["details"]["timestamp"].is_string());
+    }
+
+    #[tokio::test]
+    async fn test_export_then_import_for_another_user() {
+        let store = setup_store().await;
+        store
+            .save_permission(&permission(
+                "tool:a",
+                "user:alice",
+                ApprovalAction::AlwaysAllow,
+            ))
+            .await
+            .unwrap();
+        let mut set = store.export_permissions("user:alice").await.unwrap();
+        assert_eq!(set.user_id.as_str(), "user:alice");
+        assert_eq!(set.permissions.len(), 1);
+        assert!(set.permissions[0].id.is_none());
+
+        let mut expired = permission("tool:b", "user:alice", ApprovalAction::Deny);
+        expired.expires_at = Some((chrono::Utc::now() - chrono::Duration::hours(1)).to_rfc3339());
+        set.permissions.push(expired);
+        set.signature = store.sign(&set).unwrap();
+
+        let imported = store
+            .import_permissions(set, "user:bob", ConflictPolicy::Skip)
+            .await
+            .unwrap();
+        assert_eq!(imported.len(), 1);
+
+        let bob = store
+            .get_permission("tool:a", "service:github", "user:bob")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(bob.action, ApprovalAction::AlwaysAllow);
+        assert!(
+            store
+                .get_permission("tool:b", "service:github", "user:bob")
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_import_conflict_policy() {
+        let store = setup_store().await;
+        store
+            .save_permission(&permission(
+                "tool:a",
+                "user:alice",
+                ApprovalAction::AlwaysAllow,
+            ))
+            .await
+            .unwrap();
+        store
+            .save_permission(&permission("tool:a", "user:bob", ApprovalAction::Deny))
+            .await
+            .unwrap();
+        let set = store.export_permissions("user:alice").await.unwrap();
+
+        let imported = store
+            .import_permissions(set.clone(), "user:bob", ConflictPolicy::Skip)
+            .await
+            .unwrap();
+        assert!(imported.is_empty());
+        let bob = store
+            .get_permission("tool:a", "service:github", "user:bob")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(bob.action, ApprovalAction::Deny);
+
+        let imported = store
+            .import_permissions(set, "user:bob", ConflictPolicy::Overwrite)
+            .await
+            .unwrap();
+        assert_eq!(imported.len(), 1);
+        let bob = store
+            .get_permission("tool:a", "service:github", "user:bob")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(bob.action, ApprovalAction::AlwaysAllow);
+    }
+
+    #[tokio::test]
+    async fn test_import_rejects_tampered_or_foreign_sets() {
+        let store = setup_store().await;
+        store
+            .save_permission(&permission("tool:a", "user:alice", ApprovalAction::Deny))
+            .await
+            .unwrap();
+        let set = store.export_permissions("user:alice").await.unwrap();
+
+        let mut tampered = set.clone();
+        tampered.permissions[0].action = ApprovalAction::AlwaysAllow;
+        assert!(matches!(
+            store
+                .import_permissions(tampered, "user:bob", ConflictPolicy::Skip)
+                .await,
+            Err(ElicitationError::InvalidSignature)
+        ));
+
+        let other = store.clone().with_signing_key("other-key");
+        assert!(matches!(
+            other
+                .import_permissions(set, "user:bob", ConflictPolicy::Skip)
+                .await,
+            Err(ElicitationError::InvalidSignature)
+        ));
+    }
 }
@@ -1,14 +1,186 @@
+use crate::auth::UserContext;
+use crate::config::TrustLevel;
 use crate::db::queries::QueryBuilder;
+use crate::db::{ToolRecord, UserContextField};
 use crate::knowledge_graph::ToolSelection;
-use crate::mcp_client::RunningService;
+use crate::mcp_client::SharedRunningServices;
+use crate::orchestrator::result_cache::ToolResultCache;
 use anyhow::{Result, anyhow};
+use jsonschema::error::ValidationErrorKind;
 use rmcp::model::{Content, JsonObject};
-use std::collections::HashMap;
-use std::sync::Arc;
-use surrealdb::RecordId;
+use serde::Serialize;
+use serde_json::Value;
+use std::time::{Duration, Instant};
 use surrealdb::Surreal;
 use surrealdb::engine::any::Any;
 
+/// Errors from `execute_selection` that callers may want to tell apart.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExecutorError {
+    /// The service's trust level does not allow running the tool.
+    PolicyViolation {
+        service: String,
+        tool: String,
+        reason: String,
+    },
+}
+
+impl std::fmt::Display for ExecutorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExecutorError::PolicyViolation {
+                service,
+                tool,
+                reason,
+            } => write!(
+                f,
+                "Policy violation: tool `{}` of service `{}` may not run: {}",
+                tool, service, reason
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ExecutorError {}
+
+/// Check `tool` against its service's `trust_level`.
+fn check_trust_policy(tool: &ToolRecord, trust_level: &TrustLevel) -> Result<(), ExecutorError> {
+    trust_level
+        .check_tool(tool.is_output_only(), &tool.declared_capabilities())
+        .map_err(|reason| ExecutorError::PolicyViolation {
+            service: tool.service_id.to_string(),
+            tool: tool.name.clone(),
+            reason,
+        })
+}
+
+/// An argument that does not match the tool's input schema.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InputValidationError {
+    /// Dotted path of the offending argument, e.g. `options.limit`; empty for
+    /// the arguments object itself.
+    pub field: String,
+    pub message: String,
+    /// Type the schema declares for `field`, or `any` if it declares none.
+    pub expected_type: String,
+}
+
+/// Check `args` against the tool's `input_schema`, returning every mismatch.
+///
+/// A schema that does not compile is logged and treated as accepting
+/// anything, so a sloppy schema never blocks a tool that used to work.
+pub fn validate_input_args(
+    tool: &ToolRecord,
+    args: &JsonObject,
+) -> std::result::Result<(), Vec<InputValidationError>> {
+    let schema = Value::Object(tool.input_schema.clone());
+    let validator = match jsonschema::validator_for(&schema) {
+        Ok(validator) => validator,
+        Err(e) => {
+            tracing::warn!(tool = %tool.name, "Ignoring invalid input schema: {}", e);
+            return Ok(());
+        }
+    };
+
+    let instance = Value::Object(args.clone());
+    let errors: Vec<InputValidationError> = validator
+        .iter_errors(&instance)
+        .map(|error| {
+            let mut path: Vec<String> = error
+                .instance_path
+                .as_str()
+                .split('/')
+                .skip(1)
+                .map(|segment| segment.replace("~1", "/").replace("~0", "~"))
+                .collect();
+            // Missing properties are reported on their parent object
+            if let ValidationErrorKind::Required { property } = &error.kind
+                && let Some(property) = property.as_str()
+            {
+                path.push(property.to_string());
+            }
+
+            InputValidationError {
+                field: path.join("."),
+                message: error.to_string(),
+                expected_type: expected_type(&schema, &path),
+            }
+        })
+        .collect();
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Set the tool's `inject_user_context` arguments from the caller's identity.
+///
+/// Injected values replace whatever the caller passed, so callers can't act as
+/// someone else. Fields the context doesn't have, or every field when there
+/// is no user context, are removed instead.
+pub fn inject_user_context(
+    tool: &ToolRecord,
+    args: &mut JsonObject,
+    user_context: Option<&UserContext>,
+) {
+    for field in &tool.inject_user_context {
+        let value = user_context.and_then(|ctx| match field.source {
+            UserContextField::UserId => Some(ctx.external_id().to_string()),
+            UserContextField::Email => ctx.email().map(str::to_string),
+            UserContextField::DisplayName => ctx.display_name().map(str::to_string),
+            UserContextField::Provider => Some(ctx.provider().to_string()),
+            UserContextField::IpAddress => ctx.ip_address().map(str::to_string),
+        });
+        match value {
+            Some(value) => {
+                args.insert(field.arg_name.clone(), Value::String(value));
+            }
+            None => {
+                args.remove(&field.arg_name);
+            }
+        }
+    }
+}
+
+/// `args` without the tool's injected arguments, for recording without
+/// storing the caller's personal data.
+pub fn strip_injected_args(tool: &ToolRecord, args: &JsonObject) -> JsonObject {
+    let mut stripped = args.clone();
+    for field in &tool.inject_user_context {
+        stripped.remove(&field.arg_name);
+    }
+    stripped
+}
+
+/// The `type` declared for the value at `path`, following `properties` and
+/// `items`.
+fn expected_type(schema: &Value, path: &[String]) -> String {
+    let mut current = schema;
+    for segment in path {
+        let next = match segment.parse::<usize>() {
+            Ok(_) if current.get("items").is_some() => current.get("items"),
+            _ => current.get("properties").and_then(|p| p.get(segment)),
+        };
+        match next {
+            Some(next) => current = next,
+            None => return "any".to_string(),
+        }
+    }
+
+    match current.get("type") {
+        Some(Value::String(ty)) => ty.clone(),
+        Some(Value::Array(types)) => types
+            .iter()
+            .filter_map(|ty| ty.as_str())
+            .collect::<Vec<_>>()
+            .join(" | "),
+        _ => "any".to_string(),
+    }
+}
+
 /// Execute a single selected tool by:
 /// 1. Looking up the tool row by `tool_id`.
 /// 2. Using the tool's `service_id` to find an already-running rmcp client.
@@ -16,29 +188,237 @@ use surrealdb::engine::any::Any;
 ///
 /// This does **not** perform any planning or selection; it only executes the
 /// given selection.
-#[allow(clippy::mutable_key_type)]
 pub async fn execute_selection(
     db: &Surreal<Any>,
-    running_services: &HashMap<RecordId, Arc<RunningService>>,
+    running_services: &SharedRunningServices,
     selection: &ToolSelection,
     args: JsonObject,
+    trust_level: &TrustLevel,
+    result_cache: &ToolResultCache,
 ) -> Result<Vec<Content>> {
-    // 1) Load the selected tool from the database using its RecordId.
+    // 1) Load the selected tool from the database using its RecordId, and
+    // check that its service is trusted to run it.
     let tool = QueryBuilder::find_tool_by_id(db, selection.tool_id.clone())
         .await?
         .ok_or_else(|| anyhow!("Tool not found for id {}", selection.tool_id))?;
+    check_trust_policy(&tool, trust_level)?;
+
+    // Tools with a cache TTL answer identical calls from the cache
+    let cache_ttl = tool.cache_ttl_seconds.map(Duration::from_secs);
+    if cache_ttl.is_some() {
+        if let Some(cached) = result_cache.get(&tool.id, &args, Instant::now()) {
+            tracing::debug!(tool_id = %tool.id, "Tool result cache hit");
+            return Ok(cached);
+        }
+        tracing::debug!(tool_id = %tool.id, "Tool result cache miss");
+    }
+
+    // 2) Find the running service client for this tool's service_id. The
+    // client is cloned out so the map is not locked for the duration of the call.
+    let svc = running_services
+        .lock()
+        .await
+        .get(&tool.service_id.to_string())
+        .cloned()
+        .ok_or_else(|| {
+            anyhow!(
+                "No running service client for service_id {}",
+                tool.service_id
+            )
+        })?;
+
+    // 3) Call the underlying MCP tool via rmcp. A result the tool marked as
+    // an error fails the execution and is never cached.
+    let cache_args = cache_ttl.map(|_| args.clone());
+    let result = crate::mcp_client::call_tool(&svc, &tool.name, args).await?;
+    if result.is_error == Some(true) {
+        return Err(anyhow!(
+            "Tool {} returned an error: {}",
+            tool.name,
+            error_text(&result.content)
+        ));
+    }
+    if let (Some(ttl), Some(args)) = (cache_ttl, cache_args) {
+        result_cache.insert(&tool.id, &args, result.content.clone(), ttl, Instant::now());
+    }
+
+    Ok(result.content)
+}
+
+/// The text parts of a failed tool result, for the error message.
+fn error_text(content: &[Content]) -> String {
+    let text: Vec<&str> = content
+        .iter()
+        .filter_map(|c| c.as_text().map(|t| t.text.as_str()))
+        .collect();
+    if text.is_empty() {
+        "no details".to_string()
+    } else {
+        text.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use surrealdb::RecordId;
 
-    // 2) Find the running service client for this tool's service_id.
-    let svc = running_services.get(&tool.service_id).ok_or_else(|| {
-        anyhow!(
-            "No running service client for service_id {}",
-            tool.service_id
+    fn tool(input_schema: Value) -> ToolRecord {
+        ToolRecord {
+            id: RecordId::from_table_key("tool", "search"),
+            service_id: RecordId::from_table_key("service", "web"),
+            name: "search".to_string(),
+            description: None,
+            input_schema: input_schema.as_object().cloned().unwrap_or_default(),
+            output_schema: None,
+            embedding_id: None,
+            input_ty: None,
+            output_ty: None,
+            usage_count: 0,
+            status: None,
+            discovery_source: Default::default(),
+            metadata: Default::default(),
+            input_examples: Vec::new(),
+            changelog: Vec::new(),
+            deprecation_notice: None,
+            descriptions: Default::default(),
+            inject_user_context: Vec::new(),
+            geo_availability: None,
+            required_env_vars: Vec::new(),
+            sla_class: None,
+            cost_per_call: None,
+            cache_ttl_seconds: None,
+            required_role: None,
+            actual_latency_p99_ms: None,
+            actual_error_rate_pct: None,
+            block_reason: None,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    fn args(value: Value) -> JsonObject {
+        value.as_object().cloned().unwrap()
+    }
+
+    #[test]
+    fn test_check_trust_policy_reports_violation() {
+        let mut tool = tool(json!({}));
+        tool.metadata = args(json!({ "output_only": true, "capabilities": ["filesystem"] }));
+        assert!(tool.is_output_only());
+        assert_eq!(tool.declared_capabilities(), vec!["filesystem".to_string()]);
+
+        assert!(check_trust_policy(&tool, &TrustLevel::Trusted).is_ok());
+        let allowed = TrustLevel::Community(vec![crate::config::AllowedCapability::Filesystem]);
+        assert!(check_trust_policy(&tool, &allowed).is_ok());
+        assert_eq!(
+            check_trust_policy(&tool, &TrustLevel::community_default()),
+            Err(ExecutorError::PolicyViolation {
+                service: "service:web".to_string(),
+                tool: "search".to_string(),
+                reason: "capability `filesystem` is not allowed".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_validate_input_args_reports_every_error() {
+        let tool = tool(json!({
+            "type": "object",
+            "properties": {
+                "query": { "type": "string" },
+                "limit": { "type": "integer" },
+                "options": {
+                    "type": "object",
+                    "properties": { "lang": { "type": ["string", "null"] } }
+                }
+            },
+            "required": ["query"]
+        }));
+
+        assert_eq!(
+            validate_input_args(&tool, &args(json!({ "query": "rust", "limit": 5 }))),
+            Ok(())
+        );
+
+        let mut errors = validate_input_args(
+            &tool,
+            &args(json!({ "limit": "five", "options": { "lang": 1 } })),
         )
-    })?;
+        .unwrap_err();
+        errors.sort_by(|a, b| a.field.cmp(&b.field));
+
+        let fields: Vec<(&str, &str)> = errors
+            .iter()
+            .map(|e| (e.field.as_str(), e.expected_type.as_str()))
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                ("limit", "integer"),
+                ("options.lang", "string | null"),
+                ("query", "string"),
+            ]
+        );
+        assert!(errors[2].message.contains("required"));
+    }
+
+    #[test]
+    fn test_inject_user_context_overrides_and_strips() {
+        let mut tool = tool(json!({
+            "type": "object",
+            "properties": { "caller": { "type": "string" } },
+            "required": ["caller"]
+        }));
+        tool.inject_user_context = crate::db::injected_fields_from_meta(&args(json!({
+            "inject_user_context": [
+                { "arg_name": "caller", "source": "user_id" },
+                { "arg_name": "email", "source": "email" },
+                { "arg_name": "ip", "source": "ip_address" },
+                { "arg_name": "bad", "source": "password" },
+            ]
+        })));
+        assert_eq!(tool.inject_user_context.len(), 3);
+
+        let user = UserContext::new(
+            RecordId::from_table_key("user", "u1"),
+            crate::types::ExternalUserId::new("sub-123"),
+            crate::types::IdentityProvider::new("jwt"),
+            Some("ada@example.com".to_string()),
+            None,
+        );
+        let mut call = args(json!({ "caller": "someone-else", "ip": "10.0.0.1", "q": "rust" }));
+        inject_user_context(&tool, &mut call, Some(&user));
+        assert_eq!(
+            call,
+            args(json!({ "caller": "sub-123", "email": "ada@example.com", "q": "rust" }))
+        );
+        assert_eq!(validate_input_args(&tool, &call), Ok(()));
+        assert_eq!(
+            strip_injected_args(&tool, &call),
+            args(json!({ "q": "rust" }))
+        );
+
+        // Without a user, injected arguments can't be supplied by the caller
+        let mut anonymous = args(json!({ "caller": "someone-else" }));
+        inject_user_context(&tool, &mut anonymous, None);
+        assert!(anonymous.is_empty());
+        assert!(validate_input_args(&tool, &anonymous).is_err());
+    }
 
-    // 3) Call the underlying MCP tool via rmcp. The actual call is delegated
-    // to `mcp_client::call_tool`, which should wrap the rmcp client API.
-    let result = crate::mcp_client::call_tool(svc, &tool.name, args).await?;
+    #[test]
+    fn test_validate_input_args_ignores_invalid_schema() {
+        let tool = tool(json!({ "type": "not-a-type" }));
+        assert_eq!(validate_input_args(&tool, &args(json!({ "a": 1 }))), Ok(()));
+    }
 
-    Ok(result)
+    #[test]
+    fn test_error_text_joins_text_content() {
+        assert_eq!(
+            error_text(&[Content::text("rate limited"), Content::text("retry later")]),
+            "rate limited\nretry later"
+        );
+        assert_eq!(error_text(&[]), "no details");
+    }
 }
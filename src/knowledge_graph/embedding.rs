@@ -1,10 +1,23 @@
+use crate::db::UserPreferencesRecord;
 use crate::db::queries::QueryBuilder;
+use crate::db::schema::{is_english, language_fallbacks};
+use crate::knowledge_graph::feedback::SharedFeedbackPenalties;
+use crate::metrics::{CounterVec, MetricSource};
+use crate::types::ServiceId;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
 use surrealdb::engine::any::Any;
 use surrealdb::{RecordId, Surreal};
+use tokio::sync::{Mutex, Semaphore};
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use url::Url;
 
 use embed_anything::{
     config::TextEmbedConfig,
@@ -16,22 +29,462 @@ use rmcp::model::JsonObject;
 pub struct EmbeddingManager {
     db: Surreal<Any>,
     cache: HashMap<String, Vec<f32>>,
-    embedder: Embedder,
-    text_config: TextEmbedConfig,
+    backend: EmbeddingBackend,
+    /// Number of texts sent per embedding request.
+    batch_size: usize,
     /// The model id used for embeddings (e.g. Hugging Face model id).
     model_name: String,
+    /// Penalties learned from selection feedback, refreshed in the background.
+    feedback: SharedFeedbackPenalties,
+    /// How strongly feedback penalties lower search similarity.
+    feedback_weight: f32,
+    /// Applied to tool text before embedding.
+    normalizer: TextNormalizer,
+    /// Models users may select instead of `model_name` for their own searches.
+    allowed_backend_overrides: Vec<EmbeddingBackendConfig>,
+    /// Minimum centroid similarity for services to be reported as duplicates.
+    service_duplicate_threshold: f32,
+    /// Override models loaded so far, by model name.
+    override_backends: HashMap<String, Arc<EmbeddingBackend>>,
+    max_concurrent_embed_requests: usize,
+    metrics: Arc<EmbeddingMetrics>,
+    /// Price of embedding 1000 tokens in US cents.
+    cost_per_1k_tokens: f32,
+}
+
+/// Searches run with override models, labelled by `backend` (the model name)
+/// and `source`: `request` for the `X-Embedding-Model` header, `user` for a
+/// user's saved preference.
+pub struct EmbeddingMetrics {
+    override_searches: CounterVec,
+}
+
+impl EmbeddingMetrics {
+    pub fn new() -> Self {
+        Self {
+            override_searches: CounterVec::new(
+                "embedding_override_searches_total",
+                "Semantic searches run with an embedding override model",
+                &["backend", "source"],
+            ),
+        }
+    }
+
+    /// Record a search with the override model `backend`.
+    pub fn record_override_search(&self, backend: &str, source: BackendOverrideSource) {
+        self.override_searches.inc(&[backend, source.as_str()]);
+    }
+}
+
+impl Default for EmbeddingMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MetricSource for EmbeddingMetrics {
+    fn encode(&self, out: &mut String) {
+        self.override_searches.encode(out);
+    }
+}
+
+/// What selected an override model for a search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackendOverrideSource {
+    /// Requested for one query, e.g. with the `X-Embedding-Model` header.
+    Request,
+    /// The user's `embedding_backend_override` preference.
+    User,
+}
+
+impl BackendOverrideSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BackendOverrideSource::Request => "request",
+            BackendOverrideSource::User => "user",
+        }
+    }
+}
+
+/// The embedding model, with a cap on concurrent embedding requests.
+pub struct EmbeddingBackend {
+    model: BackendModel,
+    text_config: TextEmbedConfig,
+    request_limit: Semaphore,
+}
+
+/// Where an [`EmbeddingBackend`] computes its vectors.
+enum BackendModel {
+    /// A model run in-process by embed_anything.
+    Local(Embedder),
+    Remote(RemoteEmbedder),
 }
 
+impl EmbeddingBackend {
+    pub fn new(embedder: Embedder, max_concurrent_requests: usize) -> Self {
+        Self::with_model(BackendModel::Local(embedder), max_concurrent_requests)
+    }
+
+    /// A backend that sends texts to an OpenAI-compatible embeddings API.
+    pub fn remote(embedder: RemoteEmbedder, max_concurrent_requests: usize) -> Self {
+        Self::with_model(BackendModel::Remote(embedder), max_concurrent_requests)
+    }
+
+    fn with_model(model: BackendModel, max_concurrent_requests: usize) -> Self {
+        Self {
+            model,
+            text_config: TextEmbedConfig::default(),
+            request_limit: Semaphore::new(max_concurrent_requests.max(1)),
+        }
+    }
+
+    /// Embed `texts` in a single request, returning one vector per text.
+    pub async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let embedder = match &self.model {
+            BackendModel::Local(embedder) => embedder,
+            BackendModel::Remote(remote) => return remote.embed(texts).await,
+        };
+        let queries: Vec<&str> = texts.iter().map(String::as_str).collect();
+        let results = embed_query(&queries, embedder, Some(&self.text_config)).await?;
+        if results.len() != texts.len() {
+            return Err(anyhow::anyhow!(
+                "embed_anything returned {} embeddings for {} texts",
+                results.len(),
+                texts.len()
+            ));
+        }
+        results
+            .into_iter()
+            .map(|data| data.embedding.to_dense())
+            .collect()
+    }
+
+    /// Embed `texts` in chunks of `chunk_size`, running chunks concurrently up
+    /// to the request limit. Results are returned in input order.
+    pub async fn embed_batch(&self, texts: &[String], chunk_size: usize) -> Result<Vec<Vec<f32>>> {
+        embed_chunked(texts, chunk_size, &self.request_limit, |chunk| {
+            self.embed(chunk)
+        })
+        .await
+    }
+}
+
+/// Split `texts` into chunks, embed them concurrently (bounded by `limit`) and
+/// reassemble the vectors in input order. Progress is logged every 100 texts.
+async fn embed_chunked<'a, F, Fut>(
+    texts: &'a [String],
+    chunk_size: usize,
+    limit: &Semaphore,
+    embed: F,
+) -> Result<Vec<Vec<f32>>>
+where
+    F: Fn(&'a [String]) -> Fut,
+    Fut: Future<Output = Result<Vec<Vec<f32>>>>,
+{
+    let total = texts.len();
+    let done = AtomicUsize::new(0);
+    let requests = texts.chunks(chunk_size.max(1)).map(|chunk| {
+        let embed = &embed;
+        let done = &done;
+        async move {
+            let _permit = limit.acquire().await?;
+            let vectors = embed(chunk).await?;
+            let before = done.fetch_add(chunk.len(), Ordering::Relaxed);
+            if (before + chunk.len()) / 100 > before / 100 {
+                tracing::info!("Embedded {}/{} texts", before + chunk.len(), total);
+            }
+            Ok::<_, anyhow::Error>(vectors)
+        }
+    });
+
+    let chunks = futures::future::try_join_all(requests).await?;
+    Ok(chunks.into_iter().flatten().collect())
+}
+
+/// Endpoint used for remote models when `EmbeddingConfig::remote_endpoint`
+/// is not set.
+pub const OPENAI_EMBEDDINGS_ENDPOINT: &str = "https://api.openai.com/v1/embeddings";
+
+/// Client for an OpenAI-compatible `/embeddings` endpoint.
+pub struct RemoteEmbedder {
+    client: reqwest::Client,
+    endpoint: Url,
+    model: String,
+    api_key: String,
+}
+
+impl RemoteEmbedder {
+    pub fn new(endpoint: Url, model: String, api_key: String) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            endpoint,
+            model,
+            api_key,
+        }
+    }
+
+    /// Embed `texts` in a single request, returning one vector per text.
+    pub async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        #[derive(Deserialize)]
+        struct Response {
+            data: Vec<Item>,
+        }
+        #[derive(Deserialize)]
+        struct Item {
+            embedding: Vec<f32>,
+            index: usize,
+        }
+
+        let response: Response = self
+            .client
+            .post(self.endpoint.clone())
+            .bearer_auth(&self.api_key)
+            .json(&serde_json::json!({
+                "input": texts,
+                "model": self.model,
+                "encoding_format": "float",
+            }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let mut data = response.data;
+        if data.len() != texts.len() {
+            return Err(anyhow::anyhow!(
+                "{} returned {} embeddings for {} texts",
+                self.endpoint,
+                data.len(),
+                texts.len()
+            ));
+        }
+        data.sort_by_key(|item| item.index);
+        Ok(data.into_iter().map(|item| item.embedding).collect())
+    }
+}
+
+/// Errors specific to embedding search.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EmbeddingError {
+    /// The query vector does not have the same length as the stored embeddings.
+    DimensionMismatch { expected: usize, got: usize },
+    /// No tool exists with the given id.
+    ToolNotFound(String),
+    /// The tool has not been embedded yet.
+    MissingEmbedding(String),
+    /// The model is not in `allowed_backend_overrides`.
+    BackendNotAllowed(String),
+}
+
+impl std::fmt::Display for EmbeddingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EmbeddingError::DimensionMismatch { expected, got } => write!(
+                f,
+                "Embedding dimension mismatch: expected {}, got {}",
+                expected, got
+            ),
+            EmbeddingError::ToolNotFound(id) => write!(f, "Tool not found: {}", id),
+            EmbeddingError::MissingEmbedding(id) => write!(f, "Tool has no embedding: {}", id),
+            EmbeddingError::BackendNotAllowed(model) => {
+                write!(f, "Embedding model not allowed: {}", model)
+            }
+        }
+    }
+}
+
+impl std::error::Error for EmbeddingError {}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EmbeddingConfig {
-    /// The Hugging Face model ID used for embeddings.
+    /// The embedding model. `Custom` uses `model_name` and
+    /// `model_architecture`; the other kinds ignore them.
+    #[serde(default)]
+    pub model: EmbeddingModelKind,
+    /// The Hugging Face model ID used for embeddings with
+    /// [`EmbeddingModelKind::Custom`].
     pub model_name: String,
     /// The model architecture for embed_anything (e.g. "jina", "qwen").
     /// If left empty, the architecture will be inferred from the model name
     /// ("qwen" in the name -> "qwen", otherwise "jina").
     pub model_architecture: String,
+    /// Embeddings endpoint for remote models; defaults to
+    /// [`OPENAI_EMBEDDINGS_ENDPOINT`].
+    #[serde(default)]
+    pub remote_endpoint: Option<Url>,
+    /// API key for remote models; defaults to the `OPENAI_API_KEY`
+    /// environment variable.
+    #[serde(default, skip_serializing)]
+    pub api_key: Option<String>,
     pub dimension: usize,
+    /// Number of texts sent per embedding request.
     pub batch_size: usize,
+    /// Maximum number of embedding requests in flight at once.
+    pub max_concurrent_embed_requests: usize,
+    /// How often the scheduler looks for tools that are due for re-embedding.
+    pub reembed_check_interval: Duration,
+    /// Re-embedding interval for tools in the top usage quartile.
+    pub high_frequency_interval: Duration,
+    /// Re-embedding interval for tools between the bottom and top quartiles.
+    pub medium_frequency_interval: Duration,
+    /// Re-embedding interval for tools in the bottom usage quartile.
+    pub low_frequency_interval: Duration,
+    /// Similarity subtracted from a tool that is always rejected for similar
+    /// queries; scaled down by its rejection rate otherwise. `0.0` disables it.
+    pub feedback_weight: f32,
+    /// How often feedback penalties are recomputed from `selection_feedback`.
+    pub feedback_refresh_interval: Duration,
+    /// Clean-up applied to tool text before it is embedded, in order.
+    #[serde(default = "default_normalization_steps")]
+    pub normalization_steps: Vec<NormalizationStep>,
+    /// Models users may choose instead of `model_name` for their own
+    /// searches. Empty disables per-user overrides.
+    #[serde(default)]
+    pub allowed_backend_overrides: Vec<EmbeddingBackendConfig>,
+    /// Minimum similarity between two services' tool embeddings for
+    /// `cross_service_similarity_matrix` to report them as possible duplicates.
+    #[serde(default = "default_service_duplicate_threshold")]
+    pub service_duplicate_threshold: f32,
+    /// Price of embedding 1000 tokens in US cents, used to estimate query
+    /// costs. `0.0` for locally run models.
+    #[serde(default)]
+    pub cost_per_1k_tokens: f32,
+}
+
+/// The embedding models the orchestrator knows how to run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmbeddingModelKind {
+    /// The Hugging Face model in `EmbeddingConfig::model_name`, run locally.
+    #[default]
+    Custom,
+    /// `sentence-transformers/all-MiniLM-L6-v2`, run locally.
+    #[serde(rename = "all_minilm_l6_v2")]
+    AllMiniLmL6V2,
+    /// `intfloat/e5-large-v2`, run locally.
+    E5Large,
+    /// OpenAI's `text-embedding-ada-002`, called over HTTP.
+    #[serde(rename = "openai_text_embedding_ada_002")]
+    OpenAiTextEmbeddingAda002,
+}
+
+impl EmbeddingModelKind {
+    /// The model ID, or `None` for `Custom`.
+    pub fn model_id(self) -> Option<&'static str> {
+        match self {
+            EmbeddingModelKind::Custom => None,
+            EmbeddingModelKind::AllMiniLmL6V2 => Some("sentence-transformers/all-MiniLM-L6-v2"),
+            EmbeddingModelKind::E5Large => Some("intfloat/e5-large-v2"),
+            EmbeddingModelKind::OpenAiTextEmbeddingAda002 => Some("text-embedding-ada-002"),
+        }
+    }
+
+    /// Length of the model's vectors, or `None` for `Custom`.
+    pub fn dimension(self) -> Option<usize> {
+        match self {
+            EmbeddingModelKind::Custom => None,
+            EmbeddingModelKind::AllMiniLmL6V2 => Some(384),
+            EmbeddingModelKind::E5Large => Some(1024),
+            EmbeddingModelKind::OpenAiTextEmbeddingAda002 => Some(1536),
+        }
+    }
+
+    /// Whether the model runs behind an HTTP API rather than in-process.
+    pub fn is_remote(self) -> bool {
+        matches!(self, EmbeddingModelKind::OpenAiTextEmbeddingAda002)
+    }
+}
+
+impl EmbeddingConfig {
+    /// The name embeddings are stored under: the model ID of `model`, or
+    /// `model_name` for `Custom`.
+    pub fn effective_model_name(&self) -> String {
+        self.model
+            .model_id()
+            .map_or_else(|| self.model_name.clone(), str::to_string)
+    }
+
+    /// Load the configured model.
+    fn backend(&self) -> Result<EmbeddingBackend> {
+        let model_name = self.effective_model_name();
+        if self.model.is_remote() {
+            let api_key = match &self.api_key {
+                Some(api_key) => api_key.clone(),
+                None => std::env::var("OPENAI_API_KEY").map_err(|_| {
+                    anyhow::anyhow!("{} needs an api_key or OPENAI_API_KEY", model_name)
+                })?,
+            };
+            let endpoint = match &self.remote_endpoint {
+                Some(endpoint) => endpoint.clone(),
+                None => Url::parse(OPENAI_EMBEDDINGS_ENDPOINT)?,
+            };
+            return Ok(EmbeddingBackend::remote(
+                RemoteEmbedder::new(endpoint, model_name, api_key),
+                self.max_concurrent_embed_requests,
+            ));
+        }
+
+        let model_architecture = match self.model {
+            EmbeddingModelKind::Custom => self.model_architecture.clone(),
+            _ => "bert".to_string(),
+        };
+        let embedder = EmbeddingBackendConfig {
+            model_name,
+            model_architecture,
+        }
+        .load()?;
+        Ok(EmbeddingBackend::new(
+            embedder,
+            self.max_concurrent_embed_requests,
+        ))
+    }
+}
+
+/// Default [`EmbeddingConfig::batch_size`].
+pub const DEFAULT_EMBED_BATCH_SIZE: usize = 32;
+
+/// Default [`EmbeddingConfig::service_duplicate_threshold`].
+pub const SERVICE_DUPLICATE_THRESHOLD: f32 = 0.9;
+
+/// Service pairs more similar than this are logged as likely duplicates
+/// during warmup.
+pub const SERVICE_DUPLICATE_WARN_THRESHOLD: f32 = 0.95;
+
+fn default_service_duplicate_threshold() -> f32 {
+    SERVICE_DUPLICATE_THRESHOLD
+}
+
+/// An embedding model a user can search with instead of the global one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EmbeddingBackendConfig {
+    /// The Hugging Face model ID.
+    pub model_name: String,
+    /// The model architecture for embed_anything; inferred from the model
+    /// name when empty.
+    #[serde(default)]
+    pub model_architecture: String,
+}
+
+impl EmbeddingBackendConfig {
+    /// The architecture passed to embed_anything. If not set, it is inferred
+    /// from the model name ("qwen3" -> "qwen3", else "jina").
+    pub fn architecture(&self) -> String {
+        if !self.model_architecture.is_empty() {
+            self.model_architecture.clone()
+        } else if self.model_name.to_lowercase().contains("qwen3") {
+            "qwen3".to_string()
+        } else {
+            "jina".to_string()
+        }
+    }
+
+    fn load(&self) -> Result<Embedder> {
+        EmbedderBuilder::new()
+            .model_architecture(&self.architecture())
+            .model_id(Some(&self.model_name))
+            .from_pretrained_hf()
+    }
 }
 
 impl Default for EmbeddingConfig {
@@ -41,42 +494,208 @@ impl Default for EmbeddingConfig {
             // embeddings instead, set `model_architecture = "qwen"` and
             // provide the appropriate Qwen embedding model id via
             // configuration.
+            model: EmbeddingModelKind::Custom,
             model_name: "Qwen/QWen3-Embedding-0.6B".to_string(),
             model_architecture: "Qwen3".to_string(),
+            remote_endpoint: None,
+            api_key: None,
             dimension: 1024,
-            batch_size: 32,
+            batch_size: DEFAULT_EMBED_BATCH_SIZE,
+            max_concurrent_embed_requests: 4,
+            reembed_check_interval: Duration::from_secs(60),
+            high_frequency_interval: Duration::from_secs(60 * 60),
+            medium_frequency_interval: Duration::from_secs(6 * 60 * 60),
+            low_frequency_interval: Duration::from_secs(24 * 60 * 60),
+            feedback_weight: 0.2,
+            feedback_refresh_interval: Duration::from_secs(5 * 60),
+            normalization_steps: default_normalization_steps(),
+            allowed_backend_overrides: Vec::new(),
+            service_duplicate_threshold: SERVICE_DUPLICATE_THRESHOLD,
+            cost_per_1k_tokens: 0.0,
         }
     }
 }
 
-impl EmbeddingManager {
-    pub async fn new(db: Surreal<Any>, config: EmbeddingConfig) -> Result<Self> {
-        // Determine the architecture. If explicitly provided, use it; otherwise
-        // infer from the model name ("qwen" -> "qwen", else default to "jina").
-        let arch = if !config.model_architecture.is_empty() {
-            config.model_architecture.clone()
-        } else if config.model_name.to_lowercase().contains("qwen3") {
-            "qwen3".to_string()
-        } else {
-            "jina".to_string()
-        };
+fn default_normalization_steps() -> Vec<NormalizationStep> {
+    vec![
+        NormalizationStep::StripHtml,
+        NormalizationStep::CollapseWhitespace,
+    ]
+}
+
+/// One step of the [`TextNormalizer`] pipeline.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum NormalizationStep {
+    /// Remove HTML tags and decode the common character entities.
+    StripHtml,
+    /// Replace runs of whitespace with a single space and trim the ends.
+    CollapseWhitespace,
+    Lowercase,
+    /// Drop words found in the list, compared case-insensitively and
+    /// ignoring surrounding punctuation. Remaining words are joined by single
+    /// spaces.
+    RemoveStopWords(Vec<String>),
+    /// Keep only the first N whitespace-separated tokens.
+    TruncateToTokens(usize),
+}
+
+/// A tool's description before and after normalization.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NormalizedDescription {
+    pub description: Option<String>,
+    pub normalized_description: Option<String>,
+    pub steps: Vec<NormalizationStep>,
+}
+
+/// Cleans up raw tool text (HTML fragments, stray whitespace, ...) before it
+/// is sent to the embedding model.
+#[derive(Debug, Clone, Default)]
+pub struct TextNormalizer {
+    steps: Vec<NormalizationStep>,
+}
 
-        let embedder = EmbedderBuilder::new()
-            .model_architecture(&arch)
-            .model_id(Some(&config.model_name))
-            .from_pretrained_hf()?;
+impl TextNormalizer {
+    pub fn new(steps: Vec<NormalizationStep>) -> Self {
+        Self { steps }
+    }
 
-        let text_config = TextEmbedConfig::default();
+    pub fn steps(&self) -> &[NormalizationStep] {
+        &self.steps
+    }
+
+    /// Run `text` through every step in order.
+    pub fn normalize(&self, text: &str) -> String {
+        self.steps
+            .iter()
+            .fold(text.to_string(), |text, step| match step {
+                NormalizationStep::StripHtml => strip_html(&text),
+                NormalizationStep::CollapseWhitespace => {
+                    text.split_whitespace().collect::<Vec<_>>().join(" ")
+                }
+                NormalizationStep::Lowercase => text.to_lowercase(),
+                NormalizationStep::RemoveStopWords(stop_words) => {
+                    let stop_words: HashSet<String> =
+                        stop_words.iter().map(|word| word.to_lowercase()).collect();
+                    text.split_whitespace()
+                        .filter(|word| {
+                            let bare = word.trim_matches(|c: char| !c.is_alphanumeric());
+                            !stop_words.contains(&bare.to_lowercase())
+                        })
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                }
+                NormalizationStep::TruncateToTokens(max_tokens) => {
+                    truncate_to_tokens(&text, *max_tokens).to_string()
+                }
+            })
+    }
+}
+
+/// Remove HTML tags, replacing each with a space so adjacent block elements
+/// don't run together, and decode common entities.
+///
+/// A `<` only starts a tag when followed by a letter, `/` or `!`, so text
+/// like `a < b` is left alone.
+fn strip_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('<') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let starts_tag = after
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '/' || c == '!');
+        match after.find('>') {
+            Some(end) if starts_tag => {
+                out.push(' ');
+                rest = &after[end + 1..];
+            }
+            _ => {
+                out.push('<');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+
+    // `&amp;` goes last so that `&amp;lt;` decodes to `&lt;`, not `<`
+    out.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Prefix of `text` ending after its first `max_tokens` whitespace-separated
+/// tokens.
+fn truncate_to_tokens(text: &str, max_tokens: usize) -> &str {
+    if max_tokens == 0 {
+        return "";
+    }
+    let mut tokens = 0;
+    let mut in_token = false;
+    for (index, c) in text.char_indices() {
+        if !c.is_whitespace() {
+            in_token = true;
+        } else if in_token {
+            in_token = false;
+            tokens += 1;
+            if tokens == max_tokens {
+                return &text[..index];
+            }
+        }
+    }
+    text
+}
+
+impl EmbeddingManager {
+    pub async fn new(db: Surreal<Any>, config: EmbeddingConfig) -> Result<Self> {
+        let backend = config.backend()?;
 
         Ok(Self {
             db,
             cache: HashMap::new(),
-            embedder,
-            text_config,
-            model_name: config.model_name,
+            backend,
+            batch_size: config.batch_size,
+            model_name: config.effective_model_name(),
+            feedback: SharedFeedbackPenalties::default(),
+            feedback_weight: config.feedback_weight,
+            normalizer: TextNormalizer::new(config.normalization_steps),
+            allowed_backend_overrides: config.allowed_backend_overrides,
+            service_duplicate_threshold: config.service_duplicate_threshold,
+            override_backends: HashMap::new(),
+            max_concurrent_embed_requests: config.max_concurrent_embed_requests,
+            metrics: Arc::new(EmbeddingMetrics::new()),
+            cost_per_1k_tokens: config.cost_per_1k_tokens,
         })
     }
 
+    /// Usage counts of override models.
+    pub fn metrics(&self) -> &Arc<EmbeddingMetrics> {
+        &self.metrics
+    }
+
+    /// Feedback penalties applied by `search_tools_by_embedding`.
+    pub fn feedback_penalties(&self) -> SharedFeedbackPenalties {
+        self.feedback.clone()
+    }
+
+    /// Price of embedding 1000 tokens in US cents.
+    pub fn cost_per_1k_tokens(&self) -> f32 {
+        self.cost_per_1k_tokens
+    }
+
+    /// Whether `embed_text` would answer `text` from its cache, without an
+    /// embedding request.
+    pub fn is_cached(&self, text: &str) -> bool {
+        self.cache.contains_key(&self.hash_content(text))
+    }
+
     pub async fn embed_text(&mut self, text: &str) -> Result<Vec<f32>> {
         // Check cache first
         let hash = self.hash_content(text);
@@ -85,17 +704,13 @@ impl EmbeddingManager {
         }
 
         // Use embed_anything to generate a real embedding
-        let queries = vec![text];
-        let results = embed_query(&queries, &self.embedder, Some(&self.text_config)).await?;
-
-        // Take the first embedding, or return an error if nothing was produced
-        let embedding = results
-            .first()
-            .map(|d| d.embedding.clone())
+        let dense = self
+            .backend
+            .embed(&[text.to_string()])
+            .await?
+            .pop()
             .ok_or_else(|| anyhow::anyhow!("embed_anything returned no embeddings for query"))?;
 
-        let dense = embedding.to_dense()?;
-
         // Cache the result
         self.cache.insert(hash, dense.clone());
 
@@ -103,12 +718,42 @@ impl EmbeddingManager {
     }
 
     pub async fn embed_tool(&mut self, tool: &crate::db::schema::ToolRecord) -> Result<Vec<f32>> {
+        let text = self.tool_text(tool);
+        self.embed_text(&text).await
+    }
+
+    pub fn normalizer(&self) -> &TextNormalizer {
+        &self.normalizer
+    }
+
+    /// A tool's description as it is embedded, after the normalization pipeline.
+    pub fn normalized_description(&self, tool: &crate::db::schema::ToolRecord) -> Option<String> {
+        tool.description
+            .as_deref()
+            .map(|description| self.normalizer.normalize(description))
+    }
+
+    /// Text embedded for a tool.
+    fn tool_text(&self, tool: &crate::db::schema::ToolRecord) -> String {
+        self.tool_text_with_description(tool, tool.description.as_deref())
+    }
+
+    /// Text embedded for a tool, with `description` in place of its own.
+    fn tool_text_with_description(
+        &self,
+        tool: &crate::db::schema::ToolRecord,
+        description: Option<&str>,
+    ) -> String {
         // Combine tool name, description, and schema for embedding
         let mut text_parts = Vec::new();
         text_parts.push(format!("Tool: {}", tool.name));
 
-        if let Some(description) = &tool.description {
-            text_parts.push(format!("Description: {}", description));
+        // Only the description is normalized; schemas and examples are JSON
+        if let Some(description) = description {
+            text_parts.push(format!(
+                "Description: {}",
+                self.normalizer.normalize(description)
+            ));
         }
 
         // Include input schema information
@@ -131,13 +776,19 @@ impl EmbeddingManager {
             ));
         }
 
-        let combined_text = text_parts.join("\n");
-        self.embed_text(&combined_text).await
+        // Concrete invocations help match queries phrased as use cases
+        for example in &tool.input_examples {
+            if let Ok(example_text) = serde_json::to_string(example) {
+                text_parts.push(format!("Example: {}", example_text));
+            }
+        }
+
+        text_parts.join("\n")
     }
 
     pub async fn embed_batch(&mut self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
         // Check cache for each text
-        let mut uncached_texts = Vec::new();
+        let mut uncached_texts: Vec<String> = Vec::new();
         let mut uncached_indices = Vec::new();
         let mut results = vec![vec![]; texts.len()];
 
@@ -146,20 +797,20 @@ impl EmbeddingManager {
             if let Some(cached) = self.cache.get(&hash) {
                 results[i] = cached.clone();
             } else {
-                uncached_texts.push(text.as_str());
+                uncached_texts.push(text.clone());
                 uncached_indices.push(i);
             }
         }
 
         // Embed uncached texts using embed_anything
         if !uncached_texts.is_empty() {
-            let batch_results =
-                embed_query(&uncached_texts, &self.embedder, Some(&self.text_config)).await?;
+            let batch_results = self
+                .backend
+                .embed_batch(&uncached_texts, self.batch_size)
+                .await?;
 
-            for (j, embed_data) in batch_results.into_iter().enumerate() {
+            for (j, dense) in batch_results.into_iter().enumerate() {
                 let original_index = uncached_indices[j];
-                let embedding = embed_data.embedding;
-                let dense = embedding.to_dense()?;
                 results[original_index] = dense.clone();
 
                 // Cache the result
@@ -254,7 +905,20 @@ impl EmbeddingManager {
         Ok(row.map(|r| r.vector))
     }
 
+    /// Embed tools without an embedding or with one from a previous model, and
+    /// translated descriptions that are new or have changed.
+    ///
+    /// Returns the number of embeddings generated.
     pub async fn update_tool_embeddings(&mut self) -> Result<usize> {
+        let stale = QueryBuilder::clear_stale_tool_embeddings(&self.db, &self.model_name).await?;
+        if stale > 0 {
+            tracing::info!(
+                model = %self.model_name,
+                "Re-embedding {} tools embedded by a previous model",
+                stale
+            );
+        }
+
         // Get all tools without embeddings
         let query = r#"
         SELECT * FROM tool
@@ -264,48 +928,176 @@ impl EmbeddingManager {
         let mut result = self.db.query(query).await?;
         let tools: Vec<crate::db::schema::ToolRecord> = result.take(0)?;
 
-        let mut updated = 0;
+        if !tools.is_empty() {
+            let started = Instant::now();
+            let texts: Vec<String> = tools.iter().map(|tool| self.tool_text(tool)).collect();
+            let embeddings = self.embed_batch(&texts).await?;
 
-        for tool in tools {
-            // Generate embedding
-            let embedding = self.embed_tool(&tool).await?;
+            for (tool, embedding) in tools.iter().zip(embeddings) {
+                self.store_tool_embedding(tool, embedding).await?;
+            }
 
-            // Build a stable content hash for this tool's semantic description.
-            let schema_str = serde_json::to_string(&tool.input_schema)?;
+            tracing::info!(
+                "Embedded {} tools in {:.2?}",
+                tools.len(),
+                started.elapsed()
+            );
+        }
 
-            let content_hash = self.hash_content(&format!(
-                "{}:{}:{}",
-                tool.name,
-                tool.description.as_deref().unwrap_or(""),
-                schema_str,
-            ));
+        Ok(tools.len() + self.update_language_embeddings().await?)
+    }
 
-            let embedding_id = self
-                .store_embedding(
-                    embedding,
-                    self.model_name.clone(),
-                    "tool".to_string(),
-                    content_hash,
-                )
-                .await?;
+    /// Embed each tool's translated descriptions into `tool_embedding_by_lang`,
+    /// skipping those whose content hash is unchanged and dropping languages a
+    /// tool no longer lists. English translations are skipped, since English
+    /// queries search the main embeddings.
+    async fn update_language_embeddings(&mut self) -> Result<usize> {
+        let tools: Vec<crate::db::schema::ToolRecord> =
+            self.db.query("SELECT * FROM tool").await?.take(0)?;
+        let stored = QueryBuilder::language_embedding_hashes(&self.db, &self.model_name).await?;
 
-            // Update tool record
-            let update_query = r#"
-            UPDATE $tool_id SET
-                embedding_id = $embedding_id,
-                updated_at = time::now()
-            "#;
-
-            self.db
-                .query(update_query)
-                .bind(("tool_id", tool.id))
-                .bind(("embedding_id", embedding_id))
-                .await?;
+        let mut pending = Vec::new();
+        for tool in &tools {
+            let languages: Vec<String> = tool
+                .descriptions
+                .keys()
+                .filter(|language| !is_english(language))
+                .cloned()
+                .collect();
+            QueryBuilder::delete_stale_language_embeddings(&self.db, &tool.id, &languages).await?;
+
+            for language in languages {
+                let text =
+                    self.tool_text_with_description(tool, Some(&tool.descriptions[&language]));
+                let hash = self.hash_content(&text);
+                let key = (tool.id.to_string(), language.clone());
+                if stored.get(&key) != Some(&hash) {
+                    pending.push((tool.id.clone(), language, hash, text));
+                }
+            }
+        }
+
+        if pending.is_empty() {
+            return Ok(0);
+        }
+
+        let started = Instant::now();
+        let texts: Vec<String> = pending.iter().map(|(.., text)| text.clone()).collect();
+        let embeddings = self.embed_batch(&texts).await?;
+        for ((tool_id, language, hash, _), embedding) in pending.iter().zip(embeddings) {
+            QueryBuilder::store_language_embedding(
+                &self.db,
+                tool_id,
+                language,
+                &self.model_name,
+                hash.clone(),
+                embedding,
+            )
+            .await?;
+        }
+
+        tracing::info!(
+            "Embedded {} translated descriptions in {:.2?}",
+            pending.len(),
+            started.elapsed()
+        );
+        Ok(pending.len())
+    }
+
+    /// Whether a tool has no embedding, or its embedding was built by another
+    /// model or from content that has since changed (compared by content hash).
+    pub async fn needs_reembedding(&self, tool: &crate::db::schema::ToolRecord) -> Result<bool> {
+        #[derive(Deserialize)]
+        struct Stored {
+            content_hash: String,
+            model: String,
+        }
+
+        let Some(embedding_id) = &tool.embedding_id else {
+            return Ok(true);
+        };
+
+        let mut res = self
+            .db
+            .query("SELECT content_hash, model FROM $id")
+            .bind(("id", embedding_id.clone()))
+            .await?;
+        let stored: Option<Stored> = res.take(0)?;
+        let content_hash = self.tool_content_hash(tool)?;
+
+        Ok(stored.is_none_or(|stored| {
+            stored.model != self.model_name || stored.content_hash != content_hash
+        }))
+    }
+
+    /// Re-embed a tool if `needs_reembedding` reports a change.
+    ///
+    /// Returns whether a new embedding was generated.
+    pub async fn reembed_if_changed(
+        &mut self,
+        tool: &crate::db::schema::ToolRecord,
+    ) -> Result<bool> {
+        if !self.needs_reembedding(tool).await? {
+            return Ok(false);
+        }
+        self.embed_and_store_tool(tool).await?;
+        Ok(true)
+    }
+
+    async fn embed_and_store_tool(&mut self, tool: &crate::db::schema::ToolRecord) -> Result<()> {
+        // Generate embedding
+        let embedding = self.embed_tool(tool).await?;
+        self.store_tool_embedding(tool, embedding).await
+    }
+
+    async fn store_tool_embedding(
+        &self,
+        tool: &crate::db::schema::ToolRecord,
+        embedding: Vec<f32>,
+    ) -> Result<()> {
+        let content_hash = self.tool_content_hash(tool)?;
+
+        let embedding_id = self
+            .store_embedding(
+                embedding,
+                self.model_name.clone(),
+                "tool".to_string(),
+                content_hash,
+            )
+            .await?;
+
+        // Update tool record
+        let update_query = r#"
+        UPDATE $tool_id SET
+            embedding_id = $embedding_id,
+            updated_at = time::now()
+        "#;
+
+        self.db
+            .query(update_query)
+            .bind(("tool_id", tool.id.clone()))
+            .bind(("embedding_id", embedding_id))
+            .await?;
+
+        Ok(())
+    }
 
-            updated += 1;
+    /// Build a stable content hash for this tool's semantic description.
+    fn tool_content_hash(&self, tool: &crate::db::schema::ToolRecord) -> Result<String> {
+        let schema_str = serde_json::to_string(&tool.input_schema)?;
+        let mut content = format!(
+            "{}:{}:{}",
+            tool.name,
+            tool.description.as_deref().unwrap_or(""),
+            schema_str,
+        );
+        // Only appended when present so existing embeddings keep their hash
+        if !tool.input_examples.is_empty() {
+            content.push(':');
+            content.push_str(&serde_json::to_string(&tool.input_examples)?);
         }
 
-        Ok(updated)
+        Ok(self.hash_content(&content))
     }
 
     pub fn clear_cache(&mut self) {
@@ -345,30 +1137,1086 @@ pub struct EmbeddingSearchResult {
 
 impl EmbeddingManager {
     /// Search for tools by embedding similarity.
+    ///
+    /// If `requested_backend` names an allowed model, the search runs against
+    /// it; an unknown name is logged and ignored. Otherwise, if `user_prefs`
+    /// selects an allowed override model, the search runs against that model
+    /// instead. See [`Self::search_with_backend`].
+    ///
+    /// A non-English `language` (a BCP-47 tag such as `de-AT`) searches the
+    /// embeddings of the tools' descriptions in that language, or in its
+    /// primary language (`de`). Tools without such a description fall back to
+    /// their English embedding. Override models only embed English.
     pub async fn search_tools_by_embedding(
         &mut self,
         query: &str,
         limit: u32,
         threshold: f32,
+        requested_backend: Option<&str>,
+        user_prefs: Option<&UserPreferencesRecord>,
+        language: Option<&str>,
     ) -> Result<Vec<EmbeddingSearchResult>> {
+        let backend = match requested_backend.map(|name| (name, self.allowed_backend(name))) {
+            Some((_, Some(backend))) => Some((backend, BackendOverrideSource::Request)),
+            Some((name, None)) => {
+                tracing::warn!(
+                    model = %name,
+                    "Ignoring request for an embedding model that is not allowed"
+                );
+                self.backend_override(user_prefs)
+                    .map(|backend| (backend, BackendOverrideSource::User))
+            }
+            None => self
+                .backend_override(user_prefs)
+                .map(|backend| (backend, BackendOverrideSource::User)),
+        };
+        if let Some((backend, source)) = backend
+            && backend.model_name != self.model_name
+        {
+            self.metrics
+                .record_override_search(&backend.model_name, source);
+            return self
+                .search_with_backend(&backend, query, limit, threshold)
+                .await;
+        }
+
         // Generate query embedding using embed_anything.
         let query_vector = self.embed_text(query).await?;
 
         // Delegate to the DB query helper to perform the vector search and
         // map embeddings back to tools.
-        let matches =
-            QueryBuilder::find_tools_by_embedding(&self.db, query_vector, limit, threshold).await?;
+        let mut matches = QueryBuilder::find_tools_by_embedding(
+            &self.db,
+            &self.model_name,
+            query_vector.clone(),
+            limit,
+            threshold,
+        )
+        .await?;
+
+        if let Some(language) = language.filter(|language| !is_english(language)) {
+            let localized = QueryBuilder::find_tools_by_language_embedding(
+                &self.db,
+                &self.model_name,
+                &language_fallbacks(language),
+                query_vector.clone(),
+                limit,
+                threshold,
+            )
+            .await?;
+            matches = merge_localized_matches(localized, matches, limit as usize);
+        }
+
+        // Tools users keep rejecting for similar queries rank lower
+        let mut results = Self::to_search_results(matches);
+        self.feedback
+            .read()
+            .expect("feedback penalties lock poisoned")
+            .apply(&mut results, &query_vector, self.feedback_weight, threshold);
+        Ok(results)
+    }
+
+    /// The allowed override model with this name. The global model always
+    /// counts as allowed.
+    pub fn allowed_backend(&self, model_name: &str) -> Option<EmbeddingBackendConfig> {
+        if model_name == self.model_name {
+            return Some(EmbeddingBackendConfig {
+                model_name: self.model_name.clone(),
+                model_architecture: String::new(),
+            });
+        }
+        self.allowed_backend_overrides
+            .iter()
+            .find(|backend| backend.model_name == model_name)
+            .cloned()
+    }
 
-        let mut results = Vec::new();
+    /// The override model selected in `user_prefs`, unless it is the global
+    /// model or has since been removed from the allowed overrides.
+    fn backend_override(
+        &self,
+        user_prefs: Option<&UserPreferencesRecord>,
+    ) -> Option<EmbeddingBackendConfig> {
+        let selected = user_prefs?.embedding_backend_override.as_ref()?;
+        if selected.model_name == self.model_name {
+            return None;
+        }
+        let allowed = self.allowed_backend(&selected.model_name);
+        if allowed.is_none() {
+            tracing::warn!(
+                model = %selected.model_name,
+                "Ignoring embedding override that is no longer allowed"
+            );
+        }
+        allowed
+    }
 
-        for (tool, similarity) in matches {
-            results.push(EmbeddingSearchResult {
+    /// Search with an override model.
+    ///
+    /// Tools the model hasn't embedded yet are embedded first, so the first
+    /// search with a model embeds the whole catalogue. Feedback penalties are
+    /// learned from the global model's vectors and don't apply here.
+    async fn search_with_backend(
+        &mut self,
+        config: &EmbeddingBackendConfig,
+        query: &str,
+        limit: u32,
+        threshold: f32,
+    ) -> Result<Vec<EmbeddingSearchResult>> {
+        let backend = self.override_backend(config)?;
+
+        let tools: Vec<crate::db::schema::ToolRecord> =
+            self.db.query("SELECT * FROM tool").await?.take(0)?;
+        let mut tools_by_hash: HashMap<String, Vec<crate::db::schema::ToolRecord>> = HashMap::new();
+        for tool in tools {
+            let hash = self.tool_content_hash(&tool)?;
+            tools_by_hash.entry(hash).or_default().push(tool);
+        }
+
+        let embedded: HashSet<String> =
+            QueryBuilder::embedded_content_hashes(&self.db, &config.model_name)
+                .await?
+                .into_iter()
+                .collect();
+        let (missing_hashes, missing_texts): (Vec<String>, Vec<String>) = tools_by_hash
+            .iter()
+            .filter(|(hash, _)| !embedded.contains(*hash))
+            .map(|(hash, tools)| (hash.clone(), self.tool_text(&tools[0])))
+            .unzip();
+        if !missing_texts.is_empty() {
+            tracing::info!(
+                model = %config.model_name,
+                "Embedding {} tools with override model",
+                missing_texts.len()
+            );
+            let vectors = backend.embed_batch(&missing_texts, self.batch_size).await?;
+            for (hash, vector) in missing_hashes.into_iter().zip(vectors) {
+                self.store_embedding(vector, config.model_name.clone(), "tool".to_string(), hash)
+                    .await?;
+            }
+        }
+
+        // Cached apart from the global model's query vectors
+        let cache_key = self.hash_content(&format!("{}:{}", config.model_name, query));
+        let query_vector = match self.cache.get(&cache_key) {
+            Some(cached) => cached.clone(),
+            None => {
+                let vector = backend
+                    .embed(&[query.to_string()])
+                    .await?
+                    .pop()
+                    .ok_or_else(|| {
+                        anyhow::anyhow!("embed_anything returned no embeddings for query")
+                    })?;
+                self.cache.insert(cache_key, vector.clone());
+                vector
+            }
+        };
+
+        let hashes: Vec<String> = tools_by_hash.keys().cloned().collect();
+        let hits = QueryBuilder::find_embeddings_by_model(
+            &self.db,
+            &config.model_name,
+            &hashes,
+            query_vector,
+            limit,
+            threshold,
+        )
+        .await?;
+
+        let matches = hits
+            .into_iter()
+            .flat_map(|(hash, similarity)| {
+                tools_by_hash
+                    .get(&hash)
+                    .into_iter()
+                    .flatten()
+                    .map(move |tool| (tool.clone(), similarity))
+            })
+            .collect();
+        Ok(Self::to_search_results(matches))
+    }
+
+    /// Load an override model on first use.
+    fn override_backend(
+        &mut self,
+        config: &EmbeddingBackendConfig,
+    ) -> Result<Arc<EmbeddingBackend>> {
+        if let Some(backend) = self.override_backends.get(&config.model_name) {
+            return Ok(backend.clone());
+        }
+        tracing::info!(model = %config.model_name, "Loading embedding override model");
+        let backend = Arc::new(EmbeddingBackend::new(
+            config.load()?,
+            self.max_concurrent_embed_requests,
+        ));
+        self.override_backends
+            .insert(config.model_name.clone(), backend.clone());
+        Ok(backend)
+    }
+
+    /// Search for the `k` tools closest to a pre-computed embedding vector.
+    ///
+    /// Skips the embedding model entirely. Fails with
+    /// [`EmbeddingError::DimensionMismatch`] if the vector's length differs from
+    /// the embeddings stored for the current model.
+    pub async fn nearest_neighbors(
+        &self,
+        embedding: Vec<f32>,
+        k: usize,
+        min_similarity: f32,
+    ) -> Result<Vec<EmbeddingSearchResult>> {
+        if let Some(expected) =
+            QueryBuilder::embedding_dimension(&self.db, &self.model_name).await?
+            && expected != embedding.len()
+        {
+            return Err(EmbeddingError::DimensionMismatch {
+                expected,
+                got: embedding.len(),
+            }
+            .into());
+        }
+
+        let limit = u32::try_from(k).unwrap_or(u32::MAX);
+        let matches = QueryBuilder::find_tools_by_embedding(
+            &self.db,
+            &self.model_name,
+            embedding,
+            limit,
+            min_similarity,
+        )
+        .await?;
+
+        Ok(Self::to_search_results(matches))
+    }
+
+    fn to_search_results(
+        matches: Vec<(crate::db::schema::ToolRecord, f32)>,
+    ) -> Vec<EmbeddingSearchResult> {
+        matches
+            .into_iter()
+            .map(|(tool, similarity)| EmbeddingSearchResult {
                 tool_id: tool.id.clone(),
                 similarity,
                 tool: Some(tool),
-            });
+            })
+            .collect()
+    }
+}
+
+/// Localized matches, then English matches of tools not matched in the
+/// localized search, best first.
+fn merge_localized_matches(
+    localized: Vec<(crate::db::schema::ToolRecord, f32)>,
+    english: Vec<(crate::db::schema::ToolRecord, f32)>,
+    limit: usize,
+) -> Vec<(crate::db::schema::ToolRecord, f32)> {
+    let mut merged = localized;
+    for (tool, similarity) in english {
+        if !merged.iter().any(|(matched, _)| matched.id == tool.id) {
+            merged.push((tool, similarity));
         }
+    }
+    merged.sort_by(|a, b| b.1.total_cmp(&a.1));
+    merged.truncate(limit);
+    merged
+}
 
-        Ok(results)
+/// Why two tools are considered similar.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimilarityExplanation {
+    /// Cosine similarity of the two tools' embeddings.
+    pub score: f32,
+    /// Keywords found in both descriptions, by TF-IDF weight.
+    pub shared_keywords: Vec<String>,
+    /// Keywords found only in the first tool's description, by TF-IDF weight.
+    pub distinctive_a: Vec<String>,
+    /// Keywords found only in the second tool's description, by TF-IDF weight.
+    pub distinctive_b: Vec<String>,
+    /// The embedding dimensions with the largest share of the cosine similarity,
+    /// as `(dimension, contribution)`; contributions sum to `score` over all dimensions.
+    pub dimension_contributions: Vec<(usize, f32)>,
+}
+
+/// Maximum number of keywords listed in each group of a `SimilarityExplanation`.
+const MAX_EXPLANATION_KEYWORDS: usize = 10;
+
+/// Number of embedding dimensions listed in a `SimilarityExplanation`.
+const TOP_DIMENSIONS: usize = 5;
+
+const STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "by", "for", "from", "in", "into", "is", "it",
+    "its", "of", "on", "or", "that", "the", "this", "to", "with",
+];
+
+impl EmbeddingManager {
+    /// Explain the embedding similarity of two tools.
+    ///
+    /// Fails with [`EmbeddingError::ToolNotFound`] or
+    /// [`EmbeddingError::MissingEmbedding`] if either tool can't be compared.
+    pub async fn explain_similarity(
+        &self,
+        tool_a_id: &str,
+        tool_b_id: &str,
+    ) -> Result<SimilarityExplanation> {
+        let (description_a, vector_a) = self.tool_description_and_vector(tool_a_id).await?;
+        let (description_b, vector_b) = self.tool_description_and_vector(tool_b_id).await?;
+
+        if vector_a.len() != vector_b.len() {
+            return Err(EmbeddingError::DimensionMismatch {
+                expected: vector_a.len(),
+                got: vector_b.len(),
+            }
+            .into());
+        }
+
+        Ok(explain_similarity(
+            &description_a,
+            &description_b,
+            &vector_a,
+            &vector_b,
+        ))
+    }
+
+    /// Pairs of services whose tools embed alike, most similar first.
+    ///
+    /// Each service is represented by the centroid of its tools' embeddings.
+    /// Pairs whose centroids are more similar than the configured
+    /// `service_duplicate_threshold` are likely the same service configured
+    /// twice under different names.
+    pub async fn cross_service_similarity_matrix(
+        &self,
+    ) -> Result<Vec<(ServiceId, ServiceId, f32)>> {
+        let vectors =
+            QueryBuilder::service_tool_embedding_vectors(&self.db, &self.model_name).await?;
+        Ok(similar_services(vectors, self.service_duplicate_threshold))
+    }
+
+    /// Show how a tool's description is normalized before embedding.
+    ///
+    /// Fails with [`EmbeddingError::ToolNotFound`] if no tool has this id.
+    pub async fn describe_normalization(
+        &self,
+        tool_id: &RecordId,
+    ) -> Result<NormalizedDescription> {
+        let tool: Option<crate::db::schema::ToolRecord> = self
+            .db
+            .query("SELECT * FROM $id")
+            .bind(("id", tool_id.clone()))
+            .await?
+            .take(0)?;
+        let tool = tool.ok_or_else(|| EmbeddingError::ToolNotFound(tool_id.to_string()))?;
+
+        Ok(NormalizedDescription {
+            normalized_description: self.normalized_description(&tool),
+            description: tool.description,
+            steps: self.normalizer.steps().to_vec(),
+        })
+    }
+
+    async fn tool_description_and_vector(&self, tool_id: &str) -> Result<(String, Vec<f32>)> {
+        let tool: Option<crate::db::schema::ToolRecord> = self
+            .db
+            .query("SELECT * FROM type::thing($id)")
+            .bind(("id", tool_id.to_string()))
+            .await?
+            .take(0)?;
+        let tool = tool.ok_or_else(|| EmbeddingError::ToolNotFound(tool_id.to_string()))?;
+
+        let embedding_id = tool
+            .embedding_id
+            .clone()
+            .ok_or_else(|| EmbeddingError::MissingEmbedding(tool_id.to_string()))?;
+        let vector: Option<Vec<f32>> = self
+            .db
+            .query("SELECT VALUE vector FROM $id")
+            .bind(("id", embedding_id))
+            .await?
+            .take(0)?;
+        let vector = vector.ok_or_else(|| EmbeddingError::MissingEmbedding(tool_id.to_string()))?;
+
+        Ok((tool.description.unwrap_or_default(), vector))
+    }
+}
+
+/// Build a `SimilarityExplanation` from two descriptions and their embeddings.
+///
+/// Keywords are weighted by TF-IDF with the two descriptions as the corpus,
+/// using smoothed IDF so that shared keywords still carry weight.
+pub fn explain_similarity(
+    description_a: &str,
+    description_b: &str,
+    vector_a: &[f32],
+    vector_b: &[f32],
+) -> SimilarityExplanation {
+    let terms_a = term_frequencies(description_a);
+    let terms_b = term_frequencies(description_b);
+
+    let idf = |term: &str| {
+        let df = usize::from(terms_a.contains_key(term)) + usize::from(terms_b.contains_key(term));
+        (3.0 / (1.0 + df as f32)).ln() + 1.0
+    };
+    let ranked = |terms: Vec<(&String, f32)>| {
+        let mut terms = terms;
+        terms.sort_by(|(term_x, x), (term_y, y)| y.total_cmp(x).then_with(|| term_x.cmp(term_y)));
+        terms
+            .into_iter()
+            .take(MAX_EXPLANATION_KEYWORDS)
+            .map(|(term, _)| term.clone())
+            .collect::<Vec<_>>()
+    };
+
+    let keys_b: HashSet<&String> = terms_b.keys().collect();
+    let shared = ranked(
+        terms_a
+            .iter()
+            .filter(|(term, _)| keys_b.contains(term))
+            .map(|(term, tf)| (term, (tf + terms_b[term]) * idf(term)))
+            .collect(),
+    );
+    let distinctive_a = ranked(
+        terms_a
+            .iter()
+            .filter(|(term, _)| !terms_b.contains_key(*term))
+            .map(|(term, tf)| (term, tf * idf(term)))
+            .collect(),
+    );
+    let distinctive_b = ranked(
+        terms_b
+            .iter()
+            .filter(|(term, _)| !terms_a.contains_key(*term))
+            .map(|(term, tf)| (term, tf * idf(term)))
+            .collect(),
+    );
+
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let denominator = norm(vector_a) * norm(vector_b);
+    let mut contributions: Vec<(usize, f32)> = if denominator == 0.0 {
+        Vec::new()
+    } else {
+        vector_a
+            .iter()
+            .zip(vector_b)
+            .map(|(a, b)| a * b / denominator)
+            .enumerate()
+            .collect()
+    };
+    let score = contributions.iter().map(|(_, c)| c).sum();
+    contributions.sort_by(|(_, x), (_, y)| y.abs().total_cmp(&x.abs()));
+    contributions.truncate(TOP_DIMENSIONS);
+
+    SimilarityExplanation {
+        score,
+        shared_keywords: shared,
+        distinctive_a,
+        distinctive_b,
+        dimension_contributions: contributions,
+    }
+}
+
+/// Relative frequency of each keyword in `text`, skipping stop words and short tokens.
+fn term_frequencies(text: &str) -> HashMap<String, f32> {
+    let tokens: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .map(str::to_lowercase)
+        .filter(|token| token.len() > 2 && !STOP_WORDS.contains(&token.as_str()))
+        .collect();
+
+    let mut frequencies = HashMap::new();
+    for token in &tokens {
+        *frequencies.entry(token.clone()).or_insert(0.0) += 1.0;
+    }
+    for frequency in frequencies.values_mut() {
+        *frequency /= tokens.len() as f32;
+    }
+    frequencies
+}
+
+/// Pairs of services whose tool embedding centroids have a cosine similarity
+/// above `threshold`, most similar first.
+fn similar_services(
+    tool_vectors: Vec<(RecordId, Vec<f32>)>,
+    threshold: f32,
+) -> Vec<(ServiceId, ServiceId, f32)> {
+    let mut by_service: BTreeMap<String, Vec<Vec<f32>>> = BTreeMap::new();
+    for (service_id, vector) in tool_vectors {
+        by_service
+            .entry(service_id.to_string())
+            .or_default()
+            .push(vector);
+    }
+
+    let (services, centroids): (Vec<String>, Vec<Vec<f32>>) = by_service
+        .into_iter()
+        .map(|(service, vectors)| {
+            let mut centroid = vec![0.0; vectors[0].len()];
+            for vector in &vectors {
+                for (sum, x) in centroid.iter_mut().zip(vector) {
+                    *sum += x;
+                }
+            }
+            for sum in &mut centroid {
+                *sum /= vectors.len() as f32;
+            }
+            (service, centroid)
+        })
+        .unzip();
+
+    let matrix = pairwise_cosine(&centroids);
+    let mut pairs = Vec::new();
+    for i in 0..services.len() {
+        for j in i + 1..services.len() {
+            if matrix[i][j] > threshold {
+                pairs.push((
+                    ServiceId::new(services[i].clone()),
+                    ServiceId::new(services[j].clone()),
+                    matrix[i][j],
+                ));
+            }
+        }
+    }
+    pairs.sort_by(|a, b| b.2.total_cmp(&a.2));
+    pairs
+}
+
+/// Cosine similarity of every pair of vectors, as a symmetric matrix.
+///
+/// Pairs involving a zero vector (e.g. a tool without an embedding) have
+/// similarity 0.
+pub fn pairwise_cosine(vectors: &[Vec<f32>]) -> Vec<Vec<f32>> {
+    let norms: Vec<f32> = vectors
+        .iter()
+        .map(|v| v.iter().map(|x| x * x).sum::<f32>().sqrt())
+        .collect();
+
+    let mut matrix = vec![vec![0.0; vectors.len()]; vectors.len()];
+    for i in 0..vectors.len() {
+        for j in i..vectors.len() {
+            let denominator = norms[i] * norms[j];
+            if denominator == 0.0 {
+                continue;
+            }
+            let dot: f32 = vectors[i].iter().zip(&vectors[j]).map(|(a, b)| a * b).sum();
+            matrix[i][j] = dot / denominator;
+            matrix[j][i] = matrix[i][j];
+        }
+    }
+    matrix
+}
+
+/// Pick `k` candidates by maximal marginal relevance.
+///
+/// Each pick maximizes `lambda * relevance - (1 - lambda) * max_similarity`,
+/// where `max_similarity` is the candidate's highest similarity to anything
+/// already picked. Returns indices into `relevance` in pick order.
+pub fn maximal_marginal_relevance(
+    relevance: &[f32],
+    similarity: &[Vec<f32>],
+    k: usize,
+    lambda: f32,
+) -> Vec<usize> {
+    let mut selected: Vec<usize> = Vec::with_capacity(k.min(relevance.len()));
+    let mut remaining: Vec<usize> = (0..relevance.len()).collect();
+
+    while selected.len() < k && !remaining.is_empty() {
+        let score = |candidate: usize| {
+            let redundancy = selected
+                .iter()
+                .map(|&picked| similarity[candidate][picked])
+                .fold(0.0, f32::max);
+            lambda * relevance[candidate] - (1.0 - lambda) * redundancy
+        };
+        let (position, _) = remaining
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| score(**a).total_cmp(&score(**b)).then(b.cmp(a)))
+            .expect("remaining is not empty");
+        selected.push(remaining.remove(position));
+    }
+    selected
+}
+
+/// How often a tool is re-embedded, based on its usage relative to other tools.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UsageTier {
+    /// Never used; checked on every scheduler pass.
+    New,
+    /// Top usage quartile.
+    High,
+    /// Between the bottom and top quartiles.
+    Medium,
+    /// Bottom usage quartile.
+    Low,
+}
+
+impl UsageTier {
+    /// Re-embedding interval for this tier.
+    pub fn interval(self, config: &EmbeddingConfig) -> Duration {
+        match self {
+            UsageTier::New => Duration::ZERO,
+            UsageTier::High => config.high_frequency_interval,
+            UsageTier::Medium => config.medium_frequency_interval,
+            UsageTier::Low => config.low_frequency_interval,
+        }
+    }
+
+    /// Whether a tool last re-embedded at `last_run` is due at `now`.
+    pub fn is_due(self, last_run: Option<Instant>, now: Instant, config: &EmbeddingConfig) -> bool {
+        last_run.is_none_or(|last| now.duration_since(last) >= self.interval(config))
+    }
+}
+
+/// Assign each usage count a tier, using quartiles of the non-zero counts.
+///
+/// Tools with zero usage are `New`. When every used tool has the same count
+/// they are all `High`.
+pub fn usage_tiers(usage_counts: &[u64]) -> Vec<UsageTier> {
+    let mut used: Vec<u64> = usage_counts.iter().copied().filter(|c| *c > 0).collect();
+    used.sort_unstable();
+
+    let offset = used.len().saturating_sub(1) / 4;
+    let (lower, upper) = match (used.get(offset), used.len().checked_sub(1 + offset)) {
+        (Some(lower), Some(upper)) => (*lower, used[upper]),
+        _ => (0, 0),
+    };
+
+    usage_counts
+        .iter()
+        .map(|&count| {
+            if count == 0 {
+                UsageTier::New
+            } else if count >= upper {
+                UsageTier::High
+            } else if count <= lower {
+                UsageTier::Low
+            } else {
+                UsageTier::Medium
+            }
+        })
+        .collect()
+}
+
+/// Background task that keeps tool embeddings fresh, re-embedding busy tools
+/// more often than rarely used ones.
+pub struct EmbeddingScheduler {
+    db: Surreal<Any>,
+    embedding_manager: Arc<Mutex<EmbeddingManager>>,
+    config: EmbeddingConfig,
+    /// Key: tool id, Value: when the scheduler last checked it
+    last_run: std::sync::Mutex<HashMap<String, Instant>>,
+    task: std::sync::Mutex<Option<JoinHandle<()>>>,
+}
+
+impl EmbeddingScheduler {
+    pub fn new(
+        db: Surreal<Any>,
+        embedding_manager: Arc<Mutex<EmbeddingManager>>,
+        config: EmbeddingConfig,
+    ) -> Self {
+        Self {
+            db,
+            embedding_manager,
+            config,
+            last_run: std::sync::Mutex::new(HashMap::new()),
+            task: std::sync::Mutex::new(None),
+        }
+    }
+
+    /// Spawn the background loop. Calling this more than once is a no-op.
+    pub fn start(self: &Arc<Self>) {
+        let mut task = self.task.lock().expect("scheduler task lock poisoned");
+        if task.is_some() {
+            return;
+        }
+
+        let scheduler = Arc::clone(self);
+        *task = Some(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(scheduler.config.reembed_check_interval);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                if let Err(e) = scheduler.run_once().await {
+                    tracing::warn!("Scheduled re-embedding failed: {}", e);
+                }
+            }
+        }));
+    }
+
+    /// Abort the background loop, if running.
+    pub fn stop(&self) {
+        let handle = self
+            .task
+            .lock()
+            .expect("scheduler task lock poisoned")
+            .take();
+        if let Some(handle) = handle {
+            handle.abort();
+        }
+    }
+
+    /// Check every tool whose tier interval has elapsed and re-embed those whose
+    /// content changed. Returns the number of tools re-embedded.
+    pub async fn run_once(&self) -> Result<usize> {
+        let tools: Vec<crate::db::schema::ToolRecord> =
+            self.db.query("SELECT * FROM tool").await?.take(0)?;
+        let counts: Vec<u64> = tools.iter().map(|t| t.usage_count).collect();
+        let now = Instant::now();
+
+        let due: Vec<_> = {
+            let last_run = self.last_run.lock().expect("scheduler lock poisoned");
+            tools
+                .into_iter()
+                .zip(usage_tiers(&counts))
+                .filter(|(tool, tier)| {
+                    tier.is_due(
+                        last_run.get(&tool.id.to_string()).copied(),
+                        now,
+                        &self.config,
+                    )
+                })
+                .map(|(tool, _)| tool)
+                .collect()
+        };
+
+        let mut reembedded = 0;
+        for tool in due {
+            let result = self
+                .embedding_manager
+                .lock()
+                .await
+                .reembed_if_changed(&tool)
+                .await;
+            match result {
+                Ok(true) => reembedded += 1,
+                Ok(false) => {}
+                Err(e) => tracing::warn!("Failed to re-embed tool `{}`: {}", tool.name, e),
+            }
+            self.last_run
+                .lock()
+                .expect("scheduler lock poisoned")
+                .insert(tool.id.to_string(), now);
+        }
+
+        if reembedded > 0 {
+            tracing::info!("Re-embedded {} tools with changed content", reembedded);
+        }
+        Ok(reembedded)
+    }
+}
+
+impl Drop for EmbeddingScheduler {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backend_architecture_inferred_from_model_name() {
+        let backend = |model_name: &str, model_architecture: &str| EmbeddingBackendConfig {
+            model_name: model_name.to_string(),
+            model_architecture: model_architecture.to_string(),
+        };
+        assert_eq!(
+            backend("Qwen/Qwen3-Embedding-0.6B", "").architecture(),
+            "qwen3"
+        );
+        assert_eq!(
+            backend("jinaai/jina-embeddings-v2-small-en", "").architecture(),
+            "jina"
+        );
+        assert_eq!(
+            backend("BAAI/bge-small-en-v1.5", "bert").architecture(),
+            "bert"
+        );
+    }
+
+    #[test]
+    fn test_model_kind_selects_stored_model_name() {
+        let config = EmbeddingConfig::default();
+        assert_eq!(config.model, EmbeddingModelKind::Custom);
+        assert_eq!(config.effective_model_name(), "Qwen/QWen3-Embedding-0.6B");
+
+        let config: EmbeddingConfig = serde_json::from_value(serde_json::json!({
+            "model": "openai_text_embedding_ada_002",
+            "model_name": "ignored",
+            "model_architecture": "",
+            "remote_endpoint": "http://localhost:8080/v1/embeddings",
+            "api_key": "sk-test",
+            "dimension": 1536,
+            "batch_size": 100,
+            "max_concurrent_embed_requests": 4,
+            "reembed_check_interval": { "secs": 60, "nanos": 0 },
+            "high_frequency_interval": { "secs": 3600, "nanos": 0 },
+            "medium_frequency_interval": { "secs": 21600, "nanos": 0 },
+            "low_frequency_interval": { "secs": 86400, "nanos": 0 },
+            "feedback_weight": 0.2,
+            "feedback_refresh_interval": { "secs": 300, "nanos": 0 }
+        }))
+        .unwrap();
+        assert_eq!(config.model, EmbeddingModelKind::OpenAiTextEmbeddingAda002);
+        assert!(config.model.is_remote());
+        assert_eq!(config.model.dimension(), Some(1536));
+        assert_eq!(config.effective_model_name(), "text-embedding-ada-002");
+        assert_eq!(
+            config.remote_endpoint.as_ref().map(Url::as_str),
+            Some("http://localhost:8080/v1/embeddings")
+        );
+        // The API key is never written back out
+        assert!(
+            serde_json::to_value(&config)
+                .unwrap()
+                .get("api_key")
+                .is_none()
+        );
+
+        let e5 = EmbeddingConfig {
+            model: EmbeddingModelKind::E5Large,
+            ..Default::default()
+        };
+        assert!(!e5.model.is_remote());
+        assert_eq!(e5.effective_model_name(), "intfloat/e5-large-v2");
+    }
+
+    #[test]
+    fn test_default_normalizer_strips_html_and_whitespace() {
+        let normalizer = TextNormalizer::new(EmbeddingConfig::default().normalization_steps);
+        assert_eq!(
+            normalizer.normalize("<p>Reads a <b>file</b>  &amp; returns\n its\tcontents</p>"),
+            "Reads a file & returns its contents"
+        );
+        // Not a tag
+        assert_eq!(normalizer.normalize("x < y and y>z"), "x < y and y>z");
+    }
+
+    #[test]
+    fn test_normalizer_runs_steps_in_order() {
+        let normalizer = TextNormalizer::new(vec![
+            NormalizationStep::Lowercase,
+            NormalizationStep::RemoveStopWords(vec!["The".to_string(), "a".to_string()]),
+            NormalizationStep::TruncateToTokens(3),
+        ]);
+        assert_eq!(
+            normalizer.normalize("Fetch THE weather, for a city (the capital)"),
+            "fetch weather, for"
+        );
+        assert_eq!(truncate_to_tokens("  one  two ", 5), "  one  two ");
+        assert_eq!(truncate_to_tokens("one two", 0), "");
+
+        let steps: Vec<NormalizationStep> = serde_json::from_value(serde_json::json!([
+            { "kind": "strip_html" },
+            { "kind": "truncate_to_tokens", "value": 128 }
+        ]))
+        .unwrap();
+        assert_eq!(
+            steps,
+            vec![
+                NormalizationStep::StripHtml,
+                NormalizationStep::TruncateToTokens(128)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_usage_tiers_by_quartile() {
+        let tiers = usage_tiers(&[0, 1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(
+            tiers,
+            vec![
+                UsageTier::New,
+                UsageTier::Low,
+                UsageTier::Low,
+                UsageTier::Medium,
+                UsageTier::Medium,
+                UsageTier::Medium,
+                UsageTier::Medium,
+                UsageTier::High,
+                UsageTier::High,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_usage_tiers_edge_cases() {
+        assert!(usage_tiers(&[]).is_empty());
+        assert_eq!(usage_tiers(&[0, 0]), vec![UsageTier::New, UsageTier::New]);
+        assert_eq!(usage_tiers(&[5]), vec![UsageTier::High]);
+        assert_eq!(
+            usage_tiers(&[3, 3, 3]),
+            vec![UsageTier::High, UsageTier::High, UsageTier::High]
+        );
+    }
+
+    #[test]
+    fn test_tier_is_due() {
+        let config = EmbeddingConfig::default();
+        let now = Instant::now();
+        let recently = now - Duration::from_secs(2 * 60 * 60);
+
+        assert!(UsageTier::Low.is_due(None, now, &config));
+        assert!(UsageTier::New.is_due(Some(now), now, &config));
+        assert!(UsageTier::High.is_due(Some(recently), now, &config));
+        assert!(!UsageTier::Medium.is_due(Some(recently), now, &config));
+        assert!(!UsageTier::Low.is_due(Some(recently), now, &config));
+    }
+
+    #[tokio::test]
+    async fn test_embed_chunked_preserves_order_and_bounds_concurrency() {
+        let texts: Vec<String> = (0..250).map(|i| i.to_string()).collect();
+        let limit = Semaphore::new(2);
+        let in_flight = AtomicUsize::new(0);
+        let max_in_flight = AtomicUsize::new(0);
+
+        let embeddings = embed_chunked(&texts, 30, &limit, |chunk| {
+            let in_flight = &in_flight;
+            let max_in_flight = &max_in_flight;
+            async move {
+                let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                max_in_flight.fetch_max(current, Ordering::SeqCst);
+                tokio::task::yield_now().await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                Ok(chunk
+                    .iter()
+                    .map(|text| vec![text.parse::<f32>().unwrap()])
+                    .collect())
+            }
+        })
+        .await
+        .unwrap();
+
+        let expected: Vec<Vec<f32>> = (0..250).map(|i| vec![i as f32]).collect();
+        assert_eq!(embeddings, expected);
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_batched_embedding_outpaces_one_request_per_text() {
+        // A mock backend whose requests take 5ms regardless of size
+        async fn embed(chunk: &[String]) -> Result<Vec<Vec<f32>>> {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            Ok(chunk.iter().map(|_| vec![0.0]).collect())
+        }
+        let config = EmbeddingConfig::default();
+        let texts: Vec<String> = (0..128).map(|i| format!("tool {i}")).collect();
+
+        let started = Instant::now();
+        let sequential = embed_chunked(&texts, 1, &Semaphore::new(1), embed)
+            .await
+            .unwrap();
+        let sequential_time = started.elapsed();
+
+        let started = Instant::now();
+        let limit = Semaphore::new(config.max_concurrent_embed_requests);
+        let batched = embed_chunked(&texts, config.batch_size, &limit, embed)
+            .await
+            .unwrap();
+        let batched_time = started.elapsed();
+
+        assert_eq!(sequential.len(), texts.len());
+        assert_eq!(batched.len(), texts.len());
+        assert!(
+            sequential_time >= batched_time * 3,
+            "sequential {sequential_time:?}, batched {batched_time:?}"
+        );
+    }
+
+    #[test]
+    fn test_explain_similarity() {
+        let explanation = explain_similarity(
+            "Read a file from the local filesystem and return its contents",
+            "Write contents to a file on the local filesystem",
+            &[1.0, 0.0, 2.0, -1.0, 0.5, 0.0],
+            &[1.0, 3.0, 1.0, 1.0, 0.5, 0.0],
+        );
+
+        assert_eq!(
+            explanation.shared_keywords,
+            vec!["contents", "file", "filesystem", "local"]
+        );
+        assert_eq!(explanation.distinctive_a, vec!["read", "return"]);
+        assert_eq!(explanation.distinctive_b, vec!["write"]);
+
+        // dot = 2.25, |a| = 2.5, |b| = 3.5
+        assert!((explanation.score - 2.25 / 8.75).abs() < 1e-6);
+        assert_eq!(explanation.dimension_contributions.len(), 5);
+        let dimensions: Vec<usize> = explanation
+            .dimension_contributions
+            .iter()
+            .map(|(dimension, _)| *dimension)
+            .collect();
+        assert_eq!(&dimensions[..3], &[2, 0, 3]);
+        assert!(explanation.dimension_contributions[2].1 < 0.0);
+    }
+
+    #[test]
+    fn test_pairwise_cosine() {
+        let matrix = pairwise_cosine(&[vec![1.0, 0.0], vec![1.0, 1.0], vec![0.0, 0.0]]);
+
+        assert!((matrix[0][0] - 1.0).abs() < 1e-6);
+        assert!((matrix[0][1] - std::f32::consts::FRAC_1_SQRT_2).abs() < 1e-6);
+        assert_eq!(matrix[0][1], matrix[1][0]);
+        assert_eq!(matrix[2], vec![0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_embedding_metrics_count_overrides_by_backend() {
+        let metrics = EmbeddingMetrics::new();
+        metrics.record_override_search("jina-small", BackendOverrideSource::Request);
+        metrics.record_override_search("jina-small", BackendOverrideSource::Request);
+        metrics.record_override_search("bge", BackendOverrideSource::User);
+
+        let mut out = String::new();
+        metrics.encode(&mut out);
+        assert!(out.contains(
+            "embedding_override_searches_total{backend=\"jina-small\",source=\"request\"} 2\n"
+        ));
+        assert!(
+            out.contains("embedding_override_searches_total{backend=\"bge\",source=\"user\"} 1\n")
+        );
+    }
+
+    #[test]
+    fn test_similar_services_compares_centroids() {
+        let service = |key: &str| RecordId::from_table_key("service", key);
+        let vectors = vec![
+            (service("fs"), vec![1.0, 0.0, 0.0]),
+            (service("fs"), vec![0.0, 1.0, 0.0]),
+            (service("files"), vec![0.9, 1.0, 0.0]),
+            (service("files"), vec![1.0, 0.1, 0.0]),
+            (service("github"), vec![0.0, 0.0, 1.0]),
+        ];
+
+        let pairs = similar_services(vectors.clone(), SERVICE_DUPLICATE_THRESHOLD);
+        assert_eq!(pairs.len(), 1);
+        let (a, b, similarity) = &pairs[0];
+        assert_eq!((a.as_str(), b.as_str()), ("service:files", "service:fs"));
+        assert!(*similarity > SERVICE_DUPLICATE_WARN_THRESHOLD);
+
+        // Every distinct pair is compared once
+        assert_eq!(similar_services(vectors, -1.0).len(), 3);
+    }
+
+    #[test]
+    fn test_maximal_marginal_relevance_prefers_diverse_candidates() {
+        // 0 and 1 are near-duplicates; 2 is less relevant but different
+        let relevance = [0.9, 0.88, 0.7];
+        let similarity = pairwise_cosine(&[vec![1.0, 0.0], vec![0.99, 0.1], vec![0.0, 1.0]]);
+
+        assert_eq!(
+            maximal_marginal_relevance(&relevance, &similarity, 2, 0.7),
+            vec![0, 2]
+        );
+        // With lambda = 1 only relevance counts
+        assert_eq!(
+            maximal_marginal_relevance(&relevance, &similarity, 2, 1.0),
+            vec![0, 1]
+        );
+        assert_eq!(
+            maximal_marginal_relevance(&relevance, &similarity, 10, 0.7).len(),
+            3
+        );
     }
 }
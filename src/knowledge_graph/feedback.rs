@@ -0,0 +1,477 @@
+//! Ranking penalties learned from user feedback on tool selections.
+//!
+//! Every piece of feedback turns into signals of the form "for this query,
+//! this tool was accepted / rejected". At search time a tool's similarity is
+//! lowered by its rejection rate among signals whose query is similar to the
+//! current one, so tools users keep passing over sink in the ranking.
+//!
+//! Feedback comes from authenticated users. Each user's signals weigh the
+//! same however many they send, and only their most recent few per tool
+//! count, so one account cannot push a tool down for everyone.
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use surrealdb::Surreal;
+use surrealdb::engine::any::Any;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+
+use crate::db::queries::QueryBuilder;
+use crate::db::schema::{SelectionFeedbackCreate, SelectionFeedbackRecord, SelectionOutcome};
+use crate::knowledge_graph::{EmbeddingManager, EmbeddingSearchResult};
+
+/// Queries at least this similar count as "the same kind of query".
+const SIMILAR_QUERY_THRESHOLD: f32 = 0.8;
+
+/// Signals needed from similar queries before a tool is penalized.
+const MIN_FEEDBACK_SIGNALS: usize = 3;
+
+/// Most recent feedback entries used when recomputing penalties.
+const FEEDBACK_HISTORY_LIMIT: usize = 5000;
+
+/// Most recent signals per user and tool that count towards a penalty.
+const MAX_SIGNALS_PER_USER: usize = 5;
+
+/// Feedback entries one user may record per hour.
+pub const MAX_FEEDBACK_PER_USER_PER_HOUR: u64 = 60;
+
+/// A user recorded more than [`MAX_FEEDBACK_PER_USER_PER_HOUR`] entries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeedbackLimitError;
+
+impl std::fmt::Display for FeedbackLimitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "at most {} selection feedback entries per user per hour",
+            MAX_FEEDBACK_PER_USER_PER_HOUR
+        )
+    }
+}
+
+impl std::error::Error for FeedbackLimitError {}
+
+/// A single accept/reject signal for a tool.
+#[derive(Debug, Clone)]
+struct FeedbackSignal {
+    user_id: String,
+    query_embedding: Vec<f32>,
+    rejected: bool,
+}
+
+/// Feedback signals grouped by tool, ready to score queries against.
+#[derive(Debug, Clone, Default)]
+pub struct FeedbackPenalties {
+    /// Key: tool id
+    signals: HashMap<String, Vec<FeedbackSignal>>,
+}
+
+impl FeedbackPenalties {
+    /// Build penalties from stored feedback, newest first. Entries without a
+    /// query embedding cannot be compared to new queries and are skipped.
+    pub fn from_feedback(feedback: &[SelectionFeedbackRecord]) -> Self {
+        let mut penalties = Self::default();
+        for entry in feedback {
+            let Some(query_embedding) = &entry.query_embedding else {
+                continue;
+            };
+
+            // A rejected selection counts against the selected tool; the tools
+            // passed over count against themselves either way.
+            if let Some(selected) = &entry.selected_tool_id {
+                penalties.push(
+                    selected.to_string(),
+                    &entry.user_id,
+                    query_embedding,
+                    entry.outcome == SelectionOutcome::Rejected,
+                );
+            }
+            for rejected in &entry.rejected_tool_ids {
+                penalties.push(rejected.to_string(), &entry.user_id, query_embedding, true);
+            }
+        }
+        penalties
+    }
+
+    fn push(&mut self, tool_id: String, user_id: &str, query_embedding: &[f32], rejected: bool) {
+        let signals = self.signals.entry(tool_id).or_default();
+        // Older signals past the user's cap are dropped
+        if signals.iter().filter(|s| s.user_id == user_id).count() >= MAX_SIGNALS_PER_USER {
+            return;
+        }
+        signals.push(FeedbackSignal {
+            user_id: user_id.to_string(),
+            query_embedding: query_embedding.to_vec(),
+            rejected,
+        });
+    }
+
+    /// Rejection rate of `tool_id` among signals from queries similar to
+    /// `query_embedding`, averaged over the users who gave them, or `0.0`
+    /// with too few signals to be consistent.
+    pub fn penalty(&self, tool_id: &str, query_embedding: &[f32]) -> f32 {
+        let Some(signals) = self.signals.get(tool_id) else {
+            return 0.0;
+        };
+
+        // Key: user id; value: (rejected, total)
+        let mut by_user: HashMap<&str, (usize, usize)> = HashMap::new();
+        let mut similar = 0;
+        for signal in signals {
+            if cosine(&signal.query_embedding, query_embedding) < SIMILAR_QUERY_THRESHOLD {
+                continue;
+            }
+            let counts = by_user.entry(signal.user_id.as_str()).or_default();
+            counts.0 += usize::from(signal.rejected);
+            counts.1 += 1;
+            similar += 1;
+        }
+        if similar < MIN_FEEDBACK_SIGNALS {
+            return 0.0;
+        }
+
+        let rates: f32 = by_user
+            .values()
+            .map(|(rejected, total)| *rejected as f32 / *total as f32)
+            .sum();
+        rates / by_user.len() as f32
+    }
+
+    /// Lower each result's similarity by `weight` times its penalty, then
+    /// re-sort and drop results that fall below `threshold`.
+    pub fn apply(
+        &self,
+        results: &mut Vec<EmbeddingSearchResult>,
+        query_embedding: &[f32],
+        weight: f32,
+        threshold: f32,
+    ) {
+        if self.signals.is_empty() || weight == 0.0 {
+            return;
+        }
+
+        for result in results.iter_mut() {
+            result.similarity -=
+                weight * self.penalty(&result.tool_id.to_string(), query_embedding);
+        }
+        results.retain(|r| r.similarity >= threshold);
+        results.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+    }
+}
+
+/// Penalties shared between the embedding search and the refresher task.
+pub type SharedFeedbackPenalties = Arc<RwLock<FeedbackPenalties>>;
+
+/// Stores selection feedback without holding the orchestrator.
+///
+/// Embedding the query can take a while, so API handlers take a recorder
+/// from the orchestrator and release it before calling [`record`](Self::record).
+#[derive(Clone)]
+pub struct FeedbackRecorder {
+    db: Surreal<Any>,
+    embedding_manager: Arc<Mutex<EmbeddingManager>>,
+}
+
+impl FeedbackRecorder {
+    pub fn new(db: Surreal<Any>, embedding_manager: Arc<Mutex<EmbeddingManager>>) -> Self {
+        Self {
+            db,
+            embedding_manager,
+        }
+    }
+
+    /// Embed the query, if not done yet, and store the feedback.
+    ///
+    /// Fails with [`FeedbackLimitError`] once the user has recorded
+    /// [`MAX_FEEDBACK_PER_USER_PER_HOUR`] entries in the last hour.
+    pub async fn record(
+        &self,
+        mut feedback: SelectionFeedbackCreate,
+    ) -> Result<SelectionFeedbackRecord> {
+        let recent =
+            QueryBuilder::count_recent_selection_feedback(&self.db, &feedback.user_id).await?;
+        if recent >= MAX_FEEDBACK_PER_USER_PER_HOUR {
+            return Err(FeedbackLimitError.into());
+        }
+
+        if feedback.query_embedding.is_none() {
+            let mut embedding_manager = self.embedding_manager.lock().await;
+            feedback.query_embedding = Some(embedding_manager.embed_text(&feedback.query).await?);
+        }
+        QueryBuilder::create_selection_feedback(&self.db, feedback).await
+    }
+}
+
+/// Background task that periodically recomputes penalties from the
+/// `selection_feedback` table.
+pub struct FeedbackRefresher {
+    db: Surreal<Any>,
+    penalties: SharedFeedbackPenalties,
+    interval: Duration,
+    task: std::sync::Mutex<Option<JoinHandle<()>>>,
+}
+
+impl FeedbackRefresher {
+    pub fn new(db: Surreal<Any>, penalties: SharedFeedbackPenalties, interval: Duration) -> Self {
+        Self {
+            db,
+            penalties,
+            interval,
+            task: std::sync::Mutex::new(None),
+        }
+    }
+
+    /// Spawn the background loop. Calling this more than once is a no-op.
+    pub fn start(self: &Arc<Self>) {
+        let mut task = self.task.lock().expect("refresher task lock poisoned");
+        if task.is_some() {
+            return;
+        }
+
+        let refresher = Arc::clone(self);
+        *task = Some(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(refresher.interval);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                if let Err(e) = refresher.run_once().await {
+                    tracing::warn!("Recomputing feedback penalties failed: {}", e);
+                }
+            }
+        }));
+    }
+
+    /// Abort the background loop, if running.
+    pub fn stop(&self) {
+        let handle = self
+            .task
+            .lock()
+            .expect("refresher task lock poisoned")
+            .take();
+        if let Some(handle) = handle {
+            handle.abort();
+        }
+    }
+
+    /// Reload feedback and replace the shared penalties. Returns the number of
+    /// feedback entries used.
+    pub async fn run_once(&self) -> Result<usize> {
+        let feedback =
+            QueryBuilder::list_selection_feedback(&self.db, FEEDBACK_HISTORY_LIMIT).await?;
+        let penalties = FeedbackPenalties::from_feedback(&feedback);
+        *self
+            .penalties
+            .write()
+            .expect("feedback penalties lock poisoned") = penalties;
+        Ok(feedback.len())
+    }
+}
+
+impl Drop for FeedbackRefresher {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a * norm_b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{DatabaseConfig, create_connection};
+    use surrealdb::RecordId;
+
+    fn tool(key: &str) -> RecordId {
+        RecordId::from_table_key("tool", key)
+    }
+
+    fn feedback(
+        embedding: Vec<f32>,
+        selected: &str,
+        rejected: &[&str],
+        outcome: SelectionOutcome,
+    ) -> SelectionFeedbackRecord {
+        user_feedback("alice", embedding, selected, rejected, outcome)
+    }
+
+    fn user_feedback(
+        user_id: &str,
+        embedding: Vec<f32>,
+        selected: &str,
+        rejected: &[&str],
+        outcome: SelectionOutcome,
+    ) -> SelectionFeedbackRecord {
+        SelectionFeedbackRecord {
+            id: RecordId::from_table_key("selection_feedback", "f"),
+            user_id: user_id.to_string(),
+            session_id: "session".to_string(),
+            query: "query".to_string(),
+            query_embedding: Some(embedding),
+            selected_tool_id: Some(tool(selected)),
+            rejected_tool_ids: rejected.iter().map(|key| tool(key)).collect(),
+            outcome,
+            created_at: None,
+        }
+    }
+
+    fn result(key: &str, similarity: f32) -> EmbeddingSearchResult {
+        EmbeddingSearchResult {
+            tool_id: tool(key),
+            similarity,
+            tool: None,
+        }
+    }
+
+    #[test]
+    fn test_penalty_needs_consistent_feedback_for_similar_queries() {
+        let weather = vec![1.0, 0.0];
+        let finance = vec![0.0, 1.0];
+        let entries = vec![
+            feedback(
+                weather.clone(),
+                "forecast",
+                &["stocks"],
+                SelectionOutcome::Accepted,
+            ),
+            feedback(
+                weather.clone(),
+                "forecast",
+                &["stocks"],
+                SelectionOutcome::Accepted,
+            ),
+            feedback(weather.clone(), "stocks", &[], SelectionOutcome::Rejected),
+            feedback(finance.clone(), "stocks", &[], SelectionOutcome::Accepted),
+        ];
+        let penalties = FeedbackPenalties::from_feedback(&entries);
+
+        assert_eq!(penalties.penalty("tool:stocks", &weather), 1.0);
+        assert_eq!(penalties.penalty("tool:forecast", &weather), 0.0);
+        // Only one signal for finance queries
+        assert_eq!(penalties.penalty("tool:stocks", &finance), 0.0);
+        assert_eq!(penalties.penalty("tool:unknown", &weather), 0.0);
+    }
+
+    #[test]
+    fn test_penalty_weighs_each_user_equally() {
+        let query = vec![1.0, 0.0];
+        let mut entries: Vec<_> = (0..20)
+            .map(|_| {
+                user_feedback(
+                    "mallory",
+                    query.clone(),
+                    "stocks",
+                    &[],
+                    SelectionOutcome::Rejected,
+                )
+            })
+            .collect();
+        for user in ["alice", "bob"] {
+            entries.push(user_feedback(
+                user,
+                query.clone(),
+                "stocks",
+                &[],
+                SelectionOutcome::Accepted,
+            ));
+        }
+        let penalties = FeedbackPenalties::from_feedback(&entries);
+
+        // Only mallory's most recent signals are kept, and they weigh as much
+        // as alice's and bob's single ones
+        assert_eq!(
+            penalties.signals["tool:stocks"].len(),
+            MAX_SIGNALS_PER_USER + 2
+        );
+        assert!((penalties.penalty("tool:stocks", &query) - 1.0 / 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_apply_reorders_and_filters() {
+        let query = vec![1.0, 0.0];
+        let entries: Vec<_> = (0..3)
+            .map(|_| {
+                feedback(
+                    query.clone(),
+                    "forecast",
+                    &["stocks"],
+                    SelectionOutcome::Accepted,
+                )
+            })
+            .collect();
+        let penalties = FeedbackPenalties::from_feedback(&entries);
+
+        let mut results = vec![result("stocks", 0.9), result("forecast", 0.8)];
+        penalties.apply(&mut results, &query, 0.2, 0.0);
+        assert_eq!(results[0].tool_id, tool("forecast"));
+        assert!((results[1].similarity - 0.7).abs() < 1e-6);
+
+        let mut results = vec![result("stocks", 0.9), result("forecast", 0.8)];
+        penalties.apply(&mut results, &query, 0.2, 0.75);
+        assert_eq!(results.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_refresher_loads_feedback_from_db() {
+        let config = DatabaseConfig {
+            url: "memory".to_string(),
+            ..Default::default()
+        };
+        let db = create_connection(config).await.unwrap();
+        crate::db::ensure_schema(&db).await.unwrap();
+
+        for _ in 0..3 {
+            QueryBuilder::create_selection_feedback(
+                &db,
+                SelectionFeedbackCreate {
+                    user_id: "alice".to_string(),
+                    session_id: "session".to_string(),
+                    query: "weather in Paris".to_string(),
+                    query_embedding: Some(vec![1.0, 0.0]),
+                    selected_tool_id: Some(tool("stocks")),
+                    rejected_tool_ids: vec![],
+                    outcome: SelectionOutcome::Rejected,
+                },
+            )
+            .await
+            .unwrap();
+        }
+
+        assert_eq!(
+            QueryBuilder::count_recent_selection_feedback(&db, "alice")
+                .await
+                .unwrap(),
+            3
+        );
+        assert_eq!(
+            QueryBuilder::count_recent_selection_feedback(&db, "bob")
+                .await
+                .unwrap(),
+            0
+        );
+
+        let penalties = SharedFeedbackPenalties::default();
+        let refresher = FeedbackRefresher::new(db, penalties.clone(), Duration::from_secs(60));
+        assert_eq!(refresher.run_once().await.unwrap(), 3);
+        assert_eq!(
+            penalties
+                .read()
+                .unwrap()
+                .penalty("tool:stocks", &[1.0, 0.0]),
+            1.0
+        );
+    }
+}
@@ -1,7 +1,10 @@
 use crate::db::schema::*;
+use crate::knowledge_graph::symbolic::PlanStep;
+use crate::types::ToolId;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
 use surrealdb::{RecordId, Surreal, engine::any::Any};
 
 #[derive(Debug, Clone)]
@@ -103,6 +106,65 @@ pub struct CompatibilityRule {
     pub description: String,
 }
 
+/// Structural compatibility below which a tool's output can't feed another
+/// tool's input.
+pub const MIN_EDGE_COMPATIBILITY: f32 = 0.5;
+
+/// Plan steps whose dependencies form a cycle, so no valid order exists.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CycleError {
+    /// Step numbers on (or between) the dependency cycles.
+    pub steps: Vec<u32>,
+}
+
+impl std::fmt::Display for CycleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let steps: Vec<String> = self.steps.iter().map(|s| s.to_string()).collect();
+        write!(f, "Cyclic dependency between steps {}", steps.join(", "))
+    }
+}
+
+impl std::error::Error for CycleError {}
+
+/// Outcome of [`KnowledgeGraph::merge`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeResult {
+    pub added_tools: usize,
+    pub added_services: usize,
+    pub added_edges: usize,
+    /// Nodes and edges both graphs hold under one id but with different
+    /// contents; the merged graph keeps the first graph's version.
+    pub conflicts: Vec<MergeConflict>,
+}
+
+/// A node or edge whose two versions disagree.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeConflict {
+    /// Record id of the node or edge.
+    pub id: String,
+    pub reason: String,
+}
+
+/// Serialized form of a [`KnowledgeGraph`], see [`KnowledgeGraph::to_json`].
+#[derive(Serialize, Deserialize)]
+struct GraphExport {
+    nodes: Vec<GraphNode>,
+    edges: Vec<GraphEdge>,
+    #[serde(default)]
+    types: Vec<TypeInfo>,
+}
+
+/// The URI a type node stands for: `data.uri`, or else its record id.
+fn type_node_uri(node: &GraphNode) -> String {
+    node.data
+        .get("uri")
+        .and_then(|uri| uri.as_str())
+        .map(str::to_string)
+        .unwrap_or_else(|| node.id.to_string())
+}
+
 impl Default for KnowledgeGraph {
     fn default() -> Self {
         Self::new()
@@ -277,6 +339,287 @@ impl KnowledgeGraph {
         subgraph
     }
 
+    /// Reorder plan steps so every step comes after the steps it depends on.
+    ///
+    /// Uses Kahn's algorithm, keeping the original relative order wherever the
+    /// dependencies allow it. Dependencies on step numbers that are not part of
+    /// the plan are ignored. On a cycle `steps` is left unchanged.
+    pub fn topological_sort(steps: &mut Vec<PlanStep>) -> Result<(), CycleError> {
+        let index_of: HashMap<u32, usize> = steps
+            .iter()
+            .enumerate()
+            .map(|(i, step)| (step.step_number, i))
+            .collect();
+
+        // dependents[i] lists the steps that must wait for step i
+        let mut dependents: Vec<Vec<usize>> = vec![Vec::new(); steps.len()];
+        let mut in_degree = vec![0usize; steps.len()];
+        for (i, step) in steps.iter().enumerate() {
+            let deps: HashSet<usize> = step
+                .dependencies
+                .iter()
+                .filter_map(|dep| index_of.get(dep).copied())
+                .collect();
+            for dep in deps {
+                dependents[dep].push(i);
+                in_degree[i] += 1;
+            }
+        }
+
+        let mut ready: BinaryHeap<Reverse<usize>> = in_degree
+            .iter()
+            .enumerate()
+            .filter(|(_, degree)| **degree == 0)
+            .map(|(i, _)| Reverse(i))
+            .collect();
+        let mut order = Vec::with_capacity(steps.len());
+        while let Some(Reverse(i)) = ready.pop() {
+            order.push(i);
+            for &dependent in &dependents[i] {
+                in_degree[dependent] -= 1;
+                if in_degree[dependent] == 0 {
+                    ready.push(Reverse(dependent));
+                }
+            }
+        }
+
+        if order.len() < steps.len() {
+            // Whatever is left is a cycle or waits on one; peel off the steps
+            // that nothing left depends on to name only the cycles.
+            let mut remaining: HashSet<usize> =
+                (0..steps.len()).filter(|i| in_degree[*i] > 0).collect();
+            loop {
+                let leaves: Vec<usize> = remaining
+                    .iter()
+                    .copied()
+                    .filter(|i| !dependents[*i].iter().any(|d| remaining.contains(d)))
+                    .collect();
+                if leaves.is_empty() {
+                    break;
+                }
+                for leaf in leaves {
+                    remaining.remove(&leaf);
+                }
+            }
+            let mut cyclic: Vec<u32> = remaining.iter().map(|i| steps[*i].step_number).collect();
+            cyclic.sort_unstable();
+            return Err(CycleError { steps: cyclic });
+        }
+
+        let mut slots: Vec<Option<PlanStep>> = steps.drain(..).map(Some).collect();
+        steps.extend(order.into_iter().filter_map(|i| slots[i].take()));
+        Ok(())
+    }
+
+    /// Cost of running a tool chain; lower is better.
+    ///
+    /// Each step adds its `estimated_cost` (0.0 when not annotated), and each
+    /// hop adds `1.0 - ` the structural compatibility of the step's output with
+    /// the next step's input, so chains whose types only loosely line up cost
+    /// more. A step without an output schema can't feed the next one and adds
+    /// the full 1.0.
+    pub fn path_cost(path: &[ToolRecord]) -> f32 {
+        let step_costs: f32 = path
+            .iter()
+            .map(|tool| tool.estimated_cost().unwrap_or(0.0))
+            .sum();
+        let edge_costs: f32 = path
+            .windows(2)
+            .map(|pair| match pair[0].typed_output() {
+                Some(output) => 1.0 - output.structural_compatibility_score(&pair[1].typed_input()),
+                None => 1.0,
+            })
+            .sum();
+        step_costs + edge_costs
+    }
+
+    /// Tool stored in the graph under `id`.
+    fn tool(&self, id: &str) -> Option<ToolRecord> {
+        self.tools().find(|tool| tool.id.to_string() == id)
+    }
+
+    /// Every tool in the graph.
+    fn tools(&self) -> impl Iterator<Item = ToolRecord> + '_ {
+        self.nodes
+            .values()
+            .filter(|node| node.node_type == NodeType::Tool)
+            .filter_map(|node| serde_json::from_value(node.data.clone()).ok())
+    }
+
+    /// Tools that can sit between `a` and `b` to convert `a`'s output into
+    /// `b`'s input.
+    ///
+    /// A tool qualifies when `a`'s output fits its input and its output fits
+    /// `b`'s input, each with a structural compatibility of at least
+    /// [`MIN_EDGE_COMPATIBILITY`]. Results are sorted by the weaker of the two
+    /// scores, best first. Empty when either tool is unknown or `a` declares no
+    /// output schema.
+    pub fn find_intermediary_tools(&self, a: &ToolId, b: &ToolId) -> Vec<ToolRecord> {
+        let (Some(from), Some(to)) = (self.tool(a.as_str()), self.tool(b.as_str())) else {
+            return Vec::new();
+        };
+        let Some(output) = from.typed_output() else {
+            return Vec::new();
+        };
+        let input = to.typed_input();
+
+        let mut bridges: Vec<(f32, ToolRecord)> = self
+            .tools()
+            .filter(|tool| tool.id != from.id && tool.id != to.id)
+            .filter_map(|tool| {
+                let into_bridge = output.structural_compatibility_score(&tool.typed_input());
+                let out_of_bridge = tool.typed_output()?.structural_compatibility_score(&input);
+                let score = into_bridge.min(out_of_bridge);
+                (score >= MIN_EDGE_COMPATIBILITY).then_some((score, tool))
+            })
+            .collect();
+        bridges.sort_by(|(a_score, a), (b_score, b)| {
+            b_score.total_cmp(a_score).then_with(|| a.name.cmp(&b.name))
+        });
+        bridges.into_iter().map(|(_, tool)| tool).collect()
+    }
+
+    /// Export the nodes, edges and types of the graph, e.g. to merge it into
+    /// another orchestrator's graph.
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut nodes: Vec<&GraphNode> = self.nodes.values().collect();
+        nodes.sort_by_cached_key(|node| node.id.to_string());
+        let mut types: Vec<&TypeInfo> = self.type_system.types.values().collect();
+        types.sort_by(|a, b| a.name.cmp(&b.name));
+        serde_json::json!({
+            "nodes": nodes,
+            "edges": self.edges,
+            "types": types,
+        })
+    }
+
+    /// Rebuild a graph exported with [`Self::to_json`].
+    pub fn from_json(value: serde_json::Value) -> Result<Self> {
+        let export: GraphExport = serde_json::from_value(value)?;
+        let mut graph = Self::new();
+        for node in export.nodes {
+            graph.add_node(node);
+        }
+        for edge in export.edges {
+            graph.add_edge(edge);
+        }
+        for type_info in export.types {
+            graph.type_system.add_type(type_info);
+        }
+        Ok(graph)
+    }
+
+    /// Combine this graph with `other`, e.g. the graph of another orchestrator
+    /// holding a different subset of tools.
+    ///
+    /// Tools and services are deduplicated by record id, type nodes by URI and
+    /// edges by record id or by endpoints and edge type. Where both graphs hold
+    /// different versions of one node or edge, this graph's version is kept and
+    /// the difference is reported as a conflict.
+    #[allow(clippy::mutable_key_type)]
+    pub fn merge(mut self, other: KnowledgeGraph) -> (KnowledgeGraph, MergeResult) {
+        let mut result = MergeResult::default();
+        let mut type_ids: HashMap<String, RecordId> = self
+            .nodes
+            .values()
+            .filter(|node| node.node_type == NodeType::Type)
+            .map(|node| (type_node_uri(node), node.id.clone()))
+            .collect();
+        // Type nodes of `other` that already exist here under another id
+        let mut remapped: HashMap<RecordId, RecordId> = HashMap::new();
+
+        let mut nodes: Vec<GraphNode> = other.nodes.into_values().collect();
+        nodes.sort_by_cached_key(|node| node.id.to_string());
+        for node in nodes {
+            if node.node_type == NodeType::Type {
+                let uri = type_node_uri(&node);
+                if let Some(existing) = type_ids.get(&uri) {
+                    if *existing != node.id {
+                        remapped.insert(node.id, existing.clone());
+                    }
+                    continue;
+                }
+                type_ids.insert(uri, node.id.clone());
+            }
+
+            match self.nodes.get(&node.id) {
+                Some(existing) if existing.node_type != node.node_type => {
+                    result.conflicts.push(MergeConflict {
+                        id: node.id.to_string(),
+                        reason: format!(
+                            "node is a {:?} in one graph and a {:?} in the other",
+                            existing.node_type, node.node_type
+                        ),
+                    });
+                }
+                Some(existing) if existing.data != node.data => {
+                    result.conflicts.push(MergeConflict {
+                        id: node.id.to_string(),
+                        reason: "node data differs between the graphs".to_string(),
+                    });
+                }
+                Some(_) => {}
+                None => {
+                    match node.node_type {
+                        NodeType::Tool => result.added_tools += 1,
+                        NodeType::Service => result.added_services += 1,
+                        _ => {}
+                    }
+                    self.add_node(node);
+                }
+            }
+        }
+
+        for mut edge in other.edges {
+            if let Some(id) = remapped.get(&edge.from) {
+                edge.from = id.clone();
+            }
+            if let Some(id) = remapped.get(&edge.to) {
+                edge.to = id.clone();
+            }
+
+            if let Some(existing) = self.edges.iter().find(|e| e.id == edge.id) {
+                if existing.from != edge.from
+                    || existing.to != edge.to
+                    || existing.edge_type != edge.edge_type
+                {
+                    result.conflicts.push(MergeConflict {
+                        id: edge.id.to_string(),
+                        reason: "edge connects different nodes in the two graphs".to_string(),
+                    });
+                }
+                continue;
+            }
+            let duplicate = self
+                .edges
+                .iter()
+                .any(|e| e.from == edge.from && e.to == edge.to && e.edge_type == edge.edge_type);
+            if duplicate {
+                continue;
+            }
+
+            let before = self.edges.len();
+            self.add_edge(edge);
+            result.added_edges += self.edges.len() - before;
+        }
+
+        for (name, type_info) in other.type_system.types {
+            self.type_system.types.entry(name).or_insert(type_info);
+        }
+        for rule in other.type_system.compatibility_rules {
+            let known = self
+                .type_system
+                .compatibility_rules
+                .iter()
+                .any(|r| r.from_type == rule.from_type && r.to_type == rule.to_type);
+            if !known {
+                self.type_system.add_compatibility_rule(rule);
+            }
+        }
+
+        (self, result)
+    }
+
     pub async fn build_from_database(db: &Surreal<Any>) -> Result<Self> {
         let mut graph = Self::new();
 
@@ -416,6 +759,120 @@ mod tests {
         assert!(graph.type_system.compatibility_rules.is_empty());
     }
 
+    fn node(id: RecordId, node_type: NodeType, data: serde_json::Value) -> GraphNode {
+        GraphNode {
+            id,
+            node_type,
+            data,
+            embeddings: None,
+            metadata: HashMap::new(),
+        }
+    }
+
+    fn edge(id: &str, from: &RecordId, to: &RecordId, edge_type: EdgeType) -> GraphEdge {
+        GraphEdge {
+            id: RecordId::from(("graph_edge", id)),
+            from: from.clone(),
+            to: to.clone(),
+            edge_type,
+            weight: 1.0,
+            metadata: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_merge() {
+        let service_a = RecordId::from(("service", "a"));
+        let service_b = RecordId::from(("service", "b"));
+        let read = RecordId::from(("tool", "read"));
+        let summarize = RecordId::from(("tool", "summarize"));
+        let text = RecordId::from(("type_node", "text"));
+        let text_b = RecordId::from(("type_node", "text_b"));
+
+        let mut first = KnowledgeGraph::new();
+        first.add_node(node(service_a.clone(), NodeType::Service, json!({})));
+        first.add_node(node(read.clone(), NodeType::Tool, json!({"name": "read"})));
+        first.add_node(node(
+            text.clone(),
+            NodeType::Type,
+            json!({"uri": "schema:Text"}),
+        ));
+        first.add_edge(edge("read_a", &read, &service_a, EdgeType::BelongsTo));
+
+        let mut second = KnowledgeGraph::new();
+        second.add_node(node(service_b.clone(), NodeType::Service, json!({})));
+        second.add_node(node(
+            read.clone(),
+            NodeType::Tool,
+            json!({"name": "read_v2"}),
+        ));
+        second.add_node(node(
+            summarize.clone(),
+            NodeType::Tool,
+            json!({"name": "summarize"}),
+        ));
+        // Same URI as `text`, so it's the same type node
+        second.add_node(node(
+            text_b.clone(),
+            NodeType::Type,
+            json!({"uri": "schema:Text"}),
+        ));
+        second.add_edge(edge("read_a", &read, &service_a, EdgeType::BelongsTo));
+        second.add_edge(edge(
+            "summarize_b",
+            &summarize,
+            &service_b,
+            EdgeType::BelongsTo,
+        ));
+        second.add_edge(edge("reads_text", &read, &text_b, EdgeType::TypeRelation));
+        second.add_edge(edge(
+            "read_to_summarize",
+            &read,
+            &summarize,
+            EdgeType::DataFlow,
+        ));
+
+        let (merged, result) = first.merge(second);
+        assert_eq!(result.added_tools, 1);
+        assert_eq!(result.added_services, 1);
+        assert_eq!(result.added_edges, 3);
+        assert_eq!(
+            result.conflicts,
+            vec![MergeConflict {
+                id: read.to_string(),
+                reason: "node data differs between the graphs".to_string(),
+            }]
+        );
+
+        assert_eq!(merged.nodes.len(), 5);
+        assert!(!merged.nodes.contains_key(&text_b));
+        assert_eq!(merged.get_node(&read).unwrap().data["name"], "read");
+        assert!(
+            merged
+                .edges
+                .iter()
+                .any(|e| e.edge_type == EdgeType::TypeRelation && e.to == text)
+        );
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let tool = RecordId::from(("tool", "read"));
+        let service = RecordId::from(("service", "fs"));
+        let mut graph = KnowledgeGraph::new();
+        graph.add_node(node(service.clone(), NodeType::Service, json!({})));
+        graph.add_node(node(tool.clone(), NodeType::Tool, json!({"name": "read"})));
+        graph.add_edge(edge("read_fs", &tool, &service, EdgeType::BelongsTo));
+
+        let restored = KnowledgeGraph::from_json(graph.to_json()).unwrap();
+        assert_eq!(restored.nodes.len(), 2);
+        assert_eq!(restored.edges.len(), 1);
+        assert_eq!(
+            restored.get_node(&tool).unwrap().data,
+            json!({"name": "read"})
+        );
+    }
+
     #[test]
     fn test_add_node() {
         let mut graph = KnowledgeGraph::new();
@@ -1250,4 +1707,292 @@ mod tests {
         assert!(path.is_some());
         assert_eq!(path.unwrap().len(), 3); // tool1 -> tool2 -> tool3
     }
+
+    fn plan_step(step_number: u32, dependencies: Vec<u32>) -> PlanStep {
+        PlanStep {
+            step_number,
+            tool_id: RecordId::from(("tool", format!("tool{}", step_number))),
+            tool_name: format!("tool{}", step_number),
+            inputs: HashMap::new(),
+            expected_outputs: vec![],
+            parallel: false,
+            dependencies,
+            description_template: None,
+        }
+    }
+
+    fn step_numbers(steps: &[PlanStep]) -> Vec<u32> {
+        steps.iter().map(|s| s.step_number).collect()
+    }
+
+    #[test]
+    fn test_topological_sort_orders_dependencies_first() {
+        let mut steps = vec![
+            plan_step(1, vec![3]),
+            plan_step(2, vec![]),
+            plan_step(3, vec![2]),
+            plan_step(4, vec![99]),
+        ];
+
+        KnowledgeGraph::topological_sort(&mut steps).unwrap();
+
+        // Unknown dependency 99 is ignored; independent steps keep their order
+        assert_eq!(step_numbers(&steps), vec![2, 3, 1, 4]);
+    }
+
+    #[test]
+    fn test_topological_sort_reports_cycle() {
+        let mut steps = vec![
+            plan_step(1, vec![]),
+            plan_step(2, vec![1, 4]),
+            plan_step(3, vec![2]),
+            plan_step(4, vec![3]),
+            plan_step(5, vec![4]),
+        ];
+
+        let err = KnowledgeGraph::topological_sort(&mut steps).unwrap_err();
+
+        // Step 5 depends on the cycle but is not part of it
+        assert_eq!(err.steps, vec![2, 3, 4]);
+        assert_eq!(step_numbers(&steps), vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_topological_sort_property_random_dags() {
+        // Small xorshift generator so the property check is reproducible
+        let mut seed: u64 = 0x2545_F491_4F6C_DD1D;
+        let mut next = move |bound: u64| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed % bound
+        };
+
+        for _ in 0..200 {
+            let n = next(12) as u32 + 1;
+            // Build a DAG over a random permutation, where each step may only
+            // depend on steps earlier in the permutation
+            let mut perm: Vec<u32> = (1..=n).collect();
+            for i in (1..perm.len()).rev() {
+                perm.swap(i, next(i as u64 + 1) as usize);
+            }
+            let mut steps: Vec<PlanStep> = (0..perm.len())
+                .map(|i| {
+                    let deps = (0..i).filter(|_| next(3) == 0).map(|j| perm[j]).collect();
+                    plan_step(perm[i], deps)
+                })
+                .collect();
+            // Shuffle the input order
+            for i in (1..steps.len()).rev() {
+                steps.swap(i, next(i as u64 + 1) as usize);
+            }
+
+            KnowledgeGraph::topological_sort(&mut steps).unwrap();
+
+            assert_eq!(steps.len(), n as usize);
+            let position: HashMap<u32, usize> = steps
+                .iter()
+                .enumerate()
+                .map(|(i, s)| (s.step_number, i))
+                .collect();
+            for (i, step) in steps.iter().enumerate() {
+                for dep in &step.dependencies {
+                    assert!(
+                        position[dep] < i,
+                        "step {} ran before its dependency {}",
+                        step.step_number,
+                        dep
+                    );
+                }
+            }
+        }
+    }
+
+    fn schema(value: serde_json::Value) -> TypedSchema {
+        TypedSchema::from_json_schema(value.as_object().unwrap())
+    }
+
+    fn tool_with_schemas(
+        name: &str,
+        input: serde_json::Value,
+        output: serde_json::Value,
+    ) -> ToolRecord {
+        ToolRecord {
+            id: RecordId::from_table_key("tool", name),
+            service_id: RecordId::from_table_key("service", "test"),
+            name: name.to_string(),
+            description: None,
+            input_schema: input.as_object().cloned().unwrap(),
+            output_schema: output.as_object().cloned(),
+            embedding_id: None,
+            input_ty: None,
+            output_ty: None,
+            usage_count: 0,
+            status: None,
+            discovery_source: Default::default(),
+            metadata: Default::default(),
+            input_examples: Vec::new(),
+            changelog: Vec::new(),
+            deprecation_notice: None,
+            descriptions: HashMap::new(),
+            inject_user_context: Vec::new(),
+            geo_availability: None,
+            required_env_vars: Vec::new(),
+            sla_class: None,
+            cost_per_call: None,
+            cache_ttl_seconds: None,
+            required_role: None,
+            actual_latency_p99_ms: None,
+            actual_error_rate_pct: None,
+            block_reason: None,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    #[test]
+    fn test_structural_compatibility_score() {
+        let file = schema(json!({
+            "type": "object",
+            "properties": {"path": {"type": "string"}, "size": {"type": "integer"}},
+        }));
+        let needs_path = schema(json!({
+            "type": "object",
+            "properties": {"path": {"type": "string"}},
+            "required": ["path"],
+        }));
+        let needs_path_and_mode = schema(json!({
+            "type": "object",
+            "properties": {"path": {"type": "string"}, "mode": {"type": "string"}},
+            "required": ["path", "mode"],
+        }));
+        let needs_numeric_size = schema(json!({
+            "type": "object",
+            "properties": {"size": {"type": "number"}, "path": {"type": "boolean"}},
+        }));
+
+        assert_eq!(file.structural_compatibility_score(&needs_path), 1.0);
+        assert_eq!(
+            file.structural_compatibility_score(&needs_path_and_mode),
+            0.5
+        );
+        assert_eq!(
+            file.structural_compatibility_score(&needs_numeric_size),
+            0.5
+        );
+        assert_eq!(
+            schema(json!({"type": "string"}))
+                .structural_compatibility_score(&schema(json!({"type": ["string", "null"]}))),
+            1.0
+        );
+        assert_eq!(
+            schema(json!({"type": "array", "items": {"type": "string"}}))
+                .structural_compatibility_score(&schema(
+                    json!({"type": "array", "items": {"type": "number"}})
+                )),
+            0.0
+        );
+        assert_eq!(
+            schema(json!({})).structural_compatibility_score(&needs_path),
+            1.0
+        );
+    }
+
+    fn graph_with_tools(tools: &[ToolRecord]) -> KnowledgeGraph {
+        let mut graph = KnowledgeGraph::new();
+        for tool in tools {
+            graph.add_node(GraphNode {
+                id: tool.id.clone(),
+                node_type: NodeType::Tool,
+                data: serde_json::to_value(tool).unwrap(),
+                embeddings: None,
+                metadata: HashMap::new(),
+            });
+        }
+        graph
+    }
+
+    #[test]
+    fn test_find_intermediary_tools_bridges_incompatible_types() {
+        let article = json!({
+            "type": "object",
+            "properties": {"text": {"type": "string"}, "title": {"type": "string"}},
+            "required": ["text", "title"],
+        });
+        let image = json!({
+            "type": "object",
+            "properties": {"pixels": {"type": "array", "items": {"type": "integer"}}},
+            "required": ["pixels"],
+        });
+
+        let camera = tool_with_schemas("camera", json!({"type": "object"}), image.clone());
+        let summarizer = tool_with_schemas("summarize", article.clone(), json!({"type": "string"}));
+        let ocr = tool_with_schemas("ocr", image.clone(), article);
+        // Only produces one of the two fields the summarizer needs
+        let basic_ocr = tool_with_schemas(
+            "basic_ocr",
+            image.clone(),
+            json!({"type": "object", "properties": {"text": {"type": "string"}}}),
+        );
+        let resizer = tool_with_schemas("resize", image.clone(), image);
+        let graph =
+            graph_with_tools(&[camera.clone(), summarizer.clone(), ocr, basic_ocr, resizer]);
+
+        let camera_id = ToolId::new(camera.id.to_string());
+        let summarizer_id = ToolId::new(summarizer.id.to_string());
+        let bridges: Vec<String> = graph
+            .find_intermediary_tools(&camera_id, &summarizer_id)
+            .into_iter()
+            .map(|tool| tool.name)
+            .collect();
+        assert_eq!(bridges, vec!["ocr", "basic_ocr"]);
+
+        // Nothing converts a summary back into an image
+        assert!(
+            graph
+                .find_intermediary_tools(&summarizer_id, &camera_id)
+                .is_empty()
+        );
+        assert!(
+            graph
+                .find_intermediary_tools(&ToolId::new("tool:missing"), &summarizer_id)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_path_cost_scores_hops_and_step_costs() {
+        let text = json!({"type": "object", "properties": {"text": {"type": "string"}}});
+        let reader = tool_with_schemas("read", json!({"type": "string"}), text.clone());
+        let summarizer = tool_with_schemas(
+            "summarize",
+            json!({"type": "object", "properties": {"text": {"type": "string"}}, "required": ["text"]}),
+            json!({"type": "string"}),
+        );
+        let resizer = tool_with_schemas(
+            "resize",
+            json!({"type": "object", "properties": {"width": {"type": "integer"}}, "required": ["width"]}),
+            json!({"type": "string"}),
+        );
+
+        assert_eq!(KnowledgeGraph::path_cost(&[]), 0.0);
+        assert_eq!(
+            KnowledgeGraph::path_cost(&[reader.clone(), summarizer.clone()]),
+            0.0
+        );
+        assert_eq!(
+            KnowledgeGraph::path_cost(&[reader.clone(), resizer.clone()]),
+            1.0
+        );
+
+        let mut costly = summarizer.clone();
+        costly
+            .metadata
+            .insert("estimated_cost".to_string(), json!(0.25));
+        assert_eq!(KnowledgeGraph::path_cost(&[reader.clone(), costly]), 0.25);
+
+        let mut sink = reader;
+        sink.output_schema = None;
+        assert_eq!(KnowledgeGraph::path_cost(&[sink, summarizer]), 1.0);
+    }
 }
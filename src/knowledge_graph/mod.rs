@@ -1,9 +1,11 @@
-pub mod graph;
-// pub mod traversal;
 pub mod embedding;
+pub mod feedback;
+pub mod graph;
 pub mod symbolic;
+pub mod traversal;
 
-pub use graph::*;
-// pub use traversal::*; // TODO
 pub use embedding::*;
+pub use feedback::*;
+pub use graph::*;
 pub use symbolic::*;
+pub use traversal::*;
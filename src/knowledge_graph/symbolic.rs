@@ -4,7 +4,9 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::cmp::PartialEq;
 use std::collections::HashMap;
+use std::fmt::Write as _;
 use surrealdb::engine::any::Any;
+use surrealdb::sql::Datetime;
 use surrealdb::{RecordId, Surreal};
 
 // TODO: Go full prolog style with unification, variable bindings, etc.
@@ -28,12 +30,313 @@ pub struct SymbolicRule {
     pub priority: u32,
 }
 
+/// Rules below this confidence are rejected as too weak to be useful.
+pub const MIN_RULE_CONFIDENCE: f32 = 0.1;
+
+/// A problem found by [`SymbolicRule::validate`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum RuleValidationError {
+    /// A consequent uses a variable that no antecedent binds.
+    UnboundConsequentVariable(String),
+    /// The rule has no conditions.
+    EmptyAntecedents,
+    /// The rule derives nothing.
+    EmptyConsequents,
+    /// The confidence is not a number between 0 and 1.
+    InvalidConfidence(f32),
+    /// The confidence is below [`MIN_RULE_CONFIDENCE`].
+    ConfidenceTooLow(f32),
+    /// A consequent derives a predicate the rule's own antecedents depend on.
+    CyclicFactReference(String),
+}
+
+impl std::fmt::Display for RuleValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuleValidationError::UnboundConsequentVariable(name) => {
+                write!(
+                    f,
+                    "Consequent variable `{}` is not bound by any antecedent",
+                    name
+                )
+            }
+            RuleValidationError::EmptyAntecedents => write!(f, "Rule has no antecedents"),
+            RuleValidationError::EmptyConsequents => write!(f, "Rule has no consequents"),
+            RuleValidationError::InvalidConfidence(confidence) => {
+                write!(f, "Confidence {} is not between 0 and 1", confidence)
+            }
+            RuleValidationError::ConfidenceTooLow(confidence) => write!(
+                f,
+                "Confidence {} is below the minimum of {}",
+                confidence, MIN_RULE_CONFIDENCE
+            ),
+            RuleValidationError::CyclicFactReference(predicate) => write!(
+                f,
+                "Predicate `{}` is both required and derived by the rule",
+                predicate
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RuleValidationError {}
+
+/// A rule was rejected because [`SymbolicRule::validate`] found problems.
+#[derive(Debug, Clone)]
+pub struct InvalidRuleError {
+    pub errors: Vec<RuleValidationError>,
+}
+
+impl std::fmt::Display for InvalidRuleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let errors: Vec<String> = self.errors.iter().map(|e| e.to_string()).collect();
+        write!(f, "Invalid rule: {}", errors.join("; "))
+    }
+}
+
+impl std::error::Error for InvalidRuleError {}
+
+/// How a new rule conflicts with an existing one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleConflictKind {
+    /// Both rules have the same antecedents and consequents.
+    Duplicate,
+    /// Both rules have the same antecedents, but one derives the negation of
+    /// something the other derives.
+    Contradiction,
+}
+
+/// An existing rule that a new rule conflicts with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleConflict {
+    pub rule_id: RecordId,
+    pub rule_name: String,
+    pub kind: RuleConflictKind,
+}
+
+impl std::fmt::Display for RuleConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            RuleConflictKind::Duplicate => {
+                write!(f, "Duplicates rule {} ({})", self.rule_id, self.rule_name)
+            }
+            RuleConflictKind::Contradiction => {
+                write!(f, "Contradicts rule {} ({})", self.rule_id, self.rule_name)
+            }
+        }
+    }
+}
+
+/// A rule was rejected because it conflicts with rules already loaded.
+#[derive(Debug, Clone)]
+pub struct RuleConflictError {
+    pub conflicts: Vec<RuleConflict>,
+}
+
+impl std::fmt::Display for RuleConflictError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let conflicts: Vec<String> = self.conflicts.iter().map(|c| c.to_string()).collect();
+        write!(f, "Conflicting rule: {}", conflicts.join("; "))
+    }
+}
+
+impl std::error::Error for RuleConflictError {}
+
+impl SymbolicRule {
+    /// Check the rule for inconsistencies that would make inference misbehave.
+    ///
+    /// Returns every problem found; an empty list means the rule is valid.
+    pub fn validate(&self) -> Vec<RuleValidationError> {
+        let mut errors = Vec::new();
+
+        if self.antecedents.is_empty() {
+            errors.push(RuleValidationError::EmptyAntecedents);
+        }
+        if self.consequents.is_empty() {
+            errors.push(RuleValidationError::EmptyConsequents);
+        }
+
+        if !(0.0..=1.0).contains(&self.confidence) {
+            errors.push(RuleValidationError::InvalidConfidence(self.confidence));
+        } else if self.confidence < MIN_RULE_CONFIDENCE {
+            errors.push(RuleValidationError::ConfidenceTooLow(self.confidence));
+        }
+
+        let mut bound = Vec::new();
+        let mut required = Vec::new();
+        for antecedent in &self.antecedents {
+            antecedent.free_variables(&mut bound);
+            antecedent.predicates(&mut required);
+        }
+
+        let mut used = Vec::new();
+        let mut derived = Vec::new();
+        for consequent in &self.consequents {
+            consequent.free_variables(&mut used);
+            consequent.predicates(&mut derived);
+        }
+
+        errors.extend(
+            used.into_iter()
+                .filter(|name| !bound.contains(name))
+                .map(RuleValidationError::UnboundConsequentVariable),
+        );
+        errors.extend(
+            derived
+                .into_iter()
+                .filter(|predicate| required.contains(predicate))
+                .map(RuleValidationError::CyclicFactReference),
+        );
+
+        errors
+    }
+}
+
+impl SymbolicRule {
+    /// Render the rule as `name: [antecedents] ⟹ [consequents] [conf=N, prio=N]`.
+    pub fn pretty_print(&self) -> String {
+        let join = |expressions: &[SymbolicExpression]| {
+            expressions
+                .iter()
+                .map(|e| e.pretty_print(0))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        format!(
+            "{}: [{}] ⟹ [{}] [conf={}, prio={}]",
+            self.name,
+            join(&self.antecedents),
+            join(&self.consequents),
+            self.confidence,
+            self.priority
+        )
+    }
+}
+
+impl SymbolicExpression {
+    /// Render the expression in logic notation, indented by `indent` spaces:
+    /// facts as `predicate(arg1, arg2)`, connectives as `(A ∧ B)`, `(A ∨ B)`,
+    /// `¬A` and `A ⟹ B`, quantifiers as `∀X: A`, variables as `?X` and
+    /// literals by value.
+    pub fn pretty_print(&self, indent: usize) -> String {
+        format!("{}{}", " ".repeat(indent), self.render())
+    }
+
+    fn render(&self) -> String {
+        let join = |expressions: &[SymbolicExpression], separator: &str| {
+            expressions
+                .iter()
+                .map(SymbolicExpression::render)
+                .collect::<Vec<_>>()
+                .join(separator)
+        };
+        match self {
+            SymbolicExpression::Fact(fact) => {
+                format!("{}({})", fact.predicate, join(&fact.arguments, ", "))
+            }
+            SymbolicExpression::And(expressions) => format!("({})", join(expressions, " ∧ ")),
+            SymbolicExpression::Or(expressions) => format!("({})", join(expressions, " ∨ ")),
+            SymbolicExpression::Not(expression) => format!("¬{}", expression.render()),
+            SymbolicExpression::Implies(lhs, rhs) => {
+                format!("{} ⟹ {}", lhs.render(), rhs.render())
+            }
+            SymbolicExpression::Quantified(quantifier, variable, expression) => {
+                let symbol = match quantifier {
+                    Quantifier::ForAll => "∀",
+                    Quantifier::Exists => "∃",
+                };
+                format!("{}{}: {}", symbol, variable, expression.render())
+            }
+            SymbolicExpression::Comparison(op, lhs, rhs) => {
+                let symbol = match op {
+                    ComparisonOp::Equals => "=",
+                    ComparisonOp::NotEquals => "≠",
+                    ComparisonOp::GreaterThan => ">",
+                    ComparisonOp::LessThan => "<",
+                    ComparisonOp::GreaterEqual => "≥",
+                    ComparisonOp::LessEqual => "≤",
+                    ComparisonOp::Contains => "contains",
+                    ComparisonOp::StartsWith => "starts_with",
+                    ComparisonOp::EndsWith => "ends_with",
+                };
+                format!("{} {} {}", lhs.render(), symbol, rhs.render())
+            }
+            SymbolicExpression::Variable(name) => format!("?{}", name),
+            SymbolicExpression::Literal(literal) => literal.render(),
+        }
+    }
+
+    /// Append the variables not bound by a quantifier, in order of first use.
+    fn free_variables(&self, out: &mut Vec<String>) {
+        match self {
+            SymbolicExpression::Variable(name) => {
+                if !out.contains(name) {
+                    out.push(name.clone());
+                }
+            }
+            SymbolicExpression::Fact(fact) => {
+                for argument in &fact.arguments {
+                    argument.free_variables(out);
+                }
+            }
+            SymbolicExpression::And(expressions) | SymbolicExpression::Or(expressions) => {
+                for expression in expressions {
+                    expression.free_variables(out);
+                }
+            }
+            SymbolicExpression::Not(expression) => expression.free_variables(out),
+            SymbolicExpression::Implies(lhs, rhs) | SymbolicExpression::Comparison(_, lhs, rhs) => {
+                lhs.free_variables(out);
+                rhs.free_variables(out);
+            }
+            SymbolicExpression::Quantified(_, variable, expression) => {
+                let mut inner = Vec::new();
+                expression.free_variables(&mut inner);
+                for name in inner {
+                    if &name != variable && !out.contains(&name) {
+                        out.push(name);
+                    }
+                }
+            }
+            SymbolicExpression::Literal(_) => {}
+        }
+    }
+
+    /// Append the fact predicates referenced by this expression, without duplicates.
+    fn predicates(&self, out: &mut Vec<String>) {
+        match self {
+            SymbolicExpression::Fact(fact) => {
+                if !out.contains(&fact.predicate) {
+                    out.push(fact.predicate.clone());
+                }
+                for argument in &fact.arguments {
+                    argument.predicates(out);
+                }
+            }
+            SymbolicExpression::And(expressions) | SymbolicExpression::Or(expressions) => {
+                for expression in expressions {
+                    expression.predicates(out);
+                }
+            }
+            SymbolicExpression::Not(expression)
+            | SymbolicExpression::Quantified(_, _, expression) => expression.predicates(out),
+            SymbolicExpression::Implies(lhs, rhs) | SymbolicExpression::Comparison(_, lhs, rhs) => {
+                lhs.predicates(out);
+                rhs.predicates(out);
+            }
+            SymbolicExpression::Variable(_) | SymbolicExpression::Literal(_) => {}
+        }
+    }
+}
+
 /// A symbolic expression used in rules, facts, and queries.
 ///
 /// This forms a small logic language that can represent predicates,
 /// logical connectives (AND/OR/NOT), implications, quantified expressions,
 /// comparisons, variable references, and literal values.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum SymbolicExpression {
     /// A fact or predicate
     Fact(Fact),
@@ -63,21 +366,21 @@ pub enum SymbolicExpression {
 ///
 /// Facts can be asserted directly or derived by rules. Confidence can be
 /// attached to indicate the reliability of the fact.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Fact {
     pub predicate: String,
     pub arguments: Vec<SymbolicExpression>,
     pub confidence: Option<f32>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum Quantifier {
     ForAll,
     Exists,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum ComparisonOp {
     Equals,
@@ -91,8 +394,9 @@ pub enum ComparisonOp {
     EndsWith,
 }
 
+/// A literal value, serialized as `{"type": "Number", "value": 0.9}`.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(tag = "type")]
+#[serde(tag = "type", content = "value")]
 pub enum LiteralValue {
     String(String),
     Number(f64),
@@ -101,11 +405,42 @@ pub enum LiteralValue {
     Object(HashMap<String, LiteralValue>),
 }
 
+impl LiteralValue {
+    /// The value as written in a rule: strings quoted, object keys sorted.
+    fn render(&self) -> String {
+        match self {
+            LiteralValue::String(s) => format!("{:?}", s),
+            LiteralValue::Number(n) => n.to_string(),
+            LiteralValue::Boolean(b) => b.to_string(),
+            LiteralValue::Array(values) => format!(
+                "[{}]",
+                values
+                    .iter()
+                    .map(LiteralValue::render)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            LiteralValue::Object(fields) => {
+                let mut fields: Vec<(&String, &LiteralValue)> = fields.iter().collect();
+                fields.sort_by(|a, b| a.0.cmp(b.0));
+                format!(
+                    "{{{}}}",
+                    fields
+                        .iter()
+                        .map(|(key, value)| format!("{}: {}", key, value.render()))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            }
+        }
+    }
+}
+
 /// The mutable state over which the symbolic reasoner operates.
 ///
 /// This holds asserted and derived facts, bound variables, and tool states.
 /// It is passed into the rule engine for forward and backward chaining.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct WorkingMemory {
     /// Holds all facts, grouped by predicate. Each predicate can have multiple facts.
     pub facts: HashMap<String, Vec<Fact>>,
@@ -113,6 +448,222 @@ pub struct WorkingMemory {
     pub tool_states: HashMap<String, ToolState>,
 }
 
+impl WorkingMemory {
+    /// Remove the first fact for `predicate` whose arguments are exactly `args`.
+    ///
+    /// Returns `true` if a fact was removed.
+    pub fn retract(&mut self, predicate: &str, args: &[LiteralValue]) -> bool {
+        let Some(facts) = self.facts.get_mut(predicate) else {
+            return false;
+        };
+        let Some(index) = facts.iter().position(|fact| {
+            fact.arguments.len() == args.len()
+                && fact.arguments.iter().zip(args).all(|(arg, expected)| {
+                    matches!(arg, SymbolicExpression::Literal(value) if value == expected)
+                })
+        }) else {
+            return false;
+        };
+
+        facts.remove(index);
+        if facts.is_empty() {
+            self.facts.remove(predicate);
+        }
+        true
+    }
+
+    /// Remove every fact for `predicate`, returning how many were removed.
+    pub fn retract_all(&mut self, predicate: &str) -> usize {
+        self.facts.remove(predicate).map_or(0, |facts| facts.len())
+    }
+
+    /// Remove every fact, of any predicate, matching `predicate_fn`.
+    fn retract_where(&mut self, predicate_fn: impl Fn(&Fact) -> bool) -> usize {
+        let mut removed = 0;
+        self.facts.retain(|_, facts| {
+            let before = facts.len();
+            facts.retain(|fact| !predicate_fn(fact));
+            removed += before - facts.len();
+            !facts.is_empty()
+        });
+        removed
+    }
+
+    /// Capture the current facts, variables and tool states.
+    pub fn checkpoint(&self) -> MemorySnapshot {
+        MemorySnapshot {
+            facts: self.facts.clone(),
+            variables: self.variables.clone(),
+            tool_states: self.tool_states.clone(),
+        }
+    }
+
+    /// Replace the current state with a previously captured snapshot.
+    pub fn restore(&mut self, snapshot: MemorySnapshot) {
+        self.facts = snapshot.facts;
+        self.variables = snapshot.variables;
+        self.tool_states = snapshot.tool_states;
+    }
+
+    /// Remove the most recently added fact equal to `fact`.
+    fn remove_fact(&mut self, fact: &Fact) -> bool {
+        let Some(facts) = self.facts.get_mut(&fact.predicate) else {
+            return false;
+        };
+        let Some(index) = facts.iter().rposition(|existing| existing == fact) else {
+            return false;
+        };
+
+        facts.remove(index);
+        if facts.is_empty() {
+            self.facts.remove(&fact.predicate);
+        }
+        true
+    }
+
+    /// Combine the knowledge of two memories.
+    ///
+    /// Facts with the same predicate and arguments, from either memory, are
+    /// kept once with the highest confidence. Tool states present in both keep the higher success
+    /// rate; variables bound in both keep the value from `self`.
+    pub fn union(&self, other: &WorkingMemory) -> WorkingMemory {
+        let mut merged = WorkingMemory {
+            facts: HashMap::new(),
+            variables: self.variables.clone(),
+            tool_states: self.tool_states.clone(),
+        };
+
+        for fact in self.facts.values().chain(other.facts.values()).flatten() {
+            let facts = merged.facts.entry(fact.predicate.clone()).or_default();
+            match facts.iter_mut().find(|f| f.arguments == fact.arguments) {
+                Some(existing) => {
+                    if fact_confidence(fact) > fact_confidence(existing) {
+                        existing.confidence = fact.confidence;
+                    }
+                }
+                None => facts.push(fact.clone()),
+            }
+        }
+
+        for (name, value) in &other.variables {
+            merged
+                .variables
+                .entry(name.clone())
+                .or_insert_with(|| value.clone());
+        }
+
+        for (tool, state) in &other.tool_states {
+            match merged.tool_states.get(tool) {
+                Some(existing) if existing.success_rate >= state.success_rate => {}
+                _ => {
+                    merged.tool_states.insert(tool.clone(), state.clone());
+                }
+            }
+        }
+
+        merged
+    }
+
+    /// Keep only what both memories know.
+    ///
+    /// Facts in both keep the lower confidence, tool states in both keep the
+    /// lower success rate, and variables must be bound to the same value.
+    pub fn intersection(&self, other: &WorkingMemory) -> WorkingMemory {
+        let mut facts: HashMap<String, Vec<Fact>> = HashMap::new();
+        for fact in self.facts.values().flatten() {
+            let Some(shared) = other.find_fact(fact) else {
+                continue;
+            };
+            let mut fact = fact.clone();
+            if fact_confidence(shared) < fact_confidence(&fact) {
+                fact.confidence = shared.confidence;
+            }
+            facts.entry(fact.predicate.clone()).or_default().push(fact);
+        }
+
+        let variables = self
+            .variables
+            .iter()
+            .filter(|(name, value)| other.variables.get(*name) == Some(value))
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+
+        let tool_states = self
+            .tool_states
+            .iter()
+            .filter_map(|(tool, state)| {
+                let shared = other.tool_states.get(tool)?;
+                let lower = if shared.success_rate < state.success_rate {
+                    shared
+                } else {
+                    state
+                };
+                Some((tool.clone(), lower.clone()))
+            })
+            .collect();
+
+        WorkingMemory {
+            facts,
+            variables,
+            tool_states,
+        }
+    }
+
+    /// Everything `self` knows that `other` does not.
+    pub fn difference(&self, other: &WorkingMemory) -> WorkingMemory {
+        let mut facts: HashMap<String, Vec<Fact>> = HashMap::new();
+        for fact in self.facts.values().flatten() {
+            if other.find_fact(fact).is_none() {
+                facts
+                    .entry(fact.predicate.clone())
+                    .or_default()
+                    .push(fact.clone());
+            }
+        }
+
+        let variables = self
+            .variables
+            .iter()
+            .filter(|(name, _)| !other.variables.contains_key(*name))
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+
+        let tool_states = self
+            .tool_states
+            .iter()
+            .filter(|(tool, _)| !other.tool_states.contains_key(*tool))
+            .map(|(tool, state)| (tool.clone(), state.clone()))
+            .collect();
+
+        WorkingMemory {
+            facts,
+            variables,
+            tool_states,
+        }
+    }
+
+    /// Find a fact with the same predicate and arguments, ignoring confidence.
+    fn find_fact(&self, fact: &Fact) -> Option<&Fact> {
+        self.facts
+            .get(&fact.predicate)?
+            .iter()
+            .find(|f| f.arguments == fact.arguments)
+    }
+}
+
+/// Confidence of a fact; facts asserted without one are treated as certain.
+fn fact_confidence(fact: &Fact) -> f32 {
+    fact.confidence.unwrap_or(1.0)
+}
+
+/// Opaque copy of a `WorkingMemory`, used to undo a failed planning branch.
+#[derive(Debug, Clone)]
+pub struct MemorySnapshot {
+    facts: HashMap<String, Vec<Fact>>,
+    variables: HashMap<String, LiteralValue>,
+    tool_states: HashMap<String, ToolState>,
+}
+
 /// Snapshot of the state of a single tool as seen by the symbolic layer.
 ///
 /// This can be used to drive rules that reason about availability,
@@ -138,6 +689,9 @@ pub enum ToolStatus {
     Blocked,
 }
 
+/// Predicates of the facts `infer_tool_selection` asserts about a single query.
+const QUERY_PREDICATES: [&str; 3] = ["user_query", "user_query_text", "user_context"];
+
 /// High-level entry point for symbolic reasoning over tools and user queries.
 ///
 /// This type owns the rule set, working memory, and access to the database
@@ -149,9 +703,28 @@ pub struct SymbolicReasoner {
     db: Surreal<Any>,
     rules: Vec<SymbolicRule>,
     working_memory: WorkingMemory,
+    /// Facts produced by forward chaining, re-derived after a retraction.
+    derived_facts: Vec<Fact>,
+    /// Facts derived by the most recent `infer_tool_selection`.
+    last_inference_steps: usize,
     rule_engine: RuleEngine,
 }
 
+/// Whether a stored rule validates; invalid rules are logged and skipped.
+fn is_loadable(rule: &SymbolicRule) -> bool {
+    let errors = rule.validate();
+    if errors.is_empty() {
+        return true;
+    }
+    tracing::warn!(
+        "Skipping invalid symbolic rule {} ({}): {}",
+        rule.id,
+        rule.name,
+        InvalidRuleError { errors }
+    );
+    false
+}
+
 impl SymbolicReasoner {
     pub fn new(db: Surreal<Any>) -> Self {
         Self {
@@ -162,54 +735,208 @@ impl SymbolicReasoner {
                 variables: HashMap::new(),
                 tool_states: HashMap::new(),
             },
+            derived_facts: Vec::new(),
+            last_inference_steps: 0,
             rule_engine: RuleEngine::new(),
         }
     }
 
+    /// The rules currently loaded.
+    pub fn rules(&self) -> &[SymbolicRule] {
+        &self.rules
+    }
+
     /// Load all active symbolic rules from the database into memory.
     ///
     /// Rules are currently fetched from the `symbolic_rule` table and
-    /// ordered by priority descending.
+    /// ordered by priority descending. Rules that fail validation are skipped.
     pub async fn load_rules(&mut self) -> Result<()> {
         // Load symbolic rules from database
         let query = r#"
         SELECT * FROM symbolic_rule
-        WHERE is_active = true
+        WHERE is_active = true AND soft_deleted_at = NONE
         ORDER BY priority DESC
         "#;
 
         let mut result = self.db.query(query).await?;
         let rules: Vec<SymbolicRule> = result.take(0)?;
 
-        self.rules = rules;
+        self.rules = rules.into_iter().filter(is_loadable).collect();
         Ok(())
     }
 
-    // TODO
-    // /// Add a new symbolic rule to the in-memory rule set.
-    // ///
-    // /// NOTE: This currently only logs and appends to the in-memory list.
-    // /// Persisting symbolic rules back into SurrealDB is left as a TODO.
-    // pub async fn add_rule(&mut self, rule: SymbolicRule) -> Result<()> {
-    //     let rule_id = rule.id.clone();
-    //
-    //     // Store in database (placeholder)
-    //     tracing::info!("Adding rule: {}", rule_id);
-    //
-    //     self.rules.push(rule);
-    //     Ok(())
-    // }
+    /// Load only the rules that changed since `since`, or every rule when
+    /// `since` is `None`.
+    ///
+    /// Changed rules replace the in-memory rule with the same ID or are
+    /// appended. Rules that were soft-deleted, deactivated or no longer
+    /// validate are removed. Returns the number of changed rules, which for a
+    /// full load is the number of rules loaded.
+    pub async fn load_rules_incremental(&mut self, since: Option<Datetime>) -> Result<usize> {
+        let Some(since) = since else {
+            self.load_rules().await?;
+            return Ok(self.rules.len());
+        };
+
+        let mut result = self
+            .db
+            .query(
+                r#"
+                SELECT * FROM symbolic_rule
+                WHERE updated_at > $since AND is_active = true AND soft_deleted_at = NONE;
+                SELECT VALUE id FROM symbolic_rule
+                WHERE updated_at > $since AND (is_active = false OR soft_deleted_at != NONE);
+                "#,
+            )
+            .bind(("since", since))
+            .await?;
+        let changed: Vec<SymbolicRule> = result.take(0)?;
+        let removed: Vec<RecordId> = result.take(1)?;
+        let count = changed.len() + removed.len();
+
+        self.rules
+            .retain(|rule| !removed.contains(&rule.id) && !changed.iter().any(|c| c.id == rule.id));
+        self.rules.extend(changed.into_iter().filter(is_loadable));
+        self.rules
+            .sort_by_key(|rule| std::cmp::Reverse(rule.priority));
+        Ok(count)
+    }
+
+    /// Validate a rule, store it in the `symbolic_rule` table and add it to the
+    /// in-memory rule set.
+    ///
+    /// A rule with the ID of an existing rule replaces it, reviving it if it
+    /// was soft-deleted. Fails with [`InvalidRuleError`] if the rule does not
+    /// validate and with [`RuleConflictError`] if it duplicates or contradicts
+    /// another loaded rule. Returns the ID of the stored rule.
+    pub async fn add_rule(&mut self, rule: SymbolicRule) -> Result<RecordId> {
+        Ok(self.store_rule(rule).await?.id)
+    }
+
+    /// Validate, conflict-check and upsert `rule`, then put it in the
+    /// in-memory rule set in priority order.
+    async fn store_rule(&mut self, rule: SymbolicRule) -> Result<SymbolicRule> {
+        let errors = rule.validate();
+        if !errors.is_empty() {
+            return Err(InvalidRuleError { errors }.into());
+        }
+
+        let conflicts = self.rule_engine.detect_conflicts(&self.rules, &rule);
+        if !conflicts.is_empty() {
+            return Err(RuleConflictError { conflicts }.into());
+        }
+
+        let mut result = self
+            .db
+            .query(
+                r#"
+                UPSERT $id CONTENT {
+                    name: $name,
+                    description: $description,
+                    antecedents: $antecedents,
+                    consequents: $consequents,
+                    confidence: $confidence,
+                    priority: $priority,
+                    is_active: true,
+                    soft_deleted_at: NONE
+                }
+                "#,
+            )
+            .bind(("id", rule.id.clone()))
+            .bind(("name", rule.name.clone()))
+            .bind(("description", rule.description.clone()))
+            .bind(("antecedents", serde_json::to_value(&rule.antecedents)?))
+            .bind(("consequents", serde_json::to_value(&rule.consequents)?))
+            .bind(("confidence", rule.confidence))
+            .bind(("priority", rule.priority))
+            .await?
+            .check()?;
+        let stored: Option<SymbolicRule> = result.take(0)?;
+        let stored = stored.ok_or_else(|| {
+            anyhow::anyhow!("Upserting symbolic rule {} returned nothing", rule.id)
+        })?;
+
+        tracing::info!("Added symbolic rule {} ({})", stored.id, stored.name);
+        self.rules.retain(|existing| existing.id != stored.id);
+        self.rules.push(stored.clone());
+        self.rules
+            .sort_by_key(|rule| std::cmp::Reverse(rule.priority));
+        Ok(stored)
+    }
+
+    /// Replace the live rule with the ID of `rule`, in the database and in
+    /// memory. Returns the rule as stored.
+    ///
+    /// Returns `None` if no live rule has that ID, and otherwise fails like
+    /// [`Self::add_rule`].
+    pub async fn update_rule(&mut self, rule: SymbolicRule) -> Result<Option<SymbolicRule>> {
+        let mut result = self
+            .db
+            .query("SELECT * FROM $id WHERE soft_deleted_at = NONE")
+            .bind(("id", rule.id.clone()))
+            .await?
+            .check()?;
+        let existing: Vec<SymbolicRule> = result.take(0)?;
+        if existing.is_empty() {
+            return Ok(None);
+        }
+        self.store_rule(rule).await.map(Some)
+    }
+
+    /// Soft-delete a rule in the `symbolic_rule` table and drop it from the
+    /// in-memory rule set.
+    ///
+    /// Returns `false` if no live rule has the given ID.
+    pub async fn remove_rule(&mut self, id: &RecordId) -> Result<bool> {
+        let mut result = self
+            .db
+            .query("UPDATE $id SET soft_deleted_at = time::now() WHERE soft_deleted_at = NONE")
+            .bind(("id", id.clone()))
+            .await?
+            .check()?;
+        let removed: Vec<SymbolicRule> = result.take(0)?;
+
+        self.rules.retain(|rule| &rule.id != id);
+        if removed.is_empty() {
+            return Ok(false);
+        }
+        tracing::info!("Removed symbolic rule {}", id);
+        Ok(true)
+    }
+
+    /// Number of facts the most recent `infer_tool_selection` derived.
+    pub fn last_inference_steps(&self) -> usize {
+        self.last_inference_steps
+    }
 
     /// Use the symbolic engine to propose a set of tools for a natural language query.
     ///
     /// This parses the query into symbolic expressions, seeds the working memory
     /// with query and tool facts, runs forward chaining, and then extracts any
     /// `tool_selected(...)` facts produced by rules into concrete `ToolSelection`s.
+    ///
+    /// The query and context facts are retracted again before returning, so the
+    /// working memory shared by all clients only holds tool state and one
+    /// client's query never feeds into another's inference.
     pub async fn infer_tool_selection(
         &mut self,
         query: &str,
         available_tools: &[crate::db::schema::ToolRecord],
         context: &HashMap<String, serde_json::Value>,
+    ) -> Result<Vec<ToolSelection>> {
+        let selections = self.select_tools(query, available_tools, context);
+        for predicate in QUERY_PREDICATES {
+            self.working_memory.retract_all(predicate);
+        }
+        self.rederive()?;
+        selections
+    }
+
+    fn select_tools(
+        &mut self,
+        query: &str,
+        available_tools: &[crate::db::schema::ToolRecord],
+        context: &HashMap<String, serde_json::Value>,
     ) -> Result<Vec<ToolSelection>> {
         // Parse query into symbolic representation
         let query_expr = self.parse_query_to_expression(query, context)?;
@@ -260,6 +987,8 @@ impl SymbolicReasoner {
         let inferences = self
             .rule_engine
             .forward_chain(&self.rules, &mut self.working_memory)?;
+        self.record_derived(&inferences);
+        self.last_inference_steps = inferences.len();
 
         // Extract tool selections from inferences
         let mut selections = Vec::new();
@@ -317,7 +1046,9 @@ impl SymbolicReasoner {
         };
 
         // Use backward chaining to find plan
-        let plan = self.rule_engine.backward_chain(&problem, &self.rules)?;
+        let plan =
+            self.rule_engine
+                .backward_chain(&problem, &self.rules, &mut self.working_memory)?;
 
         Ok(plan)
     }
@@ -349,6 +1080,123 @@ impl SymbolicReasoner {
         }
     }
 
+    /// Find out which tools the loaded rules can select on their own.
+    ///
+    /// Each tool is seeded alone into an empty working memory and forward
+    /// chaining is run; the tool is covered if a rule derives
+    /// `tool_selected` for it. `rule_hit_counts` counts, for every loaded rule,
+    /// the tools whose seeding made it fire. The reasoner's own working memory
+    /// is left untouched.
+    pub fn compute_rule_coverage(
+        &mut self,
+        tools: &[crate::db::schema::ToolRecord],
+    ) -> Result<RuleCoverageReport> {
+        let saved = std::mem::take(&mut self.working_memory);
+        let coverage = self.rule_coverage_of(tools);
+        self.working_memory = saved;
+        coverage
+    }
+
+    fn rule_coverage_of(
+        &mut self,
+        tools: &[crate::db::schema::ToolRecord],
+    ) -> Result<RuleCoverageReport> {
+        let mut report = RuleCoverageReport {
+            rule_hit_counts: self.rules.iter().map(|rule| (rule.id.clone(), 0)).collect(),
+            ..Default::default()
+        };
+
+        for tool in tools {
+            self.working_memory = WorkingMemory::default();
+            self.add_tool_state_to_memory(tool)?;
+            let derived = self
+                .rule_engine
+                .forward_chain_by_rule(&self.rules, &mut self.working_memory)?;
+
+            let mut fired: Vec<usize> = derived.iter().map(|(rule, _)| *rule).collect();
+            fired.sort_unstable();
+            fired.dedup();
+            for rule in fired {
+                *report
+                    .rule_hit_counts
+                    .entry(self.rules[rule].id.clone())
+                    .or_default() += 1;
+            }
+
+            let selected = derived.iter().any(|(_, fact)| {
+                fact.predicate == "tool_selected"
+                    && matches!(
+                        fact.arguments.first(),
+                        Some(SymbolicExpression::Literal(LiteralValue::String(name))) if *name == tool.name
+                    )
+            });
+            if selected {
+                report.covered_tools.push(tool.name.clone());
+            } else {
+                report.uncovered_tools.push(tool.name.clone());
+            }
+        }
+
+        report.covered_tools.sort();
+        report.covered_tools.dedup();
+        report.uncovered_tools.sort();
+        report.uncovered_tools.dedup();
+        Ok(report)
+    }
+
+    /// Retract the first fact matching `predicate` and `args`, then re-derive.
+    ///
+    /// Returns `true` if a fact was removed.
+    pub fn retract_fact(&mut self, predicate: &str, args: &[LiteralValue]) -> Result<bool> {
+        let removed = self.working_memory.retract(predicate, args);
+        if removed {
+            self.rederive()?;
+        }
+        Ok(removed)
+    }
+
+    /// Retract everything known about a tool: its `ToolState` and every fact
+    /// whose first argument is the tool name. Derived facts are then recomputed
+    /// so that conclusions drawn from the tool no longer hold.
+    ///
+    /// Returns `true` if anything was removed.
+    pub fn retract_tool_state(&mut self, tool_name: &str) -> Result<bool> {
+        let removed_state = self.working_memory.tool_states.remove(tool_name).is_some();
+        let removed_facts = self.working_memory.retract_where(|fact| {
+            matches!(
+                fact.arguments.first(),
+                Some(SymbolicExpression::Literal(LiteralValue::String(name))) if name == tool_name
+            )
+        });
+
+        let removed = removed_state || removed_facts > 0;
+        if removed {
+            self.rederive()?;
+        }
+        Ok(removed)
+    }
+
+    /// Truth maintenance: drop all derived facts and run forward chaining again
+    /// over the remaining asserted facts.
+    fn rederive(&mut self) -> Result<()> {
+        for fact in std::mem::take(&mut self.derived_facts) {
+            self.working_memory.remove_fact(&fact);
+        }
+        let inferences = self
+            .rule_engine
+            .forward_chain(&self.rules, &mut self.working_memory)?;
+        self.record_derived(&inferences);
+        Ok(())
+    }
+
+    fn record_derived(&mut self, inferences: &[SymbolicExpression]) {
+        self.derived_facts
+            .extend(inferences.iter().filter_map(|inference| match inference {
+                SymbolicExpression::Fact(fact) => Some(fact.clone()),
+                _ => None,
+            }));
+    }
+
     fn add_fact_to_memory(
         &mut self,
         predicate: &str,
@@ -371,7 +1219,7 @@ impl SymbolicReasoner {
     fn add_tool_state_to_memory(&mut self, tool: &crate::db::schema::ToolRecord) -> Result<()> {
         let tool_state = ToolState {
             tool_id: tool.id.clone(),
-            status: ToolStatus::Available,
+            status: tool.status.clone().unwrap_or(ToolStatus::Available),
             last_output: None,
             input_requirements: tool.input_schema.clone(),
             execution_count: tool.usage_count as u32,
@@ -468,6 +1316,21 @@ impl SymbolicReasoner {
     }
 }
 
+/// Which tools the symbolic rules can select, from
+/// [`SymbolicReasoner::compute_rule_coverage`].
+#[derive(Debug, Clone, Default)]
+pub struct RuleCoverageReport {
+    /// Tools some rule selects, sorted by name.
+    pub covered_tools: Vec<String>,
+    /// Tools no rule selects, sorted by name. These are only found through
+    /// the embedding fallback and are candidates for new rules.
+    pub uncovered_tools: Vec<String>,
+    /// Number of tools each loaded rule fired for; rules that never fire
+    /// have a count of 0.
+    #[allow(clippy::mutable_key_type)]
+    pub rule_hit_counts: HashMap<RecordId, usize>,
+}
+
 /// Hard constraints and preferences for planning a tool sequence.
 #[derive(Debug, Clone)]
 pub struct PlanningConstraints {
@@ -478,6 +1341,8 @@ pub struct PlanningConstraints {
     pub forbidden_tools: Option<Vec<String>>,
     #[allow(dead_code)]
     pub max_cost: Option<f32>,
+    /// Typed plans whose `KnowledgeGraph::path_cost` exceeds this are dropped.
+    pub max_path_cost: Option<f32>,
     #[allow(dead_code)]
     pub requirements: Vec<String>,
 }
@@ -490,6 +1355,7 @@ impl Default for PlanningConstraints {
             allowed_tools: None,
             forbidden_tools: None,
             max_cost: None,
+            max_path_cost: None,
             requirements: Vec::new(),
         }
     }
@@ -529,14 +1395,81 @@ pub struct ToolPlan {
     pub confidence: f32,
 }
 
+impl ToolPlan {
+    /// Render the plan as a Mermaid `flowchart TD`.
+    ///
+    /// Each step is a node labeled `Step N: tool_name` with an edge from each
+    /// of its dependencies. Parallel steps that share the same dependencies
+    /// are grouped in a `parallel_group_N` subgraph.
+    pub fn to_mermaid(&self) -> String {
+        // Parallel steps keyed by their sorted dependencies, in order of appearance
+        let mut groups: Vec<(Vec<u32>, Vec<&PlanStep>)> = Vec::new();
+        for step in self.steps.iter().filter(|step| step.parallel) {
+            let mut dependencies = step.dependencies.clone();
+            dependencies.sort_unstable();
+            match groups.iter_mut().find(|(deps, _)| *deps == dependencies) {
+                Some((_, members)) => members.push(step),
+                None => groups.push((dependencies, vec![step])),
+            }
+        }
+
+        let node = |step: &PlanStep| {
+            let name = if step.tool_name.is_empty() {
+                step.tool_id.to_string()
+            } else {
+                step.tool_name.clone()
+            };
+            format!(
+                "step{}[\"Step {}: {}\"]",
+                step.step_number,
+                step.step_number,
+                name.replace('"', "#quot;")
+            )
+        };
+
+        let mut out = String::from("flowchart TD\n");
+        for step in &self.steps {
+            if !step.parallel {
+                let _ = writeln!(out, "    {}", node(step));
+                continue;
+            }
+            // A group is written out where its first step appears
+            let Some((index, (_, members))) = groups
+                .iter()
+                .enumerate()
+                .find(|(_, (_, members))| std::ptr::eq(members[0], step))
+            else {
+                continue;
+            };
+            let _ = writeln!(out, "    subgraph parallel_group_{}", index + 1);
+            for member in members {
+                let _ = writeln!(out, "        {}", node(member));
+            }
+            out.push_str("    end\n");
+        }
+        for step in &self.steps {
+            for dependency in &step.dependencies {
+                let _ = writeln!(out, "    step{} --> step{}", dependency, step.step_number);
+            }
+        }
+        out
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PlanStep {
     pub step_number: u32,
     pub tool_id: RecordId,
+    /// Name of the tool at `tool_id`, used to label the step.
+    #[serde(default)]
+    pub tool_name: String,
     pub inputs: HashMap<String, serde_json::Value>,
     pub expected_outputs: Vec<String>,
     pub parallel: bool,
     pub dependencies: Vec<u32>,
+    /// Optional description with `{{key}}` placeholders resolved from `inputs`.
+    #[serde(default)]
+    pub description_template: Option<String>,
 }
 
 /// Rule engine implementation for forward and backward chaining over symbolic rules.
@@ -551,18 +1484,74 @@ impl RuleEngine {
         Self
     }
 
+    /// Find the rules in `rules` that `candidate` duplicates or contradicts.
+    ///
+    /// Only rules with the same antecedents (in any order) are compared, and
+    /// expressions are compared syntactically, so rules that differ only in
+    /// variable names are not detected. A rule with the candidate's ID is
+    /// skipped, since the candidate replaces it.
+    fn detect_conflicts(
+        &self,
+        rules: &[SymbolicRule],
+        candidate: &SymbolicRule,
+    ) -> Vec<RuleConflict> {
+        let same_set = |a: &[SymbolicExpression], b: &[SymbolicExpression]| {
+            a.len() == b.len() && a.iter().all(|e| b.contains(e)) && b.iter().all(|e| a.contains(e))
+        };
+        let negates = |a: &SymbolicExpression, b: &SymbolicExpression| matches!(a, SymbolicExpression::Not(inner) if inner.as_ref() == b);
+
+        rules
+            .iter()
+            .filter(|rule| rule.id != candidate.id)
+            .filter(|rule| same_set(&rule.antecedents, &candidate.antecedents))
+            .filter_map(|rule| {
+                let kind = if same_set(&rule.consequents, &candidate.consequents) {
+                    RuleConflictKind::Duplicate
+                } else if rule.consequents.iter().any(|existing| {
+                    candidate
+                        .consequents
+                        .iter()
+                        .any(|new| negates(existing, new) || negates(new, existing))
+                }) {
+                    RuleConflictKind::Contradiction
+                } else {
+                    return None;
+                };
+                Some(RuleConflict {
+                    rule_id: rule.id.clone(),
+                    rule_name: rule.name.clone(),
+                    kind,
+                })
+            })
+            .collect()
+    }
+
     fn forward_chain(
         &self,
         rules: &[SymbolicRule],
         memory: &mut WorkingMemory,
     ) -> Result<Vec<SymbolicExpression>> {
+        Ok(self
+            .forward_chain_by_rule(rules, memory)?
+            .into_iter()
+            .map(|(_, fact)| SymbolicExpression::Fact(fact))
+            .collect())
+    }
+
+    /// Forward chain to a fixpoint, returning each new fact with the index in
+    /// `rules` of the rule that derived it.
+    fn forward_chain_by_rule(
+        &self,
+        rules: &[SymbolicRule],
+        memory: &mut WorkingMemory,
+    ) -> Result<Vec<(usize, Fact)>> {
         let mut new_facts = Vec::new();
         let mut changed = true;
 
         while changed {
             changed = false;
 
-            for rule in rules {
+            for (index, rule) in rules.iter().enumerate() {
                 // Special case: if the rule has a single fact antecedent, we try to
                 // perform simple variable unification and instantiate the consequents
                 // for each matching binding.
@@ -590,7 +1579,7 @@ impl RuleEngine {
 
                                         if !exists {
                                             entry.push(instantiated.clone());
-                                            new_facts.push(SymbolicExpression::Fact(instantiated));
+                                            new_facts.push((index, instantiated));
                                             changed = true;
                                         }
                                     }
@@ -617,7 +1606,7 @@ impl RuleEngine {
 
                             if !exists {
                                 entry.push(fact.clone());
-                                new_facts.push(SymbolicExpression::Fact(fact.clone()));
+                                new_facts.push((index, fact.clone()));
                                 changed = true;
                             }
                         }
@@ -629,10 +1618,14 @@ impl RuleEngine {
         Ok(new_facts)
     }
 
+    /// Applying a rule asserts its consequents into `memory`; if no concrete
+    /// step can be built from the rule, memory is restored to its state before
+    /// the rule was tried and the next rule is attempted.
     fn backward_chain(
         &self,
         problem: &PlanningProblem,
         rules: &[SymbolicRule],
+        memory: &mut WorkingMemory,
     ) -> Result<ToolPlan> {
         // Simplified backward chaining implementation
         let mut steps = Vec::new();
@@ -664,6 +1657,18 @@ impl RuleEngine {
 
                     let next_step_number = step_number + 1;
 
+                    // Tentatively apply the rule, rolling back if the branch fails
+                    let snapshot = memory.checkpoint();
+                    for consequent in &rule.consequents {
+                        if let SymbolicExpression::Fact(fact) = consequent {
+                            memory
+                                .facts
+                                .entry(fact.predicate.clone())
+                                .or_default()
+                                .push(fact.clone());
+                        }
+                    }
+
                     // Create step from rule; if we fail to derive a concrete tool, skip this rule.
                     match self.create_plan_step(rule, next_step_number, &current_goal, problem) {
                         Ok(step) => {
@@ -682,7 +1687,9 @@ impl RuleEngine {
                             break 'rules;
                         }
                         Err(_) => {
-                            // Could not create a concrete step from this rule; try the next rule.
+                            // Could not create a concrete step from this rule; undo it and
+                            // try the next rule.
+                            memory.restore(snapshot);
                             continue 'rules;
                         }
                     }
@@ -726,6 +1733,23 @@ impl RuleEngine {
         None
     }
 
+    /// Extract a step description template from a `step_description("...")` fact.
+    fn extract_description_template_from_rule(&self, rule: &SymbolicRule) -> Option<String> {
+        rule.consequents.iter().find_map(|expr| match expr {
+            SymbolicExpression::Fact(Fact {
+                predicate,
+                arguments,
+                ..
+            }) if predicate == "step_description" => match arguments.first() {
+                Some(SymbolicExpression::Literal(LiteralValue::String(template))) => {
+                    Some(template.clone())
+                }
+                _ => None,
+            },
+            _ => None,
+        })
+    }
+
     fn evaluate_antecedents(
         &self,
         antecedents: &[SymbolicExpression],
@@ -922,10 +1946,821 @@ impl RuleEngine {
         Ok(PlanStep {
             step_number,
             tool_id: tool.id.clone(),
+            tool_name: tool.name.clone(),
             inputs: HashMap::new(),
             expected_outputs: vec![goal.to_string()],
             parallel: false,
             dependencies: vec![],
+            description_template: self.extract_description_template_from_rule(rule),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{DatabaseConfig, create_connection};
+
+    async fn setup_reasoner() -> SymbolicReasoner {
+        let config = DatabaseConfig {
+            url: "memory".to_string(),
+            ..Default::default()
+        };
+        SymbolicReasoner::new(create_connection(config).await.unwrap())
+    }
+
+    fn string(value: &str) -> SymbolicExpression {
+        SymbolicExpression::Literal(LiteralValue::String(value.to_string()))
+    }
+
+    fn fact(predicate: &str, arguments: Vec<SymbolicExpression>) -> Fact {
+        Fact {
+            predicate: predicate.to_string(),
+            arguments,
+            confidence: Some(1.0),
+        }
+    }
+
+    /// `tool_exists(T) => tool_selected(T, 0.9, "exists")`
+    fn select_existing_rule() -> SymbolicRule {
+        SymbolicRule {
+            id: RecordId::from_table_key("symbolic_rule", "select_existing"),
+            name: "select_existing".to_string(),
+            description: String::new(),
+            antecedents: vec![SymbolicExpression::Fact(fact(
+                "tool_exists",
+                vec![SymbolicExpression::Variable("T".to_string())],
+            ))],
+            consequents: vec![SymbolicExpression::Fact(fact(
+                "tool_selected",
+                vec![
+                    SymbolicExpression::Variable("T".to_string()),
+                    SymbolicExpression::Literal(LiteralValue::Number(0.9)),
+                    string("exists"),
+                ],
+            ))],
+            confidence: 1.0,
+            priority: 1,
+        }
+    }
+
+    fn tool(name: &str) -> crate::db::schema::ToolRecord {
+        crate::db::schema::ToolRecord {
+            id: RecordId::from_table_key("tool", name),
+            service_id: RecordId::from_table_key("service", "test"),
+            name: name.to_string(),
+            description: None,
+            input_schema: JsonObject::new(),
+            output_schema: None,
+            embedding_id: None,
+            input_ty: None,
+            output_ty: None,
+            usage_count: 0,
+            status: None,
+            discovery_source: Default::default(),
+            metadata: Default::default(),
+            input_examples: Vec::new(),
+            changelog: Vec::new(),
+            deprecation_notice: None,
+            descriptions: HashMap::new(),
+            inject_user_context: Vec::new(),
+            geo_availability: None,
+            required_env_vars: Vec::new(),
+            sla_class: None,
+            cost_per_call: None,
+            cache_ttl_seconds: None,
+            required_role: None,
+            actual_latency_p99_ms: None,
+            actual_error_rate_pct: None,
+            block_reason: None,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    fn planning_rule(key: &str, tool_name: &str) -> SymbolicRule {
+        SymbolicRule {
+            id: RecordId::from_table_key("symbolic_rule", key),
+            name: key.to_string(),
+            description: String::new(),
+            antecedents: vec![],
+            consequents: vec![
+                SymbolicExpression::Fact(fact("summarize", vec![])),
+                SymbolicExpression::Fact(fact("use_tool", vec![string(tool_name)])),
+            ],
+            confidence: 1.0,
+            priority: 1,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_checkpoint_and_restore() {
+        let mut reasoner = setup_reasoner().await;
+        reasoner
+            .add_fact_to_memory("likes", vec![string("a")], 1.0)
+            .unwrap();
+        let snapshot = reasoner.working_memory.checkpoint();
+
+        reasoner
+            .add_fact_to_memory("likes", vec![string("b")], 1.0)
+            .unwrap();
+        reasoner.add_tool_state_to_memory(&tool("alpha")).unwrap();
+        reasoner
+            .working_memory
+            .variables
+            .insert("x".to_string(), LiteralValue::Boolean(true));
+
+        reasoner.working_memory.restore(snapshot);
+        let memory = &reasoner.working_memory;
+        assert_eq!(memory.facts.len(), 1);
+        assert_eq!(
+            memory.facts["likes"],
+            vec![fact("likes", vec![string("a")])]
+        );
+        assert!(memory.variables.is_empty());
+        assert!(memory.tool_states.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_plan_rolls_back_failed_branch() {
+        let mut reasoner = setup_reasoner().await;
+        reasoner.rules = vec![
+            planning_rule("missing", "missing_tool"),
+            planning_rule("available", "alpha"),
+        ];
+        // Known before planning, so undoing the failed branch must keep it
+        reasoner
+            .add_fact_to_memory("use_tool", vec![string("missing_tool")], 1.0)
+            .unwrap();
+
+        let plan = reasoner
+            .plan_tool_sequence(
+                "summarize",
+                &[tool("alpha")],
+                &PlanningConstraints::default(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(plan.steps.len(), 1);
+        assert_eq!(plan.steps[0].tool_id, tool("alpha").id);
+
+        // Only the consequents of the branch that produced a step remain
+        let memory = &reasoner.working_memory;
+        assert_eq!(memory.facts["summarize"].len(), 1);
+        assert_eq!(
+            memory.facts["use_tool"],
+            vec![
+                fact("use_tool", vec![string("missing_tool")]),
+                fact("use_tool", vec![string("alpha")])
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_retract_and_retract_all() {
+        let mut reasoner = setup_reasoner().await;
+        reasoner
+            .add_fact_to_memory("likes", vec![string("a")], 1.0)
+            .unwrap();
+        reasoner
+            .add_fact_to_memory("likes", vec![string("a")], 1.0)
+            .unwrap();
+        reasoner
+            .add_fact_to_memory("likes", vec![string("b")], 1.0)
+            .unwrap();
+        let memory = &mut reasoner.working_memory;
+
+        assert!(memory.retract("likes", &[LiteralValue::String("a".to_string())]));
+        assert_eq!(memory.facts["likes"].len(), 2);
+        assert!(!memory.retract("likes", &[LiteralValue::String("c".to_string())]));
+        assert!(!memory.retract("missing", &[]));
+
+        assert_eq!(memory.retract_all("likes"), 2);
+        assert_eq!(memory.retract_all("likes"), 0);
+        assert!(!memory.facts.contains_key("likes"));
+    }
+
+    #[tokio::test]
+    async fn test_retract_tool_state_revises_derived_facts() {
+        let mut reasoner = setup_reasoner().await;
+        reasoner.rules = vec![select_existing_rule()];
+
+        let selections = reasoner
+            .infer_tool_selection("query", &[tool("alpha"), tool("beta")], &HashMap::new())
+            .await
+            .unwrap();
+        assert_eq!(selections.len(), 2);
+
+        assert!(reasoner.retract_tool_state("alpha").unwrap());
+        let memory = &reasoner.working_memory;
+        assert!(!memory.tool_states.contains_key("alpha"));
+        assert!(memory.tool_states.contains_key("beta"));
+        assert_eq!(
+            memory.facts["tool_exists"],
+            vec![fact("tool_exists", vec![string("beta")])]
+        );
+
+        let selected: Vec<_> = memory.facts["tool_selected"]
+            .iter()
+            .map(|f| f.arguments[0].clone())
+            .collect();
+        assert_eq!(selected, vec![string("beta")]);
+
+        assert!(!reasoner.retract_tool_state("alpha").unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_query_facts_do_not_leak_between_queries() {
+        let mut reasoner = setup_reasoner().await;
+        // `user_query_text("secret") => tool_selected("alpha", 0.9, "secret")`
+        reasoner.rules = vec![SymbolicRule {
+            id: RecordId::from_table_key("symbolic_rule", "select_on_secret"),
+            name: "select_on_secret".to_string(),
+            description: String::new(),
+            antecedents: vec![SymbolicExpression::Fact(fact(
+                "user_query_text",
+                vec![string("secret")],
+            ))],
+            consequents: vec![SymbolicExpression::Fact(fact(
+                "tool_selected",
+                vec![
+                    string("alpha"),
+                    SymbolicExpression::Literal(LiteralValue::Number(0.9)),
+                    string("secret"),
+                ],
+            ))],
+            confidence: 1.0,
+            priority: 1,
+        }];
+        let context = HashMap::from([("tenant".to_string(), serde_json::json!("a"))]);
+
+        let first = reasoner
+            .infer_tool_selection("secret", &[tool("alpha")], &context)
+            .await
+            .unwrap();
+        assert_eq!(first.len(), 1);
+
+        // Only tool state is left for the next client
+        let memory = &reasoner.working_memory;
+        for predicate in QUERY_PREDICATES {
+            assert!(
+                !memory.facts.contains_key(predicate),
+                "{} leaked",
+                predicate
+            );
+        }
+        assert!(!memory.facts.contains_key("tool_selected"));
+        assert!(memory.tool_states.contains_key("alpha"));
+
+        let second = reasoner
+            .infer_tool_selection("other", &[tool("alpha")], &HashMap::new())
+            .await
+            .unwrap();
+        assert!(second.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_compute_rule_coverage() {
+        let mut reasoner = setup_reasoner().await;
+        let mut select_alpha = select_existing_rule();
+        select_alpha.id = RecordId::from_table_key("symbolic_rule", "select_alpha");
+        select_alpha.antecedents = vec![SymbolicExpression::Fact(fact(
+            "tool_exists",
+            vec![string("alpha")],
+        ))];
+        select_alpha.consequents = vec![SymbolicExpression::Fact(fact(
+            "tool_selected",
+            vec![
+                string("alpha"),
+                SymbolicExpression::Literal(LiteralValue::Number(0.9)),
+                string("alpha exists"),
+            ],
+        ))];
+        let mut never_fires = planning_rule("never_fires", "gamma");
+        never_fires.antecedents = vec![SymbolicExpression::Fact(fact("missing", vec![]))];
+        reasoner.rules = vec![select_alpha.clone(), never_fires.clone()];
+        reasoner
+            .working_memory
+            .facts
+            .insert("kept".to_string(), vec![fact("kept", vec![])]);
+
+        let report = reasoner
+            .compute_rule_coverage(&[tool("beta"), tool("alpha")])
+            .unwrap();
+        assert_eq!(report.covered_tools, vec!["alpha".to_string()]);
+        assert_eq!(report.uncovered_tools, vec!["beta".to_string()]);
+        assert_eq!(report.rule_hit_counts[&select_alpha.id], 1);
+        assert_eq!(report.rule_hit_counts[&never_fires.id], 0);
+
+        // The reasoner's own memory is restored
+        let memory = &reasoner.working_memory;
+        assert!(memory.facts.contains_key("kept"));
+        assert!(!memory.facts.contains_key("tool_exists"));
+        assert!(memory.tool_states.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_retract_fact_revises_derived_facts() {
+        let mut reasoner = setup_reasoner().await;
+        reasoner.rules = vec![select_existing_rule()];
+        reasoner
+            .infer_tool_selection("query", &[tool("alpha")], &HashMap::new())
+            .await
+            .unwrap();
+
+        let alpha = [LiteralValue::String("alpha".to_string())];
+        assert!(reasoner.retract_fact("tool_exists", &alpha).unwrap());
+        assert!(!reasoner.working_memory.facts.contains_key("tool_selected"));
+        assert!(!reasoner.retract_fact("tool_exists", &alpha).unwrap());
+    }
+
+    #[test]
+    fn test_pretty_print() {
+        assert_eq!(
+            select_existing_rule().pretty_print(),
+            r#"select_existing: [tool_exists(?T)] ⟹ [tool_selected(?T, 0.9, "exists")] [conf=1, prio=1]"#
+        );
+
+        let expression = SymbolicExpression::Quantified(
+            Quantifier::ForAll,
+            "X".to_string(),
+            Box::new(SymbolicExpression::Implies(
+                Box::new(SymbolicExpression::And(vec![
+                    SymbolicExpression::Fact(fact(
+                        "tool_exists",
+                        vec![SymbolicExpression::Variable("X".to_string())],
+                    )),
+                    SymbolicExpression::Not(Box::new(SymbolicExpression::Fact(fact(
+                        "blocked",
+                        vec![SymbolicExpression::Variable("X".to_string())],
+                    )))),
+                ])),
+                Box::new(SymbolicExpression::Or(vec![
+                    SymbolicExpression::Literal(LiteralValue::Boolean(true)),
+                    SymbolicExpression::Comparison(
+                        ComparisonOp::GreaterEqual,
+                        Box::new(SymbolicExpression::Variable("X".to_string())),
+                        Box::new(SymbolicExpression::Literal(LiteralValue::Array(vec![
+                            LiteralValue::Number(2.5),
+                        ]))),
+                    ),
+                ])),
+            )),
+        );
+        assert_eq!(
+            expression.pretty_print(2),
+            "  ∀X: (tool_exists(?X) ∧ ¬blocked(?X)) ⟹ (true ∨ ?X ≥ [2.5])"
+        );
+    }
+
+    #[test]
+    fn test_validate_accepts_well_formed_rule() {
+        assert!(select_existing_rule().validate().is_empty());
+    }
+
+    #[test]
+    fn test_validate_reports_every_problem() {
+        let mut rule = select_existing_rule();
+        rule.antecedents = vec![];
+        rule.consequents = vec![];
+        rule.confidence = 1.5;
+        assert_eq!(
+            rule.validate(),
+            vec![
+                RuleValidationError::EmptyAntecedents,
+                RuleValidationError::EmptyConsequents,
+                RuleValidationError::InvalidConfidence(1.5),
+            ]
+        );
+
+        let mut rule = select_existing_rule();
+        rule.confidence = 0.05;
+        rule.consequents.push(SymbolicExpression::Fact(fact(
+            "tool_exists",
+            vec![SymbolicExpression::Variable("U".to_string())],
+        )));
+        rule.consequents.push(SymbolicExpression::Quantified(
+            Quantifier::ForAll,
+            "X".to_string(),
+            Box::new(SymbolicExpression::Fact(fact(
+                "covers",
+                vec![SymbolicExpression::Variable("X".to_string())],
+            ))),
+        ));
+        assert_eq!(
+            rule.validate(),
+            vec![
+                RuleValidationError::ConfidenceTooLow(0.05),
+                RuleValidationError::UnboundConsequentVariable("U".to_string()),
+                RuleValidationError::CyclicFactReference("tool_exists".to_string()),
+            ]
+        );
+
+        rule.confidence = f32::NAN;
+        assert!(matches!(
+            rule.validate()[0],
+            RuleValidationError::InvalidConfidence(c) if c.is_nan()
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_add_rule_persists_and_load_skips_invalid_rules() {
+        let db = create_connection(DatabaseConfig {
+            url: "memory".to_string(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        crate::db::ensure_schema(&db).await.unwrap();
+        let mut reasoner = SymbolicReasoner::new(db.clone());
+
+        reasoner.add_rule(select_existing_rule()).await.unwrap();
+
+        let mut invalid = select_existing_rule();
+        invalid.id = RecordId::from_table_key("symbolic_rule", "invalid");
+        invalid.antecedents = vec![];
+        let err = reasoner.add_rule(invalid).await.unwrap_err();
+        let err = err.downcast_ref::<InvalidRuleError>().unwrap();
+        assert_eq!(
+            err.errors,
+            vec![
+                RuleValidationError::EmptyAntecedents,
+                RuleValidationError::UnboundConsequentVariable("T".to_string()),
+            ]
+        );
+
+        // Rules written around `add_rule` are still validated on load
+        db.query(
+            "CREATE symbolic_rule:weak CONTENT {
+                name: 'weak', description: '', antecedents: [], consequents: [],
+                confidence: 0.0, priority: 5
+            }",
+        )
+        .await
+        .unwrap()
+        .check()
+        .unwrap();
+
+        let mut reloaded = SymbolicReasoner::new(db);
+        reloaded.load_rules().await.unwrap();
+        assert_eq!(reloaded.rules.len(), 1);
+        assert_eq!(reloaded.rules[0].name, "select_existing");
+        assert_eq!(
+            reloaded.rules[0].consequents,
+            select_existing_rule().consequents
+        );
+    }
+
+    #[tokio::test]
+    async fn test_load_rules_incremental_merges_changes() {
+        let db = create_connection(DatabaseConfig {
+            url: "memory".to_string(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        crate::db::ensure_schema(&db).await.unwrap();
+        let mut writer = SymbolicReasoner::new(db.clone());
+        // Distinct consequents so the rules don't conflict as duplicates
+        let rule = |key: &str, priority: u32| SymbolicRule {
+            id: RecordId::from_table_key("symbolic_rule", key),
+            name: key.to_string(),
+            consequents: vec![SymbolicExpression::Fact(fact(
+                "tool_selected",
+                vec![
+                    SymbolicExpression::Variable("T".to_string()),
+                    SymbolicExpression::Literal(LiteralValue::Number(0.9)),
+                    string(key),
+                ],
+            ))],
+            priority,
+            ..select_existing_rule()
+        };
+        writer.add_rule(rule("a", 1)).await.unwrap();
+        writer.add_rule(rule("b", 2)).await.unwrap();
+
+        let mut reasoner = SymbolicReasoner::new(db.clone());
+        assert_eq!(reasoner.load_rules_incremental(None).await.unwrap(), 2);
+
+        let mut res = db.query("RETURN time::now()").await.unwrap();
+        let since: Option<Datetime> = res.take(0).unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+
+        db.query(
+            "UPDATE symbolic_rule:a SET priority = 10;
+             UPDATE symbolic_rule:b SET soft_deleted_at = time::now();",
+        )
+        .await
+        .unwrap()
+        .check()
+        .unwrap();
+        writer.add_rule(rule("c", 5)).await.unwrap();
+
+        assert_eq!(reasoner.load_rules_incremental(since).await.unwrap(), 3);
+        let loaded: Vec<(&str, u32)> = reasoner
+            .rules
+            .iter()
+            .map(|r| (r.name.as_str(), r.priority))
+            .collect();
+        assert_eq!(loaded, vec![("a", 10), ("c", 5)]);
+
+        // A full load agrees with the incremental result
+        let mut full = SymbolicReasoner::new(db);
+        full.load_rules().await.unwrap();
+        assert_eq!(full.rules.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_add_rule_rejects_conflicts_and_upserts() {
+        let db = create_connection(DatabaseConfig {
+            url: "memory".to_string(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        crate::db::ensure_schema(&db).await.unwrap();
+        let mut reasoner = SymbolicReasoner::new(db.clone());
+        let id = reasoner.add_rule(select_existing_rule()).await.unwrap();
+        assert_eq!(id, select_existing_rule().id);
+
+        let duplicate = SymbolicRule {
+            id: RecordId::from_table_key("symbolic_rule", "duplicate"),
+            ..select_existing_rule()
+        };
+        let err = reasoner.add_rule(duplicate).await.unwrap_err();
+        let err = err.downcast_ref::<RuleConflictError>().unwrap();
+        assert_eq!(err.conflicts.len(), 1);
+        assert_eq!(err.conflicts[0].rule_id, select_existing_rule().id);
+        assert_eq!(err.conflicts[0].kind, RuleConflictKind::Duplicate);
+
+        let mut contradiction = select_existing_rule();
+        contradiction.id = RecordId::from_table_key("symbolic_rule", "contradiction");
+        contradiction.consequents = vec![SymbolicExpression::Not(Box::new(
+            select_existing_rule().consequents[0].clone(),
+        ))];
+        let err = reasoner.add_rule(contradiction).await.unwrap_err();
+        let err = err.downcast_ref::<RuleConflictError>().unwrap();
+        assert_eq!(err.conflicts[0].kind, RuleConflictKind::Contradiction);
+
+        // Re-adding under the same ID replaces the rule instead of conflicting
+        let updated = SymbolicRule {
+            priority: 7,
+            ..select_existing_rule()
+        };
+        reasoner.add_rule(updated).await.unwrap();
+        assert_eq!(reasoner.rules.len(), 1);
+        assert_eq!(reasoner.rules[0].priority, 7);
+
+        assert!(reasoner.remove_rule(&id).await.unwrap());
+        assert!(reasoner.rules.is_empty());
+        assert!(!reasoner.remove_rule(&id).await.unwrap());
+
+        let mut reloaded = SymbolicReasoner::new(db);
+        reloaded.load_rules().await.unwrap();
+        assert!(reloaded.rules.is_empty());
+
+        // Adding a soft-deleted rule again revives it
+        reloaded.add_rule(select_existing_rule()).await.unwrap();
+        reloaded.load_rules().await.unwrap();
+        assert_eq!(reloaded.rules.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_rule_changes_survive_restart() {
+        let db = create_connection(DatabaseConfig {
+            url: "memory".to_string(),
+            ..Default::default()
         })
+        .await
+        .unwrap();
+        crate::db::ensure_schema(&db).await.unwrap();
+        let mut reasoner = SymbolicReasoner::new(db.clone());
+
+        let id = reasoner.add_rule(select_existing_rule()).await.unwrap();
+        let mut other = select_existing_rule();
+        other.id = RecordId::from_table_key("symbolic_rule", "select_named");
+        other.name = "select_named".to_string();
+        other.antecedents.push(SymbolicExpression::Fact(fact(
+            "tool_named",
+            vec![SymbolicExpression::Variable("T".to_string())],
+        )));
+        let other_id = reasoner.add_rule(other).await.unwrap();
+
+        let updated = reasoner
+            .update_rule(SymbolicRule {
+                id: id.clone(),
+                priority: 9,
+                ..select_existing_rule()
+            })
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(updated.priority, 9);
+        assert!(reasoner.remove_rule(&other_id).await.unwrap());
+
+        // A rule that doesn't exist can't be updated, nor can a removed one
+        let unknown = SymbolicRule {
+            id: RecordId::from_table_key("symbolic_rule", "unknown"),
+            ..select_existing_rule()
+        };
+        assert!(reasoner.update_rule(unknown).await.unwrap().is_none());
+        let removed = SymbolicRule {
+            id: other_id,
+            ..select_existing_rule()
+        };
+        assert!(reasoner.update_rule(removed).await.unwrap().is_none());
+
+        let mut restarted = SymbolicReasoner::new(db);
+        restarted.load_rules().await.unwrap();
+        assert_eq!(restarted.rules.len(), 1);
+        assert_eq!(restarted.rules[0].id, id);
+        assert_eq!(restarted.rules[0].priority, 9);
+    }
+
+    fn memory(facts: Vec<Fact>) -> WorkingMemory {
+        let mut memory = WorkingMemory {
+            facts: HashMap::new(),
+            variables: HashMap::new(),
+            tool_states: HashMap::new(),
+        };
+        for fact in facts {
+            memory
+                .facts
+                .entry(fact.predicate.clone())
+                .or_default()
+                .push(fact);
+        }
+        memory
+    }
+
+    fn fact_with(predicate: &str, arg: &str, confidence: Option<f32>) -> Fact {
+        Fact {
+            predicate: predicate.to_string(),
+            arguments: vec![string(arg)],
+            confidence,
+        }
+    }
+
+    fn tool_state(name: &str, success_rate: f32) -> ToolState {
+        ToolState {
+            tool_id: RecordId::from_table_key("tool", name),
+            status: ToolStatus::Available,
+            last_output: None,
+            input_requirements: JsonObject::new(),
+            execution_count: 0,
+            success_rate,
+        }
+    }
+
+    #[test]
+    fn test_working_memory_set_operations() {
+        let mut a = memory(vec![
+            fact_with("likes", "rust", Some(0.4)),
+            fact_with("likes", "go", Some(0.9)),
+        ]);
+        a.variables
+            .insert("x".to_string(), LiteralValue::Number(1.0));
+        a.variables
+            .insert("y".to_string(), LiteralValue::Number(2.0));
+        a.tool_states
+            .insert("search".to_string(), tool_state("search", 0.5));
+
+        let mut b = memory(vec![
+            fact_with("likes", "rust", Some(0.8)),
+            fact_with("knows", "surreal", None),
+        ]);
+        b.variables
+            .insert("x".to_string(), LiteralValue::Number(1.0));
+        b.variables
+            .insert("y".to_string(), LiteralValue::Number(3.0));
+        b.tool_states
+            .insert("search".to_string(), tool_state("search", 0.9));
+
+        let union = a.union(&b);
+        assert_eq!(union.facts["likes"].len(), 2);
+        assert_eq!(
+            union
+                .find_fact(&fact_with("likes", "rust", None))
+                .unwrap()
+                .confidence,
+            Some(0.8)
+        );
+        assert!(union.facts.contains_key("knows"));
+        assert_eq!(union.variables["y"], LiteralValue::Number(2.0));
+        assert_eq!(union.tool_states["search"].success_rate, 0.9);
+
+        let intersection = a.intersection(&b);
+        assert_eq!(intersection.facts.len(), 1);
+        assert_eq!(intersection.facts["likes"].len(), 1);
+        assert_eq!(intersection.facts["likes"][0].confidence, Some(0.4));
+        assert_eq!(intersection.variables.len(), 1);
+        assert!(intersection.variables.contains_key("x"));
+        assert_eq!(intersection.tool_states["search"].success_rate, 0.5);
+
+        let difference = a.difference(&b);
+        assert_eq!(
+            difference.facts["likes"],
+            vec![fact_with("likes", "go", Some(0.9))]
+        );
+        assert!(difference.variables.is_empty());
+        assert!(difference.tool_states.is_empty());
+    }
+
+    #[test]
+    fn test_to_mermaid_groups_parallel_steps() {
+        let step =
+            |step_number: u32, tool_name: &str, parallel: bool, dependencies: Vec<u32>| PlanStep {
+                step_number,
+                tool_id: RecordId::from_table_key("tool", tool_name),
+                tool_name: tool_name.to_string(),
+                inputs: HashMap::new(),
+                expected_outputs: vec![],
+                parallel,
+                dependencies,
+                description_template: None,
+            };
+        let plan = ToolPlan {
+            id: "plan-1".to_string(),
+            goal: "summarize".to_string(),
+            steps: vec![
+                step(1, "read_file", false, vec![]),
+                step(2, "fetch_url", true, vec![1]),
+                step(3, "say \"hi\"", true, vec![1]),
+                step(4, "summarize", false, vec![2, 3]),
+            ],
+            estimated_cost: 0.0,
+            estimated_time: 0.0,
+            confidence: 0.8,
+        };
+
+        let expected = r#"flowchart TD
+    step1["Step 1: read_file"]
+    subgraph parallel_group_1
+        step2["Step 2: fetch_url"]
+        step3["Step 3: say #quot;hi#quot;"]
+    end
+    step4["Step 4: summarize"]
+    step1 --> step2
+    step1 --> step3
+    step2 --> step4
+    step3 --> step4
+"#;
+        assert_eq!(plan.to_mermaid(), expected);
+    }
+
+    mod properties {
+        use super::*;
+        use proptest::prelude::*;
+
+        fn arb_fact() -> impl Strategy<Value = Fact> {
+            (
+                prop::sample::select(vec!["likes", "knows", "uses"]),
+                prop::sample::select(vec!["rust", "go", "surreal", "mcp"]),
+                prop::option::of(0.0f32..=1.0),
+            )
+                .prop_map(|(predicate, arg, confidence)| fact_with(predicate, arg, confidence))
+        }
+
+        fn arb_memory() -> impl Strategy<Value = WorkingMemory> {
+            prop::collection::vec(arb_fact(), 0..12).prop_map(memory)
+        }
+
+        proptest! {
+            #[test]
+            fn union_is_superset_of_both(a in arb_memory(), b in arb_memory()) {
+                let union = a.union(&b);
+                for fact in a.facts.values().chain(b.facts.values()).flatten() {
+                    prop_assert!(union.find_fact(fact).is_some());
+                }
+            }
+
+            #[test]
+            fn union_keeps_highest_confidence(a in arb_memory(), b in arb_memory()) {
+                let union = a.union(&b);
+                for fact in a.facts.values().chain(b.facts.values()).flatten() {
+                    let merged = union.find_fact(fact).unwrap();
+                    prop_assert!(fact_confidence(merged) >= fact_confidence(fact));
+                }
+            }
+
+            #[test]
+            fn intersection_is_subset_of_both(a in arb_memory(), b in arb_memory()) {
+                let intersection = a.intersection(&b);
+                for fact in intersection.facts.values().flatten() {
+                    prop_assert!(a.find_fact(fact).is_some());
+                    prop_assert!(b.find_fact(fact).is_some());
+                }
+            }
+
+            #[test]
+            fn difference_excludes_other(a in arb_memory(), b in arb_memory()) {
+                let difference = a.difference(&b);
+                for fact in difference.facts.values().flatten() {
+                    prop_assert!(a.find_fact(fact).is_some());
+                    prop_assert!(b.find_fact(fact).is_none());
+                }
+            }
+        }
     }
 }
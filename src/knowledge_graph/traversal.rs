@@ -1,213 +1,421 @@
-// Graph traversal using SurrealDB's native graph capabilities
-// Based on https://surrealdb.com/docs/surrealdb/models/graph
-
-use crate::db::GraphQueries;
-use crate::db::schema::ToolSequence;
-use crate::db::graph_queries::GraphStructure;
-use crate::knowledge_graph::graph::EdgeType;
-use anyhow::Result;
-use surrealdb::{RecordId, Surreal};
-use std::collections::HashMap;
-
-#[derive(Debug, Clone)]
-pub struct TraversalEngine {
-    db: Surreal<surrealdb::engine::any::Any>,
+//! Type-URI graph traversal for chaining tools.
+//!
+//! Every tool is an edge from the type URI of its input schema to the type URI
+//! of its output schema. A schema's type URI is its `$id` (or the `x-type`
+//! extension) when present, otherwise its JSON Schema type. Paths through this
+//! graph are tool chains that turn a value of one type into another.
+
+use crate::db::ToolRecord;
+use crate::db::schema::TypedSchema;
+use crate::knowledge_graph::KnowledgeGraph;
+use rmcp::model::JsonObject;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Cost of a single hop when estimating the cost of a tool chain.
+const HOP_COST: f32 = 1.0;
+
+/// Most chains [`TypeUriGraph::all_paths`] returns; dense graphs have
+/// exponentially many.
+pub const MAX_PATHS: usize = 1000;
+
+/// Directed graph of type URIs connected by tools.
+#[derive(Debug, Clone, Default)]
+pub struct TypeUriGraph {
+    /// Key: input type URI, Value: (output type URI, tool) for every tool accepting it
+    adjacency: HashMap<String, Vec<(String, ToolRecord)>>,
 }
 
-impl TraversalEngine {
-    pub fn new(db: Surreal<surrealdb::engine::any::Any>) -> Self {
-        Self { db }
-    }
-
-    /// Find tools that can transform input_type to output_type using SurrealDB graph traversal
-    pub async fn find_transformation_path(
-        &self,
-        input_type: &str,
-        output_type: &str,
-        max_depth: u8,
-    ) -> Result<Vec<TransformationPath>> {
-        // Use SurrealDB's built-in graph traversal
-        let start_tool_id = self.find_sample_tool_by_input_type(input_type).await?;
-
-        let paths = GraphQueries::find_transformation_path(
-            &self.db,
-            &start_tool_id,
-            output_type,
-            max_depth,
-        ).await?;
-
-        // Convert paths to TransformationPath structure
-        let transformation_paths = paths
-            .into_iter()
-            .map(|tool_ids| {
-                let cost = tool_ids.len() as f32 * 1.0; // Simple cost model
-                TransformationPath {
-                    tools: tool_ids,
-                    total_confidence: 0.8, // Default confidence, could be based on usage stats
-                    estimated_cost: cost,
+impl TypeUriGraph {
+    /// Create an empty graph.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build a graph from tool records.
+    ///
+    /// Tools without an output schema produce nothing we can chain on and are skipped.
+    pub fn from_tools(tools: impl IntoIterator<Item = ToolRecord>) -> Self {
+        let mut graph = Self::new();
+        for tool in tools {
+            graph.add_tool(tool);
+        }
+        graph
+    }
+
+    /// Add a tool as an edge from its input type URI to its output type URI.
+    pub fn add_tool(&mut self, tool: ToolRecord) {
+        let Some(output_schema) = &tool.output_schema else {
+            return;
+        };
+        let from = type_uri(&tool.input_schema);
+        let to = type_uri(output_schema);
+        self.adjacency.entry(from).or_default().push((to, tool));
+    }
+
+    /// Find a chain with the fewest tools from `from` to `to` using BFS.
+    ///
+    /// Returns an empty chain when `from == to`, and `None` if `to` is unreachable.
+    pub fn shortest_path(&self, from: &str, to: &str) -> Option<Vec<ToolRecord>> {
+        if from == to {
+            return Some(Vec::new());
+        }
+
+        // Key: type URI, Value: (previous type URI, index of the tool edge used)
+        let mut came_from: HashMap<&str, (&str, usize)> = HashMap::new();
+        let mut visited: HashSet<&str> = HashSet::from([from]);
+        let mut queue = VecDeque::from([from]);
+
+        while let Some(current) = queue.pop_front() {
+            for (index, (next, _)) in self.edges(current).iter().enumerate() {
+                if !visited.insert(next.as_str()) {
+                    continue;
                 }
-            })
-            .collect();
+                came_from.insert(next.as_str(), (current, index));
+
+                if next == to {
+                    return Some(self.rebuild_path(&came_from, to));
+                }
+                queue.push_back(next.as_str());
+            }
+        }
 
-        Ok(transformation_paths)
+        None
     }
 
-    /// Helper: Find a sample tool with the given input type
-    async fn find_sample_tool_by_input_type(&self, input_type: &str) -> Result<RecordId> {
-        let tools = GraphQueries::find_tools_by_input_type(&self.db, input_type).await?;
-        Ok(tools
-            .into_iter()
-            .next()
-            .map(|tool| tool.id)
-            .ok_or_else(|| anyhow::anyhow!("No tool found with input type: {}", input_type))?)
-    }
-
-    /// Find tools that are semantically similar to the query using graph similarity
-    pub async fn find_semantically_similar_tools(
-        &self,
-        _tool_id: &str,
-        _threshold: f32,
-        _limit: usize,
-    ) -> Result<Vec<SemanticMatch>> {
-        // Use SurrealDB to find tools connected through semantic similarity edges
-        // For now, return empty list as semantic matching needs embeddings
-        Ok(vec![])
-    }
-
-    /// Find tool sequences based on usage patterns stored in the database
-    pub async fn find_tool_sequences(
-        &self,
-        tool_id: &str,
-        _max_length: usize,
-    ) -> Result<Vec<ToolSequence>> {
-        // Get tool sequences from database
-        GraphQueries::get_tool_sequences(&self.db, tool_id, 10).await
-    }
-
-    /// Find alternative tools for the same task
-    pub async fn find_alternative_tools(
-        &self,
-        tool_id: &str,
-        _max_alternatives: usize,
-    ) -> Result<Vec<AlternativeTool>> {
-        // Find tools with similar input/output signatures
-        let alternatives = GraphQueries::find_similar_tools(&self.db, tool_id, 0.7, 10).await?;
-
-        let alt_tools = alternatives
+    /// Find every chain from `from` to `to` of at most `max_depth` tools,
+    /// stopping after [`MAX_PATHS`].
+    ///
+    /// Uses DFS and never visits a type URI twice within one chain, so cycles
+    /// in the graph do not produce infinite or repeating paths. Type URIs
+    /// that cannot reach `to` within the remaining depth are not explored.
+    pub fn all_paths(&self, from: &str, to: &str, max_depth: usize) -> Vec<Vec<ToolRecord>> {
+        let distances = self.distances_to(to);
+        let mut search = PathSearch {
+            to,
+            distances: &distances,
+            on_path: HashSet::from([from]),
+            current: Vec::new(),
+            paths: Vec::new(),
+        };
+        self.collect_paths(from, max_depth, &mut search);
+        search.paths
+    }
+
+    /// All type URIs reachable from `start_type` through one or more tools.
+    pub fn reachable_from(&self, start_type: &str) -> HashSet<String> {
+        let mut reachable = HashSet::new();
+        let mut queue = VecDeque::from([start_type]);
+
+        while let Some(current) = queue.pop_front() {
+            for (next, _) in self.edges(current) {
+                if reachable.insert(next.clone()) {
+                    queue.push_back(next.as_str());
+                }
+            }
+        }
+
+        reachable
+    }
+
+    /// Estimated cost of running a tool chain.
+    pub fn estimated_cost(path: &[ToolRecord]) -> f32 {
+        path.len() as f32 * HOP_COST
+    }
+
+    /// Order chains by [`KnowledgeGraph::path_cost`], then by number of tools,
+    /// keeping the best `limit`.
+    pub fn rank_paths(paths: Vec<Vec<ToolRecord>>, limit: usize) -> Vec<Vec<ToolRecord>> {
+        let mut ranked: Vec<(f32, Vec<ToolRecord>)> = paths
             .into_iter()
-            .map(|(alt_id, similarity)| AlternativeTool {
-                tool_id: alt_id,
-                similarity: similarity as f32,
-                confidence: 0.8,
-            })
+            .map(|path| (KnowledgeGraph::path_cost(&path), path))
             .collect();
+        ranked.sort_by(|(cost_a, a), (cost_b, b)| {
+            cost_a.total_cmp(cost_b).then(a.len().cmp(&b.len()))
+        });
+        ranked.truncate(limit);
+        ranked.into_iter().map(|(_, path)| path).collect()
+    }
 
-        Ok(alt_tools)
-    }
-
-    /// Recommend tools based on similar users' behavior (collaborative filtering)
-    pub async fn recommend_tools_collaborative(
-        &self,
-        _user_id: &str,
-        _context: &HashMap<String, serde_json::Value>,
-        _limit: usize,
-    ) -> Result<Vec<CollaborativeRecommendation>> {
-        // TODO: Implement collaborative filtering
-        // This would require tracking user interactions and building a recommendation model
-        Ok(vec![])
-    }
-
-    /// Build the knowledge graph from database data
-    pub async fn build_from_database(&self) -> Result<GraphStructure> {
-        GraphQueries::get_graph_structure(&self.db).await
-    }
-
-    /// Calculate similarity between two tool types
-    pub fn calculate_type_similarity(
-        &self,
-        _from_type: &crate::db::schema::TypedSchema,
-        _to_type: &crate::db::schema::TypedSchema,
-    ) -> f32 {
-        // Implement type compatibility checking
-        // This would use the TypeSystem for strict type checking
-        0.0
-    }
-
-    /// Check if two tools can be connected in the graph
-    pub async fn can_connect(
-        &self,
-        from_tool: &str,
-        to_tool: &str,
-        required_edge_type: EdgeType,
-    ) -> Result<bool> {
-        // Query the database to check if there's a valid edge
-        let mut result = self.db.query(r#"
-            SELECT count() FROM tool_compatibility
-            WHERE
-                in = type::thing('tool', $from_tool)
-                AND out = type::thing('tool', $to_tool)
-                AND compatibility_type = $edge_type
-        "#)
-            .bind(("from_tool", from_tool.to_owned()))
-            .bind(("to_tool", to_tool.to_owned()))
-            .bind(("edge_type", format!("{:?}", required_edge_type)))
-            .await?;
-        let count: Option<i64> = result.take(0)?;
-
-        Ok(count.unwrap_or(0) > 0)
+    fn edges(&self, type_uri: &str) -> &[(String, ToolRecord)] {
+        self.adjacency
+            .get(type_uri)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
     }
-}
 
-#[derive(Debug, Clone)]
-pub struct PathState {
-    pub current_tool: String,
-    pub path: Vec<String>,
-    pub current_type: String,
-    pub depth: usize,
+    fn rebuild_path(&self, came_from: &HashMap<&str, (&str, usize)>, to: &str) -> Vec<ToolRecord> {
+        let mut path = Vec::new();
+        let mut current = to;
+        while let Some((previous, index)) = came_from.get(current) {
+            path.push(self.edges(previous)[*index].1.clone());
+            current = previous;
+        }
+        path.reverse();
+        path
+    }
+
+    /// Fewest tools needed to reach `to` from each type URI that can reach it,
+    /// found by BFS over reversed edges.
+    fn distances_to(&self, to: &str) -> HashMap<&str, usize> {
+        let mut reverse: HashMap<&str, Vec<&str>> = HashMap::new();
+        for (from, edges) in &self.adjacency {
+            for (next, _) in edges {
+                reverse
+                    .entry(next.as_str())
+                    .or_default()
+                    .push(from.as_str());
+            }
+        }
+
+        let mut distances = HashMap::new();
+        let Some(target) = reverse.keys().find(|uri| **uri == to).copied() else {
+            return distances;
+        };
+        distances.insert(target, 0);
+        let mut queue = VecDeque::from([target]);
+        while let Some(current) = queue.pop_front() {
+            let distance = distances[current] + 1;
+            for previous in reverse.get(current).into_iter().flatten() {
+                if !distances.contains_key(previous) {
+                    distances.insert(previous, distance);
+                    queue.push_back(previous);
+                }
+            }
+        }
+        distances
+    }
+
+    fn collect_paths<'a>(
+        &'a self,
+        current_type: &str,
+        remaining: usize,
+        search: &mut PathSearch<'a, '_>,
+    ) {
+        if remaining == 0 {
+            return;
+        }
+
+        for (next, tool) in self.edges(current_type) {
+            if search.paths.len() >= MAX_PATHS {
+                return;
+            }
+            if next == search.to {
+                search.current.push(tool.clone());
+                search.paths.push(search.current.clone());
+                search.current.pop();
+                continue;
+            }
+            // Skip type URIs that cannot reach `to` with the tools left
+            if search
+                .distances
+                .get(next.as_str())
+                .is_none_or(|distance| *distance >= remaining)
+            {
+                continue;
+            }
+            if !search.on_path.insert(next.as_str()) {
+                continue;
+            }
+
+            search.current.push(tool.clone());
+            self.collect_paths(next, remaining - 1, search);
+            search.current.pop();
+            search.on_path.remove(next.as_str());
+        }
+    }
 }
 
-#[derive(Debug, Clone)]
-pub struct TransformationPath {
-    pub tools: Vec<String>,
-    pub total_confidence: f32,
-    pub estimated_cost: f32,
+/// State of one [`TypeUriGraph::all_paths`] search.
+struct PathSearch<'a, 'b> {
+    to: &'b str,
+    /// Fewest tools from each type URI to `to`
+    distances: &'b HashMap<&'a str, usize>,
+    /// Type URIs on the chain being built
+    on_path: HashSet<&'a str>,
+    current: Vec<ToolRecord>,
+    paths: Vec<Vec<ToolRecord>>,
 }
 
-#[derive(Debug, Clone)]
-pub struct SemanticMatch {
-    pub tool_id: String,
-    pub similarity: f32,
-    pub embedding_model: String,
+/// Type URI of a schema: `$id`, then `x-type`, then the JSON Schema type.
+pub fn type_uri(schema: &JsonObject) -> String {
+    ["$id", "x-type"]
+        .iter()
+        .find_map(|key| schema.get(*key).and_then(|v| v.as_str()))
+        .map(str::to_string)
+        .unwrap_or_else(|| TypedSchema::from_json_schema(schema).schema_type)
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use surrealdb::RecordId;
 
-#[derive(Debug, Clone)]
-pub struct AlternativeTool {
-    pub tool_id: String,
-    pub similarity: f32,
-    pub confidence: f32,
-}
+    fn tool(name: &str, from: &str, to: &str) -> ToolRecord {
+        ToolRecord {
+            id: RecordId::from_table_key("tool", name),
+            service_id: RecordId::from_table_key("service", "test"),
+            name: name.to_string(),
+            description: None,
+            input_schema: json!({"$id": from}).as_object().cloned().unwrap(),
+            output_schema: json!({"$id": to}).as_object().cloned(),
+            embedding_id: None,
+            input_ty: None,
+            output_ty: None,
+            usage_count: 0,
+            status: None,
+            discovery_source: Default::default(),
+            metadata: Default::default(),
+            input_examples: Vec::new(),
+            changelog: Vec::new(),
+            deprecation_notice: None,
+            descriptions: HashMap::new(),
+            inject_user_context: Vec::new(),
+            geo_availability: None,
+            required_env_vars: Vec::new(),
+            sla_class: None,
+            cost_per_call: None,
+            cache_ttl_seconds: None,
+            required_role: None,
+            actual_latency_p99_ms: None,
+            actual_error_rate_pct: None,
+            block_reason: None,
+            created_at: None,
+            updated_at: None,
+        }
+    }
 
-#[derive(Debug, Clone)]
-pub struct CollaborativeRecommendation {
-    pub tool_id: String,
-    pub score: f32,
-    pub similar_users: Vec<String>,
-    pub reasoning: String,
-}
+    fn names(path: &[ToolRecord]) -> Vec<&str> {
+        path.iter().map(|t| t.name.as_str()).collect()
+    }
+
+    fn sample_graph() -> TypeUriGraph {
+        // A -> B -> C -> D, plus a shortcut A -> C and a cycle C -> A
+        TypeUriGraph::from_tools([
+            tool("a_to_b", "uri:A", "uri:B"),
+            tool("b_to_c", "uri:B", "uri:C"),
+            tool("c_to_d", "uri:C", "uri:D"),
+            tool("a_to_c", "uri:A", "uri:C"),
+            tool("c_to_a", "uri:C", "uri:A"),
+        ])
+    }
+
+    #[test]
+    fn test_type_uri_prefers_id_then_extension_then_type() {
+        let with_id = json!({"$id": "uri:File", "x-type": "uri:Other", "type": "object"});
+        let with_ext = json!({"x-type": "uri:Other", "type": "object"});
+        let plain = json!({"type": "string"});
+        assert_eq!(type_uri(with_id.as_object().unwrap()), "uri:File");
+        assert_eq!(type_uri(with_ext.as_object().unwrap()), "uri:Other");
+        assert_eq!(type_uri(plain.as_object().unwrap()), "string");
+    }
 
-fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
-    if a.len() != b.len() {
-        return 0.0;
+    #[test]
+    fn test_tools_without_output_schema_are_skipped() {
+        let mut no_output = tool("sink", "uri:A", "uri:B");
+        no_output.output_schema = None;
+        let graph = TypeUriGraph::from_tools([no_output]);
+        assert!(graph.reachable_from("uri:A").is_empty());
     }
 
-    let dot_product: f32 = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum();
-    let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
-    let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    #[test]
+    fn test_shortest_path_uses_fewest_tools() {
+        let graph = sample_graph();
+        let path = graph.shortest_path("uri:A", "uri:D").unwrap();
+        assert_eq!(names(&path), vec!["a_to_c", "c_to_d"]);
+        assert_eq!(TypeUriGraph::estimated_cost(&path), 2.0);
+    }
 
-    if norm_a == 0.0 || norm_b == 0.0 {
-        0.0
-    } else {
-        dot_product / (norm_a * norm_b)
+    #[test]
+    fn test_shortest_path_same_and_unreachable() {
+        let graph = sample_graph();
+        assert_eq!(graph.shortest_path("uri:A", "uri:A").unwrap().len(), 0);
+        assert!(graph.shortest_path("uri:D", "uri:A").is_none());
+        assert!(graph.shortest_path("uri:A", "uri:Z").is_none());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_all_paths_respects_depth_and_cycles() {
+        let graph = sample_graph();
+
+        let all = graph.all_paths("uri:A", "uri:D", 5);
+        let mut paths: Vec<Vec<&str>> = all.iter().map(|p| names(p)).collect();
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec![vec!["a_to_b", "b_to_c", "c_to_d"], vec!["a_to_c", "c_to_d"]]
+        );
+
+        assert_eq!(graph.all_paths("uri:A", "uri:D", 2).len(), 1);
+        assert!(graph.all_paths("uri:A", "uri:D", 1).is_empty());
+    }
+
+    #[test]
+    fn test_all_paths_stops_at_max_paths() {
+        // Ten parallel tools per hop give 10^4 chains from uri:0 to uri:4
+        let graph = TypeUriGraph::from_tools((0..4).flat_map(|hop| {
+            (0..10).map(move |i| {
+                tool(
+                    &format!("hop{}_{}", hop, i),
+                    &format!("uri:{}", hop),
+                    &format!("uri:{}", hop + 1),
+                )
+            })
+        }));
+
+        assert_eq!(graph.all_paths("uri:0", "uri:4", 8).len(), MAX_PATHS);
+        assert!(graph.all_paths("uri:0", "uri:4", 3).is_empty());
+    }
+
+    #[test]
+    fn test_rank_paths_prefers_cheaper_chains() {
+        let graph = sample_graph();
+        let ranked = TypeUriGraph::rank_paths(graph.all_paths("uri:A", "uri:D", 5), 3);
+        assert_eq!(ranked.len(), 2);
+        assert_eq!(names(&ranked[0]), vec!["a_to_c", "c_to_d"]);
+
+        let ranked = TypeUriGraph::rank_paths(graph.all_paths("uri:A", "uri:D", 5), 1);
+        assert_eq!(ranked.len(), 1);
+    }
+
+    #[test]
+    fn test_rank_paths_prefers_compatible_schemas() {
+        // Both chains turn uri:A into uri:C, but only one's schemas line up
+        let mut a_to_b = tool("a_to_b", "uri:A", "uri:B");
+        a_to_b.output_schema = json!({"$id": "uri:B", "properties": {"text": {"type": "string"}}})
+            .as_object()
+            .cloned();
+        let mut b_to_c = tool("b_to_c", "uri:B", "uri:C");
+        b_to_c.input_schema =
+            json!({"$id": "uri:B", "properties": {"text": {"type": "string"}}, "required": ["text"]})
+                .as_object()
+                .cloned()
+                .unwrap();
+        let mut a_to_x = tool("a_to_x", "uri:A", "uri:X");
+        a_to_x.output_schema = json!({"$id": "uri:X", "type": "string"})
+            .as_object()
+            .cloned();
+        let mut x_to_c = tool("x_to_c", "uri:X", "uri:C");
+        x_to_c.input_schema = json!({"$id": "uri:X", "type": "integer"})
+            .as_object()
+            .cloned()
+            .unwrap();
+
+        let graph = TypeUriGraph::from_tools([a_to_x, x_to_c, a_to_b, b_to_c]);
+        let ranked = TypeUriGraph::rank_paths(graph.all_paths("uri:A", "uri:C", 5), 2);
+        assert_eq!(names(&ranked[0]), vec!["a_to_b", "b_to_c"]);
+        assert_eq!(KnowledgeGraph::path_cost(&ranked[1]), 1.0);
+    }
+
+    #[test]
+    fn test_reachable_from() {
+        let graph = sample_graph();
+        let reachable = graph.reachable_from("uri:B");
+        let expected: HashSet<String> = ["uri:C", "uri:D", "uri:A", "uri:B"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(reachable, expected);
+        assert!(graph.reachable_from("uri:D").is_empty());
+    }
+}
@@ -1,10 +1,13 @@
 // Core modules
 pub mod api;
 mod config;
+mod correlation;
 pub mod db;
 mod executor;
 mod knowledge_graph;
 mod mcp_client;
+mod metrics;
+pub mod registry;
 
 // NewType wrappers for strong typing
 pub mod types;
@@ -15,49 +18,192 @@ mod elicitation;
 mod orchestrator;
 mod prompts;
 mod resources;
+mod sampling;
 pub mod server;
+mod session;
 mod tools;
 
 // Re-export key types and functions
 pub use auth::{AuthConfig, UserContext, generate_api_key, hash_api_key};
-pub use config::McpServiceConfig;
+pub use config::{
+    AllowedCapability, McpServiceConfig, TrustLevel, apply_manifest_transform,
+    clear_manifest_cache, default_manifest_cache_dir, manifest_shape_errors,
+};
 pub use db::{DatabaseConfig, ToolRecord, create_connection, ensure_schema};
-pub use knowledge_graph::{EmbeddingManager, KnowledgeGraph};
+pub use elicitation::{ApprovalConfig, ElicitationFallbackPolicy};
+pub use knowledge_graph::{
+    EmbeddingConfig, EmbeddingManager, EmbeddingModelKind, Fact, KnowledgeGraph, LiteralValue,
+    NormalizationStep, SymbolicExpression, SymbolicReasoner, SymbolicRule, TextNormalizer,
+    WorkingMemory,
+};
 pub use types::{
     ApiKeyHash, ApiKeyPrefix, ExternalUserId, IdentityProvider, OAuthUrl, PromptName, RedirectUri,
     ResourceUri, ServiceConfigId, ServiceId, ServiceName, ToolId, ToolName,
 };
 
 // Re-export from new modular structure
-pub use orchestrator::{Orchestrator, PlanResult, PlanStep};
+pub use orchestrator::reload::ConfigReloadResult;
+pub use orchestrator::shutdown::ShutdownHandle;
+pub use orchestrator::{Orchestrator, PlanResult, PlanStep, ToolQueryOptions};
 pub use server::McpServer;
-pub use tools::{ToolHandler, ToolRegistry};
+pub use session::{McpServerSession, SessionRegistry, SessionStore};
+pub use tools::{HealthStatus, ToolContext, ToolHandler, ToolRegistry};
 
 use anyhow::Result;
+use auth::AuthExtractor;
+use std::path::PathBuf;
 use std::sync::Arc;
-use tools::{ExecuteToolHandler, ListDiscoveredToolsHandler, PlanToolsHandler, SelectToolHandler};
+use tools::{
+    DescribeToolHandler, ExecuteToolHandler, ExecuteWithRetryToolHandler,
+    ListDiscoveredToolsHandler, ListServicesHandler, PlanToolsHandler, SelectToolHandler,
+    SubscribeResourceHandler, UnsubscribeResourceHandler, WriteResourceHandler,
+};
+
+/// Everything needed to build a customized MCP server.
+///
+/// `Default` matches what [`create_server`] uses.
+#[derive(Default)]
+pub struct OrchestratorConfig {
+    pub db: DatabaseConfig,
+    /// Authentication for MCP sessions. `None` runs in anonymous/stdio mode.
+    pub auth: Option<AuthConfig>,
+    pub embedding: EmbeddingConfig,
+    pub approval: ApprovalConfig,
+    /// Handlers registered alongside the built-in tools. A handler with the
+    /// same name as a built-in tool replaces it.
+    pub extra_handlers: Vec<Box<dyn ToolHandler>>,
+    /// Names of tools (built-in or extra) that should not be registered,
+    /// e.g. `"unicity.list_discovered_tools"`.
+    pub disabled_handlers: Vec<String>,
+    pub elicitation_policy: ElicitationFallbackPolicy,
+    /// Path of the `mcp.json` listing downstream services. `None` looks in
+    /// `MCP_CONFIG`, then the usual locations.
+    pub mcp_config: Option<PathBuf>,
+}
 
 /// Convenience function to create a fully configured MCP server.
 ///
 /// This creates the Orchestrator, registers the default tools, and returns
 /// a McpServer that implements rmcp's ServerHandler.
 pub async fn create_server(config: DatabaseConfig) -> Result<Arc<McpServer>> {
+    create_server_with_config(OrchestratorConfig {
+        db: config,
+        ..Default::default()
+    })
+    .await
+}
+
+/// Create an MCP server with full control over its configuration and tools.
+pub async fn create_server_with_config(config: OrchestratorConfig) -> Result<Arc<McpServer>> {
+    let OrchestratorConfig {
+        db,
+        auth,
+        embedding,
+        approval,
+        extra_handlers,
+        disabled_handlers,
+        elicitation_policy,
+        mcp_config,
+    } = config;
+
     // Create the orchestrator
-    let mut orchestrator = Orchestrator::new(config).await?;
+    let mut orchestrator =
+        Orchestrator::new_with_config(db, embedding, &approval, elicitation_policy).await?;
+    orchestrator.set_mcp_config_path(mcp_config);
     orchestrator.initialize().await?;
     let orchestrator = Arc::new(orchestrator);
 
-    // Create and configure the tool registry
-    let tool_registry = ToolRegistry::new()
-        .register_handler(SelectToolHandler::new(orchestrator.clone()))
-        .register_handler(PlanToolsHandler::new(orchestrator.clone()))
-        .register_handler(ExecuteToolHandler::new(orchestrator.clone()))
-        .register_handler(ListDiscoveredToolsHandler::new(orchestrator.clone()));
+    let builtin: Vec<Arc<dyn ToolHandler>> = vec![
+        Arc::new(SelectToolHandler::new(orchestrator.clone())),
+        Arc::new(PlanToolsHandler::new(orchestrator.clone())),
+        Arc::new(ExecuteToolHandler::new(orchestrator.clone())),
+        Arc::new(ExecuteWithRetryToolHandler::new(orchestrator.clone())),
+        Arc::new(DescribeToolHandler::new(orchestrator.clone())),
+        Arc::new(ListServicesHandler::new(orchestrator.clone())),
+        Arc::new(ListDiscoveredToolsHandler::new(orchestrator.clone())),
+        Arc::new(SubscribeResourceHandler::new(orchestrator.clone())),
+        Arc::new(UnsubscribeResourceHandler::new(orchestrator.clone())),
+        Arc::new(WriteResourceHandler::new(orchestrator.clone())),
+    ];
+    let handlers = builtin
+        .into_iter()
+        .chain(extra_handlers.into_iter().map(Arc::from));
 
+    // Create and configure the tool registry
+    let tool_registry = build_tool_registry(handlers, &disabled_handlers);
     let tool_registry = Arc::new(tool_registry);
 
     // Create the server
-    let server = McpServer::new(orchestrator, tool_registry);
+    let server = match auth {
+        Some(auth) => {
+            let extractor = AuthExtractor::new(auth, orchestrator.db().clone());
+            extractor.register_metrics(orchestrator.metrics());
+            McpServer::new_with_auth(orchestrator, tool_registry, Arc::new(extractor))
+        }
+        None => McpServer::new(orchestrator, tool_registry),
+    };
 
     Ok(Arc::new(server))
 }
+
+/// Register `handlers` in order, skipping any whose name is in `disabled`.
+fn build_tool_registry(
+    handlers: impl IntoIterator<Item = Arc<dyn ToolHandler>>,
+    disabled: &[String],
+) -> ToolRegistry {
+    handlers
+        .into_iter()
+        .filter(|handler| !disabled.iter().any(|name| name == handler.name()))
+        .fold(ToolRegistry::new(), ToolRegistry::register)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::model::{CallToolResult, JsonObject};
+    use std::future::Future;
+    use std::pin::Pin;
+
+    struct NamedHandler(&'static str, &'static str);
+
+    impl ToolHandler for NamedHandler {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn description(&self) -> &str {
+            self.1
+        }
+
+        fn input_schema(&self) -> JsonObject {
+            JsonObject::new()
+        }
+
+        fn execute(
+            &self,
+            _args: JsonObject,
+            _ctx: &ToolContext,
+        ) -> Pin<Box<dyn Future<Output = Result<CallToolResult>> + Send + '_>> {
+            Box::pin(async { Ok(CallToolResult::success(Vec::new())) })
+        }
+    }
+
+    #[test]
+    fn test_build_tool_registry_skips_disabled_and_lets_extras_override() {
+        let handlers: Vec<Arc<dyn ToolHandler>> = vec![
+            Arc::new(NamedHandler("unicity.select_tool", "builtin")),
+            Arc::new(NamedHandler("unicity.plan_tools", "builtin")),
+            Arc::new(NamedHandler("custom.echo", "extra")),
+            Arc::new(NamedHandler("unicity.select_tool", "extra")),
+        ];
+        let registry = build_tool_registry(handlers, &["unicity.plan_tools".to_string()]);
+
+        let mut names = registry.list_names();
+        names.sort();
+        assert_eq!(names, vec!["custom.echo", "unicity.select_tool"]);
+        assert_eq!(
+            registry.get("unicity.select_tool").unwrap().description(),
+            "extra"
+        );
+    }
+}
@@ -0,0 +1,578 @@
+// MCP client implementation backed by rmcp
+
+pub mod supervisor;
+
+pub use supervisor::{RestartPolicy, ServiceSupervisor};
+
+use crate::config::{DEFAULT_SAMPLING_PROVENANCE_PREFIX, McpServiceConfig, allowed_mcp_commands};
+use crate::elicitation::wrap_sampling_with_provenance;
+use crate::sampling::{SamplingError, SamplingForwarder, current_session_sampling};
+use crate::types::ServiceConfigId;
+use anyhow::Result;
+use rmcp::model::{
+    CallToolRequestParams, CallToolResult, ClientCapabilities, ClientInfo,
+    CreateMessageRequestMethod, CreateMessageRequestParams, CreateMessageResult, JsonObject,
+};
+use rmcp::transport::StreamableHttpClientTransport;
+use rmcp::{
+    ClientHandler, ErrorData as McpError, ServiceExt,
+    model::{ServerInfo, Tool as McpTool},
+    service::{RequestContext, RoleClient, RunningService as RmcpRunningService},
+    transport::{ConfigureCommandExt, TokioChildProcess},
+};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+use tokio::sync::Mutex;
+use tracing::{error, info, warn};
+
+/// Wrapper for a running MCP service client.
+///
+/// This holds the SurrealDB id for the service as well as the rmcp `RunningService`
+/// handle used to talk MCP (initialize, list_tools, call_tool, etc.).
+pub struct RunningService {
+    pub client: RmcpRunningService<RoleClient, DownstreamClient>,
+    /// Process id of a stdio service's subprocess; `None` for HTTP services.
+    pub pid: Option<u32>,
+}
+
+impl RunningService {
+    /// Whether the session has ended, by cancellation or because the service
+    /// closed its end of the transport, e.g. when its process exited.
+    pub fn is_closed(&self) -> bool {
+        // rmcp only marks the client closed on cancellation; the peer channel
+        // closes once the serve loop stops on transport EOF
+        self.client.is_closed() || self.client.peer().is_transport_closed()
+    }
+
+    /// Number of tool calls to this service that haven't finished yet.
+    pub fn active_calls(&self) -> usize {
+        self.client
+            .service()
+            .active_tools
+            .lock()
+            .expect("active tools lock poisoned")
+            .len()
+    }
+
+    /// Send `SIGHUP` to the service's subprocess, asking it to reload.
+    ///
+    /// Returns `Ok(false)` when there is no subprocess to signal, e.g. for
+    /// HTTP services or on platforms without signals.
+    pub fn signal_reload(&self) -> std::io::Result<bool> {
+        #[cfg(unix)]
+        if let Some(pid) = self.pid {
+            let pid = libc::pid_t::try_from(pid).map_err(std::io::Error::other)?;
+            // SAFETY: `kill` has no memory-safety preconditions
+            if unsafe { libc::kill(pid, libc::SIGHUP) } != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            return Ok(true);
+        }
+        Ok(false)
+    }
+}
+
+/// Client-side handler for the orchestrator's connections to downstream services.
+///
+/// Offers `sampling` to every service and relays `sampling/createMessage`
+/// requests to the client session whose tool call the service is handling.
+/// Services are started before any client connects, so whether that client
+/// supports sampling is checked per request; if it does not, the request
+/// fails as if sampling was never offered.
+///
+/// Relayed requests have their system prompt prefixed with the service's
+/// `mcp.json` id and the tool being called, unless the service's
+/// `sampling_provenance` is off.
+#[derive(Clone)]
+pub struct DownstreamClient {
+    /// `mcp.json` id of the service, named in the provenance prefix.
+    service_id: Option<ServiceConfigId>,
+    /// Provenance prefix template; `None` relays sampling requests unchanged.
+    sampling_provenance: Option<String>,
+    /// The tool calls currently in flight on this service.
+    active_tools: Arc<std::sync::Mutex<Vec<ActiveCall>>>,
+    next_call_id: Arc<std::sync::atomic::AtomicU64>,
+}
+
+/// A tool call in flight on a downstream service.
+struct ActiveCall {
+    id: u64,
+    tool_name: String,
+    /// Forwarder of the session that made the call, if any.
+    sampling: Option<Arc<SamplingForwarder>>,
+}
+
+impl Default for DownstreamClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DownstreamClient {
+    pub fn new() -> Self {
+        Self {
+            service_id: None,
+            sampling_provenance: Some(DEFAULT_SAMPLING_PROVENANCE_PREFIX.to_string()),
+            active_tools: Arc::default(),
+            next_call_id: Arc::default(),
+        }
+    }
+
+    pub fn with_service_id(mut self, service_id: ServiceConfigId) -> Self {
+        self.service_id = Some(service_id);
+        self
+    }
+
+    /// Set the provenance prefix template, or turn the prefix off with `None`.
+    pub fn with_sampling_provenance(mut self, prefix: Option<&str>) -> Self {
+        self.sampling_provenance = prefix.map(str::to_string);
+        self
+    }
+
+    /// Prefix a sampling request with where it came from, if enabled.
+    fn with_provenance(&self, params: CreateMessageRequestParams) -> CreateMessageRequestParams {
+        let Some(prefix) = &self.sampling_provenance else {
+            return params;
+        };
+        let service_id = self
+            .service_id
+            .as_ref()
+            .map_or("unknown service", ServiceConfigId::as_str);
+        let tool_name = self.requesting_tool();
+        wrap_sampling_with_provenance(params, prefix, service_id, tool_name.as_deref())
+    }
+
+    /// Record a tool call, made on behalf of the current session, as active
+    /// until the returned guard is dropped.
+    fn begin_tool_call(&self, tool_name: &str) -> ActiveToolCall {
+        let id = self
+            .next_call_id
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        self.active_tools
+            .lock()
+            .expect("active tools lock poisoned")
+            .push(ActiveCall {
+                id,
+                tool_name: tool_name.to_string(),
+                sampling: current_session_sampling(),
+            });
+        ActiveToolCall {
+            active_tools: self.active_tools.clone(),
+            id,
+        }
+    }
+
+    /// The tool a sampling request most likely came from. Requests don't say
+    /// which call they belong to, so this is only known while every active
+    /// call is to the same tool.
+    fn requesting_tool(&self) -> Option<String> {
+        let active_tools = self
+            .active_tools
+            .lock()
+            .expect("active tools lock poisoned");
+        let first = active_tools.first()?;
+        active_tools
+            .iter()
+            .all(|call| call.tool_name == first.tool_name)
+            .then(|| first.tool_name.clone())
+    }
+
+    /// The session a sampling request belongs to. Like
+    /// [`requesting_tool`](Self::requesting_tool), this is only known while
+    /// every active call was made by the same session; a request is never
+    /// sent to a client that might not have caused it.
+    fn requesting_session(&self) -> Result<Arc<SamplingForwarder>, SamplingError> {
+        let active_tools = self
+            .active_tools
+            .lock()
+            .expect("active tools lock poisoned");
+        let first = active_tools
+            .first()
+            .and_then(|call| call.sampling.clone())
+            .ok_or(SamplingError::Unattributed)?;
+        active_tools
+            .iter()
+            .all(|call| {
+                call.sampling
+                    .as_ref()
+                    .is_some_and(|sampling| Arc::ptr_eq(sampling, &first))
+            })
+            .then_some(first)
+            .ok_or(SamplingError::Unattributed)
+    }
+}
+
+/// Removes a tool call from [`DownstreamClient`]'s active calls when dropped.
+struct ActiveToolCall {
+    active_tools: Arc<std::sync::Mutex<Vec<ActiveCall>>>,
+    id: u64,
+}
+
+impl Drop for ActiveToolCall {
+    fn drop(&mut self) {
+        self.active_tools
+            .lock()
+            .expect("active tools lock poisoned")
+            .retain(|call| call.id != self.id);
+    }
+}
+
+impl ClientHandler for DownstreamClient {
+    async fn create_message(
+        &self,
+        params: CreateMessageRequestParams,
+        _context: RequestContext<RoleClient>,
+    ) -> Result<CreateMessageResult, McpError> {
+        let params = self.with_provenance(params);
+
+        let forwarder = self
+            .requesting_session()
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        forwarder
+            .forward_sampling_request(params)
+            .await
+            .map_err(|e| match e {
+                SamplingError::Unsupported => {
+                    McpError::method_not_found::<CreateMessageRequestMethod>()
+                }
+                other => McpError::internal_error(other.to_string(), None),
+            })
+    }
+
+    fn get_info(&self) -> ClientInfo {
+        ClientInfo {
+            capabilities: ClientCapabilities::builder().enable_sampling().build(),
+            ..ClientInfo::default()
+        }
+    }
+}
+
+/// Running services keyed by their SurrealDB service id (as a string).
+///
+/// Shared between the orchestrator, the prompt/resource forwarders and the
+/// service supervisor so that a restarted service is picked up everywhere.
+pub type SharedRunningServices = Arc<Mutex<HashMap<String, Arc<RunningService>>>>;
+
+/// How many times each service failed to finish its `initialize` handshake
+/// within its `startup_timeout_secs`, keyed by the server name in `mcp.json`.
+///
+/// Cloning shares the counts, so the supervisor's restarts are counted
+/// alongside discovery.
+#[derive(Debug, Clone, Default)]
+pub struct StartupFailures {
+    counts: Arc<std::sync::Mutex<BTreeMap<String, u64>>>,
+}
+
+impl StartupFailures {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn record(&self, service: &str) {
+        *self
+            .counts
+            .lock()
+            .expect("startup failures lock poisoned")
+            .entry(service.to_string())
+            .or_default() += 1;
+    }
+
+    /// Failures of one service so far.
+    pub fn get(&self, service: &str) -> u64 {
+        self.counts
+            .lock()
+            .expect("startup failures lock poisoned")
+            .get(service)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Failures of every service that has failed at least once.
+    pub fn snapshot(&self) -> BTreeMap<String, u64> {
+        self.counts
+            .lock()
+            .expect("startup failures lock poisoned")
+            .clone()
+    }
+}
+
+/// Run the `initialize` handshake, giving up after `timeout`.
+///
+/// Returns `Ok(None)` on timeout. Dropping the unfinished handshake drops its
+/// transport, which for stdio services kills the subprocess with `SIGKILL`.
+async fn serve_with_timeout<T, E, A>(
+    id: &crate::types::ServiceConfigId,
+    handler: DownstreamClient,
+    transport: T,
+    pid: Option<u32>,
+    timeout: Duration,
+    failures: &StartupFailures,
+) -> Result<Option<RunningService>>
+where
+    T: rmcp::transport::IntoTransport<RoleClient, E, A>,
+    E: std::error::Error + Send + Sync + 'static,
+{
+    let started = Instant::now();
+    match tokio::time::timeout(timeout, handler.serve(transport)).await {
+        Ok(client) => Ok(Some(RunningService {
+            client: client?,
+            pid,
+        })),
+        Err(_) => {
+            error!(
+                "MCP service `{id}` did not finish starting after {:.1}s, skipping it",
+                started.elapsed().as_secs_f64()
+            );
+            failures.record(id.as_str());
+            Ok(None)
+        }
+    }
+}
+
+pub async fn start_stdio_service(
+    cfg: &McpServiceConfig,
+    handler: DownstreamClient,
+    failures: &StartupFailures,
+) -> Result<Option<RunningService>> {
+    if let McpServiceConfig::Stdio {
+        id,
+        command,
+        args,
+        env,
+        disabled,
+        ..
+    } = cfg
+    {
+        if *disabled {
+            info!("Skipping disabled MCP stdio service `{id}`");
+            return Ok(None);
+        }
+
+        // Configs added after startup skip `McpConfigs` validation
+        cfg.check_command(allowed_mcp_commands().as_deref())
+            .map_err(|e| anyhow::anyhow!("Refusing to start MCP service `{id}`: {e}"))?;
+
+        info!("Starting MCP stdio service `{id}` via rmcp");
+
+        let mut cmd = Command::new(command);
+        if !args.is_empty() {
+            cmd.args(args.iter().cloned());
+        }
+        if !env.is_empty() {
+            cmd.envs(env.iter());
+        }
+
+        let child = TokioChildProcess::new(cmd.configure(|_cmd| {
+            // extra configuration if needed
+        }))?;
+
+        let pid = child.id();
+        serve_with_timeout(id, handler, child, pid, cfg.startup_timeout(), failures).await
+    } else {
+        Ok(None)
+    }
+}
+
+pub async fn start_http_service(
+    cfg: &McpServiceConfig,
+    handler: DownstreamClient,
+    failures: &StartupFailures,
+) -> Result<Option<RunningService>> {
+    if let McpServiceConfig::Http {
+        id,
+        url,
+        headers: _,
+        disabled,
+        ..
+    } = cfg
+    {
+        if *disabled {
+            info!("Skipping disabled MCP HTTP service `{id}`");
+            return Ok(None);
+        }
+
+        info!("Starting MCP HTTP service `{id}` at `{url}` via rmcp streamable HTTP");
+
+        // Build HTTP transport (Result -> WorkerTransport)
+        let transport = StreamableHttpClientTransport::from_uri(url.as_str());
+
+        serve_with_timeout(
+            id,
+            handler,
+            transport,
+            None,
+            cfg.startup_timeout(),
+            failures,
+        )
+        .await
+    } else {
+        Ok(None)
+    }
+}
+
+/// Start a service and run its `initialize` handshake.
+///
+/// Returns `Ok(None)` if the service is disabled or doesn't finish starting
+/// within its `startup_timeout_secs`; timeouts are counted in `failures`.
+pub async fn start_service(
+    cfg: &McpServiceConfig,
+    handler: DownstreamClient,
+    failures: &StartupFailures,
+) -> Result<Option<RunningService>> {
+    let handler = handler
+        .with_service_id(cfg.id().clone())
+        .with_sampling_provenance(cfg.sampling_provenance_prefix());
+    match cfg {
+        McpServiceConfig::Stdio { .. } => start_stdio_service(cfg, handler, failures).await,
+        McpServiceConfig::Http { .. } => start_http_service(cfg, handler, failures).await,
+    }
+}
+
+/// Fetch service info + tools and normalize them using rmcp.
+pub async fn inspect_service(running: &RunningService) -> Result<(ServerInfo, Vec<McpTool>)> {
+    // Basic metadata about the server from the MCP `initialize` handshake.
+    // `peer_info` returns an Option<&ServerInfo>, so fall back to a minimal
+    // placeholder if for some reason it is not set.
+    let server_info = running.client.peer_info().cloned().unwrap_or_default();
+
+    // List tools via rmcp. The exact return type is `ListToolsResult`, which
+    // contains a `tools: Vec<Tool>` field.
+    let tools = running.client.list_tools(Default::default()).await?.tools;
+
+    if tools.is_empty() {
+        warn!(
+            "Service `{}` reported no tools",
+            server_info.server_info.name
+        );
+    }
+
+    Ok((server_info, tools))
+}
+
+/// Call a tool on a running service.
+///
+/// A tool that fails reports it with `is_error` on the returned result rather
+/// than as an `Err`, which is kept for protocol and transport failures.
+pub async fn call_tool(
+    running: &RunningService,
+    tool_name: &str,
+    args: JsonObject,
+) -> Result<CallToolResult> {
+    let request = CallToolRequestParams {
+        name: Cow::from(tool_name.to_string()),
+        arguments: Some(args),
+        meta: None,
+        task: None,
+    };
+
+    let _active = running.client.service().begin_tool_call(tool_name);
+    Ok(running.client.call_tool(request).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[tokio::test]
+    async fn test_start_service_times_out_and_counts_failure() {
+        // `sleep` never answers `initialize`
+        let cfg = McpServiceConfig::Stdio {
+            id: ServiceConfigId::new("slow"),
+            command: "sleep".to_string(),
+            args: vec!["30".to_string()],
+            env: BTreeMap::new(),
+            disabled: false,
+            auto_approve: vec![],
+            disabled_tools: vec![],
+            conflict_resolution: Default::default(),
+            elicitation_timeout_secs: 300,
+            sampling_provenance: true,
+            sampling_provenance_prefix: None,
+            startup_timeout_secs: 1,
+            trust_level: Default::default(),
+            security: Default::default(),
+            command_allowlist: None,
+        };
+        let failures = StartupFailures::new();
+        let handler = DownstreamClient::new();
+
+        let started = Instant::now();
+        let running = start_service(&cfg, handler, &failures).await.unwrap();
+
+        assert!(running.is_none());
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert_eq!(failures.get("slow"), 1);
+        assert_eq!(
+            failures.snapshot(),
+            BTreeMap::from([("slow".to_string(), 1)])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sampling_goes_to_the_calling_session() {
+        use crate::sampling::with_session_sampling;
+
+        let client = DownstreamClient::new();
+        let first = Arc::new(SamplingForwarder::new());
+        let second = Arc::new(SamplingForwarder::new());
+        assert!(client.requesting_session().is_err());
+
+        let outside_session = client.begin_tool_call("search");
+        assert!(client.requesting_session().is_err());
+        drop(outside_session);
+
+        let first_call =
+            with_session_sampling(first.clone(), async { client.begin_tool_call("search") }).await;
+        let requesting = client.requesting_session().unwrap();
+        assert!(Arc::ptr_eq(&requesting, &first));
+
+        // Another session's call on the same service makes requests ambiguous
+        let second_call =
+            with_session_sampling(second.clone(), async { client.begin_tool_call("search") }).await;
+        assert!(client.requesting_session().is_err());
+
+        drop(first_call);
+        let requesting = client.requesting_session().unwrap();
+        assert!(Arc::ptr_eq(&requesting, &second));
+        drop(second_call);
+        assert!(client.requesting_session().is_err());
+    }
+
+    #[test]
+    fn test_sampling_provenance_names_configured_service() {
+        let params = CreateMessageRequestParams {
+            meta: None,
+            task: None,
+            messages: vec![],
+            model_preferences: None,
+            system_prompt: None,
+            include_context: None,
+            temperature: None,
+            max_tokens: 100,
+            stop_sequences: None,
+            metadata: None,
+        };
+
+        let client = DownstreamClient::new().with_service_id(ServiceConfigId::new("github"));
+        let _call = client.begin_tool_call("search");
+        let wrapped = client.with_provenance(params.clone());
+        assert_eq!(
+            wrapped.system_prompt.as_deref(),
+            Some("[Requested by github/search]")
+        );
+
+        let client = client.with_sampling_provenance(Some("Sent by {origin}:"));
+        let wrapped = client.with_provenance(params.clone());
+        assert_eq!(
+            wrapped.system_prompt.as_deref(),
+            Some("Sent by github/search:")
+        );
+
+        let client = client.with_sampling_provenance(None);
+        assert_eq!(client.with_provenance(params).system_prompt, None);
+    }
+}
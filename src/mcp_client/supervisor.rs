@@ -0,0 +1,647 @@
+//! Supervision and automatic restart of stdio MCP services.
+//!
+//! rmcp's `TokioChildProcess` transport owns the spawned child process, so we
+//! cannot call `Child::wait()` on it directly. Instead the supervisor polls the
+//! client session: it closes as soon as the child exits and its stdio pipes
+//! are torn down. Exited services are restarted with exponential backoff and
+//! their tool schemas are refreshed, since the service may have been upgraded
+//! in the meantime.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use surrealdb::engine::any::Any;
+use surrealdb::{RecordId, Surreal};
+use tokio::sync::Mutex;
+use tokio::task::{JoinHandle, JoinSet};
+use tokio::time::MissedTickBehavior;
+use tracing::{error, info, warn};
+
+use super::{
+    DownstreamClient, SharedRunningServices, StartupFailures, inspect_service, start_service,
+};
+use crate::config::McpServiceConfig;
+use crate::db::QueryBuilder;
+use crate::db::schema::{
+    AuditAction, AuditLogCreate, BlockReason, CreateToolRecord, DiscoverySource,
+};
+use crate::prompts::PromptForwarder;
+
+/// Backoff and retry limits used when restarting an exited service.
+#[derive(Debug, Clone)]
+pub struct RestartPolicy {
+    /// Delay before the first restart attempt.
+    pub initial_backoff: Duration,
+    /// Upper bound for the delay between attempts.
+    pub max_backoff: Duration,
+    /// Number of restart attempts before the service's tools are blocked.
+    pub max_restart_attempts: u32,
+    /// How often running services are checked for unexpected exits.
+    pub poll_interval: Duration,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            max_restart_attempts: 5,
+            poll_interval: Duration::from_secs(5),
+        }
+    }
+}
+
+impl RestartPolicy {
+    /// Delay before the given zero-based restart attempt.
+    ///
+    /// Doubles on every attempt (1s, 2s, 4s, 8s, ...) and is capped at `max_backoff`.
+    pub fn backoff_for(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt);
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// A service the supervisor knows how to restart.
+#[derive(Debug, Clone)]
+struct SupervisedService {
+    service_id: RecordId,
+    config: McpServiceConfig,
+    /// Source recorded on tools that first appear after a restart.
+    discovery_source: DiscoverySource,
+}
+
+/// Watches stdio MCP services and restarts them when they exit unexpectedly.
+pub struct ServiceSupervisor {
+    db: Surreal<Any>,
+    running_services: SharedRunningServices,
+    watched: Mutex<HashMap<String, SupervisedService>>,
+    policy: RestartPolicy,
+    task: std::sync::Mutex<Option<JoinHandle<()>>>,
+    /// Refreshes a restarted service's prompts, if set.
+    prompt_forwarder: Option<Arc<PromptForwarder>>,
+    /// Counts restarts that time out during startup.
+    startup_failures: StartupFailures,
+    /// When each running service's session was last checked.
+    health_checks: std::sync::Mutex<HashMap<String, DateTime<Utc>>>,
+}
+
+impl ServiceSupervisor {
+    /// Create a supervisor over the shared running-services map.
+    pub fn new(
+        db: Surreal<Any>,
+        running_services: SharedRunningServices,
+        policy: RestartPolicy,
+    ) -> Self {
+        Self {
+            db,
+            running_services,
+            watched: Mutex::new(HashMap::new()),
+            policy,
+            task: std::sync::Mutex::new(None),
+            prompt_forwarder: None,
+            startup_failures: StartupFailures::new(),
+            health_checks: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Refresh a service's prompts through `forwarder` after restarting it.
+    pub fn with_prompt_forwarder(mut self, forwarder: Arc<PromptForwarder>) -> Self {
+        self.prompt_forwarder = Some(forwarder);
+        self
+    }
+
+    /// Count restarts that time out in `failures` instead of a private counter.
+    pub fn with_startup_failures(mut self, failures: StartupFailures) -> Self {
+        self.startup_failures = failures;
+        self
+    }
+
+    /// Get the restart policy.
+    pub fn policy(&self) -> &RestartPolicy {
+        &self.policy
+    }
+
+    /// Start watching a service.
+    ///
+    /// Only stdio services are supervised; HTTP services are not owned by the
+    /// orchestrator and are ignored.
+    pub async fn watch(
+        &self,
+        service_id: &RecordId,
+        config: McpServiceConfig,
+        discovery_source: DiscoverySource,
+    ) {
+        if !matches!(config, McpServiceConfig::Stdio { .. }) {
+            return;
+        }
+
+        self.watched.lock().await.insert(
+            service_id.to_string(),
+            SupervisedService {
+                service_id: service_id.clone(),
+                config,
+                discovery_source,
+            },
+        );
+    }
+
+    /// Stop watching a service.
+    pub async fn unwatch(&self, service_id: &RecordId) {
+        self.watched.lock().await.remove(&service_id.to_string());
+    }
+
+    /// Number of services currently being watched.
+    pub async fn watched_count(&self) -> usize {
+        self.watched.lock().await.len()
+    }
+
+    /// When [`Self::check_services`] last checked each service, by service id.
+    /// Services never checked are missing.
+    pub fn health_checks(&self) -> HashMap<String, DateTime<Utc>> {
+        self.health_checks
+            .lock()
+            .expect("health checks lock poisoned")
+            .clone()
+    }
+
+    /// Spawn the background watch loop. Calling this more than once is a no-op.
+    pub fn start(self: &Arc<Self>) {
+        let mut task = self.task.lock().expect("supervisor task lock poisoned");
+        if task.is_some() {
+            return;
+        }
+
+        let supervisor = Arc::clone(self);
+        *task = Some(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(supervisor.policy.poll_interval);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                supervisor.check_services().await;
+            }
+        }));
+    }
+
+    /// Abort the background watch loop, if running.
+    pub fn stop(&self) {
+        let handle = self
+            .task
+            .lock()
+            .expect("supervisor task lock poisoned")
+            .take();
+        if let Some(handle) = handle {
+            handle.abort();
+        }
+    }
+
+    /// Check every watched service once and restart those whose session has closed.
+    ///
+    /// The check time of every running service is recorded for [`Self::health_checks`].
+    /// Each service is restarted in its own task, so one backing off doesn't
+    /// hold up the others. Returns the number successfully restarted.
+    pub async fn check_services(self: &Arc<Self>) -> usize {
+        let exited: Vec<(String, SupervisedService)> = {
+            let watched = self.watched.lock().await;
+            let running = self.running_services.lock().await;
+            let checked_at = Utc::now();
+            self.health_checks
+                .lock()
+                .expect("health checks lock poisoned")
+                .extend(running.keys().map(|key| (key.clone(), checked_at)));
+            watched
+                .iter()
+                .filter(|(key, _)| {
+                    running
+                        .get(*key)
+                        .map(|svc| svc.is_closed())
+                        .unwrap_or(false)
+                })
+                .map(|(key, svc)| (key.clone(), svc.clone()))
+                .collect()
+        };
+
+        let mut restarts = JoinSet::new();
+        for (key, service) in exited {
+            let supervisor = Arc::clone(self);
+            restarts.spawn(async move { supervisor.restart(&key, &service).await });
+        }
+
+        let mut restarted = 0;
+        while let Some(result) = restarts.join_next().await {
+            match result {
+                Ok(true) => restarted += 1,
+                Ok(false) => {}
+                Err(e) => error!("MCP service restart task failed: {}", e),
+            }
+        }
+        restarted
+    }
+
+    /// Restart a single service, retrying with backoff until the policy gives up.
+    async fn restart(&self, key: &str, service: &SupervisedService) -> bool {
+        warn!("MCP service `{}` exited unexpectedly, restarting", key);
+
+        for attempt in 0..self.policy.max_restart_attempts {
+            tokio::time::sleep(self.policy.backoff_for(attempt)).await;
+
+            match self.reconnect(key, service).await {
+                Ok(tool_count) => {
+                    info!(
+                        "Restarted MCP service `{}` after {} attempt(s), refreshed {} tools",
+                        key,
+                        attempt + 1,
+                        tool_count
+                    );
+                    self.audit(
+                        AuditAction::ServiceRestarted,
+                        &service.service_id,
+                        serde_json::json!({
+                            "attempts": attempt + 1,
+                            "tools_refreshed": tool_count,
+                        }),
+                    )
+                    .await;
+                    self.refresh_prompts(key).await;
+                    return true;
+                }
+                Err(e) => {
+                    warn!(
+                        "Restart attempt {}/{} for MCP service `{}` failed: {}",
+                        attempt + 1,
+                        self.policy.max_restart_attempts,
+                        key,
+                        e
+                    );
+                }
+            }
+        }
+
+        error!(
+            "Giving up on MCP service `{}` after {} restart attempts, blocking its tools",
+            key, self.policy.max_restart_attempts
+        );
+
+        self.watched.lock().await.remove(key);
+        self.running_services.lock().await.remove(key);
+
+        if let Err(e) = QueryBuilder::block_service_tools(
+            &self.db,
+            &service.service_id,
+            BlockReason::ServiceUnavailable,
+        )
+        .await
+        {
+            warn!("Failed to block tools for service `{}`: {}", key, e);
+        }
+
+        self.audit(
+            AuditAction::ServiceRestartFailed,
+            &service.service_id,
+            serde_json::json!({
+                "attempts": self.policy.max_restart_attempts,
+            }),
+        )
+        .await;
+
+        false
+    }
+
+    /// Start the service again, refresh its tools and swap it into the shared map.
+    async fn reconnect(&self, key: &str, service: &SupervisedService) -> Result<usize> {
+        // Each session tracks its own in-flight calls, so the restarted
+        // service gets a fresh handler rather than sharing one
+        let running = start_service(
+            &service.config,
+            DownstreamClient::new(),
+            &self.startup_failures,
+        )
+        .await?
+        .ok_or_else(|| anyhow!("service is disabled or timed out starting"))?;
+
+        let (_, tools) = inspect_service(&running).await?;
+        let records: Vec<CreateToolRecord> = tools
+            .iter()
+            .map(|tool| {
+                CreateToolRecord::from_mcp_tool(
+                    &service.service_id,
+                    tool,
+                    service.discovery_source.clone(),
+                )
+            })
+            .collect();
+        let refreshed =
+            QueryBuilder::refresh_service_tools(&self.db, &service.service_id, &records).await?;
+        // Refreshing clears the status, so tools still missing variables are blocked again
+        for record in &records {
+            if let Some(name) = service.config.missing_env_var(&record.required_env_vars)
+                && let Some(tool) =
+                    QueryBuilder::find_tool_by_name(&self.db, &service.service_id, &record.name)
+                        .await?
+            {
+                QueryBuilder::block_tool(
+                    &self.db,
+                    &tool.id,
+                    BlockReason::MissingEnvVar(name.to_string()),
+                )
+                .await?;
+            }
+        }
+
+        self.running_services
+            .lock()
+            .await
+            .insert(key.to_string(), Arc::new(running));
+
+        Ok(refreshed)
+    }
+
+    /// Replace a restarted service's prompts; the old ones are kept on failure.
+    async fn refresh_prompts(&self, key: &str) {
+        let Some(forwarder) = &self.prompt_forwarder else {
+            return;
+        };
+        match forwarder.refresh_service_prompts(key).await {
+            Ok(result) => info!(
+                "Refreshed prompts of MCP service `{}`: {} added, {} removed",
+                key, result.added, result.removed
+            ),
+            Err(e) => warn!("Failed to refresh prompts of MCP service `{}`: {}", key, e),
+        }
+    }
+
+    async fn audit(&self, action: AuditAction, service_id: &RecordId, details: serde_json::Value) {
+        let entry = AuditLogCreate {
+            user_id: None,
+            action: action.as_str().to_string(),
+            resource_type: "service".to_string(),
+            resource_id: Some(service_id.to_string()),
+            details: Some(details),
+            ip_address: None,
+            user_agent: None,
+        };
+
+        if let Err(e) = QueryBuilder::create_audit_log(&self.db, entry).await {
+            warn!("Failed to write audit log: {}", e);
+        }
+    }
+}
+
+impl Drop for ServiceSupervisor {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{DatabaseConfig, create_connection};
+    use crate::types::ServiceConfigId;
+    use std::collections::BTreeMap;
+
+    async fn setup() -> Arc<ServiceSupervisor> {
+        setup_with_policy(RestartPolicy::default()).await
+    }
+
+    async fn setup_with_policy(policy: RestartPolicy) -> Arc<ServiceSupervisor> {
+        let db = create_connection(DatabaseConfig {
+            url: "memory".to_string(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        Arc::new(ServiceSupervisor::new(
+            db,
+            Arc::new(Mutex::new(HashMap::new())),
+            policy,
+        ))
+    }
+
+    /// A stdio MCP server in `sh` that answers `initialize` and `tools/list`.
+    const FIXTURE_SCRIPT: &str = r#"
+while IFS= read -r line; do
+  id=$(printf '%s' "$line" | sed -n 's/.*"id":\([0-9]*\).*/\1/p')
+  case "$line" in
+    *'"initialize"'*)
+      printf '{"jsonrpc":"2.0","id":%s,"result":{"protocolVersion":"2025-03-26","capabilities":{"tools":{}},"serverInfo":{"name":"fixture","version":"0.1.0"}}}\n' "$id" ;;
+    *'"tools/list"'*)
+      printf '{"jsonrpc":"2.0","id":%s,"result":{"tools":[{"name":"echo","inputSchema":{"type":"object"}}]}}\n' "$id" ;;
+  esac
+done
+"#;
+
+    fn fixture_config(id: &str) -> McpServiceConfig {
+        McpServiceConfig::Stdio {
+            id: ServiceConfigId::new(id),
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), FIXTURE_SCRIPT.to_string()],
+            env: BTreeMap::new(),
+            disabled: false,
+            auto_approve: vec![],
+            disabled_tools: vec![],
+            conflict_resolution: Default::default(),
+            elicitation_timeout_secs: 300,
+            sampling_provenance: true,
+            sampling_provenance_prefix: None,
+            startup_timeout_secs: 10,
+            trust_level: Default::default(),
+            security: Default::default(),
+            command_allowlist: None,
+        }
+    }
+
+    fn stdio_config() -> McpServiceConfig {
+        McpServiceConfig::Stdio {
+            id: ServiceConfigId::new("fs"),
+            command: "mcp-fs".to_string(),
+            args: vec![],
+            env: BTreeMap::new(),
+            disabled: false,
+            auto_approve: vec![],
+            disabled_tools: vec![],
+            conflict_resolution: Default::default(),
+            elicitation_timeout_secs: 300,
+            sampling_provenance: true,
+            sampling_provenance_prefix: None,
+            startup_timeout_secs: 30,
+            trust_level: Default::default(),
+            security: Default::default(),
+            command_allowlist: None,
+        }
+    }
+
+    fn http_config() -> McpServiceConfig {
+        McpServiceConfig::Http {
+            id: ServiceConfigId::new("remote"),
+            url: "http://localhost:3000/mcp".to_string(),
+            headers: BTreeMap::new(),
+            disabled: false,
+            auto_approve: vec![],
+            disabled_tools: vec![],
+            conflict_resolution: Default::default(),
+            elicitation_timeout_secs: 300,
+            sampling_provenance: true,
+            sampling_provenance_prefix: None,
+            startup_timeout_secs: 30,
+            trust_level: Default::default(),
+            security: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_backoff_doubles_and_caps() {
+        let policy = RestartPolicy::default();
+        assert_eq!(policy.backoff_for(0), Duration::from_secs(1));
+        assert_eq!(policy.backoff_for(1), Duration::from_secs(2));
+        assert_eq!(policy.backoff_for(2), Duration::from_secs(4));
+        assert_eq!(policy.backoff_for(3), Duration::from_secs(8));
+        assert_eq!(policy.backoff_for(6), Duration::from_secs(60));
+        assert_eq!(policy.backoff_for(40), Duration::from_secs(60));
+    }
+
+    #[tokio::test]
+    async fn test_watch_only_supervises_stdio_services() {
+        let supervisor = setup().await;
+
+        supervisor
+            .watch(
+                &RecordId::from(("service", "fs")),
+                stdio_config(),
+                DiscoverySource::default(),
+            )
+            .await;
+        supervisor
+            .watch(
+                &RecordId::from(("service", "remote")),
+                http_config(),
+                DiscoverySource::default(),
+            )
+            .await;
+        assert_eq!(supervisor.watched_count().await, 1);
+
+        supervisor.unwatch(&RecordId::from(("service", "fs"))).await;
+        assert_eq!(supervisor.watched_count().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_check_services_ignores_services_that_are_not_running() {
+        let supervisor = setup().await;
+        supervisor
+            .watch(
+                &RecordId::from(("service", "fs")),
+                stdio_config(),
+                DiscoverySource::default(),
+            )
+            .await;
+
+        // Nothing in the running map, so there is nothing to restart
+        assert_eq!(supervisor.check_services().await, 0);
+        assert_eq!(supervisor.watched_count().await, 1);
+        assert!(supervisor.health_checks().is_empty());
+    }
+
+    /// Start the fixture as `name`, add it to the running map and watch it.
+    /// Returns the service's map key and process id.
+    #[cfg(unix)]
+    async fn start_fixture(supervisor: &ServiceSupervisor, name: &str) -> (String, u32) {
+        let service_id = RecordId::from(("service", name));
+        let key = service_id.to_string();
+        let config = fixture_config(name);
+
+        let running = start_service(
+            &config,
+            DownstreamClient::new(),
+            &supervisor.startup_failures,
+        )
+        .await
+        .unwrap()
+        .expect("fixture should start");
+        let pid = running.pid.unwrap();
+        supervisor
+            .running_services
+            .lock()
+            .await
+            .insert(key.clone(), Arc::new(running));
+        supervisor
+            .watch(&service_id, config, DiscoverySource::default())
+            .await;
+        (key, pid)
+    }
+
+    /// Kill a fixture and wait for its session to close.
+    #[cfg(unix)]
+    async fn kill_fixture(supervisor: &ServiceSupervisor, key: &str, pid: u32) {
+        // SAFETY: signalling a fixture process this test spawned
+        assert_eq!(unsafe { libc::kill(pid as i32, libc::SIGKILL) }, 0);
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while !supervisor.running_services.lock().await[key].is_closed() {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .expect("session should close once the fixture is killed");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_check_services_restarts_killed_service() {
+        let supervisor = setup_with_policy(RestartPolicy {
+            initial_backoff: Duration::from_millis(10),
+            ..Default::default()
+        })
+        .await;
+        let (key, old_pid) = start_fixture(&supervisor, "fixture").await;
+        kill_fixture(&supervisor, &key, old_pid).await;
+
+        let before = Utc::now();
+        assert_eq!(supervisor.check_services().await, 1);
+        assert!(supervisor.health_checks()[&key] >= before);
+
+        let restarted = supervisor.running_services.lock().await[&key].clone();
+        assert!(!restarted.is_closed());
+        assert_ne!(restarted.pid, Some(old_pid));
+        assert!(
+            QueryBuilder::find_tool_by_name(
+                &supervisor.db,
+                &RecordId::from(("service", "fixture")),
+                "echo"
+            )
+            .await
+            .unwrap()
+            .is_some()
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_restarted_services_get_their_own_client() {
+        let supervisor = setup_with_policy(RestartPolicy {
+            initial_backoff: Duration::from_millis(10),
+            ..Default::default()
+        })
+        .await;
+        let (first, first_pid) = start_fixture(&supervisor, "first").await;
+        let (second, second_pid) = start_fixture(&supervisor, "second").await;
+        kill_fixture(&supervisor, &first, first_pid).await;
+        kill_fixture(&supervisor, &second, second_pid).await;
+
+        assert_eq!(supervisor.check_services().await, 2);
+
+        let running = supervisor.running_services.lock().await;
+        let (first, second) = (&running[&first], &running[&second]);
+        assert!(!first.is_closed() && !second.is_closed());
+        // A call in flight on one service must not show up on the other
+        assert!(!Arc::ptr_eq(
+            &first.client.service().active_tools,
+            &second.client.service().active_tools
+        ));
+        let _call = first.client.service().begin_tool_call("echo");
+        assert_eq!(first.active_calls(), 1);
+        assert_eq!(second.active_calls(), 0);
+    }
+}
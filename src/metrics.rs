@@ -0,0 +1,271 @@
+//! Prometheus metrics served on `GET /metrics`.
+//!
+//! Components keep their own counters and histograms and register them with
+//! the orchestrator's [`MetricsCollector`], which renders every registered
+//! source in the Prometheus text exposition format.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex, RwLock};
+
+/// Content type of the Prometheus text exposition format.
+pub const PROMETHEUS_CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Something that can write its metrics in the Prometheus text format.
+pub trait MetricSource: Send + Sync {
+    /// Append this source's metric families to `out`.
+    fn encode(&self, out: &mut String);
+}
+
+/// Registry of the metric sources rendered on `GET /metrics`.
+#[derive(Default)]
+pub struct MetricsCollector {
+    sources: RwLock<Vec<Arc<dyn MetricSource>>>,
+}
+
+impl MetricsCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a source whose metrics are rendered from now on.
+    pub fn register(&self, source: Arc<dyn MetricSource>) {
+        self.sources
+            .write()
+            .expect("metrics lock poisoned")
+            .push(source);
+    }
+
+    /// Render every registered source in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for source in self.sources.read().expect("metrics lock poisoned").iter() {
+            source.encode(&mut out);
+        }
+        out
+    }
+}
+
+/// A family of counters split by label values.
+pub struct CounterVec {
+    name: &'static str,
+    help: &'static str,
+    label_names: &'static [&'static str],
+    values: Mutex<BTreeMap<Vec<String>, u64>>,
+}
+
+impl CounterVec {
+    pub fn new(
+        name: &'static str,
+        help: &'static str,
+        label_names: &'static [&'static str],
+    ) -> Self {
+        Self {
+            name,
+            help,
+            label_names,
+            values: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Increment the counter for `labels`, given in the order of the label names.
+    pub fn inc(&self, labels: &[&str]) {
+        debug_assert_eq!(labels.len(), self.label_names.len());
+        let key = labels.iter().map(|l| l.to_string()).collect();
+        *self
+            .values
+            .lock()
+            .expect("metrics lock poisoned")
+            .entry(key)
+            .or_default() += 1;
+    }
+
+    /// Current value of the counter for `labels`.
+    #[cfg(test)]
+    pub fn get(&self, labels: &[&str]) -> u64 {
+        let key: Vec<String> = labels.iter().map(|l| l.to_string()).collect();
+        self.values
+            .lock()
+            .expect("metrics lock poisoned")
+            .get(&key)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Append this family to `out`.
+    pub fn encode(&self, out: &mut String) {
+        write_header(out, self.name, self.help, "counter");
+        for (labels, value) in self.values.lock().expect("metrics lock poisoned").iter() {
+            let _ = writeln!(
+                out,
+                "{}{} {}",
+                self.name,
+                format_labels(self.label_names, labels, None),
+                value
+            );
+        }
+    }
+}
+
+/// Bucket counts, sum and count of one labelled histogram.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct HistogramData {
+    /// Observations per bucket, not cumulative; the last slot is `+Inf`.
+    buckets: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+/// A family of histograms split by label values.
+pub struct HistogramVec {
+    name: &'static str,
+    help: &'static str,
+    label_names: &'static [&'static str],
+    /// Upper bounds of the buckets, ascending, without `+Inf`.
+    bounds: &'static [f64],
+    values: Mutex<BTreeMap<Vec<String>, HistogramData>>,
+}
+
+impl HistogramVec {
+    pub fn new(
+        name: &'static str,
+        help: &'static str,
+        label_names: &'static [&'static str],
+        bounds: &'static [f64],
+    ) -> Self {
+        Self {
+            name,
+            help,
+            label_names,
+            bounds,
+            values: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Record `value` in the histogram for `labels`.
+    pub fn observe(&self, labels: &[&str], value: f64) {
+        debug_assert_eq!(labels.len(), self.label_names.len());
+        let key = labels.iter().map(|l| l.to_string()).collect();
+        let mut values = self.values.lock().expect("metrics lock poisoned");
+        let data = values.entry(key).or_insert_with(|| HistogramData {
+            buckets: vec![0; self.bounds.len() + 1],
+            ..Default::default()
+        });
+        let bucket = self
+            .bounds
+            .iter()
+            .position(|bound| value <= *bound)
+            .unwrap_or(self.bounds.len());
+        data.buckets[bucket] += 1;
+        data.sum += value;
+        data.count += 1;
+    }
+
+    /// Number of observations recorded for `labels`.
+    #[cfg(test)]
+    pub fn count(&self, labels: &[&str]) -> u64 {
+        let key: Vec<String> = labels.iter().map(|l| l.to_string()).collect();
+        self.values
+            .lock()
+            .expect("metrics lock poisoned")
+            .get(&key)
+            .map_or(0, |data| data.count)
+    }
+
+    /// Append this family to `out`.
+    pub fn encode(&self, out: &mut String) {
+        write_header(out, self.name, self.help, "histogram");
+        for (labels, data) in self.values.lock().expect("metrics lock poisoned").iter() {
+            let mut cumulative = 0;
+            for (i, observations) in data.buckets.iter().enumerate() {
+                cumulative += observations;
+                let le = self
+                    .bounds
+                    .get(i)
+                    .map_or_else(|| "+Inf".to_string(), |bound| bound.to_string());
+                let _ = writeln!(
+                    out,
+                    "{}_bucket{} {}",
+                    self.name,
+                    format_labels(self.label_names, labels, Some(&le)),
+                    cumulative
+                );
+            }
+            let labels = format_labels(self.label_names, labels, None);
+            let _ = writeln!(out, "{}_sum{} {}", self.name, labels, data.sum);
+            let _ = writeln!(out, "{}_count{} {}", self.name, labels, data.count);
+        }
+    }
+}
+
+pub(crate) fn write_header(out: &mut String, name: &str, help: &str, kind: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+/// Format `{name="value",...}`, adding the histogram bucket's `le` label if given.
+fn format_labels(names: &[&str], values: &[String], le: Option<&str>) -> String {
+    let mut pairs: Vec<String> = names
+        .iter()
+        .zip(values)
+        .map(|(name, value)| format!("{}=\"{}\"", name, escape_label_value(value)))
+        .collect();
+    if let Some(le) = le {
+        pairs.push(format!("le=\"{}\"", le));
+    }
+    if pairs.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", pairs.join(","))
+    }
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counter_vec_encodes_labelled_values() {
+        let counter = CounterVec::new("requests_total", "Requests handled", &["mode"]);
+        counter.inc(&["form"]);
+        counter.inc(&["form"]);
+        counter.inc(&["u\"rl"]);
+        assert_eq!(counter.get(&["form"]), 2);
+        assert_eq!(counter.get(&["missing"]), 0);
+
+        let mut out = String::new();
+        counter.encode(&mut out);
+        assert_eq!(
+            out,
+            "# HELP requests_total Requests handled\n\
+             # TYPE requests_total counter\n\
+             requests_total{mode=\"form\"} 2\n\
+             requests_total{mode=\"u\\\"rl\"} 1\n"
+        );
+    }
+
+    #[test]
+    fn test_histogram_vec_encodes_cumulative_buckets() {
+        let histogram = HistogramVec::new("wait_seconds", "Wait time", &["mode"], &[1.0, 5.0]);
+        histogram.observe(&["form"], 0.5);
+        histogram.observe(&["form"], 3.0);
+        histogram.observe(&["form"], 10.0);
+        assert_eq!(histogram.count(&["form"]), 3);
+
+        let mut out = String::new();
+        histogram.encode(&mut out);
+        assert!(out.contains("# TYPE wait_seconds histogram\n"));
+        assert!(out.contains("wait_seconds_bucket{mode=\"form\",le=\"1\"} 1\n"));
+        assert!(out.contains("wait_seconds_bucket{mode=\"form\",le=\"5\"} 2\n"));
+        assert!(out.contains("wait_seconds_bucket{mode=\"form\",le=\"+Inf\"} 3\n"));
+        assert!(out.contains("wait_seconds_sum{mode=\"form\"} 13.5\n"));
+        assert!(out.contains("wait_seconds_count{mode=\"form\"} 3\n"));
+    }
+}
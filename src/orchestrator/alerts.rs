@@ -0,0 +1,70 @@
+//! Alerts posted to the operator's notification webhook.
+
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::db::{ChangelogEntry, ToolRecord};
+
+/// A rediscovered tool advertises a breaking release it didn't list before.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaChangeAlert {
+    /// Always `schema_change`, so receivers can tell alert kinds apart.
+    pub kind: &'static str,
+    pub tool_id: String,
+    pub tool_name: String,
+    pub service_id: String,
+    pub version: String,
+    pub date: String,
+    pub changes: Vec<String>,
+}
+
+impl SchemaChangeAlert {
+    pub fn new(tool: &ToolRecord, entry: &ChangelogEntry) -> Self {
+        Self {
+            kind: "schema_change",
+            tool_id: tool.id.to_string(),
+            tool_name: tool.name.clone(),
+            service_id: tool.service_id.to_string(),
+            version: entry.version.clone(),
+            date: entry.date.clone(),
+            changes: entry.changes.clone(),
+        }
+    }
+}
+
+/// Posts alerts as JSON to a webhook URL.
+pub struct AlertWebhook {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl AlertWebhook {
+    pub fn new(url: String) -> Self {
+        Self {
+            url,
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .expect("Failed to create HTTP client"),
+        }
+    }
+
+    /// Post `alert` on a background task. Failures are logged, not retried.
+    pub fn spawn_send(self: &Arc<Self>, alert: SchemaChangeAlert) {
+        let webhook = Arc::clone(self);
+        tokio::spawn(async move {
+            let result = webhook
+                .client
+                .post(&webhook.url)
+                .json(&alert)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+            if let Err(e) = result {
+                tracing::warn!(tool_id = %alert.tool_id, "Sending schema change alert failed: {}", e);
+            }
+        });
+    }
+}
@@ -0,0 +1,119 @@
+//! Up-front cost estimates for queries.
+//!
+//! `Orchestrator::estimate_query_cost` runs tool selection and planning
+//! without executing anything; the functions here price the result.
+
+use serde::Serialize;
+
+use crate::db::ToolRecord;
+
+/// Rough number of characters per token of embedding input.
+const CHARS_PER_TOKEN: usize = 4;
+
+/// What answering a query is expected to cost.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CostEstimate {
+    /// Embedding requests needed; `0` when the query's embedding is cached.
+    pub embedding_api_calls: u32,
+    /// Facts derived by symbolic inference while selecting tools.
+    pub symbolic_inference_steps: u32,
+    /// One call per plan step, or one for the best selection without a plan.
+    pub expected_tool_calls: u32,
+    /// Price of the embedding requests plus each expected tool's `cost_per_call`.
+    pub estimated_usd_cents: f32,
+}
+
+impl CostEstimate {
+    /// Price a query whose execution calls `expected_tools`.
+    ///
+    /// Tools that don't declare a `cost_per_call` are assumed to be free.
+    pub fn new(
+        query: &str,
+        embedding_api_calls: u32,
+        cost_per_1k_tokens: f32,
+        symbolic_inference_steps: u32,
+        expected_tools: &[ToolRecord],
+    ) -> Self {
+        let embedding_cents = embedding_api_calls as f32 * estimate_tokens(query) as f32 / 1000.0
+            * cost_per_1k_tokens;
+        let tool_cents: f32 = expected_tools
+            .iter()
+            .filter_map(|tool| tool.cost_per_call)
+            .sum();
+        Self {
+            embedding_api_calls,
+            symbolic_inference_steps,
+            expected_tool_calls: u32::try_from(expected_tools.len()).unwrap_or(u32::MAX),
+            estimated_usd_cents: embedding_cents + tool_cents,
+        }
+    }
+}
+
+/// Approximate number of tokens `text` is split into for embedding.
+pub fn estimate_tokens(text: &str) -> u32 {
+    let tokens = text.chars().count().div_ceil(CHARS_PER_TOKEN).max(1);
+    u32::try_from(tokens).unwrap_or(u32::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use surrealdb::RecordId;
+
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(""), 1);
+        assert_eq!(estimate_tokens("read"), 1);
+        assert_eq!(estimate_tokens("read a file"), 3);
+    }
+
+    fn tool(cost_per_call: Option<f32>) -> ToolRecord {
+        ToolRecord {
+            id: RecordId::from_table_key("tool", "search"),
+            service_id: RecordId::from_table_key("service", "web"),
+            name: "search".to_string(),
+            description: None,
+            input_schema: Default::default(),
+            output_schema: None,
+            embedding_id: None,
+            input_ty: None,
+            output_ty: None,
+            usage_count: 0,
+            status: None,
+            discovery_source: Default::default(),
+            metadata: Default::default(),
+            input_examples: Vec::new(),
+            changelog: Vec::new(),
+            deprecation_notice: None,
+            descriptions: Default::default(),
+            inject_user_context: Vec::new(),
+            geo_availability: None,
+            required_env_vars: Vec::new(),
+            sla_class: None,
+            cost_per_call,
+            cache_ttl_seconds: None,
+            required_role: None,
+            actual_latency_p99_ms: None,
+            actual_error_rate_pct: None,
+            block_reason: None,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    #[test]
+    fn test_cost_estimate_prices_embedding_and_tools() {
+        // 2000 characters is 500 tokens
+        let query = "x".repeat(2000);
+
+        let estimate = CostEstimate::new(&query, 1, 0.02, 7, &[tool(Some(1.5)), tool(None)]);
+        assert_eq!(estimate.embedding_api_calls, 1);
+        assert_eq!(estimate.symbolic_inference_steps, 7);
+        assert_eq!(estimate.expected_tool_calls, 2);
+        assert!((estimate.estimated_usd_cents - 1.51).abs() < 1e-6);
+
+        let cached = CostEstimate::new(&query, 0, 0.02, 0, &[]);
+        assert_eq!(cached.estimated_usd_cents, 0.0);
+    }
+}
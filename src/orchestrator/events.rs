@@ -0,0 +1,67 @@
+//! Tool execution lifecycle events, broadcast to live subscribers.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use crate::types::ExternalUserId;
+
+/// Events buffered per subscriber before slow subscribers start missing some.
+pub const EXECUTION_EVENT_CAPACITY: usize = 256;
+
+/// Outcome of a single tool execution.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolExecutionEvent {
+    pub tool_id: String,
+    pub tool_name: String,
+    pub service_id: String,
+    /// The user the tool ran for, if the execution went through the approval flow.
+    pub user_id: Option<ExternalUserId>,
+    pub success: bool,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl ToolExecutionEvent {
+    /// Whether a subscriber with the given filters should receive this event.
+    ///
+    /// Events without a user are never delivered; they cannot be attributed
+    /// to anyone allowed to see them.
+    pub fn is_visible_to(&self, user_id: &str, tool_id: Option<&str>) -> bool {
+        self.user_id.as_ref().is_some_and(|u| u.as_str() == user_id)
+            && tool_id.is_none_or(|t| t == self.tool_id)
+    }
+}
+
+/// Sender shared by all executions; each subscriber holds its own receiver.
+pub type ExecutionEventSender = broadcast::Sender<ToolExecutionEvent>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(user_id: Option<&str>) -> ToolExecutionEvent {
+        ToolExecutionEvent {
+            tool_id: "tool:search".to_string(),
+            tool_name: "search".to_string(),
+            service_id: "service:web".to_string(),
+            user_id: user_id.map(ExternalUserId::new),
+            success: true,
+            error: None,
+            duration_ms: 12,
+            timestamp: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_is_visible_to() {
+        let e = event(Some("user:alice"));
+        assert!(e.is_visible_to("user:alice", None));
+        assert!(e.is_visible_to("user:alice", Some("tool:search")));
+        assert!(!e.is_visible_to("user:alice", Some("tool:other")));
+        assert!(!e.is_visible_to("user:bob", None));
+        assert!(!event(None).is_visible_to("user:alice", None));
+    }
+}
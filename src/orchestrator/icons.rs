@@ -0,0 +1,397 @@
+//! Downloads service icons advertised in `server_info.icons` and caches the
+//! image data in the `service_icon` table, so clients don't have to fetch
+//! arbitrary third-party URLs themselves.
+
+use anyhow::{Result, anyhow};
+use base64::Engine;
+use rmcp::model::Icon;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use surrealdb::Surreal;
+use surrealdb::engine::any::Any;
+
+use crate::db::ServiceRecord;
+use crate::db::queries::QueryBuilder;
+
+/// Icon formats that are stored; anything else is skipped.
+const SUPPORTED_MIME_TYPES: &[&str] = &["image/png", "image/svg+xml"];
+
+/// Icons larger than this are rejected.
+const MAX_ICON_BYTES: usize = 1024 * 1024;
+
+/// Attempts per icon: the first try plus one retry.
+const FETCH_ATTEMPTS: usize = 2;
+
+/// Signature every PNG file starts with.
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Downloads service icons and stores them in the database.
+pub struct IconFetcher {
+    db: Surreal<Any>,
+    client: reqwest::Client,
+}
+
+impl IconFetcher {
+    pub fn new(db: Surreal<Any>) -> Self {
+        Self {
+            db,
+            // Icon URLs come from downstream servers, so only public addresses
+            // are reachable and redirects are not followed
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .redirect(reqwest::redirect::Policy::none())
+                .no_proxy()
+                .dns_resolver(Arc::new(PublicAddrResolver))
+                .build()
+                .expect("Failed to create HTTP client"),
+        }
+    }
+
+    /// Fetch icons for `services` on a background task.
+    pub fn spawn_fetch(self: &Arc<Self>, services: Vec<ServiceRecord>) {
+        if services.is_empty() {
+            return;
+        }
+        let fetcher = Arc::clone(self);
+        tokio::spawn(async move {
+            for service in services {
+                if let Err(e) = fetcher.fetch_service_icon(&service).await {
+                    tracing::warn!(service_id = %service.id, "Fetching service icon failed: {}", e);
+                }
+            }
+        });
+    }
+
+    /// Download the first usable icon of a service and store it.
+    ///
+    /// Each icon is retried once. If no icon could be downloaded the service is
+    /// marked with `icon_fetch_failed`. Returns whether an icon was stored;
+    /// services without icons are left untouched.
+    pub async fn fetch_service_icon(&self, service: &ServiceRecord) -> Result<bool> {
+        let icons = service.icons.as_deref().unwrap_or_default();
+        if icons.is_empty() {
+            return Ok(false);
+        }
+
+        for icon in icons {
+            for attempt in 1..=FETCH_ATTEMPTS {
+                match self.download(icon).await {
+                    Ok((data, mime_type)) => {
+                        QueryBuilder::store_service_icon(&self.db, &service.id, data, &mime_type)
+                            .await?;
+                        return Ok(true);
+                    }
+                    Err(e) => tracing::debug!(
+                        service_id = %service.id,
+                        src = %icon.src,
+                        attempt,
+                        "Icon download failed: {}",
+                        e
+                    ),
+                }
+            }
+        }
+
+        QueryBuilder::mark_service_icon_fetch_failed(&self.db, &service.id).await?;
+        Ok(false)
+    }
+
+    /// Download an icon, returning its bytes and MIME type.
+    ///
+    /// The MIME type comes from the data itself (the `data:` media type or the
+    /// response `Content-Type`); the icon's declared `mimeType` is not trusted.
+    async fn download(&self, icon: &Icon) -> Result<(Vec<u8>, String)> {
+        let (data, content_type) = if let Some(uri) = icon.src.strip_prefix("data:") {
+            let (content_type, data) = decode_data_uri(uri)?;
+            if data.len() > MAX_ICON_BYTES {
+                return Err(anyhow!("Icon is larger than {} bytes", MAX_ICON_BYTES));
+            }
+            (data, Some(content_type))
+        } else if icon.src.starts_with("https://") || icon.src.starts_with("http://") {
+            self.fetch(&icon.src).await?
+        } else {
+            return Err(anyhow!("Unsupported icon URI scheme"));
+        };
+
+        let mime_type = resolve_mime_type(content_type.as_deref())
+            .ok_or_else(|| anyhow!("Unsupported icon type"))?;
+        if mime_type == "image/png" && !data.starts_with(PNG_SIGNATURE) {
+            return Err(anyhow!("Icon is not a PNG image"));
+        }
+        Ok((data, mime_type.to_string()))
+    }
+
+    /// Fetch an `http(s)` icon, refusing non-public hosts and reading at most
+    /// `MAX_ICON_BYTES`.
+    async fn fetch(&self, src: &str) -> Result<(Vec<u8>, Option<String>)> {
+        let url = reqwest::Url::parse(src)?;
+        // IP literals skip the resolver, so they are checked here
+        let literal = match url.host() {
+            Some(url::Host::Ipv4(ip)) => Some(IpAddr::V4(ip)),
+            Some(url::Host::Ipv6(ip)) => Some(IpAddr::V6(ip)),
+            Some(url::Host::Domain(_)) => None,
+            None => return Err(anyhow!("Icon URL has no host")),
+        };
+        if literal.is_some_and(|ip| !is_public_ip(ip)) {
+            return Err(anyhow!("Icon host is not a public address"));
+        }
+
+        let mut response = self.client.get(url).send().await?.error_for_status()?;
+        if response
+            .content_length()
+            .is_some_and(|len| len > MAX_ICON_BYTES as u64)
+        {
+            return Err(anyhow!("Icon is larger than {} bytes", MAX_ICON_BYTES));
+        }
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string());
+
+        let mut data = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            if data.len() + chunk.len() > MAX_ICON_BYTES {
+                return Err(anyhow!("Icon is larger than {} bytes", MAX_ICON_BYTES));
+            }
+            data.extend_from_slice(&chunk);
+        }
+        Ok((data, content_type))
+    }
+}
+
+/// DNS resolver that drops every address `is_public_ip` rejects, so icon
+/// hosts can't point at the orchestrator's own network.
+struct PublicAddrResolver;
+
+impl reqwest::dns::Resolve for PublicAddrResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0))
+                .await?
+                .filter(|addr| is_public_ip(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(format!("{} does not resolve to a public address", host).into());
+            }
+            Ok(Box::new(addrs.into_iter()) as reqwest::dns::Addrs)
+        })
+    }
+}
+
+/// Whether `ip` is a publicly routable address. Loopback, private, link-local,
+/// unspecified, multicast and similar special-purpose ranges are not.
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                || a == 0
+                // Shared address space (100.64.0.0/10)
+                || (a == 100 && (b & 0xc0) == 64))
+        }
+        IpAddr::V6(ip) => {
+            if let Some(v4) = ip.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(v4));
+            }
+            let first = ip.segments()[0];
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // Unique local (fc00::/7) and link-local (fe80::/10)
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+/// Map a `Content-Type` (or `data:` media type) to a supported MIME type.
+fn resolve_mime_type(content_type: Option<&str>) -> Option<&'static str> {
+    // Drop parameters such as `; charset=utf-8`
+    let essence = content_type?.split(';').next().unwrap_or_default().trim();
+    SUPPORTED_MIME_TYPES
+        .iter()
+        .find(|supported| supported.eq_ignore_ascii_case(essence))
+        .copied()
+}
+
+/// Decode the part of a `data:` URI after the scheme.
+fn decode_data_uri(uri: &str) -> Result<(String, Vec<u8>)> {
+    let (header, payload) = uri
+        .split_once(',')
+        .ok_or_else(|| anyhow!("Malformed data URI"))?;
+    match header.strip_suffix(";base64") {
+        Some(mime_type) => Ok((
+            mime_type.to_string(),
+            base64::engine::general_purpose::STANDARD.decode(payload)?,
+        )),
+        None => Ok((header.to_string(), payload.as_bytes().to_vec())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{DatabaseConfig, ServiceCreate, ServiceOrigin, create_connection};
+
+    fn icon(src: &str, mime_type: Option<&str>) -> Icon {
+        Icon {
+            src: src.to_string(),
+            mime_type: mime_type.map(|m| m.to_string()),
+            sizes: None,
+        }
+    }
+
+    async fn service_with_icons(db: &Surreal<Any>, icons: Vec<Icon>) -> ServiceRecord {
+        QueryBuilder::upsert_service(
+            db,
+            &ServiceCreate {
+                name: "weather".to_string(),
+                title: None,
+                version: "1.0.0".to_string(),
+                icons: Some(icons),
+                website_url: None,
+                origin: ServiceOrigin::StaticConfig,
+                registry_id: None,
+            },
+        )
+        .await
+        .unwrap()
+    }
+
+    #[test]
+    fn test_resolve_mime_type() {
+        assert_eq!(
+            resolve_mime_type(Some("image/svg+xml; charset=utf-8")),
+            Some("image/svg+xml")
+        );
+        assert_eq!(resolve_mime_type(Some("IMAGE/PNG")), Some("image/png"));
+        assert_eq!(resolve_mime_type(Some("application/octet-stream")), None);
+        assert_eq!(resolve_mime_type(Some("image/gif")), None);
+        assert_eq!(resolve_mime_type(None), None);
+    }
+
+    #[test]
+    fn test_is_public_ip() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "0.0.0.0",
+            "100.64.0.1",
+            "::1",
+            "::",
+            "fe80::1",
+            "fd00::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(
+                !is_public_ip(ip.parse().unwrap()),
+                "{} should be refused",
+                ip
+            );
+        }
+        for ip in ["93.184.216.34", "2606:2800:220:1:248:1893:25c8:1946"] {
+            assert!(
+                is_public_ip(ip.parse().unwrap()),
+                "{} should be allowed",
+                ip
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_download_refuses_local_and_mismatched_icons() {
+        let config = DatabaseConfig {
+            url: "memory".to_string(),
+            ..Default::default()
+        };
+        let db = create_connection(config).await.unwrap();
+        let fetcher = IconFetcher::new(db);
+
+        // A server is listening, but loopback targets are never contacted
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        for src in [
+            format!("http://{}/icon.png", addr),
+            format!("http://localhost:{}/icon.png", addr.port()),
+        ] {
+            assert!(
+                fetcher
+                    .download(&icon(&src, Some("image/png")))
+                    .await
+                    .is_err()
+            );
+        }
+        let accepted = tokio::time::timeout(Duration::from_millis(100), listener.accept()).await;
+        assert!(accepted.is_err(), "loopback icon host was contacted");
+
+        // The declared type is ignored; PNG data must carry the signature
+        let not_png = icon("data:image/png;base64,iVBORw==", Some("image/png"));
+        assert!(fetcher.download(&not_png).await.is_err());
+        let gif = icon("data:image/gif,GIF89a", Some("image/png"));
+        assert!(fetcher.download(&gif).await.is_err());
+        let png = icon("data:image/png;base64,iVBORw0KGgo=", None);
+        let (data, mime_type) = fetcher.download(&png).await.unwrap();
+        assert_eq!(data, PNG_SIGNATURE);
+        assert_eq!(mime_type, "image/png");
+    }
+
+    #[test]
+    fn test_decode_data_uri() {
+        let (mime, data) = decode_data_uri("image/png;base64,iVBORw==").unwrap();
+        assert_eq!(mime, "image/png");
+        assert_eq!(data, vec![0x89, b'P', b'N', b'G']);
+
+        let (mime, data) = decode_data_uri("image/svg+xml,<svg/>").unwrap();
+        assert_eq!(mime, "image/svg+xml");
+        assert_eq!(data, b"<svg/>");
+    }
+
+    #[tokio::test]
+    async fn test_fetch_service_icon_stores_or_marks_failed() {
+        let config = DatabaseConfig {
+            url: "memory".to_string(),
+            ..Default::default()
+        };
+        let db = create_connection(config).await.unwrap();
+        crate::db::ensure_schema(&db).await.unwrap();
+        let fetcher = IconFetcher::new(db.clone());
+
+        // Nothing listens on port 1, so both attempts fail
+        let broken = service_with_icons(&db, vec![icon("http://127.0.0.1:1/icon.png", None)]).await;
+        assert!(!fetcher.fetch_service_icon(&broken).await.unwrap());
+        let broken = QueryBuilder::find_service_by_id(&db, &broken.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(broken.icon_fetch_failed);
+
+        // Falls through to the next icon when one is unusable
+        let working = service_with_icons(
+            &db,
+            vec![
+                icon("ftp://example.com/icon.png", None),
+                icon("data:image/svg+xml,<svg/>", None),
+            ],
+        )
+        .await;
+        assert!(fetcher.fetch_service_icon(&working).await.unwrap());
+        let stored = QueryBuilder::get_service_icon(&db, &working.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.mime_type, "image/svg+xml");
+        assert_eq!(stored.data.into_inner(), b"<svg/>");
+    }
+}
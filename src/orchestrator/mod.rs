@@ -1,32 +1,133 @@
 //! Core orchestrator logic - the "brain" that handles tool selection,
 //! planning, and execution using semantic search and symbolic reasoning.
 
+pub mod alerts;
+pub mod cost;
+pub mod events;
+pub mod icons;
+pub mod plan_cache;
+pub mod reload;
+pub mod result_cache;
+pub mod service_info;
+pub mod shutdown;
+pub mod sla;
+pub mod suggest;
+pub mod template;
 pub mod user_filter;
 
 use anyhow::{Result, anyhow};
 use serde_json::Value;
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::Arc;
 use surrealdb::engine::any::Any;
 use surrealdb::{RecordId, Surreal};
 use tokio::sync::Mutex;
+use unicity_macros::orchestrate_traced;
 
-use crate::auth::UserContext;
-use crate::config::McpConfigs;
-use crate::db::schema::{AuditAction, AuditLogCreate};
-use crate::db::{DatabaseConfig, ServiceRecord, ToolRecord, create_connection, ensure_schema};
+use crate::auth::{AuthConfig, AuthRateLimiter, MissingRoleError, UserContext, UserStore};
+use crate::config::{McpConfigs, McpServiceConfig, TrustLevel};
+use crate::db::schema::{
+    AuditAction, AuditLogCreate, BlockReason, ChangelogEntry, DiscoverySource, ServiceOrigin,
+    SlaClass, new_breaking_changes,
+};
+use crate::db::{
+    DatabaseConfig, JsonSchemaResolver, PruneResult, QueryBuilder, SchemaDiff, ServiceRecord,
+    ToolRecord, create_connection, ensure_schema,
+};
 use crate::elicitation::{
-    ApprovalRequest, ElicitationCoordinator, ElicitationFallbackPolicy, PermissionStatus,
+    ApprovalAction, ApprovalConfig, ApprovalRequest, ElicitationCoordinator,
+    ElicitationFallbackPolicy, PermissionStatus,
+};
+use crate::knowledge_graph::{
+    EmbeddingBackendConfig, EmbeddingConfig, EmbeddingError, EmbeddingManager, EmbeddingScheduler,
+    EmbeddingSearchResult, FeedbackRecorder, FeedbackRefresher, KnowledgeGraph,
+    MIN_EDGE_COMPATIBILITY, MergeResult, NormalizedDescription, PlanningConstraints,
+    RuleCoverageReport, SERVICE_DUPLICATE_WARN_THRESHOLD, SimilarityExplanation, SymbolicReasoner,
+    SymbolicRule, ToolPlan, ToolSelection, TypeUriGraph, maximal_marginal_relevance,
+    pairwise_cosine, type_uri,
+};
+use crate::mcp_client::{
+    DownstreamClient, RestartPolicy, RunningService, ServiceSupervisor, SharedRunningServices,
+    StartupFailures,
+};
+use crate::metrics::MetricsCollector;
+use crate::orchestrator::alerts::{AlertWebhook, SchemaChangeAlert};
+use crate::orchestrator::cost::CostEstimate;
+use crate::orchestrator::events::{
+    EXECUTION_EVENT_CAPACITY, ExecutionEventSender, ToolExecutionEvent,
 };
-use crate::knowledge_graph::{EmbeddingManager, KnowledgeGraph, SymbolicReasoner, ToolSelection};
-use crate::mcp_client::RunningService;
+use crate::orchestrator::icons::IconFetcher;
+use crate::orchestrator::plan_cache::PlanCache;
+use crate::orchestrator::reload::{ConfigReloadResult, diff_configs};
+use crate::orchestrator::result_cache::ToolResultCache;
+use crate::orchestrator::service_info::{ServiceInfo, build_service_infos};
+use crate::orchestrator::shutdown::{InFlightTracker, ShutdownHandle};
+use crate::orchestrator::sla::{SLA_REFRESH_INTERVAL, SlaMonitor};
+use crate::orchestrator::suggest::{
+    ArgumentSuggestion, MAX_SUGGESTIONS, rank_argument_suggestions,
+};
+use crate::orchestrator::template::TemplateEngine;
 use crate::prompts::{PromptForwarder, PromptRegistry};
-use crate::resources::{ResourceForwarder, ResourceRegistry};
-use crate::types::{ExternalUserId, ServiceId, ServiceName, ToolId};
+use crate::resources::{ResourceForwarder, ResourceRegistry, ResourceSubscriptions};
+use crate::session::SessionRegistry;
+use crate::types::{ApiKeyPrefix, ExternalUserId, ServiceId, ServiceName, ToolId};
 use rmcp::model::JsonObject;
 use std::sync::Arc as StdArc;
 use tokio::sync::Mutex as TokioMutex;
 
+/// Relevance/diversity trade-off used by `select_tools` when diversity is enforced.
+const MMR_LAMBDA: f32 = 0.7;
+
+/// Confidence multiplier `query_tools` applies to deprecated tools.
+const DEPRECATED_CONFIDENCE_FACTOR: f32 = 0.5;
+
+/// Longest tool chain `plan_tools_by_type` considers.
+const TYPED_PLAN_MAX_DEPTH: usize = 5;
+
+/// Number of alternative plans returned by `plan_tools_by_type`.
+const TYPED_PLAN_ALTERNATIVES: usize = 3;
+
+/// How many past invocations `suggest_arguments` considers.
+const SUGGESTION_HISTORY_LIMIT: usize = 200;
+
+/// Most recent plans kept for `Orchestrator::find_plan`.
+const RECENT_PLAN_LIMIT: usize = 64;
+
+/// How long a service removed from `mcp.json` gets to finish its running calls.
+const SERVICE_DRAIN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// How often a draining service is checked for finished calls.
+const SERVICE_DRAIN_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// Prefix of the permission ids resource writes are approved under, followed
+/// by the resource URI.
+const WRITE_RESOURCE_PERMISSION_PREFIX: &str = "resources/write:";
+
+/// A service from `mcp.json` and what it is currently running as.
+#[derive(Debug, Clone)]
+struct ConfiguredService {
+    config: McpServiceConfig,
+    /// Database id of the service, once it has started.
+    service_id: Option<RecordId>,
+    /// Name the service reported when it started.
+    server_name: Option<String>,
+}
+
+/// How a tool execution or resource write was approved.
+struct Approval {
+    /// Recorded as `permission_type` in the audit log.
+    permission_type: String,
+    /// Whether the permission was granted for this call only and must be
+    /// consumed once the call has finished.
+    consume_after: bool,
+}
+
+/// Oldest `last_seen_at` kept when pruning with `max_age_days`.
+fn prune_cutoff(max_age_days: u32) -> surrealdb::sql::Datetime {
+    (chrono::Utc::now() - chrono::Duration::days(i64::from(max_age_days))).into()
+}
+
 /// A single step in a proposed multi-tool plan.
 #[derive(Debug, Clone)]
 pub struct PlanStep {
@@ -36,44 +137,165 @@ pub struct PlanStep {
     pub inputs: Vec<String>,
 }
 
+/// Options for `Orchestrator::query_tools` and `Orchestrator::select_tools`.
+#[derive(Debug, Clone, Default)]
+pub struct ToolQueryOptions {
+    /// Rank deprecated tools like any other instead of scaling their
+    /// confidence by `DEPRECATED_CONFIDENCE_FACTOR`.
+    pub include_deprecated: bool,
+    /// BCP-47 tag of the query's language; defaults to the user's
+    /// `preferred_language`, then English.
+    pub language: Option<String>,
+    /// ISO 3166-1 alpha-2 code to check region-restricted tools against
+    /// instead of the user's `country_code`, for admin queries.
+    pub geo_override: Option<String>,
+    /// Embedding model to search with for this query only, if it is the
+    /// global model or in `allowed_backend_overrides`. Takes precedence over
+    /// the user's preference; an unknown model is ignored.
+    pub embedding_model: Option<String>,
+    /// Only select tools that meet this service level; see
+    /// [`ToolRecord::meets_sla`].
+    pub required_sla: Option<SlaClass>,
+}
+
 /// Result of planning: a sequence of steps plus overall confidence and reasoning.
 #[derive(Debug, Clone)]
 pub struct PlanResult {
+    /// Id for looking the plan up again with `Orchestrator::find_plan`.
+    pub id: String,
     pub steps: Vec<PlanStep>,
     pub confidence: f32,
     pub reasoning: String,
+    /// Mermaid flowchart of the steps and their dependencies.
+    pub diagram: String,
+}
+
+/// Errors produced while assembling a plan.
+#[derive(Debug, Clone)]
+pub enum PlanningError {
+    /// The plan's step dependencies form a cycle; holds the cyclic step numbers.
+    CyclicDependency(Vec<u32>),
+    /// A step can't take the output of a step it depends on; holds tools that
+    /// could convert between the two, best first.
+    SuggestedBridges(Vec<ToolRecord>),
 }
 
+impl std::fmt::Display for PlanningError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlanningError::CyclicDependency(steps) => {
+                let steps: Vec<String> = steps.iter().map(|s| s.to_string()).collect();
+                write!(f, "Plan has cyclic step dependencies: {}", steps.join(", "))
+            }
+            PlanningError::SuggestedBridges(tools) => {
+                let names: Vec<&str> = tools.iter().map(|t| t.name.as_str()).collect();
+                write!(
+                    f,
+                    "Plan chains tools with incompatible types; intermediary tools: {}",
+                    names.join(", ")
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for PlanningError {}
+
 /// The core orchestrator - uses embeddings + symbolic reasoning to select and chain tools.
 pub struct Orchestrator {
     db: Surreal<Any>,
     knowledge_graph: KnowledgeGraph,
-    embedding_manager: Mutex<EmbeddingManager>,
+    embedding_manager: StdArc<Mutex<EmbeddingManager>>,
+    embedding_scheduler: StdArc<EmbeddingScheduler>,
+    feedback_refresher: StdArc<FeedbackRefresher>,
+    sla_monitor: StdArc<SlaMonitor>,
     symbolic_reasoner: Mutex<SymbolicReasoner>,
-    #[allow(clippy::mutable_key_type)]
-    running_services: HashMap<RecordId, Arc<RunningService>>,
+    /// Running services keyed by service id, shared with the forwarders and supervisor.
+    running_services: SharedRunningServices,
     prompt_forwarder: StdArc<PromptForwarder>,
     resource_forwarder: StdArc<ResourceForwarder>,
+    resource_subscriptions: StdArc<ResourceSubscriptions>,
     elicitation_coordinator: StdArc<ElicitationCoordinator>,
+    supervisor: StdArc<ServiceSupervisor>,
+    /// Services that timed out during startup, shared with the supervisor.
+    startup_failures: StartupFailures,
+    /// The services loaded from `mcp.json`, keyed by server name.
+    service_configs: TokioMutex<HashMap<String, ConfiguredService>>,
+    /// Path of `mcp.json`; found by `resolve_mcp_json_path` when `None`.
+    mcp_config_path: Option<PathBuf>,
+    /// Downloads service icons in the background after discovery.
+    icon_fetcher: StdArc<IconFetcher>,
+    /// Receives alerts such as newly advertised breaking changes, if configured.
+    alert_webhook: Option<StdArc<AlertWebhook>>,
+    /// Confidence of each tool in the most recent query that selected it, keyed by tool id.
+    last_confidence: std::sync::Mutex<HashMap<String, f32>>,
+    /// Plans most recently returned by planning, newest last.
+    recent_plans: std::sync::Mutex<std::collections::VecDeque<PlanResult>>,
+    /// Database time of the last symbolic rule load; later warmups only load
+    /// rules changed since then.
+    rules_loaded_at: Option<surrealdb::sql::Datetime>,
+    /// Broadcasts the outcome of every tool execution to live subscribers.
+    execution_events: ExecutionEventSender,
+    /// Recent `plan_tools_for_query` results; cleared when tools or rules change.
+    plan_cache: PlanCache,
+    /// Results of tools that declare a `cache_ttl_seconds`.
+    tool_result_cache: ToolResultCache,
+    /// Running tool executions, drained on graceful shutdown.
+    in_flight: InFlightTracker,
+    /// Metrics rendered on `GET /metrics`.
+    metrics: MetricsCollector,
+    /// Sessions of the MCP clients currently connected.
+    sessions: StdArc<SessionRegistry>,
+    /// Failed API key authentications per client IP on the REST API.
+    auth_rate_limiter: StdArc<AuthRateLimiter>,
 }
 
+#[orchestrate_traced]
 impl Orchestrator {
     /// Create a new orchestrator with the given database configuration.
     pub async fn new(config: DatabaseConfig) -> Result<Self> {
+        Self::new_with_config(
+            config,
+            EmbeddingConfig::default(),
+            &ApprovalConfig::default(),
+            ElicitationFallbackPolicy::default(),
+        )
+        .await
+    }
+
+    /// Create a new orchestrator with explicit embedding, approval and
+    /// elicitation settings.
+    pub async fn new_with_config(
+        config: DatabaseConfig,
+        embedding_config: EmbeddingConfig,
+        approval: &ApprovalConfig,
+        elicitation_policy: ElicitationFallbackPolicy,
+    ) -> Result<Self> {
         let db = create_connection(config).await?;
         ensure_schema(&db).await?;
 
         let knowledge_graph = KnowledgeGraph::new();
-        let embedding_manager_inner = EmbeddingManager::new(
+        let embedding_manager_inner =
+            EmbeddingManager::new(db.clone(), embedding_config.clone()).await?;
+        // Recomputes the ranking penalties learned from selection feedback
+        let feedback_refresher = StdArc::new(FeedbackRefresher::new(
             db.clone(),
-            crate::knowledge_graph::embedding::EmbeddingConfig::default(),
-        )
-        .await?;
+            embedding_manager_inner.feedback_penalties(),
+            embedding_config.feedback_refresh_interval,
+        ));
+        let embedding_metrics = embedding_manager_inner.metrics().clone();
+        let embedding_manager = StdArc::new(Mutex::new(embedding_manager_inner));
+        // Periodically re-embeds tools whose content changed, busiest first
+        let embedding_scheduler = StdArc::new(EmbeddingScheduler::new(
+            db.clone(),
+            embedding_manager.clone(),
+            embedding_config,
+        ));
         let symbolic_reasoner_inner = SymbolicReasoner::new(db.clone());
 
         // Initialize prompt registry and forwarder
         let prompt_registry = StdArc::new(TokioMutex::new(PromptRegistry::new()));
-        let running_services_arc: StdArc<TokioMutex<HashMap<String, StdArc<RunningService>>>> =
+        let running_services_arc: SharedRunningServices =
             StdArc::new(TokioMutex::new(HashMap::new()));
         let prompt_forwarder = StdArc::new(PromptForwarder::new(
             prompt_registry,
@@ -88,25 +310,103 @@ impl Orchestrator {
             running_services_arc.clone(),
             db.clone(),
         ));
+        let resource_subscriptions =
+            StdArc::new(ResourceSubscriptions::new(resource_forwarder.clone()));
 
         // Initialize elicitation coordinator
-        let elicitation_coordinator = StdArc::new(ElicitationCoordinator::new(db.clone())?);
+        let elicitation_coordinator = StdArc::new(ElicitationCoordinator::new_with_config(
+            db.clone(),
+            elicitation_policy,
+            approval,
+        )?);
+
+        // Supervisor restarts stdio services that exit unexpectedly
+        let startup_failures = StartupFailures::new();
+        let supervisor = StdArc::new(
+            ServiceSupervisor::new(
+                db.clone(),
+                running_services_arc.clone(),
+                RestartPolicy::default(),
+            )
+            .with_prompt_forwarder(prompt_forwarder.clone())
+            .with_startup_failures(startup_failures.clone()),
+        );
+
+        let icon_fetcher = StdArc::new(IconFetcher::new(db.clone()));
+        // Summarizes timed executions into each tool's measured SLA
+        let sla_monitor = StdArc::new(SlaMonitor::new(db.clone(), SLA_REFRESH_INTERVAL));
+
+        let metrics = MetricsCollector::new();
+        metrics.register(elicitation_coordinator.metrics().clone());
+        metrics.register(embedding_metrics);
 
         Ok(Self {
             db,
             knowledge_graph,
-            embedding_manager: Mutex::new(embedding_manager_inner),
+            embedding_manager,
+            embedding_scheduler,
+            feedback_refresher,
+            sla_monitor,
             symbolic_reasoner: Mutex::new(symbolic_reasoner_inner),
-            running_services: HashMap::new(),
+            running_services: running_services_arc,
             prompt_forwarder,
             resource_forwarder,
+            resource_subscriptions,
             elicitation_coordinator,
+            supervisor,
+            startup_failures,
+            service_configs: TokioMutex::new(HashMap::new()),
+            mcp_config_path: None,
+            icon_fetcher,
+            alert_webhook: None,
+            last_confidence: std::sync::Mutex::new(HashMap::new()),
+            recent_plans: std::sync::Mutex::new(std::collections::VecDeque::new()),
+            rules_loaded_at: None,
+            execution_events: tokio::sync::broadcast::channel(EXECUTION_EVENT_CAPACITY).0,
+            plan_cache: PlanCache::default(),
+            tool_result_cache: ToolResultCache::new(),
+            in_flight: InFlightTracker::new(),
+            metrics,
+            sessions: StdArc::new(SessionRegistry::new()),
+            auth_rate_limiter: StdArc::new(AuthRateLimiter::from_config(&AuthConfig::default())),
         })
     }
 
-    /// Initialize the orchestrator - run warmup pipeline.
+    /// Reload symbolic rules from the database.
+    ///
+    /// Unless `full` is set, only rules changed since the previous load are
+    /// read. Returns the number of rules loaded or changed.
+    pub async fn reload_rules(&mut self, full: bool) -> Result<usize> {
+        let since = if full {
+            None
+        } else {
+            self.rules_loaded_at.clone()
+        };
+
+        // Use the database clock so rules written by other nodes aren't missed
+        let mut res = self.db.query("RETURN time::now()").await?;
+        let loaded_at: Option<surrealdb::sql::Datetime> = res.take(0)?;
+
+        let count = self
+            .symbolic_reasoner
+            .lock()
+            .await
+            .load_rules_incremental(since)
+            .await?;
+        self.plan_cache.invalidate();
+        self.rules_loaded_at = loaded_at;
+        Ok(count)
+    }
+
+    /// Initialize the orchestrator - run warmup pipeline, start supervising services
+    /// and schedule embedding, feedback and measured SLA refreshes.
     pub async fn initialize(&mut self) -> Result<()> {
-        self.warmup().await
+        self.warmup().await?;
+        self.supervisor.start();
+        self.embedding_scheduler.start();
+        self.feedback_refresher.start();
+        self.sla_monitor.start();
+        Ok(())
     }
 
     /// Warmup pipeline: discover tools, normalize types, update embeddings, build graph.
@@ -117,18 +417,31 @@ impl Orchestrator {
         // Normalize tool schemas into typed representations
         self.normalize_tool_types().await?;
 
-        // Update embeddings for all tools
+        // Update embeddings for all tools, then look for services configured twice
         {
             let mut embedding_manager = self.embedding_manager.lock().await;
             embedding_manager.update_tool_embeddings().await?;
+            match embedding_manager.cross_service_similarity_matrix().await {
+                Ok(pairs) => {
+                    for (a, b, similarity) in pairs
+                        .into_iter()
+                        .filter(|(_, _, similarity)| *similarity > SERVICE_DUPLICATE_WARN_THRESHOLD)
+                    {
+                        tracing::warn!(
+                            service_a = %a,
+                            service_b = %b,
+                            similarity,
+                            "Services expose near-identical tools and may be configured twice"
+                        );
+                    }
+                }
+                Err(e) => tracing::warn!("Failed to compare service embeddings: {}", e),
+            }
         }
 
-        // Rebuild knowledge graph and load symbolic rules
+        // Rebuild knowledge graph and load symbolic rules changed since the last warmup
         self.knowledge_graph = KnowledgeGraph::build_from_database(&self.db).await?;
-        {
-            let mut symbolic_reasoner = self.symbolic_reasoner.lock().await;
-            symbolic_reasoner.load_rules().await?;
-        }
+        self.reload_rules(false).await?;
 
         // Discover prompts from all running services
         let _ = self.discover_prompts().await?;
@@ -141,95 +454,475 @@ impl Orchestrator {
 
     /// Discover MCP services and tools from local config.
     pub async fn discover_tools(&mut self) -> Result<(usize, usize)> {
-        let services = McpConfigs::load()?;
+        self.plan_cache.invalidate();
+        let config_path = self.mcp_config_path()?;
+        let discovery_source = DiscoverySource::StaticConfig {
+            config_path: config_path.display().to_string(),
+        };
+        let services = McpConfigs::load_from_path(config_path)?;
         let mut discovered_servers = 0;
         let mut discovered_tools = 0;
+        let mut discovered_services = Vec::new();
+        let mut configured = HashMap::new();
 
         for service_config in services {
-            match crate::mcp_client::start_service(&service_config).await {
-                Ok(Some(running_service)) => {
-                    match crate::mcp_client::inspect_service(&running_service).await {
-                        Ok((server_info, tools)) => {
-                            let server_info = server_info.server_info;
-                            let service = crate::db::queries::QueryBuilder::upsert_service(
-                                &self.db,
-                                &crate::db::schema::ServiceCreate {
-                                    name: server_info.name.clone(),
-                                    title: server_info.title.clone(),
-                                    version: server_info.version.clone(),
-                                    icons: server_info.icons.clone(),
-                                    website_url: server_info.website_url.clone(),
-                                    origin: crate::db::schema::ServiceOrigin::StaticConfig,
-                                    registry_id: None,
-                                },
-                            )
-                            .await?;
-
-                            let service_id = service.id.clone();
-                            let rc = Arc::new(running_service);
-                            self.running_services.insert(service_id.clone(), rc.clone());
-                            discovered_servers += 1;
-
-                            // Also add to prompt_forwarder's running_services map
-                            {
-                                let mut services_map =
-                                    self.prompt_forwarder.running_services.lock().await;
-                                services_map.insert(service_id.to_string(), rc.clone());
-                            }
-
-                            // Also add to resource_forwarder's running_services map
-                            {
-                                let mut services_map =
-                                    self.resource_forwarder.running_services.lock().await;
-                                services_map.insert(service_id.to_string(), rc.clone());
-                            }
-
-                            for tool in tools {
-                                let input_schema = (*tool.input_schema).clone();
-                                let output_schema =
-                                    tool.output_schema.as_ref().map(|schema| (**schema).clone());
-
-                                let create_tool = crate::db::schema::CreateToolRecord {
-                                    service_id: service.id.clone(),
-                                    name: tool.name.to_string(),
-                                    description: tool.description.as_ref().map(|d| d.to_string()),
-                                    input_schema,
-                                    output_schema,
-                                    embedding_id: None,
-                                    input_ty: None,
-                                    output_ty: None,
-                                };
-
-                                let _tool_record = crate::db::queries::QueryBuilder::upsert_tool(
-                                    &self.db,
-                                    &create_tool,
-                                )
-                                .await?;
-                                discovered_tools += 1;
-                            }
-                        }
-                        Err(e) => tracing::error!("Failed to inspect service: {}", e),
-                    }
-                }
-                Ok(None) => continue,
-                Err(e) => tracing::error!("Failed to start service: {}", e),
+            let started = self
+                .start_configured_service(&service_config, &discovery_source)
+                .await?;
+            let mut entry = ConfiguredService {
+                config: service_config.clone(),
+                service_id: None,
+                server_name: None,
+            };
+            if let Some((service, tool_count)) = started {
+                entry.service_id = Some(service.id.clone());
+                entry.server_name = service.name.clone();
+                discovered_servers += 1;
+                discovered_tools += tool_count;
+                discovered_services.push(service);
             }
+            configured.insert(service_config.id().as_str().to_string(), entry);
         }
+        *self.service_configs.lock().await = configured;
+
+        self.icon_fetcher.spawn_fetch(discovered_services);
 
         Ok((discovered_servers, discovered_tools))
     }
 
+    /// Start a configured service, store it and its tools and register it with
+    /// the supervisor and forwarders.
+    ///
+    /// Returns the stored service and its number of tools, or `None` if the
+    /// service is disabled or failed to start; start failures are logged.
+    async fn start_configured_service(
+        &self,
+        service_config: &McpServiceConfig,
+        discovery_source: &DiscoverySource,
+    ) -> Result<Option<(ServiceRecord, usize)>> {
+        let handler = DownstreamClient::new();
+        let running_service =
+            match crate::mcp_client::start_service(service_config, handler, &self.startup_failures)
+                .await
+            {
+                Ok(Some(running_service)) => running_service,
+                Ok(None) => return Ok(None),
+                Err(e) => {
+                    tracing::error!("Failed to start service: {}", e);
+                    return Ok(None);
+                }
+            };
+        let (server_info, tools) = match crate::mcp_client::inspect_service(&running_service).await
+        {
+            Ok(inspected) => inspected,
+            Err(e) => {
+                tracing::error!("Failed to inspect service: {}", e);
+                return Ok(None);
+            }
+        };
+
+        let server_info = server_info.server_info;
+        let service = crate::db::queries::QueryBuilder::upsert_service(
+            &self.db,
+            &crate::db::schema::ServiceCreate {
+                name: server_info.name.clone(),
+                title: server_info.title.clone(),
+                version: server_info.version.clone(),
+                icons: server_info.icons.clone(),
+                website_url: server_info.website_url.clone(),
+                origin: crate::db::schema::ServiceOrigin::StaticConfig,
+                registry_id: None,
+            },
+        )
+        .await?;
+
+        let service_id = service.id.clone();
+        let rc = Arc::new(running_service);
+
+        // The map is shared with the prompt/resource forwarders
+        self.running_services
+            .lock()
+            .await
+            .insert(service_id.to_string(), rc.clone());
+        self.supervisor
+            .watch(
+                &service_id,
+                service_config.clone(),
+                discovery_source.clone(),
+            )
+            .await;
+        self.apply_service_settings(&service_id, Some(&server_info.name), service_config)
+            .await;
+
+        let tool_count = tools.len();
+        for tool in tools {
+            let create_tool = crate::db::schema::CreateToolRecord::from_mcp_tool(
+                &service.id,
+                &tool,
+                discovery_source.clone(),
+            );
+
+            let previous =
+                QueryBuilder::find_tool_by_name(&self.db, &service.id, &create_tool.name).await?;
+            let tool_record = QueryBuilder::upsert_tool(&self.db, &create_tool).await?;
+            if let Some(previous) = previous {
+                self.alert_breaking_changes(&previous, &tool_record);
+            }
+            if let Some(name) = service_config.missing_env_var(&tool_record.required_env_vars) {
+                tracing::warn!(
+                    "Blocking tool `{}` of MCP service `{}`: environment variable `{}` is not set",
+                    tool_record.name,
+                    service_config.id(),
+                    name
+                );
+                QueryBuilder::block_tool(
+                    &self.db,
+                    &tool_record.id,
+                    BlockReason::MissingEnvVar(name.to_string()),
+                )
+                .await?;
+            }
+        }
+
+        Ok(Some((service, tool_count)))
+    }
+
+    /// Apply the per-service settings from `mcp.json` that take effect
+    /// without restarting the service.
+    async fn apply_service_settings(
+        &self,
+        service_id: &RecordId,
+        server_name: Option<&str>,
+        service_config: &McpServiceConfig,
+    ) {
+        self.resource_forwarder
+            .set_conflict_resolution(
+                ServiceId::new(service_id.to_string()),
+                service_config.conflict_resolution(),
+            )
+            .await;
+        if let Some(server_name) = server_name {
+            self.elicitation_coordinator
+                .set_service_timeout(
+                    server_name.to_string(),
+                    service_config.elicitation_timeout(),
+                )
+                .await;
+        }
+    }
+
+    /// Re-read `mcp.json` and apply the differences to the running services.
+    ///
+    /// New services are started. Stdio services whose config changed are
+    /// sent `SIGHUP`; the supervisor restarts them with the new config if the
+    /// signal makes them exit. Other changed services are stopped and started
+    /// again. Removed services are stopped once their in-flight calls have
+    /// finished, or after `SERVICE_DRAIN_TIMEOUT`, and their tools blocked.
+    pub async fn reload_config(&self) -> Result<ConfigReloadResult> {
+        let config_path = self.mcp_config_path()?;
+        let discovery_source = DiscoverySource::StaticConfig {
+            config_path: config_path.display().to_string(),
+        };
+        let configs: Vec<McpServiceConfig> = McpConfigs::load_from_path(config_path)?.collect();
+
+        // Held throughout so concurrent reloads apply one after the other
+        let mut configured = self.service_configs.lock().await;
+        let current: HashMap<String, McpServiceConfig> = configured
+            .iter()
+            .map(|(name, entry)| (name.clone(), entry.config.clone()))
+            .collect();
+        let result = diff_configs(&current, &configs);
+        if result.is_empty() {
+            return Ok(result);
+        }
+
+        for name in &result.removed {
+            if let Some(entry) = configured.remove(name)
+                && let Some(service_id) = &entry.service_id
+            {
+                self.stop_configured_service(service_id).await;
+            }
+            tracing::info!("Removed MCP service `{}`", name);
+        }
+
+        for config in &configs {
+            let name = config.id().as_str();
+            if result.added.iter().any(|added| added == name) {
+                let entry = self
+                    .start_reloaded_service(config, &discovery_source)
+                    .await?;
+                configured.insert(name.to_string(), entry);
+                tracing::info!("Added MCP service `{}`", name);
+            } else if result.updated.iter().any(|updated| updated == name)
+                && let Some(entry) = configured.get_mut(name)
+            {
+                *entry = self
+                    .update_configured_service(entry, config, &discovery_source)
+                    .await?;
+                tracing::info!("Updated MCP service `{}`", name);
+            }
+        }
+        drop(configured);
+
+        self.plan_cache.invalidate();
+        if let Err(e) = self.discover_resources().await {
+            tracing::warn!("Failed to rediscover resources after reload: {}", e);
+        }
+        Ok(result)
+    }
+
+    /// Apply a changed config to a service, signalling it where possible.
+    async fn update_configured_service(
+        &self,
+        entry: &ConfiguredService,
+        config: &McpServiceConfig,
+        discovery_source: &DiscoverySource,
+    ) -> Result<ConfiguredService> {
+        let running = match &entry.service_id {
+            Some(service_id) => self
+                .running_services
+                .lock()
+                .await
+                .get(&service_id.to_string())
+                .cloned(),
+            None => None,
+        };
+
+        if let (Some(service_id), Some(running)) = (&entry.service_id, running)
+            && !config.is_disabled()
+        {
+            // The supervisor restarts the service with the new config if it exits
+            self.supervisor
+                .watch(service_id, config.clone(), discovery_source.clone())
+                .await;
+            self.apply_service_settings(service_id, entry.server_name.as_deref(), config)
+                .await;
+            match running.signal_reload() {
+                Ok(true) => {
+                    return Ok(ConfiguredService {
+                        config: config.clone(),
+                        ..entry.clone()
+                    });
+                }
+                Ok(false) => {}
+                Err(e) => tracing::warn!(
+                    "Failed to send SIGHUP to MCP service `{}`: {}",
+                    config.id(),
+                    e
+                ),
+            }
+        }
+
+        if let Some(service_id) = &entry.service_id {
+            self.stop_configured_service(service_id).await;
+        }
+        self.start_reloaded_service(config, discovery_source).await
+    }
+
+    /// Start a service added by a reload, unblocking tools a previous removal blocked.
+    async fn start_reloaded_service(
+        &self,
+        config: &McpServiceConfig,
+        discovery_source: &DiscoverySource,
+    ) -> Result<ConfiguredService> {
+        let mut entry = ConfiguredService {
+            config: config.clone(),
+            service_id: None,
+            server_name: None,
+        };
+        if let Some((service, _)) = self
+            .start_configured_service(config, discovery_source)
+            .await?
+        {
+            QueryBuilder::set_service_tools_status(&self.db, &service.id, None).await?;
+            if let Err(e) = self
+                .prompt_forwarder
+                .refresh_service_prompts(&service.id.to_string())
+                .await
+            {
+                tracing::warn!("Failed to discover prompts of `{}`: {}", config.id(), e);
+            }
+            entry.service_id = Some(service.id.clone());
+            entry.server_name = service.name.clone();
+            self.icon_fetcher.spawn_fetch(vec![service]);
+        }
+        Ok(entry)
+    }
+
+    /// Stop a service after its in-flight calls finish and block its tools.
+    async fn stop_configured_service(&self, service_id: &RecordId) {
+        let key = service_id.to_string();
+        // Stop the supervisor from restarting it once it's gone
+        self.supervisor.unwatch(service_id).await;
+
+        let running = self.running_services.lock().await.get(&key).cloned();
+        if let Some(running) = running {
+            let started = std::time::Instant::now();
+            while running.active_calls() > 0 && started.elapsed() < SERVICE_DRAIN_TIMEOUT {
+                tokio::time::sleep(SERVICE_DRAIN_POLL_INTERVAL).await;
+            }
+            if running.active_calls() > 0 {
+                tracing::warn!(
+                    "Stopping MCP service `{}` with {} call(s) still running",
+                    key,
+                    running.active_calls()
+                );
+            }
+            self.running_services.lock().await.remove(&key);
+            running.client.cancellation_token().cancel();
+        }
+
+        if let Err(e) =
+            QueryBuilder::block_service_tools(&self.db, service_id, BlockReason::ServiceRemoved)
+                .await
+        {
+            tracing::warn!("Failed to block tools of removed service `{}`: {}", key, e);
+        }
+        if let Err(e) = self.prompt_forwarder.refresh_service_prompts(&key).await {
+            tracing::warn!("Failed to remove prompts of `{}`: {}", key, e);
+        }
+    }
+
+    /// Read services from `path` instead of the `mcp.json` found by
+    /// [`crate::config::resolve_mcp_json_path`].
+    pub fn set_mcp_config_path(&mut self, path: Option<PathBuf>) {
+        self.mcp_config_path = path;
+    }
+
+    fn mcp_config_path(&self) -> Result<PathBuf> {
+        match &self.mcp_config_path {
+            Some(path) => Ok(path.clone()),
+            None => crate::config::resolve_mcp_json_path(),
+        }
+    }
+
+    /// The security headers layer for the HTTP servers, with the
+    /// `security.csp_policy` set in `mcp.json`.
+    pub fn security_headers(&self) -> Result<crate::api::SecurityHeadersLayer> {
+        let configs = McpConfigs::load_from_path(self.mcp_config_path()?)?;
+        match configs.csp_policy() {
+            Some(policy) => crate::api::SecurityHeadersLayer::with_csp_policy(policy),
+            None => Ok(crate::api::SecurityHeadersLayer::new()),
+        }
+    }
+
+    /// Post the notification webhook to send alerts to, or `None` to stop.
+    pub fn set_alert_webhook(&mut self, url: Option<String>) {
+        self.alert_webhook = url.map(|url| StdArc::new(AlertWebhook::new(url)));
+    }
+
+    /// Limit failed REST API authentications with the thresholds and trusted
+    /// proxies of `config`, dropping the failures counted so far.
+    pub fn set_auth_config(&mut self, config: &AuthConfig) {
+        self.auth_rate_limiter = StdArc::new(AuthRateLimiter::from_config(config));
+    }
+
+    /// Send a `SchemaChangeAlert` for each breaking release `tool` advertises
+    /// that `previous` did not.
+    fn alert_breaking_changes(&self, previous: &ToolRecord, tool: &ToolRecord) {
+        for entry in new_breaking_changes(&previous.changelog, &tool.changelog) {
+            tracing::warn!(
+                tool_id = %tool.id,
+                version = %entry.version,
+                "Tool advertises a breaking change"
+            );
+            if let Some(webhook) = &self.alert_webhook {
+                webhook.spawn_send(SchemaChangeAlert::new(tool, entry));
+            }
+        }
+    }
+
+    /// A tool's stored changelog, newest first, or `None` if no tool has this id.
+    pub async fn tool_changelog(&self, tool_id: &RecordId) -> Result<Option<Vec<ChangelogEntry>>> {
+        Ok(QueryBuilder::find_tool_by_id(&self.db, tool_id.clone())
+            .await?
+            .map(|tool| tool.changelog))
+    }
+
+    /// Diff between two recorded input schema versions of a tool, returned
+    /// with the versions compared.
+    ///
+    /// `to_version` defaults to the latest version and `from_version` to the
+    /// one before it. Returns `None` if no tool has this id or a requested
+    /// version was never recorded.
+    pub async fn tool_schema_diff(
+        &self,
+        tool_id: &RecordId,
+        from_version: Option<u32>,
+        to_version: Option<u32>,
+    ) -> Result<Option<(u32, u32, SchemaDiff)>> {
+        let Some(tool) = QueryBuilder::find_tool_by_id(&self.db, tool_id.clone()).await? else {
+            return Ok(None);
+        };
+        let mut history =
+            QueryBuilder::list_tool_schema_history(&self.db, &tool.service_id, &tool.name).await?;
+        if history.is_empty() {
+            // Tools discovered before schema history existed
+            QueryBuilder::record_tool_schema(
+                &self.db,
+                &tool.service_id,
+                &tool.name,
+                &tool.input_schema,
+                &tool.typed_input(),
+            )
+            .await?;
+            history =
+                QueryBuilder::list_tool_schema_history(&self.db, &tool.service_id, &tool.name)
+                    .await?;
+        }
+
+        let Some(latest) = history.last().map(|snapshot| snapshot.version) else {
+            return Ok(None);
+        };
+        let to_version = to_version.unwrap_or(latest);
+        let from_version = from_version.unwrap_or(to_version.saturating_sub(1).max(1));
+        let snapshot = |version: u32| history.iter().find(|s| s.version == version);
+        let (Some(from), Some(to)) = (snapshot(from_version), snapshot(to_version)) else {
+            return Ok(None);
+        };
+
+        Ok(Some((
+            from_version,
+            to_version,
+            SchemaDiff::between(&from.input_ty, &to.input_ty),
+        )))
+    }
+
+    /// Delete unused tools not seen by discovery in `max_age_days`, with their
+    /// edges, embeddings and any services left without tools, then rebuild
+    /// the knowledge graph.
+    pub async fn prune_stale_records(&mut self, max_age_days: u32) -> Result<PruneResult> {
+        let result =
+            QueryBuilder::prune_stale_records(&self.db, prune_cutoff(max_age_days), false).await?;
+        if result != PruneResult::default() {
+            self.knowledge_graph = KnowledgeGraph::build_from_database(&self.db).await?;
+            self.plan_cache.invalidate();
+            tracing::info!(
+                tools = result.tools_removed,
+                services = result.services_removed,
+                embeddings = result.embeddings_removed,
+                "Pruned stale records"
+            );
+        }
+        Ok(result)
+    }
+
+    /// What `prune_stale_records` would delete, without deleting anything.
+    pub async fn preview_stale_records(&self, max_age_days: u32) -> Result<PruneResult> {
+        QueryBuilder::prune_stale_records(&self.db, prune_cutoff(max_age_days), true).await
+    }
+
     /// Normalize tool input/output schemas into `TypedSchema` and persist them.
     pub async fn normalize_tool_types(&self) -> Result<()> {
         let mut res = self.db.query("SELECT * FROM tool").await?;
         let tools: Vec<ToolRecord> = res.take(0)?;
 
         for tool in tools {
-            let input_ty = crate::db::schema::TypedSchema::from_json_schema(&tool.input_schema);
+            let input_ty = JsonSchemaResolver::typed_schema(&tool.input_schema);
             let output_ty = tool
                 .output_schema
                 .as_ref()
-                .map(crate::db::schema::TypedSchema::from_json_schema);
+                .map(JsonSchemaResolver::typed_schema);
 
             self.db
                 .query(
@@ -256,12 +949,28 @@ impl Orchestrator {
     /// * `query` - Natural language query describing the desired tool
     /// * `context` - Optional JSON context to guide tool selection
     /// * `user_context` - Optional user context for multi-tenant filtering
+    /// * `options` - Deprecated-tool handling and the query's language
     pub async fn query_tools(
         &self,
         query: &str,
         context: Option<Value>,
         user_context: Option<&UserContext>,
+        options: &ToolQueryOptions,
     ) -> Result<Vec<ToolSelection>> {
+        let (selections, _) = self
+            .query_tools_with_steps(query, context, user_context, options)
+            .await?;
+        Ok(selections)
+    }
+
+    /// `query_tools`, also returning the number of facts symbolic inference derived.
+    async fn query_tools_with_steps(
+        &self,
+        query: &str,
+        context: Option<Value>,
+        user_context: Option<&UserContext>,
+        options: &ToolQueryOptions,
+    ) -> Result<(Vec<ToolSelection>, usize)> {
         // Import user filter for multi-tenant filtering
         use crate::orchestrator::user_filter::UserToolFilter;
 
@@ -270,11 +979,26 @@ impl Orchestrator {
             Some(ctx) => UserToolFilter::from_user_context(&self.db, ctx).await?,
             None => UserToolFilter::allow_all(),
         };
+        let language = options
+            .language
+            .as_deref()
+            .or_else(|| filter.preferred_language());
+        let country = options
+            .geo_override
+            .as_deref()
+            .or_else(|| user_context.and_then(UserContext::country_code));
         // Semantic search first
         let semantic_hits = {
             let mut embedding_manager = self.embedding_manager.lock().await;
             embedding_manager
-                .search_tools_by_embedding(query, 32, 0.25)
+                .search_tools_by_embedding(
+                    query,
+                    32,
+                    0.25,
+                    options.embedding_model.as_deref(),
+                    filter.preferences(),
+                    language,
+                )
                 .await?
         };
 
@@ -293,18 +1017,38 @@ impl Orchestrator {
             self.db.query("SELECT * FROM tool").await?.take(0)?
         };
 
-        // Apply user filter to tools (removes blocked services)
-        let tools = filter.filter_tools(tools);
+        // Apply user filter to tools (removes blocked services) and drop tools
+        // not offered in the user's country or below the required SLA, then
+        // tools whose service could not be restarted
+        let meets_sla = |tool: &ToolRecord| {
+            options
+                .required_sla
+                .as_ref()
+                .is_none_or(|required| tool.meets_sla(required))
+        };
+        let mut tools = filter.filter_tools(tools);
+        tools.retain(|t| t.is_available_in(country) && meets_sla(t));
+        let blocked: Vec<String> = tools
+            .iter()
+            .filter(|t| t.is_blocked())
+            .map(|t| t.name.clone())
+            .collect();
+        tools.retain(|t| !t.is_blocked());
 
         let context_map = context
             .map(|c| serde_json::from_value(c).unwrap_or_default())
             .unwrap_or_default();
 
-        let mut selections = {
+        let (mut selections, inference_steps) = {
             let mut symbolic_reasoner = self.symbolic_reasoner.lock().await;
-            symbolic_reasoner
+            // Tools seen by an earlier query may have been blocked since
+            for name in &blocked {
+                symbolic_reasoner.retract_tool_state(name)?;
+            }
+            let selections = symbolic_reasoner
                 .infer_tool_selection(query, &tools, &context_map)
-                .await?
+                .await?;
+            (selections, symbolic_reasoner.last_inference_steps())
         };
 
         // Apply trust boost for trusted services
@@ -317,7 +1061,11 @@ impl Orchestrator {
             for hit in &semantic_hits {
                 if let Some(tool) = &hit.tool {
                     // Skip blocked tools in fallback
-                    if !filter.is_tool_allowed(tool) {
+                    if !filter.is_tool_allowed(tool)
+                        || tool.is_blocked()
+                        || !tool.is_available_in(country)
+                        || !meets_sla(tool)
+                    {
                         continue;
                     }
                     let mut confidence = hit.similarity;
@@ -340,10 +1088,297 @@ impl Orchestrator {
                 }
             }
 
-            return Ok(fallback);
+            selections = fallback;
         }
 
-        Ok(selections)
+        if !options.include_deprecated {
+            for selection in &mut selections {
+                let deprecated = tools
+                    .iter()
+                    .any(|t| t.id == selection.tool_id && t.deprecation_notice.is_some());
+                if deprecated {
+                    selection.confidence *= DEPRECATED_CONFIDENCE_FACTOR;
+                }
+            }
+        }
+
+        self.record_confidence(&selections);
+        Ok((selections, inference_steps))
+    }
+
+    /// Estimate what answering `query` would cost, without executing any tool.
+    ///
+    /// Runs embedding search, symbolic inference and planning as an anonymous
+    /// caller. The expected tool calls are the plan's steps or, without a plan,
+    /// the best selection; each is priced by its `cost_per_call`. Embedding is
+    /// priced by `EmbeddingConfig::cost_per_1k_tokens` unless the query's
+    /// embedding is already cached.
+    pub async fn estimate_query_cost(
+        &self,
+        query: &str,
+        context: Option<Value>,
+    ) -> Result<CostEstimate> {
+        let (embedding_api_calls, cost_per_1k_tokens) = {
+            let embedding_manager = self.embedding_manager.lock().await;
+            (
+                u32::from(!embedding_manager.is_cached(query)),
+                embedding_manager.cost_per_1k_tokens(),
+            )
+        };
+
+        let (mut selections, inference_steps) = self
+            .query_tools_with_steps(query, context, None, &ToolQueryOptions::default())
+            .await?;
+        // Planning reuses the query embedding cached by the search above
+        let plan = match self.build_plan_for_query(query, None).await {
+            Ok(plan) => plan,
+            Err(e) => {
+                tracing::debug!("No plan for cost estimate of {:?}: {}", query, e);
+                None
+            }
+        };
+
+        let mut expected_tools = Vec::new();
+        match plan {
+            Some(plan) => {
+                for step in &plan.steps {
+                    if let Some(tool) =
+                        QueryBuilder::find_tool_by_name(&self.db, &step.service_id, &step.tool_name)
+                            .await?
+                    {
+                        expected_tools.push(tool);
+                    }
+                }
+            }
+            None => {
+                selections.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+                if let Some(best) = selections.first()
+                    && let Some(tool) =
+                        QueryBuilder::find_tool_by_id(&self.db, best.tool_id.clone()).await?
+                {
+                    expected_tools.push(tool);
+                }
+            }
+        }
+
+        Ok(CostEstimate::new(
+            query,
+            embedding_api_calls,
+            cost_per_1k_tokens,
+            u32::try_from(inference_steps).unwrap_or(u32::MAX),
+            &expected_tools,
+        ))
+    }
+
+    /// Get the `top_k` best tools for a query.
+    ///
+    /// With `enforce_diversity`, candidates are re-ranked by maximal marginal
+    /// relevance over their embeddings, so near-duplicate tools (typically the
+    /// same capability offered by several services) don't crowd out the rest.
+    /// See [`Self::query_tools`] for `options`.
+    pub async fn select_tools(
+        &self,
+        query: &str,
+        context: Option<Value>,
+        user_context: Option<&UserContext>,
+        top_k: usize,
+        enforce_diversity: bool,
+        options: &ToolQueryOptions,
+    ) -> Result<Vec<ToolSelection>> {
+        let mut selections = self
+            .query_tools(query, context, user_context, options)
+            .await?;
+        selections.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+
+        if !enforce_diversity || selections.len() <= 1 {
+            selections.truncate(top_k);
+            return Ok(selections);
+        }
+
+        let ids: Vec<RecordId> = selections.iter().map(|s| s.tool_id.clone()).collect();
+        let vectors =
+            crate::db::queries::QueryBuilder::tool_embedding_vectors(&self.db, &ids).await?;
+        let vectors: Vec<Vec<f32>> = selections
+            .iter()
+            .map(|s| {
+                vectors
+                    .get(&s.tool_id.to_string())
+                    .cloned()
+                    .unwrap_or_default()
+            })
+            .collect();
+        let relevance: Vec<f32> = selections.iter().map(|s| s.confidence).collect();
+        let picked =
+            maximal_marginal_relevance(&relevance, &pairwise_cosine(&vectors), top_k, MMR_LAMBDA);
+
+        Ok(picked.into_iter().map(|i| selections[i].clone()).collect())
+    }
+
+    /// Remember the confidence of each selected tool for later inspection.
+    fn record_confidence(&self, selections: &[ToolSelection]) {
+        let mut last = self
+            .last_confidence
+            .lock()
+            .expect("confidence lock poisoned");
+        for selection in selections {
+            last.insert(selection.tool_id.to_string(), selection.confidence);
+        }
+    }
+
+    /// Confidence reported for a tool the last time a query selected it.
+    pub fn last_query_confidence(&self, tool_id: &RecordId) -> Option<f32> {
+        self.last_confidence
+            .lock()
+            .expect("confidence lock poisoned")
+            .get(&tool_id.to_string())
+            .copied()
+    }
+
+    /// Find the `k` tools nearest to a pre-computed embedding vector.
+    ///
+    /// Blocked tools are dropped from the results.
+    pub async fn query_tools_by_vector(
+        &self,
+        embedding: Vec<f32>,
+        k: usize,
+        min_similarity: f32,
+    ) -> Result<Vec<EmbeddingSearchResult>> {
+        let mut hits = {
+            let embedding_manager = self.embedding_manager.lock().await;
+            embedding_manager
+                .nearest_neighbors(embedding, k, min_similarity)
+                .await?
+        };
+        hits.retain(|hit| hit.tool.as_ref().is_some_and(|t| !t.is_blocked()));
+        Ok(hits)
+    }
+
+    /// The symbolic rules currently used for inference.
+    pub async fn symbolic_rules(&self) -> Vec<SymbolicRule> {
+        self.symbolic_reasoner.lock().await.rules().to_vec()
+    }
+
+    /// Validate and store a symbolic rule, making it available to inference immediately.
+    pub async fn add_symbolic_rule(&self, rule: SymbolicRule) -> Result<RecordId> {
+        let mut symbolic_reasoner = self.symbolic_reasoner.lock().await;
+        let id = symbolic_reasoner.add_rule(rule).await?;
+        self.plan_cache.invalidate();
+        Ok(id)
+    }
+
+    /// Replace a live symbolic rule, applying the change to inference immediately.
+    ///
+    /// Returns `None` if no live rule has the ID of `rule`.
+    pub async fn update_symbolic_rule(&self, rule: SymbolicRule) -> Result<Option<SymbolicRule>> {
+        let mut symbolic_reasoner = self.symbolic_reasoner.lock().await;
+        let updated = symbolic_reasoner.update_rule(rule).await?;
+        if updated.is_some() {
+            self.plan_cache.invalidate();
+        }
+        Ok(updated)
+    }
+
+    /// Soft-delete a symbolic rule, removing it from inference immediately.
+    ///
+    /// Returns `false` if no live rule has the given ID.
+    pub async fn remove_symbolic_rule(&self, rule_id: &RecordId) -> Result<bool> {
+        let mut symbolic_reasoner = self.symbolic_reasoner.lock().await;
+        let removed = symbolic_reasoner.remove_rule(rule_id).await?;
+        if removed {
+            self.plan_cache.invalidate();
+        }
+        Ok(removed)
+    }
+
+    /// Make a blocked tool available again, e.g. once a missing environment
+    /// variable is set. Returns `None` if no tool has the given ID.
+    pub async fn unblock_tool(&self, tool_id: &str) -> Result<Option<ToolRecord>> {
+        let tool = QueryBuilder::unblock_tool(&self.db, tool_id).await?;
+        if tool.is_some() {
+            self.plan_cache.invalidate();
+        }
+        Ok(tool)
+    }
+
+    /// Report which stored tools the loaded symbolic rules select on their own
+    /// and how often each rule fires. Blocked tools are left out.
+    pub async fn rule_coverage(&self) -> Result<RuleCoverageReport> {
+        let mut tools: Vec<ToolRecord> = self.db.query("SELECT * FROM tool").await?.take(0)?;
+        tools.retain(|t| !t.is_blocked());
+
+        let mut symbolic_reasoner = self.symbolic_reasoner.lock().await;
+        symbolic_reasoner.compute_rule_coverage(&tools)
+    }
+
+    /// Explain why two tools' embeddings are (dis)similar.
+    pub async fn explain_tool_similarity(
+        &self,
+        tool_a_id: &str,
+        tool_b_id: &str,
+    ) -> Result<SimilarityExplanation> {
+        let embedding_manager = self.embedding_manager.lock().await;
+        embedding_manager
+            .explain_similarity(tool_a_id, tool_b_id)
+            .await
+    }
+
+    /// Pairs of services whose tools embed alike, most similar first.
+    pub async fn service_similarity(&self) -> Result<Vec<(ServiceId, ServiceId, f32)>> {
+        let embedding_manager = self.embedding_manager.lock().await;
+        embedding_manager.cross_service_similarity_matrix().await
+    }
+
+    /// A tool's description before and after the embedding normalization pipeline.
+    pub async fn normalized_tool_description(
+        &self,
+        tool_id: &RecordId,
+    ) -> Result<NormalizedDescription> {
+        let embedding_manager = self.embedding_manager.lock().await;
+        embedding_manager.describe_normalization(tool_id).await
+    }
+
+    /// Set or clear the embedding model used for a user's searches.
+    ///
+    /// Fails with [`EmbeddingError::BackendNotAllowed`] unless the model is
+    /// the global one or listed in `allowed_backend_overrides`.
+    pub async fn set_user_embedding_backend(
+        &self,
+        user: &UserContext,
+        model_name: Option<&str>,
+    ) -> Result<Option<EmbeddingBackendConfig>> {
+        let backend = match model_name {
+            Some(model_name) => Some(
+                self.embedding_manager
+                    .lock()
+                    .await
+                    .allowed_backend(model_name)
+                    .ok_or_else(|| EmbeddingError::BackendNotAllowed(model_name.to_string()))?,
+            ),
+            None => None,
+        };
+
+        UserStore::new(self.db.clone())
+            .set_embedding_backend_override(user.user_id(), backend.clone())
+            .await?;
+        Ok(backend)
+    }
+
+    /// Replace the active API key with `key_prefix` by a new key for the same
+    /// user; the old key is rejected as revoked from then on.
+    ///
+    /// Returns the new full key and its prefix, or `None` if no active key
+    /// has `key_prefix`.
+    pub async fn rotate_api_key(&self, key_prefix: &str) -> Result<Option<(String, ApiKeyPrefix)>> {
+        let Some(old) = QueryBuilder::find_api_key_by_prefix(&self.db, key_prefix)
+            .await?
+            .filter(|key| key.is_active)
+        else {
+            return Ok(None);
+        };
+        let (full_key, prefix, _) = UserStore::new(self.db.clone())
+            .rotate_api_key(&old.key_hash)
+            .await?;
+        Ok(Some((full_key, prefix)))
     }
 
     /// Get the single best tool for a query.
@@ -358,20 +1393,67 @@ impl Orchestrator {
         context: Option<Value>,
         user_context: Option<&UserContext>,
     ) -> Result<Option<ToolSelection>> {
-        let selections = self.query_tools(query, context, user_context).await?;
+        let selections = self
+            .query_tools(query, context, user_context, &ToolQueryOptions::default())
+            .await?;
         Ok(selections.into_iter().next())
     }
 
-    /// Plan a multi-step tool sequence for a query.
-    ///
-    /// # Arguments
-    /// * `query` - Natural language query describing the goal
-    /// * `context` - Optional JSON context to guide tool planning
-    /// * `user_context` - Optional user context for multi-tenant filtering
-    pub async fn plan_tools_for_query(
+    /// Plan a multi-step tool sequence for a query.
+    ///
+    /// Identical queries within the plan cache's TTL reuse the earlier result.
+    ///
+    /// # Arguments
+    /// * `query` - Natural language query describing the goal
+    /// * `context` - Optional JSON context to guide tool planning
+    /// * `user_context` - Optional user context for multi-tenant filtering
+    pub async fn plan_tools_for_query(
+        &self,
+        query: &str,
+        context: Option<Value>,
+        user_context: Option<&UserContext>,
+    ) -> Result<Option<PlanResult>> {
+        let scope = user_context.map(|ctx| ctx.user_id_string());
+        let key = PlanCache::key(query, context.as_ref(), scope.as_deref());
+        if let Some(plan) = self.plan_cache.get(&key) {
+            self.remember_plans(plan.iter());
+            return Ok(plan);
+        }
+
+        let plan = self.build_plan_for_query(query, user_context).await?;
+        self.plan_cache.insert(key, plan.clone());
+        self.remember_plans(plan.iter());
+        Ok(plan)
+    }
+
+    /// Keep `plans` for `find_plan`, dropping the oldest beyond `RECENT_PLAN_LIMIT`.
+    fn remember_plans<'a>(&self, plans: impl IntoIterator<Item = &'a PlanResult>) {
+        let mut recent = self
+            .recent_plans
+            .lock()
+            .expect("recent plans lock poisoned");
+        for plan in plans {
+            recent.retain(|p| p.id != plan.id);
+            recent.push_back(plan.clone());
+        }
+        while recent.len() > RECENT_PLAN_LIMIT {
+            recent.pop_front();
+        }
+    }
+
+    /// A plan recently returned by `plan_tools_for_query` or `plan_tools_by_type`.
+    pub fn find_plan(&self, id: &str) -> Option<PlanResult> {
+        self.recent_plans
+            .lock()
+            .expect("recent plans lock poisoned")
+            .iter()
+            .find(|plan| plan.id == id)
+            .cloned()
+    }
+
+    async fn build_plan_for_query(
         &self,
         query: &str,
-        _context: Option<Value>,
         user_context: Option<&UserContext>,
     ) -> Result<Option<PlanResult>> {
         // Import user filter for multi-tenant filtering
@@ -386,7 +1468,14 @@ impl Orchestrator {
         let semantic_hits = {
             let mut embedding_manager = self.embedding_manager.lock().await;
             embedding_manager
-                .search_tools_by_embedding(query, 32, 0.25)
+                .search_tools_by_embedding(
+                    query,
+                    32,
+                    0.25,
+                    None,
+                    filter.preferences(),
+                    filter.preferred_language(),
+                )
                 .await?
         };
 
@@ -405,8 +1494,12 @@ impl Orchestrator {
             self.db.query("SELECT * FROM tool").await?.take(0)?
         };
 
-        // Apply user filter to tools (removes blocked services)
-        let tools = filter.filter_tools(tools);
+        // Apply user filter to tools (removes blocked services) and drop tools
+        // not offered in the user's country, then tools whose service could
+        // not be restarted
+        let country = user_context.and_then(UserContext::country_code);
+        let mut tools = filter.filter_tools(tools);
+        tools.retain(|t| !t.is_blocked() && t.is_available_in(country));
 
         if tools.is_empty() {
             return Ok(None);
@@ -425,7 +1518,7 @@ impl Orchestrator {
                 .await?
         };
 
-        let plan = match plan_opt {
+        let mut plan = match plan_opt {
             Some(p) => p,
             None => return Ok(None),
         };
@@ -434,6 +1527,12 @@ impl Orchestrator {
             return Ok(None);
         }
 
+        // Make sure every step follows the steps whose output it consumes
+        KnowledgeGraph::topological_sort(&mut plan.steps)
+            .map_err(|e| PlanningError::CyclicDependency(e.steps))?;
+        self.check_step_types(&plan.steps, &tool_map)?;
+        let diagram = plan.to_mermaid();
+
         let mut steps = Vec::new();
         for step in plan.steps {
             if let Some(tool) = tool_map.get(&step.tool_id) {
@@ -447,10 +1546,19 @@ impl Orchestrator {
                     inputs.extend(props.keys().cloned());
                 }
 
-                let description = tool
-                    .description
-                    .clone()
-                    .unwrap_or_else(|| format!("Step {}: call {}", step.step_number, tool.name));
+                let description = match &step.description_template {
+                    Some(template) => {
+                        let mut vars = step.inputs.clone();
+                        vars.entry("step_number".to_string())
+                            .or_insert_with(|| Value::from(step.step_number));
+                        vars.entry("tool_name".to_string())
+                            .or_insert_with(|| Value::from(tool.name.clone()));
+                        TemplateEngine::render(template, &vars)
+                    }
+                    None => tool.description.clone().unwrap_or_else(|| {
+                        format!("Step {}: call {}", step.step_number, tool.name)
+                    }),
+                };
 
                 steps.push(PlanStep {
                     description,
@@ -472,19 +1580,225 @@ impl Orchestrator {
         );
 
         Ok(Some(PlanResult {
+            id: plan.id,
             steps,
             confidence: plan.confidence,
             reasoning,
+            diagram,
         }))
     }
 
+    /// Check that every step accepts the output of the steps it depends on.
+    ///
+    /// Fails with [`PlanningError::SuggestedBridges`] at the first dependency
+    /// whose output doesn't fit, if the knowledge graph has tools that could
+    /// convert it. Mismatches nothing can bridge are left to the caller.
+    #[allow(clippy::mutable_key_type)]
+    fn check_step_types(
+        &self,
+        steps: &[crate::knowledge_graph::PlanStep],
+        tool_map: &HashMap<RecordId, ToolRecord>,
+    ) -> Result<(), PlanningError> {
+        let tool_of = |step_number: u32| {
+            steps
+                .iter()
+                .find(|step| step.step_number == step_number)
+                .and_then(|step| tool_map.get(&step.tool_id))
+        };
+        for step in steps {
+            let Some(tool) = tool_of(step.step_number) else {
+                continue;
+            };
+            for dependency in step.dependencies.iter().filter_map(|dep| tool_of(*dep)) {
+                let Some(output) = dependency.typed_output() else {
+                    continue;
+                };
+                if output.structural_compatibility_score(&tool.typed_input())
+                    >= MIN_EDGE_COMPATIBILITY
+                {
+                    continue;
+                }
+                let bridges = self.knowledge_graph.find_intermediary_tools(
+                    &ToolId::new(dependency.id.to_string()),
+                    &ToolId::new(tool.id.to_string()),
+                );
+                if !bridges.is_empty() {
+                    return Err(PlanningError::SuggestedBridges(bridges));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Recorder for users' feedback on tool selections.
+    ///
+    /// The query is embedded so the feedback can later be matched against
+    /// similar queries; it takes effect on the next feedback refresh.
+    pub fn selection_feedback_recorder(&self) -> FeedbackRecorder {
+        FeedbackRecorder::new(self.db.clone(), self.embedding_manager.clone())
+    }
+
+    /// Plan tool chains that turn a value of `input_type_uri` into `output_type_uri`.
+    ///
+    /// Walks the type-URI graph instead of matching text, which is more
+    /// reliable for structured pipelines. Returns up to three plans, cheapest
+    /// by [`KnowledgeGraph::path_cost`] first, or an empty list when no chain
+    /// exists. Chains costing more than `constraints.max_path_cost` are dropped.
+    pub async fn plan_tools_by_type(
+        &self,
+        input_type_uri: &str,
+        output_type_uri: &str,
+        constraints: &PlanningConstraints,
+        user_context: Option<&UserContext>,
+    ) -> Result<Vec<PlanResult>> {
+        use crate::orchestrator::user_filter::UserToolFilter;
+
+        let filter = match user_context {
+            Some(ctx) => UserToolFilter::from_user_context(&self.db, ctx).await?,
+            None => UserToolFilter::allow_all(),
+        };
+
+        let tools: Vec<ToolRecord> = self.db.query("SELECT * FROM tool").await?.take(0)?;
+        let mut tools = filter.filter_tools(tools);
+        tools.retain(|t| !t.is_blocked());
+
+        let graph = TypeUriGraph::from_tools(tools);
+        let mut paths = graph.all_paths(input_type_uri, output_type_uri, TYPED_PLAN_MAX_DEPTH);
+        if let Some(max_path_cost) = constraints.max_path_cost {
+            paths.retain(|path| KnowledgeGraph::path_cost(path) <= max_path_cost);
+        }
+
+        let plans: Vec<PlanResult> = TypeUriGraph::rank_paths(paths, TYPED_PLAN_ALTERNATIVES)
+            .into_iter()
+            .map(|path| typed_plan(&path, input_type_uri, output_type_uri))
+            .collect();
+        self.remember_plans(&plans);
+        Ok(plans)
+    }
+
     /// Execute a selected tool (without approval checks - for internal use).
+    ///
+    /// Tools with a `required_role` are refused, as there is no user to hold it.
     pub async fn execute_selected_tool(
         &self,
         selection: &ToolSelection,
         args: JsonObject,
     ) -> Result<Vec<rmcp::model::Content>> {
-        crate::executor::execute_selection(&self.db, &self.running_services, selection, args).await
+        self.check_required_role(selection, None).await?;
+        self.execute_for_user(selection, args, None).await
+    }
+
+    /// Refuse to run a tool whose `required_role` the user doesn't hold,
+    /// with a [`MissingRoleError`]. Calls without a user hold no roles.
+    ///
+    /// Returns the role the tool requires, if any.
+    async fn check_required_role(
+        &self,
+        selection: &ToolSelection,
+        user_context: Option<&UserContext>,
+    ) -> Result<Option<String>> {
+        let Some(required_role) =
+            QueryBuilder::find_tool_by_id(&self.db, selection.tool_id.clone())
+                .await?
+                .and_then(|tool| tool.required_role)
+        else {
+            return Ok(None);
+        };
+        if !user_context.is_some_and(|ctx| ctx.has_role(&required_role)) {
+            tracing::warn!(
+                tool_id = %selection.tool_id,
+                required_role = %required_role,
+                "Tool execution refused: user lacks the required role"
+            );
+            return Err(MissingRoleError {
+                tool_id: selection.tool_id.to_string(),
+                required_role,
+            }
+            .into());
+        }
+        Ok(Some(required_role))
+    }
+
+    /// Channel carrying the outcome of every tool execution; call
+    /// `subscribe()` on it to receive events from then on.
+    pub fn execution_events(&self) -> &ExecutionEventSender {
+        &self.execution_events
+    }
+
+    /// Handle for shutting down gracefully, usable without access to the
+    /// orchestrator itself.
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        ShutdownHandle {
+            in_flight: self.in_flight.clone(),
+            elicitation: self.elicitation_coordinator.clone(),
+            sessions: self.sessions.clone(),
+        }
+    }
+
+    /// Cache of recent `plan_tools_for_query` results.
+    pub fn plan_cache(&self) -> &PlanCache {
+        &self.plan_cache
+    }
+
+    /// Drop the cached results of one tool, or of every tool, so the next
+    /// calls reach the services. Returns the number of results dropped.
+    pub fn clear_tool_cache(&self, tool_id: Option<&RecordId>) -> usize {
+        let cleared = self.tool_result_cache.clear(tool_id);
+        tracing::info!(?tool_id, "Cleared {} cached tool results", cleared);
+        cleared
+    }
+
+    /// Metrics rendered on `GET /metrics`.
+    pub fn metrics(&self) -> &MetricsCollector {
+        &self.metrics
+    }
+
+    /// Sessions of the MCP clients currently connected.
+    pub fn sessions(&self) -> &StdArc<SessionRegistry> {
+        &self.sessions
+    }
+
+    /// Failed API key authentications per client IP on the REST API.
+    pub fn auth_rate_limiter(&self) -> &StdArc<AuthRateLimiter> {
+        &self.auth_rate_limiter
+    }
+
+    /// Execute a tool and broadcast the outcome, attributed to `user_id`.
+    async fn execute_for_user(
+        &self,
+        selection: &ToolSelection,
+        args: JsonObject,
+        user_id: Option<&ExternalUserId>,
+    ) -> Result<Vec<rmcp::model::Content>> {
+        let _in_flight = self
+            .in_flight
+            .begin()
+            .ok_or_else(|| anyhow!("Orchestrator is shutting down"))?;
+        let started = std::time::Instant::now();
+        let trust_level = self.trust_level(&selection.service_id).await;
+        let result = crate::executor::execute_selection(
+            &self.db,
+            &self.running_services,
+            selection,
+            args,
+            &trust_level,
+            &self.tool_result_cache,
+        )
+        .await;
+
+        // Sending only fails when nobody is subscribed
+        let _ = self.execution_events.send(ToolExecutionEvent {
+            tool_id: selection.tool_id.to_string(),
+            tool_name: selection.tool_name.clone(),
+            service_id: selection.service_id.to_string(),
+            user_id: user_id.cloned(),
+            success: result.is_ok(),
+            error: result.as_ref().err().map(|e| e.to_string()),
+            duration_ms: started.elapsed().as_millis() as u64,
+            timestamp: chrono::Utc::now(),
+        });
+
+        result
     }
 
     /// Execute a selected tool with approval checks.
@@ -497,6 +1811,8 @@ impl Orchestrator {
     /// * `selection` - The tool selection to execute
     /// * `args` - Arguments to pass to the tool
     /// * `user_context` - Optional user context for permission checks
+    /// * `elicitation` - Coordinator of the calling client's session, which
+    ///   approval requests are sent to
     ///
     /// # Returns
     /// * `Ok(contents)` - Tool execution results if approved
@@ -506,6 +1822,7 @@ impl Orchestrator {
         selection: &ToolSelection,
         args: JsonObject,
         user_context: Option<&UserContext>,
+        elicitation: &ElicitationCoordinator,
     ) -> Result<Vec<rmcp::model::Content>> {
         // Get user ID - use "anonymous" for stdio/local mode
         let user_id = ExternalUserId::new(
@@ -524,50 +1841,293 @@ impl Orchestrator {
         let tool_id = ToolId::new(selection.tool_id.to_string());
         let service_id = ServiceId::new(selection.service_id.to_string());
 
+        let required_role = match self.check_required_role(selection, user_context).await {
+            Ok(required_role) => required_role,
+            Err(e) => {
+                if let Some(missing) = e.downcast_ref::<MissingRoleError>() {
+                    self.audit_log(AuditLogCreate {
+                        user_id: Some(user_id.to_string()),
+                        action: AuditAction::PermissionDenied.as_str().to_string(),
+                        resource_type: "tool".to_string(),
+                        resource_id: Some(tool_id.to_string()),
+                        details: Some(serde_json::json!({
+                            "service_id": service_id.to_string(),
+                            "reason": "missing_role",
+                            "required_role": missing.required_role,
+                        })),
+                        ip_address: user_context
+                            .and_then(|ctx| ctx.ip_address().map(|s| s.to_string())),
+                        user_agent: user_context
+                            .and_then(|ctx| ctx.user_agent().map(|s| s.to_string())),
+                    })
+                    .await;
+                }
+                return Err(e);
+            }
+        };
+
+        let request = ApprovalRequest {
+            arguments: Some(serde_json::to_value(&args).unwrap_or_default()),
+            tool_id,
+            service_id,
+            service_name,
+            user_id,
+            required_role,
+            granter_roles: user_context
+                .map(|ctx| ctx.roles().to_vec())
+                .unwrap_or_default(),
+        };
+        let approval = self
+            .approve(&request, "tool", user_context, elicitation)
+            .await?;
+
+        let result = self
+            .execute_and_record_usage(selection, args, &request.user_id)
+            .await;
+
+        // Audit log the execution
+        self.audit_log(AuditLogCreate {
+            user_id: Some(request.user_id.to_string()),
+            action: AuditAction::ToolExecuted.as_str().to_string(),
+            resource_type: "tool".to_string(),
+            resource_id: Some(request.tool_id.to_string()),
+            details: Some(serde_json::json!({
+                "service_id": request.service_id.to_string(),
+                "service_name": request.service_name.to_string(),
+                "success": result.is_ok(),
+                "permission_type": approval.permission_type,
+            })),
+            ip_address: user_context.and_then(|ctx| ctx.ip_address().map(|s| s.to_string())),
+            user_agent: user_context.and_then(|ctx| ctx.user_agent().map(|s| s.to_string())),
+        })
+        .await;
+
+        if approval.consume_after {
+            let _ = elicitation
+                .approval_manager()
+                .consume_permission(&request.tool_id, &request.service_id, &request.user_id)
+                .await;
+        }
+
+        result
+    }
+
+    /// Write `content` to a resource (by URI or namespaced name) on behalf of
+    /// a user, with the role check, approval and audit logging of
+    /// [`Self::execute_selected_tool_with_approval`].
+    ///
+    /// Writes are approved per resource, so `always_allow` covers later
+    /// writes to the same URI. A resource declaring `_meta.required_role`
+    /// can only be written by users holding that role.
+    pub async fn write_resource_with_approval(
+        &self,
+        uri_or_name: &str,
+        content: Vec<u8>,
+        mime_type: &str,
+        user_context: Option<&UserContext>,
+        elicitation: &ElicitationCoordinator,
+    ) -> Result<()> {
+        let resource = self.resource_forwarder.find_resource(uri_or_name).await?;
+        let user_id = ExternalUserId::new(
+            user_context
+                .map(|ctx| ctx.user_id_string())
+                .unwrap_or_else(|| "anonymous".to_string()),
+        );
+        let permission_id = ToolId::new(format!(
+            "{}{}",
+            WRITE_RESOURCE_PERMISSION_PREFIX, resource.uri
+        ));
+
+        if let Some(required_role) = &resource.required_role
+            && !user_context.is_some_and(|ctx| ctx.has_role(required_role))
+        {
+            tracing::warn!(
+                uri = %resource.uri,
+                required_role = %required_role,
+                "Resource write refused: user lacks the required role"
+            );
+            self.audit_log(AuditLogCreate {
+                user_id: Some(user_id.to_string()),
+                action: AuditAction::PermissionDenied.as_str().to_string(),
+                resource_type: "resource".to_string(),
+                resource_id: Some(resource.uri.to_string()),
+                details: Some(serde_json::json!({
+                    "service_id": resource.service_id.to_string(),
+                    "reason": "missing_role",
+                    "required_role": required_role,
+                })),
+                ip_address: user_context.and_then(|ctx| ctx.ip_address().map(|s| s.to_string())),
+                user_agent: user_context.and_then(|ctx| ctx.user_agent().map(|s| s.to_string())),
+            })
+            .await;
+            return Err(MissingRoleError {
+                tool_id: resource.uri.to_string(),
+                required_role: required_role.clone(),
+            }
+            .into());
+        }
+
+        let bytes = content.len();
+        let request = ApprovalRequest {
+            tool_id: permission_id,
+            service_id: resource.service_id.clone(),
+            service_name: resource.service_name.clone(),
+            user_id,
+            arguments: Some(serde_json::json!({
+                "uri": resource.uri.to_string(),
+                "mime_type": mime_type,
+                "bytes": bytes,
+            })),
+            required_role: resource.required_role.clone(),
+            granter_roles: user_context
+                .map(|ctx| ctx.roles().to_vec())
+                .unwrap_or_default(),
+        };
+        let approval = self
+            .approve(&request, "resource", user_context, elicitation)
+            .await?;
+
+        let result = self
+            .resource_forwarder
+            .write_resource(resource.uri.as_str(), content, mime_type)
+            .await;
+
+        self.audit_log(AuditLogCreate {
+            user_id: Some(request.user_id.to_string()),
+            action: AuditAction::ResourceWritten.as_str().to_string(),
+            resource_type: "resource".to_string(),
+            resource_id: Some(resource.uri.to_string()),
+            details: Some(serde_json::json!({
+                "service_id": request.service_id.to_string(),
+                "service_name": request.service_name.to_string(),
+                "bytes": bytes,
+                "success": result.is_ok(),
+                "permission_type": approval.permission_type,
+            })),
+            ip_address: user_context.and_then(|ctx| ctx.ip_address().map(|s| s.to_string())),
+            user_agent: user_context.and_then(|ctx| ctx.user_agent().map(|s| s.to_string())),
+        })
+        .await;
+
+        if approval.consume_after {
+            let _ = elicitation
+                .approval_manager()
+                .consume_permission(&request.tool_id, &request.service_id, &request.user_id)
+                .await;
+        }
+
+        Ok(result?)
+    }
+
+    /// Execute a tool and record the invocation in `tool_usage_event`.
+    ///
+    /// Arguments injected from the user's identity are left out of the
+    /// record. Recording failures are logged but never fail the execution.
+    async fn execute_and_record_usage(
+        &self,
+        selection: &ToolSelection,
+        args: JsonObject,
+        user_id: &ExternalUserId,
+    ) -> Result<Vec<rmcp::model::Content>> {
+        let recorded_args = args.clone();
+        let started = std::time::Instant::now();
+        let result = self.execute_for_user(selection, args, Some(user_id)).await;
+        let duration_ms = u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX);
+
+        let recorded = async {
+            let tool = QueryBuilder::find_tool_by_id(&self.db, selection.tool_id.clone())
+                .await?
+                .ok_or_else(|| anyhow!("tool no longer exists"))?;
+            QueryBuilder::record_tool_usage(
+                &self.db,
+                &selection.tool_id,
+                user_id,
+                &crate::executor::strip_injected_args(&tool, &recorded_args),
+                result.is_ok(),
+                duration_ms,
+            )
+            .await
+        };
+        if let Err(e) = recorded.await {
+            tracing::warn!(tool_id = %selection.tool_id, "Failed to record tool usage: {}", e);
+        }
+
+        result
+    }
+
+    /// Suggest complete argument sets for a tool from the user's past
+    /// successful invocations.
+    ///
+    /// Candidates are ranked by key similarity to `partial_args` and by
+    /// recency; at most five are returned.
+    pub async fn suggest_arguments(
+        &self,
+        tool_id: &RecordId,
+        partial_args: &JsonObject,
+        user_id: &ExternalUserId,
+    ) -> Result<Vec<ArgumentSuggestion>> {
+        let events = crate::db::queries::QueryBuilder::list_successful_tool_usage(
+            &self.db,
+            tool_id,
+            user_id,
+            SUGGESTION_HISTORY_LIMIT,
+        )
+        .await?;
+
+        Ok(rank_argument_suggestions(
+            events,
+            partial_args,
+            MAX_SUGGESTIONS,
+        ))
+    }
+
+    /// Check the user's stored permission for `request` and, if there is
+    /// none, ask for approval through the calling session's `elicitation`
+    /// coordinator.
+    ///
+    /// `resource_type` is recorded in the audit log entries written here.
+    /// Fails if the user denies or cancels, or if the client can't be asked
+    /// and the fallback policy denies.
+    async fn approve(
+        &self,
+        request: &ApprovalRequest,
+        resource_type: &str,
+        user_context: Option<&UserContext>,
+        elicitation: &ElicitationCoordinator,
+    ) -> Result<Approval> {
+        let ApprovalRequest {
+            tool_id,
+            service_id,
+            service_name,
+            user_id,
+            ..
+        } = request;
+
         // Check existing permission
-        let approval_manager = self.elicitation_coordinator.approval_manager();
+        let approval_manager = elicitation.approval_manager();
         let permission_status = approval_manager
-            .check_permission(&tool_id, &service_id, &user_id)
+            .check_permission(tool_id, service_id, user_id)
             .await
             .map_err(|e| anyhow!("Failed to check permission: {:?}", e))?;
 
         match permission_status {
             PermissionStatus::Granted => {
-                // Permission already granted - execute the tool
                 tracing::debug!(
                     tool_id = %tool_id,
                     user_id = %user_id,
                     "Tool execution approved (existing permission)"
                 );
-                let result = self.execute_selected_tool(selection, args).await;
-
-                // Audit log the execution
-                self.audit_log(AuditLogCreate {
-                    user_id: Some(user_id.to_string()),
-                    action: AuditAction::ToolExecuted.as_str().to_string(),
-                    resource_type: "tool".to_string(),
-                    resource_id: Some(tool_id.to_string()),
-                    details: Some(serde_json::json!({
-                        "service_id": service_id.to_string(),
-                        "service_name": service_name.to_string(),
-                        "success": result.is_ok(),
-                        "permission_type": "existing",
-                    })),
-                    ip_address: user_context
-                        .and_then(|ctx| ctx.ip_address().map(|s| s.to_string())),
-                    user_agent: user_context
-                        .and_then(|ctx| ctx.user_agent().map(|s| s.to_string())),
-                })
-                .await;
-
-                result
+                return Ok(Approval {
+                    permission_type: "existing".to_string(),
+                    consume_after: false,
+                });
             }
             PermissionStatus::Denied => {
                 // User previously denied this tool
                 self.audit_log(AuditLogCreate {
                     user_id: Some(user_id.to_string()),
                     action: AuditAction::PermissionDenied.as_str().to_string(),
-                    resource_type: "tool".to_string(),
+                    resource_type: resource_type.to_string(),
                     resource_id: Some(tool_id.to_string()),
                     details: Some(serde_json::json!({
                         "service_id": service_id.to_string(),
@@ -580,86 +2140,58 @@ impl Orchestrator {
                 })
                 .await;
 
-                Err(anyhow!("Tool execution denied by user"))
-            }
-            PermissionStatus::Expired => {
-                // Permission expired - need to re-approve
-                self.request_tool_approval(
-                    selection,
-                    args,
-                    &tool_id,
-                    &service_id,
-                    &service_name,
-                    &user_id,
-                )
-                .await
-            }
-            PermissionStatus::Required => {
-                // No permission yet - need to request approval
-                self.request_tool_approval(
-                    selection,
-                    args,
-                    &tool_id,
-                    &service_id,
-                    &service_name,
-                    &user_id,
-                )
-                .await
+                return Err(anyhow!("Tool execution denied by user"));
             }
+            // No permission yet, or it expired - need to request approval
+            PermissionStatus::Expired | PermissionStatus::Required => {}
         }
-    }
 
-    /// Request approval from the user via elicitation.
-    async fn request_tool_approval(
-        &self,
-        selection: &ToolSelection,
-        args: JsonObject,
-        tool_id: &ToolId,
-        service_id: &ServiceId,
-        service_name: &ServiceName,
-        user_id: &ExternalUserId,
-    ) -> Result<Vec<rmcp::model::Content>> {
         // Check if client supports elicitation
-        if !self
-            .elicitation_coordinator
-            .client_supports_elicitation()
-            .await
-        {
+        if !elicitation.client_supports_elicitation().await {
             // Client doesn't support elicitation - check fallback policy
-            let policy = self.elicitation_coordinator.fallback_policy().await;
-            match policy {
+            let policy = elicitation.fallback_policy().await;
+            return match policy {
                 ElicitationFallbackPolicy::Allow => {
                     tracing::warn!(
                         tool_id = %tool_id,
                         "Client does not support elicitation, allowing tool execution (fallback policy: allow)"
                     );
-                    return self.execute_selected_tool(selection, args).await;
+                    Ok(Approval {
+                        permission_type: "fallback_allow".to_string(),
+                        consume_after: false,
+                    })
                 }
                 ElicitationFallbackPolicy::Deny => {
                     tracing::warn!(
                         tool_id = %tool_id,
                         "Client does not support elicitation, denying tool execution (fallback policy: deny)"
                     );
-                    return Err(anyhow!(
+                    Err(anyhow!(
                         "Tool execution denied: client does not support elicitation and fallback policy is set to deny"
-                    ));
+                    ))
                 }
-            }
+                ElicitationFallbackPolicy::AskOnce { cache_result } => {
+                    tracing::warn!(
+                        tool_id = %tool_id,
+                        cache_result,
+                        "Client does not support elicitation, allowing tool execution once (fallback policy: ask_once)"
+                    );
+                    // Granted like an `AllowOnce` through elicitation; unless
+                    // `cache_result` is set it is consumed after the execution
+                    approval_manager
+                        .grant_permission(request, ApprovalAction::AllowOnce)
+                        .await
+                        .map_err(|e| anyhow!("Failed to grant permission: {:?}", e))?;
+                    Ok(Approval {
+                        permission_type: "fallback_ask_once".to_string(),
+                        consume_after: !cache_result,
+                    })
+                }
+            };
         }
 
-        let approval_manager = self.elicitation_coordinator.approval_manager();
-
-        // Create the approval request
-        let request = ApprovalRequest {
-            tool_id: tool_id.clone(),
-            service_id: service_id.clone(),
-            service_name: service_name.clone(),
-            user_id: user_id.clone(),
-            arguments: Some(serde_json::to_value(&args).unwrap_or_default()),
-        };
-
         // Create the elicitation schema and message
-        let (message, schema) = approval_manager.create_approval_elicitation(&request);
+        let (message, schema) = approval_manager.create_approval_elicitation(request);
 
         tracing::info!(
             tool_id = %tool_id,
@@ -669,15 +2201,14 @@ impl Orchestrator {
         );
 
         // Send the elicitation request
-        let result = self
-            .elicitation_coordinator
-            .create_elicitation(&message, schema)
+        let result = elicitation
+            .create_user_elicitation(user_id, tool_id, service_name.as_str(), message, schema)
             .await
             .map_err(|e| anyhow!("Failed to send elicitation request: {:?}", e))?;
 
         // Handle the response
         let permission_status = approval_manager
-            .handle_approval_response(&request, &result)
+            .handle_approval_response(request, &result)
             .await
             .map_err(|e| anyhow!("Failed to handle approval response: {:?}", e))?;
 
@@ -695,54 +2226,32 @@ impl Orchestrator {
                     .as_ref()
                     .and_then(|c| c.get("action"))
                     .and_then(|v| v.as_str())
-                    .unwrap_or("unknown");
-
-                // Check if this was a one-time approval
-                let is_one_time = permission_type == "allow_once";
+                    .unwrap_or("unknown")
+                    .to_string();
 
-                // Execute the tool
-                let exec_result = self.execute_selected_tool(selection, args).await;
-
-                // Audit log the permission grant and execution
+                // Audit log the permission grant
                 self.audit_log(AuditLogCreate {
                     user_id: Some(user_id.to_string()),
                     action: AuditAction::PermissionGranted.as_str().to_string(),
-                    resource_type: "tool".to_string(),
+                    resource_type: resource_type.to_string(),
                     resource_id: Some(tool_id.to_string()),
                     details: Some(serde_json::json!({
                         "service_id": service_id.to_string(),
                         "service_name": service_name.to_string(),
                         "permission_type": permission_type,
                     })),
-                    ip_address: None,
-                    user_agent: None,
+                    ip_address: user_context
+                        .and_then(|ctx| ctx.ip_address().map(|s| s.to_string())),
+                    user_agent: user_context
+                        .and_then(|ctx| ctx.user_agent().map(|s| s.to_string())),
                 })
                 .await;
 
-                self.audit_log(AuditLogCreate {
-                    user_id: Some(user_id.to_string()),
-                    action: AuditAction::ToolExecuted.as_str().to_string(),
-                    resource_type: "tool".to_string(),
-                    resource_id: Some(tool_id.to_string()),
-                    details: Some(serde_json::json!({
-                        "service_id": service_id.to_string(),
-                        "service_name": service_name.to_string(),
-                        "success": exec_result.is_ok(),
-                        "permission_type": permission_type,
-                    })),
-                    ip_address: None,
-                    user_agent: None,
+                // One-time permissions are consumed after execution
+                Ok(Approval {
+                    consume_after: permission_type == "allow_once",
+                    permission_type,
                 })
-                .await;
-
-                // Consume one-time permission after execution
-                if is_one_time {
-                    let _ = approval_manager
-                        .consume_permission(tool_id, service_id, user_id)
-                        .await;
-                }
-
-                exec_result
             }
             PermissionStatus::Denied => {
                 tracing::info!(
@@ -755,15 +2264,17 @@ impl Orchestrator {
                 self.audit_log(AuditLogCreate {
                     user_id: Some(user_id.to_string()),
                     action: AuditAction::PermissionDenied.as_str().to_string(),
-                    resource_type: "tool".to_string(),
+                    resource_type: resource_type.to_string(),
                     resource_id: Some(tool_id.to_string()),
                     details: Some(serde_json::json!({
                         "service_id": service_id.to_string(),
                         "service_name": service_name.to_string(),
                         "reason": "user_denied",
                     })),
-                    ip_address: None,
-                    user_agent: None,
+                    ip_address: user_context
+                        .and_then(|ctx| ctx.ip_address().map(|s| s.to_string())),
+                    user_agent: user_context
+                        .and_then(|ctx| ctx.user_agent().map(|s| s.to_string())),
                 })
                 .await;
 
@@ -777,6 +2288,29 @@ impl Orchestrator {
     }
 
     /// Look up the service name by ID.
+    /// Trust level of a service: from its `mcp.json` entry if it has one,
+    /// otherwise `Community` for registry-synced services and `Trusted` for
+    /// the rest.
+    async fn trust_level(&self, service_id: &RecordId) -> TrustLevel {
+        let configured = self
+            .service_configs
+            .lock()
+            .await
+            .values()
+            .find(|configured| configured.service_id.as_ref() == Some(service_id))
+            .map(|configured| configured.config.trust_level().clone());
+        if let Some(trust_level) = configured {
+            return trust_level;
+        }
+
+        match QueryBuilder::find_service_by_id(&self.db, service_id).await {
+            Ok(Some(service)) if matches!(service.origin, ServiceOrigin::Registry) => {
+                TrustLevel::community_default()
+            }
+            _ => TrustLevel::Trusted,
+        }
+    }
+
     async fn get_service_name(&self, service_id: &RecordId) -> Option<String> {
         let query = "SELECT * FROM service WHERE id = $id LIMIT 1";
         let mut res = self
@@ -800,12 +2334,61 @@ impl Orchestrator {
         &self.knowledge_graph
     }
 
-    /// Get reference to running services map.
-    #[allow(clippy::mutable_key_type)]
-    pub fn running_services(&self) -> &HashMap<RecordId, Arc<RunningService>> {
+    /// Merge the graph of another orchestrator into this one's, so plans can
+    /// use its tools. The merged nodes last until the graph is next rebuilt
+    /// from the database.
+    pub fn merge_knowledge_graph(&mut self, other: KnowledgeGraph) -> MergeResult {
+        let graph = std::mem::take(&mut self.knowledge_graph);
+        let (merged, result) = graph.merge(other);
+        self.knowledge_graph = merged;
+        self.plan_cache.invalidate();
+        result
+    }
+
+    /// Get reference to the shared running services map.
+    pub fn running_services(&self) -> &SharedRunningServices {
         &self.running_services
     }
 
+    /// List every known service with its tool count and health.
+    ///
+    /// Joins the `service` table with the running-services map: services that
+    /// are not running are `disconnected`, and running services without a
+    /// database record are `ephemeral`.
+    pub async fn list_services(&self) -> Result<Vec<ServiceInfo>> {
+        let services: Vec<ServiceRecord> = self
+            .db
+            .query("SELECT * FROM service ORDER BY updated_at DESC")
+            .await?
+            .take(0)?;
+        let tool_counts =
+            crate::db::queries::QueryBuilder::tool_counts_by_service(&self.db).await?;
+        let running: HashMap<String, bool> = self
+            .running_services
+            .lock()
+            .await
+            .iter()
+            .map(|(id, service)| (id.clone(), service.is_closed()))
+            .collect();
+
+        Ok(build_service_infos(
+            services,
+            &tool_counts,
+            &running,
+            &self.supervisor.health_checks(),
+        ))
+    }
+
+    /// Get reference to the service supervisor.
+    pub fn supervisor(&self) -> &StdArc<ServiceSupervisor> {
+        &self.supervisor
+    }
+
+    /// Startup timeouts per service, as counted by discovery and restarts.
+    pub fn startup_failures(&self) -> &StartupFailures {
+        &self.startup_failures
+    }
+
     /// Get reference to the prompt forwarder.
     pub fn prompt_forwarder(&self) -> &StdArc<PromptForwarder> {
         &self.prompt_forwarder
@@ -821,6 +2404,11 @@ impl Orchestrator {
         &self.resource_forwarder
     }
 
+    /// Resource subscriptions created by MCP clients.
+    pub fn resource_subscriptions(&self) -> &StdArc<ResourceSubscriptions> {
+        &self.resource_subscriptions
+    }
+
     /// Discover resources from all running services.
     pub async fn discover_resources(&self) -> Result<usize> {
         self.resource_forwarder.discover_resources().await
@@ -828,11 +2416,7 @@ impl Orchestrator {
 
     /// Get running services as a String-keyed map for use by the prompt forwarder.
     pub async fn running_services_as_string_map(&self) -> HashMap<String, StdArc<RunningService>> {
-        let mut map = HashMap::new();
-        for (id, service) in &self.running_services {
-            map.insert(id.to_string(), service.clone());
-        }
-        map
+        self.running_services.lock().await.clone()
     }
 
     /// Get reference to the elicitation coordinator.
@@ -871,3 +2455,137 @@ impl Orchestrator {
         }
     }
 }
+
+/// Turn a type-URI chain into a plan; shorter chains get higher confidence.
+fn typed_plan(path: &[ToolRecord], input_type_uri: &str, output_type_uri: &str) -> PlanResult {
+    let steps =
+        path.iter()
+            .map(|tool| {
+                let from = type_uri(&tool.input_schema);
+                let to = tool
+                    .output_schema
+                    .as_ref()
+                    .map(type_uri)
+                    .unwrap_or_default();
+                let inputs = tool
+                    .input_schema
+                    .get("properties")
+                    .and_then(|v| v.as_object())
+                    .map(|props| props.keys().cloned().collect())
+                    .unwrap_or_default();
+
+                PlanStep {
+                    description: tool.description.clone().unwrap_or_else(|| {
+                        format!("Convert {} to {} with {}", from, to, tool.name)
+                    }),
+                    service_id: tool.service_id.clone(),
+                    tool_name: tool.name.clone(),
+                    inputs,
+                }
+            })
+            .collect::<Vec<_>>();
+
+    // Each tool in the chain consumes the output of the one before it
+    let chain = ToolPlan {
+        id: uuid::Uuid::new_v4().to_string(),
+        goal: format!("{} -> {}", input_type_uri, output_type_uri),
+        steps: path
+            .iter()
+            .zip(1u32..)
+            .map(|(tool, step_number)| crate::knowledge_graph::PlanStep {
+                step_number,
+                tool_id: tool.id.clone(),
+                tool_name: tool.name.clone(),
+                inputs: HashMap::new(),
+                expected_outputs: Vec::new(),
+                parallel: false,
+                dependencies: if step_number > 1 {
+                    vec![step_number - 1]
+                } else {
+                    Vec::new()
+                },
+                description_template: None,
+            })
+            .collect(),
+        estimated_cost: 0.0,
+        estimated_time: 0.0,
+        confidence: 0.0,
+    };
+
+    let cost = KnowledgeGraph::path_cost(path);
+    PlanResult {
+        id: chain.id.clone(),
+        diagram: chain.to_mermaid(),
+        confidence: 1.0 / steps.len().max(1) as f32,
+        reasoning: format!(
+            "Typed plan from \"{}\" to \"{}\" using {} steps (path cost {:.2}).",
+            input_type_uri,
+            output_type_uri,
+            steps.len(),
+            cost
+        ),
+        steps,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn tool(name: &str, from: &str, to: &str) -> ToolRecord {
+        ToolRecord {
+            id: RecordId::from_table_key("tool", name),
+            service_id: RecordId::from_table_key("service", "convert"),
+            name: name.to_string(),
+            description: None,
+            input_schema: json!({"$id": from, "properties": {"data": {}}})
+                .as_object()
+                .cloned()
+                .unwrap(),
+            output_schema: json!({"$id": to}).as_object().cloned(),
+            embedding_id: None,
+            input_ty: None,
+            output_ty: None,
+            usage_count: 0,
+            status: None,
+            discovery_source: Default::default(),
+            metadata: Default::default(),
+            input_examples: Vec::new(),
+            changelog: Vec::new(),
+            deprecation_notice: None,
+            descriptions: HashMap::new(),
+            inject_user_context: Vec::new(),
+            geo_availability: None,
+            required_env_vars: Vec::new(),
+            sla_class: None,
+            cost_per_call: None,
+            cache_ttl_seconds: None,
+            required_role: None,
+            actual_latency_p99_ms: None,
+            actual_error_rate_pct: None,
+            block_reason: None,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    #[test]
+    fn test_typed_plan_describes_each_hop() {
+        let path = vec![
+            tool("csv_to_json", "uri:Csv", "uri:Json"),
+            tool("json_to_report", "uri:Json", "uri:Report"),
+        ];
+        let plan = typed_plan(&path, "uri:Csv", "uri:Report");
+
+        assert_eq!(plan.steps.len(), 2);
+        assert_eq!(plan.steps[0].tool_name, "csv_to_json");
+        assert_eq!(
+            plan.steps[0].description,
+            "Convert uri:Csv to uri:Json with csv_to_json"
+        );
+        assert_eq!(plan.steps[1].inputs, vec!["data".to_string()]);
+        assert_eq!(plan.confidence, 0.5);
+        assert!(plan.reasoning.contains("path cost 0.00"));
+    }
+}
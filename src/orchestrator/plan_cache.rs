@@ -0,0 +1,247 @@
+//! Short-lived cache of `plan_tools_for_query` results.
+//!
+//! Agents in retry loops often ask for the same plan many times in a row.
+//! Results (including "no plan") are kept for a fixed time and dropped
+//! whenever tools or symbolic rules change.
+
+use serde::Serialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::orchestrator::PlanResult;
+
+/// Plans kept before the oldest is evicted.
+pub const DEFAULT_PLAN_CACHE_CAPACITY: usize = 256;
+
+/// How long a cached plan stays valid.
+pub const DEFAULT_PLAN_CACHE_TTL: Duration = Duration::from_secs(60);
+
+struct CachedPlan {
+    plan: Option<PlanResult>,
+    created_at: Instant,
+}
+
+/// Hit/miss counters and size of a [`PlanCache`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlanCacheStats {
+    pub entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+    /// Hits divided by lookups; `0.0` before the first lookup.
+    pub hit_ratio: f64,
+}
+
+/// Bounded, time-limited cache of plans keyed by query and context.
+pub struct PlanCache {
+    capacity: usize,
+    ttl: Duration,
+    entries: Mutex<HashMap<String, CachedPlan>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl PlanCache {
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            capacity,
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Cache key for a query and its optional context.
+    ///
+    /// Object keys in the context are sorted first so that equal contexts
+    /// always hash the same regardless of key order. `scope` keeps plans
+    /// built for different users apart, since they see different tools.
+    pub fn key(query: &str, context: Option<&Value>, scope: Option<&str>) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(query.as_bytes());
+        hasher.update([0]);
+        if let Some(context) = context {
+            hasher.update(canonical_json(context).to_string().as_bytes());
+        }
+        hasher.update([0]);
+        hasher.update(scope.unwrap_or_default().as_bytes());
+        format!("{:x}", hasher.finalize())
+    }
+
+    /// Look up a plan. The outer `Option` is `None` on a miss; a hit may
+    /// still hold `None` when the query produced no plan.
+    pub fn get(&self, key: &str) -> Option<Option<PlanResult>> {
+        let mut entries = self.entries.lock().expect("plan cache lock poisoned");
+        let cached = match entries.get(key) {
+            Some(entry) if entry.created_at.elapsed() < self.ttl => Some(entry.plan.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        };
+
+        let counter = if cached.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        cached
+    }
+
+    /// Store a plan, evicting expired entries and then the oldest one if the
+    /// cache is full.
+    pub fn insert(&self, key: String, plan: Option<PlanResult>) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut entries = self.entries.lock().expect("plan cache lock poisoned");
+        if !entries.contains_key(&key) && entries.len() >= self.capacity {
+            entries.retain(|_, entry| entry.created_at.elapsed() < self.ttl);
+            if entries.len() >= self.capacity
+                && let Some(oldest) = entries
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.created_at)
+                    .map(|(key, _)| key.clone())
+            {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(
+            key,
+            CachedPlan {
+                plan,
+                created_at: Instant::now(),
+            },
+        );
+    }
+
+    /// Drop every cached plan, e.g. after tools or rules changed.
+    pub fn invalidate(&self) {
+        self.entries
+            .lock()
+            .expect("plan cache lock poisoned")
+            .clear();
+    }
+
+    pub fn stats(&self) -> PlanCacheStats {
+        let entries = self.entries.lock().expect("plan cache lock poisoned");
+        let hits = self.hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        let lookups = hits + misses;
+        PlanCacheStats {
+            entries: entries
+                .values()
+                .filter(|entry| entry.created_at.elapsed() < self.ttl)
+                .count(),
+            hits,
+            misses,
+            hit_ratio: if lookups == 0 {
+                0.0
+            } else {
+                hits as f64 / lookups as f64
+            },
+        }
+    }
+}
+
+impl Default for PlanCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_PLAN_CACHE_CAPACITY, DEFAULT_PLAN_CACHE_TTL)
+    }
+}
+
+/// Copy of `value` with object keys in sorted order at every level.
+pub(crate) fn canonical_json(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            Value::Object(
+                keys.into_iter()
+                    .map(|key| (key.clone(), canonical_json(&map[key])))
+                    .collect(),
+            )
+        }
+        Value::Array(items) => Value::Array(items.iter().map(canonical_json).collect()),
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn plan(reasoning: &str) -> Option<PlanResult> {
+        Some(PlanResult {
+            id: reasoning.to_string(),
+            steps: Vec::new(),
+            confidence: 0.9,
+            reasoning: reasoning.to_string(),
+            diagram: String::new(),
+        })
+    }
+
+    #[test]
+    fn test_key_ignores_context_key_order() {
+        let a = json!({ "city": "Paris", "options": { "units": "metric", "days": 3 } });
+        let b = json!({ "options": { "days": 3, "units": "metric" }, "city": "Paris" });
+        assert_eq!(
+            PlanCache::key("weather", Some(&a), None),
+            PlanCache::key("weather", Some(&b), None)
+        );
+        assert_ne!(
+            PlanCache::key("weather", Some(&a), None),
+            PlanCache::key("weather", None, None)
+        );
+        assert_ne!(
+            PlanCache::key("weather", None, Some("user:alice")),
+            PlanCache::key("weather", None, Some("user:bob"))
+        );
+    }
+
+    #[test]
+    fn test_get_counts_hits_and_misses() {
+        let cache = PlanCache::new(8, Duration::from_secs(60));
+        assert!(cache.get("a").is_none());
+
+        cache.insert("a".to_string(), plan("first"));
+        cache.insert("b".to_string(), None);
+        assert_eq!(cache.get("a").unwrap().unwrap().reasoning, "first");
+        // A cached "no plan" is still a hit
+        assert!(cache.get("b").unwrap().is_none());
+
+        let stats = cache.stats();
+        assert_eq!(stats.entries, 2);
+        assert_eq!((stats.hits, stats.misses), (2, 1));
+        assert!((stats.hit_ratio - 2.0 / 3.0).abs() < 1e-9);
+
+        cache.invalidate();
+        assert!(cache.get("a").is_none());
+        assert_eq!(cache.stats().entries, 0);
+    }
+
+    #[test]
+    fn test_entries_expire_and_oldest_is_evicted() {
+        let cache = PlanCache::new(2, Duration::from_secs(60));
+        cache.insert("a".to_string(), plan("a"));
+        cache.insert("b".to_string(), plan("b"));
+        cache.insert("c".to_string(), plan("c"));
+        assert!(cache.get("a").is_none());
+        assert!(cache.get("b").is_some());
+        assert!(cache.get("c").is_some());
+
+        let cache = PlanCache::new(2, Duration::ZERO);
+        cache.insert("a".to_string(), plan("a"));
+        assert!(cache.get("a").is_none());
+        assert_eq!(cache.stats().entries, 0);
+    }
+}
@@ -0,0 +1,109 @@
+//! Hot-reloading of `mcp.json`: work out which services were added, changed
+//! or removed since the config was last loaded.
+
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::config::McpServiceConfig;
+
+/// Server names from `mcp.json` affected by `Orchestrator::reload_config`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ConfigReloadResult {
+    pub added: Vec<String>,
+    pub updated: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl ConfigReloadResult {
+    /// Whether the reload changed nothing.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.updated.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Compare the loaded configs, keyed by server name, with a freshly read set.
+///
+/// Names in each list are sorted so results don't depend on map order.
+pub fn diff_configs(
+    current: &HashMap<String, McpServiceConfig>,
+    configs: &[McpServiceConfig],
+) -> ConfigReloadResult {
+    let mut result = ConfigReloadResult::default();
+    for config in configs {
+        let name = config.id().as_str().to_string();
+        match current.get(&name) {
+            None => result.added.push(name),
+            Some(existing) if existing != config => result.updated.push(name),
+            Some(_) => {}
+        }
+    }
+    result.removed = current
+        .keys()
+        .filter(|name| !configs.iter().any(|c| c.id().as_str() == name.as_str()))
+        .cloned()
+        .collect();
+
+    result.added.sort();
+    result.updated.sort();
+    result.removed.sort();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ServiceConfigId;
+    use std::collections::BTreeMap;
+
+    fn config(name: &str, command: &str) -> McpServiceConfig {
+        McpServiceConfig::Stdio {
+            id: ServiceConfigId::new(name),
+            command: command.to_string(),
+            args: vec![],
+            env: BTreeMap::new(),
+            disabled: false,
+            auto_approve: vec![],
+            disabled_tools: vec![],
+            conflict_resolution: Default::default(),
+            elicitation_timeout_secs: 300,
+            sampling_provenance: true,
+            sampling_provenance_prefix: None,
+            startup_timeout_secs: 30,
+            trust_level: Default::default(),
+            security: Default::default(),
+            command_allowlist: None,
+        }
+    }
+
+    #[test]
+    fn test_diff_configs() {
+        let current = HashMap::from([
+            ("fs".to_string(), config("fs", "mcp-fs")),
+            ("git".to_string(), config("git", "mcp-git")),
+            ("old".to_string(), config("old", "mcp-old")),
+        ]);
+        let configs = vec![
+            config("fs", "mcp-fs"),
+            config("git", "mcp-git --verbose"),
+            config("web", "mcp-web"),
+        ];
+
+        let result = diff_configs(&current, &configs);
+        assert_eq!(result.added, vec!["web"]);
+        assert_eq!(result.updated, vec!["git"]);
+        assert_eq!(result.removed, vec!["old"]);
+        assert!(!result.is_empty());
+
+        assert!(
+            diff_configs(
+                &current,
+                &[
+                    config("fs", "mcp-fs"),
+                    config("git", "mcp-git"),
+                    config("old", "mcp-old")
+                ]
+            )
+            .is_empty()
+        );
+    }
+}
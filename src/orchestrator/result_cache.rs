@@ -0,0 +1,224 @@
+//! In-process cache of tool results.
+//!
+//! Tools that declare a `cache_ttl_seconds` have the result of each call kept
+//! for that long, keyed by tool and a hash of the arguments. An identical
+//! call within the TTL is answered from the cache instead of the service.
+//! Only successful calls are cached.
+//!
+//! The cache holds at most [`MAX_CACHED_RESULTS`] results. Expired results
+//! are dropped when looked up, or all at once when the cache fills up; if it
+//! is still full, the result closest to expiry makes room for the new one.
+
+use dashmap::DashMap;
+use rmcp::model::{Content, JsonObject};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::time::{Duration, Instant};
+use surrealdb::RecordId;
+
+use crate::orchestrator::plan_cache::canonical_json;
+
+/// Most results kept at once, across all tools.
+pub const MAX_CACHED_RESULTS: usize = 1000;
+
+struct CachedResult {
+    content: Vec<Content>,
+    expires_at: Instant,
+}
+
+/// Tool results keyed by `(tool id, SHA-256 of the arguments)`.
+pub struct ToolResultCache {
+    entries: DashMap<(String, String), CachedResult>,
+    max_entries: usize,
+}
+
+impl Default for ToolResultCache {
+    fn default() -> Self {
+        Self::with_max_entries(MAX_CACHED_RESULTS)
+    }
+}
+
+impl ToolResultCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A cache holding at most `max_entries` results.
+    pub fn with_max_entries(max_entries: usize) -> Self {
+        Self {
+            entries: DashMap::new(),
+            max_entries,
+        }
+    }
+
+    /// Cache key for a call. Object keys in the arguments are sorted first,
+    /// so equal arguments always hash the same.
+    fn key(tool_id: &RecordId, args: &JsonObject) -> (String, String) {
+        let args = canonical_json(&Value::Object(args.clone()));
+        let hash = Sha256::digest(args.to_string().as_bytes());
+        (tool_id.to_string(), format!("{:x}", hash))
+    }
+
+    /// The cached result of calling `tool_id` with `args`, unless it has
+    /// expired by `now`.
+    pub fn get(&self, tool_id: &RecordId, args: &JsonObject, now: Instant) -> Option<Vec<Content>> {
+        let key = Self::key(tool_id, args);
+        if let Some(entry) = self.entries.get(&key)
+            && now < entry.expires_at
+        {
+            return Some(entry.content.clone());
+        }
+        self.entries
+            .remove_if(&key, |_, entry| now >= entry.expires_at);
+        None
+    }
+
+    /// Keep the result of calling `tool_id` with `args` until `ttl` after `now`.
+    pub fn insert(
+        &self,
+        tool_id: &RecordId,
+        args: &JsonObject,
+        content: Vec<Content>,
+        ttl: Duration,
+        now: Instant,
+    ) {
+        let key = Self::key(tool_id, args);
+        if self.entries.len() >= self.max_entries && !self.entries.contains_key(&key) {
+            self.make_room(now);
+        }
+        self.entries.insert(
+            key,
+            CachedResult {
+                content,
+                expires_at: now + ttl,
+            },
+        );
+    }
+
+    /// Drop expired results, or the one closest to expiry if none has expired.
+    fn make_room(&self, now: Instant) {
+        self.entries.retain(|_, entry| now < entry.expires_at);
+        if self.entries.len() < self.max_entries {
+            return;
+        }
+        let soonest = self
+            .entries
+            .iter()
+            .min_by_key(|entry| entry.expires_at)
+            .map(|entry| entry.key().clone());
+        if let Some(key) = soonest {
+            self.entries.remove(&key);
+        }
+    }
+
+    /// Drop the cached results of one tool, or of every tool.
+    ///
+    /// Returns the number of results dropped.
+    pub fn clear(&self, tool_id: Option<&RecordId>) -> usize {
+        let before = self.entries.len();
+        match tool_id {
+            Some(tool_id) => {
+                let tool_id = tool_id.to_string();
+                self.entries.retain(|(id, _), _| *id != tool_id);
+            }
+            None => self.entries.clear(),
+        }
+        before - self.entries.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn args(value: Value) -> JsonObject {
+        value.as_object().cloned().unwrap()
+    }
+
+    #[test]
+    fn test_hit_until_ttl_expires() {
+        let cache = ToolResultCache::new();
+        let tool = RecordId::from_table_key("tool", "weather");
+        let now = Instant::now();
+        cache.insert(
+            &tool,
+            &args(json!({ "city": "Berlin", "days": 2 })),
+            vec![Content::text("sunny")],
+            Duration::from_secs(30),
+            now,
+        );
+
+        // Argument order doesn't matter, argument values do
+        let hit = cache.get(&tool, &args(json!({ "days": 2, "city": "Berlin" })), now);
+        assert_eq!(hit, Some(vec![Content::text("sunny")]));
+        assert!(
+            cache
+                .get(&tool, &args(json!({ "city": "Paris", "days": 2 })), now)
+                .is_none()
+        );
+
+        let later = now + Duration::from_secs(30);
+        assert!(
+            cache
+                .get(&tool, &args(json!({ "city": "Berlin", "days": 2 })), later)
+                .is_none()
+        );
+        // The expired result was dropped on lookup
+        assert_eq!(cache.clear(None), 0);
+    }
+
+    #[test]
+    fn test_full_cache_evicts_expired_then_soonest_to_expire() {
+        let cache = ToolResultCache::with_max_entries(2);
+        let tool = RecordId::from_table_key("tool", "weather");
+        let now = Instant::now();
+        let city = |name: &str| args(json!({ "city": name }));
+        cache.insert(&tool, &city("Berlin"), vec![], Duration::from_secs(10), now);
+        cache.insert(&tool, &city("Paris"), vec![], Duration::from_secs(60), now);
+
+        // Nothing has expired, so Berlin, the soonest to expire, goes
+        cache.insert(&tool, &city("Rome"), vec![], Duration::from_secs(30), now);
+        assert!(cache.get(&tool, &city("Berlin"), now).is_none());
+        assert!(cache.get(&tool, &city("Paris"), now).is_some());
+
+        // Rome has expired by then and makes room instead of Paris
+        let later = now + Duration::from_secs(40);
+        cache.insert(&tool, &city("Oslo"), vec![], Duration::from_secs(60), later);
+        assert!(cache.get(&tool, &city("Paris"), later).is_some());
+        assert!(cache.get(&tool, &city("Oslo"), later).is_some());
+        assert_eq!(cache.clear(None), 2);
+    }
+
+    #[test]
+    fn test_clear_one_tool_or_all() {
+        let cache = ToolResultCache::new();
+        let weather = RecordId::from_table_key("tool", "weather");
+        let search = RecordId::from_table_key("tool", "search");
+        let now = Instant::now();
+        let ttl = Duration::from_secs(60);
+        cache.insert(
+            &weather,
+            &args(json!({ "city": "Berlin" })),
+            vec![],
+            ttl,
+            now,
+        );
+        cache.insert(
+            &weather,
+            &args(json!({ "city": "Paris" })),
+            vec![],
+            ttl,
+            now,
+        );
+        cache.insert(&search, &args(json!({ "q": "rust" })), vec![], ttl, now);
+
+        assert_eq!(cache.clear(Some(&weather)), 2);
+        assert!(
+            cache
+                .get(&search, &args(json!({ "q": "rust" })), now)
+                .is_some()
+        );
+        assert_eq!(cache.clear(None), 1);
+    }
+}
@@ -0,0 +1,171 @@
+//! Service metadata for operators.
+//!
+//! Joins the `service` table with the in-memory running-services map so that
+//! services which failed to start, or which are running without a database
+//! record, are both visible.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::db::ServiceRecord;
+
+/// Health of a service as seen by the orchestrator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceHealth {
+    /// Running with an open client session.
+    Healthy,
+    /// Running, but its client session has closed; the supervisor restarts it.
+    Exited,
+    /// Known to the database but not running.
+    Disconnected,
+    /// Running but has no database record.
+    Ephemeral,
+}
+
+/// Metadata for a single service.
+#[derive(Debug, Clone, Serialize)]
+pub struct ServiceInfo {
+    pub id: String,
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub tool_count: usize,
+    pub health_status: ServiceHealth,
+    /// When the supervisor last checked the session; `None` if it never has.
+    pub last_health_check: Option<DateTime<Utc>>,
+}
+
+/// Build service info from database records and the running-services map.
+///
+/// `running` maps service id to whether its client session is closed, and
+/// `health_checks` to when it was last checked. Database services keep their
+/// order; ephemeral services follow, sorted by id.
+pub fn build_service_infos(
+    services: Vec<ServiceRecord>,
+    tool_counts: &HashMap<String, usize>,
+    running: &HashMap<String, bool>,
+    health_checks: &HashMap<String, DateTime<Utc>>,
+) -> Vec<ServiceInfo> {
+    let mut infos: Vec<ServiceInfo> = services
+        .into_iter()
+        .map(|service| {
+            let id = service.id.to_string();
+            let health_status = match running.get(&id) {
+                Some(false) => ServiceHealth::Healthy,
+                Some(true) => ServiceHealth::Exited,
+                None => ServiceHealth::Disconnected,
+            };
+            ServiceInfo {
+                last_health_check: health_checks.get(&id).copied(),
+                tool_count: tool_counts.get(&id).copied().unwrap_or(0),
+                id,
+                name: service.name,
+                version: Some(service.version),
+                health_status,
+            }
+        })
+        .collect();
+
+    let mut ephemeral: Vec<&String> = running
+        .keys()
+        .filter(|id| !infos.iter().any(|info| &info.id == *id))
+        .collect();
+    ephemeral.sort();
+
+    infos.extend(ephemeral.into_iter().map(|id| ServiceInfo {
+        id: id.clone(),
+        name: None,
+        version: None,
+        tool_count: tool_counts.get(id).copied().unwrap_or(0),
+        health_status: ServiceHealth::Ephemeral,
+        last_health_check: health_checks.get(id).copied(),
+    }));
+
+    infos
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::schema::ServiceOrigin;
+    use surrealdb::RecordId;
+
+    fn service(key: &str) -> ServiceRecord {
+        ServiceRecord {
+            id: RecordId::from_table_key("service", key),
+            name: Some(key.to_string()),
+            title: None,
+            version: "1.0.0".to_string(),
+            icons: None,
+            website_url: None,
+            origin: ServiceOrigin::StaticConfig,
+            registry_id: None,
+            icon_fetch_failed: false,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    #[test]
+    fn test_build_service_infos_joins_db_and_running() {
+        let now = Utc::now();
+        let tool_counts = HashMap::from([("service:up".to_string(), 3)]);
+        let running = HashMap::from([
+            ("service:up".to_string(), false),
+            ("service:closed".to_string(), true),
+            ("service:zeta".to_string(), false),
+            ("service:extra".to_string(), false),
+        ]);
+
+        // `extra` started after the supervisor's last check
+        let health_checks = HashMap::from([
+            ("service:up".to_string(), now),
+            ("service:closed".to_string(), now),
+            ("service:zeta".to_string(), now),
+        ]);
+
+        let infos = build_service_infos(
+            vec![service("up"), service("closed"), service("down")],
+            &tool_counts,
+            &running,
+            &health_checks,
+        );
+
+        let summary: Vec<(&str, ServiceHealth)> = infos
+            .iter()
+            .map(|i| (i.id.as_str(), i.health_status))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("service:up", ServiceHealth::Healthy),
+                ("service:closed", ServiceHealth::Exited),
+                ("service:down", ServiceHealth::Disconnected),
+                ("service:extra", ServiceHealth::Ephemeral),
+                ("service:zeta", ServiceHealth::Ephemeral),
+            ]
+        );
+
+        assert_eq!(infos[0].tool_count, 3);
+        assert_eq!(infos[0].last_health_check, Some(now));
+        assert_eq!(infos[2].tool_count, 0);
+        assert_eq!(infos[2].last_health_check, None);
+        assert_eq!(infos[3].name, None);
+        assert_eq!(infos[3].version, None);
+        assert_eq!(infos[3].last_health_check, None);
+        assert_eq!(infos[4].last_health_check, Some(now));
+    }
+
+    #[test]
+    fn test_service_health_serializes_snake_case() {
+        assert_eq!(
+            serde_json::to_value(ServiceHealth::Disconnected).unwrap(),
+            "disconnected"
+        );
+        assert_eq!(
+            serde_json::to_value(ServiceHealth::Ephemeral).unwrap(),
+            "ephemeral"
+        );
+    }
+}
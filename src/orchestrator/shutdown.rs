@@ -0,0 +1,163 @@
+//! Graceful shutdown: stop taking new work, cancel pending elicitations and
+//! wait for running tool executions to finish.
+
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore, watch};
+
+use crate::elicitation::ElicitationCoordinator;
+use crate::session::SessionRegistry;
+
+/// Upper bound on concurrently tracked executions; draining waits until all
+/// of these permits are free again.
+const MAX_IN_FLIGHT_EXECUTIONS: u32 = u32::MAX >> 3;
+
+/// Counts in-flight tool executions with a semaphore so shutdown can wait for
+/// them. Clones share the same count.
+#[derive(Clone)]
+pub struct InFlightTracker {
+    permits: Arc<Semaphore>,
+    shutting_down: watch::Sender<bool>,
+}
+
+impl InFlightTracker {
+    pub fn new() -> Self {
+        Self {
+            permits: Arc::new(Semaphore::new(MAX_IN_FLIGHT_EXECUTIONS as usize)),
+            shutting_down: watch::Sender::new(false),
+        }
+    }
+
+    /// Register an execution; it counts as in flight until the permit is
+    /// dropped. Returns `None` once shutdown has started.
+    pub fn begin(&self) -> Option<OwnedSemaphorePermit> {
+        if self.is_shutting_down() {
+            return None;
+        }
+        self.permits.clone().try_acquire_owned().ok()
+    }
+
+    /// Number of executions currently running.
+    pub fn in_flight(&self) -> usize {
+        MAX_IN_FLIGHT_EXECUTIONS as usize - self.permits.available_permits()
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        *self.shutting_down.borrow()
+    }
+
+    /// Refuse new executions from now on.
+    pub fn stop_accepting(&self) {
+        self.shutting_down.send_replace(true);
+    }
+
+    /// Resolves once shutdown has started.
+    pub async fn stopped(&self) {
+        let mut rx = self.shutting_down.subscribe();
+        // The sender lives in `self`, so this can't fail
+        let _ = rx.wait_for(|shutting_down| *shutting_down).await;
+    }
+
+    /// Wait up to `timeout` for running executions to finish. Returns whether
+    /// everything finished in time.
+    pub async fn drain(&self, timeout: Duration) -> bool {
+        match tokio::time::timeout(timeout, self.permits.acquire_many(MAX_IN_FLIGHT_EXECUTIONS))
+            .await
+        {
+            Ok(Ok(permits)) => {
+                // Keep the permits so nothing can start after draining
+                permits.forget();
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Default for InFlightTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Everything needed to shut down an orchestrator without holding on to it,
+/// so it can run while the orchestrator itself is locked by a request.
+#[derive(Clone)]
+pub struct ShutdownHandle {
+    pub(crate) in_flight: InFlightTracker,
+    pub(crate) elicitation: Arc<ElicitationCoordinator>,
+    pub(crate) sessions: Arc<SessionRegistry>,
+}
+
+impl ShutdownHandle {
+    pub fn is_shutting_down(&self) -> bool {
+        self.in_flight.is_shutting_down()
+    }
+
+    /// Resolves once shutdown has started; use it to stop accepting connections.
+    pub async fn stopped(&self) {
+        self.in_flight.stopped().await
+    }
+
+    /// Stop accepting tool executions, cancel pending elicitations and wait up
+    /// to `timeout` for running executions to finish.
+    ///
+    /// Elicitations are cancelled before waiting because an execution may be
+    /// blocked on the user's answer. Returns whether all executions finished.
+    pub async fn shutdown(&self, timeout: Duration) -> bool {
+        self.in_flight.stop_accepting();
+
+        let mut cancelled = self
+            .elicitation
+            .cancel_pending_elicitations("Server is shutting down")
+            .await;
+        for session in self.sessions.sessions() {
+            cancelled += session
+                .elicitation
+                .cancel_pending_elicitations("Server is shutting down")
+                .await;
+        }
+        let in_flight = self.in_flight.in_flight();
+        tracing::info!(
+            cancelled_elicitations = cancelled,
+            in_flight,
+            "Shutting down, waiting for tool executions to finish"
+        );
+
+        let drained = self.in_flight.drain(timeout).await;
+        if !drained {
+            tracing::warn!(
+                remaining = self.in_flight.in_flight(),
+                timeout_secs = timeout.as_secs(),
+                "Tool executions still running after shutdown timeout"
+            );
+        }
+        drained
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_drain_waits_for_in_flight_executions() {
+        let tracker = InFlightTracker::new();
+        let permit = tracker.begin().unwrap();
+        assert_eq!(tracker.in_flight(), 1);
+
+        tracker.stop_accepting();
+        assert!(tracker.begin().is_none());
+        tracker.stopped().await;
+
+        // Still running: the drain times out
+        assert!(!tracker.drain(Duration::from_millis(20)).await);
+
+        let draining = tokio::spawn({
+            let tracker = tracker.clone();
+            async move { tracker.drain(Duration::from_secs(5)).await }
+        });
+        drop(permit);
+        assert!(draining.await.unwrap());
+    }
+}
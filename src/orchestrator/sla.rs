@@ -0,0 +1,221 @@
+//! Measured latency and error rates, for comparison against declared SLAs.
+//!
+//! Every tool execution is timed and recorded in `tool_usage_event`. The
+//! [`SlaMonitor`] periodically summarizes the recent events of each tool into
+//! its `actual_latency_p99_ms` and `actual_error_rate_pct`, which
+//! `ToolRecord::meets_sla` checks alongside the tool's declared `sla_class`.
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use surrealdb::Surreal;
+use surrealdb::engine::any::Any;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+
+use crate::db::queries::QueryBuilder;
+use crate::db::schema::ToolUsageEventRecord;
+
+/// Executions older than this don't count towards a tool's measured SLA.
+pub const SLA_WINDOW: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How often the measured SLAs are recomputed.
+pub const SLA_REFRESH_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Measured service level of one tool.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ToolSlaStats {
+    pub latency_p99_ms: u32,
+    pub error_rate_pct: f32,
+}
+
+/// Latency p99 (nearest rank) and error rate of each tool's timed events,
+/// keyed by tool id.
+pub fn tool_sla_stats(events: &[ToolUsageEventRecord]) -> HashMap<String, ToolSlaStats> {
+    let mut by_tool: HashMap<String, Vec<&ToolUsageEventRecord>> = HashMap::new();
+    for event in events.iter().filter(|event| event.duration_ms.is_some()) {
+        by_tool
+            .entry(event.tool_id.to_string())
+            .or_default()
+            .push(event);
+    }
+
+    by_tool
+        .into_iter()
+        .map(|(tool_id, events)| {
+            let mut durations: Vec<u64> = events.iter().filter_map(|e| e.duration_ms).collect();
+            durations.sort_unstable();
+            let rank = (durations.len() * 99).div_ceil(100).max(1);
+            let p99 = durations[rank - 1];
+            let failures = events.iter().filter(|e| !e.success).count();
+            (
+                tool_id,
+                ToolSlaStats {
+                    latency_p99_ms: u32::try_from(p99).unwrap_or(u32::MAX),
+                    error_rate_pct: failures as f32 * 100.0 / events.len() as f32,
+                },
+            )
+        })
+        .collect()
+}
+
+/// Background task that periodically stores each tool's measured SLA.
+pub struct SlaMonitor {
+    db: Surreal<Any>,
+    interval: Duration,
+    task: std::sync::Mutex<Option<JoinHandle<()>>>,
+}
+
+impl SlaMonitor {
+    pub fn new(db: Surreal<Any>, interval: Duration) -> Self {
+        Self {
+            db,
+            interval,
+            task: std::sync::Mutex::new(None),
+        }
+    }
+
+    /// Spawn the background loop. Calling this more than once is a no-op.
+    pub fn start(self: &Arc<Self>) {
+        let mut task = self.task.lock().expect("sla monitor task lock poisoned");
+        if task.is_some() {
+            return;
+        }
+
+        let monitor = Arc::clone(self);
+        *task = Some(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(monitor.interval);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                if let Err(e) = monitor.run_once().await {
+                    tracing::warn!("Recomputing measured SLAs failed: {}", e);
+                }
+            }
+        }));
+    }
+
+    /// Abort the background loop, if running.
+    pub fn stop(&self) {
+        let handle = self
+            .task
+            .lock()
+            .expect("sla monitor task lock poisoned")
+            .take();
+        if let Some(handle) = handle {
+            handle.abort();
+        }
+    }
+
+    /// Recompute and store the measured SLA of every tool executed within
+    /// [`SLA_WINDOW`]. Returns the number of tools updated.
+    pub async fn run_once(&self) -> Result<usize> {
+        let events = QueryBuilder::list_timed_tool_usage(&self.db, SLA_WINDOW).await?;
+        let stats = tool_sla_stats(&events);
+        for (tool_id, stats) in &stats {
+            QueryBuilder::update_tool_sla_stats(
+                &self.db,
+                tool_id,
+                stats.latency_p99_ms,
+                stats.error_rate_pct,
+            )
+            .await?;
+        }
+        Ok(stats.len())
+    }
+}
+
+impl Drop for SlaMonitor {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use surrealdb::RecordId;
+
+    fn event(tool: &str, duration_ms: Option<u64>, success: bool) -> ToolUsageEventRecord {
+        ToolUsageEventRecord {
+            id: RecordId::from_table_key("tool_usage_event", "e"),
+            tool_id: RecordId::from_table_key("tool", tool),
+            user_id: "alice".to_string(),
+            arguments: json!({}).as_object().unwrap().clone(),
+            success,
+            duration_ms,
+            created_at: None,
+        }
+    }
+
+    #[test]
+    fn test_tool_sla_stats() {
+        let mut events: Vec<_> = (1..=100).map(|ms| event("fast", Some(ms), true)).collect();
+        events.push(event("flaky", Some(900), false));
+        events.push(event("flaky", Some(100), true));
+        events.push(event("flaky", Some(200), true));
+        events.push(event("flaky", Some(300), false));
+        // Untimed events from before executions were timed are ignored
+        events.push(event("flaky", None, false));
+
+        let stats = tool_sla_stats(&events);
+        assert_eq!(stats.len(), 2);
+        assert_eq!(
+            stats["tool:fast"],
+            ToolSlaStats {
+                latency_p99_ms: 99,
+                error_rate_pct: 0.0
+            }
+        );
+        assert_eq!(
+            stats["tool:flaky"],
+            ToolSlaStats {
+                latency_p99_ms: 900,
+                error_rate_pct: 50.0
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_once_stores_measured_sla() {
+        let db_config = crate::db::DatabaseConfig {
+            url: "memory".to_string(),
+            ..Default::default()
+        };
+        let db = crate::db::create_connection(db_config).await.unwrap();
+        crate::db::ensure_schema(&db).await.unwrap();
+
+        let tool_id = RecordId::from_table_key("tool", "read");
+        db.query("CREATE $id SET name = 'read', service_id = service:fs, input_schema = {}")
+            .bind(("id", tool_id.clone()))
+            .await
+            .unwrap()
+            .check()
+            .unwrap();
+        let alice = crate::types::ExternalUserId::new("alice");
+        for (duration_ms, success) in [(40, true), (60, true), (500, false), (80, true)] {
+            QueryBuilder::record_tool_usage(
+                &db,
+                &tool_id,
+                &alice,
+                &Default::default(),
+                success,
+                duration_ms,
+            )
+            .await
+            .unwrap();
+        }
+
+        let monitor = SlaMonitor::new(db.clone(), SLA_REFRESH_INTERVAL);
+        assert_eq!(monitor.run_once().await.unwrap(), 1);
+
+        let tool = QueryBuilder::find_tool_by_id(&db, tool_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(tool.actual_latency_p99_ms, Some(500));
+        assert_eq!(tool.actual_error_rate_pct, Some(25.0));
+    }
+}
@@ -0,0 +1,151 @@
+//! Argument suggestions drawn from a user's past successful tool invocations.
+//!
+//! Distinct argument sets are ranked by how closely their keys match the
+//! arguments the caller has filled in so far (Jaccard similarity) and by how
+//! recently they were used.
+
+use rmcp::model::JsonObject;
+use serde::Serialize;
+use std::collections::HashSet;
+use surrealdb::sql::Datetime;
+
+use crate::db::ToolUsageEventRecord;
+
+/// Maximum number of suggestions returned.
+pub const MAX_SUGGESTIONS: usize = 5;
+
+/// Weight of key similarity in the combined score; recency gets the rest.
+const SIMILARITY_WEIGHT: f32 = 0.7;
+
+/// A previously used argument set for a tool.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArgumentSuggestion {
+    /// The full argument set as it was passed to the tool.
+    pub arguments: JsonObject,
+    /// How many successful invocations used exactly these arguments.
+    pub success_count: usize,
+    /// When these arguments were last used successfully.
+    pub last_used_at: Option<Datetime>,
+    /// Combined similarity/recency score in `[0, 1]`.
+    pub score: f32,
+}
+
+/// Jaccard similarity of the key sets of two argument objects.
+///
+/// Two empty objects are considered identical.
+pub fn key_jaccard(a: &JsonObject, b: &JsonObject) -> f32 {
+    let a: HashSet<&String> = a.keys().collect();
+    let b: HashSet<&String> = b.keys().collect();
+    let union = a.union(&b).count();
+    if union == 0 {
+        return 1.0;
+    }
+    a.intersection(&b).count() as f32 / union as f32
+}
+
+/// Rank past invocations into at most `limit` suggestions.
+///
+/// `events` must be ordered newest first. Identical argument sets are merged,
+/// keeping the most recent use.
+pub fn rank_argument_suggestions(
+    events: Vec<ToolUsageEventRecord>,
+    partial_args: &JsonObject,
+    limit: usize,
+) -> Vec<ArgumentSuggestion> {
+    let mut suggestions: Vec<ArgumentSuggestion> = Vec::new();
+    for event in events {
+        match suggestions
+            .iter_mut()
+            .find(|s| s.arguments == event.arguments)
+        {
+            Some(existing) => existing.success_count += 1,
+            None => suggestions.push(ArgumentSuggestion {
+                arguments: event.arguments,
+                success_count: 1,
+                last_used_at: event.created_at,
+                score: 0.0,
+            }),
+        }
+    }
+
+    // Suggestions are still in recency order here
+    for (position, suggestion) in suggestions.iter_mut().enumerate() {
+        let recency = 1.0 / (1.0 + position as f32);
+        let similarity = key_jaccard(partial_args, &suggestion.arguments);
+        suggestion.score = SIMILARITY_WEIGHT * similarity + (1.0 - SIMILARITY_WEIGHT) * recency;
+    }
+
+    // Stable sort keeps the more recent suggestion first on ties
+    suggestions.sort_by(|a, b| b.score.total_cmp(&a.score));
+    suggestions.truncate(limit);
+    suggestions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use surrealdb::RecordId;
+
+    fn object(value: serde_json::Value) -> JsonObject {
+        value.as_object().unwrap().clone()
+    }
+
+    fn event(arguments: serde_json::Value) -> ToolUsageEventRecord {
+        ToolUsageEventRecord {
+            id: RecordId::from_table_key("tool_usage_event", "e"),
+            tool_id: RecordId::from_table_key("tool", "search"),
+            user_id: "alice".to_string(),
+            arguments: object(arguments),
+            success: true,
+            duration_ms: None,
+            created_at: None,
+        }
+    }
+
+    #[test]
+    fn test_key_jaccard() {
+        let a = object(json!({ "query": "x", "limit": 5 }));
+        let b = object(json!({ "query": "y", "offset": 0 }));
+        assert!((key_jaccard(&a, &b) - 1.0 / 3.0).abs() < 1e-6);
+        assert_eq!(key_jaccard(&a, &a), 1.0);
+        assert_eq!(key_jaccard(&JsonObject::new(), &JsonObject::new()), 1.0);
+        assert_eq!(key_jaccard(&JsonObject::new(), &a), 0.0);
+    }
+
+    #[test]
+    fn test_rank_argument_suggestions() {
+        // Newest first
+        let events = vec![
+            event(json!({ "path": "/tmp" })),
+            event(json!({ "query": "rust", "limit": 10 })),
+            event(json!({ "path": "/tmp" })),
+            event(json!({ "query": "surreal", "limit": 5 })),
+        ];
+        let partial = object(json!({ "query": "ru", "limit": 10 }));
+
+        let ranked = rank_argument_suggestions(events, &partial, MAX_SUGGESTIONS);
+        assert_eq!(ranked.len(), 3);
+        // Matching keys outrank the most recent but dissimilar set
+        assert_eq!(
+            ranked[0].arguments,
+            object(json!({ "query": "rust", "limit": 10 }))
+        );
+        assert_eq!(
+            ranked[1].arguments,
+            object(json!({ "query": "surreal", "limit": 5 }))
+        );
+        assert_eq!(ranked[2].arguments, object(json!({ "path": "/tmp" })));
+        assert_eq!(ranked[2].success_count, 2);
+    }
+
+    #[test]
+    fn test_rank_argument_suggestions_limit() {
+        let events = (0..8).map(|i| event(json!({ "n": i }))).collect();
+        let ranked = rank_argument_suggestions(events, &JsonObject::new(), MAX_SUGGESTIONS);
+        assert_eq!(ranked.len(), MAX_SUGGESTIONS);
+        // With nothing filled in, recency decides
+        assert_eq!(ranked[0].arguments, object(json!({ "n": 0 })));
+    }
+}
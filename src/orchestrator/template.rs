@@ -0,0 +1,108 @@
+//! Minimal `{{key}}` template rendering for plan step descriptions.
+//!
+//! This intentionally avoids a full template engine (Tera, Handlebars) to keep
+//! dependencies small: the only supported syntax is `{{key}}` substitution.
+
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Renders `{{key}}` placeholders from a map of JSON values.
+pub struct TemplateEngine;
+
+impl TemplateEngine {
+    /// Render `template`, replacing each `{{key}}` with the matching value in `vars`.
+    ///
+    /// String values are inserted without quotes; other values use their JSON
+    /// representation. Whitespace inside the braces is ignored, so `{{ key }}`
+    /// and `{{key}}` are equivalent. Placeholders without a matching variable
+    /// are left untouched.
+    pub fn render(template: &str, vars: &HashMap<String, Value>) -> String {
+        let mut out = String::with_capacity(template.len());
+        let mut rest = template;
+
+        while let Some(start) = rest.find("{{") {
+            out.push_str(&rest[..start]);
+            let after_open = &rest[start + 2..];
+
+            let Some(end) = after_open.find("}}") else {
+                // Unterminated placeholder: keep the remainder verbatim.
+                out.push_str(&rest[start..]);
+                return out;
+            };
+
+            let key = after_open[..end].trim();
+            match vars.get(key) {
+                Some(Value::String(s)) => out.push_str(s),
+                Some(other) => out.push_str(&other.to_string()),
+                None => {
+                    tracing::debug!(key, "Unresolved template variable");
+                    out.push_str(&rest[start..start + 2 + end + 2]);
+                }
+            }
+
+            rest = &after_open[end + 2..];
+        }
+
+        out.push_str(rest);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn vars(pairs: &[(&str, Value)]) -> HashMap<String, Value> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_render_substitutes_values() {
+        let vars = vars(&[("file_path", json!("/tmp/a.txt")), ("step", json!(1))]);
+        let rendered = TemplateEngine::render(
+            "Step {{step}}: Read {{file_path}} from the filesystem",
+            &vars,
+        );
+        assert_eq!(rendered, "Step 1: Read /tmp/a.txt from the filesystem");
+    }
+
+    #[test]
+    fn test_render_trims_whitespace_in_placeholders() {
+        let vars = vars(&[("name", json!("world"))]);
+        assert_eq!(
+            TemplateEngine::render("hello {{ name }}", &vars),
+            "hello world"
+        );
+    }
+
+    #[test]
+    fn test_render_leaves_unresolved_placeholders() {
+        let vars = vars(&[("a", json!("x"))]);
+        assert_eq!(
+            TemplateEngine::render("{{a}} and {{ missing }}", &vars),
+            "x and {{ missing }}"
+        );
+    }
+
+    #[test]
+    fn test_render_non_string_values_use_json() {
+        let vars = vars(&[("flags", json!(["a", "b"])), ("on", json!(true))]);
+        assert_eq!(
+            TemplateEngine::render("{{flags}} / {{on}}", &vars),
+            r#"["a","b"] / true"#
+        );
+    }
+
+    #[test]
+    fn test_render_unterminated_placeholder() {
+        let vars = vars(&[("a", json!("x"))]);
+        assert_eq!(
+            TemplateEngine::render("{{a}} then {{b", &vars),
+            "x then {{b"
+        );
+    }
+}
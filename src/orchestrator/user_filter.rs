@@ -22,6 +22,8 @@ pub struct UserToolFilter {
     blocked_services: HashSet<String>,
     /// Service IDs that are trusted (tools from these services get a confidence boost)
     trusted_services: HashSet<String>,
+    /// The preferences the filter was built from, if the user has any
+    preferences: Option<UserPreferencesRecord>,
 }
 
 impl UserToolFilter {
@@ -32,6 +34,7 @@ impl UserToolFilter {
         Self {
             blocked_services: HashSet::new(),
             trusted_services: HashSet::new(),
+            preferences: None,
         }
     }
 
@@ -51,12 +54,23 @@ impl UserToolFilter {
 
         match prefs {
             Some(p) => {
-                let blocked_services = p.blocked_services.unwrap_or_default().into_iter().collect();
-                let trusted_services = p.trusted_services.unwrap_or_default().into_iter().collect();
+                let blocked_services = p
+                    .blocked_services
+                    .clone()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .collect();
+                let trusted_services = p
+                    .trusted_services
+                    .clone()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .collect();
 
                 Ok(Self {
                     blocked_services,
                     trusted_services,
+                    preferences: Some(p),
                 })
             }
             None => {
@@ -66,10 +80,24 @@ impl UserToolFilter {
         }
     }
 
+    /// The user's preferences, e.g. for their embedding model override.
+    pub fn preferences(&self) -> Option<&UserPreferencesRecord> {
+        self.preferences.as_ref()
+    }
+
+    /// The language the user writes queries in, if they set one.
+    pub fn preferred_language(&self) -> Option<&str> {
+        self.preferences.as_ref()?.preferred_language.as_deref()
+    }
+
     /// Check if a tool is allowed based on its service.
     pub fn is_tool_allowed(&self, tool: &ToolRecord) -> bool {
-        let service_id_str = tool.service_id.to_string();
-        !self.blocked_services.contains(&service_id_str)
+        self.is_service_allowed(&tool.service_id.to_string())
+    }
+
+    /// Check if the user hasn't blocked a service.
+    pub fn is_service_allowed(&self, service_id: &str) -> bool {
+        !self.blocked_services.contains(service_id)
     }
 
     /// Check if a service is trusted.
@@ -153,6 +181,23 @@ mod tests {
             input_ty: None,
             output_ty: None,
             usage_count: 0,
+            status: None,
+            discovery_source: Default::default(),
+            metadata: Default::default(),
+            input_examples: Vec::new(),
+            changelog: Vec::new(),
+            deprecation_notice: None,
+            descriptions: Default::default(),
+            inject_user_context: Vec::new(),
+            geo_availability: None,
+            required_env_vars: Vec::new(),
+            sla_class: None,
+            cost_per_call: None,
+            cache_ttl_seconds: None,
+            required_role: None,
+            actual_latency_p99_ms: None,
+            actual_error_rate_pct: None,
+            block_reason: None,
             created_at: None,
             updated_at: None,
         }
@@ -188,6 +233,7 @@ mod tests {
         let filter = UserToolFilter {
             blocked_services: vec!["service:service1".to_string()].into_iter().collect(),
             trusted_services: HashSet::new(),
+            preferences: None,
         };
 
         let tools = vec![
@@ -198,6 +244,8 @@ mod tests {
         let filtered = filter.filter_tools(tools);
         assert_eq!(filtered.len(), 1);
         assert_eq!(filtered[0].name, "tool2");
+        assert!(!filter.is_service_allowed("service:service1"));
+        assert!(filter.is_service_allowed("service:service2"));
     }
 
     #[test]
@@ -205,6 +253,7 @@ mod tests {
         let filter = UserToolFilter {
             blocked_services: HashSet::new(),
             trusted_services: vec!["service:service1".to_string()].into_iter().collect(),
+            preferences: None,
         };
 
         let mut selections = vec![
@@ -225,6 +274,7 @@ mod tests {
         let filter = UserToolFilter {
             blocked_services: HashSet::new(),
             trusted_services: vec!["service:service1".to_string()].into_iter().collect(),
+            preferences: None,
         };
 
         let mut selections = vec![make_tool_selection("service1", "tool1", 0.95)];
@@ -0,0 +1,188 @@
+//! Static catalog of all discovered prompts, for generating documentation.
+//!
+//! [`CatalogExport`] is served as JSON and rendered as Markdown by the admin
+//! API, and written to disk by the `generate-catalog` command.
+
+use chrono::{DateTime, Utc};
+use rmcp::model::PromptArgument as McpPromptArgument;
+use serde::Serialize;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+
+use crate::types::{ServiceId, ServiceName};
+
+/// Every discovered prompt, grouped by service.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogExport {
+    pub generated_at: DateTime<Utc>,
+    /// Services with at least one prompt, sorted by name.
+    pub services: Vec<ServiceCatalogEntry>,
+    /// Prompts keyed by service id, sorted by namespaced name.
+    pub prompts_by_service: HashMap<String, Vec<PromptCatalogEntry>>,
+}
+
+/// A service listed in the catalog.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceCatalogEntry {
+    pub service_id: ServiceId,
+    pub service_name: ServiceName,
+    pub prompt_count: usize,
+}
+
+/// A prompt listed in the catalog.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptCatalogEntry {
+    /// Name the service defines the prompt under.
+    pub name: String,
+    /// Name clients address the prompt by, e.g. `github-commit`.
+    pub namespaced_name: String,
+    pub description: Option<String>,
+    pub arguments: Vec<McpPromptArgument>,
+    /// `prompts/get` params invoking the prompt, with a placeholder per argument.
+    pub example_call: Value,
+}
+
+impl PromptCatalogEntry {
+    pub(super) fn new(
+        name: String,
+        namespaced_name: String,
+        description: Option<String>,
+        arguments: Vec<McpPromptArgument>,
+    ) -> Self {
+        let example_arguments: serde_json::Map<String, Value> = arguments
+            .iter()
+            .map(|arg| (arg.name.clone(), Value::String(format!("<{}>", arg.name))))
+            .collect();
+        let example_call = json!({
+            "name": namespaced_name,
+            "arguments": example_arguments,
+        });
+        Self {
+            name,
+            namespaced_name,
+            description,
+            arguments,
+            example_call,
+        }
+    }
+}
+
+impl CatalogExport {
+    /// Render the catalog as Markdown, with a table of contents linking to
+    /// one section per service.
+    pub fn to_markdown(&self) -> String {
+        let mut md = String::new();
+        let _ = writeln!(md, "# Prompt Catalog\n");
+        let _ = writeln!(
+            md,
+            "Generated at {}.\n",
+            self.generated_at.format("%Y-%m-%d %H:%M:%S UTC")
+        );
+
+        let _ = writeln!(md, "## Contents\n");
+        for service in &self.services {
+            let _ = writeln!(
+                md,
+                "- [{}](#{}) ({} prompt{})",
+                service.service_name.as_str(),
+                anchor(service.service_name.as_str()),
+                service.prompt_count,
+                if service.prompt_count == 1 { "" } else { "s" }
+            );
+        }
+
+        for service in &self.services {
+            let _ = writeln!(md, "\n## {}", service.service_name.as_str());
+            let prompts = self
+                .prompts_by_service
+                .get(service.service_id.as_str())
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            for prompt in prompts {
+                let _ = writeln!(md, "\n### `{}`\n", prompt.namespaced_name);
+                if let Some(description) = prompt.description.as_deref().filter(|d| !d.is_empty()) {
+                    let _ = writeln!(md, "{}\n", description);
+                }
+
+                if !prompt.arguments.is_empty() {
+                    let _ = writeln!(md, "| Argument | Required | Description |");
+                    let _ = writeln!(md, "| --- | --- | --- |");
+                    for arg in &prompt.arguments {
+                        let _ = writeln!(
+                            md,
+                            "| `{}` | {} | {} |",
+                            arg.name,
+                            if arg.required.unwrap_or(false) {
+                                "yes"
+                            } else {
+                                "no"
+                            },
+                            table_cell(arg.description.as_deref().unwrap_or(""))
+                        );
+                    }
+                    md.push('\n');
+                }
+
+                let example = serde_json::to_string_pretty(&prompt.example_call)
+                    .unwrap_or_else(|_| prompt.example_call.to_string());
+                let _ = writeln!(md, "```json\n{}\n```", example);
+            }
+        }
+
+        md
+    }
+}
+
+/// GitHub-style heading anchor: lowercase, spaces as hyphens, punctuation
+/// other than `-` and `_` dropped.
+fn anchor(heading: &str) -> String {
+    heading
+        .trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Keep a value on one line of a Markdown table.
+fn table_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace(['\r', '\n'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_anchor() {
+        assert_eq!(anchor("GitHub Tools"), "github-tools");
+        assert_eq!(anchor("fs (local)"), "fs-local");
+    }
+
+    #[test]
+    fn test_example_call_has_placeholder_per_argument() {
+        let entry = PromptCatalogEntry::new(
+            "commit".to_string(),
+            "github-commit".to_string(),
+            None,
+            vec![McpPromptArgument {
+                name: "message".to_string(),
+                title: None,
+                description: None,
+                required: Some(true),
+            }],
+        );
+        assert_eq!(
+            entry.example_call,
+            json!({ "name": "github-commit", "arguments": { "message": "<message>" } })
+        );
+    }
+}
@@ -0,0 +1,203 @@
+//! Argument type inference for prompts that only declare argument names.
+//!
+//! Values from past successful `prompts/get` calls are checked against a few
+//! simple shapes, most specific first: boolean, number, ISO date, a small set
+//! of repeated values (enum), and finally plain string.
+
+use rmcp::model::{JsonObject, PromptArgument as McpPromptArgument};
+use serde::Serialize;
+use serde_json::{Value, json};
+use std::collections::BTreeSet;
+
+/// Number of past calls inspected per prompt.
+pub const INFERENCE_SAMPLE_SIZE: usize = 20;
+
+/// An argument with at most this many distinct values may be an enum.
+const MAX_ENUM_VALUES: usize = 5;
+
+/// A declared prompt argument together with its inferred JSON Schema.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InferredPromptArgument {
+    #[serde(flatten)]
+    pub argument: McpPromptArgument,
+    /// JSON Schema inferred from past values; `{"type": "string"}` without samples.
+    pub schema: Value,
+    /// Number of past values the schema is based on.
+    pub sample_count: usize,
+}
+
+/// Merge declared arguments with schemas inferred from past argument sets.
+///
+/// Arguments that were used but never declared are appended as optional.
+pub fn infer_argument_types(
+    declared: &[McpPromptArgument],
+    samples: &[JsonObject],
+) -> Vec<InferredPromptArgument> {
+    let mut arguments: Vec<McpPromptArgument> = declared.to_vec();
+    let undeclared: BTreeSet<&String> = samples
+        .iter()
+        .flat_map(|sample| sample.keys())
+        .filter(|name| !declared.iter().any(|arg| &arg.name == *name))
+        .collect();
+    arguments.extend(undeclared.into_iter().map(|name| McpPromptArgument {
+        name: name.clone(),
+        title: None,
+        description: None,
+        required: Some(false),
+    }));
+
+    arguments
+        .into_iter()
+        .map(|argument| {
+            let values: Vec<&Value> = samples
+                .iter()
+                .filter_map(|sample| sample.get(&argument.name))
+                .filter(|value| !value.is_null())
+                .collect();
+            InferredPromptArgument {
+                schema: infer_schema(&values),
+                sample_count: values.len(),
+                argument,
+            }
+        })
+        .collect()
+}
+
+/// Infer a JSON Schema from observed values.
+///
+/// Prompt arguments are usually sent as strings, so strings are parsed as
+/// well as checked for their JSON type.
+pub fn infer_schema(values: &[&Value]) -> Value {
+    if values.is_empty() {
+        return json!({ "type": "string" });
+    }
+
+    if values.iter().all(|v| as_bool(v).is_some()) {
+        return json!({ "type": "boolean" });
+    }
+    if values.iter().all(|v| as_number(v).is_some()) {
+        return json!({ "type": "number" });
+    }
+
+    let Some(strings) = values
+        .iter()
+        .map(|v| v.as_str())
+        .collect::<Option<Vec<_>>>()
+    else {
+        return json!({});
+    };
+    if strings.iter().all(|s| is_iso_date(s)) {
+        return json!({ "type": "string", "format": "date" });
+    }
+
+    // Only call it an enum when values actually repeat
+    let distinct: BTreeSet<&str> = strings.iter().copied().collect();
+    if distinct.len() <= MAX_ENUM_VALUES && distinct.len() < strings.len() {
+        return json!({ "type": "string", "enum": distinct });
+    }
+
+    json!({ "type": "string" })
+}
+
+fn as_bool(value: &Value) -> Option<bool> {
+    match value {
+        Value::Bool(b) => Some(*b),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+fn as_number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse::<f64>().ok().filter(|n| n.is_finite()),
+        _ => None,
+    }
+}
+
+fn is_iso_date(s: &str) -> bool {
+    chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d").is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn argument(name: &str, required: bool) -> McpPromptArgument {
+        McpPromptArgument {
+            name: name.to_string(),
+            title: None,
+            description: None,
+            required: Some(required),
+        }
+    }
+
+    fn schema_of(values: &[Value]) -> Value {
+        infer_schema(&values.iter().collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn test_infer_schema_heuristics() {
+        assert_eq!(schema_of(&[]), json!({ "type": "string" }));
+        assert_eq!(
+            schema_of(&[json!("true"), json!(false)]),
+            json!({ "type": "boolean" })
+        );
+        assert_eq!(
+            schema_of(&[json!("42"), json!(3.5), json!("-1")]),
+            json!({ "type": "number" })
+        );
+        assert_eq!(
+            schema_of(&[json!("2026-01-31"), json!("2025-12-01")]),
+            json!({ "type": "string", "format": "date" })
+        );
+        assert_eq!(
+            schema_of(&[json!("high"), json!("low"), json!("high")]),
+            json!({ "type": "string", "enum": ["high", "low"] })
+        );
+        // All distinct: no reason to believe the set is closed
+        assert_eq!(
+            schema_of(&[json!("fix login"), json!("add docs")]),
+            json!({ "type": "string" })
+        );
+        assert_eq!(
+            schema_of(&[json!("2026-02-30")]),
+            json!({ "type": "string" })
+        );
+    }
+
+    #[test]
+    fn test_infer_argument_types_merges_declared_and_observed() {
+        let declared = vec![argument("priority", true), argument("title", false)];
+        let samples: Vec<JsonObject> = [
+            json!({ "priority": "high", "due": "2026-03-01" }),
+            json!({ "priority": "low", "due": "2026-04-15" }),
+            json!({ "priority": "high" }),
+        ]
+        .into_iter()
+        .map(|v| v.as_object().unwrap().clone())
+        .collect();
+
+        let inferred = infer_argument_types(&declared, &samples);
+        assert_eq!(inferred.len(), 3);
+
+        assert_eq!(inferred[0].argument, declared[0]);
+        assert_eq!(inferred[0].sample_count, 3);
+        assert_eq!(
+            inferred[0].schema,
+            json!({ "type": "string", "enum": ["high", "low"] })
+        );
+
+        // Declared but never used
+        assert_eq!(inferred[1].sample_count, 0);
+        assert_eq!(inferred[1].schema, json!({ "type": "string" }));
+
+        // Used but never declared
+        assert_eq!(inferred[2].argument, argument("due", false));
+        assert_eq!(
+            inferred[2].schema,
+            json!({ "type": "string", "format": "date" })
+        );
+    }
+}
@@ -4,14 +4,23 @@
 //! When multiple services define prompts with the same name, the orchestrator creates
 //! namespaced aliases to avoid conflicts (e.g., `github-commit`, `gitlab-commit`).
 
+mod catalog;
+mod inference;
+
+pub use catalog::{CatalogExport, PromptCatalogEntry, ServiceCatalogEntry};
+pub use inference::{INFERENCE_SAMPLE_SIZE, InferredPromptArgument, infer_argument_types};
+
+use crate::db::QueryBuilder;
 use crate::types::{PromptName, ServiceId, ServiceName};
 use anyhow::Result;
 use rmcp::model::{
-    GetPromptRequestParams, GetPromptResult, Icon, JsonObject, ListPromptsResult,
+    GetPromptRequestParams, GetPromptResult, Icon, JsonObject, ListPromptsResult, Meta,
     Prompt as McpPrompt, PromptArgument as McpPromptArgument,
 };
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::Mutex;
 
 /// Error types for prompt operations.
@@ -25,6 +34,8 @@ pub enum PromptError {
     InvalidArguments(String),
     /// Internal error during prompt operations.
     Internal(String),
+    /// The owning service's circuit breaker is open; the service was not called.
+    ServiceUnavailable { retry_after: Duration },
 }
 
 impl std::fmt::Display for PromptError {
@@ -34,6 +45,11 @@ impl std::fmt::Display for PromptError {
             PromptError::InvalidName(name) => write!(f, "Invalid prompt name: {}", name),
             PromptError::InvalidArguments(msg) => write!(f, "Invalid arguments: {}", msg),
             PromptError::Internal(msg) => write!(f, "Internal error: {}", msg),
+            PromptError::ServiceUnavailable { retry_after } => write!(
+                f,
+                "Service unavailable, retry after {}s",
+                retry_after.as_secs()
+            ),
         }
     }
 }
@@ -73,11 +89,12 @@ fn is_valid_arguments(arguments: &Option<JsonObject>) -> bool {
 }
 
 /// Sanitize a string for use as a prompt identifier.
-/// Replaces spaces and special characters with hyphens, ensuring URL-safe output.
+/// Replaces spaces and special characters (including non-ASCII letters) with
+/// hyphens, ensuring URL-safe output.
 fn sanitize_name(name: &str) -> String {
     name.chars()
         .map(|c| {
-            if c.is_alphanumeric() || c == '-' || c == '_' {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
                 c
             } else {
                 '-' // Replace whitespace and other special chars with hyphens
@@ -101,6 +118,44 @@ pub struct DiscoveredPrompt {
     pub icons: Option<Vec<Icon>>,
     pub service_id: ServiceId,
     pub service_name: ServiceName,
+    /// Capabilities advertised in the prompt's `_meta.capabilities`, e.g. `code_review`.
+    pub capabilities: Vec<String>,
+}
+
+/// Read the capability tags from a prompt's `_meta.capabilities`, skipping
+/// non-string and empty entries.
+fn capabilities_from_meta(meta: Option<&Meta>) -> Vec<String> {
+    let mut capabilities: Vec<String> = Vec::new();
+    let entries = meta
+        .and_then(|meta| meta.0.get("capabilities"))
+        .and_then(|v| v.as_array());
+    for capability in entries.into_iter().flatten().filter_map(|v| v.as_str()) {
+        let capability = capability.trim();
+        if !capability.is_empty() && !capabilities.iter().any(|c| c == capability) {
+            capabilities.push(capability.to_string());
+        }
+    }
+    capabilities
+}
+
+/// Narrows the prompts returned by `PromptForwarder::list_prompts`.
+///
+/// Clients pass it as `_meta.filter` on `prompts/list`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PromptFilter {
+    /// Only list prompts advertising this capability.
+    pub capability: Option<String>,
+}
+
+/// Outcome of `PromptForwarder::refresh_service_prompts`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RefreshResult {
+    /// Number of prompts discovered from the service.
+    pub added: usize,
+    /// Number of the service's prompts removed before rediscovery.
+    pub removed: usize,
+    /// Names of the service's prompts that other services also define.
+    pub conflicts: Vec<String>,
 }
 
 /// An entry in the prompt registry with alias information.
@@ -117,6 +172,9 @@ pub struct PromptRegistry {
     prompts: HashMap<String, PromptEntry>, // Key: namespaced_name or prompt_name
     prompt_to_services: HashMap<String, Vec<String>>, // prompt_name -> [service_ids]
     aliases: HashMap<String, String>,      // alias -> namespaced_name
+    capability_to_prompts: HashMap<String, Vec<String>>, // capability -> [namespaced_names]
+    /// Argument types inferred from past calls, keyed by `service_id/prompt_name`.
+    inferred_arguments: HashMap<String, Vec<InferredPromptArgument>>,
 }
 
 impl PromptRegistry {
@@ -126,6 +184,8 @@ impl PromptRegistry {
             prompts: HashMap::new(),
             prompt_to_services: HashMap::new(),
             aliases: HashMap::new(),
+            capability_to_prompts: HashMap::new(),
+            inferred_arguments: HashMap::new(),
         }
     }
 
@@ -145,6 +205,17 @@ impl PromptRegistry {
         let sanitized_prompt = sanitize_name(&prompt_name);
         let namespaced_name = format!("{}-{}", sanitized_service, sanitized_prompt);
 
+        // Index the prompt under each capability it advertises
+        for capability in &prompt.capabilities {
+            let names = self
+                .capability_to_prompts
+                .entry(capability.clone())
+                .or_default();
+            if !names.contains(&namespaced_name) {
+                names.push(namespaced_name.clone());
+            }
+        }
+
         // Register the namespaced variant
         self.prompts.insert(
             namespaced_name.clone(),
@@ -186,65 +257,177 @@ impl PromptRegistry {
             }
         }
 
-        // Update is_conflict flag for affected prompts
+        // Update is_conflict flag, clearing it for prompts whose other
+        // services have since been removed
         for entry in self.prompts.values_mut() {
-            if conflicting.contains(entry.prompt.name.as_str()) {
-                entry.is_conflict = true;
-            }
+            entry.is_conflict = conflicting.contains(entry.prompt.name.as_str());
         }
     }
 
-    /// List all registered prompts as MCP Prompt objects.
-    pub fn list_prompts(&self) -> Vec<DiscoveredPrompt> {
+    /// Remove every prompt registered by `service_id`, along with its aliases
+    /// and cached inferred arguments.
+    ///
+    /// Aliases shared with another service's prompt are re-pointed to it.
+    /// Returns the number of prompts removed.
+    pub fn remove_by_service(&mut self, service_id: &str) -> usize {
+        let before = self.prompts.len();
         self.prompts
-            .values()
-            .map(|entry| {
-                let mut p = entry.prompt.clone();
-
-                // For conflicts, update description to note the conflict
-                if entry.is_conflict {
-                    let desc = p
-                        .description
-                        .as_deref()
-                        .filter(|s| !s.is_empty())
-                        .unwrap_or("Prompt");
-
-                    // Count arguments for additional info
-                    let arg_count = p.arguments.as_ref().map(|args| args.len()).unwrap_or(0);
-                    let arg_info = if arg_count > 0 {
-                        format!(
-                            " ({} argument{})",
-                            arg_count,
-                            if arg_count == 1 { "" } else { "s" }
-                        )
-                    } else {
-                        String::new()
-                    };
-
-                    p.description = Some(format!(
-                        "{} (from {}){}\n\nNote: This prompt name is used by multiple services. \
-                     Use the namespaced variant (e.g. {}-{}) to be specific.",
-                        desc,
-                        entry.prompt.service_name.as_str(),
-                        arg_info,
-                        sanitize_name(entry.prompt.service_name.as_str()),
-                        sanitize_name(entry.prompt.name.as_str())
-                    ));
-                } else if p.description.as_ref().map(|s| s.is_empty()).unwrap_or(true) {
-                    // Provide a sensible default for empty or missing descriptions
-                    p.description = Some(format!(
-                        "Prompt from {}",
-                        entry.prompt.service_name.as_str()
-                    ));
+            .retain(|_, entry| entry.prompt.service_id.as_str() != service_id);
+
+        self.prompt_to_services.retain(|_, services| {
+            services.retain(|id| id != service_id);
+            !services.is_empty()
+        });
+
+        let prompts = &self.prompts;
+        self.capability_to_prompts.retain(|_, names| {
+            names.retain(|name| prompts.contains_key(name.as_str()));
+            !names.is_empty()
+        });
+
+        self.aliases.retain(|alias, namespaced| {
+            if prompts.contains_key(namespaced.as_str()) {
+                return true;
+            }
+            match prompts
+                .values()
+                .find(|entry| entry.prompt.name.as_str() == alias)
+            {
+                Some(entry) => {
+                    *namespaced = entry.namespaced_name.clone();
+                    true
                 }
+                None => false,
+            }
+        });
 
-                // Use namespaced name for display
-                p.name = PromptName::new(entry.namespaced_name.clone());
-                p
-            })
+        let prefix = format!("{}/", service_id);
+        self.inferred_arguments
+            .retain(|key, _| !key.starts_with(&prefix));
+
+        before - self.prompts.len()
+    }
+
+    /// Names of `service_id`'s prompts marked as conflicting by the last
+    /// `mark_conflicts`, sorted.
+    pub fn conflicting_prompts(&self, service_id: &str) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .prompts
+            .values()
+            .filter(|entry| entry.is_conflict && entry.prompt.service_id.as_str() == service_id)
+            .map(|entry| entry.prompt.name.to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// List all registered prompts as MCP Prompt objects.
+    pub fn list_prompts(&self) -> Vec<DiscoveredPrompt> {
+        self.prompts.values().map(Self::display_prompt).collect()
+    }
+
+    /// List the prompts advertising `capability`, in registration order.
+    pub fn list_by_capability(&self, capability: &str) -> Vec<DiscoveredPrompt> {
+        self.capability_to_prompts
+            .get(capability)
+            .into_iter()
+            .flatten()
+            .filter_map(|name| self.prompts.get(name))
+            .map(Self::display_prompt)
             .collect()
     }
 
+    /// Export every registered prompt, grouped by service, for documentation.
+    pub fn export_catalog(&self) -> CatalogExport {
+        let mut services: HashMap<String, ServiceCatalogEntry> = HashMap::new();
+        let mut prompts_by_service: HashMap<String, Vec<PromptCatalogEntry>> = HashMap::new();
+        for entry in self.prompts.values() {
+            let prompt = &entry.prompt;
+            let service_id = prompt.service_id.to_string();
+            services
+                .entry(service_id.clone())
+                .or_insert_with(|| ServiceCatalogEntry {
+                    service_id: prompt.service_id.clone(),
+                    service_name: prompt.service_name.clone(),
+                    prompt_count: 0,
+                })
+                .prompt_count += 1;
+            prompts_by_service
+                .entry(service_id)
+                .or_default()
+                .push(PromptCatalogEntry::new(
+                    prompt.name.to_string(),
+                    entry.namespaced_name.clone(),
+                    prompt.description.clone(),
+                    prompt.arguments.clone().unwrap_or_default(),
+                ));
+        }
+
+        let mut services: Vec<ServiceCatalogEntry> = services.into_values().collect();
+        services.sort_by(|a, b| {
+            a.service_name
+                .as_str()
+                .cmp(b.service_name.as_str())
+                .then_with(|| a.service_id.as_str().cmp(b.service_id.as_str()))
+        });
+        for prompts in prompts_by_service.values_mut() {
+            prompts.sort_by(|a, b| a.namespaced_name.cmp(&b.namespaced_name));
+        }
+
+        CatalogExport {
+            generated_at: chrono::Utc::now(),
+            services,
+            prompts_by_service,
+        }
+    }
+
+    /// Prepare a registry entry for listing: namespaced name, and a
+    /// description noting conflicts or filling in a missing one.
+    fn display_prompt(entry: &PromptEntry) -> DiscoveredPrompt {
+        let mut p = entry.prompt.clone();
+
+        // For conflicts, update description to note the conflict
+        if entry.is_conflict {
+            let desc = p
+                .description
+                .as_deref()
+                .filter(|s| !s.is_empty())
+                .unwrap_or("Prompt");
+
+            // Count arguments for additional info
+            let arg_count = p.arguments.as_ref().map(|args| args.len()).unwrap_or(0);
+            let arg_info = if arg_count > 0 {
+                format!(
+                    " ({} argument{})",
+                    arg_count,
+                    if arg_count == 1 { "" } else { "s" }
+                )
+            } else {
+                String::new()
+            };
+
+            p.description = Some(format!(
+                "{} (from {}){}\n\nNote: This prompt name is used by multiple services. \
+             Use the namespaced variant (e.g. {}-{}) to be specific.",
+                desc,
+                entry.prompt.service_name.as_str(),
+                arg_info,
+                sanitize_name(entry.prompt.service_name.as_str()),
+                sanitize_name(entry.prompt.name.as_str())
+            ));
+        } else if p.description.as_ref().map(|s| s.is_empty()).unwrap_or(true) {
+            // Provide a sensible default for empty or missing descriptions
+            p.description = Some(format!(
+                "Prompt from {}",
+                entry.prompt.service_name.as_str()
+            ));
+        }
+
+        // Use namespaced name for display
+        p.name = PromptName::new(entry.namespaced_name.clone());
+        p
+    }
+
     /// Resolve a prompt name to its entry.
     /// Returns the namespaced name and the service ID.
     ///
@@ -331,6 +514,35 @@ impl PromptRegistry {
         None
     }
 
+    /// Find a prompt by its owning service and original name.
+    pub fn find(&self, service_id: &str, prompt_name: &str) -> Option<&DiscoveredPrompt> {
+        self.prompts
+            .values()
+            .map(|entry| &entry.prompt)
+            .find(|p| p.service_id.as_str() == service_id && p.name.as_str() == prompt_name)
+    }
+
+    /// Argument types previously inferred for a prompt in this session.
+    pub fn inferred_arguments(
+        &self,
+        service_id: &str,
+        prompt_name: &str,
+    ) -> Option<&Vec<InferredPromptArgument>> {
+        self.inferred_arguments
+            .get(&format!("{}/{}", service_id, prompt_name))
+    }
+
+    /// Cache inferred argument types until the next re-discovery.
+    pub fn cache_inferred_arguments(
+        &mut self,
+        service_id: &str,
+        prompt_name: &str,
+        arguments: Vec<InferredPromptArgument>,
+    ) {
+        self.inferred_arguments
+            .insert(format!("{}/{}", service_id, prompt_name), arguments);
+    }
+
     /// Return the number of registered prompts.
     pub fn len(&self) -> usize {
         self.prompts.len()
@@ -347,6 +559,8 @@ impl PromptRegistry {
         self.prompts.clear();
         self.prompt_to_services.clear();
         self.aliases.clear();
+        self.capability_to_prompts.clear();
+        self.inferred_arguments.clear();
     }
 }
 
@@ -359,6 +573,130 @@ impl Default for PromptRegistry {
 /// Pagination constants for prompts.
 const DEFAULT_PAGE_SIZE: usize = 100;
 
+/// State of a service's `prompts/get` circuit breaker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitBreakerState {
+    /// Requests pass through; holds the number of consecutive failures.
+    Closed(u32),
+    /// Requests are rejected until `reset_timeout` has elapsed since the breaker opened.
+    Open(Instant),
+    /// A single probe request, started at the given time, is in flight; its
+    /// outcome closes or reopens the breaker. A probe that hasn't reported
+    /// back within `reset_timeout` (e.g. because it was cancelled) is
+    /// assumed lost, and another one is allowed.
+    HalfOpen(Instant),
+}
+
+/// Thresholds for the `prompts/get` circuit breakers.
+#[derive(Debug, Clone, Copy)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive failures after which the breaker opens.
+    pub failure_threshold: u32,
+    /// How long an open breaker waits before allowing a probe request.
+    pub reset_timeout: Duration,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            reset_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Per-service circuit breakers guarding `prompts/get` calls.
+struct CircuitBreakers {
+    config: CircuitBreakerConfig,
+    states: std::sync::Mutex<HashMap<ServiceId, CircuitBreakerState>>,
+}
+
+impl CircuitBreakers {
+    fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            states: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Decide whether a request to `service_id` may proceed at `now`.
+    ///
+    /// Returns the time to wait before retrying if the request is rejected.
+    fn check(&self, service_id: &ServiceId, now: Instant) -> Result<(), Duration> {
+        let mut states = self.states.lock().expect("circuit breaker lock poisoned");
+        match states.get(service_id).copied() {
+            None | Some(CircuitBreakerState::Closed(_)) => Ok(()),
+            Some(CircuitBreakerState::Open(opened_at)) => {
+                let elapsed = now.saturating_duration_since(opened_at);
+                if elapsed < self.config.reset_timeout {
+                    return Err(self.config.reset_timeout - elapsed);
+                }
+                tracing::info!(
+                    "Circuit breaker for service {} is half-open, allowing a probe request",
+                    service_id
+                );
+                states.insert(service_id.clone(), CircuitBreakerState::HalfOpen(now));
+                Ok(())
+            }
+            Some(CircuitBreakerState::HalfOpen(probe_started)) => {
+                let elapsed = now.saturating_duration_since(probe_started);
+                // Another request is already probing the service
+                if elapsed < self.config.reset_timeout {
+                    return Err(self.config.reset_timeout - elapsed);
+                }
+                tracing::info!(
+                    "Probe request for service {} never finished, allowing another",
+                    service_id
+                );
+                states.insert(service_id.clone(), CircuitBreakerState::HalfOpen(now));
+                Ok(())
+            }
+        }
+    }
+
+    fn record_success(&self, service_id: &ServiceId) {
+        let mut states = self.states.lock().expect("circuit breaker lock poisoned");
+        let previous = states.insert(service_id.clone(), CircuitBreakerState::Closed(0));
+        if matches!(
+            previous,
+            Some(CircuitBreakerState::Open(_) | CircuitBreakerState::HalfOpen(_))
+        ) {
+            tracing::info!("Circuit breaker for service {} closed", service_id);
+        }
+    }
+
+    fn record_failure(&self, service_id: &ServiceId, now: Instant) {
+        let mut states = self.states.lock().expect("circuit breaker lock poisoned");
+        let next = match states.get(service_id).copied() {
+            state @ (None | Some(CircuitBreakerState::Closed(_))) => {
+                let failures = match state {
+                    Some(CircuitBreakerState::Closed(n)) => n + 1,
+                    _ => 1,
+                };
+                if failures >= self.config.failure_threshold {
+                    tracing::info!(
+                        "Circuit breaker for service {} opened after {} consecutive failures",
+                        service_id,
+                        failures
+                    );
+                    CircuitBreakerState::Open(now)
+                } else {
+                    CircuitBreakerState::Closed(failures)
+                }
+            }
+            Some(CircuitBreakerState::HalfOpen(_)) => {
+                tracing::info!(
+                    "Circuit breaker for service {} reopened after a failed probe",
+                    service_id
+                );
+                CircuitBreakerState::Open(now)
+            }
+            Some(open @ CircuitBreakerState::Open(_)) => open,
+        };
+        states.insert(service_id.clone(), next);
+    }
+}
+
 /// Handles prompt forwarding to discovered MCP services.
 pub struct PromptForwarder {
     pub(crate) registry: Arc<Mutex<PromptRegistry>>,
@@ -366,6 +704,8 @@ pub struct PromptForwarder {
         Arc<Mutex<HashMap<String, Arc<crate::mcp_client::RunningService>>>>,
     /// Database reference for querying service metadata.
     db: surrealdb::Surreal<surrealdb::engine::any::Any>,
+    /// Circuit breakers for `prompts/get`, keyed by service id.
+    circuit_breakers: CircuitBreakers,
 }
 
 impl PromptForwarder {
@@ -379,6 +719,7 @@ impl PromptForwarder {
             registry,
             running_services,
             db,
+            circuit_breakers: CircuitBreakers::new(CircuitBreakerConfig::default()),
         }
     }
 
@@ -386,13 +727,13 @@ impl PromptForwarder {
     ///
     /// # Arguments
     /// * `cursor` - Optional pagination cursor as a stringified offset (e.g., "0", "100")
-    pub async fn list_prompts(&self, cursor: Option<&str>) -> Result<ListPromptsResult> {
-        let mut registry = self.registry.lock().await;
-
-        // Mark conflicts before listing
-        registry.mark_conflicts();
-
-        let prompts = registry.list_prompts();
+    /// * `filter` - Optional filter narrowing the prompts listed
+    pub async fn list_prompts(
+        &self,
+        cursor: Option<&str>,
+        filter: Option<&PromptFilter>,
+    ) -> Result<ListPromptsResult> {
+        let prompts = self.discovered_prompts(filter).await;
 
         // Parse cursor to get offset
         let offset = cursor.and_then(|c| c.parse::<usize>().ok()).unwrap_or(0);
@@ -437,7 +778,28 @@ impl PromptForwarder {
         })
     }
 
+    /// List the discovered prompts matching `filter`, with namespaced names.
+    pub async fn discovered_prompts(&self, filter: Option<&PromptFilter>) -> Vec<DiscoveredPrompt> {
+        let mut registry = self.registry.lock().await;
+
+        // Mark conflicts before listing
+        registry.mark_conflicts();
+
+        match filter.and_then(|f| f.capability.as_deref()) {
+            Some(capability) => registry.list_by_capability(capability),
+            None => registry.list_prompts(),
+        }
+    }
+
+    /// Export the catalog of discovered prompts; see `PromptRegistry::export_catalog`.
+    pub async fn export_catalog(&self) -> CatalogExport {
+        self.registry.lock().await.export_catalog()
+    }
+
     /// Get a specific prompt by name.
+    ///
+    /// Services whose `prompts/get` keeps failing are short-circuited with
+    /// `PromptError::ServiceUnavailable` until their circuit breaker resets.
     pub async fn get_prompt(
         &self,
         name: &str,
@@ -471,18 +833,94 @@ impl PromptForwarder {
             .get(&service_id)
             .ok_or_else(|| PromptError::Internal(format!("Service not found: {}", service_id)))?;
 
+        // Checked last, so an allowed probe always reaches the service and
+        // reports its outcome
+        let breaker_key = ServiceId::new(service_id.clone());
+        self.circuit_breakers
+            .check(&breaker_key, Instant::now())
+            .map_err(|retry_after| PromptError::ServiceUnavailable { retry_after })?;
+
         // Call the service's prompts/get method via rmcp
+        let recorded_args = arguments.clone().unwrap_or_default();
         let result = service
             .client
             .get_prompt(GetPromptRequestParams {
-                name: prompt_name,
+                name: prompt_name.clone(),
                 arguments: arguments.map(|a| a.into_iter().collect()),
                 meta: None,
             })
-            .await
-            .map_err(|e| PromptError::Internal(format!("Failed to get prompt: {}", e)))?;
+            .await;
+
+        // Argument history feeds `infer_argument_types`
+        if let Err(e) = QueryBuilder::record_prompt_usage(
+            &self.db,
+            &breaker_key,
+            &prompt_name,
+            &recorded_args,
+            result.is_ok(),
+        )
+        .await
+        {
+            tracing::warn!(prompt = %prompt_name, "Failed to record prompt usage: {}", e);
+        }
 
-        Ok(result)
+        match result {
+            Ok(result) => {
+                self.circuit_breakers.record_success(&breaker_key);
+                Ok(result)
+            }
+            Err(e) => {
+                self.circuit_breakers
+                    .record_failure(&breaker_key, Instant::now());
+                Err(PromptError::Internal(format!(
+                    "Failed to get prompt: {}",
+                    e
+                )))
+            }
+        }
+    }
+
+    /// Declared arguments of a prompt, with JSON Schema types inferred from
+    /// its last 20 successful calls.
+    ///
+    /// Results are cached in the registry until prompts are re-discovered.
+    pub async fn infer_argument_types(
+        &self,
+        service_id: &ServiceId,
+        prompt_name: &str,
+    ) -> Result<Vec<InferredPromptArgument>, PromptError> {
+        let declared = {
+            let registry = self.registry.lock().await;
+            if let Some(cached) = registry.inferred_arguments(service_id.as_str(), prompt_name) {
+                return Ok(cached.clone());
+            }
+            registry
+                .find(service_id.as_str(), prompt_name)
+                .ok_or_else(|| PromptError::NotFound(format!("{}/{}", service_id, prompt_name)))?
+                .arguments
+                .clone()
+                .unwrap_or_default()
+        };
+
+        let samples: Vec<JsonObject> = QueryBuilder::list_successful_prompt_usage(
+            &self.db,
+            service_id,
+            prompt_name,
+            INFERENCE_SAMPLE_SIZE,
+        )
+        .await
+        .map_err(|e| PromptError::Internal(format!("Failed to load prompt usage: {}", e)))?
+        .into_iter()
+        .map(|event| event.arguments)
+        .collect();
+
+        let inferred = infer_argument_types(&declared, &samples);
+        self.registry.lock().await.cache_inferred_arguments(
+            service_id.as_str(),
+            prompt_name,
+            inferred.clone(),
+        );
+        Ok(inferred)
     }
 
     /// Discover prompts from all running services.
@@ -518,12 +956,56 @@ impl PromptForwarder {
         Ok(count)
     }
 
+    /// Re-discover the prompts of a single service, leaving the others alone.
+    ///
+    /// The service's prompts are replaced in one step once the new list has
+    /// been fetched, so if the service can't be reached its old prompts are
+    /// kept. A service that is no longer running has its prompts removed.
+    pub async fn refresh_service_prompts(&self, service_id: &str) -> Result<RefreshResult> {
+        let service = self.running_services.lock().await.get(service_id).cloned();
+        let prompts = match &service {
+            Some(service) => self.fetch_service_prompts(service_id, service).await?,
+            None => Vec::new(),
+        };
+
+        let mut registry = self.registry.lock().await;
+        let removed = registry.remove_by_service(service_id);
+        let added = prompts.len();
+        for prompt in prompts {
+            registry.register(prompt);
+        }
+        registry.mark_conflicts();
+
+        Ok(RefreshResult {
+            added,
+            removed,
+            conflicts: registry.conflicting_prompts(service_id),
+        })
+    }
+
     /// Discover prompts from a specific service.
     async fn discover_service_prompts(
         &self,
         service_id: &str,
         service: &Arc<crate::mcp_client::RunningService>,
     ) -> Result<usize> {
+        let prompts = self.fetch_service_prompts(service_id, service).await?;
+        let count = prompts.len();
+
+        let mut registry = self.registry.lock().await;
+        for prompt in prompts {
+            registry.register(prompt);
+        }
+
+        Ok(count)
+    }
+
+    /// List a service's prompts without registering them.
+    async fn fetch_service_prompts(
+        &self,
+        service_id: &str,
+        service: &Arc<crate::mcp_client::RunningService>,
+    ) -> Result<Vec<DiscoveredPrompt>> {
         let list_result = service
             .client
             .list_prompts(None)
@@ -541,11 +1023,10 @@ impl PromptForwarder {
                 .to_string()
         });
 
-        let mut registry = self.registry.lock().await;
-        let mut count = 0;
-
-        for prompt in list_result.prompts {
-            registry.register(DiscoveredPrompt {
+        Ok(list_result
+            .prompts
+            .into_iter()
+            .map(|prompt| DiscoveredPrompt {
                 name: PromptName::new(prompt.name.to_string()),
                 title: prompt.title.map(|s| s.to_string()),
                 description: prompt.description.map(|s| s.to_string()),
@@ -562,11 +1043,9 @@ impl PromptForwarder {
                 icons: prompt.icons,
                 service_id: ServiceId::new(service_id),
                 service_name: ServiceName::new(service_name.clone()),
-            });
-            count += 1;
-        }
-
-        Ok(count)
+                capabilities: capabilities_from_meta(prompt.meta.as_ref()),
+            })
+            .collect())
     }
 
     /// Query the service name from the database by service_id.
@@ -613,6 +1092,7 @@ mod tests {
             icons: None,
             service_id: ServiceId::new(format!("service:{}", service_name)),
             service_name: ServiceName::new(service_name),
+            capabilities: Vec::new(),
         }
     }
 
@@ -642,6 +1122,42 @@ mod tests {
         assert_eq!(sanitize_name("my @ service"), "my-service");
     }
 
+    #[test]
+    fn test_export_catalog() {
+        let mut registry = PromptRegistry::new();
+        registry.register(mock_prompt("gitlab", "commit", Some("Create a commit")));
+        registry.register(mock_prompt("github", "review", None));
+        let mut commit = mock_prompt("github", "commit", Some("Create a commit"));
+        commit.arguments = Some(vec![McpPromptArgument {
+            name: "message".to_string(),
+            title: None,
+            description: Some("Commit | message".to_string()),
+            required: Some(true),
+        }]);
+        registry.register(commit);
+
+        let catalog = registry.export_catalog();
+        let services: Vec<(&str, usize)> = catalog
+            .services
+            .iter()
+            .map(|s| (s.service_name.as_str(), s.prompt_count))
+            .collect();
+        assert_eq!(services, vec![("github", 2), ("gitlab", 1)]);
+
+        let github: Vec<&str> = catalog.prompts_by_service["service:github"]
+            .iter()
+            .map(|p| p.namespaced_name.as_str())
+            .collect();
+        assert_eq!(github, vec!["github-commit", "github-review"]);
+
+        let md = catalog.to_markdown();
+        assert!(md.contains("- [github](#github) (2 prompts)"));
+        assert!(md.contains("- [gitlab](#gitlab) (1 prompt)"));
+        assert!(md.contains("### `github-commit`"));
+        assert!(md.contains("| `message` | yes | Commit \\| message |"));
+        assert!(md.find("## github").unwrap() < md.find("## gitlab").unwrap());
+    }
+
     #[test]
     fn test_prompt_registry_clear() {
         let mut registry = PromptRegistry::new();
@@ -694,6 +1210,85 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_prompt_registry_remove_by_service_resolves_conflicts() {
+        let mut registry = PromptRegistry::new();
+        registry.register(mock_prompt("github", "commit", Some("Create a commit")));
+        registry.register(mock_prompt("github", "review", None));
+        registry.register(mock_prompt("gitlab", "commit", Some("Create a commit")));
+        registry.cache_inferred_arguments("service:gitlab", "commit", Vec::new());
+        registry.mark_conflicts();
+        assert_eq!(
+            registry.conflicting_prompts("service:github"),
+            vec!["commit"]
+        );
+
+        assert_eq!(registry.remove_by_service("service:gitlab"), 1);
+        assert_eq!(registry.remove_by_service("service:gitlab"), 0);
+        registry.mark_conflicts();
+
+        assert_eq!(registry.len(), 2);
+        assert!(registry.conflicting_prompts("service:github").is_empty());
+        assert!(
+            registry
+                .inferred_arguments("service:gitlab", "commit")
+                .is_none()
+        );
+        // The bare alias now points at the remaining service's prompt
+        assert_eq!(
+            registry.resolve("commit"),
+            Some(("service:github".to_string(), "commit".to_string()))
+        );
+        assert!(
+            registry.list_prompts().iter().all(|p| !p
+                .description
+                .as_ref()
+                .unwrap()
+                .contains("Note:"))
+        );
+    }
+
+    #[test]
+    fn test_prompt_registry_list_by_capability() {
+        let mut registry = PromptRegistry::new();
+        let mut review = mock_prompt("github", "review", None);
+        review.capabilities = vec!["code_review".to_string(), "git".to_string()];
+        let mut lint = mock_prompt("linter", "lint", None);
+        lint.capabilities = vec!["code_review".to_string()];
+        registry.register(review);
+        registry.register(lint);
+        registry.register(mock_prompt("github", "commit", None));
+
+        let names: Vec<String> = registry
+            .list_by_capability("code_review")
+            .iter()
+            .map(|p| p.name.to_string())
+            .collect();
+        assert_eq!(names, vec!["github-review", "linter-lint"]);
+        assert!(registry.list_by_capability("deploy").is_empty());
+
+        registry.remove_by_service("service:linter");
+        assert_eq!(registry.list_by_capability("code_review").len(), 1);
+        registry.remove_by_service("service:github");
+        assert!(registry.list_by_capability("git").is_empty());
+    }
+
+    #[test]
+    fn test_capabilities_from_meta() {
+        let meta = Meta(
+            serde_json::json!({ "capabilities": ["code_review", " git ", "", 3, "git"] })
+                .as_object()
+                .cloned()
+                .unwrap(),
+        );
+        assert_eq!(
+            capabilities_from_meta(Some(&meta)),
+            vec!["code_review", "git"]
+        );
+        assert!(capabilities_from_meta(Some(&Meta::new())).is_empty());
+        assert!(capabilities_from_meta(None).is_empty());
+    }
+
     #[test]
     fn test_prompt_registry_case_insensitive_conflict() {
         let mut registry = PromptRegistry::new();
@@ -936,6 +1531,46 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_refresh_service_prompts_only_touches_that_service() {
+        let db = crate::db::create_connection(crate::db::DatabaseConfig {
+            url: "memory".to_string(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        crate::db::ensure_schema(&db).await.unwrap();
+
+        let registry = Arc::new(Mutex::new(PromptRegistry::new()));
+        let forwarder =
+            PromptForwarder::new(registry.clone(), Arc::new(Mutex::new(HashMap::new())), db);
+        {
+            let mut reg = registry.lock().await;
+            reg.register(mock_prompt("github", "commit", None));
+            reg.register(mock_prompt("gitlab", "commit", None));
+            reg.register(mock_prompt("gitlab", "merge", None));
+            reg.mark_conflicts();
+        }
+
+        // gitlab isn't running, so its prompts are dropped and none come back
+        let result = forwarder
+            .refresh_service_prompts("service:gitlab")
+            .await
+            .unwrap();
+        assert_eq!(
+            result,
+            RefreshResult {
+                added: 0,
+                removed: 2,
+                conflicts: Vec::new(),
+            }
+        );
+
+        let reg = registry.lock().await;
+        assert_eq!(reg.len(), 1);
+        assert!(reg.find("service:github", "commit").is_some());
+    }
+
     // === Security validation tests ===
 
     #[test]
@@ -1075,4 +1710,222 @@ mod tests {
         let result = forwarder.get_prompt("nonexistent-prompt", None).await;
         assert!(matches!(result, Err(PromptError::NotFound(_))));
     }
+
+    #[tokio::test]
+    async fn test_infer_argument_types_from_usage() {
+        let db_config = crate::db::DatabaseConfig {
+            url: "memory".to_string(),
+            ..Default::default()
+        };
+        let db = crate::db::create_connection(db_config).await.unwrap();
+        crate::db::ensure_schema(&db).await.unwrap();
+
+        let registry = Arc::new(Mutex::new(PromptRegistry::new()));
+        let running_services = Arc::new(Mutex::new(HashMap::new()));
+        let forwarder = PromptForwarder::new(registry.clone(), running_services, db.clone());
+
+        let mut prompt = mock_prompt("github", "review", None);
+        prompt.arguments = Some(vec![McpPromptArgument {
+            name: "pr_number".to_string(),
+            title: None,
+            description: None,
+            required: Some(true),
+        }]);
+        registry.lock().await.register(prompt);
+
+        let service_id = ServiceId::new("service:github");
+        for (args, success) in [
+            (serde_json::json!({ "pr_number": "12" }), true),
+            (serde_json::json!({ "pr_number": "7" }), true),
+            (serde_json::json!({ "pr_number": "not a number" }), false),
+        ] {
+            QueryBuilder::record_prompt_usage(
+                &db,
+                &service_id,
+                "review",
+                args.as_object().unwrap(),
+                success,
+            )
+            .await
+            .unwrap();
+        }
+
+        let inferred = forwarder
+            .infer_argument_types(&service_id, "review")
+            .await
+            .unwrap();
+        assert_eq!(inferred.len(), 1);
+        // The failed call is ignored
+        assert_eq!(inferred[0].sample_count, 2);
+        assert_eq!(inferred[0].schema, serde_json::json!({ "type": "number" }));
+        assert!(
+            registry
+                .lock()
+                .await
+                .inferred_arguments("service:github", "review")
+                .is_some()
+        );
+
+        let result = forwarder.infer_argument_types(&service_id, "unknown").await;
+        assert!(matches!(result, Err(PromptError::NotFound(_))));
+    }
+
+    // === Circuit breaker tests ===
+
+    fn breakers() -> CircuitBreakers {
+        CircuitBreakers::new(CircuitBreakerConfig {
+            failure_threshold: 3,
+            reset_timeout: Duration::from_secs(10),
+        })
+    }
+
+    fn state(breakers: &CircuitBreakers, service_id: &ServiceId) -> Option<CircuitBreakerState> {
+        breakers.states.lock().unwrap().get(service_id).copied()
+    }
+
+    #[test]
+    fn test_circuit_breaker_opens_after_threshold() {
+        let breakers = breakers();
+        let service = ServiceId::new("service:github");
+        let now = Instant::now();
+
+        breakers.record_failure(&service, now);
+        breakers.record_failure(&service, now);
+        assert_eq!(
+            state(&breakers, &service),
+            Some(CircuitBreakerState::Closed(2))
+        );
+        assert!(breakers.check(&service, now).is_ok());
+
+        breakers.record_failure(&service, now);
+        assert_eq!(
+            state(&breakers, &service),
+            Some(CircuitBreakerState::Open(now))
+        );
+
+        let retry_after = breakers
+            .check(&service, now + Duration::from_secs(4))
+            .unwrap_err();
+        assert_eq!(retry_after, Duration::from_secs(6));
+
+        // Other services are unaffected
+        assert!(
+            breakers
+                .check(&ServiceId::new("service:gitlab"), now)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_circuit_breaker_success_resets_failures() {
+        let breakers = breakers();
+        let service = ServiceId::new("service:github");
+        let now = Instant::now();
+
+        breakers.record_failure(&service, now);
+        breakers.record_failure(&service, now);
+        breakers.record_success(&service);
+        breakers.record_failure(&service, now);
+
+        assert_eq!(
+            state(&breakers, &service),
+            Some(CircuitBreakerState::Closed(1))
+        );
+    }
+
+    #[test]
+    fn test_circuit_breaker_half_open_allows_one_probe() {
+        let breakers = breakers();
+        let service = ServiceId::new("service:github");
+        let now = Instant::now();
+        for _ in 0..3 {
+            breakers.record_failure(&service, now);
+        }
+
+        let later = now + Duration::from_secs(10);
+        assert!(breakers.check(&service, later).is_ok());
+        assert_eq!(
+            state(&breakers, &service),
+            Some(CircuitBreakerState::HalfOpen(later))
+        );
+        assert!(breakers.check(&service, later).is_err());
+
+        // A failed probe reopens the breaker
+        breakers.record_failure(&service, later);
+        assert_eq!(
+            state(&breakers, &service),
+            Some(CircuitBreakerState::Open(later))
+        );
+
+        // A successful probe closes it
+        let much_later = later + Duration::from_secs(10);
+        assert!(breakers.check(&service, much_later).is_ok());
+        breakers.record_success(&service);
+        assert_eq!(
+            state(&breakers, &service),
+            Some(CircuitBreakerState::Closed(0))
+        );
+    }
+
+    #[test]
+    fn test_circuit_breaker_allows_new_probe_when_one_is_lost() {
+        let breakers = breakers();
+        let service = ServiceId::new("service:github");
+        let now = Instant::now();
+        for _ in 0..3 {
+            breakers.record_failure(&service, now);
+        }
+
+        // The probe never reports back, e.g. because its caller went away
+        let probe = now + Duration::from_secs(10);
+        assert!(breakers.check(&service, probe).is_ok());
+        assert_eq!(
+            breakers
+                .check(&service, probe + Duration::from_secs(4))
+                .unwrap_err(),
+            Duration::from_secs(6)
+        );
+
+        let retry = probe + Duration::from_secs(10);
+        assert!(breakers.check(&service, retry).is_ok());
+        assert_eq!(
+            state(&breakers, &service),
+            Some(CircuitBreakerState::HalfOpen(retry))
+        );
+    }
+
+    mod properties {
+        use super::*;
+        use proptest::prelude::*;
+
+        proptest! {
+            #[test]
+            fn sanitize_name_has_no_consecutive_hyphens(name in any::<String>()) {
+                prop_assert!(!sanitize_name(&name).contains("--"));
+            }
+
+            #[test]
+            fn sanitize_name_is_url_safe(name in any::<String>()) {
+                prop_assert!(
+                    sanitize_name(&name)
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+                );
+            }
+
+            #[test]
+            fn sanitized_names_are_valid_prompt_names(name in ".{1,256}") {
+                let sanitized = sanitize_name(&name);
+                // Names made only of special characters sanitize to nothing
+                prop_assume!(!sanitized.is_empty());
+                prop_assert!(is_valid_prompt_name(&sanitized));
+            }
+
+            #[test]
+            fn sanitize_name_is_idempotent(name in any::<String>()) {
+                let once = sanitize_name(&name);
+                prop_assert_eq!(sanitize_name(&once), once);
+            }
+        }
+    }
 }
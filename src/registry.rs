@@ -1,11 +1,19 @@
+//! MCP registries: remote catalogues of server manifests.
+//!
+//! A [`RegistryProvider`] lists and downloads manifests from one registry;
+//! [`McpRegistryManager`] syncs them into the `manifest` table, refusing
+//! manifests that target an MCP version this orchestrator cannot talk to.
+
 use anyhow::Result;
-use async_trait::async_trait;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::time::Duration;
-use surrealdb::engine::any::Any;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::time::{Duration, SystemTime};
 use surrealdb::Surreal;
+use surrealdb::engine::any::Any;
 use tracing::{debug, error, info, warn};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -17,6 +25,53 @@ pub struct RegistryConfig {
     pub auth_token: Option<String>,
     pub sync_interval: Duration,
     pub is_active: bool,
+    /// Install manifests even when they target an unsupported MCP version.
+    #[serde(default)]
+    pub force_install: bool,
+    /// Keep downloaded manifests on disk so syncs work offline.
+    #[serde(default)]
+    pub cache_manifests: bool,
+    /// Cache location; defaults to `crate::config::default_manifest_cache_dir()`.
+    #[serde(default)]
+    pub cache_dir: Option<PathBuf>,
+    /// How long a cached manifest is used before it is downloaded again.
+    #[serde(default = "default_cache_max_age_hours")]
+    pub cache_max_age_hours: u64,
+    /// JMESPath expression mapping the registry's `GET /manifests` response to
+    /// a list of `RegistryManifest`s, for registries with their own shape.
+    #[serde(default)]
+    pub transform_manifest: Option<String>,
+}
+
+fn default_cache_max_age_hours() -> u64 {
+    24
+}
+
+/// MCP protocol versions the orchestrator can talk to, oldest first.
+pub const MCP_SUPPORTED_VERSIONS: &[&str] =
+    &["2024-11-05", "2025-03-26", "2025-06-18", "2025-11-25"];
+
+/// Orchestrator features unavailable to services on older supported MCP versions.
+const MCP_VERSION_GAPS: &[(&str, &[&str])] = &[
+    (
+        "2024-11-05",
+        &[
+            "elicitation",
+            "structured tool output",
+            "streamable HTTP transport",
+        ],
+    ),
+    ("2025-03-26", &["elicitation", "structured tool output"]),
+];
+
+/// Whether a manifest's MCP version can be used by this orchestrator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompatibilityStatus {
+    Compatible,
+    /// Usable, but the listed features will not be available.
+    Degraded(Vec<String>),
+    /// Not usable; holds the reason.
+    Incompatible(String),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -35,6 +90,40 @@ pub struct RegistryManifest {
     pub dependencies: Vec<Dependency>,
 }
 
+impl RegistryManifest {
+    /// Check `mcp_version` against the versions this orchestrator supports.
+    pub fn is_compatible(&self) -> CompatibilityStatus {
+        let version = self.mcp_version.trim();
+
+        if !MCP_SUPPORTED_VERSIONS.contains(&version) {
+            let latest = MCP_SUPPORTED_VERSIONS
+                .last()
+                .expect("at least one supported MCP version");
+            // MCP versions are dates, so they compare lexically
+            let reason = if version.len() == latest.len() && version > *latest {
+                format!(
+                    "requires MCP {}, newer than the latest supported {}",
+                    version, latest
+                )
+            } else {
+                format!(
+                    "unsupported MCP version `{}` (supported: {})",
+                    version,
+                    MCP_SUPPORTED_VERSIONS.join(", ")
+                )
+            };
+            return CompatibilityStatus::Incompatible(reason);
+        }
+
+        match MCP_VERSION_GAPS.iter().find(|(v, _)| *v == version) {
+            Some((_, missing)) => {
+                CompatibilityStatus::Degraded(missing.iter().map(|f| f.to_string()).collect())
+            }
+            None => CompatibilityStatus::Compatible,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuthorInfo {
     pub name: String,
@@ -49,17 +138,43 @@ pub struct Dependency {
     pub optional: bool,
 }
 
-#[async_trait]
+/// Future returned by [`RegistryProvider`] methods.
+pub type ProviderFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T>> + Send + 'a>>;
+
+/// Source of manifests for one registry.
 pub trait RegistryProvider: Send + Sync {
-    async fn list_manifests(&self) -> Result<Vec<RegistryManifest>>;
-    async fn get_manifest(&self, name: &str, version: &str) -> Result<Option<RegistryManifest>>;
-    async fn download_manifest(&self, manifest: &RegistryManifest) -> Result<serde_json::Value>;
-    async fn verify_manifest(&self, manifest: &RegistryManifest, content: &[u8]) -> Result<bool>;
+    fn list_manifests(&self) -> ProviderFuture<'_, Vec<RegistryManifest>>;
+    fn get_manifest<'a>(
+        &'a self,
+        name: &'a str,
+        version: &'a str,
+    ) -> ProviderFuture<'a, Option<RegistryManifest>>;
+    fn download_manifest<'a>(
+        &'a self,
+        manifest: &'a RegistryManifest,
+    ) -> ProviderFuture<'a, serde_json::Value>;
+
+    /// Whether `content` matches the manifest's checksum, if it has one.
+    fn verify_manifest(&self, manifest: &RegistryManifest, content: &[u8]) -> bool {
+        verify_checksum(manifest, content)
+    }
+}
+
+/// Whether the SHA-256 of `content` is the manifest's checksum; manifests
+/// without a checksum always pass.
+fn verify_checksum(manifest: &RegistryManifest, content: &[u8]) -> bool {
+    use sha2::{Digest, Sha256};
+    match &manifest.checksum {
+        Some(checksum) => format!("{:x}", Sha256::digest(content)) == *checksum,
+        None => true,
+    }
 }
 
 pub struct McpRegistryManager {
     db: Surreal<Any>,
     registries: HashMap<String, Box<dyn RegistryProvider>>,
+    /// Registries whose manifests skip the MCP version check
+    force_install: HashSet<String>,
     client: Client,
 }
 
@@ -68,11 +183,12 @@ impl McpRegistryManager {
         Self {
             db,
             registries: HashMap::new(),
+            force_install: HashSet::new(),
             client: Client::builder()
                 .timeout(Duration::from_secs(30))
                 .user_agent("unicity-orchestrator/0.1.0")
                 .build()
-                .unwrap(),
+                .expect("registry HTTP client"),
         }
     }
 
@@ -80,39 +196,55 @@ impl McpRegistryManager {
         info!("Adding registry: {}", config.name);
 
         // Store in database
-        let registry_id = surrealdb::sql::Id::from(config.id.clone());
-        let query = r#"
-        UPSERT registry SET
-            id = $id,
-            url = $url,
-            name = $name,
-            description = $description,
-            is_active = $active,
-            created_at = time::now(),
-            updated_at = time::now()
-        RETURN id
-        "#;
-
-        let mut result = self.db
-            .query(query)
-            .bind(("id", registry_id))
+        self.db
+            .query(
+                r#"
+                UPSERT type::thing('registry', $id) SET
+                    url = $url,
+                    name = $name,
+                    description = $description,
+                    is_active = $active
+                "#,
+            )
+            .bind(("id", config.id.clone()))
             .bind(("url", config.url.clone()))
             .bind(("name", config.name.clone()))
             .bind(("description", config.description.clone()))
             .bind(("active", config.is_active))
-            .await?;
-
-        let _registry_id: Option<String> = result.take("id")?;
+            .await?
+            .check()?;
 
         // Create provider based on URL pattern
         let provider: Box<dyn RegistryProvider> = if config.url.contains("github.com") {
-            Box::new(GitHubRegistryProvider::new(config.clone(), self.client.clone()))
+            Box::new(GitHubRegistryProvider::new(
+                config.clone(),
+                self.client.clone(),
+            ))
         } else if config.url.contains("npm") {
-            Box::new(NpmRegistryProvider::new(config.clone(), self.client.clone()))
+            Box::new(NpmRegistryProvider::new(
+                config.clone(),
+                self.client.clone(),
+            ))
+        } else {
+            Box::new(HttpRegistryProvider::new(
+                config.clone(),
+                self.client.clone(),
+            ))
+        };
+        let provider: Box<dyn RegistryProvider> = if config.cache_manifests {
+            Box::new(CachingRegistryProvider::new(
+                provider,
+                ManifestCache::for_registry(&config),
+            ))
         } else {
-            Box::new(HttpRegistryProvider::new(config.clone(), self.client.clone()))
+            provider
         };
 
+        if config.force_install {
+            self.force_install.insert(config.id.clone());
+        } else {
+            self.force_install.remove(&config.id);
+        }
         self.registries.insert(config.id, provider);
         Ok(())
     }
@@ -122,6 +254,7 @@ impl McpRegistryManager {
 
         // Remove from memory
         self.registries.remove(&registry_id);
+        self.force_install.remove(&registry_id);
         Ok(())
     }
 
@@ -140,7 +273,10 @@ impl McpRegistryManager {
                     total_manifests += result.total_manifests;
                     new_manifests += result.new_manifests;
                     updated_manifests += result.updated_manifests;
-                    info!("Registry {} synced: {} manifests", registry_id, result.total_manifests);
+                    info!(
+                        "Registry {} synced: {} manifests",
+                        registry_id, result.total_manifests
+                    );
                 }
                 Err(e) => {
                     error!("Failed to sync registry {}: {}", registry_id, e);
@@ -149,8 +285,13 @@ impl McpRegistryManager {
             }
         }
 
-        info!("Sync complete: {} total, {} new, {} updated, {} errors",
-              total_manifests, new_manifests, updated_manifests, errors.len());
+        info!(
+            "Sync complete: {} total, {} new, {} updated, {} errors",
+            total_manifests,
+            new_manifests,
+            updated_manifests,
+            errors.len()
+        );
 
         Ok(SyncResult {
             total_manifests,
@@ -161,7 +302,9 @@ impl McpRegistryManager {
     }
 
     pub async fn sync_registry(&mut self, registry_id: &str) -> Result<RegistrySyncResult> {
-        let provider = self.registries.get(registry_id)
+        let provider = self
+            .registries
+            .get(registry_id)
             .ok_or_else(|| anyhow::anyhow!("Registry not found: {}", registry_id))?;
 
         debug!("Syncing registry: {}", registry_id);
@@ -176,39 +319,49 @@ impl McpRegistryManager {
             total_manifests += 1;
 
             // Check if manifest already exists
-            let existing = self.get_manifest(manifest.name.clone(), manifest.version.clone()).await?;
+            let existing = self
+                .stored_checksum(&manifest.name, &manifest.version)
+                .await?;
 
             if existing.is_none() {
                 // Download and store new manifest
                 match provider.download_manifest(&manifest).await {
                     Ok(content) => {
-                        if let Err(e) = self.store_manifest(registry_id.to_string(), manifest.clone(), content).await {
-                            error!("Failed to store manifest {} {}: {}",
-                                   manifest.name, manifest.version, e);
+                        if let Err(e) = self
+                            .store_manifest(registry_id.to_string(), manifest.clone(), content)
+                            .await
+                        {
+                            error!(
+                                "Failed to store manifest {} {}: {}",
+                                manifest.name, manifest.version, e
+                            );
                         } else {
                             new_manifests += 1;
                         }
                     }
                     Err(e) => {
-                        error!("Failed to download manifest {}: {}",
-                               manifest.name, e);
+                        error!("Failed to download manifest {}: {}", manifest.name, e);
                     }
                 }
             } else {
                 // Check for updates
-                if self.should_update_manifest(manifest.clone()).await? {
+                if Self::should_update_manifest(&manifest, existing.flatten()) {
                     match provider.download_manifest(&manifest).await {
                         Ok(content) => {
-                            if let Err(e) = self.update_manifest(registry_id.to_string(), manifest.clone(), content).await {
-                                error!("Failed to update manifest {} {}: {}",
-                                       &manifest.name, &manifest.version, e);
+                            if let Err(e) = self.update_manifest(manifest.clone(), content).await {
+                                error!(
+                                    "Failed to update manifest {} {}: {}",
+                                    &manifest.name, &manifest.version, e
+                                );
                             } else {
                                 updated_manifests += 1;
                             }
                         }
                         Err(e) => {
-                            error!("Failed to download updated manifest {} {}: {}",
-                                   manifest.name, manifest.version, e);
+                            error!(
+                                "Failed to download updated manifest {} {}: {}",
+                                manifest.name, manifest.version, e
+                            );
                         }
                     }
                 }
@@ -216,7 +369,7 @@ impl McpRegistryManager {
         }
 
         // Update last sync time
-        self.update_registry_sync_time(registry_id.to_string()).await?;
+        self.update_registry_sync_time(registry_id).await?;
 
         Ok(RegistrySyncResult {
             total_manifests,
@@ -225,6 +378,8 @@ impl McpRegistryManager {
         })
     }
 
+    /// Stored manifests whose name or description contains `query`, from
+    /// one registry and carrying every tag in `tags` if given.
     pub async fn search_manifests(
         &self,
         query: &str,
@@ -232,22 +387,18 @@ impl McpRegistryManager {
         tags: Option<Vec<String>>,
     ) -> Result<Vec<RegistryManifest>> {
         let mut conditions = Vec::new();
-
-        if let Some(rid) = registry_id {
-            conditions.push(format!("manifest.registry_id = record('registry', '{}')", rid));
+        if registry_id.is_some() {
+            conditions.push("registry_id = type::thing('registry', $registry_id)");
         }
-
         if !query.is_empty() {
-            conditions.push(format!("manifest.name ~ ${} OR manifest.description ~ ${}",
-                                  query, query));
+            conditions.push(
+                "(string::contains(string::lowercase(name), $query) \
+                 OR string::contains(string::lowercase(description ?? ''), $query))",
+            );
         }
-
-        if let Some(tag_list) = tags {
-            for tag in tag_list {
-                conditions.push(format!("manifest.tags CONTAINS '{}'", tag));
-            }
+        if tags.is_some() {
+            conditions.push("tags CONTAINSALL $tags");
         }
-
         let where_clause = if conditions.is_empty() {
             String::new()
         } else {
@@ -255,61 +406,58 @@ impl McpRegistryManager {
         };
 
         let query_str = format!(
-            r#"
-            SELECT manifest.*
-            FROM manifest
-            {}
-            ORDER BY manifest.name ASC
-            LIMIT 100
-            "#,
+            "SELECT name, metadata FROM manifest {} ORDER BY name ASC LIMIT 100",
             where_clause
         );
+        let mut result = self
+            .db
+            .query(query_str)
+            .bind(("registry_id", registry_id.map(str::to_string)))
+            .bind(("query", query.to_lowercase()))
+            .bind(("tags", tags.unwrap_or_default()))
+            .await?;
 
-        let mut result = self.db.query(&query_str).await?;
-        let manifests: Vec<RegistryManifest> = result.take("manifest")?;
+        #[derive(Deserialize)]
+        struct Row {
+            metadata: Option<RegistryManifest>,
+        }
 
-        Ok(manifests)
+        let rows: Vec<Row> = result.take(0)?;
+        Ok(rows.into_iter().filter_map(|row| row.metadata).collect())
     }
 
-    async fn get_manifest(&self, name: String, version: String) -> Result<Option<RegistryManifest>> {
-        let query = r#"
-        SELECT * FROM manifest
-        WHERE name = $name AND version = $version
-        LIMIT 1
-        "#;
+    /// The checksum of the stored manifest `name` `version`: `None` if it
+    /// isn't stored, `Some(None)` if it is but without a checksum.
+    async fn stored_checksum(&self, name: &str, version: &str) -> Result<Option<Option<String>>> {
+        #[derive(Deserialize)]
+        struct Stored {
+            checksum: Option<String>,
+        }
 
-        let mut result = self.db
-            .query(query)
-            .bind(("name", name))
-            .bind(("version", version))
+        let mut result = self
+            .db
+            .query(
+                r#"
+                SELECT checksum FROM manifest
+                WHERE name = $name AND version = $version
+                LIMIT 1
+                "#,
+            )
+            .bind(("name", name.to_string()))
+            .bind(("version", version.to_string()))
             .await?;
 
-        let manifest: Option<RegistryManifest> = result.take(0)?;
-        Ok(manifest)
+        let stored: Option<Stored> = result.take(0)?;
+        Ok(stored.map(|stored| stored.checksum))
     }
 
-    async fn should_update_manifest(&self, manifest: RegistryManifest) -> Result<bool> {
-        // Implement update logic based on checksum, version, etc.
-        // For now, always update if checksum is provided and different
-        if let Some(checksum) = &manifest.checksum {
-            let query = r#"
-            SELECT checksum FROM manifest
-            WHERE name = $name AND version = $version
-            LIMIT 1
-            "#;
-
-            let mut result = self.db
-                .query(query)
-                .bind(("name", manifest.name))
-                .bind(("version", manifest.version))
-                .await?;
-
-            let stored: Option<String> = result.take("checksum")?;
-            if let Some(stored_value) = stored {
-                return Ok(stored_value != *checksum);
-            }
+    /// A stored manifest is downloaded again when the registry now lists a
+    /// different checksum for it.
+    fn should_update_manifest(manifest: &RegistryManifest, stored: Option<String>) -> bool {
+        match (&manifest.checksum, stored) {
+            (Some(checksum), Some(stored)) => stored != *checksum,
+            _ => false,
         }
-        Ok(false)
     }
 
     async fn store_manifest(
@@ -318,47 +466,74 @@ impl McpRegistryManager {
         manifest: RegistryManifest,
         content: serde_json::Value,
     ) -> Result<()> {
-        let registry_thing = surrealdb::sql::Thing::from((String::from("registry"), registry_id));
+        match manifest.is_compatible() {
+            CompatibilityStatus::Compatible => {}
+            CompatibilityStatus::Degraded(missing) => {
+                warn!(
+                    "Manifest {} {} targets MCP {}; unavailable: {}",
+                    manifest.name,
+                    manifest.version,
+                    manifest.mcp_version,
+                    missing.join(", ")
+                );
+            }
+            CompatibilityStatus::Incompatible(reason) => {
+                if !self.force_install.contains(&registry_id) {
+                    return Err(anyhow::anyhow!(
+                        "Manifest {} {} is incompatible: {}",
+                        manifest.name,
+                        manifest.version,
+                        reason
+                    ));
+                }
+                warn!(
+                    "Force-installing incompatible manifest {} {}: {}",
+                    manifest.name, manifest.version, reason
+                );
+            }
+        }
 
         let query = r#"
         CREATE manifest SET
-            registry_id = $registry_id,
+            registry_id = type::thing('registry', $registry_id),
             name = $name,
             version = $version,
             description = $description,
+            metadata = $metadata,
             content = $content,
             hash = $hash,
             checksum = $checksum,
             tags = $tags,
-            is_active = true,
-            created_at = time::now()
+            is_active = true
         "#;
 
         let hash = self.calculate_hash(&content);
 
         self.db
             .query(query)
-            .bind(("registry_id", registry_thing))
+            .bind(("registry_id", registry_id))
             .bind(("name", manifest.name.clone()))
             .bind(("version", manifest.version.clone()))
             .bind(("description", manifest.description.clone()))
+            .bind(("metadata", serde_json::to_value(&manifest)?))
             .bind(("content", content))
-            .bind(("hash", hash.clone()))
+            .bind(("hash", hash))
             .bind(("checksum", manifest.checksum.clone()))
             .bind(("tags", manifest.tags.clone()))
-            .await?;
+            .await?
+            .check()?;
 
         Ok(())
     }
 
     async fn update_manifest(
         &self,
-        registry_id: String,
         manifest: RegistryManifest,
         content: serde_json::Value,
     ) -> Result<()> {
         let query = r#"
         UPDATE manifest SET
+            metadata = $metadata,
             content = $content,
             hash = $hash,
             checksum = $checksum,
@@ -371,28 +546,25 @@ impl McpRegistryManager {
 
         self.db
             .query(query)
+            .bind(("metadata", serde_json::to_value(&manifest)?))
             .bind(("content", content))
             .bind(("hash", hash))
             .bind(("checksum", manifest.checksum))
             .bind(("tags", manifest.tags))
             .bind(("name", manifest.name))
             .bind(("version", manifest.version))
-            .await?;
+            .await?
+            .check()?;
 
         Ok(())
     }
 
-    async fn update_registry_sync_time(&self, registry_id: String) -> Result<()> {
-        let query = r#"
-        UPDATE registry SET
-            last_sync = time::now()
-        WHERE id = $registry_id
-        "#;
-
+    async fn update_registry_sync_time(&self, registry_id: &str) -> Result<()> {
         self.db
-            .query(query)
-            .bind(("registry_id", registry_id))
-            .await?;
+            .query("UPDATE type::thing('registry', $registry_id) SET last_sync = time::now()")
+            .bind(("registry_id", registry_id.to_string()))
+            .await?
+            .check()?;
 
         Ok(())
     }
@@ -421,123 +593,243 @@ pub struct RegistrySyncResult {
     pub updated_manifests: usize,
 }
 
-// Registry Provider Implementations
+/// On-disk manifest cache, laid out as `{cache_dir}/{registry_id}/{name}-{version}.json`.
+pub struct ManifestCache {
+    cache_dir: PathBuf,
+    registry_id: String,
+    max_age: Duration,
+}
 
-pub struct HttpRegistryProvider {
-    config: RegistryConfig,
-    client: Client,
+impl ManifestCache {
+    pub fn for_registry(config: &RegistryConfig) -> Self {
+        Self {
+            cache_dir: config
+                .cache_dir
+                .clone()
+                .unwrap_or_else(crate::config::default_manifest_cache_dir),
+            registry_id: config.id.clone(),
+            max_age: Duration::from_secs(config.cache_max_age_hours * 3600),
+        }
+    }
+
+    fn path(&self, manifest: &RegistryManifest) -> std::io::Result<PathBuf> {
+        // Scoped npm names contain a slash
+        let name = manifest.name.replace(['/', '\\'], "_");
+        let version = manifest.version.replace(['/', '\\'], "_");
+        Ok(
+            crate::config::registry_cache_dir(&self.cache_dir, &self.registry_id)?
+                .join(format!("{}-{}.json", name, version)),
+        )
+    }
+
+    /// The cached manifest content and whether it is still within `max_age`.
+    pub fn read(&self, manifest: &RegistryManifest) -> Option<(serde_json::Value, bool)> {
+        let path = self.path(manifest).ok()?;
+        let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+        let content = serde_json::from_slice(&std::fs::read(&path).ok()?).ok()?;
+        let age = SystemTime::now()
+            .duration_since(modified)
+            .unwrap_or_default();
+        Some((content, age <= self.max_age))
+    }
+
+    pub fn write(&self, manifest: &RegistryManifest, content: &serde_json::Value) -> Result<()> {
+        let path = self.path(manifest)?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, serde_json::to_vec(content)?)?;
+        Ok(())
+    }
+
+    /// Delete every cached manifest of this registry.
+    pub fn clear(&self) -> Result<usize> {
+        Ok(crate::config::clear_manifest_cache(
+            &self.cache_dir,
+            &self.registry_id,
+        )?)
+    }
 }
 
-impl HttpRegistryProvider {
-    pub fn new(config: RegistryConfig, client: Client) -> Self {
-        Self { config, client }
+/// Wraps a provider so manifest downloads go through a [`ManifestCache`].
+///
+/// Fresh cache entries are served without touching the network; if a download
+/// fails, a stale entry is served instead.
+pub struct CachingRegistryProvider {
+    inner: Box<dyn RegistryProvider>,
+    cache: ManifestCache,
+}
+
+impl CachingRegistryProvider {
+    pub fn new(inner: Box<dyn RegistryProvider>, cache: ManifestCache) -> Self {
+        Self { inner, cache }
     }
 }
 
-#[async_trait]
-impl RegistryProvider for HttpRegistryProvider {
-    async fn list_manifests(&self) -> Result<Vec<RegistryManifest>> {
-        let url = format!("{}/manifests", self.config.url);
-        let response = self.client.get(&url).send().await?;
+impl RegistryProvider for CachingRegistryProvider {
+    fn list_manifests(&self) -> ProviderFuture<'_, Vec<RegistryManifest>> {
+        self.inner.list_manifests()
+    }
 
-        if response.status().is_success() {
-            let manifests: Vec<RegistryManifest> = response.json().await?;
-            Ok(manifests)
-        } else {
-            Err(anyhow::anyhow!("Failed to list manifests: {}", response.status()))
-        }
+    fn get_manifest<'a>(
+        &'a self,
+        name: &'a str,
+        version: &'a str,
+    ) -> ProviderFuture<'a, Option<RegistryManifest>> {
+        self.inner.get_manifest(name, version)
     }
 
-    async fn get_manifest(&self, name: &str, version: &str) -> Result<Option<RegistryManifest>> {
-        let url = format!("{}/manifests/{}/{}", self.config.url, name, version);
+    fn download_manifest<'a>(
+        &'a self,
+        manifest: &'a RegistryManifest,
+    ) -> ProviderFuture<'a, serde_json::Value> {
+        Box::pin(async move {
+            let cached = self.cache.read(manifest);
+            if let Some((content, true)) = &cached {
+                debug!(
+                    "Using cached manifest {} {}",
+                    manifest.name, manifest.version
+                );
+                return Ok(content.clone());
+            }
 
-        match self.client.get(&url).send().await {
-            Ok(response) if response.status().is_success() => {
-                let manifest: RegistryManifest = response.json().await?;
-                Ok(Some(manifest))
+            match self.inner.download_manifest(manifest).await {
+                Ok(content) => {
+                    if let Err(e) = self.cache.write(manifest, &content) {
+                        warn!(
+                            "Failed to cache manifest {} {}: {}",
+                            manifest.name, manifest.version, e
+                        );
+                    }
+                    Ok(content)
+                }
+                Err(e) => match cached {
+                    Some((content, _)) => {
+                        warn!(
+                            "Failed to download manifest {} {}, using stale cached copy: {}",
+                            manifest.name, manifest.version, e
+                        );
+                        Ok(content)
+                    }
+                    None => Err(e),
+                },
             }
-            Ok(_) => Ok(None),
-            Err(e) => Err(e.into()),
-        }
+        })
     }
 
-    async fn download_manifest(&self, manifest: &RegistryManifest) -> Result<serde_json::Value> {
-        let response = self.client.get(&manifest.manifest_url).send().await?;
-
-        if response.status().is_success() {
-            let content: serde_json::Value = response.json().await?;
-            Ok(content)
-        } else {
-            Err(anyhow::anyhow!("Failed to download manifest: {}", response.status()))
-        }
+    fn verify_manifest(&self, manifest: &RegistryManifest, content: &[u8]) -> bool {
+        self.inner.verify_manifest(manifest, content)
     }
+}
 
-    async fn verify_manifest(&self, manifest: &RegistryManifest, content: &[u8]) -> Result<bool> {
-        if let Some(checksum) = &manifest.checksum {
-            use sha2::{Digest, Sha256};
-            let mut hasher = Sha256::new();
-            hasher.update(content);
-            let calculated = format!("{:x}", hasher.finalize());
-            Ok(calculated == *checksum)
-        } else {
-            Ok(true) // No checksum to verify against
-        }
+// Registry Provider Implementations
+
+/// Download a manifest's content as JSON from its `manifest_url`.
+async fn fetch_manifest(
+    client: &Client,
+    manifest: &RegistryManifest,
+    source: &str,
+) -> Result<serde_json::Value> {
+    let response = client.get(&manifest.manifest_url).send().await?;
+
+    if response.status().is_success() {
+        Ok(response.json().await?)
+    } else {
+        Err(anyhow::anyhow!(
+            "Failed to download manifest from {}: {}",
+            source,
+            response.status()
+        ))
     }
 }
 
-pub struct GitHubRegistryProvider {
+pub struct HttpRegistryProvider {
     config: RegistryConfig,
     client: Client,
 }
 
-impl GitHubRegistryProvider {
+impl HttpRegistryProvider {
     pub fn new(config: RegistryConfig, client: Client) -> Self {
         Self { config, client }
     }
 }
 
-#[async_trait]
-impl RegistryProvider for GitHubRegistryProvider {
-    async fn list_manifests(&self) -> Result<Vec<RegistryManifest>> {
-        // Parse GitHub URL and search for mcp.json files
-        let url = if self.config.url.ends_with(".json") {
-            self.config.url.clone()
-        } else {
-            format!("{}/tree/main", self.config.url)
-        };
+impl RegistryProvider for HttpRegistryProvider {
+    fn list_manifests(&self) -> ProviderFuture<'_, Vec<RegistryManifest>> {
+        Box::pin(async move {
+            let url = format!("{}/manifests", self.config.url);
+            let response = self.client.get(&url).send().await?;
+
+            if response.status().is_success() {
+                let mut body: serde_json::Value = response.json().await?;
+                if let Some(expression) = &self.config.transform_manifest {
+                    body = crate::config::apply_manifest_transform(expression, &body)?;
+                }
+                let manifests: Vec<RegistryManifest> = serde_json::from_value(body)?;
+                Ok(manifests)
+            } else {
+                Err(anyhow::anyhow!(
+                    "Failed to list manifests: {}",
+                    response.status()
+                ))
+            }
+        })
+    }
 
-        // For now, implement a basic search
-        // In a real implementation, you'd use GitHub API to search for repositories with mcp.json
-        let manifests: Vec<RegistryManifest> = vec![];
-        Ok(manifests)
+    fn get_manifest<'a>(
+        &'a self,
+        name: &'a str,
+        version: &'a str,
+    ) -> ProviderFuture<'a, Option<RegistryManifest>> {
+        Box::pin(async move {
+            let url = format!("{}/manifests/{}/{}", self.config.url, name, version);
+            let response = self.client.get(&url).send().await?;
+            if response.status().is_success() {
+                Ok(Some(response.json().await?))
+            } else {
+                Ok(None)
+            }
+        })
     }
 
-    async fn get_manifest(&self, name: &str, version: &str) -> Result<Option<RegistryManifest>> {
-        // Implementation for GitHub
-        Ok(None)
+    fn download_manifest<'a>(
+        &'a self,
+        manifest: &'a RegistryManifest,
+    ) -> ProviderFuture<'a, serde_json::Value> {
+        Box::pin(fetch_manifest(&self.client, manifest, "registry"))
     }
+}
 
-    async fn download_manifest(&self, manifest: &RegistryManifest) -> Result<serde_json::Value> {
-        let response = self.client.get(&manifest.manifest_url).send().await?;
+pub struct GitHubRegistryProvider {
+    client: Client,
+}
 
-        if response.status().is_success() {
-            let content: serde_json::Value = response.json().await?;
-            Ok(content)
-        } else {
-            Err(anyhow::anyhow!("Failed to download manifest from GitHub: {}", response.status()))
-        }
+impl GitHubRegistryProvider {
+    pub fn new(_config: RegistryConfig, client: Client) -> Self {
+        Self { client }
     }
+}
 
-    async fn verify_manifest(&self, manifest: &RegistryManifest, content: &[u8]) -> Result<bool> {
-        if let Some(checksum) = &manifest.checksum {
-            use sha2::{Digest, Sha256};
-            let mut hasher = Sha256::new();
-            hasher.update(content);
-            let calculated = format!("{:x}", hasher.finalize());
-            Ok(calculated == *checksum)
-        } else {
-            Ok(true)
-        }
+impl RegistryProvider for GitHubRegistryProvider {
+    fn list_manifests(&self) -> ProviderFuture<'_, Vec<RegistryManifest>> {
+        // Discovering mcp.json files needs the GitHub search API, which isn't
+        // wired up yet
+        Box::pin(async { Ok(Vec::new()) })
+    }
+
+    fn get_manifest<'a>(
+        &'a self,
+        _name: &'a str,
+        _version: &'a str,
+    ) -> ProviderFuture<'a, Option<RegistryManifest>> {
+        Box::pin(async { Ok(None) })
+    }
+
+    fn download_manifest<'a>(
+        &'a self,
+        manifest: &'a RegistryManifest,
+    ) -> ProviderFuture<'a, serde_json::Value> {
+        Box::pin(fetch_manifest(&self.client, manifest, "GitHub"))
     }
 }
 
@@ -552,115 +844,98 @@ impl NpmRegistryProvider {
     }
 }
 
-#[async_trait]
 impl RegistryProvider for NpmRegistryProvider {
-    async fn list_manifests(&self) -> Result<Vec<RegistryManifest>> {
-        // Search npm for MCP packages
-        let url = format!("{}/-/v1/search", self.config.url);
-
-        let mut params = HashMap::new();
-        params.insert("text", "mcp");
-        params.insert("size", "100");
-
-        let response = self.client.get(&url).query(&params).send().await?;
-
-        if response.status().is_success() {
-            let search_result: serde_json::Value = response.json().await?;
-            let manifests = self.parse_npm_search_result(search_result)?;
-            Ok(manifests)
-        } else {
-            Err(anyhow::anyhow!("Failed to search npm registry: {}", response.status()))
-        }
+    fn list_manifests(&self) -> ProviderFuture<'_, Vec<RegistryManifest>> {
+        Box::pin(async move {
+            // Search npm for MCP packages
+            let url = format!("{}/-/v1/search?text=mcp&size=100", self.config.url);
+            let response = self.client.get(&url).send().await?;
+
+            if response.status().is_success() {
+                let search_result: serde_json::Value = response.json().await?;
+                Ok(Self::parse_npm_search_result(search_result))
+            } else {
+                Err(anyhow::anyhow!(
+                    "Failed to search npm registry: {}",
+                    response.status()
+                ))
+            }
+        })
     }
 
-    async fn get_manifest(&self, name: &str, version: &str) -> Result<Option<RegistryManifest>> {
-        let url = format!("{}/{}", self.config.url, name);
-
-        match self.client.get(&url).send().await {
-            Ok(response) if response.status().is_success() => {
+    fn get_manifest<'a>(
+        &'a self,
+        name: &'a str,
+        version: &'a str,
+    ) -> ProviderFuture<'a, Option<RegistryManifest>> {
+        Box::pin(async move {
+            let url = format!("{}/{}", self.config.url, name);
+            let response = self.client.get(&url).send().await?;
+            if response.status().is_success() {
                 let package: serde_json::Value = response.json().await?;
-                let manifest = self.parse_npm_package(name, version, package)?;
-                Ok(Some(manifest))
+                Ok(Some(Self::parse_npm_package(name, version, &package)?))
+            } else {
+                Ok(None)
             }
-            Ok(_) => Ok(None),
-            Err(e) => Err(e.into()),
-        }
-    }
-
-    async fn download_manifest(&self, manifest: &RegistryManifest) -> Result<serde_json::Value> {
-        let response = self.client.get(&manifest.manifest_url).send().await?;
-
-        if response.status().is_success() {
-            let content: serde_json::Value = response.json().await?;
-            Ok(content)
-        } else {
-            Err(anyhow::anyhow!("Failed to download manifest from npm: {}", response.status()))
-        }
+        })
     }
 
-    async fn verify_manifest(&self, manifest: &RegistryManifest, content: &[u8]) -> Result<bool> {
-        if let Some(checksum) = &manifest.checksum {
-            use sha2::{Digest, Sha256};
-            let mut hasher = Sha256::new();
-            hasher.update(content);
-            let calculated = format!("{:x}", hasher.finalize());
-            Ok(calculated == *checksum)
-        } else {
-            Ok(true)
-        }
+    fn download_manifest<'a>(
+        &'a self,
+        manifest: &'a RegistryManifest,
+    ) -> ProviderFuture<'a, serde_json::Value> {
+        Box::pin(fetch_manifest(&self.client, manifest, "npm"))
     }
 }
 
 impl NpmRegistryProvider {
-    fn parse_npm_search_result(&self, result: serde_json::Value) -> Result<Vec<RegistryManifest>> {
-        let mut manifests = Vec::new();
-
-        if let Some(objects) = result.get("objects").and_then(|o| o.as_array()) {
-            for obj in objects {
-                if let Some(package) = obj.get("package") {
-                    if let Some(name) = package.get("name").and_then(|n| n.as_str()) {
-                        if name.contains("mcp") || name.contains("model-context-protocol") {
-                            if let Some(manifest) = self.parse_npm_package(
-                                name,
-                                "latest",
-                                package.clone()
-                            ).ok() {
-                                manifests.push(manifest);
-                            }
-                        }
-                    }
-                }
-            }
-        }
+    fn parse_npm_search_result(result: serde_json::Value) -> Vec<RegistryManifest> {
+        let Some(objects) = result.get("objects").and_then(|o| o.as_array()) else {
+            return Vec::new();
+        };
 
-        Ok(manifests)
+        objects
+            .iter()
+            .filter_map(|obj| {
+                let package = obj.get("package")?;
+                let name = package.get("name").and_then(|n| n.as_str())?;
+                if !(name.contains("mcp") || name.contains("model-context-protocol")) {
+                    return None;
+                }
+                Self::parse_npm_package(name, "latest", package).ok()
+            })
+            .collect()
     }
 
     fn parse_npm_package(
-        &self,
         name: &str,
         version: &str,
-        package: serde_json::Value,
+        package: &serde_json::Value,
     ) -> Result<RegistryManifest> {
-        let description = package.get("description")
+        let description = package
+            .get("description")
             .and_then(|d| d.as_str())
             .map(|s| s.to_string());
 
-        let latest_version = package.get("dist-tags")
+        let latest_version = package
+            .get("dist-tags")
             .and_then(|tags| tags.get("latest"))
             .and_then(|v| v.as_str())
             .unwrap_or(version);
 
-        let dist = package.get("versions")
+        let dist = package
+            .get("versions")
             .and_then(|v| v.get(latest_version))
             .and_then(|ver| ver.get("dist"))
             .ok_or_else(|| anyhow::anyhow!("No distribution info found"))?;
 
-        let tarball_url = dist.get("tarball")
+        let tarball_url = dist
+            .get("tarball")
             .and_then(|url| url.as_str())
             .ok_or_else(|| anyhow::anyhow!("No tarball URL found"))?;
 
-        let checksum = dist.get("shasum")
+        let checksum = dist
+            .get("shasum")
             .and_then(|sum| sum.as_str())
             .map(|s| s.to_string());
 
@@ -675,10 +950,255 @@ impl NpmRegistryProvider {
             checksum,
             tags: vec!["npm".to_string()],
             author: None,
-            license: package.get("license")
+            license: package
+                .get("license")
                 .and_then(|l| l.as_str())
                 .map(|s| s.to_string()),
             dependencies: vec![], // Would need to parse from package.json
         })
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{DatabaseConfig, create_connection, ensure_schema};
+
+    async fn setup_test_db() -> crate::db::Db {
+        let config = DatabaseConfig {
+            url: "memory".to_string(),
+            ..Default::default()
+        };
+        let db = create_connection(config).await.unwrap();
+        ensure_schema(&db).await.unwrap();
+        db
+    }
+
+    fn manifest(name: &str, mcp_version: &str) -> RegistryManifest {
+        RegistryManifest {
+            name: name.to_string(),
+            version: "1.0.0".to_string(),
+            description: Some(format!("The {} server", name)),
+            mcp_version: mcp_version.to_string(),
+            schema_version: "1.0.0".to_string(),
+            manifest_url: format!("https://registry.example.com/{}.json", name),
+            download_url: format!("https://registry.example.com/{}.tgz", name),
+            checksum: None,
+            tags: vec!["files".to_string()],
+            author: None,
+            license: None,
+            dependencies: Vec::new(),
+        }
+    }
+
+    fn registry_config(id: &str, force_install: bool) -> RegistryConfig {
+        RegistryConfig {
+            id: id.to_string(),
+            name: id.to_string(),
+            url: "https://registry.example.com".to_string(),
+            description: None,
+            auth_token: None,
+            sync_interval: Duration::from_secs(3600),
+            is_active: true,
+            force_install,
+            cache_manifests: false,
+            cache_dir: None,
+            cache_max_age_hours: default_cache_max_age_hours(),
+            transform_manifest: None,
+        }
+    }
+
+    #[test]
+    fn test_is_compatible() {
+        assert_eq!(
+            manifest("fs", "2025-06-18").is_compatible(),
+            CompatibilityStatus::Compatible
+        );
+        assert!(matches!(
+            manifest("fs", "2025-03-26").is_compatible(),
+            CompatibilityStatus::Degraded(missing) if missing.contains(&"elicitation".to_string())
+        ));
+        assert!(matches!(
+            manifest("fs", "2099-01-01").is_compatible(),
+            CompatibilityStatus::Incompatible(reason) if reason.contains("newer than")
+        ));
+        assert!(matches!(
+            manifest("fs", "1.0").is_compatible(),
+            CompatibilityStatus::Incompatible(reason) if reason.contains("unsupported")
+        ));
+    }
+
+    #[test]
+    fn test_verify_checksum() {
+        let mut manifest = manifest("fs", "2025-06-18");
+        assert!(verify_checksum(&manifest, b"{}"));
+
+        manifest.checksum =
+            Some("44136fa355b3678a1146ad16f7e8649e94fb4fc21fe77e8310c060f61caaff8a".to_string());
+        assert!(verify_checksum(&manifest, b"{}"));
+        assert!(!verify_checksum(&manifest, b"[]"));
+    }
+
+    #[tokio::test]
+    async fn test_store_manifest_rejects_incompatible_unless_forced() {
+        let mut manager = McpRegistryManager::new(setup_test_db().await);
+        manager
+            .add_registry(registry_config("strict", false))
+            .await
+            .unwrap();
+        manager
+            .add_registry(registry_config("forced", true))
+            .await
+            .unwrap();
+
+        let future = manifest("future", "2099-01-01");
+        assert!(
+            manager
+                .store_manifest("strict".to_string(), future.clone(), serde_json::json!({}))
+                .await
+                .is_err()
+        );
+        manager
+            .store_manifest("forced".to_string(), future, serde_json::json!({}))
+            .await
+            .unwrap();
+        manager
+            .store_manifest(
+                "strict".to_string(),
+                manifest("fs", "2025-06-18"),
+                serde_json::json!({}),
+            )
+            .await
+            .unwrap();
+
+        let strict = manager
+            .search_manifests("", Some("strict"), None)
+            .await
+            .unwrap();
+        assert_eq!(strict.len(), 1);
+        assert_eq!(strict[0].name, "fs");
+
+        let found = manager
+            .search_manifests("FUT", None, Some(vec!["files".to_string()]))
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].name, "future");
+    }
+
+    /// Provider serving `{"name": <manifest name>}`, or failing when `fail` is set.
+    struct FakeProvider {
+        downloads: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+        fail: bool,
+    }
+
+    impl RegistryProvider for FakeProvider {
+        fn list_manifests(&self) -> ProviderFuture<'_, Vec<RegistryManifest>> {
+            Box::pin(async { Ok(Vec::new()) })
+        }
+
+        fn get_manifest<'a>(
+            &'a self,
+            _name: &'a str,
+            _version: &'a str,
+        ) -> ProviderFuture<'a, Option<RegistryManifest>> {
+            Box::pin(async { Ok(None) })
+        }
+
+        fn download_manifest<'a>(
+            &'a self,
+            manifest: &'a RegistryManifest,
+        ) -> ProviderFuture<'a, serde_json::Value> {
+            Box::pin(async move {
+                self.downloads
+                    .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                if self.fail {
+                    Err(anyhow::anyhow!("registry unreachable"))
+                } else {
+                    Ok(serde_json::json!({ "name": manifest.name }))
+                }
+            })
+        }
+    }
+
+    fn caching_provider(
+        cache_dir: &std::path::Path,
+        fail: bool,
+    ) -> (
+        CachingRegistryProvider,
+        std::sync::Arc<std::sync::atomic::AtomicUsize>,
+    ) {
+        let downloads = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let mut config = registry_config("main", false);
+        config.cache_dir = Some(cache_dir.to_path_buf());
+        let provider = CachingRegistryProvider::new(
+            Box::new(FakeProvider {
+                downloads: downloads.clone(),
+                fail,
+            }),
+            ManifestCache::for_registry(&config),
+        );
+        (provider, downloads)
+    }
+
+    /// Backdate a cached manifest so it is older than the default max age.
+    fn make_stale(cache: &ManifestCache, manifest: &RegistryManifest) {
+        let path = cache.path(manifest).unwrap();
+        let file = std::fs::File::options().write(true).open(path).unwrap();
+        file.set_modified(SystemTime::now() - Duration::from_secs(48 * 3600))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_manifest_cache_read_write_clear() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut config = registry_config("main", false);
+        config.cache_dir = Some(temp_dir.path().to_path_buf());
+        let cache = ManifestCache::for_registry(&config);
+
+        // Scoped npm names contain a slash
+        let scoped = manifest("@scope/fs", "2025-06-18");
+        assert!(cache.read(&scoped).is_none());
+
+        let content = serde_json::json!({ "tools": ["read_file"] });
+        cache.write(&scoped, &content).unwrap();
+        assert_eq!(cache.read(&scoped), Some((content.clone(), true)));
+
+        make_stale(&cache, &scoped);
+        assert_eq!(cache.read(&scoped), Some((content, false)));
+
+        assert_eq!(cache.clear().unwrap(), 1);
+        assert!(cache.read(&scoped).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_caching_provider_serves_fresh_cache() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let (provider, downloads) = caching_provider(temp_dir.path(), false);
+        let fs = manifest("fs", "2025-06-18");
+
+        let first = provider.download_manifest(&fs).await.unwrap();
+        let second = provider.download_manifest(&fs).await.unwrap();
+        assert_eq!(first, second);
+        assert_eq!(downloads.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_caching_provider_falls_back_to_stale_cache() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let fs = manifest("fs", "2025-06-18");
+        let git = manifest("git", "2025-06-18");
+
+        let (online, _) = caching_provider(temp_dir.path(), false);
+        let cached = online.download_manifest(&fs).await.unwrap();
+        make_stale(&online.cache, &fs);
+
+        // A stale entry is downloaded again, and served if that fails
+        let (offline, downloads) = caching_provider(temp_dir.path(), true);
+        assert_eq!(offline.download_manifest(&fs).await.unwrap(), cached);
+        assert_eq!(downloads.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        // Without a cached copy the download error is returned
+        assert!(offline.download_manifest(&git).await.is_err());
+    }
+}
@@ -7,13 +7,24 @@
 use crate::db::Db;
 use crate::types::{ResourceUri, ServiceId, ServiceName};
 use anyhow::Result;
+use base64::Engine;
 use rmcp::model::{
-    AnnotateAble, Annotations, Icon, ListResourceTemplatesResult, ListResourcesResult, RawResource,
-    RawResourceTemplate, ReadResourceRequestParams, ReadResourceResult,
+    AnnotateAble, Annotations, ClientRequest, CustomRequest, Icon, ListResourceTemplatesResult,
+    ListResourcesResult, RawResource, RawResourceTemplate, ReadResourceRequestParams,
+    ReadResourceResult, ServerInfo, ServerResult,
 };
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::{Mutex, mpsc};
+use tokio::task::JoinSet;
+use tokio::time::MissedTickBehavior;
+
+mod subscriptions;
+
+pub use subscriptions::{MAX_POLL_INTERVAL_SECS, MIN_POLL_INTERVAL_SECS, ResourceSubscriptions};
 
 /// Default page size for paginated resource listings.
 const DEFAULT_PAGE_SIZE: usize = 100;
@@ -21,6 +32,9 @@ const DEFAULT_PAGE_SIZE: usize = 100;
 /// Maximum URI length to prevent abuse.
 const MAX_URI_LENGTH: usize = 4096;
 
+/// Method of the draft `resources/write` extension.
+const WRITE_RESOURCE_METHOD: &str = "resources/write";
+
 /// A discovered resource from an MCP service.
 #[derive(Clone, Debug)]
 pub struct DiscoveredResource {
@@ -34,6 +48,9 @@ pub struct DiscoveredResource {
     pub annotations: Option<Annotations>,
     pub service_id: ServiceId,
     pub service_name: ServiceName,
+    /// Role a user must hold to write the resource, from its
+    /// `_meta.required_role`. `None` lets every user write it.
+    pub required_role: Option<String>,
 }
 
 /// A discovered resource template from an MCP service.
@@ -56,6 +73,8 @@ pub enum ResourceError {
     NotFound(String),
     /// Invalid URI (contains unsafe characters or fails validation).
     InvalidUri(String),
+    /// The service owning the resource hasn't declared `write_resources`.
+    WriteNotSupported(String),
     /// Internal error during resource operations.
     Internal(String),
 }
@@ -65,6 +84,9 @@ impl std::fmt::Display for ResourceError {
         match self {
             ResourceError::NotFound(uri) => write!(f, "Resource not found: {}", uri),
             ResourceError::InvalidUri(uri) => write!(f, "Invalid URI: {}", uri),
+            ResourceError::WriteNotSupported(service_id) => {
+                write!(f, "Service {} does not support resource writes", service_id)
+            }
             ResourceError::Internal(msg) => write!(f, "Internal error: {}", msg),
         }
     }
@@ -97,6 +119,33 @@ fn is_valid_uri(uri: &str) -> bool {
     true
 }
 
+/// How to resolve a resource URI that is exposed by more than one service.
+///
+/// The resolution of the service registering the duplicate URI decides the outcome.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictResolution {
+    /// Keep the resource from the service that registered it first.
+    #[default]
+    FirstWins,
+    /// Replace the existing resource with the newly registered one.
+    LastWins,
+    /// Stop serving the URI until an operator resolves the conflict.
+    Manual,
+}
+
+/// A resource URI exposed by more than one service.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ResourceConflict {
+    pub uri: String,
+    /// Services exposing the URI, in registration order.
+    pub service_ids: Vec<ServiceId>,
+    /// Resolution applied to the conflict.
+    pub resolution: ConflictResolution,
+    /// Service currently serving the URI, if any.
+    pub served_by: Option<ServiceId>,
+}
+
 /// Registry for managing discovered resources from MCP services.
 #[derive(Clone)]
 pub struct ResourceRegistry {
@@ -106,6 +155,10 @@ pub struct ResourceRegistry {
     resource_to_services: HashMap<String, Vec<ServiceId>>,
     /// Store discovered resource templates
     templates: Vec<DiscoveredResourceTemplate>,
+    /// Per-service conflict resolution (from config). Survives `clear()`.
+    resolutions: HashMap<ServiceId, ConflictResolution>,
+    /// Resolution applied to each conflicting URI
+    conflicts: HashMap<String, ConflictResolution>,
 }
 
 impl ResourceRegistry {
@@ -115,24 +168,90 @@ impl ResourceRegistry {
             resources: HashMap::new(),
             resource_to_services: HashMap::new(),
             templates: Vec::new(),
+            resolutions: HashMap::new(),
+            conflicts: HashMap::new(),
         }
     }
 
+    /// Set how URI conflicts caused by a service's resources are resolved.
+    pub fn set_conflict_resolution(
+        &mut self,
+        service_id: ServiceId,
+        resolution: ConflictResolution,
+    ) {
+        self.resolutions.insert(service_id, resolution);
+    }
+
+    /// Get the conflict resolution configured for a service.
+    pub fn conflict_resolution(&self, service_id: &ServiceId) -> ConflictResolution {
+        self.resolutions
+            .get(service_id)
+            .copied()
+            .unwrap_or_default()
+    }
+
     /// Register a discovered resource.
+    ///
+    /// If another service already registered the same URI, the conflict is
+    /// resolved according to the registering service's [`ConflictResolution`].
     pub fn register(&mut self, resource: DiscoveredResource) {
         let service_id = resource.service_id.clone();
         let uri = resource.uri.to_string();
 
         // Track which services have this resource
-        self.resource_to_services
-            .entry(uri.clone())
-            .or_default()
-            .push(service_id.clone());
+        let services = self.resource_to_services.entry(uri.clone()).or_default();
+        let is_conflict = services.iter().any(|s| s != &service_id);
+        services.push(service_id.clone());
+
+        if !is_conflict {
+            self.resources
+                .entry(uri)
+                .or_insert_with(|| (service_id, resource));
+            return;
+        }
 
-        // Store the resource (using the first service's version)
-        self.resources
-            .entry(uri)
-            .or_insert_with(|| (service_id, resource));
+        let resolution = self.conflict_resolution(&service_id);
+        // A manual conflict stays unresolved even if a later service prefers otherwise
+        if self.conflicts.get(&uri) == Some(&ConflictResolution::Manual) {
+            return;
+        }
+        self.conflicts.insert(uri.clone(), resolution);
+
+        match resolution {
+            ConflictResolution::FirstWins => {}
+            ConflictResolution::LastWins => {
+                self.resources.insert(uri, (service_id, resource));
+            }
+            ConflictResolution::Manual => {
+                self.resources.remove(&uri);
+            }
+        }
+    }
+
+    /// List resource URIs that are exposed by more than one service, sorted by URI.
+    pub fn detect_conflicts(&self) -> Vec<ResourceConflict> {
+        let mut conflicts: Vec<ResourceConflict> = self
+            .conflicts
+            .iter()
+            .map(|(uri, resolution)| {
+                let mut service_ids: Vec<ServiceId> = Vec::new();
+                for id in self.resource_to_services.get(uri).into_iter().flatten() {
+                    if !service_ids.contains(id) {
+                        service_ids.push(id.clone());
+                    }
+                }
+
+                ResourceConflict {
+                    uri: uri.clone(),
+                    service_ids,
+                    resolution: *resolution,
+                    served_by: self.resources.get(uri).map(|(id, _)| id.clone()),
+                }
+            })
+            .collect();
+
+        conflicts.sort_by(|a, b| a.uri.cmp(&b.uri));
+        conflicts
     }
 
     /// Register a discovered resource template.
@@ -153,6 +272,11 @@ impl ResourceRegistry {
         self.templates.clone()
     }
 
+    /// Get the registered resource served at `uri`.
+    pub fn get(&self, uri: &str) -> Option<&DiscoveredResource> {
+        self.resources.get(uri).map(|(_, resource)| resource)
+    }
+
     /// Resolve a resource URI to its entry.
     /// Returns the service_id and the original URI.
     pub fn resolve(&self, uri: &str) -> Option<(ServiceId, String)> {
@@ -177,6 +301,7 @@ impl ResourceRegistry {
         self.resources.clear();
         self.resource_to_services.clear();
         self.templates.clear();
+        self.conflicts.clear();
     }
 }
 
@@ -283,17 +408,14 @@ impl ResourceForwarder {
         })
     }
 
-    /// Read a specific resource by URI or namespaced name.
+    /// Turn a raw URI or namespaced name into a validated URI.
     ///
     /// Accepts either:
     /// - Raw URI: `file:///config.json`
     /// - Namespaced name: `filesystem:config` (parses service and looks up by original resource name)
     ///
     /// Namespaced lookups are case-insensitive.
-    pub async fn read_resource(
-        &self,
-        uri_or_name: &str,
-    ) -> Result<ReadResourceResult, ResourceError> {
+    async fn resolve_uri(&self, uri_or_name: &str) -> Result<String, ResourceError> {
         // Check if this is a namespaced name (service:resource)
         let uri = if uri_or_name.contains(':') && !uri_or_name.contains("://") {
             // This is a namespaced name - look up the actual URI
@@ -328,22 +450,56 @@ impl ResourceForwarder {
         if !is_valid_uri(&uri) {
             return Err(ResourceError::InvalidUri(uri));
         }
+        Ok(uri)
+    }
 
-        let registry = self.registry.lock().await;
-
-        // Resolve the URI to service_id
-        let (service_id, _) = registry
-            .resolve(&uri)
-            .ok_or_else(|| ResourceError::NotFound(uri.clone()))?;
+    /// Look up the registered resource for a URI or namespaced name (see
+    /// [`Self::resolve_uri`]).
+    pub async fn find_resource(
+        &self,
+        uri_or_name: &str,
+    ) -> Result<DiscoveredResource, ResourceError> {
+        let uri = self.resolve_uri(uri_or_name).await?;
+        self.registry
+            .lock()
+            .await
+            .get(&uri)
+            .cloned()
+            .ok_or(ResourceError::NotFound(uri))
+    }
 
-        // Drop the registry lock before making the async call
-        drop(registry);
+    /// Look up the running service that owns `uri`.
+    ///
+    /// The map lock is released before returning so requests from other
+    /// callers can run concurrently.
+    async fn owning_service(
+        &self,
+        uri: &str,
+    ) -> Result<(ServiceId, Arc<crate::mcp_client::RunningService>), ResourceError> {
+        let (service_id, _) = self
+            .registry
+            .lock()
+            .await
+            .resolve(uri)
+            .ok_or_else(|| ResourceError::NotFound(uri.to_string()))?;
 
-        // Forward the request to the appropriate service
-        let services = self.running_services.lock().await;
-        let service = services
+        let service = self
+            .running_services
+            .lock()
+            .await
             .get(service_id.as_str())
+            .cloned()
             .ok_or_else(|| ResourceError::Internal(format!("Service not found: {}", service_id)))?;
+        Ok((service_id, service))
+    }
+
+    /// Read a specific resource by URI or namespaced name (see [`Self::resolve_uri`]).
+    pub async fn read_resource(
+        &self,
+        uri_or_name: &str,
+    ) -> Result<ReadResourceResult, ResourceError> {
+        let uri = self.resolve_uri(uri_or_name).await?;
+        let (_, service) = self.owning_service(&uri).await?;
 
         // Read the actual resource contents
         let request = ReadResourceRequestParams {
@@ -358,6 +514,133 @@ impl ResourceForwarder {
             .map_err(|e| ResourceError::Internal(format!("Failed to read resource: {}", e)))
     }
 
+    /// Write `content` to a resource by URI or namespaced name, using the
+    /// draft `resources/write` extension.
+    ///
+    /// The owning service must declare `write_resources: true` under
+    /// `experimental.resources` in its server capabilities; otherwise this
+    /// fails with [`ResourceError::WriteNotSupported`]. The content is sent
+    /// base64-encoded as `blob`.
+    pub async fn write_resource(
+        &self,
+        uri_or_name: &str,
+        content: Vec<u8>,
+        mime_type: &str,
+    ) -> Result<(), ResourceError> {
+        let uri = self.resolve_uri(uri_or_name).await?;
+        let (service_id, service) = self.owning_service(&uri).await?;
+
+        if !supports_resource_writes(service.client.peer_info()) {
+            return Err(ResourceError::WriteNotSupported(service_id.to_string()));
+        }
+
+        let params = serde_json::json!({
+            "uri": uri,
+            "mimeType": mime_type,
+            "blob": base64::engine::general_purpose::STANDARD.encode(&content),
+        });
+        let request =
+            ClientRequest::CustomRequest(CustomRequest::new(WRITE_RESOURCE_METHOD, Some(params)));
+
+        match service.client.send_request(request).await {
+            Ok(ServerResult::EmptyResult(_) | ServerResult::CustomResult(_)) => {
+                tracing::info!(uri = %uri, service_id = %service_id, bytes = content.len(), "Wrote resource");
+                Ok(())
+            }
+            Ok(_) => Err(ResourceError::Internal(
+                "Unexpected response to resources/write".to_string(),
+            )),
+            Err(e) => Err(ResourceError::Internal(format!(
+                "Failed to write resource: {}",
+                e
+            ))),
+        }
+    }
+
+    /// Read several resources, at most `max_concurrent` at a time.
+    ///
+    /// Each URI (or namespaced name) is read as by [`Self::read_resource`], so
+    /// reads fan out to whichever services serve them. A failed read does not
+    /// affect the others. Results are returned in the order of `uris`.
+    pub async fn batch_read(
+        self: &Arc<Self>,
+        uris: &[String],
+        max_concurrent: usize,
+    ) -> Vec<(String, Result<ReadResourceResult, ResourceError>)> {
+        let mut results: Vec<Option<Result<ReadResourceResult, ResourceError>>> =
+            vec![None; uris.len()];
+        let mut pending = uris.iter().cloned().enumerate();
+        let mut reads = JoinSet::new();
+
+        loop {
+            while reads.len() < max_concurrent.max(1) {
+                let Some((index, uri)) = pending.next() else {
+                    break;
+                };
+                let forwarder = Arc::clone(self);
+                reads.spawn(async move { (index, forwarder.read_resource(&uri).await) });
+            }
+            let Some(joined) = reads.join_next().await else {
+                break;
+            };
+            match joined {
+                Ok((index, result)) => results[index] = Some(result),
+                Err(e) => tracing::error!("Resource read task failed: {}", e),
+            }
+        }
+
+        uris.iter()
+            .cloned()
+            .zip(results)
+            .map(|(uri, result)| {
+                let result = result.unwrap_or_else(|| {
+                    Err(ResourceError::Internal(
+                        "Resource read task failed".to_string(),
+                    ))
+                });
+                (uri, result)
+            })
+            .collect()
+    }
+
+    /// Poll a resource every `interval` and send the SHA-256 of its contents
+    /// to `changes` whenever they differ from the previous read.
+    ///
+    /// The first successful read only sets the baseline. Failed reads are
+    /// skipped, so a service that is briefly unavailable doesn't count as a
+    /// change. Returns once `changes` is closed.
+    pub async fn watch_resource(
+        &self,
+        uri: &str,
+        interval: Duration,
+        changes: mpsc::Sender<String>,
+    ) {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut last_hash: Option<String> = None;
+
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {}
+                _ = changes.closed() => return,
+            }
+
+            let hash = match self.read_resource(uri).await {
+                Ok(result) => content_sha256(&result),
+                Err(e) => {
+                    tracing::debug!(uri, "Failed to poll watched resource: {}", e);
+                    continue;
+                }
+            };
+            if last_hash.as_ref().is_some_and(|last| *last != hash)
+                && changes.send(hash.clone()).await.is_err()
+            {
+                return;
+            }
+            last_hash = Some(hash);
+        }
+    }
+
     /// List resource templates from discovered services.
     /// Template names are namespaced with their service name for provenance (e.g., "github:git-file").
     /// Accepts an optional service filter to return only templates from a specific service (case-insensitive).
@@ -429,6 +712,23 @@ impl ResourceForwarder {
         })
     }
 
+    /// Set how URI conflicts caused by a service's resources are resolved.
+    pub async fn set_conflict_resolution(
+        &self,
+        service_id: ServiceId,
+        resolution: ConflictResolution,
+    ) {
+        self.registry
+            .lock()
+            .await
+            .set_conflict_resolution(service_id, resolution);
+    }
+
+    /// List resource URIs that are currently exposed by more than one service.
+    pub async fn conflicts(&self) -> Vec<ResourceConflict> {
+        self.registry.lock().await.detect_conflicts()
+    }
+
     /// Discover resources from all running services.
     pub async fn discover_resources(&self) -> Result<usize> {
         // Clear any existing resources to avoid duplicates on re-discovery
@@ -453,6 +753,16 @@ impl ResourceForwarder {
             }
         }
 
+        for conflict in self.registry.lock().await.detect_conflicts() {
+            let services: Vec<&str> = conflict.service_ids.iter().map(|s| s.as_str()).collect();
+            tracing::warn!(
+                "Resource URI {} is exposed by multiple services [{}], resolution: {:?}",
+                conflict.uri,
+                services.join(", "),
+                conflict.resolution
+            );
+        }
+
         Ok(count)
     }
 
@@ -493,6 +803,14 @@ impl ResourceForwarder {
                 annotations: resource.annotations.clone(),
                 service_id: ServiceId::new(service_id),
                 service_name: ServiceName::new(service_name.clone()),
+                required_role: resource
+                    .meta
+                    .as_ref()
+                    .and_then(|meta| meta.0.get("required_role"))
+                    .and_then(|role| role.as_str())
+                    .map(str::trim)
+                    .filter(|role| !role.is_empty())
+                    .map(str::to_string),
             });
             count += 1;
         }
@@ -542,9 +860,34 @@ impl ResourceForwarder {
     }
 }
 
+/// Whether a service declared `write_resources: true` under
+/// `experimental.resources` in its server capabilities.
+fn supports_resource_writes(info: Option<&ServerInfo>) -> bool {
+    info.and_then(|info| info.capabilities.experimental.as_ref())
+        .and_then(|experimental| experimental.get("resources"))
+        .and_then(|resources| resources.get("write_resources"))
+        .and_then(|flag| flag.as_bool())
+        .unwrap_or(false)
+}
+
+/// Hex SHA-256 of a resource's contents, used to detect changes.
+fn content_sha256(result: &ReadResourceResult) -> String {
+    let bytes = serde_json::to_vec(&result.contents).unwrap_or_default();
+    format!("{:x}", Sha256::digest(bytes))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    #[test]
+    fn test_content_sha256_changes_with_contents() {
+        let read = |text: &str| ReadResourceResult {
+            contents: vec![rmcp::model::ResourceContents::text(text, "file:///a.txt")],
+        };
+        assert_eq!(content_sha256(&read("a")), content_sha256(&read("a")));
+        assert_ne!(content_sha256(&read("a")), content_sha256(&read("b")));
+    }
     use std::collections::HashMap;
 
     /// Create a mock DiscoveredResource for testing.
@@ -560,6 +903,7 @@ mod tests {
             annotations: None,
             service_id: ServiceId::new(format!("service:{}", service_name)),
             service_name: ServiceName::new(service_name),
+            required_role: None,
         }
     }
 
@@ -655,6 +999,88 @@ mod tests {
         assert!(result.is_some());
     }
 
+    #[test]
+    fn test_detect_conflicts_first_wins_by_default() {
+        let mut registry = ResourceRegistry::new();
+        registry.register(mock_resource("github", "file:///config.json", "config"));
+        registry.register(mock_resource("gitlab", "file:///config.json", "config"));
+        registry.register(mock_resource("gitlab", "file:///other.json", "other"));
+
+        let conflicts = registry.detect_conflicts();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].uri, "file:///config.json");
+        assert_eq!(
+            conflicts[0].service_ids,
+            vec![
+                ServiceId::new("service:github"),
+                ServiceId::new("service:gitlab")
+            ]
+        );
+        assert_eq!(conflicts[0].resolution, ConflictResolution::FirstWins);
+        assert_eq!(
+            registry.resolve("file:///config.json").unwrap().0,
+            ServiceId::new("service:github")
+        );
+    }
+
+    #[test]
+    fn test_conflict_resolution_last_wins() {
+        let mut registry = ResourceRegistry::new();
+        registry.set_conflict_resolution(
+            ServiceId::new("service:gitlab"),
+            ConflictResolution::LastWins,
+        );
+        registry.register(mock_resource("github", "file:///config.json", "config"));
+        registry.register(mock_resource("gitlab", "file:///config.json", "config"));
+
+        assert_eq!(registry.len(), 1);
+        let conflicts = registry.detect_conflicts();
+        assert_eq!(conflicts[0].resolution, ConflictResolution::LastWins);
+        assert_eq!(
+            conflicts[0].served_by,
+            Some(ServiceId::new("service:gitlab"))
+        );
+    }
+
+    #[test]
+    fn test_conflict_resolution_manual_withholds_uri() {
+        let mut registry = ResourceRegistry::new();
+        registry
+            .set_conflict_resolution(ServiceId::new("service:gitlab"), ConflictResolution::Manual);
+        registry.register(mock_resource("github", "file:///config.json", "config"));
+        registry.register(mock_resource("gitlab", "file:///config.json", "config"));
+        // A later first-wins service must not bring the URI back
+        registry.register(mock_resource("bitbucket", "file:///config.json", "config"));
+
+        assert!(registry.resolve("file:///config.json").is_none());
+        let conflicts = registry.detect_conflicts();
+        assert_eq!(conflicts[0].resolution, ConflictResolution::Manual);
+        assert_eq!(conflicts[0].service_ids.len(), 3);
+        assert_eq!(conflicts[0].served_by, None);
+    }
+
+    #[test]
+    fn test_clear_keeps_conflict_resolutions() {
+        let mut registry = ResourceRegistry::new();
+        let service_id = ServiceId::new("service:gitlab");
+        registry.set_conflict_resolution(service_id.clone(), ConflictResolution::LastWins);
+        registry.register(mock_resource("github", "file:///config.json", "config"));
+        registry.register(mock_resource("gitlab", "file:///config.json", "config"));
+
+        registry.clear();
+        assert!(registry.detect_conflicts().is_empty());
+        assert_eq!(
+            registry.conflict_resolution(&service_id),
+            ConflictResolution::LastWins
+        );
+    }
+
+    #[test]
+    fn test_conflict_resolution_deserialize() {
+        let resolution: ConflictResolution = serde_json::from_str(r#""last_wins""#).unwrap();
+        assert_eq!(resolution, ConflictResolution::LastWins);
+    }
+
     #[test]
     fn test_resource_registry_templates() {
         let mut registry = ResourceRegistry::new();
@@ -735,6 +1161,126 @@ mod tests {
         assert!(matches!(result, Err(ResourceError::NotFound(_))));
     }
 
+    #[tokio::test]
+    async fn test_write_resource_resolves_before_forwarding() {
+        let db_config = crate::db::DatabaseConfig {
+            url: "memory".to_string(),
+            ..Default::default()
+        };
+        let db = crate::db::create_connection(db_config).await.unwrap();
+        crate::db::ensure_schema(&db).await.unwrap();
+
+        let registry = Arc::new(Mutex::new(ResourceRegistry::new()));
+        let running_services = Arc::new(Mutex::new(HashMap::new()));
+        let forwarder = ResourceForwarder::new(registry.clone(), running_services.clone(), db);
+        registry.lock().await.register(mock_resource(
+            "filesystem",
+            "file:///config.json",
+            "config",
+        ));
+
+        let result = forwarder
+            .write_resource("file:///../etc/passwd", b"x".to_vec(), "text/plain")
+            .await;
+        assert!(matches!(result, Err(ResourceError::InvalidUri(_))));
+
+        let result = forwarder
+            .write_resource("file:///missing.txt", b"x".to_vec(), "text/plain")
+            .await;
+        assert!(matches!(result, Err(ResourceError::NotFound(_))));
+
+        // Found by namespaced name, but the service isn't running
+        let result = forwarder
+            .write_resource("filesystem:config", b"x".to_vec(), "text/plain")
+            .await;
+        assert!(matches!(result, Err(ResourceError::Internal(_))));
+    }
+
+    #[tokio::test]
+    async fn test_find_resource_by_uri_or_name() {
+        let db_config = crate::db::DatabaseConfig {
+            url: "memory".to_string(),
+            ..Default::default()
+        };
+        let db = crate::db::create_connection(db_config).await.unwrap();
+        crate::db::ensure_schema(&db).await.unwrap();
+
+        let registry = Arc::new(Mutex::new(ResourceRegistry::new()));
+        let running_services = Arc::new(Mutex::new(HashMap::new()));
+        let forwarder = ResourceForwarder::new(registry.clone(), running_services, db);
+        let mut resource = mock_resource("filesystem", "file:///config.json", "config");
+        resource.required_role = Some("admin".to_string());
+        registry.lock().await.register(resource);
+
+        let found = forwarder.find_resource("filesystem:config").await.unwrap();
+        assert_eq!(found.uri.as_str(), "file:///config.json");
+        assert_eq!(found.required_role.as_deref(), Some("admin"));
+        assert!(forwarder.find_resource("file:///config.json").await.is_ok());
+        assert!(matches!(
+            forwarder.find_resource("file:///missing.txt").await,
+            Err(ResourceError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_supports_resource_writes_reads_experimental_flag() {
+        assert!(!supports_resource_writes(None));
+
+        let mut info = ServerInfo::default();
+        assert!(!supports_resource_writes(Some(&info)));
+
+        let mut resources = rmcp::model::JsonObject::new();
+        resources.insert("write_resources".to_string(), serde_json::json!(false));
+        info.capabilities.experimental =
+            Some([("resources".to_string(), resources.clone())].into());
+        assert!(!supports_resource_writes(Some(&info)));
+
+        resources.insert("write_resources".to_string(), serde_json::json!(true));
+        info.capabilities.experimental = Some([("resources".to_string(), resources)].into());
+        assert!(supports_resource_writes(Some(&info)));
+    }
+
+    #[tokio::test]
+    async fn test_batch_read_reports_each_uri() {
+        let db_config = crate::db::DatabaseConfig {
+            url: "memory".to_string(),
+            ..Default::default()
+        };
+        let db = crate::db::create_connection(db_config).await.unwrap();
+        crate::db::ensure_schema(&db).await.unwrap();
+
+        let registry = Arc::new(Mutex::new(ResourceRegistry::new()));
+        let running_services = Arc::new(Mutex::new(HashMap::new()));
+        let forwarder = Arc::new(ResourceForwarder::new(
+            registry.clone(),
+            running_services.clone(),
+            db,
+        ));
+        registry
+            .lock()
+            .await
+            .register(mock_resource("github", "file:///stopped.rs", "stopped"));
+
+        let uris: Vec<String> = [
+            "file:///missing.txt",
+            "/etc/passwd",
+            "file:///stopped.rs",
+            "github:stopped",
+        ]
+        .iter()
+        .map(|uri| uri.to_string())
+        .collect();
+        let results = forwarder.batch_read(&uris, 2).await;
+
+        let returned: Vec<&String> = results.iter().map(|(uri, _)| uri).collect();
+        assert_eq!(returned, uris.iter().collect::<Vec<_>>());
+        assert!(matches!(results[0].1, Err(ResourceError::NotFound(_))));
+        assert!(matches!(results[1].1, Err(ResourceError::InvalidUri(_))));
+        // Registered, but its service isn't running
+        assert!(matches!(results[2].1, Err(ResourceError::Internal(_))));
+        assert!(matches!(results[3].1, Err(ResourceError::Internal(_))));
+    }
+
     #[tokio::test]
     async fn test_list_resources_namespaces_with_service() {
         let db_config = crate::db::DatabaseConfig {
@@ -1005,4 +1551,50 @@ mod tests {
             .unwrap();
         assert_eq!(result.resources.len(), 1);
     }
+
+    mod properties {
+        use super::*;
+        use proptest::prelude::*;
+
+        proptest! {
+            #[test]
+            fn is_valid_uri_accepts_plain_uris(
+                scheme in "[a-z][a-z0-9+.-]{0,15}",
+                path in "[A-Za-z0-9/_-]{0,200}",
+            ) {
+                let uri = format!("{scheme}://{path}");
+                prop_assert!(is_valid_uri(&uri));
+            }
+
+            #[test]
+            fn is_valid_uri_rejects_path_traversal(
+                prefix in "[a-z]+://[A-Za-z0-9/]{0,50}",
+                suffix in "[A-Za-z0-9/]{0,50}",
+                separator in prop::sample::select(vec!["../", "..\\"]),
+            ) {
+                let uri = format!("{prefix}{separator}{suffix}");
+                prop_assert!(!is_valid_uri(&uri));
+            }
+
+            #[test]
+            fn is_valid_uri_rejects_null_bytes(
+                prefix in "[a-z]+://[A-Za-z0-9/]{0,50}",
+                suffix in "[A-Za-z0-9/]{0,50}",
+            ) {
+                let uri = format!("{prefix}\0{suffix}");
+                prop_assert!(!is_valid_uri(&uri));
+            }
+
+            #[test]
+            fn is_valid_uri_rejects_overlong_uris(extra in 1usize..512) {
+                let uri = format!("file://{}", "a".repeat(MAX_URI_LENGTH + extra));
+                prop_assert!(!is_valid_uri(&uri));
+            }
+
+            #[test]
+            fn is_valid_uri_requires_a_scheme_separator(uri in "[^:]*") {
+                prop_assert!(!is_valid_uri(&uri));
+            }
+        }
+    }
 }
@@ -0,0 +1,252 @@
+//! Resource subscriptions requested through the `unicity.subscribe_resource` tool.
+//!
+//! Each subscription polls one resource with
+//! [`ResourceForwarder::watch_resource`] and sends a
+//! `notifications/resources/updated` to the session that created it when the
+//! contents change. Subscriptions end when they are cancelled or when the
+//! session's transport closes.
+
+use super::{ResourceError, ResourceForwarder};
+use rmcp::model::{Meta, Notification, ResourceUpdatedNotificationParam, ServerNotification};
+use rmcp::service::{Peer, RoleServer};
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// Shortest allowed poll interval.
+pub const MIN_POLL_INTERVAL_SECS: u64 = 10;
+
+/// Longest allowed poll interval.
+pub const MAX_POLL_INTERVAL_SECS: u64 = 3600;
+
+/// Errors from creating a subscription.
+#[derive(Debug, Clone)]
+pub enum SubscriptionError {
+    /// The poll interval is outside `MIN_POLL_INTERVAL_SECS..=MAX_POLL_INTERVAL_SECS`.
+    InvalidInterval(u64),
+    /// The resource can't be read.
+    Resource(ResourceError),
+}
+
+impl std::fmt::Display for SubscriptionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SubscriptionError::InvalidInterval(secs) => write!(
+                f,
+                "poll_interval_secs must be between {} and {}, got {}",
+                MIN_POLL_INTERVAL_SECS, MAX_POLL_INTERVAL_SECS, secs
+            ),
+            SubscriptionError::Resource(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for SubscriptionError {}
+
+struct Subscription {
+    session_id: String,
+    uri: String,
+    task: JoinHandle<()>,
+}
+
+/// Active resource subscriptions of all sessions, by subscription ID.
+pub struct ResourceSubscriptions {
+    forwarder: Arc<ResourceForwarder>,
+    subscriptions: Arc<Mutex<HashMap<String, Subscription>>>,
+}
+
+impl ResourceSubscriptions {
+    pub fn new(forwarder: Arc<ResourceForwarder>) -> Self {
+        Self {
+            forwarder,
+            subscriptions: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Start watching `uri` for `session_id` and return the subscription ID.
+    ///
+    /// The resource is read once up front so that unknown URIs are rejected.
+    /// Subscribing again to the same URI from the same session replaces the
+    /// earlier subscription.
+    pub async fn subscribe(
+        &self,
+        session_id: &str,
+        uri: &str,
+        poll_interval_secs: u64,
+        peer: Peer<RoleServer>,
+    ) -> Result<String, SubscriptionError> {
+        let interval = poll_interval(poll_interval_secs)?;
+        self.forwarder
+            .read_resource(uri)
+            .await
+            .map_err(SubscriptionError::Resource)?;
+
+        let subscription_id = uuid::Uuid::new_v4().to_string();
+
+        // Locked before spawning so the task can't end and remove itself
+        // before it has been added
+        let mut subscriptions = self
+            .subscriptions
+            .lock()
+            .expect("subscriptions lock poisoned");
+        subscriptions.retain(|_, existing| {
+            let replaced = existing.session_id == session_id && existing.uri == uri;
+            if replaced {
+                existing.task.abort();
+            }
+            !replaced
+        });
+        let task = tokio::spawn(watch_for_session(
+            self.forwarder.clone(),
+            self.subscriptions.clone(),
+            subscription_id.clone(),
+            uri.to_string(),
+            interval,
+            peer,
+        ));
+        subscriptions.insert(
+            subscription_id.clone(),
+            Subscription {
+                session_id: session_id.to_string(),
+                uri: uri.to_string(),
+                task,
+            },
+        );
+        drop(subscriptions);
+
+        tracing::info!(
+            subscription_id = %subscription_id,
+            session_id,
+            uri,
+            poll_interval_secs,
+            "Resource subscription started"
+        );
+        Ok(subscription_id)
+    }
+
+    /// Cancel a subscription owned by `session_id`. Returns the URI it watched,
+    /// or `None` if the session has no such subscription.
+    pub fn unsubscribe(&self, session_id: &str, subscription_id: &str) -> Option<String> {
+        let mut subscriptions = self
+            .subscriptions
+            .lock()
+            .expect("subscriptions lock poisoned");
+        let owned = subscriptions
+            .get(subscription_id)
+            .is_some_and(|subscription| subscription.session_id == session_id);
+        if !owned {
+            return None;
+        }
+        let subscription = subscriptions.remove(subscription_id)?;
+        subscription.task.abort();
+        Some(subscription.uri)
+    }
+
+    /// Cancel every subscription of a session. Returns how many were cancelled.
+    pub fn unsubscribe_session(&self, session_id: &str) -> usize {
+        let mut subscriptions = self
+            .subscriptions
+            .lock()
+            .expect("subscriptions lock poisoned");
+        let before = subscriptions.len();
+        subscriptions.retain(|_, subscription| {
+            let owned = subscription.session_id == session_id;
+            if owned {
+                subscription.task.abort();
+            }
+            !owned
+        });
+        before - subscriptions.len()
+    }
+
+    /// Number of active subscriptions across all sessions.
+    pub fn len(&self) -> usize {
+        self.subscriptions
+            .lock()
+            .expect("subscriptions lock poisoned")
+            .len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Validate a requested poll interval.
+pub fn poll_interval(secs: u64) -> Result<Duration, SubscriptionError> {
+    if (MIN_POLL_INTERVAL_SECS..=MAX_POLL_INTERVAL_SECS).contains(&secs) {
+        Ok(Duration::from_secs(secs))
+    } else {
+        Err(SubscriptionError::InvalidInterval(secs))
+    }
+}
+
+/// Notify `peer` of changes to `uri` until the transport closes or a
+/// notification can't be delivered, then drop the subscription.
+async fn watch_for_session(
+    forwarder: Arc<ResourceForwarder>,
+    subscriptions: Arc<Mutex<HashMap<String, Subscription>>>,
+    subscription_id: String,
+    uri: String,
+    interval: Duration,
+    peer: Peer<RoleServer>,
+) {
+    let (tx, mut rx) = mpsc::channel(1);
+    let watch = forwarder.watch_resource(&uri, interval, tx);
+    let notify = async {
+        while let Some(content_sha256) = rx.recv().await {
+            let mut notification =
+                Notification::new(ResourceUpdatedNotificationParam { uri: uri.clone() });
+            let meta = json!({
+                "subscriptionId": subscription_id,
+                "contentSha256": content_sha256,
+            });
+            if let serde_json::Value::Object(meta) = meta {
+                notification.extensions.insert(Meta(meta));
+            }
+            let notification = ServerNotification::ResourceUpdatedNotification(notification);
+            if let Err(e) = peer.send_notification(notification).await {
+                tracing::debug!(uri = %uri, "Failed to send resource update: {}", e);
+                return;
+            }
+        }
+    };
+    // Changes may be rare, so check for a closed session on every poll too
+    let session_closed = async {
+        let mut ticker = tokio::time::interval(interval);
+        while !peer.is_transport_closed() {
+            ticker.tick().await;
+        }
+    };
+
+    tokio::select! {
+        _ = watch => {}
+        _ = notify => {}
+        _ = session_closed => {}
+    }
+
+    subscriptions
+        .lock()
+        .expect("subscriptions lock poisoned")
+        .remove(&subscription_id);
+    tracing::info!(subscription_id = %subscription_id, uri = %uri, "Resource subscription ended");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_poll_interval_bounds() {
+        assert_eq!(poll_interval(10).unwrap(), Duration::from_secs(10));
+        assert_eq!(poll_interval(3600).unwrap(), Duration::from_secs(3600));
+        assert!(matches!(
+            poll_interval(9),
+            Err(SubscriptionError::InvalidInterval(9))
+        ));
+        assert!(poll_interval(3601).is_err());
+    }
+}
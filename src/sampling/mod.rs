@@ -0,0 +1,198 @@
+//! Sampling forwarding for MCP servers.
+//!
+//! Downstream MCP services may ask their client to run an LLM completion via
+//! `sampling/createMessage`. The orchestrator is that client, so it relays the
+//! request to the LLM client whose tool call the service is handling and
+//! returns its answer.
+//!
+//! Each client session has its own [`SamplingForwarder`]. It is made current
+//! with [`with_session_sampling`] while that session's tool call runs, so the
+//! downstream connection can tell whose call a request belongs to.
+
+use rmcp::model::{ClientCapabilities, CreateMessageRequestParams, CreateMessageResult};
+use rmcp::service::{Peer, RoleServer};
+use std::future::Future;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+tokio::task_local! {
+    static SESSION_SAMPLING: Arc<SamplingForwarder>;
+}
+
+/// Run `future` with `forwarder` as the client that sampling requests made
+/// during its downstream tool calls are relayed to.
+pub async fn with_session_sampling<F: Future>(
+    forwarder: Arc<SamplingForwarder>,
+    future: F,
+) -> F::Output {
+    SESSION_SAMPLING.scope(forwarder, future).await
+}
+
+/// The forwarder of the session whose request the current task is handling.
+pub fn current_session_sampling() -> Option<Arc<SamplingForwarder>> {
+    SESSION_SAMPLING.try_with(Clone::clone).ok()
+}
+
+/// Errors that can occur while forwarding a sampling request.
+#[derive(Debug, Clone)]
+pub enum SamplingError {
+    /// The connected client did not declare the `sampling` capability.
+    Unsupported,
+    /// No client is connected to the orchestrator.
+    NotConnected,
+    /// The request could not be tied to the session whose tool call it
+    /// belongs to.
+    Unattributed,
+    /// The client failed to produce a completion.
+    Request(String),
+}
+
+impl std::fmt::Display for SamplingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SamplingError::Unsupported => write!(f, "Client does not support sampling"),
+            SamplingError::NotConnected => write!(f, "No client connected"),
+            SamplingError::Unattributed => {
+                write!(f, "Sampling request does not belong to a single session")
+            }
+            SamplingError::Request(msg) => write!(f, "Sampling request failed: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for SamplingError {}
+
+/// Result type for sampling operations.
+pub type SamplingResult<T> = std::result::Result<T, SamplingError>;
+
+/// Relays `sampling/createMessage` requests from downstream services to one client.
+#[derive(Clone, Default)]
+pub struct SamplingForwarder {
+    /// Client capabilities from the `initialize` request
+    client_capabilities: Arc<RwLock<Option<ClientCapabilities>>>,
+
+    /// Peer reference for sending sampling requests to the client
+    peer: Arc<RwLock<Option<Peer<RoleServer>>>>,
+}
+
+impl SamplingForwarder {
+    /// Create a forwarder with no client connected yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store the peer reference for sending sampling requests.
+    pub async fn set_peer(&self, peer: Peer<RoleServer>) {
+        *self.peer.write().await = Some(peer);
+    }
+
+    /// Update client capabilities from the initialize request.
+    pub async fn set_client_capabilities(&self, capabilities: &ClientCapabilities) {
+        *self.client_capabilities.write().await = Some(capabilities.clone());
+    }
+
+    /// Whether the connected client declared the `sampling` capability.
+    ///
+    /// Returns false until client capabilities have been received.
+    pub async fn client_supports_sampling(&self) -> bool {
+        self.client_capabilities
+            .read()
+            .await
+            .as_ref()
+            .is_some_and(|capabilities| capabilities.sampling.is_some())
+    }
+
+    /// Forward a sampling request from a downstream service to the client.
+    pub async fn forward_sampling_request(
+        &self,
+        params: CreateMessageRequestParams,
+    ) -> SamplingResult<CreateMessageResult> {
+        if !self.client_supports_sampling().await {
+            return Err(SamplingError::Unsupported);
+        }
+
+        let peer = self
+            .peer
+            .read()
+            .await
+            .clone()
+            .ok_or(SamplingError::NotConnected)?;
+
+        tracing::debug!(
+            messages = params.messages.len(),
+            max_tokens = params.max_tokens,
+            "Forwarding sampling request to client"
+        );
+
+        peer.create_message(params)
+            .await
+            .map_err(|e| SamplingError::Request(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::model::{Content, Role, SamplingMessage};
+
+    fn params() -> CreateMessageRequestParams {
+        CreateMessageRequestParams {
+            meta: None,
+            task: None,
+            messages: vec![SamplingMessage {
+                role: Role::User,
+                content: Content::text("Summarize this"),
+            }],
+            model_preferences: None,
+            system_prompt: None,
+            include_context: None,
+            temperature: None,
+            max_tokens: 100,
+            stop_sequences: None,
+            metadata: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_client_supports_sampling() {
+        let forwarder = SamplingForwarder::new();
+        assert!(!forwarder.client_supports_sampling().await);
+
+        forwarder
+            .set_client_capabilities(&ClientCapabilities::default())
+            .await;
+        assert!(!forwarder.client_supports_sampling().await);
+
+        forwarder
+            .set_client_capabilities(&ClientCapabilities::builder().enable_sampling().build())
+            .await;
+        assert!(forwarder.client_supports_sampling().await);
+    }
+
+    #[tokio::test]
+    async fn test_current_session_sampling_is_scoped() {
+        assert!(current_session_sampling().is_none());
+
+        let forwarder = Arc::new(SamplingForwarder::new());
+        let current =
+            with_session_sampling(forwarder.clone(), async { current_session_sampling() }).await;
+        assert!(current.is_some_and(|current| Arc::ptr_eq(&current, &forwarder)));
+    }
+
+    #[tokio::test]
+    async fn test_forward_requires_capability_and_peer() {
+        let forwarder = SamplingForwarder::new();
+        assert!(matches!(
+            forwarder.forward_sampling_request(params()).await,
+            Err(SamplingError::Unsupported)
+        ));
+
+        forwarder
+            .set_client_capabilities(&ClientCapabilities::builder().enable_sampling().build())
+            .await;
+        assert!(matches!(
+            forwarder.forward_sampling_request(params()).await,
+            Err(SamplingError::NotConnected)
+        ));
+    }
+}
@@ -4,7 +4,10 @@
 
 use std::collections::HashSet;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::Instrument;
 
 use anyhow::Result;
 use axum::Router;
@@ -18,9 +21,15 @@ use rmcp::{
     service::{NotificationContext, Peer, RequestContext, RoleServer},
 };
 
-use crate::auth::{AuthConfig, AuthError, AuthExtractor, UserContext};
+use crate::auth::{
+    AuthConfig, AuthError, AuthExtractor, MissingRoleError, UNAUTHORIZED_ERROR_CODE, UserContext,
+};
+use crate::correlation::{new_correlation_id, with_correlation_id};
 use crate::orchestrator::Orchestrator;
+use crate::prompts::PromptFilter;
 use crate::resources::ResourceError;
+use crate::sampling::with_session_sampling;
+use crate::session::McpServerSession;
 use crate::tools::ToolRegistry;
 
 /// Type alias for HTTP request parts stored in rmcp extensions.
@@ -40,6 +49,8 @@ pub struct McpServer {
     user_context: Arc<RwLock<Option<UserContext>>>,
     /// Optional auth extractor for HTTP mode.
     auth_extractor: Option<Arc<AuthExtractor>>,
+    /// This client's session, created during initialize().
+    session: Arc<RwLock<Option<Arc<McpServerSession>>>>,
 }
 
 impl McpServer {
@@ -54,6 +65,7 @@ impl McpServer {
             subscriptions: Arc::new(RwLock::new(HashSet::new())),
             user_context: Arc::new(RwLock::new(None)), // Anonymous/stdio mode
             auth_extractor: None,
+            session: Arc::new(RwLock::new(None)),
         }
     }
 
@@ -72,6 +84,7 @@ impl McpServer {
             subscriptions: Arc::new(RwLock::new(HashSet::new())),
             user_context: Arc::new(RwLock::new(user_context)),
             auth_extractor: None,
+            session: Arc::new(RwLock::new(None)),
         }
     }
 
@@ -91,6 +104,7 @@ impl McpServer {
             subscriptions: Arc::new(RwLock::new(HashSet::new())),
             user_context: Arc::new(RwLock::new(None)),
             auth_extractor: Some(auth_extractor),
+            session: Arc::new(RwLock::new(None)),
         }
     }
 
@@ -99,6 +113,21 @@ impl McpServer {
         self.user_context.read().await.clone()
     }
 
+    /// Get this client's session, once it has initialized.
+    pub async fn session(&self) -> Option<Arc<McpServerSession>> {
+        self.session.read().await.clone()
+    }
+
+    /// Create a session for a newly connected client and register it with
+    /// the orchestrator; it is unregistered when dropped.
+    pub fn new_session(&self, user_context: Option<UserContext>) -> Arc<McpServerSession> {
+        McpServerSession::new(
+            self.orchestrator.sessions(),
+            user_context,
+            self.orchestrator.elicitation_coordinator().for_session(),
+        )
+    }
+
     /// Get the orchestrator.
     pub fn orchestrator(&self) -> &Arc<Orchestrator> {
         &self.orchestrator
@@ -109,6 +138,15 @@ impl McpServer {
         &self.tool_registry
     }
 
+    /// Shut down gracefully: refuse new tool calls, cancel elicitations still
+    /// waiting for an answer and wait up to `timeout` for running tool
+    /// executions to finish.
+    ///
+    /// Returns whether every execution finished before the timeout.
+    pub async fn graceful_shutdown(&self, timeout: Duration) -> bool {
+        self.orchestrator.shutdown_handle().shutdown(timeout).await
+    }
+
     /// Send a resource list changed notification to the client.
     pub async fn notify_resource_list_changed(&self) -> Result<(), anyhow::Error> {
         if let Some(peer) = self.peer.read().await.as_ref() {
@@ -119,6 +157,16 @@ impl McpServer {
         Ok(())
     }
 
+    /// Send a tool list changed notification to the client.
+    pub async fn notify_tool_list_changed(&self) -> Result<(), anyhow::Error> {
+        if let Some(peer) = self.peer.read().await.as_ref() {
+            peer.notify_tool_list_changed()
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to send notification: {:?}", e))?;
+        }
+        Ok(())
+    }
+
     /// Send a prompt list changed notification to the client.
     pub async fn notify_prompt_list_changed(&self) -> Result<(), anyhow::Error> {
         if let Some(peer) = self.peer.read().await.as_ref() {
@@ -211,13 +259,15 @@ impl ServerHandler for McpServer {
         request: InitializeRequestParams,
         context: RequestContext<RoleServer>,
     ) -> impl Future<Output = Result<InitializeResult, McpError>> + Send + '_ {
-        // Update client capabilities in elicitation coordinator
+        // Capabilities and peer of this client, for its session
         let capabilities = request.capabilities.clone();
-        let coordinator = self.orchestrator.elicitation_coordinator().clone();
         let peer_storage = self.peer.clone();
         let peer = context.peer.clone();
+        let context_peer = context.peer.clone();
         let user_context_storage = self.user_context.clone();
         let auth_extractor = self.auth_extractor.clone();
+        let server = self.clone();
+        let mut tool_changes = self.tool_registry.subscribe_changes();
 
         // Try to extract HTTP request parts from rmcp extensions for auth
         // The rmcp library stores http::request::Parts in extensions when using HTTP transport
@@ -227,17 +277,22 @@ impl ServerHandler for McpServer {
             // Store the peer for sending notifications later
             *peer_storage.write().await = Some(peer.clone());
 
-            // Store client capabilities for elicitation
-            coordinator.set_client_capabilities(&capabilities).await;
-
-            // Store peer in coordinator for sending elicitation requests
-            coordinator.set_peer(peer).await;
+            // Tell this client whenever a tool handler is unregistered, until
+            // the session goes away
+            let tools_peer = peer.clone();
+            tokio::spawn(async move {
+                while let Ok(()) | Err(RecvError::Lagged(_)) = tool_changes.recv().await {
+                    if tools_peer.notify_tool_list_changed().await.is_err() {
+                        break;
+                    }
+                }
+            });
 
             // Extract user context from HTTP headers if auth extractor is configured
             if let Some(extractor) = auth_extractor {
                 // Try to get HTTP request parts from extensions
                 // rmcp stores http::request::Parts in extensions for HTTP transport
-                let (authorization, api_key, ip_address, user_agent) =
+                let (authorization, api_key, ip_address, user_agent, country_code) =
                     if let Some(parts) = extensions.get::<HttpParts>() {
                         let auth = parts
                             .headers
@@ -249,20 +304,23 @@ impl ServerHandler for McpServer {
                             .get("X-API-Key")
                             .and_then(|v| v.to_str().ok())
                             .map(|s| s.to_string());
-                        let ip = parts
+                        let ip = extractor
+                            .rate_limiter()
+                            .client_ip(&parts.headers, &parts.extensions);
+                        let ua = parts
                             .headers
-                            .get("X-Forwarded-For")
-                            .or_else(|| parts.headers.get("X-Real-IP"))
+                            .get(http::header::USER_AGENT)
                             .and_then(|v| v.to_str().ok())
                             .map(|s| s.to_string());
-                        let ua = parts
+                        // Set by the load balancer or CDN in front of the server
+                        let country = parts
                             .headers
-                            .get(http::header::USER_AGENT)
+                            .get("X-Geo-Country")
                             .and_then(|v| v.to_str().ok())
                             .map(|s| s.to_string());
-                        (auth, api_key, ip, ua)
+                        (auth, api_key, ip, ua, country)
                     } else {
-                        (None, None, None, None)
+                        (None, None, None, None, None)
                     };
 
                 match extractor
@@ -275,6 +333,7 @@ impl ServerHandler for McpServer {
                     .await
                 {
                     Ok(ctx) => {
+                        let ctx = ctx.with_country_code(country_code);
                         tracing::info!(
                             user_id = %ctx.user_id_string(),
                             provider = %ctx.provider().as_str(),
@@ -344,13 +403,35 @@ impl ServerHandler for McpServer {
                             None,
                         ));
                     }
+                    Err(e @ AuthError::RateLimited { .. }) => {
+                        tracing::warn!("MCP session rejected: {}", e);
+                        return Err(McpError::new(ErrorCode(-32001), e.to_string(), None));
+                    }
                 }
             }
 
+            // Give this client its own session so its elicitations and state
+            // don't mix with other connected clients
+            let session = server.new_session(user_context_storage.read().await.clone());
+            session
+                .elicitation
+                .set_client_capabilities(&capabilities)
+                .await;
+            session.elicitation.set_peer(context_peer.clone()).await;
+            // Relay sampling requests from this client's tool calls to it
+            session
+                .sampling
+                .set_client_capabilities(&capabilities)
+                .await;
+            session.sampling.set_peer(context_peer).await;
+            tracing::debug!(session_id = %session.session_id, "MCP session started");
+            *server.session.write().await = Some(session);
+
             Ok(InitializeResult {
                 protocol_version: ProtocolVersion::V_2025_06_18,
                 capabilities: ServerCapabilities::builder()
                     .enable_tools()
+                    .enable_tool_list_changed()
                     .enable_prompts()
                     .enable_prompts_list_changed()
                     .enable_resources()
@@ -392,26 +473,92 @@ impl ServerHandler for McpServer {
         let args = request.arguments.unwrap_or_default();
         let registry = self.tool_registry.clone();
         let user_context_storage = self.user_context.clone();
+        let auth_extractor = self.auth_extractor.clone();
+        let session_storage = self.session.clone();
+        let shutdown = self.orchestrator.shutdown_handle();
+        let correlation_id = new_correlation_id();
+        let span = tracing::info_span!(
+            "mcp_call_tool",
+            tool = %tool_name,
+            correlation_id = %correlation_id,
+        );
 
-        async move {
-            // Read user context from the lock (set during initialize())
-            let user_context = user_context_storage.read().await.clone();
+        let call = async move {
+            if shutdown.is_shutting_down() {
+                return Ok(CallToolResult::error(vec![Content::text(
+                    "Server is shutting down",
+                )]));
+            }
+
+            // Credentials in `_meta.auth` scope this call to their user; otherwise
+            // use the session user from initialize()
+            let call_user = match &auth_extractor {
+                Some(extractor) => {
+                    let session = user_context_storage.read().await.clone();
+                    let (ip_address, user_agent) = session
+                        .as_ref()
+                        .map(|ctx| {
+                            (
+                                ctx.ip_address().map(str::to_string),
+                                ctx.user_agent().map(str::to_string),
+                            )
+                        })
+                        .unwrap_or_default();
+                    extractor
+                        .extract_from_mcp_metadata(&context.meta, ip_address, user_agent)
+                        .await
+                        .map_err(|e| {
+                            tracing::warn!(tool = %tool_name, "Tool call rejected: {}", e);
+                            auth_error_to_mcp(e)
+                        })?
+                }
+                None => None,
+            };
+            let user_context = match call_user {
+                Some(ctx) => Some(ctx),
+                None => user_context_storage.read().await.clone(),
+            };
+            let session = session_storage.read().await.clone();
+            let sampling = session.as_ref().map(|session| session.sampling.clone());
             let ctx = crate::tools::ToolContext {
                 request_context: context,
                 user_context,
+                session,
             };
 
-            match registry.call_tool(&tool_name, args, &ctx).await {
+            // The handler may have been unregistered since the client listed tools
+            let Some(handler) = registry.get(&tool_name) else {
+                return Ok(CallToolResult::error(vec![Content::text(format!(
+                    "Unknown tool: {}",
+                    tool_name
+                ))]));
+            };
+
+            // Sampling requests made by services during this call go back to
+            // this client
+            let result = match sampling {
+                Some(sampling) => {
+                    with_session_sampling(sampling, handler.execute(args, &ctx)).await
+                }
+                None => handler.execute(args, &ctx).await,
+            };
+            match result {
                 Ok(result) => Ok(result),
-                Err(e) => {
+                Err(e) => match e.downcast_ref::<MissingRoleError>() {
+                    Some(missing) => Err(McpError::new(
+                        ErrorCode(UNAUTHORIZED_ERROR_CODE),
+                        missing.to_string(),
+                        None,
+                    )),
                     // Convert anyhow error to McpError
-                    Err(McpError::internal_error(
+                    None => Err(McpError::internal_error(
                         format!("Tool execution failed: {}", e),
                         None,
-                    ))
-                }
+                    )),
+                },
             }
-        }
+        };
+        with_correlation_id(correlation_id, call).instrument(span)
     }
 
     // Default implementations for unsupported features
@@ -476,6 +623,13 @@ impl ServerHandler for McpServer {
                         None,
                     ))
                 }
+                Err(err @ PromptError::ServiceUnavailable { retry_after }) => {
+                    // -32603: Internal error, with a retry hint for the client
+                    Err(McpError::internal_error(
+                        err.to_string(),
+                        Some(serde_json::json!({ "retryAfterSecs": retry_after.as_secs() })),
+                    ))
+                }
             }
         }
     }
@@ -489,9 +643,17 @@ impl ServerHandler for McpServer {
         let cursor = request
             .as_ref()
             .and_then(|r| r.cursor.as_ref().map(|c| c.to_string()));
+        let filter = request
+            .as_ref()
+            .and_then(|r| r.meta.as_ref())
+            .and_then(|meta| meta.0.get("filter"))
+            .and_then(|f| serde_json::from_value::<PromptFilter>(f.clone()).ok());
 
         async move {
-            match forwarder.list_prompts(cursor.as_deref()).await {
+            match forwarder
+                .list_prompts(cursor.as_deref(), filter.as_ref())
+                .await
+            {
                 Ok(result) => Ok(result),
                 Err(e) => Err(McpError::internal_error(
                     format!("Failed to list prompts: {}", e),
@@ -569,6 +731,10 @@ impl ServerHandler for McpServer {
                         None,
                     ))
                 }
+                Err(e @ ResourceError::WriteNotSupported(_)) => {
+                    // Reads never check write support
+                    Err(McpError::internal_error(e.to_string(), None))
+                }
                 Err(ResourceError::Internal(msg)) => {
                     // -32603: Internal error
                     Err(McpError::internal_error(
@@ -645,6 +811,7 @@ impl ServerHandler for McpServer {
             protocol_version: ProtocolVersion::V_2025_06_18,
             capabilities: ServerCapabilities::builder()
                 .enable_tools()
+                .enable_tool_list_changed()
                 .enable_prompts()
                 .enable_prompts_list_changed()
                 .enable_resources()
@@ -661,6 +828,17 @@ impl ServerHandler for McpServer {
     }
 }
 
+/// Map an authentication failure to an MCP error: `-32001` for rejected
+/// credentials, internal error when the check itself failed.
+fn auth_error_to_mcp(err: AuthError) -> McpError {
+    match err {
+        AuthError::DatabaseError(_) | AuthError::JwksError(_) => {
+            McpError::internal_error(format!("Authentication failed: {}", err), None)
+        }
+        _ => McpError::new(ErrorCode(-32001), err.to_string(), None),
+    }
+}
+
 /// Start the orchestrator as an MCP Streamable HTTP server.
 ///
 /// This exposes the MCP endpoint at `/mcp` on the given bind address,
@@ -679,9 +857,13 @@ pub async fn start_mcp_http(
     let orchestrator = server.orchestrator().clone();
     let tool_registry = server.tool_registry().clone();
     let db = orchestrator.db().clone();
+    let security = orchestrator.security_headers()?;
 
     // Create auth extractor if config provided
     let auth_extractor = auth_config.map(|config| Arc::new(AuthExtractor::new(config, db)));
+    if let Some(extractor) = &auth_extractor {
+        extractor.register_metrics(orchestrator.metrics());
+    }
 
     let service = StreamableHttpService::new(
         {
@@ -706,7 +888,34 @@ pub async fn start_mcp_http(
         Default::default(),
     );
 
-    let router = Router::new().nest_service("/mcp", service);
+    let mut router = Router::new()
+        .route("/health", axum::routing::get(crate::api::health_check))
+        .route(
+            "/metrics",
+            axum::routing::get({
+                let orchestrator = orchestrator.clone();
+                move || async move {
+                    (
+                        [(
+                            axum::http::header::CONTENT_TYPE,
+                            crate::metrics::PROMETHEUS_CONTENT_TYPE,
+                        )],
+                        orchestrator.metrics().render(),
+                    )
+                }
+            }),
+        )
+        .layer(axum::Extension(tool_registry))
+        .nest_service("/mcp", service);
+    // IPs with too many failed authentications get `429` before reaching MCP
+    if let Some(extractor) = &auth_extractor {
+        router = router.layer(axum::middleware::from_fn_with_state(
+            extractor.rate_limiter().clone(),
+            crate::auth::rate_limit::reject_rate_limited_ips,
+        ));
+    }
+    // Outermost, so rate-limited responses carry the headers too
+    let router = router.layer(security);
     let listener = tokio::net::TcpListener::bind(bind).await?;
 
     if auth_extractor.is_some() {
@@ -721,7 +930,15 @@ pub async fn start_mcp_http(
         );
     }
 
-    axum::serve(listener, router).await?;
+    // Stop accepting connections once a graceful shutdown starts
+    let shutdown = orchestrator.shutdown_handle();
+    // The auth rate limiter keys failures on the peer address
+    axum::serve(
+        listener,
+        router.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+    )
+    .with_graceful_shutdown(async move { shutdown.stopped().await })
+    .await?;
 
     Ok(())
 }
@@ -0,0 +1,210 @@
+//! Per-client MCP sessions.
+//!
+//! Every client that initializes a connection gets its own [`McpServerSession`]
+//! holding the state that must not leak between clients: the authenticated
+//! user, an elicitation coordinator and a sampling forwarder bound to that
+//! client's peer, and a key-value [`SessionStore`] for tool handlers. The [`SessionRegistry`] tracks
+//! the sessions that are still connected.
+
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock, Weak};
+use uuid::Uuid;
+
+use crate::auth::UserContext;
+use crate::elicitation::ElicitationCoordinator;
+use crate::sampling::SamplingForwarder;
+
+/// Key-value state scoped to one client session, dropped with the session.
+#[derive(Debug, Default)]
+pub struct SessionStore {
+    values: RwLock<HashMap<String, Value>>,
+}
+
+impl SessionStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get a copy of the value stored under `key`.
+    pub fn get(&self, key: &str) -> Option<Value> {
+        self.values
+            .read()
+            .expect("session store lock poisoned")
+            .get(key)
+            .cloned()
+    }
+
+    /// Store `value` under `key`, returning the previous value.
+    pub fn insert(&self, key: impl Into<String>, value: Value) -> Option<Value> {
+        self.values
+            .write()
+            .expect("session store lock poisoned")
+            .insert(key.into(), value)
+    }
+
+    /// Remove the value stored under `key`.
+    pub fn remove(&self, key: &str) -> Option<Value> {
+        self.values
+            .write()
+            .expect("session store lock poisoned")
+            .remove(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.values
+            .read()
+            .expect("session store lock poisoned")
+            .len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// State of one connected MCP client.
+///
+/// Created by `McpServer::new_session` when the client initializes, and
+/// removed from the [`SessionRegistry`] when the last reference is dropped,
+/// i.e. when the client disconnects.
+pub struct McpServerSession {
+    pub session_id: Uuid,
+    /// The authenticated user (None for anonymous/stdio mode).
+    pub user_context: Option<UserContext>,
+    /// Sends elicitations to this client only.
+    pub elicitation: ElicitationCoordinator,
+    /// Relays sampling requests from this client's tool calls to this client only.
+    pub sampling: Arc<SamplingForwarder>,
+    pub session_store: Arc<SessionStore>,
+    registry: Weak<SessionRegistry>,
+}
+
+impl McpServerSession {
+    /// Create a session and register it in `registry`.
+    pub fn new(
+        registry: &Arc<SessionRegistry>,
+        user_context: Option<UserContext>,
+        elicitation: ElicitationCoordinator,
+    ) -> Arc<Self> {
+        let session = Arc::new(Self {
+            session_id: Uuid::new_v4(),
+            user_context,
+            elicitation,
+            sampling: Arc::new(SamplingForwarder::new()),
+            session_store: Arc::new(SessionStore::new()),
+            registry: Arc::downgrade(registry),
+        });
+        registry.register(&session);
+        session
+    }
+}
+
+impl Drop for McpServerSession {
+    fn drop(&mut self) {
+        if let Some(registry) = self.registry.upgrade() {
+            registry.remove(&self.session_id);
+        }
+        tracing::debug!(session_id = %self.session_id, "MCP session closed");
+    }
+}
+
+/// Sessions of the clients currently connected.
+///
+/// Holds weak references so that a disconnected client's session is freed
+/// even if nothing removes it explicitly.
+#[derive(Default)]
+pub struct SessionRegistry {
+    sessions: RwLock<HashMap<Uuid, Weak<McpServerSession>>>,
+}
+
+impl SessionRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn register(&self, session: &Arc<McpServerSession>) {
+        self.sessions
+            .write()
+            .expect("session registry lock poisoned")
+            .insert(session.session_id, Arc::downgrade(session));
+    }
+
+    fn remove(&self, session_id: &Uuid) {
+        self.sessions
+            .write()
+            .expect("session registry lock poisoned")
+            .remove(session_id);
+    }
+
+    /// Get a connected session by id.
+    pub fn get(&self, session_id: &Uuid) -> Option<Arc<McpServerSession>> {
+        self.sessions
+            .read()
+            .expect("session registry lock poisoned")
+            .get(session_id)
+            .and_then(Weak::upgrade)
+    }
+
+    /// All connected sessions.
+    pub fn sessions(&self) -> Vec<Arc<McpServerSession>> {
+        self.sessions
+            .read()
+            .expect("session registry lock poisoned")
+            .values()
+            .filter_map(Weak::upgrade)
+            .collect()
+    }
+
+    /// Number of connected sessions.
+    pub fn len(&self) -> usize {
+        self.sessions
+            .read()
+            .expect("session registry lock poisoned")
+            .len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn coordinator() -> ElicitationCoordinator {
+        let db_config = crate::db::DatabaseConfig {
+            url: "memory".to_string(),
+            ..Default::default()
+        };
+        let db = crate::db::create_connection(db_config).await.unwrap();
+        ElicitationCoordinator::new(db).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_sessions_are_isolated_and_removed_on_drop() {
+        let registry = Arc::new(SessionRegistry::new());
+        let coordinator = coordinator().await;
+
+        let first = McpServerSession::new(&registry, None, coordinator.for_session());
+        let second = McpServerSession::new(&registry, None, coordinator.for_session());
+        assert_ne!(first.session_id, second.session_id);
+        assert_eq!(registry.len(), 2);
+
+        first
+            .session_store
+            .insert("cursor", serde_json::json!("abc"));
+        assert_eq!(
+            first.session_store.get("cursor"),
+            Some(serde_json::json!("abc"))
+        );
+        assert!(second.session_store.is_empty());
+
+        let second_id = second.session_id;
+        drop(second);
+        assert_eq!(registry.len(), 1);
+        assert!(registry.get(&second_id).is_none());
+        assert!(registry.get(&first.session_id).is_some());
+    }
+}
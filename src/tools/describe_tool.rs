@@ -0,0 +1,686 @@
+//! Handler for the `unicity.describe_tool` tool.
+//!
+//! Returns Markdown documentation for a discovered tool: description, a
+//! parameter table built from its input schema, the output schema, an example
+//! `unicity.execute_tool` call and usage statistics. Everything is read from
+//! the database so the underlying service is never contacted.
+
+use crate::db::schema::ServiceRecord;
+use crate::db::{SchemaDiff, ToolRecord};
+use crate::orchestrator::Orchestrator;
+use crate::orchestrator::user_filter::UserToolFilter;
+use crate::tools::{ToolContext, ToolHandler};
+use rmcp::model::{CallToolResult, Content, JsonObject};
+use serde_json::{Value, json};
+use std::fmt::Write as _;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use surrealdb::RecordId;
+
+/// Handler for the `unicity.describe_tool` tool.
+pub struct DescribeToolHandler {
+    orchestrator: Arc<Orchestrator>,
+}
+
+impl DescribeToolHandler {
+    /// Create a new describe tool handler.
+    pub fn new(orchestrator: Arc<Orchestrator>) -> Self {
+        Self { orchestrator }
+    }
+
+    /// Build the input schema for this tool.
+    fn input_schema(&self) -> JsonObject {
+        let mut schema = JsonObject::new();
+        schema.insert("type".to_string(), json!("object"));
+
+        let mut properties = serde_json::Map::new();
+        properties.insert(
+            "tool_name".to_string(),
+            json!({
+                "type": "string",
+                "description": "Name of the tool to describe (as returned by unicity.select_tool)."
+            }),
+        );
+        properties.insert(
+            "tool_id".to_string(),
+            json!({
+                "type": "string",
+                "description": "Orchestrator toolId (e.g. 'tool:abc123'). Takes precedence over tool_name."
+            }),
+        );
+        properties.insert(
+            "plan_id".to_string(),
+            json!({
+                "type": "string",
+                "description": "Optional planId from unicity.plan_tools. The plan's Mermaid diagram is appended to the documentation."
+            }),
+        );
+        properties.insert(
+            "show_schema_diff".to_string(),
+            json!({
+                "type": "boolean",
+                "description": "Append the changes between two versions of the tool's input schema (default: the latest change)."
+            }),
+        );
+        properties.insert(
+            "from_version".to_string(),
+            json!({
+                "type": "integer",
+                "minimum": 1,
+                "description": "Older schema version to diff with show_schema_diff."
+            }),
+        );
+        properties.insert(
+            "to_version".to_string(),
+            json!({
+                "type": "integer",
+                "minimum": 1,
+                "description": "Newer schema version to diff with show_schema_diff."
+            }),
+        );
+
+        schema.insert("properties".to_string(), json!(properties));
+        schema.insert("required".to_string(), json!([]));
+        schema
+    }
+
+    /// Load the tools matching the requested id or name.
+    async fn lookup_tools(
+        orchestrator: &Orchestrator,
+        tool_id: Option<String>,
+        tool_name: Option<String>,
+    ) -> Result<Vec<ToolRecord>, String> {
+        let db_res = match (tool_id, tool_name) {
+            (Some(id), _) => {
+                orchestrator
+                    .db()
+                    .query("SELECT * FROM type::thing($id)")
+                    .bind(("id", id))
+                    .await
+            }
+            (None, Some(name)) => {
+                orchestrator
+                    .db()
+                    .query("SELECT * FROM tool WHERE name = $name")
+                    .bind(("name", name))
+                    .await
+            }
+            (None, None) => return Ok(Vec::new()),
+        };
+
+        match db_res {
+            Ok(mut res) => res
+                .take(0)
+                .map_err(|e| format!("Failed to decode ToolRecord: {}", e)),
+            Err(e) => Err(format!("Database error while loading tool: {}", e)),
+        }
+    }
+
+    /// Load the display name of a service, if it is known.
+    async fn service_name(orchestrator: &Orchestrator, service_id: &RecordId) -> Option<String> {
+        let mut res = orchestrator
+            .db()
+            .query("SELECT * FROM service WHERE id = $id")
+            .bind(("id", service_id.clone()))
+            .await
+            .ok()?;
+        let service: Option<ServiceRecord> = res.take(0).ok()?;
+        service.and_then(|s| s.name)
+    }
+}
+
+impl ToolHandler for DescribeToolHandler {
+    fn name(&self) -> &str {
+        "unicity.describe_tool"
+    }
+
+    fn title(&self) -> Option<&str> {
+        Some("Unicity Orchestrator: Describe Tool")
+    }
+
+    fn description(&self) -> &str {
+        "Return Markdown documentation for a discovered tool: its description, a \
+         parameter table, the output schema, an example unicity.execute_tool call \
+         and usage statistics. Call this after unicity.select_tool to learn how to \
+         build the `args` for unicity.execute_tool. If several services expose a \
+         tool with the same name, all candidates are listed so you can pick one \
+         by `tool_id`. Set `show_schema_diff` to see how its input schema changed \
+         between versions."
+    }
+
+    fn input_schema(&self) -> JsonObject {
+        self.input_schema()
+    }
+
+    fn execute(
+        &self,
+        args: JsonObject,
+        ctx: &ToolContext,
+    ) -> Pin<Box<dyn Future<Output = anyhow::Result<CallToolResult>> + Send + '_>> {
+        let orchestrator = self.orchestrator.clone();
+        let user_context = ctx.user_context.clone();
+
+        Box::pin(async move {
+            let tool_id = args
+                .get("tool_id")
+                .and_then(|v| v.as_str())
+                .map(str::to_string);
+            let tool_name = args
+                .get("tool_name")
+                .and_then(|v| v.as_str())
+                .map(str::to_string);
+            let plan = args
+                .get("plan_id")
+                .and_then(|v| v.as_str())
+                .and_then(|id| orchestrator.find_plan(id));
+            let show_schema_diff = args
+                .get("show_schema_diff")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            let version = |key: &str| {
+                args.get(key)
+                    .and_then(|v| v.as_u64())
+                    .and_then(|v| u32::try_from(v).ok())
+            };
+            let (from_version, to_version) = (version("from_version"), version("to_version"));
+
+            let Some(requested) = tool_id.clone().or_else(|| tool_name.clone()) else {
+                return Ok(error_result(json!({
+                    "status": "error",
+                    "reason": "unicity.describe_tool requires a `tool_name` or `tool_id` string argument"
+                })));
+            };
+
+            let tools = match Self::lookup_tools(&orchestrator, tool_id, tool_name).await {
+                Ok(tools) => tools,
+                Err(reason) => {
+                    return Ok(error_result(json!({
+                        "status": "error",
+                        "reason": reason,
+                    })));
+                }
+            };
+
+            // Hide tools from services the user has blocked
+            let filter = match &user_context {
+                Some(ctx) => UserToolFilter::from_user_context(orchestrator.db(), ctx)
+                    .await
+                    .unwrap_or_else(|_| UserToolFilter::allow_all()),
+                None => UserToolFilter::allow_all(),
+            };
+            let tools = filter.filter_tools(tools);
+
+            let mut described = Vec::with_capacity(tools.len());
+            for tool in tools {
+                let service_name = Self::service_name(&orchestrator, &tool.service_id).await;
+                described.push((tool, service_name));
+            }
+
+            let mut markdown = match described.as_slice() {
+                [] => {
+                    return Ok(error_result(json!({
+                        "status": "not_found",
+                        "reason": format!("No tool found for `{}`", requested),
+                    })));
+                }
+                [(tool, service_name)] => {
+                    let mut md = render_tool_markdown(
+                        tool,
+                        service_name.as_deref(),
+                        orchestrator.last_query_confidence(&tool.id),
+                    );
+                    if show_schema_diff {
+                        md.push_str("\n## Schema changes\n\n");
+                        match orchestrator
+                            .tool_schema_diff(&tool.id, from_version, to_version)
+                            .await
+                        {
+                            Ok(Some((from, to, diff))) => {
+                                md.push_str(&render_schema_diff(from, to, &diff));
+                            }
+                            Ok(None) => md.push_str("_Schema version not found._\n"),
+                            Err(e) => {
+                                let _ = writeln!(md, "_Failed to load schema history: {}_", e);
+                            }
+                        }
+                    }
+                    md
+                }
+                candidates => render_candidates_markdown(&requested, candidates),
+            };
+            if let Some(plan) = plan {
+                let _ = write!(
+                    markdown,
+                    "\n## Plan `{}`\n\n```mermaid\n{}```\n",
+                    plan.id, plan.diagram
+                );
+            }
+
+            Ok(CallToolResult {
+                content: vec![Content::text(markdown)],
+                structured_content: None,
+                is_error: Some(false),
+                meta: None,
+            })
+        })
+    }
+}
+
+fn error_result(payload: Value) -> CallToolResult {
+    let text = serde_json::to_string(&payload)
+        .unwrap_or_else(|_| "internal serialization error".to_string());
+    CallToolResult {
+        content: vec![Content::text(text)],
+        structured_content: None,
+        is_error: Some(true),
+        meta: None,
+    }
+}
+
+/// Render the full documentation for a single tool.
+fn render_tool_markdown(
+    tool: &ToolRecord,
+    service_name: Option<&str>,
+    last_confidence: Option<f32>,
+) -> String {
+    let mut md = String::new();
+    let service = service_name.unwrap_or("unknown service");
+
+    let _ = writeln!(md, "# `{}`\n", tool.name);
+    let _ = writeln!(md, "- **Tool ID:** `{}`", tool.id);
+    let _ = writeln!(md, "- **Service:** {} (`{}`)", service, tool.service_id);
+    if tool.is_blocked() {
+        let _ = writeln!(md, "- **Status:** blocked");
+    }
+    md.push('\n');
+
+    match tool.description.as_deref() {
+        Some(description) if !description.trim().is_empty() => {
+            let _ = writeln!(md, "{}\n", description.trim());
+        }
+        _ => md.push_str("_No description provided._\n\n"),
+    }
+
+    md.push_str("## Parameters\n\n");
+    md.push_str(&render_parameter_table(&tool.input_schema));
+
+    md.push_str("\n## Output\n\n");
+    match &tool.output_schema {
+        Some(schema) => {
+            let pretty = serde_json::to_string_pretty(schema).unwrap_or_default();
+            let _ = writeln!(md, "```json\n{}\n```", pretty);
+        }
+        None => md.push_str("_No output schema declared._\n"),
+    }
+
+    if !tool.input_examples.is_empty() {
+        md.push_str("\n## Examples\n\n");
+        md.push_str("Argument sets advertised by the service:\n\n");
+        for example in &tool.input_examples {
+            let pretty = serde_json::to_string_pretty(example).unwrap_or_default();
+            let _ = writeln!(md, "```json\n{}\n```", pretty);
+        }
+    }
+
+    md.push_str("\n## Example call\n\n");
+    let example = json!({
+        "toolId": tool.id.to_string(),
+        "args": example_arguments(&tool.input_schema),
+    });
+    let pretty = serde_json::to_string_pretty(&example).unwrap_or_default();
+    let _ = writeln!(md, "```json\n{}\n```", pretty);
+
+    md.push_str("\n## Usage\n\n");
+    let _ = writeln!(md, "- **Executions:** {}", tool.usage_count);
+    match last_confidence {
+        Some(confidence) => {
+            let _ = writeln!(md, "- **Last query confidence:** {:.2}", confidence);
+        }
+        None => md.push_str("- **Last query confidence:** not selected by a recent query\n"),
+    }
+
+    md
+}
+
+/// Render the changes between schema versions `from` and `to`.
+fn render_schema_diff(from: u32, to: u32, diff: &SchemaDiff) -> String {
+    let mut md = String::new();
+    if from == to || diff.is_empty() {
+        let _ = writeln!(md, "_No changes between versions {} and {}._", from, to);
+        return md;
+    }
+
+    let _ = writeln!(
+        md,
+        "Version {} → {}{}\n",
+        from,
+        to,
+        if diff.breaking { " (**breaking**)" } else { "" }
+    );
+    let required = |required: bool| if required { ", required" } else { "" };
+    for field in &diff.added {
+        let _ = writeln!(
+            md,
+            "- Added `{}` ({}{})",
+            field.path,
+            field.schema_type,
+            required(field.required)
+        );
+    }
+    for field in &diff.removed {
+        let _ = writeln!(
+            md,
+            "- Removed `{}` ({}{})",
+            field.path,
+            field.schema_type,
+            required(field.required)
+        );
+    }
+    for field in &diff.modified {
+        let _ = writeln!(
+            md,
+            "- Changed `{}`: {}{} → {}{}{}",
+            field.path,
+            field.old_type,
+            required(field.old_required),
+            field.new_type,
+            required(field.new_required),
+            if field.narrowed { " (narrowed)" } else { "" }
+        );
+    }
+    md
+}
+
+/// Render the list of tools sharing the requested name.
+fn render_candidates_markdown(
+    requested: &str,
+    candidates: &[(ToolRecord, Option<String>)],
+) -> String {
+    let mut md = String::new();
+    let _ = writeln!(md, "# Multiple tools named `{}`\n", requested);
+    md.push_str("Call `unicity.describe_tool` again with one of these `tool_id` values:\n\n");
+
+    for (tool, service_name) in candidates {
+        let _ = write!(
+            md,
+            "- `{}` from {} (`{}`)",
+            tool.id,
+            service_name.as_deref().unwrap_or("unknown service"),
+            tool.service_id
+        );
+        if let Some(description) = tool.description.as_deref() {
+            let _ = write!(md, ": {}", single_line(description));
+        }
+        md.push('\n');
+    }
+
+    md
+}
+
+/// Render the properties of an object schema as a Markdown table.
+fn render_parameter_table(schema: &JsonObject) -> String {
+    let properties = match schema.get("properties").and_then(|p| p.as_object()) {
+        Some(properties) if !properties.is_empty() => properties,
+        _ => return "_This tool takes no parameters._\n".to_string(),
+    };
+    let required = required_fields(schema);
+
+    let mut md = String::from("| Name | Type | Required | Description |\n");
+    md.push_str("|------|------|----------|-------------|\n");
+    for (name, prop) in properties {
+        let description = prop
+            .get("description")
+            .and_then(|d| d.as_str())
+            .map(single_line)
+            .unwrap_or_default();
+        let _ = writeln!(
+            md,
+            "| `{}` | {} | {} | {} |",
+            name,
+            type_label(prop),
+            if required.contains(&name.as_str()) {
+                "Yes"
+            } else {
+                "No"
+            },
+            description
+        );
+    }
+    md
+}
+
+/// Build example arguments containing every required field with a placeholder value.
+fn example_arguments(schema: &JsonObject) -> Value {
+    let mut args = serde_json::Map::new();
+    let properties = schema.get("properties").and_then(|p| p.as_object());
+
+    for name in required_fields(schema) {
+        let placeholder = properties
+            .and_then(|p| p.get(name))
+            .map(|prop| placeholder_value(name, prop))
+            .unwrap_or(Value::Null);
+        args.insert(name.to_string(), placeholder);
+    }
+
+    Value::Object(args)
+}
+
+fn required_fields(schema: &JsonObject) -> Vec<&str> {
+    schema
+        .get("required")
+        .and_then(|r| r.as_array())
+        .map(|r| r.iter().filter_map(|v| v.as_str()).collect())
+        .unwrap_or_default()
+}
+
+fn placeholder_value(name: &str, prop: &Value) -> Value {
+    if let Some(default) = prop.get("default") {
+        return default.clone();
+    }
+    if let Some(first) = prop
+        .get("enum")
+        .and_then(|e| e.as_array())
+        .and_then(|e| e.first())
+    {
+        return first.clone();
+    }
+
+    match primary_type(prop) {
+        Some("string") => json!(format!("<{}>", name)),
+        Some("integer") => json!(0),
+        Some("number") => json!(0.0),
+        Some("boolean") => json!(false),
+        Some("array") => json!([]),
+        Some("object") => json!({}),
+        _ => Value::Null,
+    }
+}
+
+/// First non-null JSON Schema type of a property.
+fn primary_type(prop: &Value) -> Option<&str> {
+    match prop.get("type") {
+        Some(Value::String(ty)) => Some(ty.as_str()),
+        Some(Value::Array(types)) => types
+            .iter()
+            .filter_map(|t| t.as_str())
+            .find(|t| *t != "null"),
+        _ => None,
+    }
+}
+
+fn type_label(prop: &Value) -> String {
+    if let Some(values) = prop.get("enum").and_then(|e| e.as_array()) {
+        let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+        return format!("enum: {}", values.join(" \\| "));
+    }
+
+    match prop.get("type") {
+        Some(Value::String(ty)) if ty == "array" => {
+            match prop.get("items").and_then(primary_type) {
+                Some(item) => format!("array<{}>", item),
+                None => "array".to_string(),
+            }
+        }
+        Some(Value::String(ty)) => ty.clone(),
+        Some(Value::Array(types)) => {
+            let types: Vec<&str> = types.iter().filter_map(|t| t.as_str()).collect();
+            types.join(" \\| ")
+        }
+        _ => "any".to_string(),
+    }
+}
+
+/// Collapse text onto one line so it fits in a table cell or list item.
+fn single_line(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace('|', "\\|")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_tool(name: &str, service: &str, input_schema: Value) -> ToolRecord {
+        ToolRecord {
+            id: RecordId::from_table_key("tool", format!("{}_{}", service, name)),
+            service_id: RecordId::from_table_key("service", service),
+            name: name.to_string(),
+            description: Some("Read a file from disk".to_string()),
+            input_schema: input_schema.as_object().cloned().unwrap_or_default(),
+            output_schema: None,
+            embedding_id: None,
+            input_ty: None,
+            output_ty: None,
+            usage_count: 3,
+            status: None,
+            discovery_source: Default::default(),
+            metadata: Default::default(),
+            input_examples: Vec::new(),
+            changelog: Vec::new(),
+            deprecation_notice: None,
+            descriptions: Default::default(),
+            inject_user_context: Vec::new(),
+            geo_availability: None,
+            required_env_vars: Vec::new(),
+            sla_class: None,
+            cost_per_call: None,
+            cache_ttl_seconds: None,
+            required_role: None,
+            actual_latency_p99_ms: None,
+            actual_error_rate_pct: None,
+            block_reason: None,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    fn file_schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "path": {"type": "string", "description": "Path to\nthe file"},
+                "encoding": {"type": "string", "enum": ["utf8", "base64"]},
+                "limit": {"type": "integer"},
+                "tags": {"type": "array", "items": {"type": "string"}}
+            },
+            "required": ["path", "encoding", "limit"]
+        })
+    }
+
+    #[test]
+    fn test_example_arguments_use_required_fields_only() {
+        let schema = file_schema();
+        let args = example_arguments(schema.as_object().unwrap());
+        assert_eq!(
+            args,
+            json!({"path": "<path>", "encoding": "utf8", "limit": 0})
+        );
+    }
+
+    #[test]
+    fn test_parameter_table_lists_properties() {
+        let schema = file_schema();
+        let table = render_parameter_table(schema.as_object().unwrap());
+        assert!(table.contains("| `path` | string | Yes | Path to the file |"));
+        assert!(table.contains("| `encoding` | enum: \"utf8\" \\| \"base64\" | Yes |"));
+        assert!(table.contains("| `tags` | array<string> | No |"));
+    }
+
+    #[test]
+    fn test_parameter_table_without_properties() {
+        let table = render_parameter_table(&JsonObject::new());
+        assert_eq!(table, "_This tool takes no parameters._\n");
+    }
+
+    #[test]
+    fn test_render_tool_markdown_sections() {
+        let tool = make_tool("read_file", "fs", file_schema());
+        let md = render_tool_markdown(&tool, Some("filesystem"), Some(0.875));
+
+        assert!(md.starts_with("# `read_file`"));
+        assert!(md.contains("filesystem (`service:fs`)"));
+        assert!(md.contains("Read a file from disk"));
+        assert!(md.contains("## Parameters"));
+        assert!(md.contains("_No output schema declared._"));
+        assert!(md.contains("\"toolId\": \"tool:fs_read_file\""));
+        assert!(md.contains("**Executions:** 3"));
+        assert!(md.contains("**Last query confidence:** 0.88"));
+    }
+
+    #[test]
+    fn test_render_tool_markdown_examples() {
+        let mut tool = make_tool("read_file", "fs", file_schema());
+        assert!(!render_tool_markdown(&tool, None, None).contains("## Examples"));
+
+        tool.input_examples = vec![json!({ "path": "/etc/hosts" }).as_object().unwrap().clone()];
+        let md = render_tool_markdown(&tool, None, None);
+        assert!(md.contains("## Examples"));
+        assert!(md.contains("\"path\": \"/etc/hosts\""));
+    }
+
+    #[test]
+    fn test_render_schema_diff() {
+        let old = crate::db::TypedSchema::from_json_schema(file_schema().as_object().unwrap());
+        let new = crate::db::TypedSchema::from_json_schema(
+            json!({
+                "type": "object",
+                "properties": {
+                    "path": {"type": "string"},
+                    "limit": {"type": "string"},
+                    "tags": {"type": "array", "items": {"type": "string"}}
+                },
+                "required": ["path", "limit"]
+            })
+            .as_object()
+            .unwrap(),
+        );
+        let md = render_schema_diff(1, 2, &SchemaDiff::between(&old, &new));
+
+        assert!(md.contains("Version 1 → 2 (**breaking**)"));
+        assert!(md.contains("- Removed `encoding` (string (\"utf8\", \"base64\"), required)"));
+        assert!(md.contains("- Changed `limit`: integer, required → string, required (narrowed)"));
+        assert!(!md.contains("`tags`"));
+
+        let unchanged = render_schema_diff(1, 1, &SchemaDiff::default());
+        assert_eq!(unchanged, "_No changes between versions 1 and 1._\n");
+    }
+
+    #[test]
+    fn test_render_candidates_lists_every_tool() {
+        let candidates = vec![
+            (
+                make_tool("read_file", "fs", json!({})),
+                Some("filesystem".to_string()),
+            ),
+            (make_tool("read_file", "s3", json!({})), None),
+        ];
+        let md = render_candidates_markdown("read_file", &candidates);
+
+        assert!(md.contains("Multiple tools named `read_file`"));
+        assert!(md.contains("`tool:fs_read_file` from filesystem"));
+        assert!(md.contains("`tool:s3_read_file` from unknown service"));
+    }
+}
@@ -2,19 +2,24 @@
 //!
 //! Execute a previously selected underlying MCP tool by toolId with the given arguments.
 
-use crate::auth::UserStore;
+use crate::auth::{MissingRoleError, UserContext, UserStore};
 use crate::db::ToolRecord;
-use crate::elicitation::ElicitationSchema;
+use crate::elicitation::{ElicitationAction, ElicitationCoordinator, ElicitationSchema};
+use crate::executor::{inject_user_context, validate_input_args};
 use crate::knowledge_graph::ToolSelection;
 use crate::orchestrator::Orchestrator;
 use crate::orchestrator::user_filter::UserToolFilter;
-use crate::tools::{ToolContext, ToolHandler};
+use crate::tools::{HealthStatus, ToolContext, ToolHandler, probe_orchestrator};
 use rmcp::model::{CallToolResult, Content, EnumSchema, JsonObject};
 use serde_json::json;
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
 
+/// Start of the `reason` of a result whose tool was called but failed, as
+/// opposed to one refused before calling it.
+pub(crate) const EXECUTION_FAILED_PREFIX: &str = "Tool execution failed: ";
+
 /// Handler for the `unicity.execute_tool` tool.
 pub struct ExecuteToolHandler {
     orchestrator: Arc<Orchestrator>,
@@ -74,6 +79,103 @@ impl ExecuteToolHandler {
     }
 }
 
+/// Result of a call refused because its service is blocked.
+fn blocked_result(service_id: &str, reason: &str) -> CallToolResult {
+    let payload = json!({
+        "status": "blocked",
+        "reason": format!("Blocked: service '{}' is blocked{}", service_id, reason),
+    });
+    let text = serde_json::to_string(&payload)
+        .unwrap_or_else(|_| "internal serialization error".to_string());
+    CallToolResult {
+        content: vec![Content::text(text)],
+        structured_content: None,
+        is_error: Some(true),
+        meta: None,
+    }
+}
+
+/// If the user has blocked `service_id`, ask them through `elicitation`
+/// whether to go ahead with the call to `target` (e.g. "tool 'search'")
+/// once, unblock the service, or keep it blocked.
+///
+/// Returns the result to send back when the call must not go ahead.
+pub(crate) async fn confirm_blocked_service(
+    orchestrator: &Orchestrator,
+    ctx: &UserContext,
+    elicitation: &ElicitationCoordinator,
+    target: &str,
+    service_id: &str,
+) -> Option<CallToolResult> {
+    let filter = UserToolFilter::from_user_context(orchestrator.db(), ctx)
+        .await
+        .unwrap_or_else(|_| UserToolFilter::allow_all());
+    if filter.is_service_allowed(service_id) {
+        return None;
+    }
+
+    // Service is blocked - trigger elicitation to ask user what to do
+    let message = format!(
+        "The {} is from a blocked service '{}'.\n\n\
+         Choose how to proceed:\n\
+         - allow_once: Go ahead just this once\n\
+         - unblock_service: Remove the block and go ahead\n\
+         - keep_blocked: Don't go ahead, keep the service blocked",
+        target, service_id
+    );
+
+    let schema = ElicitationSchema::builder()
+        .required_enum_schema(
+            "action",
+            EnumSchema::builder(vec![
+                "allow_once".to_string(),
+                "unblock_service".to_string(),
+                "keep_blocked".to_string(),
+            ])
+            .build(),
+        )
+        .description("Blocked service action")
+        .build()
+        .expect("Invalid blocked service schema");
+
+    let response = match elicitation.create_elicitation(&message, schema).await {
+        Ok(response) => response,
+        Err(e) => {
+            // Elicitation failed - block execution
+            tracing::warn!("Blocked service elicitation failed: {}", e);
+            return Some(blocked_result(service_id, " (elicitation unavailable)"));
+        }
+    };
+
+    let action = match response.action {
+        ElicitationAction::Accept => response
+            .content
+            .as_ref()
+            .and_then(|c| c.get("action"))
+            .and_then(|v| v.as_str())
+            .unwrap_or("keep_blocked"),
+        ElicitationAction::Decline | ElicitationAction::Cancel => "keep_blocked",
+    };
+
+    match action {
+        "allow_once" => None,
+        "unblock_service" => {
+            let user_store = UserStore::new(orchestrator.db().clone());
+            if let Err(e) = user_store.unblock_service(ctx.user_id(), service_id).await {
+                tracing::warn!("Failed to unblock service: {}", e);
+            } else {
+                tracing::info!(
+                    user_id = %ctx.user_id_string(),
+                    service_id = %service_id,
+                    "User unblocked service via elicitation"
+                );
+            }
+            None
+        }
+        _ => Some(blocked_result(service_id, "")),
+    }
+}
+
 impl ToolHandler for ExecuteToolHandler {
     fn name(&self) -> &str {
         "unicity.execute_tool"
@@ -99,6 +201,7 @@ impl ToolHandler for ExecuteToolHandler {
         let orchestrator = self.orchestrator.clone();
         // Clone user context for use in async block
         let user_context = ctx.user_context.clone();
+        let session = ctx.session.clone();
 
         Box::pin(async move {
             let tool_id_str = match args.get("toolId").and_then(|v| v.as_str()) {
@@ -121,7 +224,7 @@ impl ToolHandler for ExecuteToolHandler {
 
             let tool_id_str_clone = tool_id_str.clone();
 
-            let tool_args: JsonObject = args
+            let mut tool_args: JsonObject = args
                 .get("args")
                 .and_then(|v| v.as_object())
                 .cloned()
@@ -160,129 +263,46 @@ impl ToolHandler for ExecuteToolHandler {
                 }
             };
 
+            inject_user_context(&tool, &mut tool_args, user_context.as_ref());
+
+            // Reject arguments that don't match the tool's input schema before
+            // asking the user anything or calling the service
+            if let Err(errors) = validate_input_args(&tool, &tool_args) {
+                let payload = json!({
+                    "status": "invalid_arguments",
+                    "reason": format!("Arguments do not match the input schema of {}", tool.name),
+                    "errors": errors,
+                });
+                let text = serde_json::to_string(&payload)
+                    .unwrap_or_else(|_| "internal serialization error".to_string());
+                return Ok(CallToolResult {
+                    content: vec![Content::text(text)],
+                    structured_content: None,
+                    is_error: Some(true),
+                    meta: None,
+                });
+            }
+
+            // Ask the calling client, not whichever client connected last.
+            // The shared coordinator has no peer, so before the client has
+            // initialized its fallback policy applies.
+            let elicitation = match &session {
+                Some(session) => &session.elicitation,
+                None => orchestrator.elicitation_coordinator().as_ref(),
+            };
+
             // Check if the tool's service is blocked by the user
-            if let Some(ref ctx) = user_context {
-                let filter = UserToolFilter::from_user_context(orchestrator.db(), ctx)
-                    .await
-                    .unwrap_or_else(|_| UserToolFilter::allow_all());
-
-                if !filter.is_tool_allowed(&tool) {
-                    // Service is blocked - trigger elicitation to ask user what to do
-                    let service_id_str = tool.service_id.to_string();
-
-                    let message = format!(
-                        "The tool '{}' is from a blocked service '{}'.\n\n\
-                         Choose how to proceed:\n\
-                         - allow_once: Execute this tool just this once\n\
-                         - unblock_service: Remove the block and execute\n\
-                         - keep_blocked: Don't execute, keep the service blocked",
-                        tool.name, service_id_str
-                    );
-
-                    let schema = ElicitationSchema::builder()
-                        .required_enum_schema(
-                            "action",
-                            EnumSchema::builder(vec![
-                                "allow_once".to_string(),
-                                "unblock_service".to_string(),
-                                "keep_blocked".to_string(),
-                            ])
-                            .build(),
-                        )
-                        .description("Blocked service action")
-                        .build()
-                        .expect("Invalid blocked service schema");
-
-                    let coordinator = orchestrator.elicitation_coordinator();
-                    match coordinator.create_elicitation(&message, schema).await {
-                        Ok(response) => {
-                            use crate::elicitation::ElicitationAction;
-
-                            match response.action {
-                                ElicitationAction::Accept => {
-                                    let action_str = response
-                                        .content
-                                        .as_ref()
-                                        .and_then(|c| c.get("action"))
-                                        .and_then(|v| v.as_str())
-                                        .unwrap_or("keep_blocked");
-
-                                    match action_str {
-                                        "allow_once" => {
-                                            // Continue with execution (fall through)
-                                        }
-                                        "unblock_service" => {
-                                            // Unblock the service and continue
-                                            let user_store =
-                                                UserStore::new(orchestrator.db().clone());
-                                            if let Err(e) = user_store
-                                                .unblock_service(ctx.user_id(), &service_id_str)
-                                                .await
-                                            {
-                                                tracing::warn!("Failed to unblock service: {}", e);
-                                            } else {
-                                                tracing::info!(
-                                                    user_id = %ctx.user_id_string(),
-                                                    service_id = %service_id_str,
-                                                    "User unblocked service via elicitation"
-                                                );
-                                            }
-                                            // Continue with execution (fall through)
-                                        }
-                                        _ => {
-                                            let payload = json!({
-                                                "status": "blocked",
-                                                "reason": format!("Tool execution blocked: service '{}' is blocked", service_id_str),
-                                            });
-                                            let text = serde_json::to_string(&payload)
-                                                .unwrap_or_else(|_| {
-                                                    "internal serialization error".to_string()
-                                                });
-                                            return Ok(CallToolResult {
-                                                content: vec![Content::text(text)],
-                                                structured_content: None,
-                                                is_error: Some(true),
-                                                meta: None,
-                                            });
-                                        }
-                                    }
-                                }
-                                ElicitationAction::Decline | ElicitationAction::Cancel => {
-                                    let payload = json!({
-                                        "status": "blocked",
-                                        "reason": format!("Tool execution blocked: service '{}' is blocked", service_id_str),
-                                    });
-                                    let text =
-                                        serde_json::to_string(&payload).unwrap_or_else(|_| {
-                                            "internal serialization error".to_string()
-                                        });
-                                    return Ok(CallToolResult {
-                                        content: vec![Content::text(text)],
-                                        structured_content: None,
-                                        is_error: Some(true),
-                                        meta: None,
-                                    });
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            // Elicitation failed - block execution
-                            tracing::warn!("Blocked service elicitation failed: {}", e);
-                            let payload = json!({
-                                "status": "blocked",
-                                "reason": format!("Tool execution blocked: service '{}' is blocked (elicitation unavailable)", service_id_str),
-                            });
-                            let text = serde_json::to_string(&payload)
-                                .unwrap_or_else(|_| "internal serialization error".to_string());
-                            return Ok(CallToolResult {
-                                content: vec![Content::text(text)],
-                                structured_content: None,
-                                is_error: Some(true),
-                                meta: None,
-                            });
-                        }
-                    }
-                }
+            if let Some(ref ctx) = user_context
+                && let Some(blocked) = confirm_blocked_service(
+                    &orchestrator,
+                    ctx,
+                    elicitation,
+                    &format!("tool '{}'", tool.name),
+                    &tool.service_id.to_string(),
+                )
+                .await
+            {
+                return Ok(blocked);
             }
 
             let selection = ToolSelection {
@@ -296,10 +316,17 @@ impl ToolHandler for ExecuteToolHandler {
             };
 
             let (content, is_error) = match orchestrator
-                .execute_selected_tool_with_approval(&selection, tool_args, user_context.as_ref())
+                .execute_selected_tool_with_approval(
+                    &selection,
+                    tool_args,
+                    user_context.as_ref(),
+                    elicitation,
+                )
                 .await
             {
                 Ok(contents) => (contents, false),
+                // Surfaced as an MCP error rather than a failed result
+                Err(e) if e.is::<MissingRoleError>() => return Err(e),
                 Err(e) => {
                     let error_msg = e.to_string();
                     let (status, reason) = if error_msg.contains("denied by user") {
@@ -307,7 +334,7 @@ impl ToolHandler for ExecuteToolHandler {
                     } else if error_msg.contains("cancelled") {
                         ("cancelled", error_msg)
                     } else {
-                        ("error", format!("Tool execution failed: {}", e))
+                        ("error", format!("{}{}", EXECUTION_FAILED_PREFIX, e))
                     };
                     let payload = json!({
                         "status": status,
@@ -327,4 +354,8 @@ impl ToolHandler for ExecuteToolHandler {
             })
         })
     }
+
+    fn health_check(&self) -> Pin<Box<dyn Future<Output = HealthStatus> + Send + '_>> {
+        Box::pin(probe_orchestrator(&self.orchestrator))
+    }
 }
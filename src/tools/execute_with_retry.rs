@@ -0,0 +1,335 @@
+//! Handler for the `unicity.execute_with_retry` tool.
+//!
+//! Execute a tool like `unicity.execute_tool`, retrying failed executions with
+//! exponential backoff. Only calls that reached the tool and failed are
+//! retried; refusals such as a denied approval, a blocked service or invalid
+//! arguments are returned straight away.
+
+use crate::orchestrator::Orchestrator;
+use crate::tools::execute_tool::EXECUTION_FAILED_PREFIX;
+use crate::tools::{ExecuteToolHandler, HealthStatus, ToolContext, ToolHandler};
+use rmcp::model::{CallToolResult, JsonObject, Meta, RawContent};
+use serde_json::json;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Retries when `max_retries` isn't given.
+const DEFAULT_MAX_RETRIES: u8 = 3;
+
+/// Base delay in seconds when `retry_delay_secs` isn't given.
+const DEFAULT_RETRY_DELAY_SECS: f32 = 1.0;
+
+/// Longest wait between two attempts, however many retries came before.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Transient failures retried when `retry_on_errors` isn't given.
+const DEFAULT_RETRY_ON_ERRORS: &[&str] = &["timed out", "timeout", "connection", "unavailable"];
+
+/// When and how often a failed execution is retried.
+#[derive(Debug, Clone, PartialEq)]
+struct RetryPolicy {
+    max_retries: u8,
+    /// Substrings of the error text that make an error retryable; empty
+    /// uses [`DEFAULT_RETRY_ON_ERRORS`].
+    retry_on_errors: Vec<String>,
+    /// Delay before the first retry, doubled for each one after.
+    retry_delay_secs: f32,
+}
+
+impl RetryPolicy {
+    /// Read the policy from the tool arguments, falling back to the defaults
+    /// for missing or malformed values.
+    fn from_args(args: &JsonObject) -> Self {
+        let max_retries = args
+            .get("max_retries")
+            .and_then(|v| v.as_u64())
+            .map_or(DEFAULT_MAX_RETRIES, |n| n.min(u8::MAX as u64) as u8);
+        let retry_on_errors = args
+            .get("retry_on_errors")
+            .and_then(|v| v.as_array())
+            .map(|patterns| {
+                patterns
+                    .iter()
+                    .filter_map(|p| p.as_str())
+                    .filter(|p| !p.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default();
+        let retry_delay_secs = args
+            .get("retry_delay_secs")
+            .and_then(|v| v.as_f64())
+            .map(|secs| secs as f32)
+            .filter(|secs| secs.is_finite() && *secs >= 0.0)
+            .unwrap_or(DEFAULT_RETRY_DELAY_SECS);
+
+        Self {
+            max_retries,
+            retry_on_errors,
+            retry_delay_secs,
+        }
+    }
+
+    /// Whether `result` is a failed tool call worth another attempt: the
+    /// tool was called, and the failure matches one of the error patterns.
+    fn should_retry(&self, result: &CallToolResult) -> bool {
+        if result.is_error != Some(true) {
+            return false;
+        }
+        result.content.iter().any(|content| {
+            let RawContent::Text(text) = &content.raw else {
+                return false;
+            };
+            let Some(reason) = execution_failure(&text.text) else {
+                return false;
+            };
+            if self.retry_on_errors.is_empty() {
+                DEFAULT_RETRY_ON_ERRORS
+                    .iter()
+                    .any(|pattern| reason.contains(pattern))
+            } else {
+                self.retry_on_errors
+                    .iter()
+                    .any(|pattern| reason.contains(pattern.as_str()))
+            }
+        })
+    }
+
+    /// How long to wait before retry number `retry` (starting at 0).
+    fn delay(&self, retry: u8) -> Duration {
+        let secs = self.retry_delay_secs as f64 * 2f64.powi(retry as i32);
+        Duration::try_from_secs_f64(secs)
+            .unwrap_or(MAX_RETRY_DELAY)
+            .min(MAX_RETRY_DELAY)
+    }
+}
+
+/// The reason of an `unicity.execute_tool` result whose tool was called but
+/// failed; `None` for successes and for calls refused before reaching the tool.
+fn execution_failure(text: &str) -> Option<String> {
+    let payload: serde_json::Value = serde_json::from_str(text).ok()?;
+    if payload.get("status")?.as_str()? != "error" {
+        return None;
+    }
+    let reason = payload.get("reason")?.as_str()?;
+    reason
+        .starts_with(EXECUTION_FAILED_PREFIX)
+        .then(|| reason.to_string())
+}
+
+/// Mark the text content of a failed result with `retry_exhausted: true` and
+/// the number of attempts made.
+fn mark_retry_exhausted(result: &mut CallToolResult, attempts: u32) {
+    for content in &mut result.content {
+        if let RawContent::Text(text) = &mut content.raw {
+            let meta = text.meta.get_or_insert_with(Meta::new);
+            meta.insert("retry_exhausted".to_string(), json!(true));
+            meta.insert("attempts".to_string(), json!(attempts));
+        }
+    }
+}
+
+/// Handler for the `unicity.execute_with_retry` tool.
+pub struct ExecuteWithRetryToolHandler {
+    execute: ExecuteToolHandler,
+}
+
+impl ExecuteWithRetryToolHandler {
+    /// Create a new execute-with-retry tool handler.
+    pub fn new(orchestrator: Arc<Orchestrator>) -> Self {
+        Self {
+            execute: ExecuteToolHandler::new(orchestrator),
+        }
+    }
+
+    /// Build the input schema for this tool: `unicity.execute_tool`'s
+    /// arguments plus the retry settings.
+    fn input_schema(&self) -> JsonObject {
+        let mut schema = ToolHandler::input_schema(&self.execute);
+        if let Some(properties) = schema.get_mut("properties").and_then(|p| p.as_object_mut()) {
+            properties.insert(
+                "max_retries".to_string(),
+                json!({
+                    "type": "integer",
+                    "minimum": 0,
+                    "maximum": 255,
+                    "description": format!("How many times to retry a failed execution (default {}).", DEFAULT_MAX_RETRIES)
+                }),
+            );
+            properties.insert(
+                "retry_on_errors".to_string(),
+                json!({
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Only retry failed executions whose error contains one of these substrings. Omit to retry timeouts and connection failures. Denied approvals, blocked services and invalid arguments are never retried."
+                }),
+            );
+            properties.insert(
+                "retry_delay_secs".to_string(),
+                json!({
+                    "type": "number",
+                    "minimum": 0,
+                    "description": format!("Seconds to wait before the first retry, doubled for each retry after it (default {}).", DEFAULT_RETRY_DELAY_SECS)
+                }),
+            );
+        }
+        schema
+    }
+}
+
+impl ToolHandler for ExecuteWithRetryToolHandler {
+    fn name(&self) -> &str {
+        "unicity.execute_with_retry"
+    }
+
+    fn title(&self) -> Option<&str> {
+        Some("Unicity Orchestrator: Execute Tool With Retry")
+    }
+
+    fn description(&self) -> &str {
+        "Execute a previously selected underlying MCP tool by toolId, retrying failed executions \
+         with exponential backoff."
+    }
+
+    fn input_schema(&self) -> JsonObject {
+        self.input_schema()
+    }
+
+    fn execute(
+        &self,
+        args: JsonObject,
+        ctx: &ToolContext,
+    ) -> Pin<Box<dyn Future<Output = anyhow::Result<CallToolResult>> + Send + '_>> {
+        let policy = RetryPolicy::from_args(&args);
+        let ctx = ctx.clone();
+
+        Box::pin(async move {
+            let mut retry = 0;
+            loop {
+                let mut result = self.execute.execute(args.clone(), &ctx).await?;
+                if !policy.should_retry(&result) {
+                    return Ok(result);
+                }
+                if retry == policy.max_retries {
+                    mark_retry_exhausted(&mut result, retry as u32 + 1);
+                    return Ok(result);
+                }
+
+                let delay = policy.delay(retry);
+                retry += 1;
+                tracing::debug!(
+                    tool_id = ?args.get("toolId"),
+                    retry,
+                    delay_ms = delay.as_millis() as u64,
+                    "Retrying failed tool execution"
+                );
+                ctx.notify_progress(
+                    retry as f64,
+                    Some(policy.max_retries as f64),
+                    format!("Retry {} of {}", retry, policy.max_retries),
+                )
+                .await;
+                tokio::time::sleep(delay).await;
+            }
+        })
+    }
+
+    fn health_check(&self) -> Pin<Box<dyn Future<Output = HealthStatus> + Send + '_>> {
+        self.execute.health_check()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::model::Content;
+
+    fn args(value: serde_json::Value) -> JsonObject {
+        value.as_object().cloned().unwrap()
+    }
+
+    fn error_result(text: &str) -> CallToolResult {
+        CallToolResult {
+            content: vec![Content::text(text)],
+            structured_content: None,
+            is_error: Some(true),
+            meta: None,
+        }
+    }
+
+    #[test]
+    fn test_retry_policy_from_args() {
+        let policy = RetryPolicy::from_args(&args(json!({ "toolId": "tool:a", "args": {} })));
+        assert_eq!(policy.max_retries, DEFAULT_MAX_RETRIES);
+        assert!(policy.retry_on_errors.is_empty());
+        assert_eq!(policy.retry_delay_secs, DEFAULT_RETRY_DELAY_SECS);
+
+        let policy = RetryPolicy::from_args(&args(json!({
+            "max_retries": 1000,
+            "retry_on_errors": ["timeout", "", 5],
+            "retry_delay_secs": -1.0,
+        })));
+        assert_eq!(policy.max_retries, u8::MAX);
+        assert_eq!(policy.retry_on_errors, vec!["timeout".to_string()]);
+        assert_eq!(policy.retry_delay_secs, DEFAULT_RETRY_DELAY_SECS);
+    }
+
+    #[test]
+    fn test_should_retry_matches_error_patterns() {
+        let failed = |reason: &str| {
+            let reason = format!("{}{}", EXECUTION_FAILED_PREFIX, reason);
+            error_result(&json!({ "status": "error", "reason": reason }).to_string())
+        };
+
+        let transient = RetryPolicy::from_args(&JsonObject::new());
+        assert!(transient.should_retry(&failed("request timed out")));
+        assert!(!transient.should_retry(&failed("boom")));
+        let success = CallToolResult::success(vec![Content::text("timeout")]);
+        assert!(!transient.should_retry(&success));
+
+        let booms = RetryPolicy::from_args(&args(json!({ "retry_on_errors": ["boom"] })));
+        assert!(booms.should_retry(&failed("boom")));
+        assert!(!booms.should_retry(&failed("request timed out")));
+    }
+
+    #[test]
+    fn test_should_not_retry_refusals() {
+        let policy = RetryPolicy::from_args(&args(json!({ "retry_on_errors": ["e"] })));
+        for payload in [
+            json!({ "status": "denied", "reason": "Tool execution denied by user" }),
+            json!({ "status": "cancelled", "reason": "Tool approval cancelled" }),
+            json!({ "status": "blocked", "reason": "Blocked: service 'service:a' is blocked" }),
+            json!({ "status": "invalid_arguments", "reason": "Arguments do not match the input schema of a" }),
+            json!({ "status": "error", "reason": "No tool found with id tool:missing" }),
+        ] {
+            assert!(
+                !policy.should_retry(&error_result(&payload.to_string())),
+                "{}",
+                payload
+            );
+        }
+    }
+
+    #[test]
+    fn test_delay_doubles_up_to_the_cap() {
+        let policy = RetryPolicy::from_args(&args(json!({ "retry_delay_secs": 0.5 })));
+        assert_eq!(policy.delay(0), Duration::from_millis(500));
+        assert_eq!(policy.delay(2), Duration::from_secs(2));
+        assert_eq!(policy.delay(200), MAX_RETRY_DELAY);
+    }
+
+    #[test]
+    fn test_mark_retry_exhausted_sets_content_meta() {
+        let mut result = error_result("boom");
+        mark_retry_exhausted(&mut result, 4);
+
+        let RawContent::Text(text) = &result.content[0].raw else {
+            panic!("expected text content");
+        };
+        let meta = text.meta.as_ref().unwrap();
+        assert_eq!(meta.get("retry_exhausted"), Some(&json!(true)));
+        assert_eq!(meta.get("attempts"), Some(&json!(4)));
+    }
+}
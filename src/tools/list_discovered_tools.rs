@@ -3,7 +3,7 @@
 //! Debug tool for listing all discovered MCP service tools.
 //! Not intended for LLM use - use `unicity.select_tool` for semantic search instead.
 
-use crate::db::ToolRecord;
+use crate::db::{QueryBuilder, ToolFilter, decode_tool_cursor, encode_tool_cursor};
 use crate::orchestrator::Orchestrator;
 use crate::orchestrator::user_filter::UserToolFilter;
 use crate::tools::{ToolContext, ToolHandler};
@@ -12,6 +12,7 @@ use serde_json::json;
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
+use surrealdb::RecordId;
 
 /// Handler for the `unicity.debug.list_tools` debug tool.
 pub struct ListDiscoveredToolsHandler {
@@ -54,11 +55,10 @@ impl ListDiscoveredToolsHandler {
             }),
         );
         properties.insert(
-            "offset".to_string(),
+            "cursor".to_string(),
             json!({
-                "type": "integer",
-                "description": "Offset for pagination (default: 0).",
-                "default": 0
+                "type": "string",
+                "description": "`pagination.nextCursor` of the previous page; omit for the first page."
             }),
         );
 
@@ -116,8 +116,19 @@ impl ToolHandler for ListDiscoveredToolsHandler {
                 .get("include_blocked")
                 .and_then(|v| v.as_bool())
                 .unwrap_or(true);
-            let limit = args.get("limit").and_then(|v| v.as_u64()).unwrap_or(100) as usize;
-            let offset = args.get("offset").and_then(|v| v.as_u64()).unwrap_or(0) as usize;
+            let limit = args
+                .get("limit")
+                .and_then(|v| v.as_u64())
+                .unwrap_or(100)
+                .max(1) as usize;
+            let cursor = args.get("cursor").and_then(|v| v.as_str());
+            let after_id = match cursor.map(decode_tool_cursor) {
+                Some(None) => {
+                    return Ok(error_result("Invalid pagination cursor".to_string()));
+                }
+                Some(id) => id,
+                None => None,
+            };
 
             // Load user's filter to determine blocked/trusted status
             let filter = match &user_context {
@@ -127,31 +138,22 @@ impl ToolHandler for ListDiscoveredToolsHandler {
                 None => UserToolFilter::allow_all(),
             };
 
-            // Query all tools from database
-            let query = if let Some(svc) = service_filter {
-                format!(
-                    "SELECT * FROM tool WHERE service_id CONTAINS '{}' OR service_id = type::thing('service', '{}') LIMIT {} START {}",
-                    svc, svc, limit, offset
-                )
-            } else {
-                format!("SELECT * FROM tool LIMIT {} START {}", limit, offset)
+            // Query one page of tools from the database
+            let tool_filter = ToolFilter {
+                service_id: service_filter.map(service_record_id),
+                ..Default::default()
             };
-
-            let tools: Vec<ToolRecord> = match orchestrator.db().query(&query).await {
-                Ok(mut res) => res.take(0).unwrap_or_default(),
+            let (tools, next) = match QueryBuilder::paginated_tools(
+                orchestrator.db(),
+                after_id,
+                limit,
+                tool_filter,
+            )
+            .await
+            {
+                Ok(page) => page,
                 Err(e) => {
-                    let payload = json!({
-                        "status": "error",
-                        "reason": format!("Failed to query tools: {}", e)
-                    });
-                    let text = serde_json::to_string(&payload)
-                        .unwrap_or_else(|_| "internal serialization error".to_string());
-                    return Ok(CallToolResult {
-                        content: vec![Content::text(text)],
-                        structured_content: None,
-                        is_error: Some(true),
-                        meta: None,
-                    });
+                    return Ok(error_result(format!("Failed to query tools: {}", e)));
                 }
             };
 
@@ -194,7 +196,7 @@ impl ToolHandler for ListDiscoveredToolsHandler {
                 "trustedCount": trusted_count,
                 "pagination": {
                     "limit": limit,
-                    "offset": offset,
+                    "nextCursor": next.as_ref().map(encode_tool_cursor),
                 }
             });
 
@@ -210,3 +212,27 @@ impl ToolHandler for ListDiscoveredToolsHandler {
         })
     }
 }
+
+/// The service id for `service_filter`, given as `service:<key>` or just the key.
+fn service_record_id(service: &str) -> RecordId {
+    service
+        .parse::<RecordId>()
+        .ok()
+        .filter(|id| id.table() == "service")
+        .unwrap_or_else(|| RecordId::from_table_key("service", service))
+}
+
+fn error_result(reason: String) -> CallToolResult {
+    let payload = json!({
+        "status": "error",
+        "reason": reason
+    });
+    let text = serde_json::to_string(&payload)
+        .unwrap_or_else(|_| "internal serialization error".to_string());
+    CallToolResult {
+        content: vec![Content::text(text)],
+        structured_content: None,
+        is_error: Some(true),
+        meta: None,
+    }
+}
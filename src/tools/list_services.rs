@@ -0,0 +1,106 @@
+//! Handler for the `unicity.list_services` tool.
+//!
+//! Lists the MCP services known to the orchestrator with their tool counts
+//! and health, including services that failed to start.
+
+use crate::orchestrator::Orchestrator;
+use crate::tools::{ToolContext, ToolHandler};
+use rmcp::model::{CallToolResult, Content, JsonObject};
+use serde_json::json;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// Handler for the `unicity.list_services` tool.
+pub struct ListServicesHandler {
+    orchestrator: Arc<Orchestrator>,
+}
+
+impl ListServicesHandler {
+    /// Create a new list services handler.
+    pub fn new(orchestrator: Arc<Orchestrator>) -> Self {
+        Self { orchestrator }
+    }
+
+    /// Build the input schema for this tool.
+    fn input_schema(&self) -> JsonObject {
+        let mut schema = JsonObject::new();
+        schema.insert("type".to_string(), json!("object"));
+        schema.insert("properties".to_string(), json!({}));
+        schema.insert("required".to_string(), json!([]));
+        schema
+    }
+
+    /// Build the output schema for this tool.
+    fn output_schema(&self) -> JsonObject {
+        let mut schema = JsonObject::new();
+        schema.insert("type".to_string(), json!("object"));
+        schema.insert(
+            "description".to_string(),
+            json!("Known services with id, name, version, tool_count, health_status and last_health_check."),
+        );
+        schema
+    }
+}
+
+impl ToolHandler for ListServicesHandler {
+    fn name(&self) -> &str {
+        "unicity.list_services"
+    }
+
+    fn title(&self) -> Option<&str> {
+        Some("List Services")
+    }
+
+    fn description(&self) -> &str {
+        "List the MCP services the orchestrator knows about, with their tool counts and health. \
+         health_status is `healthy`, `exited` (restarting), `disconnected` (not running) or \
+         `ephemeral` (running without a database record)."
+    }
+
+    fn input_schema(&self) -> JsonObject {
+        self.input_schema()
+    }
+
+    fn output_schema(&self) -> Option<JsonObject> {
+        Some(self.output_schema())
+    }
+
+    fn execute(
+        &self,
+        _args: JsonObject,
+        _ctx: &ToolContext,
+    ) -> Pin<Box<dyn Future<Output = anyhow::Result<CallToolResult>> + Send + '_>> {
+        let orchestrator = self.orchestrator.clone();
+
+        Box::pin(async move {
+            let (payload, is_error) = match orchestrator.list_services().await {
+                Ok(services) => (
+                    json!({
+                        "status": "ok",
+                        "count": services.len(),
+                        "services": services,
+                    }),
+                    false,
+                ),
+                Err(e) => (
+                    json!({
+                        "status": "error",
+                        "reason": format!("Failed to list services: {}", e)
+                    }),
+                    true,
+                ),
+            };
+
+            let text = serde_json::to_string(&payload)
+                .unwrap_or_else(|_| "internal serialization error".to_string());
+
+            Ok(CallToolResult {
+                content: vec![Content::text(text)],
+                structured_content: None,
+                is_error: Some(is_error),
+                meta: None,
+            })
+        })
+    }
+}
@@ -6,15 +6,28 @@
 
 mod registry;
 
-pub use registry::{ToolContext, ToolHandler, ToolRegistry};
+pub(crate) use registry::probe_orchestrator;
+pub use registry::{HealthStatus, ToolContext, ToolHandler, ToolRegistry};
 
 // Tool handler implementations
+mod describe_tool;
 mod execute_tool;
+mod execute_with_retry;
 mod list_discovered_tools;
+mod list_services;
 mod plan_tools;
 mod select_tool;
+mod subscribe_resource;
+mod unsubscribe_resource;
+mod write_resource;
 
+pub use describe_tool::DescribeToolHandler;
 pub use execute_tool::ExecuteToolHandler;
+pub use execute_with_retry::ExecuteWithRetryToolHandler;
 pub use list_discovered_tools::ListDiscoveredToolsHandler;
+pub use list_services::ListServicesHandler;
 pub use plan_tools::PlanToolsHandler;
 pub use select_tool::SelectToolHandler;
+pub use subscribe_resource::SubscribeResourceHandler;
+pub use unsubscribe_resource::UnsubscribeResourceHandler;
+pub use write_resource::WriteResourceHandler;
@@ -3,10 +3,11 @@
 //! Given a higher-level goal, proposes a multi-step plan using underlying
 //! MCP tools, without executing them.
 
-use crate::orchestrator::Orchestrator;
-use crate::tools::{ToolContext, ToolHandler};
+use crate::knowledge_graph::PlanningConstraints;
+use crate::orchestrator::{Orchestrator, PlanResult};
+use crate::tools::{HealthStatus, ToolContext, ToolHandler, probe_orchestrator};
 use rmcp::model::{CallToolResult, Content, JsonObject};
-use serde_json::json;
+use serde_json::{Value, json};
 use std::pin::Pin;
 use std::sync::Arc;
 
@@ -42,6 +43,34 @@ impl PlanToolsHandler {
                 "additionalProperties": true,
             }),
         );
+        properties.insert(
+            "input_type_uri".to_string(),
+            json!({
+                "type": "string",
+                "description": "Optional type URI of the value the pipeline starts from. Together with `output_type_uri`, plans are built by chaining tools through their input and output types.",
+            }),
+        );
+        properties.insert(
+            "output_type_uri".to_string(),
+            json!({
+                "type": "string",
+                "description": "Optional type URI of the value the pipeline should produce.",
+            }),
+        );
+        properties.insert(
+            "max_path_cost".to_string(),
+            json!({
+                "type": "number",
+                "description": "Optional budget for typed planning. Chains whose summed tool costs and type mismatches exceed it are not proposed.",
+            }),
+        );
+        properties.insert(
+            "include_diagram".to_string(),
+            json!({
+                "type": "boolean",
+                "description": "Also return the plan as a Mermaid flowchart, in a second text content block. Defaults to false.",
+            }),
+        );
 
         schema.insert("properties".to_string(), json!(properties));
         schema.insert("required".to_string(), json!(["query"]));
@@ -54,6 +83,13 @@ impl PlanToolsHandler {
         schema.insert("type".to_string(), json!("object"));
 
         let mut properties = serde_json::Map::new();
+        properties.insert(
+            "planId".to_string(),
+            json!({
+                "type": "string",
+                "description": "Id of the plan, e.g. for `GET /plans/{id}/diagram` on the admin API."
+            }),
+        );
         properties.insert(
             "steps".to_string(),
             json!({
@@ -88,6 +124,22 @@ impl PlanToolsHandler {
                 "description": "High-level explanation of why this plan was proposed."
             }),
         );
+        properties.insert(
+            "alternatives".to_string(),
+            json!({
+                "type": "array",
+                "description": "For typed planning, up to three plans ordered by path cost; the first is the one described by `steps`.",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "planId": { "type": "string" },
+                        "steps": { "type": "array" },
+                        "confidence": { "type": "number" },
+                        "reasoning": { "type": "string" }
+                    }
+                }
+            }),
+        );
 
         schema.insert("properties".to_string(), json!(properties));
         schema.insert("required".to_string(), json!(["steps"]));
@@ -95,6 +147,39 @@ impl PlanToolsHandler {
     }
 }
 
+/// One line per step, e.g. `1. read_file (service:fs): Read the config`.
+fn plan_outline(plan: &PlanResult) -> String {
+    plan.steps
+        .iter()
+        .enumerate()
+        .map(|(i, step)| {
+            format!(
+                "{}. {} ({}): {}",
+                i + 1,
+                step.tool_name,
+                step.service_id,
+                step.description
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// JSON for the steps of a plan.
+fn steps_json(plan: &PlanResult) -> Vec<Value> {
+    plan.steps
+        .iter()
+        .map(|step| {
+            json!({
+                "description": step.description,
+                "serviceId": step.service_id.to_string(),
+                "toolName": step.tool_name,
+                "inputs": step.inputs,
+            })
+        })
+        .collect()
+}
+
 impl ToolHandler for PlanToolsHandler {
     fn name(&self) -> &str {
         "unicity.plan_tools"
@@ -122,7 +207,7 @@ impl ToolHandler for PlanToolsHandler {
         ctx: &ToolContext,
     ) -> Pin<Box<dyn Future<Output = anyhow::Result<CallToolResult>> + Send + '_>> {
         let orchestrator = self.orchestrator.clone();
-        let user_context = ctx.user_context.clone();
+        let ctx = ctx.clone();
 
         Box::pin(async move {
             let query = match args.get("query").and_then(|v| v.as_str()) {
@@ -144,33 +229,73 @@ impl ToolHandler for PlanToolsHandler {
             };
 
             let context_value = args.get("context").cloned();
+            let include_diagram = args
+                .get("include_diagram")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            let input_type_uri = args.get("input_type_uri").and_then(|v| v.as_str());
+            let output_type_uri = args.get("output_type_uri").and_then(|v| v.as_str());
+            let constraints = PlanningConstraints {
+                max_path_cost: args
+                    .get("max_path_cost")
+                    .and_then(|v| v.as_f64())
+                    .map(|cost| cost as f32),
+                ..PlanningConstraints::default()
+            };
 
-            let plan_result = orchestrator
-                .plan_tools_for_query(&query, context_value, user_context.as_ref())
-                .await;
+            // Typed planning first; an error or empty result falls back to text planning
+            let mut typed_plans = Vec::new();
+            if let (Some(input), Some(output)) = (input_type_uri, output_type_uri) {
+                match orchestrator
+                    .plan_tools_by_type(input, output, &constraints, ctx.user_context.as_ref())
+                    .await
+                {
+                    Ok(plans) => typed_plans = plans,
+                    Err(e) => tracing::warn!("Typed planning failed, using text planning: {}", e),
+                }
+            }
+
+            let plan_result = match typed_plans.first() {
+                Some(best) => Ok(Some(best.clone())),
+                None => {
+                    orchestrator
+                        .plan_tools_for_query(&query, context_value, ctx.user_context.as_ref())
+                        .await
+                }
+            };
 
             let mut is_error = false;
+            let mut diagram = None;
             let payload = match plan_result {
                 Ok(Some(plan)) => {
-                    let steps_json: Vec<_> = plan
-                        .steps
-                        .into_iter()
-                        .map(|step| {
-                            json!({
-                                "description": step.description,
-                                "serviceId": step.service_id.to_string(),
-                                "toolName": step.tool_name,
-                                "inputs": step.inputs,
-                            })
-                        })
-                        .collect();
+                    // Step 0: the plan is assembled but nothing has run yet
+                    ctx.notify_progress(0.0, Some(plan.steps.len() as f64), plan_outline(&plan))
+                        .await;
 
-                    json!({
+                    if include_diagram {
+                        diagram = Some(plan.diagram.clone());
+                    }
+                    let mut payload = json!({
                         "status": "ok",
-                        "steps": steps_json,
+                        "planId": plan.id,
+                        "steps": steps_json(&plan),
                         "confidence": plan.confidence,
                         "reasoning": plan.reasoning,
-                    })
+                    });
+                    if !typed_plans.is_empty() {
+                        payload["alternatives"] = typed_plans
+                            .iter()
+                            .map(|plan| {
+                                json!({
+                                    "planId": plan.id,
+                                    "steps": steps_json(plan),
+                                    "confidence": plan.confidence,
+                                    "reasoning": plan.reasoning,
+                                })
+                            })
+                            .collect();
+                    }
+                    payload
                 }
                 Ok(None) => {
                     is_error = true;
@@ -190,12 +315,50 @@ impl ToolHandler for PlanToolsHandler {
 
             let text = serde_json::to_string(&payload)
                 .unwrap_or_else(|_| "internal serialization error".to_string());
+            let mut content = vec![Content::text(text)];
+            content.extend(diagram.map(Content::text));
             Ok(CallToolResult {
-                content: vec![Content::text(text)],
+                content,
                 structured_content: None,
                 is_error: Some(is_error),
                 meta: None,
             })
         })
     }
+
+    fn health_check(&self) -> Pin<Box<dyn Future<Output = HealthStatus> + Send + '_>> {
+        Box::pin(probe_orchestrator(&self.orchestrator))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orchestrator::PlanStep;
+    use surrealdb::RecordId;
+
+    #[test]
+    fn test_plan_outline_lists_steps_in_order() {
+        let step = |tool: &str, description: &str| PlanStep {
+            description: description.to_string(),
+            service_id: RecordId::from_table_key("service", "fs"),
+            tool_name: tool.to_string(),
+            inputs: Vec::new(),
+        };
+        let plan = PlanResult {
+            id: "plan-1".to_string(),
+            steps: vec![
+                step("read_file", "Read the config"),
+                step("write_file", "Write the result"),
+            ],
+            confidence: 0.9,
+            reasoning: String::new(),
+            diagram: String::new(),
+        };
+
+        assert_eq!(
+            plan_outline(&plan),
+            "1. read_file (service:fs): Read the config\n2. write_file (service:fs): Write the result"
+        );
+    }
 }
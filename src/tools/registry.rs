@@ -4,15 +4,22 @@
 //! for registering and invoking them.
 
 use anyhow::Result;
+use futures::FutureExt;
 use rmcp::RoleServer;
-use rmcp::model::{CallToolResult, JsonObject, Tool as McpTool};
+use rmcp::model::{CallToolResult, JsonObject, ProgressNotificationParam, Tool as McpTool};
 use rmcp::service::RequestContext;
+use serde::Serialize;
 use std::collections::HashMap;
-use std::future::Future;
+use std::future::{Future, IntoFuture};
+use std::panic::AssertUnwindSafe;
 use std::pin::Pin;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::Duration;
+use tokio::sync::broadcast;
 
 use crate::auth::UserContext;
+use crate::orchestrator::Orchestrator;
+use crate::session::McpServerSession;
 
 /// Pagination constants for tools.
 const DEFAULT_PAGE_SIZE: usize = 100;
@@ -26,6 +33,77 @@ pub struct ToolContext {
     pub request_context: RequestContext<RoleServer>,
     /// User context for multi-tenant isolation (None for anonymous/stdio mode)
     pub user_context: Option<UserContext>,
+    /// The calling client's session (None before the client has initialized)
+    pub session: Option<Arc<McpServerSession>>,
+}
+
+impl ToolContext {
+    /// Identifies the client session making this request: the
+    /// `Mcp-Session-Id` header over streamable HTTP, or `"stdio"` when there
+    /// is only one session.
+    pub fn session_id(&self) -> String {
+        self.request_context
+            .extensions
+            .get::<http::request::Parts>()
+            .and_then(|parts| parts.headers.get("mcp-session-id"))
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("stdio")
+            .to_string()
+    }
+
+    /// Send a `notifications/progress` for this request.
+    ///
+    /// Does nothing unless the client asked for progress by sending a
+    /// `progressToken` in the request's `_meta`. Delivery failures are only
+    /// logged, since progress is advisory.
+    pub async fn notify_progress(&self, progress: f64, total: Option<f64>, message: String) {
+        let Some(progress_token) = self.request_context.meta.get_progress_token() else {
+            return;
+        };
+
+        let param = ProgressNotificationParam {
+            progress_token,
+            progress,
+            total,
+            message: Some(message),
+        };
+        if let Err(e) = self.request_context.peer.notify_progress(param).await {
+            tracing::debug!("Failed to send progress notification: {}", e);
+        }
+    }
+}
+
+/// Result of a tool handler's health check.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", content = "reason", rename_all = "snake_case")]
+pub enum HealthStatus {
+    /// The handler can serve requests
+    Ok,
+    /// The handler works but a dependency reported an error
+    Degraded(String),
+    /// The handler cannot serve requests
+    Failed(String),
+}
+
+/// How long the orchestrator probe waits for the database to answer.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Check an orchestrator-backed handler by pinging its database.
+///
+/// The health endpoint runs this once per handler, so it must stay cheap:
+/// embedding a query here would put a model call on every health check.
+pub(crate) async fn probe_orchestrator(orchestrator: &Orchestrator) -> HealthStatus {
+    let ping =
+        AssertUnwindSafe(orchestrator.db().query("RETURN true").into_future()).catch_unwind();
+    match tokio::time::timeout(PROBE_TIMEOUT, ping).await {
+        Ok(Ok(Ok(_))) => HealthStatus::Ok,
+        Ok(Ok(Err(e))) => HealthStatus::Degraded(format!("database ping failed: {}", e)),
+        Ok(Err(_)) => HealthStatus::Failed("database ping panicked".to_string()),
+        Err(_) => HealthStatus::Degraded(format!(
+            "database did not answer within {}s",
+            PROBE_TIMEOUT.as_secs()
+        )),
+    }
 }
 
 /// Trait for handling MCP tool invocations.
@@ -58,6 +136,11 @@ pub trait ToolHandler: Send + Sync {
         ctx: &ToolContext,
     ) -> Pin<Box<dyn Future<Output = Result<CallToolResult>> + Send + '_>>;
 
+    /// Checks that the handler is able to serve requests.
+    fn health_check(&self) -> Pin<Box<dyn Future<Output = HealthStatus> + Send + '_>> {
+        Box::pin(async { HealthStatus::Ok })
+    }
+
     /// Converts this handler to an `McpTool` for use in `list_tools`.
     fn to_mcp_tool(&self) -> McpTool {
         use std::borrow::Cow;
@@ -77,40 +160,71 @@ pub trait ToolHandler: Send + Sync {
 }
 
 /// Registry for managing tool handlers.
+///
+/// Handlers can be added and removed while the server is running; clones share
+/// the same set of handlers.
 #[derive(Clone)]
 pub struct ToolRegistry {
-    handlers: HashMap<String, Arc<dyn ToolHandler>>,
+    handlers: Arc<RwLock<HashMap<String, Arc<dyn ToolHandler>>>>,
+    /// Signalled whenever the set of handlers changes after startup.
+    changes: broadcast::Sender<()>,
 }
 
 impl ToolRegistry {
     /// Create a new empty tool registry.
     pub fn new() -> Self {
         Self {
-            handlers: HashMap::new(),
+            handlers: Arc::new(RwLock::new(HashMap::new())),
+            changes: broadcast::channel(16).0,
         }
     }
 
+    fn read(&self) -> RwLockReadGuard<'_, HashMap<String, Arc<dyn ToolHandler>>> {
+        self.handlers.read().expect("tool registry lock poisoned")
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, HashMap<String, Arc<dyn ToolHandler>>> {
+        self.handlers.write().expect("tool registry lock poisoned")
+    }
+
     /// Register a tool handler.
-    pub fn register(mut self, handler: Arc<dyn ToolHandler>) -> Self {
-        self.handlers.insert(handler.name().to_string(), handler);
+    pub fn register(self, handler: Arc<dyn ToolHandler>) -> Self {
+        self.write().insert(handler.name().to_string(), handler);
         self
     }
 
     /// Register a tool handler from a type that implements `ToolHandler`.
-    pub fn register_handler<T: ToolHandler + 'static>(mut self, handler: T) -> Self {
-        self.handlers
-            .insert(handler.name().to_string(), Arc::new(handler));
-        self
+    pub fn register_handler<T: ToolHandler + 'static>(self, handler: T) -> Self {
+        self.register(Arc::new(handler))
+    }
+
+    /// Remove a tool handler, returning it if it was registered.
+    ///
+    /// Subscribers of [`ToolRegistry::subscribe_changes`] are notified, so
+    /// connected clients receive `notifications/tools/list_changed`. Calls
+    /// already executing the handler run to completion.
+    pub fn unregister_handler(&self, name: &str) -> Option<Arc<dyn ToolHandler>> {
+        let removed = self.write().remove(name);
+        if removed.is_some() {
+            // Sending only fails when nobody is subscribed
+            let _ = self.changes.send(());
+        }
+        removed
+    }
+
+    /// Receive a message each time a handler is unregistered.
+    pub fn subscribe_changes(&self) -> broadcast::Receiver<()> {
+        self.changes.subscribe()
     }
 
     /// Get a tool handler by name.
     pub fn get(&self, name: &str) -> Option<Arc<dyn ToolHandler>> {
-        self.handlers.get(name).cloned()
+        self.read().get(name).cloned()
     }
 
     /// List all registered tool names.
     pub fn list_names(&self) -> Vec<String> {
-        self.handlers.keys().cloned().collect()
+        self.read().keys().cloned().collect()
     }
 
     /// Get all registered tools as `McpTool` instances for `list_tools`.
@@ -123,12 +237,12 @@ impl ToolRegistry {
         // Parse cursor to get offset
         let offset = cursor.and_then(|c| c.parse::<usize>().ok()).unwrap_or(0);
 
-        let total = self.handlers.len();
+        let handlers = self.read();
+        let total = handlers.len();
         let next_offset = offset + DEFAULT_PAGE_SIZE;
 
         // Collect tools with pagination
-        let tools: Vec<McpTool> = self
-            .handlers
+        let tools: Vec<McpTool> = handlers
             .values()
             .skip(offset)
             .take(DEFAULT_PAGE_SIZE)
@@ -157,19 +271,35 @@ impl ToolRegistry {
         handler.execute(args, ctx).await
     }
 
+    /// Run every handler's health check, returning results sorted by tool name.
+    pub async fn health_check_all(&self) -> Vec<(String, HealthStatus)> {
+        let handlers: Vec<(String, Arc<dyn ToolHandler>)> = self
+            .read()
+            .iter()
+            .map(|(name, handler)| (name.clone(), handler.clone()))
+            .collect();
+        let checks = handlers
+            .into_iter()
+            .map(|(name, handler)| async move { (name, handler.health_check().await) });
+
+        let mut results = futures::future::join_all(checks).await;
+        results.sort_by(|(a, _), (b, _)| a.cmp(b));
+        results
+    }
+
     /// Check if a tool with the given name is registered.
     pub fn contains(&self, name: &str) -> bool {
-        self.handlers.contains_key(name)
+        self.read().contains_key(name)
     }
 
     /// Return the number of registered tools.
     pub fn len(&self) -> usize {
-        self.handlers.len()
+        self.read().len()
     }
 
     /// Return `true` if no tools are registered.
     pub fn is_empty(&self) -> bool {
-        self.handlers.is_empty()
+        self.read().is_empty()
     }
 }
 
@@ -178,3 +308,95 @@ impl Default for ToolRegistry {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct StubHandler {
+        name: &'static str,
+        health: HealthStatus,
+    }
+
+    impl ToolHandler for StubHandler {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn description(&self) -> &str {
+            "stub"
+        }
+
+        fn input_schema(&self) -> JsonObject {
+            JsonObject::new()
+        }
+
+        fn execute(
+            &self,
+            _args: JsonObject,
+            _ctx: &ToolContext,
+        ) -> Pin<Box<dyn Future<Output = Result<CallToolResult>> + Send + '_>> {
+            Box::pin(async { Ok(CallToolResult::success(Vec::new())) })
+        }
+
+        fn health_check(&self) -> Pin<Box<dyn Future<Output = HealthStatus> + Send + '_>> {
+            Box::pin(async { self.health.clone() })
+        }
+    }
+
+    #[test]
+    fn test_unregister_handler_notifies_subscribers() {
+        let registry = ToolRegistry::new().register_handler(StubHandler {
+            name: "a.ok",
+            health: HealthStatus::Ok,
+        });
+        let shared = registry.clone();
+        let mut changes = registry.subscribe_changes();
+
+        let removed = shared.unregister_handler("a.ok").unwrap();
+        assert_eq!(removed.name(), "a.ok");
+        assert!(!registry.contains("a.ok"));
+        assert!(registry.list_tools(None).0.is_empty());
+        assert!(changes.try_recv().is_ok());
+
+        // Unknown names change nothing
+        assert!(shared.unregister_handler("a.ok").is_none());
+        assert!(changes.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_health_check_all_reports_every_handler() {
+        let registry = ToolRegistry::new()
+            .register_handler(StubHandler {
+                name: "b.failed",
+                health: HealthStatus::Failed("gone".to_string()),
+            })
+            .register_handler(StubHandler {
+                name: "a.ok",
+                health: HealthStatus::Ok,
+            })
+            .register_handler(StubHandler {
+                name: "c.degraded",
+                health: HealthStatus::Degraded("slow".to_string()),
+            });
+
+        assert_eq!(
+            registry.health_check_all().await,
+            vec![
+                ("a.ok".to_string(), HealthStatus::Ok),
+                (
+                    "b.failed".to_string(),
+                    HealthStatus::Failed("gone".to_string())
+                ),
+                (
+                    "c.degraded".to_string(),
+                    HealthStatus::Degraded("slow".to_string())
+                ),
+            ]
+        );
+        assert_eq!(
+            serde_json::to_value(HealthStatus::Degraded("slow".to_string())).unwrap(),
+            serde_json::json!({"status": "degraded", "reason": "slow"})
+        );
+    }
+}
@@ -4,13 +4,21 @@
 //! appropriate tool from discovered MCP services using semantic search
 //! and symbolic reasoning.
 
-use crate::orchestrator::Orchestrator;
-use crate::tools::{ToolContext, ToolHandler};
+use crate::db::ToolRecord;
+use crate::knowledge_graph::ToolSelection;
+use crate::orchestrator::{Orchestrator, ToolQueryOptions};
+use crate::tools::{HealthStatus, ToolContext, ToolHandler, probe_orchestrator};
 use rmcp::model::{CallToolResult, Content, JsonObject};
-use serde_json::json;
+use serde_json::{Value, json};
 use std::pin::Pin;
 use std::sync::Arc;
 
+/// Number of tools returned when `top_k` is not given.
+const DEFAULT_TOP_K: usize = 1;
+
+/// Largest accepted `top_k`; larger values are clamped.
+const MAX_TOP_K: usize = 10;
+
 /// Handler for the `unicity.select_tool` tool.
 pub struct SelectToolHandler {
     orchestrator: Arc<Orchestrator>,
@@ -43,6 +51,40 @@ impl SelectToolHandler {
                 "additionalProperties": true,
             }),
         );
+        properties.insert(
+            "top_k".to_string(),
+            json!({
+                "type": "integer",
+                "description": "How many candidate tools to return, best first.",
+                "minimum": 1,
+                "maximum": MAX_TOP_K,
+                "default": DEFAULT_TOP_K,
+            }),
+        );
+        properties.insert(
+            "enforce_diversity".to_string(),
+            json!({
+                "type": "boolean",
+                "description": "Prefer candidates that are dissimilar to each other, \
+                    e.g. from different services, over near-duplicates.",
+                "default": false,
+            }),
+        );
+        properties.insert(
+            "include_deprecated".to_string(),
+            json!({
+                "type": "boolean",
+                "description": "Rank deprecated tools like any other instead of halving their confidence.",
+                "default": false,
+            }),
+        );
+        properties.insert(
+            "language".to_string(),
+            json!({
+                "type": "string",
+                "description": "BCP-47 tag of the query's language, e.g. `de`. Defaults to the user's preferred language, then English.",
+            }),
+        );
 
         schema.insert("properties".to_string(), json!(properties));
         schema.insert("required".to_string(), json!(["query"]));
@@ -61,6 +103,23 @@ impl SelectToolHandler {
     }
 }
 
+/// One ranked entry of the `results` array.
+fn selection_entry(rank: usize, sel: &ToolSelection, tool: &ToolRecord) -> Value {
+    json!({
+        "rank": rank,
+        "toolId": tool.id.to_string(),
+        "toolName": tool.name,
+        "serviceId": tool.service_id.to_string(),
+        "confidence": sel.confidence,
+        "reasoning": sel.reasoning,
+        "dependencies": sel.dependencies,
+        "estimatedCost": sel.estimated_cost,
+        "inputSchema": tool.input_schema,
+        "outputSchema": tool.output_schema,
+        "deprecationNotice": tool.deprecation_notice,
+    })
+}
+
 impl ToolHandler for SelectToolHandler {
     fn name(&self) -> &str {
         "unicity.select_tool"
@@ -128,55 +187,94 @@ impl ToolHandler for SelectToolHandler {
 
             let context_value = args.get("context").cloned();
 
+            let top_k = args
+                .get("top_k")
+                .and_then(|v| v.as_u64())
+                .map_or(DEFAULT_TOP_K, |k| k.clamp(1, MAX_TOP_K as u64) as usize);
+            let enforce_diversity = args
+                .get("enforce_diversity")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            let options = ToolQueryOptions {
+                include_deprecated: args
+                    .get("include_deprecated")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false),
+                language: args
+                    .get("language")
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string),
+                ..Default::default()
+            };
+
             let selection_result = orchestrator
-                .orchestrate_tool(&query, context_value, user_context.as_ref())
+                .select_tools(
+                    &query,
+                    context_value,
+                    user_context.as_ref(),
+                    top_k,
+                    enforce_diversity,
+                    &options,
+                )
                 .await;
 
             let mut is_error = false;
             let payload = match selection_result {
-                Ok(Some(sel)) => {
+                Ok(selections) if !selections.is_empty() => {
+                    let ids: Vec<_> = selections.iter().map(|s| s.tool_id.clone()).collect();
                     let db_res = orchestrator
                         .db()
-                        .query("SELECT * FROM tool WHERE id = $id")
-                        .bind(("id", sel.tool_id.clone()))
+                        .query("SELECT * FROM tool WHERE id IN $ids")
+                        .bind(("ids", ids))
                         .await;
 
                     match db_res {
-                        Ok(mut res) => {
-                            let tool_res = res.take::<Option<crate::db::ToolRecord>>(0);
-                            match tool_res {
-                                Ok(Some(tool)) => {
-                                    json!({
-                                        "status": "ok",
-                                        "selection": {
-                                            "toolId": tool.id.to_string(),
-                                            "toolName": tool.name,
-                                            "serviceId": tool.service_id.to_string(),
-                                            "confidence": sel.confidence,
-                                            "reasoning": sel.reasoning,
-                                            "dependencies": sel.dependencies,
-                                            "estimatedCost": sel.estimated_cost,
-                                            "inputSchema": tool.input_schema,
-                                            "outputSchema": tool.output_schema,
-                                        }
-                                    })
-                                }
-                                Ok(None) => {
-                                    is_error = true;
-                                    json!({
-                                        "status": "error",
-                                        "reason": "Selected tool not found in database"
-                                    })
-                                }
-                                Err(e) => {
-                                    is_error = true;
-                                    json!({
-                                        "status": "error",
-                                        "reason": format!("Failed to decode ToolRecord: {}", e),
+                        Ok(mut res) => match res.take::<Vec<ToolRecord>>(0) {
+                            Ok(tools) => {
+                                // Keep the selection order; skip tools deleted meanwhile
+                                let ranked: Vec<_> = selections
+                                    .iter()
+                                    .filter_map(|sel| {
+                                        tools.iter().find(|t| t.id == sel.tool_id).map(|t| (sel, t))
                                     })
+                                    .collect();
+                                let results: Vec<_> = ranked
+                                    .iter()
+                                    .enumerate()
+                                    .map(|(i, (sel, tool))| selection_entry(i + 1, sel, tool))
+                                    .collect();
+
+                                match results.first() {
+                                    Some(best) => {
+                                        let mut payload = json!({
+                                            "status": "ok",
+                                            "selection": best,
+                                            "results": results,
+                                        });
+                                        let (_, best_tool) = ranked[0];
+                                        if let Some(notice) = &best_tool.deprecation_notice {
+                                            payload["deprecated_warning"] =
+                                                json!(notice.warning(&best_tool.name));
+                                        }
+                                        payload
+                                    }
+                                    None => {
+                                        is_error = true;
+                                        json!({
+                                            "status": "error",
+                                            "reason": "Selected tool not found in database"
+                                        })
+                                    }
                                 }
                             }
-                        }
+                            Err(e) => {
+                                is_error = true;
+                                json!({
+                                    "status": "error",
+                                    "reason": format!("Failed to decode ToolRecord: {}", e),
+                                })
+                            }
+                        },
                         Err(e) => {
                             is_error = true;
                             json!({
@@ -186,7 +284,7 @@ impl ToolHandler for SelectToolHandler {
                         }
                     }
                 }
-                Ok(None) => {
+                Ok(_) => {
                     is_error = true;
                     json!({
                         "status": "no_match",
@@ -212,4 +310,8 @@ impl ToolHandler for SelectToolHandler {
             })
         })
     }
+
+    fn health_check(&self) -> Pin<Box<dyn Future<Output = HealthStatus> + Send + '_>> {
+        Box::pin(probe_orchestrator(&self.orchestrator))
+    }
 }
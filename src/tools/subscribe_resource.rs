@@ -0,0 +1,140 @@
+//! Handler for the `unicity.subscribe_resource` tool.
+//!
+//! Starts polling a resource for the calling session. Each change is sent to
+//! that session as a `notifications/resources/updated` whose `_meta` carries
+//! the `subscriptionId` and the `contentSha256` of the new contents.
+
+use crate::orchestrator::Orchestrator;
+use crate::resources::{MAX_POLL_INTERVAL_SECS, MIN_POLL_INTERVAL_SECS};
+use crate::tools::{ToolContext, ToolHandler};
+use rmcp::model::{CallToolResult, Content, JsonObject};
+use serde_json::json;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// Poll interval used when `poll_interval_secs` is omitted.
+const DEFAULT_POLL_INTERVAL_SECS: u64 = 60;
+
+/// Handler for the `unicity.subscribe_resource` tool.
+pub struct SubscribeResourceHandler {
+    orchestrator: Arc<Orchestrator>,
+}
+
+impl SubscribeResourceHandler {
+    /// Create a new subscribe resource handler.
+    pub fn new(orchestrator: Arc<Orchestrator>) -> Self {
+        Self { orchestrator }
+    }
+
+    /// Build the input schema for this tool.
+    fn input_schema(&self) -> JsonObject {
+        let mut schema = JsonObject::new();
+        schema.insert("type".to_string(), json!("object"));
+
+        let mut properties = serde_json::Map::new();
+        properties.insert(
+            "uri".to_string(),
+            json!({
+                "type": "string",
+                "description": "URI (or namespaced name such as 'filesystem:config') of the resource to watch."
+            }),
+        );
+        properties.insert(
+            "poll_interval_secs".to_string(),
+            json!({
+                "type": "integer",
+                "minimum": MIN_POLL_INTERVAL_SECS,
+                "maximum": MAX_POLL_INTERVAL_SECS,
+                "default": DEFAULT_POLL_INTERVAL_SECS,
+                "description": "How often to check the resource for changes, in seconds."
+            }),
+        );
+
+        schema.insert("properties".to_string(), json!(properties));
+        schema.insert("required".to_string(), json!(["uri"]));
+        schema
+    }
+}
+
+impl ToolHandler for SubscribeResourceHandler {
+    fn name(&self) -> &str {
+        "unicity.subscribe_resource"
+    }
+
+    fn title(&self) -> Option<&str> {
+        Some("Unicity Orchestrator: Subscribe to Resource")
+    }
+
+    fn description(&self) -> &str {
+        "Watch a resource for changes. Returns a subscription_id; whenever the resource's \
+         contents change, a notifications/resources/updated is sent with the subscriptionId \
+         and contentSha256 in its _meta. Cancel with unicity.unsubscribe_resource. \
+         Subscriptions end automatically when the session disconnects."
+    }
+
+    fn input_schema(&self) -> JsonObject {
+        self.input_schema()
+    }
+
+    fn execute(
+        &self,
+        args: JsonObject,
+        ctx: &ToolContext,
+    ) -> Pin<Box<dyn Future<Output = anyhow::Result<CallToolResult>> + Send + '_>> {
+        let orchestrator = self.orchestrator.clone();
+        let session_id = ctx.session_id();
+        let peer = ctx.request_context.peer.clone();
+
+        Box::pin(async move {
+            let uri = args.get("uri").and_then(|v| v.as_str()).unwrap_or_default();
+            let poll_interval_secs = args
+                .get("poll_interval_secs")
+                .and_then(|v| v.as_u64())
+                .unwrap_or(DEFAULT_POLL_INTERVAL_SECS);
+
+            let (payload, is_error) = if uri.is_empty() {
+                (
+                    json!({
+                        "status": "error",
+                        "reason": "unicity.subscribe_resource requires a `uri` string"
+                    }),
+                    true,
+                )
+            } else {
+                match orchestrator
+                    .resource_subscriptions()
+                    .subscribe(&session_id, uri, poll_interval_secs, peer)
+                    .await
+                {
+                    Ok(subscription_id) => (
+                        json!({
+                            "status": "ok",
+                            "subscription_id": subscription_id,
+                            "uri": uri,
+                            "poll_interval_secs": poll_interval_secs,
+                        }),
+                        false,
+                    ),
+                    Err(e) => (
+                        json!({
+                            "status": "error",
+                            "reason": e.to_string(),
+                        }),
+                        true,
+                    ),
+                }
+            };
+
+            let text = serde_json::to_string(&payload)
+                .unwrap_or_else(|_| "internal serialization error".to_string());
+
+            Ok(CallToolResult {
+                content: vec![Content::text(text)],
+                structured_content: None,
+                is_error: Some(is_error),
+                meta: None,
+            })
+        })
+    }
+}
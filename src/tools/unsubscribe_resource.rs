@@ -0,0 +1,108 @@
+//! Handler for the `unicity.unsubscribe_resource` tool.
+//!
+//! Cancels a subscription created by `unicity.subscribe_resource` in the same
+//! session.
+
+use crate::orchestrator::Orchestrator;
+use crate::tools::{ToolContext, ToolHandler};
+use rmcp::model::{CallToolResult, Content, JsonObject};
+use serde_json::json;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// Handler for the `unicity.unsubscribe_resource` tool.
+pub struct UnsubscribeResourceHandler {
+    orchestrator: Arc<Orchestrator>,
+}
+
+impl UnsubscribeResourceHandler {
+    /// Create a new unsubscribe resource handler.
+    pub fn new(orchestrator: Arc<Orchestrator>) -> Self {
+        Self { orchestrator }
+    }
+
+    /// Build the input schema for this tool.
+    fn input_schema(&self) -> JsonObject {
+        let mut schema = JsonObject::new();
+        schema.insert("type".to_string(), json!("object"));
+
+        let mut properties = serde_json::Map::new();
+        properties.insert(
+            "subscription_id".to_string(),
+            json!({
+                "type": "string",
+                "description": "The subscription_id returned by unicity.subscribe_resource."
+            }),
+        );
+
+        schema.insert("properties".to_string(), json!(properties));
+        schema.insert("required".to_string(), json!(["subscription_id"]));
+        schema
+    }
+}
+
+impl ToolHandler for UnsubscribeResourceHandler {
+    fn name(&self) -> &str {
+        "unicity.unsubscribe_resource"
+    }
+
+    fn title(&self) -> Option<&str> {
+        Some("Unicity Orchestrator: Unsubscribe from Resource")
+    }
+
+    fn description(&self) -> &str {
+        "Stop watching a resource subscribed to with unicity.subscribe_resource."
+    }
+
+    fn input_schema(&self) -> JsonObject {
+        self.input_schema()
+    }
+
+    fn execute(
+        &self,
+        args: JsonObject,
+        ctx: &ToolContext,
+    ) -> Pin<Box<dyn Future<Output = anyhow::Result<CallToolResult>> + Send + '_>> {
+        let orchestrator = self.orchestrator.clone();
+        let session_id = ctx.session_id();
+
+        Box::pin(async move {
+            let subscription_id = args
+                .get("subscription_id")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default();
+
+            let removed = orchestrator
+                .resource_subscriptions()
+                .unsubscribe(&session_id, subscription_id);
+            let (payload, is_error) = match removed {
+                Some(uri) => (
+                    json!({
+                        "status": "ok",
+                        "subscription_id": subscription_id,
+                        "uri": uri,
+                    }),
+                    false,
+                ),
+                None => (
+                    json!({
+                        "status": "error",
+                        "reason": format!("No subscription with id {}", subscription_id),
+                    }),
+                    true,
+                ),
+            };
+
+            let text = serde_json::to_string(&payload)
+                .unwrap_or_else(|_| "internal serialization error".to_string());
+
+            Ok(CallToolResult {
+                content: vec![Content::text(text)],
+                structured_content: None,
+                is_error: Some(is_error),
+                meta: None,
+            })
+        })
+    }
+}
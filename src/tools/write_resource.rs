@@ -0,0 +1,236 @@
+//! Handler for the `unicity.write_resource` tool.
+//!
+//! Writes content to a resource of a downstream service through the draft
+//! `resources/write` extension. Only services that declare `write_resources`
+//! accept writes. Writes go through the same blocked-service check, role
+//! check, approval and audit log as `unicity.execute_tool`.
+
+use crate::auth::MissingRoleError;
+use crate::orchestrator::Orchestrator;
+use crate::tools::execute_tool::confirm_blocked_service;
+use crate::tools::{ToolContext, ToolHandler};
+use base64::Engine;
+use rmcp::model::{CallToolResult, Content, JsonObject};
+use serde_json::json;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// MIME type used when `mime_type` is omitted.
+const DEFAULT_MIME_TYPE: &str = "text/plain";
+
+/// Handler for the `unicity.write_resource` tool.
+pub struct WriteResourceHandler {
+    orchestrator: Arc<Orchestrator>,
+}
+
+impl WriteResourceHandler {
+    /// Create a new write resource handler.
+    pub fn new(orchestrator: Arc<Orchestrator>) -> Self {
+        Self { orchestrator }
+    }
+
+    /// Build the input schema for this tool.
+    fn input_schema(&self) -> JsonObject {
+        let mut schema = JsonObject::new();
+        schema.insert("type".to_string(), json!("object"));
+
+        let mut properties = serde_json::Map::new();
+        properties.insert(
+            "uri".to_string(),
+            json!({
+                "type": "string",
+                "description": "URI (or namespaced name such as 'filesystem:config') of the resource to write."
+            }),
+        );
+        properties.insert(
+            "content".to_string(),
+            json!({
+                "type": "string",
+                "description": "New contents of the resource."
+            }),
+        );
+        properties.insert(
+            "encoding".to_string(),
+            json!({
+                "type": "string",
+                "enum": ["text", "base64"],
+                "default": "text",
+                "description": "How `content` is encoded; use base64 for binary contents."
+            }),
+        );
+        properties.insert(
+            "mime_type".to_string(),
+            json!({
+                "type": "string",
+                "default": DEFAULT_MIME_TYPE,
+                "description": "MIME type of the contents."
+            }),
+        );
+
+        schema.insert("properties".to_string(), json!(properties));
+        schema.insert("required".to_string(), json!(["uri", "content"]));
+        schema
+    }
+}
+
+/// Decode the tool's `content` argument according to `encoding`.
+fn decode_content(content: &str, encoding: &str) -> Result<Vec<u8>, String> {
+    match encoding {
+        "text" => Ok(content.as_bytes().to_vec()),
+        "base64" => base64::engine::general_purpose::STANDARD
+            .decode(content)
+            .map_err(|e| format!("`content` is not valid base64: {}", e)),
+        other => Err(format!("Unknown encoding: {}", other)),
+    }
+}
+
+impl ToolHandler for WriteResourceHandler {
+    fn name(&self) -> &str {
+        "unicity.write_resource"
+    }
+
+    fn title(&self) -> Option<&str> {
+        Some("Unicity Orchestrator: Write Resource")
+    }
+
+    fn description(&self) -> &str {
+        "Write new contents to a resource. Only works for services that declare \
+         write_resources in their capabilities; other services are reported as not \
+         supporting writes."
+    }
+
+    fn input_schema(&self) -> JsonObject {
+        self.input_schema()
+    }
+
+    fn execute(
+        &self,
+        args: JsonObject,
+        ctx: &ToolContext,
+    ) -> Pin<Box<dyn Future<Output = anyhow::Result<CallToolResult>> + Send + '_>> {
+        let orchestrator = self.orchestrator.clone();
+        let user_context = ctx.user_context.clone();
+        let session = ctx.session.clone();
+
+        Box::pin(async move {
+            let uri = args.get("uri").and_then(|v| v.as_str()).unwrap_or_default();
+            let content = args.get("content").and_then(|v| v.as_str());
+            let encoding = args
+                .get("encoding")
+                .and_then(|v| v.as_str())
+                .unwrap_or("text");
+            let mime_type = args
+                .get("mime_type")
+                .and_then(|v| v.as_str())
+                .unwrap_or(DEFAULT_MIME_TYPE);
+
+            let decoded = match content {
+                _ if uri.is_empty() => {
+                    Err("unicity.write_resource requires a `uri` string".to_string())
+                }
+                None => Err("unicity.write_resource requires a `content` string".to_string()),
+                Some(content) => decode_content(content, encoding),
+            };
+
+            let bytes = match decoded {
+                Ok(bytes) => bytes,
+                Err(reason) => {
+                    return Ok(text_result(
+                        json!({ "status": "error", "reason": reason }),
+                        true,
+                    ));
+                }
+            };
+
+            // Ask the calling client, as for unicity.execute_tool
+            let elicitation = match &session {
+                Some(session) => &session.elicitation,
+                None => orchestrator.elicitation_coordinator().as_ref(),
+            };
+
+            if let Some(ref ctx) = user_context {
+                let service_id = match orchestrator.resource_forwarder().find_resource(uri).await {
+                    Ok(resource) => resource.service_id.to_string(),
+                    Err(e) => {
+                        return Ok(text_result(
+                            json!({ "status": "error", "reason": e.to_string() }),
+                            true,
+                        ));
+                    }
+                };
+                if let Some(blocked) = confirm_blocked_service(
+                    &orchestrator,
+                    ctx,
+                    elicitation,
+                    &format!("resource '{}'", uri),
+                    &service_id,
+                )
+                .await
+                {
+                    return Ok(blocked);
+                }
+            }
+
+            let size = bytes.len();
+            let result = orchestrator
+                .write_resource_with_approval(
+                    uri,
+                    bytes,
+                    mime_type,
+                    user_context.as_ref(),
+                    elicitation,
+                )
+                .await;
+            Ok(match result {
+                Ok(()) => text_result(
+                    json!({
+                        "status": "ok",
+                        "uri": uri,
+                        "bytes_written": size,
+                    }),
+                    false,
+                ),
+                // Surfaced as an MCP error rather than a failed result
+                Err(e) if e.is::<MissingRoleError>() => return Err(e),
+                Err(e) => {
+                    let reason = e.to_string();
+                    let status = if reason.contains("denied by user") {
+                        "denied"
+                    } else if reason.contains("cancelled") {
+                        "cancelled"
+                    } else {
+                        "error"
+                    };
+                    text_result(json!({ "status": status, "reason": reason }), true)
+                }
+            })
+        })
+    }
+}
+
+/// A call result carrying `payload` as JSON text.
+fn text_result(payload: serde_json::Value, is_error: bool) -> CallToolResult {
+    let text = serde_json::to_string(&payload)
+        .unwrap_or_else(|_| "internal serialization error".to_string());
+
+    CallToolResult {
+        content: vec![Content::text(text)],
+        structured_content: None,
+        is_error: Some(is_error),
+        meta: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_content_by_encoding() {
+        assert_eq!(decode_content("hi", "text").unwrap(), b"hi");
+        assert_eq!(decode_content("aGk=", "base64").unwrap(), b"hi");
+        assert!(decode_content("not base64!", "base64").is_err());
+        assert!(decode_content("hi", "hex").is_err());
+    }
+}
@@ -0,0 +1,176 @@
+//! Minimal MCP service spawned over stdio by the end-to-end tests.
+//!
+//! Its three tools chain through the type URIs (`$id`) of their schemas:
+//!
+//! ```text
+//! urn:mock:CityName -> geocode_city -> urn:mock:Coordinates
+//!     -> weather_forecast -> urn:mock:Forecast -> format_report -> urn:mock:Report
+//! ```
+//!
+//! Results are deterministic so tests can assert on them.
+
+use std::sync::Arc;
+
+use rmcp::{
+    ErrorData as McpError, ServiceExt,
+    handler::server::ServerHandler,
+    model::*,
+    service::{RequestContext, RoleServer},
+    transport::stdio,
+};
+use serde_json::{Value, json};
+
+#[derive(Clone)]
+struct MockService;
+
+fn schema(value: Value) -> Arc<JsonObject> {
+    Arc::new(value.as_object().cloned().unwrap_or_default())
+}
+
+fn tool(name: &'static str, description: &'static str, input: Value, output: Value) -> Tool {
+    Tool {
+        name: name.into(),
+        title: None,
+        description: Some(description.into()),
+        input_schema: schema(input),
+        output_schema: Some(schema(output)),
+        annotations: None,
+        icons: None,
+        meta: None,
+    }
+}
+
+fn coordinates_schema() -> Value {
+    json!({
+        "$id": "urn:mock:Coordinates",
+        "type": "object",
+        "properties": {
+            "lat": { "type": "number" },
+            "lon": { "type": "number" }
+        },
+        "required": ["lat", "lon"]
+    })
+}
+
+fn forecast_schema() -> Value {
+    json!({
+        "$id": "urn:mock:Forecast",
+        "type": "object",
+        "properties": {
+            "summary": { "type": "string" },
+            "temperature_c": { "type": "number" }
+        },
+        "required": ["summary", "temperature_c"]
+    })
+}
+
+fn tools() -> Vec<Tool> {
+    vec![
+        tool(
+            "geocode_city",
+            "Look up the latitude and longitude of a city by name.",
+            json!({
+                "$id": "urn:mock:CityName",
+                "type": "object",
+                "properties": { "city": { "type": "string" } },
+                "required": ["city"]
+            }),
+            coordinates_schema(),
+        ),
+        tool(
+            "weather_forecast",
+            "Get tomorrow's weather forecast for a latitude and longitude.",
+            coordinates_schema(),
+            forecast_schema(),
+        ),
+        tool(
+            "format_report",
+            "Format a weather forecast as a short human-readable report.",
+            forecast_schema(),
+            json!({
+                "$id": "urn:mock:Report",
+                "type": "object",
+                "properties": { "text": { "type": "string" } },
+                "required": ["text"]
+            }),
+        ),
+    ]
+}
+
+/// The result of calling `name`, or `None` if the arguments don't fit.
+fn call(name: &str, args: &JsonObject) -> Option<Value> {
+    match name {
+        "geocode_city" => {
+            let city = args.get("city")?.as_str()?;
+            Some(json!({ "city": city, "lat": 52.52, "lon": 13.405 }))
+        }
+        "weather_forecast" => {
+            let lat = args.get("lat")?.as_f64()?;
+            let lon = args.get("lon")?.as_f64()?;
+            Some(json!({
+                "summary": format!("Sunny at {lat:.2},{lon:.2}"),
+                "temperature_c": 21.5
+            }))
+        }
+        "format_report" => {
+            let summary = args.get("summary")?.as_str()?;
+            let temperature = args.get("temperature_c")?.as_f64()?;
+            Some(json!({ "text": format!("{summary}, {temperature:.1} °C") }))
+        }
+        _ => None,
+    }
+}
+
+impl ServerHandler for MockService {
+    fn list_tools(
+        &self,
+        _request: Option<PaginatedRequestParams>,
+        _context: RequestContext<RoleServer>,
+    ) -> impl Future<Output = Result<ListToolsResult, McpError>> + Send + '_ {
+        std::future::ready(Ok(ListToolsResult {
+            tools: tools(),
+            next_cursor: None,
+            ..Default::default()
+        }))
+    }
+
+    fn call_tool(
+        &self,
+        request: CallToolRequestParams,
+        _context: RequestContext<RoleServer>,
+    ) -> impl Future<Output = Result<CallToolResult, McpError>> + Send + '_ {
+        let args = request.arguments.unwrap_or_default();
+        let result = match call(&request.name, &args) {
+            Some(value) => Ok(CallToolResult::success(vec![Content::text(
+                value.to_string(),
+            )])),
+            None => Err(McpError::invalid_params(
+                format!("invalid call to `{}`", request.name),
+                None,
+            )),
+        };
+        std::future::ready(result)
+    }
+
+    fn get_info(&self) -> ServerInfo {
+        ServerInfo {
+            protocol_version: ProtocolVersion::V_2025_06_18,
+            capabilities: ServerCapabilities::builder().enable_tools().build(),
+            server_info: Implementation {
+                name: "mock-weather".to_string(),
+                title: None,
+                version: "0.1.0".to_string(),
+                icons: None,
+                website_url: None,
+            },
+            instructions: None,
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let service = MockService.serve(stdio()).await?;
+    service.waiting().await?;
+    Ok(())
+}
@@ -0,0 +1,247 @@
+//! Procedural macros for the Unicity orchestrator.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{ToTokens, format_ident, quote};
+use syn::{
+    FnArg, ImplItem, ImplItemFn, ItemImpl, Pat, ReturnType, Type, Visibility, parse_macro_input,
+    parse_quote,
+};
+
+/// Trace every public `async fn` in an `impl` block.
+///
+/// Each call runs inside an `info` span named `Type::method` whose fields
+/// summarize the arguments without recording their contents: strings and
+/// collections are reduced to their length, options to whether they are set,
+/// and other non-primitive values to their type. `debug` events are emitted
+/// on entry and on exit; the exit event carries the elapsed time and the
+/// return value's `Display` form, falling back to `Debug`, then to its type.
+#[proc_macro_attribute]
+pub fn orchestrate_traced(attr: TokenStream, item: TokenStream) -> TokenStream {
+    if !attr.is_empty() {
+        return syn::Error::new(
+            proc_macro2::Span::call_site(),
+            "`orchestrate_traced` takes no arguments",
+        )
+        .to_compile_error()
+        .into();
+    }
+
+    let mut item_impl = parse_macro_input!(item as ItemImpl);
+    let type_name = match &*item_impl.self_ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident.to_string())
+            .unwrap_or_default(),
+        other => other.to_token_stream().to_string(),
+    };
+
+    for impl_item in &mut item_impl.items {
+        if let ImplItem::Fn(method) = impl_item
+            && matches!(method.vis, Visibility::Public(_))
+            && method.sig.asyncness.is_some()
+        {
+            trace_method(&type_name, method);
+        }
+    }
+
+    item_impl.into_token_stream().into()
+}
+
+/// How an argument is summarized in the span.
+#[derive(Debug, PartialEq, Eq)]
+enum ArgSummary {
+    /// Strings and collections: record the length only.
+    Length,
+    /// Options: record whether a value is present.
+    Presence,
+    /// Numbers, booleans and chars: record the value.
+    Value,
+    /// Anything else: record the type.
+    Opaque,
+}
+
+const LENGTH_TYPES: &[&str] = &[
+    "str",
+    "String",
+    "Vec",
+    "HashMap",
+    "HashSet",
+    "BTreeMap",
+    "JsonObject",
+];
+const VALUE_TYPES: &[&str] = &[
+    "bool", "char", "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64", "i128",
+    "isize", "f32", "f64",
+];
+
+fn classify(ty: &Type) -> ArgSummary {
+    match ty {
+        Type::Reference(reference) => classify(&reference.elem),
+        Type::Slice(_) | Type::Array(_) => ArgSummary::Length,
+        Type::Path(path) => {
+            let Some(segment) = path.path.segments.last() else {
+                return ArgSummary::Opaque;
+            };
+            let ident = segment.ident.to_string();
+            if ident == "Option" {
+                ArgSummary::Presence
+            } else if LENGTH_TYPES.contains(&ident.as_str()) {
+                ArgSummary::Length
+            } else if VALUE_TYPES.contains(&ident.as_str()) {
+                ArgSummary::Value
+            } else {
+                ArgSummary::Opaque
+            }
+        }
+        _ => ArgSummary::Opaque,
+    }
+}
+
+fn summary_expr(name: &syn::Ident, ty: &Type) -> TokenStream2 {
+    match classify(ty) {
+        ArgSummary::Length => quote!(::std::format!("len={}", #name.len())),
+        ArgSummary::Presence => quote!(if #name.is_some() { "Some(..)" } else { "None" }),
+        ArgSummary::Value => quote!(::std::string::ToString::to_string(&#name)),
+        ArgSummary::Opaque => {
+            let type_name = ty.to_token_stream().to_string();
+            quote!(#type_name)
+        }
+    }
+}
+
+fn is_result(ty: &Type) -> bool {
+    matches!(ty, Type::Path(path)
+        if path.path.segments.last().is_some_and(|segment| segment.ident == "Result"))
+}
+
+fn trace_method(type_name: &str, method: &mut ImplItemFn) {
+    let span_name = format!("{}::{}", type_name, method.sig.ident);
+    let output: Type = match &method.sig.output {
+        ReturnType::Default => parse_quote!(()),
+        ReturnType::Type(_, ty) => (**ty).clone(),
+    };
+
+    let mut summaries = Vec::new();
+    let mut fields = Vec::new();
+    for input in &method.sig.inputs {
+        // Arguments named `_x` are unused by the method, so skip them too
+        if let FnArg::Typed(arg) = input
+            && let Pat::Ident(pat) = &*arg.pat
+            && !pat.ident.to_string().starts_with('_')
+        {
+            let name = &pat.ident;
+            let summary = format_ident!("__traced_arg_{}", name);
+            let expr = summary_expr(name, &arg.ty);
+            summaries.push(quote!(let #summary = #expr;));
+            fields.push(quote!(#name = %#summary));
+        }
+    }
+
+    let result_repr = if is_result(&output) {
+        quote! {
+            match &__traced_ret {
+                ::std::result::Result::Ok(value) => {
+                    ::std::format!("Ok({})", (&&&__TracedRepr(value)).__traced_repr())
+                }
+                ::std::result::Result::Err(error) => {
+                    ::std::format!("Err({})", (&&&__TracedRepr(error)).__traced_repr())
+                }
+            }
+        }
+    } else {
+        quote!((&&&__TracedRepr(&__traced_ret)).__traced_repr())
+    };
+
+    let body = &method.block;
+    method.block = parse_quote!({
+        #(#summaries)*
+        let __traced_span = ::tracing::info_span!(#span_name, #(#fields),*);
+        ::tracing::Instrument::instrument(
+            async move {
+                // Autoref specialization: prefer Display, then Debug, then the type name
+                struct __TracedRepr<'a, T: ?Sized>(&'a T);
+                trait __TracedDisplay {
+                    fn __traced_repr(&self) -> ::std::string::String;
+                }
+                impl<T: ::std::fmt::Display + ?Sized> __TracedDisplay for &&__TracedRepr<'_, T> {
+                    fn __traced_repr(&self) -> ::std::string::String {
+                        ::std::string::ToString::to_string(self.0)
+                    }
+                }
+                trait __TracedDebug {
+                    fn __traced_repr(&self) -> ::std::string::String;
+                }
+                impl<T: ::std::fmt::Debug + ?Sized> __TracedDebug for &__TracedRepr<'_, T> {
+                    fn __traced_repr(&self) -> ::std::string::String {
+                        ::std::format!("{:?}", self.0)
+                    }
+                }
+                trait __TracedOpaque {
+                    fn __traced_repr(&self) -> ::std::string::String;
+                }
+                impl<T: ?Sized> __TracedOpaque for __TracedRepr<'_, T> {
+                    fn __traced_repr(&self) -> ::std::string::String {
+                        ::std::string::ToString::to_string(::std::any::type_name::<T>())
+                    }
+                }
+
+                ::tracing::event!(::tracing::Level::DEBUG, "enter");
+                let __traced_start = ::std::time::Instant::now();
+                #[allow(clippy::let_unit_value)]
+                let __traced_ret: #output = async move {
+                    // Pins the block's output type so `?` in the body can infer its target
+                    #[allow(unreachable_code, clippy::diverging_sub_expression, clippy::empty_loop)]
+                    if false {
+                        let __traced_type_hint: #output = loop {};
+                        return __traced_type_hint;
+                    }
+                    #body
+                }
+                .await;
+                ::tracing::event!(
+                    ::tracing::Level::DEBUG,
+                    elapsed_ms = __traced_start.elapsed().as_millis() as u64,
+                    result = %#result_repr,
+                    "exit"
+                );
+                __traced_ret
+            },
+            __traced_span,
+        )
+        .await
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classify_str(ty: &str) -> ArgSummary {
+        classify(&syn::parse_str(ty).unwrap())
+    }
+
+    #[test]
+    fn test_classify_arguments() {
+        assert_eq!(classify_str("&str"), ArgSummary::Length);
+        assert_eq!(classify_str("Vec<String>"), ArgSummary::Length);
+        assert_eq!(classify_str("&[u8]"), ArgSummary::Length);
+        assert_eq!(classify_str("JsonObject"), ArgSummary::Length);
+        assert_eq!(classify_str("Option<&UserContext>"), ArgSummary::Presence);
+        assert_eq!(classify_str("usize"), ArgSummary::Value);
+        assert_eq!(classify_str("&f32"), ArgSummary::Value);
+        assert_eq!(classify_str("&ToolSelection"), ArgSummary::Opaque);
+        assert_eq!(classify_str("impl Into<String>"), ArgSummary::Opaque);
+    }
+
+    #[test]
+    fn test_is_result() {
+        assert!(is_result(&syn::parse_str("Result<()>").unwrap()));
+        assert!(is_result(
+            &syn::parse_str("anyhow::Result<Vec<u8>>").unwrap()
+        ));
+        assert!(!is_result(&syn::parse_str("Option<u8>").unwrap()));
+    }
+}